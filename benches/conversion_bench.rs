@@ -1,10 +1,46 @@
 //! Benchmark for conversion performance
-//! TODO: Implement actual benchmarks in Phase 9
+//!
+//! Measures request-setup overhead: building a converter (and populating its
+//! model-mapping table) per request versus borrowing the shared instance held
+//! in `AppState`.
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use llm_api_converter::converters::{
+    AnthropicToGeminiConverter, OpenAIToBedrockConverter, SharedConverters,
+};
 
-fn conversion_benchmark(_c: &mut Criterion) {
-    // TODO: Add benchmarks
+const MODELS: [&str; 4] = ["gpt-4o", "gpt-4", "gpt-3.5-turbo", "o1-mini"];
+
+fn conversion_benchmark(c: &mut Criterion) {
+    let shared = SharedConverters::new();
+
+    let mut openai = c.benchmark_group("openai_to_bedrock_setup");
+    openai.bench_function("per_request_new", |b| {
+        b.iter(|| {
+            for model in MODELS {
+                let converter = OpenAIToBedrockConverter::new();
+                black_box(converter.convert_model_id(black_box(model)));
+            }
+        })
+    });
+    openai.bench_function("shared_arc", |b| {
+        b.iter(|| {
+            for model in MODELS {
+                let converter = shared.openai_to_bedrock();
+                black_box(converter.convert_model_id(black_box(model)));
+            }
+        })
+    });
+    openai.finish();
+
+    let mut gemini = c.benchmark_group("anthropic_to_gemini_setup");
+    gemini.bench_function("per_request_new", |b| {
+        b.iter(|| black_box(AnthropicToGeminiConverter::new().get_gemini_model(black_box("gemini-2.5-pro"))))
+    });
+    gemini.bench_function("shared_arc", |b| {
+        b.iter(|| black_box(shared.anthropic_to_gemini().get_gemini_model(black_box("gemini-2.5-pro"))))
+    });
+    gemini.finish();
 }
 
 criterion_group!(benches, conversion_benchmark);
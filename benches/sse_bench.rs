@@ -0,0 +1,118 @@
+//! Benchmark for SSE event emission
+//!
+//! Compares the previous per-event path (build a `serde_json::Value`,
+//! stringify it, wrap it in an axum `Event`) with the typed `SseEncoder` for a
+//! text-delta heavy stream. Allocations per token are counted with a wrapping
+//! global allocator and printed before the timing runs; the concurrent group
+//! encodes one stream per thread to expose allocator contention.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::response::sse::Event;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use llm_api_converter::api::sse::{MessageStreamEvent, SseEncoder, StreamDelta};
+
+/// Counts every allocation and reallocation
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Text deltas per simulated stream
+const TOKENS: usize = 512;
+
+fn tokens() -> Vec<String> {
+    (0..TOKENS).map(|i| format!("token {} with \"quotes\" ", i)).collect()
+}
+
+/// Previous emission path: Value tree -> String -> Event
+fn legacy_stream(tokens: &[String]) {
+    for text in tokens {
+        let data = serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": {"type": "text_delta", "text": text}
+        });
+        let event = Event::default()
+            .event("content_block_delta")
+            .data(data.to_string());
+        black_box(event);
+    }
+}
+
+/// Typed events encoded into a reused buffer
+fn typed_stream(tokens: &[String]) {
+    let mut sse = SseEncoder::new();
+    for text in tokens {
+        let frame = sse.message_event(&MessageStreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: StreamDelta::TextDelta { text },
+        });
+        // hyper drops each frame once written
+        black_box(frame);
+    }
+}
+
+fn concurrent(streams: usize, tokens: &[String], emit: fn(&[String])) {
+    std::thread::scope(|scope| {
+        for _ in 0..streams {
+            scope.spawn(|| emit(tokens));
+        }
+    });
+}
+
+fn allocations_per_token(tokens: &[String], emit: fn(&[String])) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    emit(tokens);
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / tokens.len() as f64
+}
+
+fn sse_benchmark(c: &mut Criterion) {
+    let tokens = tokens();
+    let streams = std::thread::available_parallelism()
+        .map(|n| n.get() * 4)
+        .unwrap_or(16);
+
+    println!(
+        "allocations per token: legacy {:.2}, typed {:.2}",
+        allocations_per_token(&tokens, legacy_stream),
+        allocations_per_token(&tokens, typed_stream),
+    );
+
+    let mut single = c.benchmark_group("sse_single_stream");
+    single.bench_function("legacy_value", |b| b.iter(|| legacy_stream(&tokens)));
+    single.bench_function("typed_encoder", |b| b.iter(|| typed_stream(&tokens)));
+    single.finish();
+
+    let mut parallel = c.benchmark_group(format!("sse_{}_concurrent_streams", streams));
+    parallel.sample_size(20);
+    parallel.bench_function("legacy_value", |b| {
+        b.iter(|| concurrent(streams, &tokens, legacy_stream))
+    });
+    parallel.bench_function("typed_encoder", |b| {
+        b.iter(|| concurrent(streams, &tokens, typed_stream))
+    });
+    parallel.finish();
+}
+
+criterion_group!(benches, sse_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,1260 @@
+//! Admin API endpoints
+//!
+//! Operator-only endpoints for managing API keys (persistent and in-memory
+//! ephemeral keys), managing prompt templates, feature flags, model
+//! deprecation schedules and inference presets, reloading model mappings,
+//! flushing caches, watching live traffic, reading per-backend, per-priority,
+//! stream buffer and output watchdog totals, and reconciling token usage
+//! with Bedrock's metrics.
+//! All routes are nested under `/admin` and require the master key or a key
+//! holding the `admin` scope, except the stored key lifecycle endpoints under
+//! `/admin/api-keys` (create, get, rotate, revoke, quota), which take the
+//! separate admin key in `x-admin-key`. Stored keys are
+//! addressed by their opaque key ID, never by the secret, so key values do
+//! not end up in URLs or access logs.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserializer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::api::prompts::template_error;
+use crate::api::sse::{SseEncoder, SseResponse};
+use crate::config::{ModelDeprecation, SlowClientPolicy, TrialConfig};
+use crate::db::models::{ApiKey, ApiKeySummary, FeatureFlag, TRIAL_SERVICE_TIER};
+use crate::db::{KeyQuota, StorageError};
+use crate::error::ApiError;
+use crate::middleware::ApiKeyInfo;
+use crate::server::state::AppState;
+use crate::services::{
+    BackendRoutingStats, EphemeralKey, EphemeralKeySummary, InferencePreset, KeyTrips,
+    PresetParams, PriorityStats, PromptTemplate, PromptTemplateDefinition, ReconciliationReport,
+    StreamBufferStats, UsageReconciler,
+};
+
+/// Interval between keep-alive comments on an idle tap
+const TAP_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Request body for extending a (trial) key
+#[derive(Debug, Deserialize)]
+pub struct ExtendKeyRequest {
+    /// Extend by this many days from now (or from the current expiry if later)
+    #[serde(default)]
+    pub days: Option<i64>,
+
+    /// Absolute Unix timestamp for the new expiry
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+/// Request body for creating a stored API key
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// User (tenant) the key belongs to
+    pub user_id: String,
+
+    /// Human-readable name for the key
+    pub name: String,
+
+    /// Display name for the owner
+    #[serde(default)]
+    pub owner_name: Option<String>,
+
+    /// Service tier for pricing
+    #[serde(default = "default_upgrade_tier")]
+    pub service_tier: String,
+
+    /// Rate limit (requests per window)
+    #[serde(default)]
+    pub rate_limit: Option<i32>,
+
+    /// Tokens per minute limit
+    #[serde(default)]
+    pub tpm_limit: Option<i32>,
+
+    /// Monthly budget in USD
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+
+    /// Permission scopes
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// Model patterns the key may use (empty = all models)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+
+    /// CIDR ranges the key may be used from (empty = any address)
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+
+    /// Lifetime in days (omit for a key that does not expire)
+    #[serde(default)]
+    pub expires_in_days: Option<i64>,
+
+    /// Inference preset applied to the key's requests
+    #[serde(default)]
+    pub inference_preset: Option<String>,
+}
+
+/// Request body for changing a key's quota
+///
+/// Omitted fields are unchanged; `null` removes the TPM limit or budget.
+#[derive(Debug, Default, Deserialize)]
+pub struct SetQuotaRequest {
+    /// Rate limit (requests per window)
+    #[serde(default)]
+    pub rate_limit: Option<i32>,
+
+    /// Tokens per minute limit
+    #[serde(default, deserialize_with = "present")]
+    pub tpm_limit: Option<Option<i32>>,
+
+    /// Monthly budget in USD
+    #[serde(default, deserialize_with = "present")]
+    pub monthly_budget: Option<Option<f64>>,
+}
+
+/// Tell an explicit `null` (Some(None)) from an omitted field (None)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// A newly issued key with its admin key ID
+#[derive(Debug, Serialize)]
+pub struct IssuedKey {
+    /// ID addressing the key under `/admin/api-keys/:key_id`
+    pub key_id: String,
+    #[serde(flatten)]
+    pub key: ApiKey,
+}
+
+impl From<ApiKey> for IssuedKey {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            key_id: key.key_id(),
+            key,
+        }
+    }
+}
+
+/// Response for a key rotation
+#[derive(Debug, Serialize)]
+pub struct RotateKeyResponse {
+    /// The replacement key (its secret is only returned here)
+    pub api_key: IssuedKey,
+    /// Key ID of the key it replaced, now deactivated
+    pub rotated_from: String,
+}
+
+/// Request body for upgrading a key out of the trial tier
+#[derive(Debug, Deserialize)]
+pub struct UpgradeKeyRequest {
+    /// New service tier
+    #[serde(default = "default_upgrade_tier")]
+    pub service_tier: String,
+
+    /// New rate limit (requests per window)
+    #[serde(default)]
+    pub rate_limit: Option<i32>,
+
+    /// New monthly budget in USD
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+}
+
+/// Response for a model mapping reload
+#[derive(Debug, Serialize)]
+pub struct ReloadMappingsResponse {
+    /// Number of stored mappings applied
+    pub mapping_count: usize,
+
+    /// Converter generation after the reload
+    pub generation: u64,
+}
+
+/// Request body for storing a feature flag
+#[derive(Debug, Default, Deserialize)]
+pub struct FeatureFlagUpdate {
+    /// Whether the flag is on for keys that no rule matches
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Share of tenants (0-100) the flag is rolled out to
+    #[serde(default)]
+    pub rollout_percent: u8,
+
+    /// API keys the flag is always on for
+    #[serde(default)]
+    pub enabled_keys: Vec<String>,
+
+    /// API keys the flag is always off for
+    #[serde(default)]
+    pub disabled_keys: Vec<String>,
+
+    /// Tenants (key owner user IDs) the flag is on for
+    #[serde(default)]
+    pub enabled_tenants: Vec<String>,
+
+    /// What the flag controls
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Request body for storing a model deprecation schedule
+#[derive(Debug, Deserialize)]
+pub struct ModelDeprecationUpdate {
+    /// When the model was (or will be) deprecated
+    pub deprecated_at: DateTime<Utc>,
+
+    /// When the model stops being served
+    #[serde(default)]
+    pub sunset_at: Option<DateTime<Utc>>,
+
+    /// Model clients should move to
+    #[serde(default)]
+    pub successor: Option<String>,
+
+    /// Run requests on the successor once the sunset date has passed
+    #[serde(default)]
+    pub remap_after_sunset: bool,
+}
+
+/// Request body for storing an inference preset
+#[derive(Debug, Deserialize)]
+pub struct InferencePresetUpdate {
+    /// Defaults applied when a request omits them
+    #[serde(flatten)]
+    pub params: PresetParams,
+
+    /// Model aliases (exact names or patterns) the preset applies to
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+/// Response for a feature flag reload
+#[derive(Debug, Serialize)]
+pub struct ReloadFeatureFlagsResponse {
+    /// Number of stored flags loaded
+    pub flag_count: usize,
+}
+
+/// A cache that `POST /admin/cache/flush` can clear
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    /// Model mappings held by the shared converters (re-read from the database)
+    ModelMappings,
+    /// Bedrock SDK clients cached per pooled credential
+    CredentialClients,
+}
+
+impl CacheKind {
+    const ALL: [CacheKind; 2] = [CacheKind::ModelMappings, CacheKind::CredentialClients];
+}
+
+/// Request body for flushing caches
+#[derive(Debug, Default, Deserialize)]
+pub struct FlushCacheRequest {
+    /// Caches to flush (omit or leave empty to flush all of them)
+    #[serde(default)]
+    pub caches: Vec<CacheKind>,
+}
+
+/// Response for a cache flush; only flushed caches are reported
+#[derive(Debug, Default, Serialize)]
+pub struct FlushCacheResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_mappings: Option<ReloadMappingsResponse>,
+
+    /// Number of cached clients dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_clients: Option<usize>,
+}
+
+/// Request body for minting an ephemeral key
+#[derive(Debug, Default, Deserialize)]
+pub struct MintEphemeralKeyRequest {
+    /// Optional label (e.g. the client the key is for)
+    #[serde(default)]
+    pub label: Option<String>,
+
+    /// Lifetime in seconds (omit to keep the key until shutdown)
+    #[serde(default)]
+    pub ttl_seconds: Option<i64>,
+
+    /// Scopes granted to the key
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// Query parameters for the unused-key report
+#[derive(Debug, Deserialize)]
+pub struct UnusedKeysQuery {
+    /// Report keys not used for at least this many days
+    #[serde(default = "default_unused_days")]
+    pub days: i64,
+}
+
+fn default_unused_days() -> i64 {
+    30
+}
+
+/// Entry in the unused-key report
+#[derive(Debug, Serialize)]
+pub struct UnusedKeyEntry {
+    pub key_id: String,
+    pub key_prefix: String,
+    pub user_id: String,
+    pub name: String,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub request_count: i64,
+    pub days_unused: i64,
+}
+
+/// Unused-key report
+#[derive(Debug, Serialize)]
+pub struct UnusedKeysReport {
+    pub days: i64,
+    pub generated_at: i64,
+    pub keys: Vec<UnusedKeyEntry>,
+}
+
+/// Build a new stored key from a create request
+///
+/// Trial-tier keys get the trial defaults for what the request leaves out:
+/// the trial monthly budget and an expiry `duration_days` from now.
+pub fn build_api_key(
+    request: &CreateApiKeyRequest,
+    default_rate_limit: i32,
+    trial: &TrialConfig,
+    now: i64,
+) -> Result<ApiKey, ApiError> {
+    if request.user_id.trim().is_empty() || request.name.trim().is_empty() {
+        return Err(ApiError::InvalidRequest("'user_id' and 'name' are required".to_string()));
+    }
+    validate_quota(&SetQuotaRequest {
+        rate_limit: request.rate_limit,
+        tpm_limit: Some(request.tpm_limit),
+        monthly_budget: Some(request.monthly_budget),
+    })?;
+    let is_trial = request.service_tier == TRIAL_SERVICE_TIER;
+    let expires_at = match request.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err(ApiError::InvalidRequest("'expires_in_days' must be positive".to_string()))
+        }
+        Some(days) => Some(now + days * 86_400),
+        None if is_trial => Some(now + trial.duration_days * 86_400),
+        None => None,
+    };
+    let monthly_budget = match request.monthly_budget {
+        None if is_trial => Some(trial.monthly_budget).filter(|budget| *budget > 0.0),
+        budget => budget,
+    };
+
+    Ok(ApiKey {
+        api_key: generate_api_key(),
+        user_id: request.user_id.trim().to_string(),
+        name: request.name.trim().to_string(),
+        created_at: now,
+        updated_at: None,
+        is_active: true,
+        rate_limit: request.rate_limit.unwrap_or(default_rate_limit),
+        service_tier: request.service_tier.clone(),
+        metadata: HashMap::new(),
+        owner_name: request.owner_name.clone(),
+        role: None,
+        monthly_budget,
+        budget_used: 0.0,
+        budget_used_mtd: 0.0,
+        budget_mtd_month: None,
+        deactivated_reason: None,
+        tpm_limit: request.tpm_limit,
+        scopes: request.scopes.clone(),
+        expires_at,
+        allowed_models: request.allowed_models.clone(),
+        allowed_ips: request.allowed_ips.clone(),
+        parent_key: None,
+        last_used_at: None,
+        request_count: 0,
+        recent_source_ips: Vec::new(),
+        ptc_max_iterations: None,
+        ptc_max_tokens_per_iteration: None,
+        ptc_session_token_budget: None,
+        inference_preset: request.inference_preset.clone(),
+    })
+}
+
+/// The replacement for a rotated key: same owner, limits and month-to-date
+/// spend, new secret and fresh activity counters
+pub fn rotated_key(old: &ApiKey, now: i64) -> ApiKey {
+    ApiKey {
+        api_key: generate_api_key(),
+        created_at: now,
+        updated_at: None,
+        is_active: true,
+        deactivated_reason: None,
+        last_used_at: None,
+        request_count: 0,
+        recent_source_ips: Vec::new(),
+        ..old.clone()
+    }
+}
+
+fn generate_api_key() -> String {
+    format!("sk-{}", uuid::Uuid::new_v4())
+}
+
+fn validate_quota(quota: &SetQuotaRequest) -> Result<(), ApiError> {
+    if quota.rate_limit.is_some_and(|limit| limit <= 0)
+        || quota.tpm_limit.flatten().is_some_and(|limit| limit <= 0)
+    {
+        return Err(ApiError::InvalidRequest("Rate and TPM limits must be positive".to_string()));
+    }
+    if quota.monthly_budget.flatten().is_some_and(|budget| budget <= 0.0) {
+        return Err(ApiError::InvalidRequest("'monthly_budget' must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Build the unused-key report, least recently used first
+pub fn unused_keys_report(keys: &[ApiKey], days: i64, now: i64) -> UnusedKeysReport {
+    let cutoff = now - days * 86_400;
+    let mut entries: Vec<UnusedKeyEntry> = keys
+        .iter()
+        .filter(|key| key.is_unused_since(cutoff))
+        .map(|key| UnusedKeyEntry {
+            key_id: key.key_id(),
+            key_prefix: key.summary().key_prefix,
+            user_id: key.user_id.clone(),
+            name: key.name.clone(),
+            is_active: key.is_active,
+            created_at: key.created_at,
+            last_used_at: key.last_used_at,
+            request_count: key.request_count,
+            days_unused: (now - key.last_used_at.unwrap_or(key.created_at)) / 86_400,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.days_unused.cmp(&a.days_unused));
+
+    UnusedKeysReport {
+        days,
+        generated_at: now,
+        keys: entries,
+    }
+}
+
+fn default_upgrade_tier() -> String {
+    "default".to_string()
+}
+
+fn map_storage_error(key_id: &str, err: StorageError) -> ApiError {
+    match err {
+        StorageError::NotFound => ApiError::NotFound(format!("API key not found: {}", key_id)),
+        other => ApiError::DatabaseError(other.to_string()),
+    }
+}
+
+/// Look up a stored key by its key ID
+async fn find_key(state: &AppState, key_id: &str) -> Result<ApiKey, ApiError> {
+    state
+        .storage
+        .list_api_keys()
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+        .into_iter()
+        .find(|key| key.key_id() == key_id)
+        .ok_or_else(|| ApiError::NotFound(format!("API key not found: {}", key_id)))
+}
+
+/// GET /admin/api-keys - List keys with usage hygiene fields (secrets redacted)
+pub async fn list_api_keys(State(state): State<AppState>) -> Result<Json<Vec<ApiKeySummary>>, ApiError> {
+    let mut keys = state
+        .storage
+        .list_api_keys()
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+    keys.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at).then(a.created_at.cmp(&b.created_at)));
+    Ok(Json(keys.iter().map(ApiKey::summary).collect()))
+}
+
+/// POST /admin/api-keys - Create a stored API key
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Json(body): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<IssuedKey>), ApiError> {
+    let key = build_api_key(
+        &body,
+        state.settings.rate_limit.requests_per_window as i32,
+        &state.settings.trial,
+        Utc::now().timestamp(),
+    )?;
+    state
+        .storage
+        .create_api_key(&key)
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+
+    tracing::info!(
+        user_id = %key.user_id,
+        name = %key.name,
+        key_id = %key.key_id(),
+        "Created API key via admin API"
+    );
+
+    Ok((StatusCode::CREATED, Json(key.into())))
+}
+
+/// GET /admin/api-keys/:key_id - Get one stored key
+pub async fn get_api_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKeySummary>, ApiError> {
+    Ok(Json(find_key(&state, &key_id).await?.summary()))
+}
+
+/// POST /admin/api-keys/:key_id/rotate - Replace a key with a new secret
+///
+/// The new key keeps the old one's owner, scopes, limits and month-to-date
+/// spend; the old key is deactivated with reason `rotated`.
+pub async fn rotate_api_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+) -> Result<(StatusCode, Json<RotateKeyResponse>), ApiError> {
+    let old = find_key(&state, &key_id).await?;
+    if !old.is_active {
+        return Err(ApiError::InvalidRequest(format!(
+            "API key is inactive ({}) and cannot be rotated",
+            old.deactivated_reason.as_deref().unwrap_or("deactivated")
+        )));
+    }
+
+    let replacement = rotated_key(&old, Utc::now().timestamp());
+    state
+        .storage
+        .rotate_api_key(&old.api_key, &replacement)
+        .await
+        .map_err(|e| map_storage_error(&key_id, e))?;
+
+    tracing::info!(
+        key_id = %key_id,
+        new_key_id = %replacement.key_id(),
+        "Rotated API key"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RotateKeyResponse {
+            api_key: replacement.into(),
+            rotated_from: key_id,
+        }),
+    ))
+}
+
+/// DELETE /admin/api-keys/:key_id - Revoke a key
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let key = find_key(&state, &key_id).await?;
+    state
+        .storage
+        .revoke_api_key(&key.api_key)
+        .await
+        .map_err(|e| map_storage_error(&key_id, e))?;
+
+    tracing::info!(key_id = %key_id, "Revoked API key");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /admin/api-keys/:key_id/quota - Change a key's rate limit, TPM limit or budget
+pub async fn set_api_key_quota(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    Json(body): Json<SetQuotaRequest>,
+) -> Result<Json<ApiKeySummary>, ApiError> {
+    validate_quota(&body)?;
+    let quota = KeyQuota {
+        rate_limit: body.rate_limit,
+        tpm_limit: body.tpm_limit,
+        monthly_budget: body.monthly_budget,
+    };
+    if quota.is_empty() {
+        return Err(ApiError::InvalidRequest(
+            "Provide at least one of 'rate_limit', 'tpm_limit' or 'monthly_budget'".to_string(),
+        ));
+    }
+
+    let key = find_key(&state, &key_id).await?;
+    let key = state
+        .storage
+        .set_api_key_quota(&key.api_key, &quota)
+        .await
+        .map_err(|e| map_storage_error(&key_id, e))?;
+
+    Ok(Json(key.summary()))
+}
+
+/// GET /admin/api-keys/unused?days=N - Report keys unused for N days
+pub async fn unused_api_keys(
+    State(state): State<AppState>,
+    Query(query): Query<UnusedKeysQuery>,
+) -> Result<Json<UnusedKeysReport>, ApiError> {
+    if query.days <= 0 {
+        return Err(ApiError::InvalidRequest("'days' must be positive".to_string()));
+    }
+
+    let keys = state
+        .storage
+        .list_api_keys()
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(unused_keys_report(&keys, query.days, Utc::now().timestamp())))
+}
+
+/// POST /admin/api-keys/:key_id/extend - Extend a key's expiry
+pub async fn extend_api_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    Json(body): Json<ExtendKeyRequest>,
+) -> Result<Json<ApiKeySummary>, ApiError> {
+    let current = find_key(&state, &key_id).await?;
+    let expires_at = match (body.expires_at, body.days) {
+        (Some(expires_at), _) => expires_at,
+        (None, Some(days)) if days > 0 => {
+            let base = current
+                .expires_at
+                .unwrap_or(0)
+                .max(Utc::now().timestamp());
+            base + days * 86_400
+        }
+        _ => {
+            return Err(ApiError::InvalidRequest(
+                "Provide either a positive 'days' or an 'expires_at' timestamp".to_string(),
+            ))
+        }
+    };
+
+    let key = state
+        .storage
+        .extend_api_key(&current.api_key, expires_at)
+        .await
+        .map_err(|e| map_storage_error(&key_id, e))?;
+
+    tracing::info!(key_id = %key_id, expires_at = expires_at, "Extended API key expiry");
+
+    Ok(Json(key.summary()))
+}
+
+/// POST /admin/api-keys/:key_id/upgrade - Move a key out of the trial tier
+pub async fn upgrade_api_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    Json(body): Json<UpgradeKeyRequest>,
+) -> Result<Json<ApiKeySummary>, ApiError> {
+    let key = find_key(&state, &key_id).await?;
+    let key = state
+        .storage
+        .upgrade_api_key(&key.api_key, &body.service_tier, body.rate_limit, body.monthly_budget)
+        .await
+        .map_err(|e| map_storage_error(&key_id, e))?;
+
+    Ok(Json(key.summary()))
+}
+
+/// POST /admin/ephemeral-keys - Mint a new in-memory ephemeral key
+pub async fn mint_ephemeral_key(
+    State(state): State<AppState>,
+    Json(body): Json<MintEphemeralKeyRequest>,
+) -> Result<(StatusCode, Json<EphemeralKey>), ApiError> {
+    if body.ttl_seconds.is_some_and(|ttl| ttl <= 0) {
+        return Err(ApiError::InvalidRequest(
+            "'ttl_seconds' must be positive".to_string(),
+        ));
+    }
+
+    let key = state
+        .ephemeral_keys
+        .mint(body.label, body.ttl_seconds, body.scopes);
+
+    tracing::info!(id = %key.id, expires_at = ?key.expires_at, "Minted ephemeral API key");
+
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+/// GET /admin/ephemeral-keys - List live ephemeral keys (secrets redacted)
+pub async fn list_ephemeral_keys(State(state): State<AppState>) -> Json<Vec<EphemeralKeySummary>> {
+    Json(state.ephemeral_keys.list())
+}
+
+/// DELETE /admin/ephemeral-keys/:id - Revoke an ephemeral key by id or key value
+pub async fn revoke_ephemeral_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.ephemeral_keys.revoke(&id) {
+        return Err(ApiError::NotFound(format!("Ephemeral key not found: {}", id)));
+    }
+
+    tracing::info!(id = %id, "Revoked ephemeral API key");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/prompts - List prompt templates (latest version of each)
+pub async fn list_prompt_templates(State(state): State<AppState>) -> Json<Vec<PromptTemplate>> {
+    Json(state.prompt_templates.list())
+}
+
+/// POST /admin/prompts/:name - Store a new version of a prompt template
+pub async fn create_prompt_template(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(name): Path<String>,
+    Json(body): Json<PromptTemplateDefinition>,
+) -> Result<(StatusCode, Json<PromptTemplate>), ApiError> {
+    let template = state
+        .prompt_templates
+        .create(&name, body, &key_info.user_id)
+        .map_err(template_error)?;
+
+    tracing::info!(name = %template.name, version = template.version, "Stored prompt template");
+
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+/// GET /admin/prompts/:name - List every version of a prompt template
+pub async fn get_prompt_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<PromptTemplate>>, ApiError> {
+    state
+        .prompt_templates
+        .versions(&name)
+        .map(Json)
+        .map_err(template_error)
+}
+
+/// DELETE /admin/prompts/:name - Delete a prompt template with all its versions
+pub async fn delete_prompt_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.prompt_templates.delete(&name) {
+        return Err(ApiError::NotFound(format!("Prompt template '{}' was not found", name)));
+    }
+
+    tracing::info!(name = %name, "Deleted prompt template");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/feature-flags - List stored feature flags
+pub async fn list_feature_flags(State(state): State<AppState>) -> Json<Vec<FeatureFlag>> {
+    Json(state.feature_flags.list())
+}
+
+/// GET /admin/feature-flags/:name - Get a stored feature flag
+pub async fn get_feature_flag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<FeatureFlag>, ApiError> {
+    state
+        .feature_flags
+        .get(&name)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Feature flag '{}' was not found", name)))
+}
+
+/// PUT /admin/feature-flags/:name - Create or replace a feature flag
+pub async fn put_feature_flag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<FeatureFlagUpdate>,
+) -> Result<Json<FeatureFlag>, ApiError> {
+    if body.rollout_percent > 100 {
+        return Err(ApiError::InvalidRequest(
+            "'rollout_percent' must be between 0 and 100".to_string(),
+        ));
+    }
+
+    let flag = state
+        .feature_flags
+        .upsert(FeatureFlag {
+            name,
+            enabled: body.enabled,
+            rollout_percent: body.rollout_percent,
+            enabled_keys: body.enabled_keys,
+            disabled_keys: body.disabled_keys,
+            enabled_tenants: body.enabled_tenants,
+            description: body.description,
+            updated_at: None,
+        })
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+
+    tracing::info!(
+        name = %flag.name,
+        enabled = flag.enabled,
+        rollout_percent = flag.rollout_percent,
+        "Stored feature flag"
+    );
+
+    Ok(Json(flag))
+}
+
+/// DELETE /admin/feature-flags/:name - Delete a feature flag (the settings default applies again)
+pub async fn delete_feature_flag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let removed = state
+        .feature_flags
+        .remove(&name)
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+    if !removed {
+        return Err(ApiError::NotFound(format!("Feature flag '{}' was not found", name)));
+    }
+
+    tracing::info!(name = %name, "Deleted feature flag");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /admin/feature-flags/reload - Re-read stored feature flags
+pub async fn reload_feature_flags(
+    State(state): State<AppState>,
+) -> Result<Json<ReloadFeatureFlagsResponse>, ApiError> {
+    let flag_count = state
+        .feature_flags
+        .reload()
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ReloadFeatureFlagsResponse { flag_count }))
+}
+
+/// GET /admin/model-deprecations - List model deprecation schedules
+pub async fn list_model_deprecations(State(state): State<AppState>) -> Json<Vec<ModelDeprecation>> {
+    Json(state.model_deprecations.list())
+}
+
+/// GET /admin/model-deprecations/:model - Get the deprecation schedule of a model pattern
+pub async fn get_model_deprecation(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+) -> Result<Json<ModelDeprecation>, ApiError> {
+    state
+        .model_deprecations
+        .get(&model)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No deprecation schedule for '{}'", model)))
+}
+
+/// PUT /admin/model-deprecations/:model - Create or replace a model deprecation schedule
+pub async fn put_model_deprecation(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+    Json(body): Json<ModelDeprecationUpdate>,
+) -> Result<Json<ModelDeprecation>, ApiError> {
+    if body.sunset_at.is_some_and(|sunset| sunset < body.deprecated_at) {
+        return Err(ApiError::InvalidRequest(
+            "'sunset_at' must not be before 'deprecated_at'".to_string(),
+        ));
+    }
+    if body.remap_after_sunset && body.successor.is_none() {
+        return Err(ApiError::InvalidRequest(
+            "'remap_after_sunset' requires a 'successor'".to_string(),
+        ));
+    }
+
+    let schedule = ModelDeprecation {
+        model,
+        deprecated_at: body.deprecated_at,
+        sunset_at: body.sunset_at,
+        successor: body.successor,
+        remap_after_sunset: body.remap_after_sunset,
+    };
+    state.model_deprecations.upsert(schedule.clone());
+
+    tracing::info!(
+        model = %schedule.model,
+        sunset_at = ?schedule.sunset_at,
+        successor = ?schedule.successor,
+        "Stored model deprecation schedule"
+    );
+
+    Ok(Json(schedule))
+}
+
+/// DELETE /admin/model-deprecations/:model - Delete a model deprecation schedule
+pub async fn delete_model_deprecation(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.model_deprecations.remove(&model) {
+        return Err(ApiError::NotFound(format!("No deprecation schedule for '{}'", model)));
+    }
+
+    tracing::info!(model = %model, "Deleted model deprecation schedule");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/inference-presets - List inference presets
+pub async fn list_inference_presets(State(state): State<AppState>) -> Json<Vec<InferencePreset>> {
+    Json(state.inference_presets.list())
+}
+
+/// GET /admin/inference-presets/:name - Get an inference preset
+pub async fn get_inference_preset(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<InferencePreset>, ApiError> {
+    state
+        .inference_presets
+        .get(&name)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No inference preset '{}'", name)))
+}
+
+/// PUT /admin/inference-presets/:name - Create or replace an inference preset
+pub async fn put_inference_preset(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<InferencePresetUpdate>,
+) -> Result<Json<InferencePreset>, ApiError> {
+    let preset = InferencePreset {
+        name,
+        params: body.params,
+        models: body.models,
+    };
+    preset.validate().map_err(ApiError::InvalidRequest)?;
+
+    state
+        .storage
+        .put_inference_preset(&preset)
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+    state
+        .inference_presets
+        .upsert(preset.clone())
+        .map_err(ApiError::InvalidRequest)?;
+
+    tracing::info!(
+        preset = %preset.name,
+        models = ?preset.models,
+        "Stored inference preset"
+    );
+
+    Ok(Json(preset))
+}
+
+/// DELETE /admin/inference-presets/:name - Delete an inference preset
+pub async fn delete_inference_preset(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state
+        .storage
+        .delete_inference_preset(&name)
+        .await
+        .map_err(|e| match e {
+            StorageError::NotFound => ApiError::NotFound(format!("No inference preset '{}'", name)),
+            other => ApiError::DatabaseError(other.to_string()),
+        })?;
+    state.inference_presets.remove(&name);
+
+    tracing::info!(preset = %name, "Deleted inference preset");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/output-watchdog - Streams stopped at the output ceiling, per key
+pub async fn output_watchdog_trips(State(state): State<AppState>) -> Json<Vec<KeyTrips>> {
+    Json(state.output_watchdog.trips())
+}
+
+fn usage_reconciler(state: &AppState) -> Result<&UsageReconciler, ApiError> {
+    state
+        .usage_reconciler
+        .as_deref()
+        .ok_or_else(|| ApiError::NotFound("Usage reconciliation is not enabled".to_string()))
+}
+
+/// GET /admin/usage-reconciliation - Latest token usage reconciliation report
+pub async fn get_usage_reconciliation(
+    State(state): State<AppState>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    usage_reconciler(&state)?
+        .last_report()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No reconciliation has run yet".to_string()))
+}
+
+/// POST /admin/usage-reconciliation/run - Reconcile token usage now
+pub async fn run_usage_reconciliation(
+    State(state): State<AppState>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let report = usage_reconciler(&state)?.reconcile(Utc::now()).await;
+    Ok(Json(report))
+}
+
+/// POST /admin/model-mappings/reload - Re-read stored model mappings into the shared converters
+pub async fn reload_model_mappings(
+    State(state): State<AppState>,
+) -> Result<Json<ReloadMappingsResponse>, ApiError> {
+    let mapping_count = state
+        .reload_converters()
+        .await
+        .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ReloadMappingsResponse {
+        mapping_count,
+        generation: state.converters.generation(),
+    }))
+}
+
+/// POST /admin/cache/flush - Clear the selected caches without a restart
+pub async fn flush_caches(
+    State(state): State<AppState>,
+    Json(body): Json<FlushCacheRequest>,
+) -> Result<Json<FlushCacheResponse>, ApiError> {
+    let caches = if body.caches.is_empty() {
+        CacheKind::ALL.to_vec()
+    } else {
+        body.caches
+    };
+
+    let mut response = FlushCacheResponse::default();
+    for cache in caches {
+        match cache {
+            CacheKind::ModelMappings if response.model_mappings.is_none() => {
+                let mapping_count = state
+                    .reload_converters()
+                    .await
+                    .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+                response.model_mappings = Some(ReloadMappingsResponse {
+                    mapping_count,
+                    generation: state.converters.generation(),
+                });
+            }
+            CacheKind::CredentialClients if response.credential_clients.is_none() => {
+                response.credential_clients = Some(state.bedrock.flush_client_cache());
+            }
+            _ => {}
+        }
+    }
+
+    tracing::info!(
+        model_mappings = response.model_mappings.is_some(),
+        credential_clients = ?response.credential_clients,
+        "Flushed caches"
+    );
+
+    Ok(Json(response))
+}
+
+/// Routing totals for one backend, with the derived amplification factor
+#[derive(Debug, Serialize)]
+pub struct RoutingStatsEntry {
+    #[serde(flatten)]
+    pub stats: BackendRoutingStats,
+    /// Upstream attempts per served request
+    pub amplification: f64,
+}
+
+/// GET /admin/routing-stats - Which backends served requests and how many attempts it took
+pub async fn routing_stats(State(state): State<AppState>) -> Json<Vec<RoutingStatsEntry>> {
+    Json(
+        state
+            .routing_metrics
+            .snapshot()
+            .into_iter()
+            .map(|stats| RoutingStatsEntry {
+                amplification: stats.amplification(),
+                stats,
+            })
+            .collect(),
+    )
+}
+
+/// GET /admin/priority-stats - Request totals per `x-priority` class
+pub async fn priority_stats(State(state): State<AppState>) -> Json<Vec<PriorityStats>> {
+    Json(state.priority_metrics.snapshot())
+}
+
+/// Response for `GET /admin/stream-buffers`
+#[derive(Debug, Serialize)]
+pub struct StreamBuffersResponse {
+    /// Configured frames per stream buffer (0 = unbuffered)
+    pub buffer_frames: usize,
+    pub slow_client_policy: SlowClientPolicy,
+    #[serde(flatten)]
+    pub stats: StreamBufferStats,
+}
+
+/// GET /admin/stream-buffers - Buffer depth between backends and slow clients
+pub async fn stream_buffers(State(state): State<AppState>) -> Json<StreamBuffersResponse> {
+    let config = &state.settings.stream_backpressure;
+    Json(StreamBuffersResponse {
+        buffer_frames: config.buffer_frames,
+        slow_client_policy: config.slow_client_policy,
+        stats: state.stream_buffers.snapshot(),
+    })
+}
+
+/// GET /admin/tap - Stream sanitized summaries of requests as they happen
+///
+/// Each `data:` line is a JSON `TapEvent` (`started`, `progress` or
+/// `finished`). Comments mark keep-alives and events dropped because the
+/// client fell behind.
+pub async fn tap(State(state): State<AppState>) -> SseResponse {
+    let mut events = state.request_tap.subscribe();
+    tracing::info!(
+        subscribers = state.request_tap.subscriber_count(),
+        "Request tap opened"
+    );
+
+    let stream = async_stream::stream! {
+        let mut sse = SseEncoder::new();
+        yield sse.comment("tap open");
+
+        loop {
+            match tokio::time::timeout(TAP_KEEP_ALIVE, events.recv()).await {
+                Ok(Ok(event)) => yield sse.data(&event),
+                Ok(Err(RecvError::Lagged(skipped))) => {
+                    yield sse.comment(&format!("lagged {} events", skipped));
+                }
+                Ok(Err(RecvError::Closed)) => break,
+                Err(_) => yield sse.comment("keep-alive"),
+            }
+        }
+    };
+
+    SseResponse::new(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use std::collections::HashMap;
+
+    fn key(api_key: &str, created_at: i64, last_used_at: Option<i64>) -> ApiKey {
+        let mut item = HashMap::from([
+            ("api_key".to_string(), AttributeValue::S(api_key.to_string())),
+            ("user_id".to_string(), AttributeValue::S("user1".to_string())),
+            ("created_at".to_string(), AttributeValue::N(created_at.to_string())),
+        ]);
+        if let Some(ts) = last_used_at {
+            item.insert("last_used_at".to_string(), AttributeValue::N(ts.to_string()));
+        }
+        ApiKey::from_dynamodb(&item).unwrap()
+    }
+
+    #[test]
+    fn test_unused_keys_report() {
+        let day = 86_400;
+        let now = 100 * day;
+        let keys = vec![
+            key("sk-active", 0, Some(now - day)),
+            key("sk-stale", 0, Some(now - 40 * day)),
+            key("sk-never", 0, None),
+            key("sk-new", now - 2 * day, None),
+        ];
+
+        let report = unused_keys_report(&keys, 30, now);
+        let ids: Vec<_> = report.keys.iter().map(|k| k.key_id.clone()).collect();
+        assert_eq!(ids, vec![ApiKey::id_for("sk-never"), ApiKey::id_for("sk-stale")]);
+        assert_eq!(report.keys[0].days_unused, 100);
+        assert_eq!(report.keys[1].days_unused, 40);
+    }
+
+    #[test]
+    fn test_build_and_rotate_api_key() {
+        let request: CreateApiKeyRequest = serde_json::from_value(serde_json::json!({
+            "user_id": "tenant-a",
+            "name": "ci",
+            "monthly_budget": 50.0,
+            "scopes": ["priority"],
+            "expires_in_days": 30
+        }))
+        .unwrap();
+        let key = build_api_key(&request, 100, &TrialConfig::default(), 1_000).unwrap();
+        assert!(key.api_key.starts_with("sk-"));
+
+        // Listings carry the key ID and a prefix, never the secret
+        let summary = serde_json::to_string(&key.summary()).unwrap();
+        assert!(!summary.contains(&key.api_key));
+        assert!(summary.contains(&key.key_id()));
+        assert_eq!(key.rate_limit, 100);
+        assert_eq!(key.expires_at, Some(1_000 + 30 * 86_400));
+        assert!(key.is_active);
+
+        let mut used = key.clone();
+        used.budget_used_mtd = 12.5;
+        used.request_count = 40;
+        let rotated = rotated_key(&used, 2_000);
+        assert_ne!(rotated.api_key, used.api_key);
+        assert_eq!(rotated.budget_used_mtd, 12.5);
+        assert_eq!(rotated.scopes, used.scopes);
+        assert_eq!(rotated.request_count, 0);
+        assert_eq!(rotated.created_at, 2_000);
+
+        let mut invalid = request;
+        invalid.rate_limit = Some(0);
+        assert!(build_api_key(&invalid, 100, &TrialConfig::default(), 1_000).is_err());
+    }
+
+    #[test]
+    fn test_trial_key_defaults() {
+        let trial = TrialConfig::default();
+        let request: CreateApiKeyRequest = serde_json::from_value(serde_json::json!({
+            "user_id": "tenant-a",
+            "name": "evaluation",
+            "service_tier": "trial"
+        }))
+        .unwrap();
+        let key = build_api_key(&request, 100, &trial, 1_000).unwrap();
+        assert_eq!(key.monthly_budget, Some(trial.monthly_budget));
+        assert_eq!(key.expires_at, Some(1_000 + trial.duration_days * 86_400));
+
+        // Explicit values win over the trial defaults
+        let mut explicit = request;
+        explicit.monthly_budget = Some(1.0);
+        explicit.expires_in_days = Some(3);
+        let key = build_api_key(&explicit, 100, &trial, 1_000).unwrap();
+        assert_eq!(key.monthly_budget, Some(1.0));
+        assert_eq!(key.expires_at, Some(1_000 + 3 * 86_400));
+    }
+
+    #[test]
+    fn test_set_quota_null_removes_limit() {
+        let body: SetQuotaRequest =
+            serde_json::from_str(r#"{"rate_limit": 20, "monthly_budget": null}"#).unwrap();
+        assert_eq!(body.rate_limit, Some(20));
+        assert_eq!(body.tpm_limit, None);
+        assert_eq!(body.monthly_budget, Some(None));
+        assert!(validate_quota(&body).is_ok());
+
+        let body: SetQuotaRequest = serde_json::from_str(r#"{"tpm_limit": -5}"#).unwrap();
+        assert!(validate_quota(&body).is_err());
+    }
+}
@@ -0,0 +1,850 @@
+//! Agent runner endpoint
+//!
+//! POST /v1/agents/run takes a Messages request plus the tools an agent may
+//! use, and runs the model-tool loop server-side so simple agent apps don't
+//! need a loop of their own. Tools come in three kinds:
+//!
+//! - client tools (ordinary tool definitions): the run stops with
+//!   `stop_reason: "tool_use"` and the client continues it with its results
+//! - MCP tools from the servers in `mcp_servers`, called over streamable HTTP
+//! - code execution (`{"type": "code_execution_20250825"}`), run in the PTC
+//!   sandbox for keys with code execution enabled
+//!
+//! Every model turn goes through [`messages::send_message`], so routing,
+//! key restrictions and conversions are the same as for /v1/messages. A run
+//! stops after `max_steps` model turns. With `stream: true` each turn and
+//! tool result is sent as an SSE event as soon as it is ready, followed by
+//! `agent.done` with the same body a non-streaming run returns.
+//!
+//! Tools named in `cache_tool_results` have their successful results kept
+//! for the run (see [`ToolResultCache`]); an identical call later in the run
+//! is answered from the cache instead of calling the MCP server or sandbox.
+//!
+//! For keys with the `content_offload` scope, oversized blocks are offloaded
+//! to S3 in what is sent to the client (events and the final result); the
+//! model always sees the full content.
+
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::claude_code;
+use crate::api::dry_run;
+use crate::api::messages::{self, ApiError, MessageApiResponse};
+use crate::api::sse::{SseEncoder, SseResponse};
+use crate::middleware::{ApiKeyInfo, RequestMetrics};
+use crate::schemas::anthropic::{
+    CodeExecutionTool, ContentBlock, ErrorResponse, Message, MessageContent, MessageRequest,
+    MessageResponse, StopReason, ToolResultValue, Usage,
+};
+use crate::server::state::AppState;
+use crate::services::{ContentOffloader, McpSession, ToolResultCache};
+
+/// Model turns a run may take when the request does not say
+pub const DEFAULT_MAX_STEPS: u32 = 10;
+
+/// Largest `max_steps` a request may ask for
+pub const MAX_STEPS_LIMIT: u32 = 50;
+
+/// Stop reason of a run cut off by its step limit
+pub const MAX_STEPS_STOP_REASON: &str = "max_steps";
+
+// ============================================================================
+// Request and Response
+// ============================================================================
+
+/// Body of POST /v1/agents/run: a Messages request plus agent settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentRunRequest {
+    #[serde(flatten)]
+    pub request: MessageRequest,
+    /// Remote MCP servers whose tools the agent may call
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerDefinition>,
+    /// Model turns before the run is stopped
+    #[serde(default = "default_max_steps")]
+    pub max_steps: u32,
+    /// Server-side tools whose results are reused for identical calls in
+    /// the run (`*` for all)
+    #[serde(default)]
+    pub cache_tool_results: Vec<String>,
+}
+
+fn default_max_steps() -> u32 {
+    DEFAULT_MAX_STEPS
+}
+
+/// MCP server, in the shape of Anthropic's MCP connector
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpServerDefinition {
+    pub url: String,
+    pub name: String,
+    #[serde(default)]
+    pub authorization_token: Option<String>,
+    #[serde(default)]
+    pub tool_configuration: Option<McpToolConfiguration>,
+}
+
+/// Which of an MCP server's tools are offered to the model
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpToolConfiguration {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Tool names to offer; all of them when absent
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl McpServerDefinition {
+    fn allows(&self, tool: &str) -> bool {
+        match &self.tool_configuration {
+            None => true,
+            Some(config) => {
+                config.enabled
+                    && config
+                        .allowed_tools
+                        .as_ref()
+                        .map_or(true, |allowed| allowed.iter().any(|name| name == tool))
+            }
+        }
+    }
+}
+
+/// Result of a run
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRunResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub response_type: &'static str, // "agent_run"
+    pub model: String,
+    /// The last turn's stop reason, `tool_use` when client tools are waiting
+    /// for results, or `max_steps`
+    pub stop_reason: String,
+    /// Model turns taken
+    pub steps: u32,
+    /// Messages the run added to the conversation, to send back when
+    /// continuing it
+    pub messages: Vec<Message>,
+    /// Content of the last model turn
+    pub content: Vec<ContentBlock>,
+    /// Usage summed over all turns
+    pub usage: Usage,
+    /// Tool calls answered from the run's result cache
+    pub cached_tool_results: u64,
+}
+
+/// `agent.step` event: one model turn
+#[derive(Serialize)]
+struct StepEvent<'a> {
+    step: u32,
+    message: &'a MessageResponse,
+}
+
+/// `agent.tool_result` event: one server-side tool call
+#[derive(Serialize)]
+struct ToolResultEvent<'a> {
+    step: u32,
+    #[serde(flatten)]
+    output: &'a ToolOutput,
+}
+
+// ============================================================================
+// Tools
+// ============================================================================
+
+/// Who runs a tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolKind {
+    Client,
+    /// Index into [`AgentTools::mcp`]
+    Mcp(usize),
+    CodeExecution,
+}
+
+/// A `tool_use` block from a model turn
+#[derive(Debug, Clone, PartialEq)]
+struct ToolCall {
+    id: String,
+    name: String,
+    input: Value,
+}
+
+/// Output of a server-side tool call
+#[derive(Debug, Clone, Serialize)]
+struct ToolOutput {
+    tool_use_id: String,
+    name: String,
+    content: String,
+    is_error: bool,
+    /// Reused from an identical earlier call
+    cached: bool,
+}
+
+impl ToolOutput {
+    fn new(call: &ToolCall, content: String, is_error: bool) -> Self {
+        Self {
+            tool_use_id: call.id.clone(),
+            name: call.name.clone(),
+            content,
+            is_error,
+            cached: false,
+        }
+    }
+
+    fn error(call: &ToolCall, message: impl Into<String>) -> Self {
+        Self::new(call, message.into(), true)
+    }
+
+    fn to_block(&self) -> ContentBlock {
+        ContentBlock::ToolResult {
+            tool_use_id: self.tool_use_id.clone(),
+            content: ToolResultValue::Text(self.content.clone()),
+            is_error: self.is_error.then_some(true),
+            cache_control: None,
+        }
+    }
+}
+
+/// The tool calls of a turn that stopped for tool use
+#[derive(Debug, PartialEq)]
+struct ToolPlan {
+    /// Calls run by the gateway (MCP, code execution, unknown tools)
+    server_calls: Vec<ToolCall>,
+    /// Calls left for the client
+    client_calls: usize,
+}
+
+/// Split a turn's tool calls between the gateway and the client
+///
+/// Returns `None` when the turn did not stop for tool use. Calls to tools the
+/// run does not know are answered by the gateway with an error, so the model
+/// can correct itself.
+fn plan_tools(response: &MessageResponse, kinds: &HashMap<String, ToolKind>) -> Option<ToolPlan> {
+    if response.stop_reason != Some(StopReason::ToolUse) {
+        return None;
+    }
+    let calls: Vec<ToolCall> = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, input, .. } => Some(ToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    if calls.is_empty() {
+        return None;
+    }
+
+    let (client, server): (Vec<_>, Vec<_>) = calls
+        .into_iter()
+        .partition(|call| kinds.get(&call.name) == Some(&ToolKind::Client));
+    Some(ToolPlan {
+        server_calls: server,
+        client_calls: client.len(),
+    })
+}
+
+/// Why the run stops after a turn's server-side calls, if it does
+fn stop_after_tools(plan: &ToolPlan, step: u32, max_steps: u32) -> Option<&'static str> {
+    if plan.client_calls > 0 {
+        Some("tool_use")
+    } else if step >= max_steps {
+        Some(MAX_STEPS_STOP_REASON)
+    } else {
+        None
+    }
+}
+
+/// Tools available to a run
+struct AgentTools {
+    kinds: HashMap<String, ToolKind>,
+    mcp: Vec<McpSession>,
+    sandbox: Option<CodeSandbox>,
+    cache: ToolResultCache,
+}
+
+impl AgentTools {
+    /// Resolve a request's tools, connecting to its MCP servers
+    ///
+    /// Returns the tool definitions to send to the model.
+    async fn connect(
+        state: &AppState,
+        key_info: &ApiKeyInfo,
+        tools: Vec<Value>,
+        servers: &[McpServerDefinition],
+        cache: ToolResultCache,
+    ) -> Result<(Self, Vec<Value>), ApiError> {
+        let mut agent_tools = Self {
+            kinds: HashMap::new(),
+            mcp: Vec::new(),
+            sandbox: None,
+            cache,
+        };
+        let mut definitions = Vec::with_capacity(tools.len());
+        let code_execution_type = CodeExecutionTool::default().tool_type;
+
+        for tool in tools {
+            if tool.get("type").and_then(Value::as_str) == Some(code_execution_type.as_str()) {
+                let name = tool
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("code_execution")
+                    .to_string();
+                agent_tools.register(&name, ToolKind::CodeExecution)?;
+                agent_tools.sandbox = Some(CodeSandbox::open(state, key_info).await?);
+                definitions.push(code_execution_definition(&name));
+            } else {
+                if let Some(name) = tool.get("name").and_then(Value::as_str) {
+                    agent_tools.register(name, ToolKind::Client)?;
+                }
+                definitions.push(tool);
+            }
+        }
+
+        for server in servers {
+            let session = McpSession::connect(
+                &state.settings.mcp,
+                &server.url,
+                server.authorization_token.clone(),
+            )
+            .await
+            .map_err(|e| mcp_error(server, e))?;
+            let tools = session.list_tools().await.map_err(|e| mcp_error(server, e))?;
+            let index = agent_tools.mcp.len();
+            for tool in tools.iter().filter(|tool| server.allows(&tool.name)) {
+                agent_tools.register(&tool.name, ToolKind::Mcp(index))?;
+                definitions.push(tool.to_tool_definition());
+            }
+            tracing::debug!(server = %server.name, tools = tools.len(), "Connected to MCP server");
+            agent_tools.mcp.push(session);
+        }
+
+        Ok((agent_tools, definitions))
+    }
+
+    fn register(&mut self, name: &str, kind: ToolKind) -> Result<(), ApiError> {
+        if self.kinds.insert(name.to_string(), kind).is_some() {
+            return Err(ApiError::bad_request(format!(
+                "Tool name '{}' is defined more than once",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Run one server-side call, or answer it from the result cache
+    async fn call(&self, call: &ToolCall) -> ToolOutput {
+        if let Some(content) = self.cache.get(&call.name, &call.input) {
+            return ToolOutput {
+                cached: true,
+                ..ToolOutput::new(call, content, false)
+            };
+        }
+        let output = self.execute(call).await;
+        if !output.is_error {
+            self.cache.insert(&call.name, &call.input, &output.content);
+        }
+        output
+    }
+
+    async fn execute(&self, call: &ToolCall) -> ToolOutput {
+        match self.kinds.get(&call.name) {
+            Some(ToolKind::Mcp(index)) => match self.mcp[*index].call_tool(&call.name, &call.input).await {
+                Ok(result) => ToolOutput::new(call, result.text(), result.is_error),
+                Err(e) => ToolOutput::error(call, e.to_string()),
+            },
+            Some(ToolKind::CodeExecution) => match &self.sandbox {
+                Some(sandbox) => sandbox.run(call).await,
+                None => ToolOutput::error(call, "Code execution is not available"),
+            },
+            Some(ToolKind::Client) | None => {
+                ToolOutput::error(call, format!("Unknown tool '{}'", call.name))
+            }
+        }
+    }
+
+    async fn close(&self) {
+        if let Some(ref sandbox) = self.sandbox {
+            sandbox.close().await;
+        }
+    }
+}
+
+fn code_execution_definition(name: &str) -> Value {
+    json!({
+        "name": name,
+        "description": "Run Python code in a sandbox and return its stdout, stderr and exit code.",
+        "input_schema": {
+            "type": "object",
+            "properties": {
+                "code": {"type": "string", "description": "Python source to run"}
+            },
+            "required": ["code"]
+        }
+    })
+}
+
+fn mcp_error(server: &McpServerDefinition, err: crate::services::McpError) -> ApiError {
+    if let crate::services::McpError::NotAllowed(reason) = err {
+        return ApiError::bad_request(format!("MCP server '{}': {}", server.name, reason));
+    }
+    ApiError {
+        status: StatusCode::BAD_GATEWAY,
+        error_type: "api_error".to_string(),
+        message: format!("MCP server '{}': {}", server.name, err),
+        taxonomy: None,
+    }
+}
+
+/// PTC sandbox session used for a run's code execution calls
+#[cfg(feature = "ptc")]
+struct CodeSandbox {
+    service: std::sync::Arc<crate::services::PtcService>,
+    session_id: String,
+}
+
+#[cfg(feature = "ptc")]
+impl CodeSandbox {
+    async fn open(state: &AppState, key_info: &ApiKeyInfo) -> Result<Self, ApiError> {
+        let service = state
+            .ptc_service
+            .clone()
+            .filter(|_| state.is_ptc_enabled())
+            .ok_or_else(|| ApiError::bad_request("Code execution is not enabled"))?;
+        if !state.feature_flags.is_enabled(crate::services::FLAG_PTC, key_info) {
+            return Err(ApiError::forbidden("Code execution is not enabled for this API key"));
+        }
+        let session_id = service
+            .create_session_with_budget(key_info.ptc_budget)
+            .await
+            .map_err(|e| ApiError::internal_error(format!("Failed to start sandbox: {}", e)))?;
+        Ok(Self { service, session_id })
+    }
+
+    async fn run(&self, call: &ToolCall) -> ToolOutput {
+        let Some(code) = call.input.get("code").and_then(Value::as_str) else {
+            return ToolOutput::error(call, "Missing required string field 'code'");
+        };
+        match self.service.execute_code(&self.session_id, code).await {
+            Ok(result) => {
+                let mut content = result.stdout.clone();
+                if !result.stderr.is_empty() {
+                    content.push_str(&format!("\n[stderr]\n{}", result.stderr));
+                }
+                if result.timed_out {
+                    content.push_str("\n[timed out]");
+                }
+                content.push_str(&format!("\n[exit code {}]", result.exit_code));
+                ToolOutput::new(call, content, !result.is_success())
+            }
+            Err(e) => ToolOutput::error(call, e.to_string()),
+        }
+    }
+
+    async fn close(&self) {
+        if let Err(e) = self.service.remove_session(&self.session_id).await {
+            tracing::debug!(session_id = %self.session_id, error = %e, "Failed to remove agent sandbox");
+        }
+    }
+}
+
+/// Builds without the `ptc` feature have no sandbox
+#[cfg(not(feature = "ptc"))]
+enum CodeSandbox {}
+
+#[cfg(not(feature = "ptc"))]
+impl CodeSandbox {
+    async fn open(_state: &AppState, _key_info: &ApiKeyInfo) -> Result<Self, ApiError> {
+        Err(ApiError::bad_request("Code execution is not enabled"))
+    }
+
+    async fn run(&self, _call: &ToolCall) -> ToolOutput {
+        match *self {}
+    }
+
+    async fn close(&self) {
+        match *self {}
+    }
+}
+
+// ============================================================================
+// Runner
+// ============================================================================
+
+/// State of one run: the conversation so far and the tools
+struct AgentRunner {
+    state: AppState,
+    key_info: ApiKeyInfo,
+    headers: HeaderMap,
+    /// The client's request, with the model-facing tool definitions
+    request: MessageRequest,
+    tools: AgentTools,
+    /// Offloads oversized content sent to the client (None if not opted in)
+    offloader: Option<Arc<ContentOffloader>>,
+    max_steps: u32,
+    id: String,
+    step: u32,
+    new_messages: Vec<Message>,
+    last_content: Vec<ContentBlock>,
+    usage: Usage,
+}
+
+impl AgentRunner {
+    /// Run one model turn and add it to the conversation
+    async fn model_step(&mut self) -> Result<MessageResponse, ApiError> {
+        self.step += 1;
+        let mut request = self.request.clone();
+        request.messages.extend(self.new_messages.iter().cloned());
+
+        let (_, response) = messages::send_message(
+            State(self.state.clone()),
+            Extension(self.key_info.clone()),
+            None,
+            self.headers.clone(),
+            Json(request),
+        )
+        .await?;
+        let MessageApiResponse::Json(Json(response)) = response else {
+            return Err(ApiError::internal_error("Agent step did not return a message"));
+        };
+
+        self.usage.input_tokens += response.usage.input_tokens;
+        self.usage.output_tokens += response.usage.output_tokens;
+        self.new_messages.push(Message {
+            role: "assistant".to_string(),
+            content: MessageContent::Blocks(response.content.clone()),
+        });
+        self.last_content = response.content.clone();
+        tracing::debug!(
+            run_id = %self.id,
+            step = self.step,
+            stop_reason = ?response.stop_reason,
+            "Agent step finished"
+        );
+        Ok(response)
+    }
+
+    /// Run a turn's server-side calls concurrently and add their results
+    async fn run_tools(&mut self, calls: &[ToolCall]) -> Vec<ToolOutput> {
+        let outputs = join_all(calls.iter().map(|call| self.tools.call(call))).await;
+        if !outputs.is_empty() {
+            self.new_messages.push(Message {
+                role: "user".to_string(),
+                content: MessageContent::Blocks(outputs.iter().map(ToolOutput::to_block).collect()),
+            });
+        }
+        outputs
+    }
+
+    /// Offload oversized blocks of content about to be sent to the client
+    async fn offload(&self, blocks: &mut [ContentBlock]) {
+        if let Some(ref offloader) = self.offloader {
+            offloader.offload_blocks(&self.id, blocks).await;
+        }
+    }
+
+    async fn finish(mut self, stop_reason: String) -> AgentRunResponse {
+        self.tools.close().await;
+        if let Some(offloader) = self.offloader.take() {
+            for message in &mut self.new_messages {
+                if let MessageContent::Blocks(ref mut blocks) = message.content {
+                    offloader.offload_blocks(&self.id, blocks).await;
+                }
+            }
+            offloader.offload_blocks(&self.id, &mut self.last_content).await;
+        }
+        tracing::info!(
+            run_id = %self.id,
+            steps = self.step,
+            stop_reason = %stop_reason,
+            input_tokens = self.usage.input_tokens,
+            output_tokens = self.usage.output_tokens,
+            "Agent run finished"
+        );
+        AgentRunResponse {
+            id: self.id,
+            response_type: "agent_run",
+            model: self.request.model,
+            stop_reason,
+            steps: self.step,
+            messages: self.new_messages,
+            content: self.last_content,
+            usage: self.usage,
+            cached_tool_results: self.tools.cache.hits(),
+        }
+    }
+}
+
+fn stop_reason_name(response: &MessageResponse) -> String {
+    response
+        .stop_reason
+        .as_ref()
+        .map_or_else(|| "end_turn".to_string(), StopReason::to_string)
+}
+
+// ============================================================================
+// Handler
+// ============================================================================
+
+/// POST /v1/agents/run - Run a model-tool loop server-side
+///
+/// A run whose last turn called client tools ends with `stop_reason:
+/// "tool_use"`. The client continues it by sending the original messages,
+/// the returned `messages` and a user turn with its tool results; that turn
+/// is merged into the trailing turn of server-side results when there is one.
+pub async fn run_agent(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
+    Json(body): Json<AgentRunRequest>,
+) -> Result<Response, ApiError> {
+    let AgentRunRequest {
+        mut request,
+        mcp_servers,
+        max_steps,
+        cache_tool_results,
+    } = body;
+
+    if !(1..=MAX_STEPS_LIMIT).contains(&max_steps) {
+        return Err(ApiError::bad_request(format!(
+            "max_steps must be between 1 and {}",
+            MAX_STEPS_LIMIT
+        )));
+    }
+    if dry_run::requested(&headers) {
+        return Err(ApiError::bad_request(
+            "Agent runs cannot be dry runs; use /v1/estimate for a single turn",
+        ));
+    }
+
+    let metrics = metrics.map(|Extension(metrics)| metrics);
+    if let Some(ref metrics) = metrics {
+        let backend = state.resolve_backend(&request.model);
+        metrics.set_route(&request.model, &backend);
+        metrics.set_region(&state.backend_region(&backend));
+    }
+
+    // Continuations put the client's tool results after the server's
+    claude_code::normalize_request(&mut request);
+
+    let (tools, definitions) = AgentTools::connect(
+        &state,
+        &key_info,
+        request.tools.take().unwrap_or_default(),
+        &mcp_servers,
+        ToolResultCache::new(cache_tool_results),
+    )
+    .await?;
+    request.tools = (!definitions.is_empty()).then_some(definitions);
+    let stream = std::mem::take(&mut request.stream);
+
+    let offloader = state.content_offloader_for(&key_info);
+    let runner = AgentRunner {
+        state: state.clone(),
+        key_info,
+        headers,
+        request,
+        tools,
+        offloader,
+        max_steps,
+        id: format!("agentrun_{}", Uuid::new_v4().simple()),
+        step: 0,
+        new_messages: Vec::new(),
+        last_content: Vec::new(),
+        usage: Usage::new(0, 0),
+    };
+
+    if stream {
+        let sse = SseResponse::new(stream_run(runner, metrics))
+            .buffered(&state.settings.stream_backpressure, state.stream_buffers.clone());
+        return Ok(sse.into_response());
+    }
+
+    let response = run_to_completion(runner).await?;
+    if let Some(metrics) = metrics {
+        metrics.record_tokens(response.usage.input_tokens, response.usage.output_tokens);
+    }
+    Ok(Json(response).into_response())
+}
+
+async fn run_to_completion(mut runner: AgentRunner) -> Result<AgentRunResponse, ApiError> {
+    let stop_reason = loop {
+        let response = match runner.model_step().await {
+            Ok(response) => response,
+            Err(e) => {
+                runner.tools.close().await;
+                return Err(e);
+            }
+        };
+        let Some(plan) = plan_tools(&response, &runner.tools.kinds) else {
+            break stop_reason_name(&response);
+        };
+        runner.run_tools(&plan.server_calls).await;
+        if let Some(reason) = stop_after_tools(&plan, runner.step, runner.max_steps) {
+            break reason.to_string();
+        }
+    };
+    Ok(runner.finish(stop_reason).await)
+}
+
+/// Run as SSE: `agent.step` per turn, `agent.tool_result` per server-side
+/// call, then `agent.done` (or `error`)
+fn stream_run(
+    mut runner: AgentRunner,
+    metrics: Option<RequestMetrics>,
+) -> impl futures::Stream<Item = axum::body::Bytes> + Send {
+    async_stream::stream! {
+        let mut sse = SseEncoder::new();
+        let stop_reason = loop {
+            let mut response = match runner.model_step().await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield sse.event("error", &ErrorResponse::new(&e.error_type, &e.message));
+                    break None;
+                }
+            };
+            let plan = plan_tools(&response, &runner.tools.kinds);
+            runner.offload(&mut response.content).await;
+            yield sse.event("agent.step", &StepEvent { step: runner.step, message: &response });
+
+            let Some(plan) = plan else {
+                break Some(stop_reason_name(&response));
+            };
+            for mut output in runner.run_tools(&plan.server_calls).await {
+                if let Some(ref offloader) = runner.offloader {
+                    offloader.offload_text(&runner.id, &mut output.content).await;
+                }
+                yield sse.event("agent.tool_result", &ToolResultEvent { step: runner.step, output: &output });
+            }
+            if let Some(reason) = stop_after_tools(&plan, runner.step, runner.max_steps) {
+                break Some(reason.to_string());
+            }
+        };
+
+        match stop_reason {
+            Some(reason) => {
+                let result = runner.finish(reason).await;
+                if let Some(metrics) = metrics {
+                    metrics.record_tokens(result.usage.input_tokens, result.usage.output_tokens);
+                }
+                yield sse.event("agent.done", &result);
+            }
+            None => runner.tools.close().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(stop_reason: StopReason, content: Value) -> MessageResponse {
+        let content: Vec<ContentBlock> = serde_json::from_value(content).unwrap();
+        MessageResponse::new("msg_1", "claude-sonnet-4-5", content, Usage::new(10, 5))
+            .with_stop_reason(stop_reason)
+    }
+
+    #[test]
+    fn test_request_shape() {
+        let body: AgentRunRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "What's the weather in Paris?"}],
+            "tools": [
+                {"name": "get_location", "input_schema": {"type": "object"}},
+                {"type": "code_execution_20250825", "name": "code_execution"}
+            ],
+            "mcp_servers": [{
+                "type": "url",
+                "url": "https://mcp.example.com/mcp",
+                "name": "weather",
+                "tool_configuration": {"allowed_tools": ["forecast"]}
+            }],
+            "stream": true
+        }))
+        .unwrap();
+
+        assert_eq!(body.max_steps, DEFAULT_MAX_STEPS);
+        assert!(body.cache_tool_results.is_empty());
+        assert!(body.request.stream);
+        assert_eq!(body.request.tools.as_ref().unwrap().len(), 2);
+        let server = &body.mcp_servers[0];
+        assert!(server.allows("forecast"));
+        assert!(!server.allows("delete_everything"));
+    }
+
+    #[test]
+    fn test_plan_splits_server_and_client_calls() {
+        let kinds = HashMap::from([
+            ("forecast".to_string(), ToolKind::Mcp(0)),
+            ("get_location".to_string(), ToolKind::Client),
+        ]);
+        let response = turn(
+            StopReason::ToolUse,
+            json!([
+                {"type": "text", "text": "Checking."},
+                {"type": "tool_use", "id": "t1", "name": "forecast", "input": {"city": "Paris"}},
+                {"type": "tool_use", "id": "t2", "name": "get_location", "input": {}},
+                {"type": "tool_use", "id": "t3", "name": "made_up", "input": {}}
+            ]),
+        );
+
+        let plan = plan_tools(&response, &kinds).unwrap();
+        let names: Vec<_> = plan.server_calls.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["forecast", "made_up"]);
+        assert_eq!(plan.client_calls, 1);
+        // Client calls end the run even with steps to spare
+        assert_eq!(stop_after_tools(&plan, 1, 10), Some("tool_use"));
+
+        let end = turn(StopReason::EndTurn, json!([{"type": "text", "text": "Sunny."}]));
+        assert!(plan_tools(&end, &kinds).is_none());
+        assert_eq!(stop_reason_name(&end), "end_turn");
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let plan = ToolPlan {
+            server_calls: vec![ToolCall {
+                id: "t1".to_string(),
+                name: "forecast".to_string(),
+                input: json!({}),
+            }],
+            client_calls: 0,
+        };
+        assert_eq!(stop_after_tools(&plan, 9, 10), None);
+        assert_eq!(stop_after_tools(&plan, 10, 10), Some(MAX_STEPS_STOP_REASON));
+    }
+
+    #[test]
+    fn test_tool_output_block() {
+        let call = ToolCall {
+            id: "t1".to_string(),
+            name: "made_up".to_string(),
+            input: json!({}),
+        };
+        match ToolOutput::error(&call, "Unknown tool 'made_up'").to_block() {
+            ContentBlock::ToolResult { tool_use_id, is_error, .. } => {
+                assert_eq!(tool_use_id, "t1");
+                assert_eq!(is_error, Some(true));
+            }
+            other => panic!("expected tool_result, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Parameter capability report
+//!
+//! GET /v1/capabilities describes how OpenAI Chat Completions parameters are
+//! carried over to Converse backends: passed through, mapped to a native
+//! model field, rescaled, or ignored. Clients can read it to find out ahead
+//! of time which parameters will come back as conversion warnings.
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use crate::config::TemperatureScaling;
+use crate::converters::openai_to_bedrock::{PenaltyMapping, PENALTY_MAPPINGS};
+use crate::server::state::AppState;
+
+/// How a parameter reaches the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterSupport {
+    /// Sent unchanged
+    Supported,
+    /// Sent after adjusting the value to the backend's range
+    Scaled,
+    /// Sent as a native field for some model families, ignored for the rest
+    ModelDependent,
+    /// Not sent; a conversion warning is returned
+    Ignored,
+}
+
+/// Native field used for a parameter by one model family
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldMapping {
+    pub model_family: &'static str,
+    pub field: &'static str,
+}
+
+/// One OpenAI parameter in the report
+#[derive(Debug, Clone, Serialize)]
+pub struct ParameterCapability {
+    pub parameter: &'static str,
+    pub support: ParameterSupport,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mappings: Vec<FieldMapping>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl ParameterCapability {
+    fn new(parameter: &'static str, support: ParameterSupport) -> Self {
+        Self {
+            parameter,
+            support,
+            mappings: Vec::new(),
+            note: None,
+        }
+    }
+
+    fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// Response body for GET /v1/capabilities
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilitiesResponse {
+    pub object: &'static str,
+    /// OpenAI parameters on Converse backends (Gemini takes them natively)
+    pub openai_parameters: Vec<ParameterCapability>,
+}
+
+/// GET /v1/capabilities - Report how request parameters are converted
+pub async fn get_capabilities(State(state): State<AppState>) -> Json<CapabilitiesResponse> {
+    Json(CapabilitiesResponse {
+        object: "capabilities",
+        openai_parameters: openai_parameters(state.settings.openai_temperature_scaling),
+    })
+}
+
+fn openai_parameters(scaling: TemperatureScaling) -> Vec<ParameterCapability> {
+    let temperature = match scaling {
+        TemperatureScaling::Clamp => "Values above 1.0 are clamped to 1.0",
+        TemperatureScaling::Linear => "Values are halved from 0-2 to 0-1",
+    };
+    let penalty = |parameter: &'static str, field: fn(&PenaltyMapping) -> &'static str| {
+        let mut capability = ParameterCapability::new(parameter, ParameterSupport::ModelDependent)
+            .with_note("Ignored for model families not listed, including Claude");
+        capability.mappings = PENALTY_MAPPINGS
+            .iter()
+            .map(|mapping| FieldMapping {
+                model_family: mapping.model_family,
+                field: field(mapping),
+            })
+            .collect();
+        capability
+    };
+
+    vec![
+        ParameterCapability::new("temperature", ParameterSupport::Scaled).with_note(temperature),
+        ParameterCapability::new("top_p", ParameterSupport::Supported),
+        ParameterCapability::new("stop", ParameterSupport::Supported),
+        ParameterCapability::new("max_tokens", ParameterSupport::Supported),
+        penalty("presence_penalty", |m| m.presence_penalty),
+        penalty("frequency_penalty", |m| m.frequency_penalty),
+        ParameterCapability::new("seed", ParameterSupport::Ignored).with_note(
+            "Sent for models routed to Gemini; compare system_fingerprint across responses",
+        ),
+        ParameterCapability::new("logprobs", ParameterSupport::Ignored)
+            .with_note("Returned for models routed to Gemini"),
+        ParameterCapability::new("top_logprobs", ParameterSupport::Ignored)
+            .with_note("Returned for models routed to Gemini"),
+        ParameterCapability::new("response_format", ParameterSupport::Ignored)
+            .with_note("Only the text format is honoured"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalties_list_native_fields() {
+        let parameters = openai_parameters(TemperatureScaling::Clamp);
+
+        let presence = parameters
+            .iter()
+            .find(|p| p.parameter == "presence_penalty")
+            .unwrap();
+        assert_eq!(presence.support, ParameterSupport::ModelDependent);
+        assert_eq!(presence.mappings.len(), PENALTY_MAPPINGS.len());
+        assert_eq!(presence.mappings[0].field, "presence_penalty");
+    }
+}
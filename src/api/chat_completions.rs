@@ -5,41 +5,68 @@
 //! and converts responses back to OpenAI format.
 
 use aws_sdk_bedrockruntime::types::{
-    ContentBlock as SdkContentBlock, ConversationRole, ConverseStreamOutput,
-    InferenceConfiguration, Message as SdkMessage, SystemContentBlock, Tool as SdkTool,
-    ToolConfiguration, ToolInputSchema as SdkToolInputSchema, ToolResultContentBlock,
-    ToolResultStatus, ToolSpecification, ToolUseBlock,
+    ContentBlock as SdkContentBlock, ConverseStreamOutput, ImageSource,
 };
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    response::{sse::Event, IntoResponse, Response, Sse},
+    body::Bytes,
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use futures::stream::Stream;
-use std::convert::Infallible;
-use std::time::Instant;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::converters::{OpenAIConversionError, OpenAIToBedrockConverter};
+use crate::api::client_profile::ClientProfile;
+use crate::api::dry_run::{self, DryRunReport};
+use crate::api::extra_fields::{self, ExtraFieldsError};
+use crate::api::messages::request_coalescing_key;
+use crate::api::sse::{
+    ChunkChoiceRef, ChunkDeltaRef, ChunkRef, SseEncoder, SseResponse, StreamProgress,
+    ToolCallDelta, ToolCallSequencer, CANCELLED_STOP_REASON, OUTPUT_LIMIT_FINISH_REASON,
+};
+use crate::converters::bedrock_sdk::{self, document_to_json};
+use crate::converters::warnings::{self as conversion_warnings, CONVERSION_WARNINGS_HEADER};
+#[cfg(feature = "gemini")]
+use crate::converters::gemini_to_openai::convert_logprobs;
+#[cfg(feature = "gemini")]
+use crate::converters::GeminiToOpenAIConverter;
+use crate::converters::{ApiFormat, OpenAIConversionError};
+#[cfg(feature = "gemini")]
+use crate::schemas::gemini::GeminiRequest;
 use crate::schemas::openai::{
-    AssistantMessage, ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse,
-    ChatRole, Choice, ChunkChoice, ChunkDelta, CompletionUsage, FunctionCall,
-    OpenAIErrorResponse, ToolCall, ToolCallDelta, FunctionCallDelta,
+    AssistantMessage, AudioTranscription, ChatCompletionRequest, ChatCompletionResponse, ChatRole,
+    Choice, CompletionUsage, ContentPart, FunctionCall, OpenAIErrorResponse, ToolCall,
     current_timestamp, generate_completion_id,
 };
+use crate::error::ErrorTaxonomy;
+use crate::middleware::{
+    with_taxonomy, ApiKeyInfo, ModelDowngrade, RequestMetrics, BUDGET_DOWNGRADED_FROM_HEADER,
+    SCOPE_BACKEND_OVERRIDE,
+};
 use crate::server::state::AppState;
-use crate::services::{BedrockError, ConverseRequest};
+use crate::services::{
+    prompt_dumps, pt_governor, select_tier, stream_assembly, Backend, BackendTarget, BedrockError, BedrockStreamError, ConverseRequest,
+    completion_store, EffectiveTier, ErrorClass, PriorityError, RequestCoalescer, RequestPriority, RequestedTier, RoutingOutcome, StoredCompletion, TierDecision,
+    estimate_tokens, system_fingerprint, BACKEND_OVERRIDE_HEADER, FLAG_EVAL_CAPTURE,
+};
+#[cfg(feature = "gemini")]
+use crate::services::GeminiService;
 
 // ============================================================================
 // Error Types
 // ============================================================================
 
 /// OpenAI-style API error
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OpenAIApiError {
     pub status: StatusCode,
     pub error: OpenAIErrorResponse,
+    /// Error code and retryability, when known from the backend failure
+    pub taxonomy: Option<ErrorTaxonomy>,
 }
 
 impl OpenAIApiError {
@@ -47,6 +74,7 @@ impl OpenAIApiError {
         Self {
             status: StatusCode::BAD_REQUEST,
             error: OpenAIErrorResponse::invalid_request(&message.into()),
+            taxonomy: None,
         }
     }
 
@@ -54,6 +82,23 @@ impl OpenAIApiError {
         Self {
             status: StatusCode::UNAUTHORIZED,
             error: OpenAIErrorResponse::authentication_error(&message.into()),
+            taxonomy: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            error: OpenAIErrorResponse::new("permission_error", &message.into()),
+            taxonomy: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            error: OpenAIErrorResponse::invalid_request(&message.into()),
+            taxonomy: None,
         }
     }
 
@@ -61,6 +106,7 @@ impl OpenAIApiError {
         Self {
             status: StatusCode::TOO_MANY_REQUESTS,
             error: OpenAIErrorResponse::rate_limit_error(&message.into()),
+            taxonomy: None,
         }
     }
 
@@ -68,21 +114,30 @@ impl OpenAIApiError {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
             error: OpenAIErrorResponse::server_error(&message.into()),
+            taxonomy: None,
+        }
+    }
+
+    pub fn service_unavailable(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            error: OpenAIErrorResponse::new("service_unavailable", &message.into()),
+            taxonomy: None,
         }
     }
 
     pub fn from_bedrock_error(err: &BedrockError) -> Self {
-        match err {
-            BedrockError::Throttled(msg) => Self::rate_limited(msg),
-            BedrockError::ValidationError(msg) => Self::bad_request(msg),
-            BedrockError::ModelNotFound(msg) => Self::bad_request(format!("Model not found: {}", msg)),
-            BedrockError::AccessDenied(msg) => Self::unauthorized(msg),
-            BedrockError::ServiceUnavailable(msg) => Self::internal_error(msg),
-            BedrockError::InternalError(msg) => Self::internal_error(msg),
-            BedrockError::Serialization(msg) => Self::bad_request(format!("Serialization error: {}", msg)),
-            BedrockError::Deserialization(msg) => Self::internal_error(format!("Response error: {}", msg)),
-            BedrockError::ApiError { message, .. } => Self::internal_error(message),
-            BedrockError::Unknown(msg) => Self::internal_error(msg),
+        let message = err.client_message();
+        let error = match err.class() {
+            ErrorClass::InvalidRequest => Self::bad_request(message),
+            ErrorClass::PermissionDenied => Self::forbidden(message),
+            ErrorClass::RateLimited => Self::rate_limited(message),
+            ErrorClass::Overloaded => Self::service_unavailable(message),
+            ErrorClass::Server => Self::internal_error(message),
+        };
+        Self {
+            taxonomy: Some(ErrorTaxonomy::from_bedrock_error(err)),
+            ..error
         }
     }
 
@@ -101,7 +156,7 @@ impl OpenAIApiError {
 
 impl IntoResponse for OpenAIApiError {
     fn into_response(self) -> Response {
-        (self.status, Json(self.error)).into_response()
+        with_taxonomy((self.status, Json(self.error)).into_response(), self.taxonomy)
     }
 }
 
@@ -112,7 +167,8 @@ impl IntoResponse for OpenAIApiError {
 /// Enum to represent either a JSON response or an SSE stream (OpenAI format)
 pub enum ChatCompletionApiResponse {
     Json(Json<ChatCompletionResponse>),
-    Stream(Sse<std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>),
+    Stream(SseResponse),
+    DryRun(Json<DryRunReport>),
 }
 
 impl IntoResponse for ChatCompletionApiResponse {
@@ -120,6 +176,7 @@ impl IntoResponse for ChatCompletionApiResponse {
         match self {
             ChatCompletionApiResponse::Json(json) => json.into_response(),
             ChatCompletionApiResponse::Stream(sse) => sse.into_response(),
+            ChatCompletionApiResponse::DryRun(report) => report.into_response(),
         }
     }
 }
@@ -130,77 +187,531 @@ impl IntoResponse for ChatCompletionApiResponse {
 
 /// POST /v1/chat/completions - Create a chat completion
 ///
-/// This endpoint accepts OpenAI Chat Completions API requests, converts them to Bedrock format,
-/// calls the Bedrock Converse API, and returns the response in OpenAI format.
+/// This endpoint accepts OpenAI Chat Completions API requests, converts them to Bedrock or
+/// Gemini format depending on where the model is routed, calls the backend, and returns the
+/// response in OpenAI format.
 ///
-/// Supports both streaming and non-streaming responses.
+/// Supports both streaming and non-streaming responses. With `x-dry-run: true`
+/// the request is routed and converted but not sent; see [`dry_run`]. With
+/// request coalescing enabled, identical non-streaming requests in flight at
+/// the same time share one backend call, as on `/v1/messages`.
 pub async fn chat_completions(
     State(state): State<AppState>,
-    _headers: HeaderMap,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
     Json(request): Json<ChatCompletionRequest>,
-) -> Result<ChatCompletionApiResponse, OpenAIApiError> {
+) -> Result<(HeaderMap, ChatCompletionApiResponse), OpenAIApiError> {
+    if !state.settings.features.enable_request_coalescing
+        || request.stream
+        || dry_run::requested(&headers)
+    {
+        return complete_chat(state, key_info, metrics, headers, request).await;
+    }
+
+    let key = request_coalescing_key(&key_info, &headers, &request);
+    let coalescer = state.chat_coalescer.clone();
+    let (result, joined) = coalescer
+        .run(key, async move {
+            match complete_chat(state, key_info, metrics, headers, request).await? {
+                (headers, ChatCompletionApiResponse::Json(Json(completion))) => Ok((headers, completion)),
+                // Only streams and dry runs answer otherwise, and neither is coalesced
+                _ => Err(OpenAIApiError::internal_error(
+                    "Unexpected response to a non-streaming request",
+                )),
+            }
+        })
+        .await;
+    if joined {
+        tracing::debug!("Answered by an identical request already in flight");
+    }
+    result.map(|(headers, completion)| (headers, ChatCompletionApiResponse::Json(Json(completion))))
+}
+
+/// Coalescer for non-streaming chat completions, shared through [`AppState`]
+pub type ChatCoalescer =
+    RequestCoalescer<Result<(HeaderMap, ChatCompletionResponse), OpenAIApiError>>;
+
+/// [`chat_completions`] without request coalescing
+async fn complete_chat(
+    state: AppState,
+    key_info: ApiKeyInfo,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
+    mut request: ChatCompletionRequest,
+) -> Result<(HeaderMap, ChatCompletionApiResponse), OpenAIApiError> {
     let start_time = Instant::now();
     let request_id = Uuid::new_v4().to_string();
 
-    // Use converter to get Bedrock model ID
-    let openai_converter = OpenAIToBedrockConverter::new();
-    let bedrock_model = openai_converter.convert_model_id(&request.model);
+    // Operator presets fill in parameters the client left out
+    if let Some(preset) = state
+        .inference_presets
+        .resolve(key_info.inference_preset.as_deref(), &request.model)
+    {
+        let applied = preset.params.apply_to_chat(&mut request);
+        if !applied.is_empty() {
+            tracing::debug!(
+                request_id = %request_id,
+                presets = ?preset.names,
+                ?applied,
+                "Applied inference preset defaults"
+            );
+        }
+    }
 
-    // Apply settings overrides if available
-    let bedrock_model = state.bedrock.get_bedrock_model_id(&bedrock_model);
+    // Deprecated models are announced in headers; sunset ones may run on their successor
+    let deprecation = state.model_deprecations.check(&request.model, chrono::Utc::now());
+    if let Some(successor) = deprecation.as_ref().and_then(|d| d.remap_to.as_ref()) {
+        tracing::info!(
+            request_id = %request_id,
+            from = %request.model,
+            to = %successor,
+            "Remapping sunset model to its successor"
+        );
+        request.model = successor.clone();
+    }
+
+    // Keys near their budget are moved to a cheaper model class when enabled
+    let downgrade =
+        ModelDowngrade::evaluate(&key_info, &request.model, &state.settings.budget_downgrade);
+    if let Some(ref downgrade) = downgrade {
+        tracing::info!(
+            request_id = %request_id,
+            from = %downgrade.from,
+            to = %downgrade.to,
+            used_percent = downgrade.used_percent,
+            "Downgrading model to stay within budget"
+        );
+        request.model = downgrade.to.clone();
+    }
+
+    // Trial and restricted keys may only call their allowed models
+    if !key_info.is_model_allowed(&request.model) {
+        return Err(OpenAIApiError::forbidden(format!(
+            "API key is not allowed to use model '{}'",
+            request.model
+        )));
+    }
+
+    // Resolve the backend from the routing table (scoped keys may force one per request)
+    let backend = match headers.get(BACKEND_OVERRIDE_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(spec) => {
+            if !key_info.has_scope(SCOPE_BACKEND_OVERRIDE) {
+                return Err(OpenAIApiError::forbidden(format!(
+                    "API key is not allowed to use the {} header",
+                    BACKEND_OVERRIDE_HEADER
+                )));
+            }
+            let target = BackendTarget::parse(spec).ok_or_else(|| {
+                OpenAIApiError::bad_request(format!(
+                    "Invalid {} value: {}",
+                    BACKEND_OVERRIDE_HEADER, spec
+                ))
+            })?;
+            // An unknown profile in the header is a client error, not a misconfiguration
+            if let BackendTarget::Bedrock { profile: Some(ref name) } = target {
+                if state.backend_for(&target).is_none() {
+                    return Err(OpenAIApiError::bad_request(format!(
+                        "Unknown Bedrock profile in {}: {}",
+                        BACKEND_OVERRIDE_HEADER, name
+                    )));
+                }
+            }
+            target
+        }
+        None => state.resolve_backend(&request.model),
+    };
+    let metrics = metrics.map(|Extension(metrics)| metrics);
+    if let Some(ref metrics) = metrics {
+        metrics.set_route(&request.model, &backend);
+        metrics.set_region(&state.backend_region(&backend));
+    }
+
+    // None routes to Gemini's native OpenAI converters (not with PII
+    // tokenization, which works on Converse requests)
+    let converse_backend = match &backend {
+        BackendTarget::Gemini if !state.settings.pii_tokenization.enabled => None,
+        target => Some(state.backend_for(target).ok_or_else(|| match target {
+            BackendTarget::Bedrock { profile } => OpenAIApiError::internal_error(format!(
+                "Bedrock profile '{}' is not configured",
+                profile.clone().unwrap_or_default()
+            )),
+            other => OpenAIApiError::bad_request(format!(
+                "Model '{}' is routed to backend '{}', which is not available for this endpoint",
+                request.model, other
+            )),
+        })?),
+    };
+
+    // High priority needs a scope; low priority keeps off provisioned capacity
+    let priority = RequestPriority::from_headers(&headers, &key_info).map_err(|e| match e {
+        PriorityError::Invalid(_) => OpenAIApiError::bad_request(e.to_string()),
+        PriorityError::NotAllowed(_) => OpenAIApiError::forbidden(e.to_string()),
+    })?;
+
+    // Extra Bedrock fields need a scope and must stay within the allowlist
+    let extra_fields = extra_fields::from_request(
+        &headers,
+        None,
+        &key_info,
+        &state.settings.bedrock_extra_fields,
+    )
+    .map_err(|e| match e {
+        ExtraFieldsError::Invalid(_) => OpenAIApiError::bad_request(e.to_string()),
+        _ => OpenAIApiError::forbidden(e.to_string()),
+    })?;
+
+    // Dump the request and its conversion if enabled (for debugging)
+    if let Some(ref dir) = state.settings.print_prompts_dir {
+        prompt_dumps::spawn_dump(dir, &request_id, ApiFormat::OpenAI, &backend, &request);
+    }
 
     tracing::info!(
         request_id = %request_id,
         openai_model = %request.model,
-        bedrock_model = %bedrock_model,
+        backend = %backend,
+        priority = priority.as_str(),
         message_count = request.messages.len(),
         max_tokens = request.max_tokens.or(request.max_completion_tokens),
         stream = request.stream,
         "Processing OpenAI chat completions request"
     );
 
-    // Check for unsupported features
-    if request.n.map(|n| n > 1).unwrap_or(false) {
+    // Check for unsupported features (Gemini generates several candidates natively)
+    if request.n.map(|n| n > 1).unwrap_or(false) && (converse_backend.is_some() || request.stream) {
         return Err(OpenAIApiError::bad_request(
-            "Only n=1 is supported. Multiple completions are not available.",
+            "Only n=1 is supported for this model. Multiple completions are available for non-streaming requests to Gemini models.",
         ));
     }
 
+    if let Some(ref metadata) = request.metadata {
+        completion_store::validate_metadata(metadata).map_err(OpenAIApiError::bad_request)?;
+    }
+
+    // Dry runs stop after conversion, before anything reaches a backend
+    if dry_run::requested(&headers) {
+        let report = dry_run_report(
+            &state,
+            &backend,
+            converse_backend.as_deref(),
+            &request,
+            extra_fields.as_ref(),
+            &request_id,
+        )?
+        .with_cost_estimate(&state, &key_info)
+        .await;
+        return Ok((HeaderMap::new(), ChatCompletionApiResponse::DryRun(Json(report))));
+    }
+
+    // Claude does not take audio: replace input_audio parts with their
+    // transcriptions (Gemini takes audio natively)
+    let audio_transcriptions = match converse_backend {
+        Some(_) => transcribe_audio_parts(&state, &mut request).await?,
+        None => Vec::new(),
+    };
+
+    // Keep the request messages for `store: true`
+    let stored_messages = match request.store {
+        Some(true) if request.stream => {
+            tracing::debug!(request_id = %request_id, "store is not supported for streamed completions");
+            None
+        }
+        Some(true) if state.completion_store.is_enabled() => Some(request.messages.clone()),
+        _ => None,
+    };
+
+    let tap = state.request_tap.start(
+        &request_id,
+        "chat_completions",
+        &request.model,
+        &key_info,
+        request.stream,
+    );
+    // Keys opted in to evaluation have a sample of their conversations copied
+    let mut eval = state
+        .eval_tee
+        .as_ref()
+        .filter(|_| state.feature_flags.is_enabled(FLAG_EVAL_CAPTURE, &key_info))
+        .and_then(|tee| {
+            tee.capture(&request_id, "chat_completions", &request.model, &key_info, &request, request.stream)
+        });
+    let progress = StreamProgress::new()
+        .with_tap(tap.clone())
+        .with_eval_capture(if request.stream { eval.take() } else { None })
+        .with_quirks(ClientProfile::from_headers(&headers, &state.settings.client_compat).quirks())
+        .with_metrics(metrics.as_ref().filter(|_| request.stream).map(RequestMetrics::stream))
+        .with_priority(priority)
+        .with_output_ceiling(state.output_watchdog.ceiling(
+            &key_info.api_key,
+            &key_info.user_id,
+            &request.model,
+        ))
+        .with_generation(request.stream.then(|| {
+            state
+                .generations
+                .register(generate_completion_id(), &key_info.user_id)
+        }));
+
+    // Parameters the Converse conversion drops are reported rather than lost silently
+    let warnings = match converse_backend {
+        Some(_) => state.converters.openai_to_bedrock().conversion_warnings(&request),
+        None => Vec::new(),
+    };
+    conversion_warnings::log_warnings(&request_id, &warnings);
+
+    // Route to the resolved backend
+    let mut result = match converse_backend {
+        Some(backend) => {
+            handle_backend_request(
+                &state,
+                backend.as_ref(),
+                &request,
+                extra_fields.as_ref(),
+                &request_id,
+                start_time,
+                progress,
+            )
+            .await
+        }
+        None => {
+            state.priority_metrics.record(priority, EffectiveTier::OnDemand, Duration::ZERO);
+            handle_gemini_request(&state, &request, &request_id, start_time, progress).await
+        }
+    };
+
+    if let Ok(ChatCompletionApiResponse::Json(Json(ref mut response))) = result {
+        if !audio_transcriptions.is_empty() {
+            response.audio_transcriptions = Some(audio_transcriptions);
+        }
+
+        if let Some(messages) = stored_messages {
+            state.completion_store.insert(StoredCompletion::new(
+                key_info.user_id.clone(),
+                request.metadata.take().unwrap_or_default(),
+                messages,
+                response.clone(),
+            ));
+        }
+    }
+
+    // Slow clients read streams through a bounded buffer
+    let result = result.map(|response| match response {
+        ChatCompletionApiResponse::Stream(sse) => ChatCompletionApiResponse::Stream(
+            sse.buffered(&state.settings.stream_backpressure, state.stream_buffers.clone()),
+        ),
+        json => json,
+    });
+
+    // Streams report to the tap and evaluation sink themselves when they end
+    if let (Some(capture), Ok(ChatCompletionApiResponse::Json(Json(response)))) = (eval, &result) {
+        capture.finish(response, response.usage.prompt_tokens, response.usage.completion_tokens);
+    }
+    if let (Some(metrics), Ok(ChatCompletionApiResponse::Json(Json(response)))) = (metrics, &result) {
+        metrics.record_tokens(response.usage.prompt_tokens, response.usage.completion_tokens);
+    }
+    if let Some(tap) = tap {
+        match &result {
+            Ok(ChatCompletionApiResponse::Json(Json(response))) => tap.complete(
+                StatusCode::OK.as_u16(),
+                response.usage.prompt_tokens,
+                Some(response.usage.completion_tokens),
+            ),
+            Ok(ChatCompletionApiResponse::Stream(_) | ChatCompletionApiResponse::DryRun(_)) => {}
+            Err(e) => tap.complete(e.status.as_u16(), 0, None),
+        }
+    }
+
+    let mut response_headers = HeaderMap::new();
+    if let Some(value) = conversion_warnings::header_value(&warnings)
+        .and_then(|v| HeaderValue::from_str(&v).ok())
+    {
+        response_headers.insert(CONVERSION_WARNINGS_HEADER, value);
+    }
+    if let Some(value) = downgrade.as_ref().and_then(ModelDowngrade::header_value) {
+        response_headers.insert(BUDGET_DOWNGRADED_FROM_HEADER, value);
+    }
+    if let Some(ref deprecation) = deprecation {
+        response_headers.extend(deprecation.headers());
+    }
+    result.map(|response| (response_headers, response))
+}
+
+/// Route and convert a request without calling the backend
+///
+/// `backend` is `None` for requests routed to Gemini.
+fn dry_run_report(
+    state: &AppState,
+    target: &BackendTarget,
+    backend: Option<&dyn Backend>,
+    request: &ChatCompletionRequest,
+    extra_fields: Option<&serde_json::Value>,
+    request_id: &str,
+) -> Result<DryRunReport, OpenAIApiError> {
+    let (backend_model, estimated_input_tokens, warnings) = match backend {
+        Some(backend) => {
+            let backend_model = if backend.name() == "bedrock" {
+                state.converters.openai_to_bedrock().convert_model_id(&request.model)
+            } else {
+                request.model.clone()
+            };
+            let backend_model = backend.resolve_model_id(&backend_model);
+            let converse_request =
+                build_converse_request_from_openai(state, request, extra_fields, &backend_model)?;
+            let warnings = state.converters.openai_to_bedrock().conversion_warnings(request);
+            (backend_model, estimate_tokens(&converse_request), warnings)
+        }
+        None => {
+            let (gemini_model, gemini_request) = state
+                .converters
+                .openai_to_gemini()
+                .convert_request(request)
+                .map_err(|e| {
+                    OpenAIApiError::bad_request(format!("Request conversion error: {}", e))
+                })?;
+            (gemini_model, dry_run::estimate_json_tokens(&gemini_request), Vec::new())
+        }
+    };
+
+    Ok(DryRunReport::new(request_id, target, &request.model, backend_model, estimated_input_tokens)
+        .with_max_output_tokens(request.max_tokens.or(request.max_completion_tokens))
+        .with_stream(request.stream)
+        .with_warnings(warnings))
+}
+
+/// Whether the client asked for a final usage chunk
+fn include_stream_usage(request: &ChatCompletionRequest) -> bool {
+    request
+        .stream_options
+        .as_ref()
+        .map(|o| o.include_usage)
+        .unwrap_or(false)
+}
+
+/// Handle request using a Converse backend (Bedrock or an adapted provider)
+async fn handle_backend_request(
+    state: &AppState,
+    backend: &dyn Backend,
+    request: &ChatCompletionRequest,
+    extra_fields: Option<&serde_json::Value>,
+    request_id: &str,
+    start_time: Instant,
+    progress: StreamProgress,
+) -> Result<ChatCompletionApiResponse, OpenAIApiError> {
+    // OpenAI model aliases (gpt-4o -> Claude) only make sense on Bedrock
+    let backend_model = if backend.name() == "bedrock" {
+        state.converters.openai_to_bedrock().convert_model_id(&request.model)
+    } else {
+        request.model.clone()
+    };
+
+    // Apply settings overrides if available
+    let backend_model = backend.resolve_model_id(&backend_model);
+
+    // Provisioned throughput or on-demand, per the service_tier hint
+    let priority = progress.priority();
+    let requested_tier = match RequestedTier::from_openai(request.service_tier.as_deref())
+        .map_err(OpenAIApiError::bad_request)?
+    {
+        _ if !priority.allows_provisioned() => RequestedTier::Standard,
+        requested => requested,
+    };
+    let TierDecision { tier, model_id: bedrock_model } = if backend.capabilities().service_tiers {
+        select_tier(
+            requested_tier,
+            &state.settings.provisioned_throughput,
+            &request.model,
+            &backend_model,
+        )
+    } else {
+        TierDecision { tier: EffectiveTier::OnDemand, model_id: backend_model }
+    };
+
+    tracing::debug!(
+        request_id = %request_id,
+        backend = backend.name(),
+        bedrock_model = %bedrock_model,
+        service_tier = tier.openai_name(),
+        "Routing to Converse backend"
+    );
+
+    // Reported usage is reconciled against the backend's own metrics
+    let usage_entry = state.usage_ledger.entry(backend, &bedrock_model);
+
     // Build Converse request
-    let converse_request = build_converse_request_from_openai(&state, &request, &bedrock_model)?;
+    let converse_request =
+        build_converse_request_from_openai(state, request, extra_fields, &bedrock_model)?;
+
+    // Smooth bursts on provisioned capacity instead of drawing throttles
+    let reserved = if tier == EffectiveTier::Provisioned && state.pt_governor.is_limited(&bedrock_model) {
+        let tokens = pt_governor::reservation(&converse_request);
+        let waited = state.pt_governor.reserve(&bedrock_model, tokens, priority).await.map_err(|wait| {
+            OpenAIApiError::rate_limited(format!(
+                "Provisioned throughput for model '{}' is at capacity; retry in {}s",
+                request.model,
+                wait.as_secs().max(1)
+            ))
+        })?;
+        Some((tokens, waited))
+    } else {
+        None
+    };
+    state.priority_metrics.record(priority, tier, reserved.map_or(Duration::ZERO, |(_, waited)| waited));
+    let reserved = reserved.map(|(tokens, _)| tokens);
 
     // Handle streaming vs non-streaming
     if request.stream {
-        let include_usage = request
-            .stream_options
-            .as_ref()
-            .map(|o| o.include_usage)
-            .unwrap_or(false);
-
         let sse_stream = create_openai_streaming_response(
-            &state,
+            backend,
             converse_request,
-            &request_id,
+            request_id,
             &request.model,
-            include_usage,
+            include_stream_usage(request),
+            tier.openai_name(),
+            progress.with_usage_entry(usage_entry),
         )
         .await?;
-
+        state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
         return Ok(ChatCompletionApiResponse::Stream(sse_stream));
     }
 
     // Non-streaming response
-    let converse_output = state
-        .bedrock
-        .converse(converse_request)
-        .await
+    // Configured models and large max_tokens stream upstream even here
+    let force_stream = state.settings.stream_assembly.applies_to(
+        &request.model,
+        &bedrock_model,
+        request.max_tokens.or(request.max_completion_tokens),
+    );
+    let converse_output = if force_stream {
+        stream_assembly::converse_assembled(backend, converse_request).await
+    } else {
+        backend.converse(converse_request).await
+    };
+    let converse_output = converse_output
         .map_err(|e| {
-            tracing::error!(error = %e, "Bedrock Converse API call failed");
+            tracing::error!(error = %e, backend = backend.name(), "Converse call failed");
+            if let Some(reserved) = reserved {
+                state.pt_governor.refund(&bedrock_model, reserved);
+            }
             OpenAIApiError::from_bedrock_error(&e)
         })?;
+    state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
 
     // Convert response to OpenAI format
-    let response = convert_converse_to_openai(converse_output, &request.model)?;
+    let mut response = convert_converse_to_openai(converse_output, &request.model, progress.generation_id())?;
+    response.service_tier = Some(tier.openai_name().to_string());
+    response.system_fingerprint = Some(system_fingerprint(
+        backend.name(),
+        &bedrock_model,
+        backend.region().as_deref(),
+    ));
+
+    if let Some(reserved) = reserved {
+        let used = (response.usage.prompt_tokens + response.usage.completion_tokens).max(0) as u64;
+        state.pt_governor.refund(&bedrock_model, reserved.saturating_sub(used));
+    }
+    if let Some(entry) = usage_entry {
+        entry.record(response.usage.prompt_tokens, response.usage.completion_tokens);
+    }
 
     let duration_ms = start_time.elapsed().as_millis();
 
@@ -218,324 +729,373 @@ pub async fn chat_completions(
     Ok(ChatCompletionApiResponse::Json(Json(response)))
 }
 
-// ============================================================================
-// Request Building
-// ============================================================================
-
-/// Build a Converse request from OpenAI ChatCompletionRequest
-fn build_converse_request_from_openai(
-    _state: &AppState,
+/// Handle request using Gemini backend
+#[cfg(feature = "gemini")]
+async fn handle_gemini_request(
+    state: &AppState,
     request: &ChatCompletionRequest,
-    bedrock_model: &str,
-) -> Result<ConverseRequest, OpenAIApiError> {
-    // Convert messages
-    let (system_messages, chat_messages): (Vec<_>, Vec<_>) = request
-        .messages
-        .iter()
-        .partition(|m| m.role == ChatRole::System);
-
-    let sdk_messages = convert_openai_messages_to_sdk(&chat_messages)?;
-
-    // Build inference config
-    let max_tokens = request
-        .max_completion_tokens
-        .or(request.max_tokens)
-        .unwrap_or(4096);
-
-    let mut inference_config = InferenceConfiguration::builder().max_tokens(max_tokens);
+    request_id: &str,
+    start_time: Instant,
+    progress: StreamProgress,
+) -> Result<ChatCompletionApiResponse, OpenAIApiError> {
+    let gemini_service = state
+        .gemini_service
+        .as_ref()
+        .ok_or_else(|| OpenAIApiError::internal_error("Gemini service not available"))?;
+
+    // Convert OpenAI request to Gemini format
+    let (gemini_model, gemini_request) = state
+        .converters
+        .openai_to_gemini()
+        .convert_request(request)
+        .map_err(|e| OpenAIApiError::bad_request(format!("Request conversion error: {}", e)))?;
+
+    tracing::debug!(
+        request_id = %request_id,
+        gemini_model = %gemini_model,
+        "Routing to Gemini backend"
+    );
 
-    if let Some(temp) = request.temperature {
-        // Clamp temperature to 0-1 range for Bedrock
-        inference_config = inference_config.temperature(temp.min(1.0).max(0.0));
-    }
-    if let Some(top_p) = request.top_p {
-        inference_config = inference_config.top_p(top_p);
-    }
-    if let Some(ref stop) = request.stop {
-        inference_config = inference_config.set_stop_sequences(Some(stop.to_vec()));
+    // Handle streaming vs non-streaming
+    if request.stream {
+        let (sse_stream, routing) = create_gemini_streaming_response(
+            gemini_service.clone(),
+            &gemini_model,
+            gemini_request,
+            request_id,
+            &request.model,
+            include_stream_usage(request),
+            progress,
+        )
+        .await?;
+        state.routing_metrics.record(&routing);
+        return Ok(ChatCompletionApiResponse::Stream(sse_stream));
     }
 
-    let mut converse_req = ConverseRequest::new(bedrock_model.to_string())
-        .with_messages(sdk_messages)
-        .with_inference_config(inference_config.build());
+    // Non-streaming response
+    let (gemini_response, routing) = gemini_service
+        .generate_content(&gemini_model, &gemini_request)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Gemini API call failed");
+            OpenAIApiError::internal_error(format!("Gemini API error: {}", e))
+        })?;
+    state.routing_metrics.record(&routing);
 
-    // Convert system messages
-    if !system_messages.is_empty() {
-        let system_blocks: Vec<SystemContentBlock> = system_messages
-            .iter()
-            .filter_map(|m| {
-                m.content.as_ref().map(|c| {
-                    SystemContentBlock::Text(c.to_string_content())
-                })
-            })
-            .collect();
+    // Convert Gemini response to OpenAI format
+    let mut response = GeminiToOpenAIConverter::new()
+        .convert_response(&gemini_response, &request.model)
+        .map_err(|e| OpenAIApiError::internal_error(format!("Response conversion error: {}", e)))?;
+    response.system_fingerprint = Some(system_fingerprint("gemini", &gemini_model, None));
 
-        if !system_blocks.is_empty() {
-            converse_req = converse_req.with_system(system_blocks);
-        }
-    }
+    let duration_ms = start_time.elapsed().as_millis();
 
-    // Convert tools
-    if let Some(ref tools) = request.tools {
-        if !tools.is_empty() {
-            let tool_config = convert_openai_tools_to_sdk(tools)?;
-            converse_req = converse_req.with_tool_config(tool_config);
-        }
-    }
+    tracing::info!(
+        request_id = %request_id,
+        model = %response.model,
+        gemini_model = %gemini_model,
+        prompt_tokens = response.usage.prompt_tokens,
+        completion_tokens = response.usage.completion_tokens,
+        finish_reason = ?response.choices.first().and_then(|c| c.finish_reason.as_ref()),
+        attempts = routing.attempts,
+        added_latency_ms = routing.added_latency_ms,
+        duration_ms = duration_ms,
+        "Gemini chat completion request completed"
+    );
 
-    Ok(converse_req)
+    Ok(ChatCompletionApiResponse::Json(Json(response)))
 }
 
-/// Convert OpenAI messages to SDK messages
-fn convert_openai_messages_to_sdk(
-    messages: &[&crate::schemas::openai::ChatMessage],
-) -> Result<Vec<SdkMessage>, OpenAIApiError> {
-    let mut sdk_messages = Vec::new();
-
-    for msg in messages {
-        let role = match msg.role {
-            ChatRole::User => ConversationRole::User,
-            ChatRole::Assistant => ConversationRole::Assistant,
-            ChatRole::Tool => ConversationRole::User, // Tool results come as user messages
-            ChatRole::System => continue, // Skip system messages (handled separately)
-        };
+/// Builds without the `gemini` feature have no Gemini client; requests
+/// routed to Gemini (by the routing table or `x-llm-backend`) are rejected
+#[cfg(not(feature = "gemini"))]
+async fn handle_gemini_request(
+    _state: &AppState,
+    _request: &ChatCompletionRequest,
+    _request_id: &str,
+    _start_time: Instant,
+    _progress: StreamProgress,
+) -> Result<ChatCompletionApiResponse, OpenAIApiError> {
+    Err(OpenAIApiError::bad_request(
+        "The Gemini backend is not available: this build lacks the `gemini` feature",
+    ))
+}
 
-        let content_blocks = convert_openai_content_to_sdk(msg)?;
+/// Transcribe `input_audio` parts in place, returning the transcriptions
+///
+/// Each audio part is replaced by a text part holding its transcription.
+/// Transcriptions are echoed in `audio_transcriptions` on non-streaming
+/// responses.
+async fn transcribe_audio_parts(
+    state: &AppState,
+    request: &mut ChatCompletionRequest,
+) -> Result<Vec<AudioTranscription>, OpenAIApiError> {
+    use crate::schemas::openai::{ContentPart, MessageContent};
 
-        if content_blocks.is_empty() {
+    let mut transcriptions = Vec::new();
+    for (message_index, message) in request.messages.iter_mut().enumerate() {
+        let Some(MessageContent::Parts(parts)) = message.content.as_mut() else {
             continue;
+        };
+        for (part_index, part) in parts.iter_mut().enumerate() {
+            let ContentPart::InputAudio { input_audio } = part else {
+                continue;
+            };
+            let transcription = state.transcription.as_ref().ok_or_else(|| {
+                OpenAIApiError::bad_request(
+                    "Audio input requires a transcription backend, which is not configured",
+                )
+            })?;
+            let text = transcription.transcribe(input_audio).await.map_err(|e| {
+                if e.is_client_error() {
+                    OpenAIApiError::bad_request(format!(
+                        "messages[{}].content[{}]: {}",
+                        message_index, part_index, e
+                    ))
+                } else {
+                    tracing::error!(error = %e, "Audio transcription failed");
+                    OpenAIApiError::service_unavailable(e.to_string())
+                }
+            })?;
+            *part = ContentPart::Text { text: text.clone() };
+            transcriptions.push(AudioTranscription {
+                message_index,
+                part_index,
+                text,
+            });
         }
-
-        let sdk_msg = SdkMessage::builder()
-            .role(role)
-            .set_content(Some(content_blocks))
-            .build()
-            .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build message: {}", e)))?;
-
-        sdk_messages.push(sdk_msg);
     }
-
-    Ok(sdk_messages)
+    Ok(transcriptions)
 }
 
-/// Convert OpenAI message content to SDK content blocks
-fn convert_openai_content_to_sdk(
-    msg: &crate::schemas::openai::ChatMessage,
-) -> Result<Vec<SdkContentBlock>, OpenAIApiError> {
-    use crate::schemas::openai::{ContentPart, MessageContent};
-
-    // Handle tool role (tool results)
-    if msg.role == ChatRole::Tool {
-        let tool_use_id = msg.tool_call_id.as_ref().ok_or_else(|| {
-            OpenAIApiError::bad_request("Tool message missing tool_call_id")
-        })?;
-
-        let content_text = msg
-            .content
-            .as_ref()
-            .map(|c| c.to_string_content())
-            .unwrap_or_default();
+// ============================================================================
+// Stored Completions
+// ============================================================================
 
-        use aws_sdk_bedrockruntime::types::ToolResultBlock;
+/// Default page size for stored completion listings
+const DEFAULT_LIST_LIMIT: usize = 20;
 
-        let tool_result = ToolResultBlock::builder()
-            .tool_use_id(tool_use_id)
-            .set_content(Some(vec![ToolResultContentBlock::Text(content_text)]))
-            .status(ToolResultStatus::Success)
-            .build()
-            .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build tool result: {}", e)))?;
+/// Largest page size for stored completion listings
+const MAX_LIST_LIMIT: usize = 100;
 
-        return Ok(vec![SdkContentBlock::ToolResult(tool_result)]);
-    }
+/// GET /v1/chat/completions - List stored completions
+///
+/// Supports `model`, `metadata[key]=value` filters, `after`, `limit` and
+/// `order` (`asc` or `desc`).
+pub async fn list_stored_completions(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, OpenAIApiError> {
+    let metadata: HashMap<String, String> = params
+        .iter()
+        .filter_map(|(key, value)| {
+            let key = key.strip_prefix("metadata[")?.strip_suffix(']')?;
+            Some((key.to_string(), value.clone()))
+        })
+        .collect();
+
+    let items = state
+        .completion_store
+        .list(
+            completion_owner(&key_info),
+            params.get("model").map(String::as_str),
+            &metadata,
+        )
+        .iter()
+        .map(|c| (c.id().to_string(), stored_completion_json(c)))
+        .collect();
 
-    // Handle assistant messages with tool calls
-    if msg.role == ChatRole::Assistant {
-        if let Some(ref tool_calls) = msg.tool_calls {
-            let mut blocks = Vec::new();
+    list_page(items, &params).map(Json)
+}
 
-            // Add text content if present
-            if let Some(ref content) = msg.content {
-                let text = content.to_string_content();
-                if !text.is_empty() {
-                    blocks.push(SdkContentBlock::Text(text));
-                }
-            }
+/// GET /v1/chat/completions/{completion_id} - Retrieve a stored completion
+pub async fn get_stored_completion(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(completion_id): Path<String>,
+) -> Result<Json<Value>, OpenAIApiError> {
+    let completion = find_stored_completion(&state, &key_info, &completion_id)?;
+    Ok(Json(stored_completion_json(&completion)))
+}
 
-            // Add tool use blocks
-            for tool_call in tool_calls {
-                let input: serde_json::Value = serde_json::from_str(&tool_call.function.arguments)
-                    .unwrap_or_else(|_| serde_json::json!({}));
+/// GET /v1/chat/completions/{completion_id}/messages - Request messages of a stored completion
+pub async fn get_stored_completion_messages(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(completion_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, OpenAIApiError> {
+    let completion = find_stored_completion(&state, &key_info, &completion_id)?;
 
-                let tool_use = ToolUseBlock::builder()
-                    .tool_use_id(&tool_call.id)
-                    .name(&tool_call.function.name)
-                    .input(json_to_document(&input))
-                    .build()
-                    .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build tool use: {}", e)))?;
+    let items = completion
+        .messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let id = format!("{}-{}", completion_id, index);
+            let mut value = serde_json::to_value(message).unwrap_or_default();
+            value["id"] = Value::String(id.clone());
+            (id, value)
+        })
+        .collect();
 
-                blocks.push(SdkContentBlock::ToolUse(tool_use));
-            }
+    list_page(items, &params).map(Json)
+}
 
-            return Ok(blocks);
-        }
+/// DELETE /v1/chat/completions/{completion_id} - Delete a stored completion
+pub async fn delete_stored_completion(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(completion_id): Path<String>,
+) -> Result<Json<Value>, OpenAIApiError> {
+    if !state
+        .completion_store
+        .delete(&completion_id, completion_owner(&key_info))
+    {
+        return Err(completion_not_found(&completion_id));
     }
 
-    // Handle regular content
-    match &msg.content {
-        Some(MessageContent::Text(text)) => Ok(vec![SdkContentBlock::Text(text.clone())]),
-        Some(MessageContent::Parts(parts)) => {
-            let mut blocks = Vec::new();
-            for part in parts {
-                match part {
-                    ContentPart::Text { text } => {
-                        blocks.push(SdkContentBlock::Text(text.clone()));
-                    }
-                    ContentPart::ImageUrl { image_url } => {
-                        // Parse data URL
-                        if image_url.url.starts_with("data:") {
-                            let image_block = parse_data_url_to_image(&image_url.url)?;
-                            blocks.push(image_block);
-                        } else {
-                            return Err(OpenAIApiError::bad_request(
-                                "External image URLs are not supported. Use base64 data URLs.",
-                            ));
-                        }
-                    }
-                }
-            }
-            Ok(blocks)
-        }
-        None => Ok(vec![]),
-    }
+    Ok(Json(json!({
+        "object": "chat.completion.deleted",
+        "id": completion_id,
+        "deleted": true,
+    })))
 }
 
-/// Parse a data URL and convert to SDK ImageBlock
-fn parse_data_url_to_image(url: &str) -> Result<SdkContentBlock, OpenAIApiError> {
-    use aws_sdk_bedrockruntime::types::{ImageBlock, ImageFormat, ImageSource};
-    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-
-    let parts: Vec<&str> = url.splitn(2, ',').collect();
-    if parts.len() != 2 {
-        return Err(OpenAIApiError::bad_request("Invalid data URL format"));
+/// POST /v1/chat/completions/{completion_id}/cancel - Stop an in-flight streamed completion
+///
+/// The stream ends with a `cancelled` finish reason, the usage chunk (if
+/// requested) and `[DONE]`.
+pub async fn cancel_chat_completion(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(completion_id): Path<String>,
+) -> Result<Json<Value>, OpenAIApiError> {
+    if !state
+        .generations
+        .cancel(&completion_id, completion_owner(&key_info))
+    {
+        return Err(OpenAIApiError::not_found(format!(
+            "No in-flight chat completion found with id '{}'",
+            completion_id
+        )));
     }
 
-    let metadata = parts[0];
-    let data = parts[1];
+    tracing::info!(completion_id = %completion_id, user_id = %key_info.user_id, "Chat completion cancellation requested");
 
-    // Extract media type
-    let media_type = metadata
-        .strip_prefix("data:")
-        .and_then(|s| s.split(';').next())
-        .ok_or_else(|| OpenAIApiError::bad_request("Could not parse media type"))?;
+    Ok(Json(json!({
+        "object": "chat.completion.cancelled",
+        "id": completion_id,
+        "cancelled": true,
+    })))
+}
 
-    let format = match media_type {
-        "image/png" => ImageFormat::Png,
-        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
-        "image/gif" => ImageFormat::Gif,
-        "image/webp" => ImageFormat::Webp,
-        _ => ImageFormat::Png,
-    };
+/// Owner filter for stored completions and cancellation (the master key sees all)
+fn completion_owner(key_info: &ApiKeyInfo) -> Option<&str> {
+    (!key_info.is_master).then_some(key_info.user_id.as_str())
+}
 
-    let bytes = BASE64
-        .decode(data)
-        .map_err(|e| OpenAIApiError::bad_request(format!("Invalid base64: {}", e)))?;
+fn find_stored_completion(
+    state: &AppState,
+    key_info: &ApiKeyInfo,
+    completion_id: &str,
+) -> Result<StoredCompletion, OpenAIApiError> {
+    state
+        .completion_store
+        .get(completion_id, completion_owner(key_info))
+        .ok_or_else(|| completion_not_found(completion_id))
+}
 
-    let image = ImageBlock::builder()
-        .format(format)
-        .source(ImageSource::Bytes(aws_sdk_bedrockruntime::primitives::Blob::new(bytes)))
-        .build()
-        .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build image: {}", e)))?;
+fn completion_not_found(completion_id: &str) -> OpenAIApiError {
+    OpenAIApiError::not_found(format!("No chat completion found with id '{}'", completion_id))
+}
 
-    Ok(SdkContentBlock::Image(image))
+/// Stored completion as returned by the retrieve and list endpoints
+fn stored_completion_json(completion: &StoredCompletion) -> Value {
+    let mut value = serde_json::to_value(&completion.response).unwrap_or_default();
+    value["metadata"] = json!(completion.metadata);
+    value
 }
 
-/// Convert OpenAI tools to SDK ToolConfiguration
-fn convert_openai_tools_to_sdk(
-    tools: &[crate::schemas::openai::Tool],
-) -> Result<ToolConfiguration, OpenAIApiError> {
-    let mut sdk_tools = Vec::new();
+/// Paginate `(id, item)` pairs given oldest first into an OpenAI list object
+fn list_page(
+    mut items: Vec<(String, Value)>,
+    params: &HashMap<String, String>,
+) -> Result<Value, OpenAIApiError> {
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse::<usize>()
+            .ok()
+            .filter(|l| (1..=MAX_LIST_LIMIT).contains(l))
+            .ok_or_else(|| {
+                OpenAIApiError::bad_request(format!(
+                    "limit must be between 1 and {}",
+                    MAX_LIST_LIMIT
+                ))
+            })?,
+        None => DEFAULT_LIST_LIMIT,
+    };
 
-    for tool in tools {
-        if tool.tool_type != "function" {
-            continue;
+    match params.get("order").map(String::as_str) {
+        None | Some("asc") => {}
+        Some("desc") => items.reverse(),
+        Some(other) => {
+            return Err(OpenAIApiError::bad_request(format!(
+                "Invalid order '{}': expected 'asc' or 'desc'",
+                other
+            )))
         }
+    }
 
-        let input_schema = tool
-            .function
-            .parameters
-            .clone()
-            .unwrap_or(serde_json::json!({"type": "object", "properties": {}}));
-
-        let tool_spec = ToolSpecification::builder()
-            .name(&tool.function.name)
-            .description(tool.function.description.as_deref().unwrap_or(""))
-            .input_schema(SdkToolInputSchema::Json(json_to_document(&input_schema)))
-            .build()
-            .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build tool spec: {}", e)))?;
-
-        sdk_tools.push(SdkTool::ToolSpec(tool_spec));
+    if let Some(after) = params.get("after") {
+        let position = items.iter().position(|(id, _)| id == after);
+        items.drain(..position.map_or(0, |p| p + 1));
     }
 
-    Ok(ToolConfiguration::builder()
-        .set_tools(Some(sdk_tools))
-        .build()
-        .map_err(|e| OpenAIApiError::bad_request(format!("Failed to build tool config: {}", e)))?)
-}
+    let has_more = items.len() > limit;
+    items.truncate(limit);
 
-/// Convert serde_json::Value to aws_smithy_types::Document
-fn json_to_document(value: &serde_json::Value) -> aws_smithy_types::Document {
-    match value {
-        serde_json::Value::Null => aws_smithy_types::Document::Null,
-        serde_json::Value::Bool(b) => aws_smithy_types::Document::Bool(*b),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                if i >= 0 {
-                    aws_smithy_types::Document::Number(aws_smithy_types::Number::PosInt(i as u64))
-                } else {
-                    aws_smithy_types::Document::Number(aws_smithy_types::Number::NegInt(i))
-                }
-            } else if let Some(f) = n.as_f64() {
-                aws_smithy_types::Document::Number(aws_smithy_types::Number::Float(f))
-            } else {
-                aws_smithy_types::Document::Null
-            }
-        }
-        serde_json::Value::String(s) => aws_smithy_types::Document::String(s.clone()),
-        serde_json::Value::Array(arr) => {
-            aws_smithy_types::Document::Array(arr.iter().map(json_to_document).collect())
-        }
-        serde_json::Value::Object(obj) => {
-            let map: std::collections::HashMap<String, aws_smithy_types::Document> = obj
-                .iter()
-                .map(|(k, v)| (k.clone(), json_to_document(v)))
-                .collect();
-            aws_smithy_types::Document::Object(map)
-        }
-    }
+    Ok(json!({
+        "object": "list",
+        "first_id": items.first().map(|(id, _)| id.clone()),
+        "last_id": items.last().map(|(id, _)| id.clone()),
+        "has_more": has_more,
+        "data": items.into_iter().map(|(_, item)| item).collect::<Vec<_>>(),
+    }))
 }
 
-/// Convert aws_smithy_types::Document to serde_json::Value
-fn document_to_json(doc: &aws_smithy_types::Document) -> serde_json::Value {
-    match doc {
-        aws_smithy_types::Document::Null => serde_json::Value::Null,
-        aws_smithy_types::Document::Bool(b) => serde_json::Value::Bool(*b),
-        aws_smithy_types::Document::Number(n) => match n {
-            aws_smithy_types::Number::PosInt(i) => serde_json::json!(*i),
-            aws_smithy_types::Number::NegInt(i) => serde_json::json!(*i),
-            aws_smithy_types::Number::Float(f) => serde_json::json!(*f),
-        },
-        aws_smithy_types::Document::String(s) => serde_json::Value::String(s.clone()),
-        aws_smithy_types::Document::Array(arr) => {
-            serde_json::Value::Array(arr.iter().map(document_to_json).collect())
-        }
-        aws_smithy_types::Document::Object(obj) => {
-            let map: serde_json::Map<String, serde_json::Value> = obj
-                .iter()
-                .map(|(k, v)| (k.clone(), document_to_json(v)))
-                .collect();
-            serde_json::Value::Object(map)
-        }
+// ============================================================================
+// Request Building
+// ============================================================================
+
+/// Build a Converse request from OpenAI ChatCompletionRequest
+///
+/// The request goes through the OpenAI converter and the shared SDK
+/// materialization, like the Anthropic path in `api::messages`.
+fn build_converse_request_from_openai(
+    state: &AppState,
+    request: &ChatCompletionRequest,
+    extra_fields: Option<&serde_json::Value>,
+    bedrock_model: &str,
+) -> Result<ConverseRequest, OpenAIApiError> {
+    let mut bedrock_request = state
+        .converters
+        .openai_to_bedrock()
+        .convert_request(request)
+        .map_err(|e| OpenAIApiError::from_conversion_error(&e))?;
+    bedrock_request.model_id = bedrock_model.to_string();
+
+    // Client-supplied fields are merged over the converted ones
+    if let Some(extra) = extra_fields {
+        let fields = bedrock_request
+            .additional_model_request_fields
+            .get_or_insert_with(|| serde_json::json!({}));
+        extra_fields::merge(fields, extra);
     }
+
+    bedrock_sdk::to_converse_request(bedrock_request)
+        .map_err(|e| OpenAIApiError::bad_request(e.to_string()))
 }
 
 // ============================================================================
@@ -543,16 +1103,21 @@ fn document_to_json(doc: &aws_smithy_types::Document) -> serde_json::Value {
 // ============================================================================
 
 /// Convert Converse response to OpenAI ChatCompletionResponse
+///
+/// The response takes the request's generation ID when it was registered as
+/// cancellable, and a fresh completion ID otherwise.
 fn convert_converse_to_openai(
     output: aws_sdk_bedrockruntime::operation::converse::ConverseOutput,
     original_model: &str,
+    generation_id: Option<&str>,
 ) -> Result<ChatCompletionResponse, OpenAIApiError> {
-    let completion_id = generate_completion_id();
+    let completion_id = generation_id.map_or_else(generate_completion_id, str::to_string);
     let created = current_timestamp();
 
     // Convert content blocks
     let mut text_parts = Vec::new();
     let mut tool_calls = Vec::new();
+    let mut images = Vec::new();
 
     if let Some(output_content) = output.output() {
         if let aws_sdk_bedrockruntime::types::ConverseOutput::Message(msg) = output_content {
@@ -573,6 +1138,14 @@ fn convert_converse_to_openai(
                             },
                         });
                     }
+                    SdkContentBlock::Image(image) => {
+                        if let Some(ImageSource::Bytes(bytes)) = image.source() {
+                            images.push(ContentPart::image_base64(
+                                &format!("image/{}", image.format().as_str()),
+                                &BASE64.encode(bytes.as_ref()),
+                            ));
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -618,12 +1191,15 @@ fn convert_converse_to_openai(
                 role: ChatRole::Assistant,
                 content: if content.is_empty() { None } else { Some(content) },
                 tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                images: if images.is_empty() { None } else { Some(images) },
             },
             finish_reason: Some(finish_reason),
             logprobs: None,
         }],
         usage,
         system_fingerprint: None,
+        service_tier: None,
+        audio_transcriptions: None,
     })
 }
 
@@ -631,22 +1207,40 @@ fn convert_converse_to_openai(
 // Streaming Response Handler
 // ============================================================================
 
+/// Frame carrying one tool call delta
+fn tool_call_frame(
+    sse: &mut SseEncoder,
+    completion_id: &str,
+    created: i64,
+    model_id: &str,
+    delta: &ToolCallDelta,
+) -> Bytes {
+    sse.data(&ChunkRef::new(completion_id, created, model_id, &[
+        ChunkChoiceRef::delta(ChunkDeltaRef {
+            tool_calls: Some([delta.to_delta_ref()]),
+            ..Default::default()
+        }),
+    ]))
+}
+
 /// Create a streaming response using SSE with OpenAI format
 async fn create_openai_streaming_response(
-    state: &AppState,
+    backend: &dyn Backend,
     request: ConverseRequest,
     request_id: &str,
     original_model: &str,
     include_usage: bool,
-) -> Result<Sse<std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>, OpenAIApiError>
-{
-    // Get streaming response from Bedrock
-    let mut stream_response = state
-        .bedrock
+    service_tier: &'static str,
+    mut progress: StreamProgress,
+) -> Result<SseResponse, OpenAIApiError> {
+    let fingerprint = system_fingerprint(backend.name(), &request.model_id, backend.region().as_deref());
+
+    // Get streaming response from the backend
+    let mut stream_response = backend
         .converse_stream(request)
         .await
         .map_err(|e| {
-            tracing::error!(error = %e, "Bedrock ConverseStream API call failed");
+            tracing::error!(error = %e, backend = backend.name(), "ConverseStream call failed");
             OpenAIApiError::from_bedrock_error(&e)
         })?;
 
@@ -657,8 +1251,8 @@ async fn create_openai_streaming_response(
 
     // Create the SSE stream
     let stream = async_stream::stream! {
-        let mut tool_call_index: i32 = 0;
-        let mut block_to_tool_index: std::collections::HashMap<i32, i32> = std::collections::HashMap::new();
+        let mut sse = SseEncoder::new();
+        let mut tool_calls = ToolCallSequencer::new();
         let mut total_input_tokens: i32 = 0;
         let mut total_output_tokens: i32 = 0;
         let mut sent_role = false;
@@ -667,74 +1261,95 @@ async fn create_openai_streaming_response(
 
         // Process Bedrock ConverseStream events
         loop {
-            match stream_response.recv().await {
+            if progress.output_ceiling_reached() {
+                // Runaway generation: end as if the length limit was hit
+                yield sse.data(&ChunkRef {
+                    service_tier: Some(service_tier),
+                    system_fingerprint: Some(&fingerprint),
+                    ..ChunkRef::new(&completion_id, created, &model_id, &[
+                        ChunkChoiceRef::finish(OUTPUT_LIMIT_FINISH_REASON),
+                    ])
+                });
+                total_output_tokens = progress.output_tokens();
+                if include_usage {
+                    let usage = CompletionUsage {
+                        prompt_tokens: total_input_tokens,
+                        completion_tokens: total_output_tokens,
+                        total_tokens: total_input_tokens + total_output_tokens,
+                        completion_tokens_details: None,
+                    };
+                    yield sse.data(&ChunkRef {
+                        usage: Some(&usage),
+                        ..ChunkRef::new(&completion_id, created, &model_id, &[])
+                    });
+                }
+                yield sse.raw_data("[DONE]");
+                if let Some(tap) = progress.tap() {
+                    tap.complete(StatusCode::OK.as_u16(), total_input_tokens, Some(total_output_tokens));
+                }
+                break;
+            }
+            let received = tokio::select! {
+                received = stream_response.recv() => Some(received),
+                _ = progress.cancelled() => None,
+            };
+            let Some(received) = received else {
+                // Stopped through the cancel endpoint: end with the output so far
+                tracing::info!(
+                    request_id = %req_id,
+                    completion_id = %completion_id,
+                    output_tokens = progress.output_tokens(),
+                    "OpenAI stream cancelled"
+                );
+                yield sse.data(&ChunkRef {
+                    service_tier: Some(service_tier),
+                    system_fingerprint: Some(&fingerprint),
+                    ..ChunkRef::new(&completion_id, created, &model_id, &[
+                        ChunkChoiceRef::finish(CANCELLED_STOP_REASON),
+                    ])
+                });
+                total_output_tokens = progress.output_tokens();
+                if include_usage {
+                    let usage = CompletionUsage {
+                        prompt_tokens: total_input_tokens,
+                        completion_tokens: total_output_tokens,
+                        total_tokens: total_input_tokens + total_output_tokens,
+                        completion_tokens_details: None,
+                    };
+                    yield sse.data(&ChunkRef {
+                        usage: Some(&usage),
+                        ..ChunkRef::new(&completion_id, created, &model_id, &[])
+                    });
+                }
+                yield sse.raw_data("[DONE]");
+                if let Some(tap) = progress.tap() {
+                    tap.cancel(total_input_tokens, total_output_tokens);
+                }
+                break;
+            };
+            match received {
                 Ok(Some(event)) => {
                     match event {
                         ConverseStreamOutput::MessageStart(_) => {
                             // Send initial chunk with role
                             if !sent_role {
                                 sent_role = true;
-                                let chunk = ChatCompletionChunk {
-                                    id: completion_id.clone(),
-                                    object: "chat.completion.chunk".to_string(),
-                                    created,
-                                    model: model_id.clone(),
-                                    choices: vec![ChunkChoice {
-                                        index: 0,
-                                        delta: ChunkDelta {
-                                            role: Some(ChatRole::Assistant),
-                                            content: None,
-                                            tool_calls: None,
-                                        },
-                                        finish_reason: None,
-                                        logprobs: None,
-                                    }],
-                                    system_fingerprint: None,
-                                    usage: None,
-                                };
-                                let json = serde_json::to_string(&chunk).unwrap_or_default();
-                                yield Ok(Event::default().data(json));
+                                yield sse.data(&ChunkRef::new(&completion_id, created, &model_id, &[
+                                    ChunkChoiceRef::delta(ChunkDeltaRef {
+                                        role: Some(ChatRole::Assistant),
+                                        content: progress.quirks().empty_role_content.then_some(""),
+                                        ..Default::default()
+                                    }),
+                                ]));
                             }
                         }
 
                         ConverseStreamOutput::ContentBlockStart(block_start) => {
                             let block_index = block_start.content_block_index();
 
-                            if let Some(start) = block_start.start() {
-                                if let aws_sdk_bedrockruntime::types::ContentBlockStart::ToolUse(tool_start) = start {
-                                    // Assign tool call index
-                                    block_to_tool_index.insert(block_index, tool_call_index);
-
-                                    let chunk = ChatCompletionChunk {
-                                        id: completion_id.clone(),
-                                        object: "chat.completion.chunk".to_string(),
-                                        created,
-                                        model: model_id.clone(),
-                                        choices: vec![ChunkChoice {
-                                            index: 0,
-                                            delta: ChunkDelta {
-                                                role: None,
-                                                content: None,
-                                                tool_calls: Some(vec![ToolCallDelta {
-                                                    index: tool_call_index,
-                                                    id: Some(tool_start.tool_use_id().to_string()),
-                                                    tool_type: Some("function".to_string()),
-                                                    function: Some(FunctionCallDelta {
-                                                        name: Some(tool_start.name().to_string()),
-                                                        arguments: None,
-                                                    }),
-                                                }]),
-                                            },
-                                            finish_reason: None,
-                                            logprobs: None,
-                                        }],
-                                        system_fingerprint: None,
-                                        usage: None,
-                                    };
-                                    let json = serde_json::to_string(&chunk).unwrap_or_default();
-                                    yield Ok(Event::default().data(json));
-
-                                    tool_call_index += 1;
+                            if let Some(aws_sdk_bedrockruntime::types::ContentBlockStart::ToolUse(tool_start)) = block_start.start() {
+                                for delta in tool_calls.start(block_index, tool_start.tool_use_id(), tool_start.name()) {
+                                    yield tool_call_frame(&mut sse, &completion_id, created, &model_id, &delta);
                                 }
                             }
                         }
@@ -745,100 +1360,62 @@ async fn create_openai_streaming_response(
                             if let Some(delta) = block_delta.delta() {
                                 match delta {
                                     aws_sdk_bedrockruntime::types::ContentBlockDelta::Text(text) => {
-                                        let chunk = ChatCompletionChunk {
-                                            id: completion_id.clone(),
-                                            object: "chat.completion.chunk".to_string(),
-                                            created,
-                                            model: model_id.clone(),
-                                            choices: vec![ChunkChoice {
-                                                index: 0,
-                                                delta: ChunkDelta {
-                                                    role: None,
-                                                    content: Some(text.to_string()),
-                                                    tool_calls: None,
-                                                },
-                                                finish_reason: None,
-                                                logprobs: None,
-                                            }],
-                                            system_fingerprint: None,
-                                            usage: None,
-                                        };
-                                        let json = serde_json::to_string(&chunk).unwrap_or_default();
-                                        yield Ok(Event::default().data(json));
+                                        progress.record_output(text);
+                                        yield sse.data(&ChunkRef::new(&completion_id, created, &model_id, &[
+                                            ChunkChoiceRef::delta(ChunkDeltaRef {
+                                                content: Some(text.as_str()),
+                                                ..Default::default()
+                                            }),
+                                        ]));
                                     }
                                     aws_sdk_bedrockruntime::types::ContentBlockDelta::ToolUse(tool_delta) => {
-                                        let tc_index = block_to_tool_index.get(&block_index).copied().unwrap_or(0);
-
-                                        let chunk = ChatCompletionChunk {
-                                            id: completion_id.clone(),
-                                            object: "chat.completion.chunk".to_string(),
-                                            created,
-                                            model: model_id.clone(),
-                                            choices: vec![ChunkChoice {
-                                                index: 0,
-                                                delta: ChunkDelta {
-                                                    role: None,
-                                                    content: None,
-                                                    tool_calls: Some(vec![ToolCallDelta {
-                                                        index: tc_index,
-                                                        id: None,
-                                                        tool_type: None,
-                                                        function: Some(FunctionCallDelta {
-                                                            name: None,
-                                                            arguments: Some(tool_delta.input().to_string()),
-                                                        }),
-                                                    }]),
-                                                },
-                                                finish_reason: None,
-                                                logprobs: None,
-                                            }],
-                                            system_fingerprint: None,
-                                            usage: None,
-                                        };
-                                        let json = serde_json::to_string(&chunk).unwrap_or_default();
-                                        yield Ok(Event::default().data(json));
+                                        progress.record_output(tool_delta.input());
+                                        for delta in tool_calls.arguments(block_index, tool_delta.input()) {
+                                            yield tool_call_frame(&mut sse, &completion_id, created, &model_id, &delta);
+                                        }
                                     }
                                     _ => {}
                                 }
                             }
                         }
 
-                        ConverseStreamOutput::ContentBlockStop(_) => {
-                            // No action needed for OpenAI format
+                        ConverseStreamOutput::ContentBlockStop(block_stop) => {
+                            for delta in tool_calls.stop(block_stop.content_block_index()) {
+                                yield tool_call_frame(&mut sse, &completion_id, created, &model_id, &delta);
+                            }
                         }
 
                         ConverseStreamOutput::MessageStop(stop_event) => {
                             let finish_reason = match stop_event.stop_reason() {
-                                aws_sdk_bedrockruntime::types::StopReason::EndTurn => "stop".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::MaxTokens => "length".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::StopSequence => "stop".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::ToolUse => "tool_calls".to_string(),
-                                _ => "stop".to_string(),
+                                // Some models end a tool-calling turn with end_turn
+                                aws_sdk_bedrockruntime::types::StopReason::EndTurn if tool_calls.has_calls() => "tool_calls",
+                                aws_sdk_bedrockruntime::types::StopReason::EndTurn => "stop",
+                                aws_sdk_bedrockruntime::types::StopReason::MaxTokens => "length",
+                                aws_sdk_bedrockruntime::types::StopReason::StopSequence => "stop",
+                                aws_sdk_bedrockruntime::types::StopReason::ToolUse => "tool_calls",
+                                _ => "stop",
                             };
 
-                            // Send final chunk with finish_reason
-                            let chunk = ChatCompletionChunk {
-                                id: completion_id.clone(),
-                                object: "chat.completion.chunk".to_string(),
-                                created,
-                                model: model_id.clone(),
-                                choices: vec![ChunkChoice {
-                                    index: 0,
-                                    delta: ChunkDelta::default(),
-                                    finish_reason: Some(finish_reason),
-                                    logprobs: None,
-                                }],
-                                system_fingerprint: None,
-                                usage: None,
-                            };
-                            let json = serde_json::to_string(&chunk).unwrap_or_default();
-                            yield Ok(Event::default().data(json));
+                            // Held-back tool calls go out before the finish reason
+                            for delta in tool_calls.finish() {
+                                yield tool_call_frame(&mut sse, &completion_id, created, &model_id, &delta);
+                            }
+
+                            // Send final chunk with finish_reason and the effective tier
+                            yield sse.data(&ChunkRef {
+                                service_tier: Some(service_tier),
+                                system_fingerprint: Some(&fingerprint),
+                                ..ChunkRef::new(&completion_id, created, &model_id, &[
+                                    ChunkChoiceRef::finish(finish_reason),
+                                ])
+                            });
                         }
 
                         ConverseStreamOutput::Metadata(metadata_event) => {
                             if let Some(usage) = metadata_event.usage() {
                                 total_input_tokens = usage.input_tokens();
                                 total_output_tokens = usage.output_tokens();
+                                progress.set_output_tokens(total_output_tokens);
                             }
                         }
 
@@ -853,38 +1430,439 @@ async fn create_openai_streaming_response(
 
                     // Send usage chunk if requested
                     if include_usage {
-                        let usage_chunk = ChatCompletionChunk {
-                            id: completion_id.clone(),
-                            object: "chat.completion.chunk".to_string(),
-                            created,
-                            model: model_id.clone(),
-                            choices: vec![],
-                            system_fingerprint: None,
-                            usage: Some(CompletionUsage {
-                                prompt_tokens: total_input_tokens,
-                                completion_tokens: total_output_tokens,
-                                total_tokens: total_input_tokens + total_output_tokens,
-                                completion_tokens_details: None,
-                            }),
+                        let usage = CompletionUsage {
+                            prompt_tokens: total_input_tokens,
+                            completion_tokens: total_output_tokens,
+                            total_tokens: total_input_tokens + total_output_tokens,
+                            completion_tokens_details: None,
                         };
-                        let json = serde_json::to_string(&usage_chunk).unwrap_or_default();
-                        yield Ok(Event::default().data(json));
+                        yield sse.data(&ChunkRef {
+                            usage: Some(&usage),
+                            ..ChunkRef::new(&completion_id, created, &model_id, &[])
+                        });
                     }
 
                     // Send [DONE] marker
-                    yield Ok(Event::default().data("[DONE]"));
+                    yield sse.raw_data("[DONE]");
+                    if let Some(tap) = progress.tap() {
+                        tap.complete(StatusCode::OK.as_u16(), total_input_tokens, Some(total_output_tokens));
+                    }
                     break;
                 }
                 Err(e) => {
                     tracing::error!(request_id = %req_id, error = %e, "Stream error");
-                    let error_response = OpenAIErrorResponse::server_error(&e.to_string());
-                    let json = serde_json::to_string(&error_response).unwrap_or_default();
-                    yield Ok(Event::default().data(json));
+                    let (status, error_response) = match &e {
+                        BedrockStreamError::Backend(err) => {
+                            let api_error = OpenAIApiError::from_bedrock_error(err);
+                            (api_error.status, api_error.error)
+                        }
+                        other => (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            OpenAIErrorResponse::server_error(&other.to_string()),
+                        ),
+                    };
+                    if let Some(tap) = progress.tap() {
+                        tap.complete(status.as_u16(), total_input_tokens, None);
+                    }
+                    yield sse.data(&error_response);
                     break;
                 }
             }
         }
+        progress.record_usage(total_input_tokens, total_output_tokens);
+        progress.finish_eval(total_input_tokens, total_output_tokens);
     };
 
-    Ok(Sse::new(Box::pin(stream)))
+    Ok(SseResponse::new(stream))
+}
+
+/// Create a streaming response in OpenAI format from the Gemini streaming API
+///
+/// Gemini sends whole function calls rather than argument deltas, so each
+/// call is emitted as one tool call chunk carrying its full arguments.
+#[cfg(feature = "gemini")]
+async fn create_gemini_streaming_response(
+    gemini_service: std::sync::Arc<GeminiService>,
+    gemini_model: &str,
+    gemini_request: GeminiRequest,
+    request_id: &str,
+    original_model: &str,
+    include_usage: bool,
+    mut progress: StreamProgress,
+) -> Result<(SseResponse, RoutingOutcome), OpenAIApiError> {
+    let (mut stream_response, routing) = gemini_service
+        .generate_content_stream(gemini_model, &gemini_request)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Gemini stream API call failed");
+            OpenAIApiError::internal_error(format!("Gemini API error: {}", e))
+        })?;
+    let credential_name = routing.credential.clone().unwrap_or_default();
+
+    let model_id = original_model.to_string();
+    let gemini_model_id = gemini_model.to_string();
+    let fingerprint = system_fingerprint("gemini", gemini_model, None);
+    let req_id = request_id.to_string();
+    let completion_id = progress
+        .generation_id()
+        .map_or_else(generate_completion_id, str::to_string);
+    let created = current_timestamp();
+    let converter = GeminiToOpenAIConverter::new();
+
+    let stream = async_stream::stream! {
+        let mut sse = SseEncoder::new();
+        let mut tool_call_index: i32 = 0;
+        let mut tool_calls = ToolCallSequencer::new();
+        let mut total_input_tokens: i32 = 0;
+        let mut total_output_tokens: i32 = 0;
+        let mut finish_reason = "stop".to_string();
+        let mut stream_error = false;
+        let mut cancelled = false;
+
+        tracing::debug!(request_id = %req_id, "Starting Gemini OpenAI SSE stream");
+
+        yield sse.data(&ChunkRef::new(&completion_id, created, &model_id, &[
+            ChunkChoiceRef::delta(ChunkDeltaRef {
+                role: Some(ChatRole::Assistant),
+                content: progress.quirks().empty_role_content.then_some(""),
+                ..Default::default()
+            }),
+        ]));
+
+        loop {
+            if progress.output_ceiling_reached() {
+                finish_reason = OUTPUT_LIMIT_FINISH_REASON.to_string();
+                total_output_tokens = progress.output_tokens();
+                break;
+            }
+            let received = tokio::select! {
+                received = stream_response.recv() => Some(received),
+                _ = progress.cancelled() => None,
+            };
+            let Some(received) = received else {
+                // Stopped through the cancel endpoint: end with the output so far
+                tracing::info!(request_id = %req_id, completion_id = %completion_id, "Gemini stream cancelled");
+                finish_reason = CANCELLED_STOP_REASON.to_string();
+                total_output_tokens = progress.output_tokens();
+                cancelled = true;
+                break;
+            };
+            match received {
+                Ok(Some(chunk)) => {
+                    if let Some(candidate) = chunk.candidates.first() {
+                        // A chunk's log probabilities go with its first text delta
+                        let mut logprobs = candidate.logprobs_result.as_ref().map(convert_logprobs);
+                        for part in &candidate.content.parts {
+                            if let Some(ref text) = part.text {
+                                progress.record_output(text);
+                                let chunk_logprobs = logprobs.take();
+                                yield sse.data(&ChunkRef::new(&completion_id, created, &model_id, &[
+                                    ChunkChoiceRef::delta(ChunkDeltaRef {
+                                        content: Some(text.as_str()),
+                                        ..Default::default()
+                                    })
+                                    .with_logprobs(chunk_logprobs.as_ref()),
+                                ]));
+                            }
+
+                            if let Some(ref function_call) = part.function_call {
+                                let call_id = format!("call_{}", Uuid::new_v4().simple());
+                                let arguments = function_call.args.to_string();
+                                progress.record_output(&arguments);
+                                let mut deltas = tool_calls.start(tool_call_index, &call_id, &function_call.name);
+                                deltas.extend(tool_calls.arguments(tool_call_index, &arguments));
+                                deltas.extend(tool_calls.stop(tool_call_index));
+                                for delta in deltas {
+                                    yield tool_call_frame(&mut sse, &completion_id, created, &model_id, &delta);
+                                }
+                                tool_call_index += 1;
+                            }
+                        }
+
+                        if let Some(ref reason) = candidate.finish_reason {
+                            finish_reason = converter.convert_finish_reason(Some(reason));
+                        }
+                    }
+
+                    if let Some(usage) = chunk.usage_metadata {
+                        total_input_tokens = usage.prompt_token_count;
+                        total_output_tokens = usage.candidates_token_count;
+                        progress.set_output_tokens(total_output_tokens);
+                    }
+                }
+                Ok(None) => {
+                    tracing::debug!(request_id = %req_id, "Gemini stream ended");
+                    break;
+                }
+                Err(e) => {
+                    stream_error = true;
+                    tracing::error!(request_id = %req_id, error = %e, "Gemini stream error");
+                    yield sse.data(&OpenAIErrorResponse::server_error(&format!("Gemini API error: {}", e)));
+                    break;
+                }
+            }
+        }
+
+        if !stream_error {
+            // Gemini reports STOP after function calls
+            if tool_call_index > 0 && finish_reason == "stop" {
+                finish_reason = "tool_calls".to_string();
+            }
+            yield sse.data(&ChunkRef {
+                system_fingerprint: Some(&fingerprint),
+                ..ChunkRef::new(&completion_id, created, &model_id, &[
+                    ChunkChoiceRef::finish(&finish_reason),
+                ])
+            });
+
+            if include_usage {
+                let usage = CompletionUsage {
+                    prompt_tokens: total_input_tokens,
+                    completion_tokens: total_output_tokens,
+                    total_tokens: total_input_tokens + total_output_tokens,
+                    completion_tokens_details: None,
+                };
+                yield sse.data(&ChunkRef {
+                    usage: Some(&usage),
+                    ..ChunkRef::new(&completion_id, created, &model_id, &[])
+                });
+            }
+
+            yield sse.raw_data("[DONE]");
+        }
+
+        // Record success or failure for the credential
+        if stream_error {
+            gemini_service.record_failure(&credential_name);
+        } else {
+            gemini_service.record_success(&credential_name);
+        }
+
+        tracing::info!(
+            request_id = %req_id,
+            model = %model_id,
+            gemini_model = %gemini_model_id,
+            credential = %credential_name,
+            prompt_tokens = total_input_tokens,
+            completion_tokens = total_output_tokens,
+            finish_reason = %finish_reason,
+            stream_error = stream_error,
+            "Gemini OpenAI streaming response completed"
+        );
+        progress.finish_eval(total_input_tokens, total_output_tokens);
+
+        if let Some(tap) = progress.tap() {
+            if cancelled {
+                tap.cancel(total_input_tokens, total_output_tokens);
+            } else {
+                let status = if stream_error { StatusCode::INTERNAL_SERVER_ERROR } else { StatusCode::OK };
+                tap.complete(status.as_u16(), total_input_tokens, Some(total_output_tokens));
+            }
+        }
+    };
+
+    Ok((SseResponse::new(stream), routing))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{ReplayBackend, StreamRecording};
+
+    const INTERLEAVED_TOOL_USE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/interleaved_tool_use.json"
+    );
+
+    /// Replay a recorded backend stream through the handler and return the
+    /// chunks sent to the client (without the final `[DONE]`)
+    async fn replay(fixture: &str) -> Vec<Value> {
+        let recording = StreamRecording::load(std::path::Path::new(fixture)).unwrap();
+        let response = create_openai_streaming_response(
+            &ReplayBackend::new(recording),
+            ConverseRequest::new("anthropic.claude-sonnet-4-5"),
+            "req_1",
+            "claude-sonnet-4-5",
+            true,
+            "default",
+            StreamProgress::new(),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let text = std::str::from_utf8(&body).unwrap();
+        assert!(text.ends_with("data: [DONE]\n\n"));
+        text.split("\n\n")
+            .filter_map(|frame| frame.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_interleaved_tool_calls() {
+        let chunks = replay(INTERLEAVED_TOOL_USE).await;
+
+        let mut arguments = vec![String::new(); 2];
+        let mut ids = Vec::new();
+        for chunk in &chunks {
+            let Some(calls) = chunk["choices"][0]["delta"]["tool_calls"].as_array() else {
+                continue;
+            };
+            for call in calls {
+                let index = call["index"].as_u64().unwrap() as usize;
+                if let Some(id) = call["id"].as_str() {
+                    ids.push(id.to_string());
+                }
+                if let Some(args) = call["function"]["arguments"].as_str() {
+                    arguments[index].push_str(args);
+                }
+            }
+        }
+
+        assert_eq!(ids, vec!["tooluse_paris", "tooluse_tokyo"]);
+        assert_eq!(serde_json::from_str::<Value>(&arguments[0]).unwrap(), serde_json::json!({"city": "Paris"}));
+        assert_eq!(serde_json::from_str::<Value>(&arguments[1]).unwrap(), serde_json::json!({"city": "Tokyo"}));
+
+        let finish = chunks.iter().find_map(|c| c["choices"][0]["finish_reason"].as_str());
+        assert_eq!(finish, Some("tool_calls"));
+        let usage = &chunks.last().unwrap()["usage"];
+        assert_eq!(usage["prompt_tokens"], 312);
+        assert_eq!(usage["completion_tokens"], 58);
+    }
+
+    /// Accumulate stream chunks the way LangChain's OpenAI parser does
+    ///
+    /// Tool call chunks are merged by index into `AIMessageChunk`s, and
+    /// LangChain concatenates every string field present on both sides, so a
+    /// repeated id or name corrupts the call. Also checks what OpenAI's own
+    /// streams guarantee: indices start at 0 and each call's chunks are
+    /// contiguous, and the finish reason comes last on an empty delta.
+    fn langchain_accumulate(chunks: &[Value]) -> Value {
+        let mut content = String::new();
+        let mut calls: Vec<serde_json::Map<String, Value>> = Vec::new();
+        let mut finish_reason = None;
+
+        for chunk in chunks {
+            let Some(choice) = chunk["choices"].get(0) else {
+                continue;
+            };
+            let delta = &choice["delta"];
+            if finish_reason.is_some() {
+                assert!(
+                    delta.get("content").is_none() && delta.get("tool_calls").is_none(),
+                    "delta after finish_reason: {}",
+                    chunk
+                );
+            }
+            if let Some(text) = delta["content"].as_str() {
+                content.push_str(text);
+            }
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = call["index"].as_u64().unwrap() as usize;
+                assert!(index + 1 >= calls.len(), "chunk for finished call {}", index);
+                assert!(index <= calls.len(), "tool call index {} skips ahead", index);
+                if index == calls.len() {
+                    calls.push(serde_json::Map::new());
+                }
+                let merged = &mut calls[index];
+                for (key, value) in [
+                    ("id", &call["id"]),
+                    ("name", &call["function"]["name"]),
+                    ("args", &call["function"]["arguments"]),
+                ] {
+                    let Some(value) = value.as_str() else { continue };
+                    let entry = merged.entry(key).or_insert_with(|| json!(""));
+                    *entry = json!(format!("{}{}", entry.as_str().unwrap(), value));
+                }
+            }
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                assert!(finish_reason.is_none(), "second finish_reason");
+                assert_eq!(delta, &json!({}));
+                finish_reason = Some(reason.to_string());
+            }
+        }
+
+        let tool_calls: Vec<Value> = calls
+            .into_iter()
+            .map(|call| {
+                let args = call["args"].as_str().unwrap();
+                json!({
+                    "id": call["id"],
+                    "name": call["name"],
+                    "args": serde_json::from_str::<Value>(args).unwrap(),
+                })
+            })
+            .collect();
+        json!({"content": content, "tool_calls": tool_calls, "finish_reason": finish_reason})
+    }
+
+    #[tokio::test]
+    async fn test_langchain_tool_call_streams() {
+        for name in [
+            "parallel_tools_interleaved",
+            "text_between_tools",
+            "three_calls_out_of_order",
+        ] {
+            let fixture = format!(
+                "{}/tests/fixtures/langchain/{}.json",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            );
+            let expected: Value =
+                serde_json::from_str(&std::fs::read_to_string(&fixture).unwrap()).unwrap();
+            let chunks = replay(&fixture).await;
+            assert_eq!(langchain_accumulate(&chunks), expected["langchain"], "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_bedrock_error_status_mapping() {
+        let status = |err: BedrockError| {
+            let api_err = OpenAIApiError::from_bedrock_error(&err);
+            (api_err.status.as_u16(), api_err.error.error.error_type)
+        };
+
+        assert_eq!(status(BedrockError::Throttled("t".into())), (429, "rate_limit_error".into()));
+        assert_eq!(status(BedrockError::ServiceUnavailable("t".into())), (503, "service_unavailable".into()));
+        assert_eq!(status(BedrockError::ModelEndOfLife("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::ValidationError("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::ModelNotFound("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::AccessDenied("t".into())), (403, "permission_error".into()));
+        assert_eq!(status(BedrockError::InternalError("t".into())), (500, "server_error".into()));
+        assert_eq!(status(BedrockError::Unknown("t".into())), (500, "server_error".into()));
+    }
+
+    #[test]
+    fn test_list_page() {
+        let items = || (1..=3).map(|i| (format!("id-{}", i), json!(i))).collect::<Vec<_>>();
+        let params = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let page = list_page(items(), &params(&[("limit", "2")])).unwrap();
+        assert_eq!(page["data"], json!([1, 2]));
+        assert_eq!(page["last_id"], "id-2");
+        assert_eq!(page["has_more"], true);
+
+        let page = list_page(items(), &params(&[("after", "id-2")])).unwrap();
+        assert_eq!(page["data"], json!([3]));
+        assert_eq!(page["has_more"], false);
+
+        let page = list_page(items(), &params(&[("order", "desc"), ("limit", "1")])).unwrap();
+        assert_eq!(page["first_id"], "id-3");
+
+        assert!(list_page(items(), &params(&[("limit", "0")])).is_err());
+        assert!(list_page(items(), &params(&[("order", "sideways")])).is_err());
+    }
 }
@@ -0,0 +1,306 @@
+//! Claude Code request compatibility
+//!
+//! Claude Code leans on leniencies of the Anthropic API that Bedrock Converse
+//! does not share. Over a long agent session its requests contain:
+//!
+//! - blank text blocks (an interrupted turn leaves `{"type": "text", "text": ""}`
+//!   ahead of a `tool_use`), which Converse rejects
+//! - consecutive turns of the same role (`[Request interrupted by user]`
+//!   followed by the next prompt), which the Anthropic API merges and Converse
+//!   rejects
+//! - `tool_result` blocks out of `tool_use` order, with `<system-reminder>` text
+//!   placed between or ahead of them
+//!
+//! [`normalize_request`] rewrites those into the shape Converse accepts
+//! without changing what the model sees. It runs for requests with the
+//! `claude-code` [`ClientProfile`](crate::api::client_profile::ClientProfile).
+//! The recorded sessions under `tests/fixtures/claude_code` are the reference
+//! traffic.
+
+use crate::schemas::anthropic::{ContentBlock, Message, MessageContent, MessageRequest};
+
+/// What [`normalize_request`] changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompatFixes {
+    /// Blank text blocks removed
+    pub blank_blocks: usize,
+    /// Same-role turns merged into the turn before
+    pub merged_turns: usize,
+    /// User turns whose tool results were moved ahead or into `tool_use` order
+    pub reordered_turns: usize,
+}
+
+impl CompatFixes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Rewrite a Claude Code request into the shape Bedrock Converse accepts
+pub fn normalize_request(request: &mut MessageRequest) -> CompatFixes {
+    let mut fixes = CompatFixes {
+        blank_blocks: drop_blank_text(&mut request.messages),
+        ..CompatFixes::default()
+    };
+    fixes.merged_turns = merge_same_role_turns(&mut request.messages);
+    fixes.reordered_turns = order_tool_results(&mut request.messages);
+    fixes
+}
+
+/// Remove blank text blocks, and turns left with no content
+fn drop_blank_text(messages: &mut Vec<Message>) -> usize {
+    let mut dropped = 0;
+    for message in messages.iter_mut() {
+        if let MessageContent::Blocks(blocks) = &mut message.content {
+            let before = blocks.len();
+            blocks.retain(|block| !is_blank_text(block));
+            dropped += before - blocks.len();
+        }
+    }
+    messages.retain(|message| match &message.content {
+        MessageContent::Text(text) => !text.trim().is_empty(),
+        MessageContent::Blocks(blocks) => !blocks.is_empty(),
+    });
+    dropped
+}
+
+fn is_blank_text(block: &ContentBlock) -> bool {
+    matches!(block, ContentBlock::Text { text, .. } if text.trim().is_empty())
+}
+
+/// Merge each turn into the one before it when both have the same role
+fn merge_same_role_turns(messages: &mut Vec<Message>) -> usize {
+    let mut merged: Vec<Message> = Vec::with_capacity(messages.len());
+    let mut count = 0;
+
+    for message in messages.drain(..) {
+        match merged.last_mut() {
+            Some(previous) if previous.role == message.role => {
+                let mut blocks =
+                    std::mem::replace(&mut previous.content, MessageContent::Blocks(Vec::new()))
+                        .into_blocks();
+                blocks.extend(message.content.into_blocks());
+                previous.content = MessageContent::Blocks(blocks);
+                count += 1;
+            }
+            _ => merged.push(message),
+        }
+    }
+
+    *messages = merged;
+    count
+}
+
+/// Put each user turn's tool results first, in the order of the preceding
+/// assistant turn's `tool_use` blocks
+fn order_tool_results(messages: &mut [Message]) -> usize {
+    let mut reordered = 0;
+
+    for i in 1..messages.len() {
+        let previous = &messages[i - 1];
+        let tool_use_ids: Vec<String> = match &previous.content {
+            MessageContent::Blocks(blocks) if previous.role == "assistant" => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::ToolUse { id, .. } => Some(id.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => continue,
+        };
+
+        if messages[i].role != "user" || tool_use_ids.is_empty() {
+            continue;
+        }
+        let MessageContent::Blocks(blocks) = &mut messages[i].content else {
+            continue;
+        };
+
+        let position = |block: &ContentBlock| match block {
+            ContentBlock::ToolResult { tool_use_id, .. } => tool_use_ids
+                .iter()
+                .position(|id| id == tool_use_id)
+                .unwrap_or(tool_use_ids.len()),
+            _ => usize::MAX,
+        };
+
+        let ordered = blocks.windows(2).all(|pair| position(&pair[0]) <= position(&pair[1]));
+        if !ordered {
+            // Stable, so other blocks keep their relative order after the results
+            blocks.sort_by_key(position);
+            reordered += 1;
+        }
+    }
+
+    reordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    use crate::api::client_profile::ClientProfile;
+    use crate::api::messages::CountTokensRequest;
+    use crate::config::ClientCompatConfig;
+    use crate::converters::AnthropicToBedrockConverter;
+    use crate::schemas::bedrock::BedrockContentBlock;
+    use crate::services::{count_prompt, model_capabilities};
+
+    const TOOL_LOOP: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/claude_code/tool_loop.json"
+    );
+    const INTERRUPTED_TURN: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/claude_code/interrupted_turn.json"
+    );
+    const COUNT_TOKENS: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/claude_code/count_tokens.json"
+    );
+
+    /// A recorded request: its headers and JSON body
+    fn load(fixture: &str) -> (HeaderMap, serde_json::Value) {
+        let recorded: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+        let mut headers = HeaderMap::new();
+        for (name, value) in recorded["headers"].as_object().unwrap() {
+            headers.insert(
+                axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                value.as_str().unwrap().parse().unwrap(),
+            );
+        }
+        (headers, recorded["body"].clone())
+    }
+
+    /// Normalize and convert a recorded request, checking the Converse rules
+    /// Claude Code traffic used to break
+    fn assert_converse_valid(request: &mut MessageRequest) {
+        normalize_request(request);
+        let converted = AnthropicToBedrockConverter::new()
+            .convert_request(request)
+            .unwrap();
+
+        let mut tool_use_ids: Vec<String> = Vec::new();
+        for (i, message) in converted.messages.iter().enumerate() {
+            let expected_role = if i % 2 == 0 { "user" } else { "assistant" };
+            assert_eq!(message.role, expected_role, "turn {} out of alternation", i);
+            assert!(!message.content.is_empty(), "turn {} is empty", i);
+
+            let mut results = Vec::new();
+            let mut seen_other = false;
+            for block in &message.content {
+                match block {
+                    BedrockContentBlock::Text { text, .. } => {
+                        assert!(!text.trim().is_empty(), "blank text in turn {}", i);
+                        seen_other = true;
+                    }
+                    BedrockContentBlock::ToolResult { tool_result, .. } => {
+                        assert!(!seen_other, "tool result after other content in turn {}", i);
+                        results.push(tool_result.tool_use_id.clone());
+                    }
+                    _ => seen_other = true,
+                }
+            }
+            if message.role == "user" {
+                assert_eq!(results, tool_use_ids, "tool results of turn {}", i);
+            }
+
+            tool_use_ids = message
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    BedrockContentBlock::ToolUse { tool_use, .. } => {
+                        Some(tool_use.tool_use_id.clone())
+                    }
+                    _ => None,
+                })
+                .collect();
+        }
+    }
+
+    #[test]
+    fn test_tool_loop_fixture() {
+        let (headers, body) = load(TOOL_LOOP);
+        assert_eq!(
+            ClientProfile::from_headers(&headers, &ClientCompatConfig::default()),
+            ClientProfile::ClaudeCode
+        );
+
+        // Only betas Bedrock knows are forwarded; claude-code-* stay local
+        let betas = model_capabilities::parse_betas(
+            headers.get_all("anthropic-beta").iter().filter_map(|v| v.to_str().ok()),
+        );
+        assert_eq!(
+            model_capabilities::bedrock_betas(&betas),
+            vec![model_capabilities::INTERLEAVED_THINKING_BETA.to_string()]
+        );
+
+        let mut request: MessageRequest = serde_json::from_value(body).unwrap();
+        assert!(matches!(
+            request.system,
+            Some(crate::schemas::anthropic::SystemContent::Messages(_))
+        ));
+        let original = request.clone();
+        assert_eq!(normalize_request(&mut request.clone()).reordered_turns, 1);
+        assert_converse_valid(&mut request);
+
+        // Results were reordered, never dropped
+        let results = |request: &MessageRequest| {
+            request
+                .messages
+                .iter()
+                .flat_map(|m| m.content.clone().into_blocks())
+                .filter(|b| matches!(b, ContentBlock::ToolResult { .. }))
+                .count()
+        };
+        assert_eq!(results(&request), results(&original));
+        assert_eq!(request.messages.len(), original.messages.len());
+    }
+
+    #[test]
+    fn test_interrupted_turn_fixture() {
+        let (_, body) = load(INTERRUPTED_TURN);
+        let mut request: MessageRequest = serde_json::from_value(body).unwrap();
+
+        let fixes = normalize_request(&mut request.clone());
+        assert_eq!(fixes.blank_blocks, 1);
+        assert_eq!(fixes.merged_turns, 1);
+        assert!(!fixes.is_empty());
+
+        assert_converse_valid(&mut request);
+        // The interruption note and the next prompt follow the rejected tool result
+        let last = request.messages.last().unwrap().content.clone().into_blocks();
+        assert_eq!(last.len(), 3);
+        assert!(matches!(&last[0], ContentBlock::ToolResult { .. }));
+        assert!(matches!(&last[1], ContentBlock::Text { text, .. } if text.contains("interrupted")));
+    }
+
+    #[test]
+    fn test_count_tokens_fixture() {
+        let (_, body) = load(COUNT_TOKENS);
+        let request: CountTokensRequest = serde_json::from_value(body).unwrap();
+        let breakdown = count_prompt(
+            &request.model,
+            request.system.as_ref(),
+            &request.messages,
+            request.tools.as_deref(),
+        );
+        assert_eq!(breakdown.messages.len(), request.messages.len());
+        assert!(breakdown.tools > 0);
+    }
+
+    #[test]
+    fn test_well_formed_request_unchanged() {
+        let mut request: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "hi"}]
+        }))
+        .unwrap();
+        let original = request.clone();
+        assert!(normalize_request(&mut request).is_empty());
+        assert_eq!(request.messages, original.messages);
+    }
+}
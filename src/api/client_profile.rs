@@ -0,0 +1,170 @@
+//! Client compatibility profiles
+//!
+//! SDKs and editors differ in what they tolerate in a stream: some wait for
+//! the `ping` the Anthropic API sends after `message_start`, some require a
+//! `content` string in the first OpenAI chunk, and some only understand a bare
+//! `error` event when a stream fails. A [`ClientProfile`] is picked per request
+//! and its [`StreamQuirks`] adjust those formatting details. The canonical
+//! profile keeps the default formatting. Claude Code requests are also
+//! reshaped for Converse before conversion (see [`crate::api::claude_code`]).
+
+use axum::http::{header, HeaderMap};
+
+use crate::config::ClientCompatConfig;
+
+/// Header selecting a profile explicitly
+pub const CLIENT_PROFILE_HEADER: &str = "x-client-profile";
+
+/// Client whose stream expectations are matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientProfile {
+    #[default]
+    Canonical,
+    ClaudeCode,
+    Cursor,
+    LangChain,
+    Zed,
+}
+
+/// Stream formatting details that differ between clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamQuirks {
+    /// Send a `ping` event right after `message_start`
+    pub ping_after_start: bool,
+    /// Include `"content": ""` in the OpenAI chunk carrying the role
+    pub empty_role_content: bool,
+    /// End a failed Anthropic stream with only the `error` event, without
+    /// closing open blocks or sending a final `message_delta`
+    pub bare_stream_errors: bool,
+}
+
+impl ClientProfile {
+    /// Parse a profile name (`claude-code`, `cursor`, ...)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "canonical" | "default" => Some(Self::Canonical),
+            "claude-code" => Some(Self::ClaudeCode),
+            "cursor" => Some(Self::Cursor),
+            "langchain" => Some(Self::LangChain),
+            "zed" => Some(Self::Zed),
+            _ => None,
+        }
+    }
+
+    /// Detect the client from a User-Agent
+    pub fn from_user_agent(user_agent: &str) -> Option<Self> {
+        let user_agent = user_agent.to_ascii_lowercase();
+        if user_agent.contains("claude-cli") || user_agent.contains("claude-code") {
+            Some(Self::ClaudeCode)
+        } else if user_agent.contains("cursor") {
+            Some(Self::Cursor)
+        } else if user_agent.contains("langchain") {
+            Some(Self::LangChain)
+        } else if user_agent.contains("zed") {
+            Some(Self::Zed)
+        } else {
+            None
+        }
+    }
+
+    /// Profile for a request: the header, then the User-Agent, then the default
+    pub fn from_headers(headers: &HeaderMap, config: &ClientCompatConfig) -> Self {
+        let header_value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        header_value(CLIENT_PROFILE_HEADER)
+            .and_then(Self::parse)
+            .or_else(|| {
+                if !config.detect_user_agent {
+                    return None;
+                }
+                header_value(header::USER_AGENT.as_str()).and_then(Self::from_user_agent)
+            })
+            .or_else(|| Self::parse(&config.default_profile))
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Canonical => "canonical",
+            Self::ClaudeCode => "claude-code",
+            Self::Cursor => "cursor",
+            Self::LangChain => "langchain",
+            Self::Zed => "zed",
+        }
+    }
+
+    /// Stream formatting adjustments for this client
+    pub fn quirks(&self) -> StreamQuirks {
+        match self {
+            Self::Canonical => StreamQuirks::default(),
+            Self::ClaudeCode => StreamQuirks {
+                ping_after_start: true,
+                ..StreamQuirks::default()
+            },
+            Self::Cursor | Self::LangChain => StreamQuirks {
+                empty_role_content: true,
+                ..StreamQuirks::default()
+            },
+            Self::Zed => StreamQuirks {
+                ping_after_start: true,
+                bare_stream_errors: true,
+                ..StreamQuirks::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_selection() {
+        let config = ClientCompatConfig::default();
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        let ua = headers(&[("user-agent", "claude-cli/1.0.30 (external, cli)")]);
+        assert_eq!(
+            ClientProfile::from_headers(&ua, &config),
+            ClientProfile::ClaudeCode
+        );
+
+        let explicit = headers(&[
+            ("user-agent", "claude-cli/1.0.30"),
+            ("x-client-profile", "Zed"),
+        ]);
+        assert_eq!(
+            ClientProfile::from_headers(&explicit, &config),
+            ClientProfile::Zed
+        );
+
+        let no_detection = ClientCompatConfig {
+            detect_user_agent: false,
+            default_profile: "cursor".to_string(),
+        };
+        assert_eq!(
+            ClientProfile::from_headers(&ua, &no_detection),
+            ClientProfile::Cursor
+        );
+
+        let unknown = headers(&[("user-agent", "curl/8.0")]);
+        assert_eq!(
+            ClientProfile::from_headers(&unknown, &config),
+            ClientProfile::Canonical
+        );
+    }
+
+    #[test]
+    fn test_quirks() {
+        assert_eq!(ClientProfile::Canonical.quirks(), StreamQuirks::default());
+        assert!(ClientProfile::ClaudeCode.quirks().ping_after_start);
+        assert!(ClientProfile::LangChain.quirks().empty_role_content);
+        assert!(ClientProfile::Zed.quirks().bare_stream_errors);
+    }
+}
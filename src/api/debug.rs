@@ -0,0 +1,465 @@
+//! Debug endpoints
+//!
+//! `POST /debug/convert` runs a request through the same converters the proxy
+//! uses and returns the backend payload without calling any backend, so a
+//! mangled tool schema or dropped content block can be inspected directly.
+//! `GET /debug/models/{alias}` reports how a model name resolves today.
+//! `GET /debug/bedrock-clients` shows the per-credential Bedrock client
+//! cache when `BEDROCK_CREDENTIAL_POOL` is configured.
+//! `POST /debug/stream-recordings` arms the stream recorder for the next
+//! streamed backend calls; `GET /debug/stream-recordings/{id}` returns a
+//! recording in the fixture format
+//! [`ReplayBackend`](crate::services::ReplayBackend) replays.
+//! Routes are nested under `/debug` and require the master key or a key
+//! holding the `admin` scope.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Settings;
+use crate::converters::{AnthropicToBedrockConverter, OpenAIToGeminiConverter, SharedConverters};
+use crate::error::ApiError;
+use crate::schemas::anthropic::MessageRequest;
+use crate::schemas::openai::ChatCompletionRequest;
+use crate::server::state::AppState;
+use crate::services::model_capabilities::{self, ModelCapabilities};
+use crate::services::{
+    BackendTarget, ClientCacheStats, ModelRoutingTable, RecorderStatus, RecordingSummary,
+    StreamRecording,
+};
+
+/// Cross-region inference profile prefixes of Bedrock model IDs
+const REGION_PREFIXES: &[&str] = &["global", "us", "us-gov", "eu", "apac", "jp", "au", "ca"];
+
+/// API format of the submitted request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    /// Anthropic Messages API (`/v1/messages`)
+    #[default]
+    Anthropic,
+    /// OpenAI Chat Completions API (`/v1/chat/completions`)
+    Openai,
+}
+
+/// Backend payload format to convert into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetFormat {
+    /// Bedrock Converse API
+    Bedrock,
+    /// Gemini generateContent API
+    Gemini,
+}
+
+/// Request body for `POST /debug/convert`
+#[derive(Debug, Deserialize)]
+pub struct ConvertRequest {
+    /// Format of `request` (defaults to Anthropic)
+    #[serde(default)]
+    pub source: SourceFormat,
+
+    /// Format to convert into
+    pub target: TargetFormat,
+
+    /// The request exactly as it would be sent to the proxy
+    pub request: serde_json::Value,
+}
+
+/// Response for `POST /debug/convert`
+#[derive(Debug, Serialize)]
+pub struct ConvertResponse {
+    pub source: SourceFormat,
+    pub target: TargetFormat,
+
+    /// Backend model ID the request resolves to
+    pub model: String,
+
+    /// Converted backend request body
+    pub payload: serde_json::Value,
+}
+
+/// Convert a request into the target backend payload
+pub fn convert_payload(
+    converters: &SharedConverters,
+    body: ConvertRequest,
+) -> Result<ConvertResponse, ApiError> {
+    let parse_error = |e: serde_json::Error| {
+        ApiError::InvalidRequest(format!(
+            "Request does not parse as {:?}: {}",
+            body.source, e
+        ))
+    };
+    let conversion_error =
+        |e: &dyn std::fmt::Display| ApiError::InvalidRequest(format!("Conversion error: {}", e));
+
+    let (model, payload) = match (body.source, body.target) {
+        (SourceFormat::Anthropic, TargetFormat::Bedrock) => {
+            let request: MessageRequest =
+                serde_json::from_value(body.request).map_err(parse_error)?;
+            let converted = AnthropicToBedrockConverter::new()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (converted.model_id.clone(), to_json(&converted)?)
+        }
+        (SourceFormat::Anthropic, TargetFormat::Gemini) => {
+            let request: MessageRequest =
+                serde_json::from_value(body.request).map_err(parse_error)?;
+            let (model, converted) = converters
+                .anthropic_to_gemini()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (model, to_json(&converted)?)
+        }
+        (SourceFormat::Openai, TargetFormat::Bedrock) => {
+            let request: ChatCompletionRequest =
+                serde_json::from_value(body.request).map_err(parse_error)?;
+            let converted = converters
+                .openai_to_bedrock()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (converted.model_id.clone(), to_json(&converted)?)
+        }
+        (SourceFormat::Openai, TargetFormat::Gemini) => {
+            let request: ChatCompletionRequest =
+                serde_json::from_value(body.request).map_err(parse_error)?;
+            let (model, converted) = OpenAIToGeminiConverter::new()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (model, to_json(&converted)?)
+        }
+    };
+
+    Ok(ConvertResponse {
+        source: body.source,
+        target: body.target,
+        model,
+        payload,
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<serde_json::Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::Internal(e.into()))
+}
+
+/// POST /debug/convert - Convert a request without calling any backend
+pub async fn convert(
+    State(state): State<AppState>,
+    Json(body): Json<ConvertRequest>,
+) -> Result<Json<ConvertResponse>, ApiError> {
+    convert_payload(&state.converters, body).map(Json)
+}
+
+/// Response for `GET /debug/models/{alias}`
+#[derive(Debug, Serialize)]
+pub struct ModelResolution {
+    pub alias: String,
+
+    /// Backend the routing table selects (e.g. "bedrock:west")
+    pub backend: String,
+
+    /// MODEL_ROUTES pattern that matched (None = built-in default)
+    pub matched_route: Option<String>,
+
+    /// Backend used instead when the request carries the 1M-context beta
+    pub long_context_backend: Option<String>,
+
+    /// Model ID sent for `/v1/messages`
+    pub messages: ResolvedModel,
+
+    /// Model ID sent for `/v1/chat/completions` (Bedrock only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_completions: Option<ResolvedModel>,
+
+    /// Cross-region inference prefix of the Bedrock ID (e.g. "us", "global")
+    pub region_prefix: Option<String>,
+
+    /// Provisioned throughput ARN used for `auto`/`priority` service tiers
+    pub provisioned_arn: Option<String>,
+
+    /// Known limits of the model (None = unknown, not validated)
+    pub capabilities: Option<ModelCapabilities>,
+}
+
+/// Backend model ID and where it came from
+#[derive(Debug, Serialize)]
+pub struct ResolvedModel {
+    pub model_id: String,
+
+    /// "model_mapping", "converter_mapping", "gemini_mapping", "passthrough"
+    /// or "default_fallback"
+    pub source: &'static str,
+}
+
+/// Describe how an alias resolves against the current configuration
+pub fn describe_model(
+    alias: &str,
+    settings: &Settings,
+    routes: &ModelRoutingTable,
+    converters: &SharedConverters,
+    gemini_available: bool,
+) -> ModelResolution {
+    let mut backend = routes.resolve(alias);
+    if backend == BackendTarget::Gemini && !gemini_available {
+        backend = BackendTarget::Bedrock { profile: None };
+    }
+
+    let (messages, chat_completions) = if backend == BackendTarget::Gemini {
+        let model_id = converters.anthropic_to_gemini().get_gemini_model(alias);
+        let source = if model_id == alias {
+            "passthrough"
+        } else {
+            "gemini_mapping"
+        };
+        (ResolvedModel { model_id, source }, None)
+    } else {
+        (
+            bedrock_model(alias, settings),
+            Some(openai_bedrock_model(alias, settings, converters)),
+        )
+    };
+
+    let region_prefix = messages
+        .model_id
+        .split_once('.')
+        .map(|(prefix, _)| prefix)
+        .filter(|prefix| REGION_PREFIXES.contains(prefix))
+        .map(str::to_string);
+
+    ModelResolution {
+        alias: alias.to_string(),
+        backend: backend.to_string(),
+        matched_route: routes.matching_route(alias).map(|r| r.pattern.clone()),
+        long_context_backend: routes.long_context_target().map(|t| t.to_string()),
+        provisioned_arn: settings
+            .provisioned_throughput
+            .arn_for(alias, &messages.model_id)
+            .map(str::to_string),
+        capabilities: model_capabilities::capabilities_for(alias)
+            .or_else(|| model_capabilities::capabilities_for(&messages.model_id))
+            .copied(),
+        region_prefix,
+        messages,
+        chat_completions,
+    }
+}
+
+/// Bedrock ID for `/v1/messages` (settings model mapping)
+fn bedrock_model(alias: &str, settings: &Settings) -> ResolvedModel {
+    match settings.default_model_mapping.get(alias) {
+        Some(model_id) => ResolvedModel {
+            model_id: model_id.clone(),
+            source: "model_mapping",
+        },
+        None => ResolvedModel {
+            model_id: alias.to_string(),
+            source: "passthrough",
+        },
+    }
+}
+
+/// Bedrock ID for `/v1/chat/completions` (converter mapping, then settings)
+fn openai_bedrock_model(
+    alias: &str,
+    settings: &Settings,
+    converters: &SharedConverters,
+) -> ResolvedModel {
+    let converter = converters.openai_to_bedrock();
+    let converted = converter.convert_model_id(alias);
+    let source = if converted == alias {
+        "passthrough"
+    } else if converter.has_model_mapping(alias) {
+        "converter_mapping"
+    } else {
+        "default_fallback"
+    };
+
+    match settings.default_model_mapping.get(&converted) {
+        Some(model_id) => ResolvedModel {
+            model_id: model_id.clone(),
+            source: "model_mapping",
+        },
+        None => ResolvedModel {
+            model_id: converted,
+            source,
+        },
+    }
+}
+
+/// GET /debug/models/{alias} - Report how a model name resolves
+pub async fn resolve_model(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+) -> Json<ModelResolution> {
+    Json(describe_model(
+        &alias,
+        &state.settings,
+        &state.model_routes,
+        &state.converters,
+        state.is_gemini_available(),
+    ))
+}
+
+/// GET /debug/bedrock-clients - Report the Bedrock client cache
+pub async fn bedrock_clients(
+    State(state): State<AppState>,
+) -> Result<Json<ClientCacheStats>, ApiError> {
+    state
+        .bedrock
+        .client_cache_stats()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No Bedrock credential pool is configured".to_string()))
+}
+
+/// Request body for `POST /debug/stream-recordings`
+#[derive(Debug, Deserialize)]
+pub struct ArmRecorderRequest {
+    /// Streams to capture (capped at 16)
+    #[serde(default = "default_record_count")]
+    pub count: u32,
+
+    /// Only capture streams to this routing target (e.g. "bedrock:east")
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+fn default_record_count() -> u32 {
+    1
+}
+
+/// POST /debug/stream-recordings - Record the next streamed backend calls
+pub async fn arm_stream_recorder(
+    State(state): State<AppState>,
+    Json(body): Json<ArmRecorderRequest>,
+) -> Json<RecorderStatus> {
+    let status = state.stream_recorder.arm(body.count, body.target);
+    tracing::info!(armed = status.armed, target = ?status.target, "Stream recorder armed");
+    Json(status)
+}
+
+/// GET /debug/stream-recordings - List completed recordings
+pub async fn list_stream_recordings(State(state): State<AppState>) -> Json<Vec<RecordingSummary>> {
+    Json(state.stream_recorder.list())
+}
+
+/// GET /debug/stream-recordings/{id} - Fetch one recording
+pub async fn get_stream_recording(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<StreamRecording>, ApiError> {
+    state
+        .stream_recorder
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Stream recording '{}' not found", id)))
+}
+
+/// DELETE /debug/stream-recordings - Disarm and drop all recordings
+pub async fn clear_stream_recordings(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let cleared = state.stream_recorder.clear();
+    Json(serde_json::json!({ "cleared": cleared }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn convert(
+        source: SourceFormat,
+        target: TargetFormat,
+        request: serde_json::Value,
+    ) -> Result<ConvertResponse, ApiError> {
+        convert_payload(
+            &SharedConverters::new(),
+            ConvertRequest {
+                source,
+                target,
+                request,
+            },
+        )
+    }
+
+    #[test]
+    fn test_convert_anthropic_tool_to_bedrock() {
+        let response = convert(
+            SourceFormat::Anthropic,
+            TargetFormat::Bedrock,
+            json!({
+                "model": "claude-3-5-sonnet-20241022",
+                "max_tokens": 100,
+                "messages": [{"role": "user", "content": "What's the weather?"}],
+                "tools": [{
+                    "name": "get_weather",
+                    "description": "Get the weather",
+                    "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }]
+            }),
+        )
+        .unwrap();
+
+        assert!(response.model.starts_with("anthropic."));
+        let tools = &response.payload["toolConfig"]["tools"];
+        assert_eq!(tools[0]["toolSpec"]["name"], "get_weather");
+    }
+
+    #[test]
+    fn test_convert_openai_to_gemini() {
+        let response = convert(
+            SourceFormat::Openai,
+            TargetFormat::Gemini,
+            json!({
+                "model": "gemini-2.5-flash",
+                "messages": [{"role": "user", "content": "Hello"}]
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(response.model, "gemini-2.5-flash");
+        assert_eq!(response.payload["contents"][0]["parts"][0]["text"], "Hello");
+    }
+
+    #[test]
+    fn test_describe_model() {
+        let mut settings = Settings::default();
+        settings.default_model_mapping.insert(
+            "claude-sonnet-4-5".to_string(),
+            "global.anthropic.claude-sonnet-4-5-20250929-v1:0".to_string(),
+        );
+        let mut routes = ModelRoutingTable::new();
+        routes.add_route("gemini-*", BackendTarget::Gemini);
+        let converters = SharedConverters::new();
+
+        let sonnet = describe_model("claude-sonnet-4-5", &settings, &routes, &converters, true);
+        assert_eq!(sonnet.backend, "bedrock");
+        assert_eq!(sonnet.matched_route, None);
+        assert_eq!(sonnet.messages.source, "model_mapping");
+        assert_eq!(sonnet.region_prefix.as_deref(), Some("global"));
+        assert_eq!(
+            sonnet.capabilities.unwrap().long_context_window,
+            Some(1_000_000)
+        );
+
+        let gemini = describe_model("gemini-2.5-pro", &settings, &routes, &converters, true);
+        assert_eq!(gemini.backend, "gemini");
+        assert_eq!(gemini.matched_route.as_deref(), Some("gemini-*"));
+        assert!(gemini.chat_completions.is_none());
+
+        // Gemini unavailable falls back to Bedrock
+        let fallback = describe_model("gemini-2.5-pro", &settings, &routes, &converters, false);
+        assert_eq!(fallback.backend, "bedrock");
+    }
+
+    #[test]
+    fn test_unparseable_request_rejected() {
+        let result = convert(
+            SourceFormat::Anthropic,
+            TargetFormat::Bedrock,
+            json!({"messages": 1}),
+        );
+        assert!(matches!(result, Err(ApiError::InvalidRequest(_))));
+    }
+}
@@ -0,0 +1,286 @@
+//! Request dry runs
+//!
+//! A Messages or Chat Completions request sent with `x-dry-run: true` goes
+//! through authentication, validation, routing and conversion like any other,
+//! but stops short of the backend call. The response is a [`DryRunReport`]
+//! naming the backend and model the request would have reached, an estimate
+//! of its input tokens and the conversion warnings it raised, so CI jobs can
+//! check a prompt pipeline without spending tokens.
+//!
+//! Reports also carry a [`CostEstimate`]: the projected cost range of the
+//! request at the model's price from the pricing table (or the fallback
+//! rates) and the key's service tier. `POST /v1/estimate` returns the same
+//! report for any request without the header; see [`estimate`].
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue},
+    response::Response,
+    Extension, Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::api::invoke;
+use crate::converters::warnings::ConversionWarning;
+use crate::db::{ModelPricing, ModelPricingRepository};
+use crate::middleware::ApiKeyInfo;
+use crate::server::state::AppState;
+use crate::services::usage_tracker::get_tier_multiplier;
+
+/// Request header asking for a dry run
+pub const DRY_RUN_HEADER: &str = "x-dry-run";
+
+/// Whether the request asked for a dry run
+pub fn requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(DRY_RUN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            let v = v.trim();
+            v.eq_ignore_ascii_case("true") || v == "1"
+        })
+}
+
+/// What a request would have done
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    /// Always true; tells reports apart from real responses
+    pub dry_run: bool,
+    pub request_id: String,
+    /// Backend the request was routed to
+    pub backend: String,
+    /// Model after deprecation remaps and budget downgrades
+    pub model: String,
+    /// Model ID the backend would have been called with
+    pub backend_model: String,
+    /// Estimated prompt tokens (~4 characters per token)
+    pub estimated_input_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<i32>,
+    pub stream: bool,
+    pub warnings: Vec<ConversionWarning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<CostEstimate>,
+}
+
+impl DryRunReport {
+    pub fn new(
+        request_id: &str,
+        backend: impl ToString,
+        model: &str,
+        backend_model: String,
+        estimated_input_tokens: i32,
+    ) -> Self {
+        Self {
+            dry_run: true,
+            request_id: request_id.to_string(),
+            backend: backend.to_string(),
+            model: model.to_string(),
+            backend_model,
+            estimated_input_tokens,
+            max_output_tokens: None,
+            stream: false,
+            warnings: Vec::new(),
+            estimated_cost: None,
+        }
+    }
+
+    pub fn with_max_output_tokens(mut self, max_output_tokens: Option<i32>) -> Self {
+        self.max_output_tokens = max_output_tokens;
+        self
+    }
+
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<ConversionWarning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Attach the cost range for the key's service tier
+    ///
+    /// Prices are looked up under the backend model ID, then without its
+    /// region prefix (`us.`, `global.`), then under the client model name.
+    pub async fn with_cost_estimate(mut self, state: &AppState, key_info: &ApiKeyInfo) -> Self {
+        let repository = ModelPricingRepository::new(state.dynamodb.clone());
+        let mut pricing = None;
+        for model_id in pricing_keys(&self.backend_model, &self.model) {
+            match repository.get_pricing(&model_id).await {
+                Ok(Some(found)) => {
+                    pricing = Some(found);
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(model = %model_id, error = %e, "Pricing lookup failed");
+                    break;
+                }
+            }
+        }
+        self.estimated_cost = Some(CostEstimate::new(
+            pricing.as_ref(),
+            self.estimated_input_tokens,
+            self.max_output_tokens.unwrap_or(0),
+            get_tier_multiplier(&key_info.service_tier),
+        ));
+        self
+    }
+}
+
+/// Projected cost of a request in USD
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    /// Pricing table entry used, or None for the fallback rates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing_model_id: Option<String>,
+    /// Price per 1M input tokens
+    pub input_price: f64,
+    /// Price per 1M output tokens
+    pub output_price: f64,
+    /// Service tier multiplier applied to both prices
+    pub tier_multiplier: f64,
+    /// The prompt alone, if the model stops immediately
+    pub min_cost: f64,
+    /// The prompt plus every token `max_tokens` allows
+    pub max_cost: f64,
+}
+
+impl CostEstimate {
+    pub fn new(
+        pricing: Option<&ModelPricing>,
+        input_tokens: i32,
+        max_output_tokens: i32,
+        tier_multiplier: f64,
+    ) -> Self {
+        let fallback = ModelPricing::fallback("");
+        let rates = pricing.unwrap_or(&fallback);
+        let input_cost = input_tokens.max(0) as f64 * rates.input_price / 1_000_000.0;
+        let output_cost = max_output_tokens.max(0) as f64 * rates.output_price / 1_000_000.0;
+        Self {
+            pricing_model_id: pricing.map(|p| p.model_id.clone()),
+            input_price: rates.input_price,
+            output_price: rates.output_price,
+            tier_multiplier,
+            min_cost: input_cost * tier_multiplier,
+            max_cost: (input_cost + output_cost) * tier_multiplier,
+        }
+    }
+}
+
+/// Model IDs to look prices up under, most specific first
+fn pricing_keys(backend_model: &str, model: &str) -> Vec<String> {
+    let mut keys = vec![backend_model.to_string()];
+    if let Some((prefix, rest)) = backend_model.split_once('.') {
+        // Cross-region inference profiles (us.anthropic...) price like the base model
+        if rest.contains('.') && prefix.len() <= 6 {
+            keys.push(rest.to_string());
+        }
+    }
+    if !keys.iter().any(|key| key == model) {
+        keys.push(model.to_string());
+    }
+    keys
+}
+
+/// POST /v1/estimate - Route and price a request without running it
+///
+/// Takes an Anthropic Messages or OpenAI Chat Completions body, detected as
+/// on `/v1/invoke`, and answers with its dry-run report, including the cost
+/// estimate. Errors are in the format of the detected API.
+pub async fn estimate(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    mut headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    headers.insert(DRY_RUN_HEADER, HeaderValue::from_static("true"));
+    invoke::invoke(State(state), Extension(key_info), None, headers, Json(body)).await
+}
+
+/// Rough token count (~4 characters per token) of a converted request
+///
+/// Used for backends whose requests are not Converse requests; the JSON
+/// structure is counted along with the text.
+pub fn estimate_json_tokens<T: Serialize>(request: &T) -> i32 {
+    let chars = serde_json::to_string(request).map_or(0, |json| json.len());
+    (chars / 4).max(1) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_dry_run_header() {
+        let mut headers = HeaderMap::new();
+        assert!(!requested(&headers));
+
+        headers.insert(DRY_RUN_HEADER, HeaderValue::from_static("TRUE"));
+        assert!(requested(&headers));
+        headers.insert(DRY_RUN_HEADER, HeaderValue::from_static("1"));
+        assert!(requested(&headers));
+        headers.insert(DRY_RUN_HEADER, HeaderValue::from_static("false"));
+        assert!(!requested(&headers));
+    }
+
+    #[test]
+    fn test_cost_estimate_range() {
+        let pricing = ModelPricing {
+            input_price: 1.0,
+            output_price: 5.0,
+            ..ModelPricing::fallback("anthropic.claude-haiku-4-5")
+        };
+        let estimate = CostEstimate::new(Some(&pricing), 2_000_000, 1_000_000, 0.5);
+        assert_eq!(estimate.pricing_model_id.as_deref(), Some("anthropic.claude-haiku-4-5"));
+        assert!((estimate.min_cost - 1.0).abs() < 1e-9);
+        assert!((estimate.max_cost - 3.5).abs() < 1e-9);
+
+        let fallback = CostEstimate::new(None, 1_000_000, 0, 1.0);
+        assert!(fallback.pricing_model_id.is_none());
+        assert!((fallback.min_cost - fallback.max_cost).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pricing_keys() {
+        assert_eq!(
+            pricing_keys("us.anthropic.claude-sonnet-4-5-v1:0", "claude-sonnet-4-5"),
+            vec![
+                "us.anthropic.claude-sonnet-4-5-v1:0",
+                "anthropic.claude-sonnet-4-5-v1:0",
+                "claude-sonnet-4-5"
+            ]
+        );
+        assert_eq!(
+            pricing_keys("anthropic.claude-sonnet-4-5-v1:0", "claude-sonnet-4-5").len(),
+            2
+        );
+        assert_eq!(pricing_keys("gpt-4o", "gpt-4o"), vec!["gpt-4o"]);
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let report = DryRunReport::new(
+            "req-1",
+            "bedrock",
+            "claude-3-5-haiku",
+            "haiku-id".into(),
+            12,
+        )
+        .with_warnings(vec![ConversionWarning::ignored("top_k", "not supported")]);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["dry_run"], true);
+        assert_eq!(json["backend_model"], "haiku-id");
+        assert_eq!(json["warnings"][0]["field"], "top_k");
+        assert!(json.get("max_output_tokens").is_none());
+
+        assert_eq!(
+            estimate_json_tokens(&serde_json::json!({"text": "x".repeat(400)})),
+            102
+        );
+    }
+}
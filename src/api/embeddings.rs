@@ -0,0 +1,136 @@
+//! OpenAI Embeddings API endpoint
+//!
+//! `POST /v1/embeddings` serves OpenAI embedding requests with Bedrock's
+//! Titan (`amazon.titan-embed-*`) and Cohere (`cohere.embed-*`) models through
+//! InvokeModel. The model is a Bedrock model ID or an alias from the model
+//! mapping; `encoding_format: "base64"` returns little-endian f32 bytes as
+//! OpenAI does.
+
+use axum::{
+    extract::{Extension, State},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::time::Instant;
+
+use crate::api::chat_completions::OpenAIApiError;
+use crate::middleware::ApiKeyInfo;
+use crate::schemas::openai::{
+    CreateEmbeddingRequest, CreateEmbeddingResponse, Embedding, EmbeddingEncoding, EmbeddingUsage,
+    EmbeddingVector,
+};
+use crate::server::state::AppState;
+use crate::services::{EmbeddingFamily, EmbeddingRequest};
+
+/// Most inputs OpenAI accepts in one request
+const MAX_INPUTS: usize = 2048;
+
+/// POST /v1/embeddings - OpenAI-compatible embeddings
+pub async fn create_embeddings(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Json(request): Json<CreateEmbeddingRequest>,
+) -> Result<Json<CreateEmbeddingResponse>, OpenAIApiError> {
+    let start_time = Instant::now();
+
+    if !key_info.is_model_allowed(&request.model) {
+        return Err(OpenAIApiError::forbidden(format!(
+            "API key is not allowed to use model '{}'",
+            request.model
+        )));
+    }
+
+    let bedrock_model = state.bedrock.get_bedrock_model_id(&request.model);
+    if EmbeddingFamily::from_model_id(&bedrock_model).is_none() {
+        return Err(OpenAIApiError::bad_request(format!(
+            "Model '{}' is not an embedding model; use an amazon.titan-embed-* or cohere.embed-* model",
+            request.model
+        )));
+    }
+
+    let inputs = request.input.into_vec();
+    if inputs.is_empty() || inputs.len() > MAX_INPUTS {
+        return Err(OpenAIApiError::bad_request(format!(
+            "input must contain between 1 and {} items",
+            MAX_INPUTS
+        )));
+    }
+    if inputs.iter().any(|text| text.is_empty()) {
+        return Err(OpenAIApiError::bad_request("input must not contain empty strings"));
+    }
+    let input_count = inputs.len();
+
+    let output = state
+        .bedrock
+        .embed(EmbeddingRequest {
+            model_id: bedrock_model.clone(),
+            inputs,
+            dimensions: request.dimensions,
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, model = %bedrock_model, "Embedding call failed");
+            OpenAIApiError::from_bedrock_error(&e)
+        })?;
+
+    let encoding = request.encoding_format.unwrap_or(EmbeddingEncoding::Float);
+    let data = output
+        .embeddings
+        .into_iter()
+        .enumerate()
+        .map(|(index, vector)| Embedding {
+            object: "embedding".to_string(),
+            index: index as u32,
+            embedding: encode_vector(vector, encoding),
+        })
+        .collect();
+
+    tracing::info!(
+        model = %request.model,
+        bedrock_model = %bedrock_model,
+        input_count = input_count,
+        input_tokens = output.input_tokens,
+        duration_ms = start_time.elapsed().as_millis(),
+        "Embeddings request completed"
+    );
+
+    Ok(Json(CreateEmbeddingResponse {
+        object: "list".to_string(),
+        data,
+        model: request.model,
+        usage: EmbeddingUsage {
+            prompt_tokens: output.input_tokens,
+            total_tokens: output.input_tokens,
+        },
+    }))
+}
+
+/// Vector in the requested encoding
+fn encode_vector(vector: Vec<f32>, encoding: EmbeddingEncoding) -> EmbeddingVector {
+    match encoding {
+        EmbeddingEncoding::Float => EmbeddingVector::Float(vector),
+        EmbeddingEncoding::Base64 => {
+            let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+            EmbeddingVector::Base64(BASE64.encode(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encoding_is_little_endian_f32() {
+        let EmbeddingVector::Base64(encoded) = encode_vector(vec![1.0, -2.5], EmbeddingEncoding::Base64)
+        else {
+            panic!("expected base64");
+        };
+        let bytes = BASE64.decode(encoded).unwrap();
+        let decoded: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(decoded, vec![1.0, -2.5]);
+    }
+}
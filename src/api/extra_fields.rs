@@ -0,0 +1,170 @@
+//! Client-supplied `additionalModelRequestFields`
+//!
+//! Bedrock exposes new model features through `additionalModelRequestFields`
+//! before the proxy converts them natively. Keys holding the
+//! `bedrock_extra_fields` scope can send such fields as a JSON object in the
+//! `x-bedrock-extra-fields` header (or the `bedrock_extra_fields` field of a
+//! Messages request); they are deep-merged over the fields the converter
+//! produced. Every leaf must sit under a path of the configured allowlist, so
+//! operators decide which provider features clients may reach.
+
+use axum::http::HeaderMap;
+use serde_json::{Map, Value};
+
+use crate::config::BedrockExtraFieldsConfig;
+use crate::middleware::{ApiKeyInfo, SCOPE_BEDROCK_EXTRA_FIELDS};
+
+/// Header carrying extra fields as a JSON object
+pub const BEDROCK_EXTRA_FIELDS_HEADER: &str = "x-bedrock-extra-fields";
+
+/// Rejected extra fields
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExtraFieldsError {
+    #[error("Invalid x-bedrock-extra-fields: {0}")]
+    Invalid(String),
+    #[error("API key is not allowed to send Bedrock extra fields")]
+    NotAllowed,
+    #[error("Bedrock extra field '{0}' is not in the allowlist")]
+    PathNotAllowed(String),
+}
+
+/// Validated extra fields of a request, if it sent any
+///
+/// Header fields win over body fields on conflict.
+pub fn from_request(
+    headers: &HeaderMap,
+    body: Option<Value>,
+    key_info: &ApiKeyInfo,
+    config: &BedrockExtraFieldsConfig,
+) -> Result<Option<Value>, ExtraFieldsError> {
+    let header = match headers.get(BEDROCK_EXTRA_FIELDS_HEADER) {
+        Some(value) => {
+            let value = value
+                .to_str()
+                .map_err(|_| ExtraFieldsError::Invalid("not valid UTF-8".to_string()))?;
+            Some(
+                serde_json::from_str::<Value>(value)
+                    .map_err(|e| ExtraFieldsError::Invalid(e.to_string()))?,
+            )
+        }
+        None => None,
+    };
+
+    let fields = match (body, header) {
+        (None, None) => return Ok(None),
+        (Some(fields), None) | (None, Some(fields)) => fields,
+        (Some(mut body), Some(header)) => {
+            merge(&mut body, &header);
+            body
+        }
+    };
+    if !fields.is_object() {
+        return Err(ExtraFieldsError::Invalid(
+            "expected a JSON object".to_string(),
+        ));
+    }
+    if !key_info.has_scope(SCOPE_BEDROCK_EXTRA_FIELDS) {
+        return Err(ExtraFieldsError::NotAllowed);
+    }
+
+    let mut paths = Vec::new();
+    leaf_paths(&fields, String::new(), &mut paths);
+    if let Some(path) = paths.into_iter().find(|path| !config.allows(path)) {
+        return Err(ExtraFieldsError::PathNotAllowed(path));
+    }
+    Ok(Some(fields))
+}
+
+/// Deep-merge `extra` into `target`
+///
+/// Objects merge key by key; any other value replaces what `target` held.
+pub fn merge(target: &mut Value, extra: &Value) {
+    match (target, extra) {
+        (Value::Object(target), Value::Object(extra)) => {
+            for (key, value) in extra {
+                merge(
+                    target
+                        .entry(key.clone())
+                        .or_insert_with(|| Value::Object(Map::new())),
+                    value,
+                );
+            }
+        }
+        (target, extra) => *target = extra.clone(),
+    }
+}
+
+/// Dotted paths of the non-object values in `value`
+fn leaf_paths(value: &Value, prefix: String, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                leaf_paths(value, path, paths);
+            }
+        }
+        _ if !prefix.is_empty() => paths.push(prefix),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extra_fields_validation_and_merge() {
+        let config = BedrockExtraFieldsConfig {
+            allowed_paths: vec!["thinking".to_string(), "inference.top_k".to_string()],
+        };
+        let mut key_info = ApiKeyInfo::anonymous();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            BEDROCK_EXTRA_FIELDS_HEADER,
+            r#"{"thinking": {"type": "enabled", "budget_tokens": 2048}}"#
+                .parse()
+                .unwrap(),
+        );
+
+        assert_eq!(
+            from_request(&HeaderMap::new(), None, &key_info, &config),
+            Ok(None)
+        );
+        assert_eq!(
+            from_request(&headers, None, &key_info, &config),
+            Err(ExtraFieldsError::NotAllowed)
+        );
+
+        key_info.scopes = vec![SCOPE_BEDROCK_EXTRA_FIELDS.to_string()];
+        let body = json!({"thinking": {"budget_tokens": 1024}, "inference": {"top_k": 5}});
+        let fields = from_request(&headers, Some(body), &key_info, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fields["thinking"]["budget_tokens"], 2048);
+        assert_eq!(fields["inference"]["top_k"], 5);
+
+        let body = json!({"inference": {"top_p": 0.5}});
+        assert_eq!(
+            from_request(&HeaderMap::new(), Some(body), &key_info, &config),
+            Err(ExtraFieldsError::PathNotAllowed(
+                "inference.top_p".to_string()
+            ))
+        );
+        let body = json!(["thinking"]);
+        assert!(matches!(
+            from_request(&HeaderMap::new(), Some(body), &key_info, &config),
+            Err(ExtraFieldsError::Invalid(_))
+        ));
+
+        let mut target = json!({"anthropic_beta": ["a"], "thinking": {"type": "enabled"}});
+        merge(&mut target, &fields);
+        assert_eq!(target["anthropic_beta"], json!(["a"]));
+        assert_eq!(target["thinking"]["type"], "enabled");
+        assert_eq!(target["thinking"]["budget_tokens"], 2048);
+    }
+}
@@ -0,0 +1,535 @@
+//! Gemini -> Anthropic stream translation
+//!
+//! Turns Gemini `streamGenerateContent` chunks into the Messages API event
+//! sequence. `message_start` is held back until the first chunk so it can carry
+//! the prompt token count from `usageMetadata`. Consecutive text parts share one
+//! text block; each function call becomes a complete `tool_use` block. The final
+//! `message_delta` carries the mapped finish reason and Gemini's candidate token
+//! count.
+
+use axum::body::Bytes;
+use uuid::Uuid;
+
+use crate::converters::GeminiToAnthropicConverter;
+use crate::schemas::anthropic::StopReason;
+use crate::schemas::gemini::{FunctionCall, StreamChunk};
+
+use super::sse::{
+    EmptyObject, MessageStreamEvent, SseEncoder, StreamContentBlock, StreamDelta,
+    StreamMessageStart, StreamOutputUsage, StreamProgress, StreamStopDelta, StreamUsage,
+    CANCELLED_STOP_REASON, OUTPUT_LIMIT_STOP_REASON,
+};
+
+/// Translates one Gemini stream into Anthropic SSE frames
+#[derive(Debug)]
+pub struct GeminiMessageStream {
+    message_id: String,
+    model: String,
+    converter: GeminiToAnthropicConverter,
+    started: bool,
+    /// Index of the open text block, if any
+    text_block: Option<i32>,
+    next_index: i32,
+    input_tokens: i32,
+    finish_reason: StopReason,
+    used_tools: bool,
+}
+
+impl GeminiMessageStream {
+    pub fn new(message_id: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            message_id: message_id.into(),
+            model: model.into(),
+            converter: GeminiToAnthropicConverter::new(),
+            started: false,
+            text_block: None,
+            next_index: 0,
+            input_tokens: 0,
+            finish_reason: StopReason::EndTurn,
+            used_tools: false,
+        }
+    }
+
+    /// Prompt tokens reported by Gemini so far
+    pub fn input_tokens(&self) -> i32 {
+        self.input_tokens
+    }
+
+    /// Stop reason for the final `message_delta`
+    ///
+    /// Gemini reports `STOP` after function calls; Anthropic clients expect
+    /// `tool_use` so they run the tools.
+    pub fn stop_reason(&self) -> &'static str {
+        match self.finish_reason {
+            StopReason::EndTurn if self.used_tools => "tool_use",
+            StopReason::EndTurn => "end_turn",
+            StopReason::MaxTokens => "max_tokens",
+            StopReason::StopSequence => "stop_sequence",
+            StopReason::ToolUse => "tool_use",
+            StopReason::BudgetExhausted => "budget_exhausted",
+        }
+    }
+
+    /// Frames for one Gemini chunk
+    pub fn chunk_frames(
+        &mut self,
+        chunk: &StreamChunk,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+    ) -> Vec<Bytes> {
+        if let Some(ref usage) = chunk.usage_metadata {
+            self.input_tokens = usage.prompt_token_count;
+            // Early chunks omit the candidate count; keep estimating until it arrives
+            if usage.candidates_token_count > 0 {
+                progress.set_output_tokens(usage.candidates_token_count);
+            }
+        }
+
+        let mut frames = self.start_frames(sse, progress);
+        let Some(candidate) = chunk.candidates.first() else {
+            return frames;
+        };
+
+        for part in &candidate.content.parts {
+            if let Some(ref text) = part.text {
+                self.text_frames(text, sse, progress, &mut frames);
+            }
+            if let Some(ref call) = part.function_call {
+                self.tool_use_frames(call, sse, progress, &mut frames);
+            }
+        }
+
+        if let Some(ref reason) = candidate.finish_reason {
+            self.finish_reason = self.converter.convert_finish_reason(Some(reason));
+        }
+        frames
+    }
+
+    /// Frames ending a stream that completed normally
+    pub fn finish_frames(
+        &mut self,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+    ) -> Vec<Bytes> {
+        let stop_reason = self.stop_reason();
+        self.end_frames(stop_reason, sse, progress)
+    }
+
+    /// Frames ending a stream stopped through the cancel endpoint
+    pub fn cancel_frames(
+        &mut self,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+    ) -> Vec<Bytes> {
+        self.end_frames(CANCELLED_STOP_REASON, sse, progress)
+    }
+
+    /// Frames ending a stream stopped at the output watchdog ceiling
+    pub fn truncate_frames(
+        &mut self,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+    ) -> Vec<Bytes> {
+        self.end_frames(OUTPUT_LIMIT_STOP_REASON, sse, progress)
+    }
+
+    /// Frames ending a stream that failed after `message_start` may have been sent
+    pub fn error_frames(
+        &mut self,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+        message: &str,
+    ) -> Vec<Bytes> {
+        self.text_block = None;
+        let mut frames = self.start_frames(sse, progress);
+        frames.extend(progress.error_frames(sse, "api_error", message));
+        frames
+    }
+
+    /// `message_start` (and the client's `ping`) the first time it is called
+    fn start_frames(&mut self, sse: &mut SseEncoder, progress: &StreamProgress) -> Vec<Bytes> {
+        if self.started {
+            return Vec::new();
+        }
+        self.started = true;
+
+        let mut frames = vec![sse.message_event(&MessageStreamEvent::MessageStart {
+            message: StreamMessageStart {
+                usage: StreamUsage {
+                    input_tokens: self.input_tokens,
+                    ..Default::default()
+                },
+                ..StreamMessageStart::new(&self.message_id, &self.model)
+            },
+        })];
+        frames.extend(progress.ping_after_start(sse));
+        frames
+    }
+
+    fn text_frames(
+        &mut self,
+        text: &str,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+        frames: &mut Vec<Bytes>,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+
+        let index = match self.text_block {
+            Some(index) => index,
+            None => {
+                let index = self.open_block(progress);
+                self.text_block = Some(index);
+                frames.push(sse.message_event(&MessageStreamEvent::ContentBlockStart {
+                    index,
+                    content_block: StreamContentBlock::Text { text: "" },
+                }));
+                index
+            }
+        };
+
+        progress.record_output(text);
+        frames.push(sse.message_event(&MessageStreamEvent::ContentBlockDelta {
+            index,
+            delta: StreamDelta::TextDelta { text },
+        }));
+        frames.extend(progress.interim_usage(sse));
+    }
+
+    /// A complete `tool_use` block; Gemini sends function calls whole
+    fn tool_use_frames(
+        &mut self,
+        call: &FunctionCall,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+        frames: &mut Vec<Bytes>,
+    ) {
+        // disable_parallel_tool_use: only the first call is passed on
+        if self.used_tools && progress.single_tool_use() {
+            return;
+        }
+        if let Some(index) = self.text_block.take() {
+            progress.block_stopped(index);
+            frames.push(sse.message_event(&MessageStreamEvent::ContentBlockStop { index }));
+        }
+
+        let index = self.open_block(progress);
+        let id = format!("toolu_{}", Uuid::new_v4().simple());
+        let input = if call.args.is_null() {
+            "{}".to_string()
+        } else {
+            call.args.to_string()
+        };
+
+        frames.push(sse.message_event(&MessageStreamEvent::ContentBlockStart {
+            index,
+            content_block: StreamContentBlock::ToolUse {
+                id: &id,
+                name: &call.name,
+                input: EmptyObject {},
+            },
+        }));
+        progress.record_output(&input);
+        frames.push(sse.message_event(&MessageStreamEvent::ContentBlockDelta {
+            index,
+            delta: StreamDelta::InputJsonDelta {
+                partial_json: &input,
+            },
+        }));
+        progress.block_stopped(index);
+        frames.push(sse.message_event(&MessageStreamEvent::ContentBlockStop { index }));
+        self.used_tools = true;
+    }
+
+    fn open_block(&mut self, progress: &mut StreamProgress) -> i32 {
+        let index = self.next_index;
+        self.next_index += 1;
+        progress.block_started(index);
+        index
+    }
+
+    fn end_frames(
+        &mut self,
+        stop_reason: &str,
+        sse: &mut SseEncoder,
+        progress: &mut StreamProgress,
+    ) -> Vec<Bytes> {
+        self.text_block = None;
+        let mut frames = self.start_frames(sse, progress);
+        frames.extend(progress.close_open_blocks(sse));
+        frames.push(sse.message_event(&MessageStreamEvent::MessageDelta {
+            delta: StreamStopDelta {
+                stop_reason: Some(stop_reason),
+                stop_sequence: None,
+                container: progress.container(),
+            },
+            usage: StreamOutputUsage {
+                output_tokens: progress.output_tokens(),
+            },
+        }));
+        frames.push(sse.message_event(&MessageStreamEvent::MessageStop));
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::GeminiSseDecoder;
+
+    const TEXT_STREAM: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/gemini/text_stream.sse"
+    ));
+    const FUNCTION_CALL_STREAM: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/gemini/function_call_stream.sse"
+    ));
+    const MAX_TOKENS_STREAM: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/gemini/max_tokens_stream.sse"
+    ));
+
+    /// Decode a recorded body fed in small reads, as it arrives from the network
+    fn decode(recording: &str) -> Vec<StreamChunk> {
+        let mut decoder = GeminiSseDecoder::new();
+        let mut chunks = Vec::new();
+        for read in recording.as_bytes().chunks(7) {
+            decoder.push(read);
+            while let Some(chunk) = decoder.next_chunk() {
+                chunks.push(chunk);
+            }
+        }
+        chunks.extend(decoder.finish());
+        chunks
+    }
+
+    /// Replay a recording and return the `(event, payload)` pairs sent
+    fn replay(recording: &str) -> Vec<(String, serde_json::Value)> {
+        replay_with(recording, StreamProgress::new())
+    }
+
+    fn replay_with(
+        recording: &str,
+        mut progress: StreamProgress,
+    ) -> Vec<(String, serde_json::Value)> {
+        let mut sse = SseEncoder::new();
+        let mut stream = GeminiMessageStream::new("msg_1", "claude-sonnet-4-5");
+
+        let mut frames = Vec::new();
+        for chunk in decode(recording) {
+            frames.extend(stream.chunk_frames(&chunk, &mut sse, &mut progress));
+        }
+        frames.extend(stream.finish_frames(&mut sse, &mut progress));
+
+        frames
+            .iter()
+            .map(|frame| {
+                let text = std::str::from_utf8(frame).unwrap();
+                let (event, data) = text
+                    .strip_suffix("\n\n")
+                    .and_then(|t| t.strip_prefix("event: "))
+                    .and_then(|t| t.split_once("\ndata: "))
+                    .expect("event frame");
+                (event.to_string(), serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    fn names(events: &[(String, serde_json::Value)]) -> Vec<&str> {
+        events.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_decoder_handles_split_reads() {
+        let chunks = decode(TEXT_STREAM);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks[1].candidates[0].content.parts[0].text.as_deref(),
+            Some(" of France is Paris. ")
+        );
+
+        // Multi-byte characters split across reads and an unterminated final event
+        let mut decoder = GeminiSseDecoder::new();
+        let body = "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"caf\u{e9} \u{1f600}\"}]}}]}";
+        for read in body.as_bytes().chunks(3) {
+            decoder.push(read);
+            assert!(decoder.next_chunk().is_none());
+        }
+        let chunk = decoder.finish().expect("final event");
+        assert_eq!(
+            chunk.candidates[0].content.parts[0].text.as_deref(),
+            Some("caf\u{e9} \u{1f600}")
+        );
+    }
+
+    #[test]
+    fn test_text_stream_parity() {
+        let events = replay(TEXT_STREAM);
+        assert_eq!(
+            names(&events),
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+
+        let message = &events[0].1["message"];
+        assert_eq!(message["id"], "msg_1");
+        assert_eq!(message["model"], "claude-sonnet-4-5");
+        assert_eq!(
+            message["usage"],
+            serde_json::json!({"input_tokens": 9, "output_tokens": 0})
+        );
+
+        assert_eq!(
+            events[1].1,
+            serde_json::json!({
+                "type": "content_block_start", "index": 0,
+                "content_block": {"type": "text", "text": ""}
+            })
+        );
+        let text: String = events[2..5]
+            .iter()
+            .map(|(_, payload)| payload["delta"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            text,
+            "The capital of France is Paris. It is known for the Eiffel Tower."
+        );
+        assert_eq!(events[5].1["index"], 0);
+
+        assert_eq!(
+            events[6].1,
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": "end_turn", "stop_sequence": null},
+                "usage": {"output_tokens": 17}
+            })
+        );
+    }
+
+    #[test]
+    fn test_function_call_stream_parity() {
+        let events = replay(FUNCTION_CALL_STREAM);
+        assert_eq!(
+            names(&events),
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(events[0].1["message"]["usage"]["input_tokens"], 42);
+
+        // Text block closed before the first tool_use opens
+        assert_eq!(events[3].1["index"], 0);
+
+        let weather = &events[4].1;
+        assert_eq!(weather["index"], 1);
+        assert_eq!(weather["content_block"]["type"], "tool_use");
+        assert_eq!(weather["content_block"]["name"], "get_weather");
+        assert_eq!(weather["content_block"]["input"], serde_json::json!({}));
+        assert!(weather["content_block"]["id"]
+            .as_str()
+            .unwrap()
+            .starts_with("toolu_"));
+
+        let delta = &events[5].1["delta"];
+        assert_eq!(delta["type"], "input_json_delta");
+        let input: serde_json::Value =
+            serde_json::from_str(delta["partial_json"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            input,
+            serde_json::json!({"city": "Paris", "unit": "celsius"})
+        );
+
+        assert_eq!(events[7].1["index"], 2);
+        assert_eq!(events[7].1["content_block"]["name"], "get_time");
+        assert_ne!(
+            events[7].1["content_block"]["id"],
+            weather["content_block"]["id"]
+        );
+
+        assert_eq!(events[10].1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[10].1["usage"]["output_tokens"], 23);
+    }
+
+    #[test]
+    fn test_single_tool_use_drops_later_calls() {
+        let events = replay_with(
+            FUNCTION_CALL_STREAM,
+            StreamProgress::new().with_single_tool_use(true),
+        );
+        let tools: Vec<_> = events
+            .iter()
+            .filter(|(name, data)| {
+                name == "content_block_start" && data["content_block"]["type"] == "tool_use"
+            })
+            .collect();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].1["content_block"]["name"], "get_weather");
+        assert_eq!(
+            events[events.len() - 2].1["delta"]["stop_reason"],
+            "tool_use"
+        );
+    }
+
+    #[test]
+    fn test_max_tokens_stream_parity() {
+        let events = replay(MAX_TOKENS_STREAM);
+        assert_eq!(events[0].1["message"]["usage"]["input_tokens"], 5);
+        assert_eq!(
+            events[events.len() - 2].1["delta"]["stop_reason"],
+            "max_tokens"
+        );
+        assert_eq!(events[events.len() - 2].1["usage"]["output_tokens"], 8);
+    }
+
+    #[test]
+    fn test_cancel_and_error_endings() {
+        let mut sse = SseEncoder::new();
+        let mut progress = StreamProgress::new();
+        let mut stream = GeminiMessageStream::new("msg_1", "claude-sonnet-4-5");
+        let first = &decode(TEXT_STREAM)[0];
+        let _ = stream.chunk_frames(first, &mut sse, &mut progress);
+
+        let frames = stream.cancel_frames(&mut sse, &mut progress);
+        let text: Vec<_> = frames
+            .iter()
+            .map(|f| std::str::from_utf8(f).unwrap())
+            .collect();
+        assert!(text[0].starts_with("event: content_block_stop\n"));
+        assert!(text[1].contains("\"stop_reason\":\"cancelled\""));
+        assert!(text[2].starts_with("event: message_stop\n"));
+
+        // A failure before any chunk still opens the message
+        let mut progress = StreamProgress::new();
+        let mut stream = GeminiMessageStream::new("msg_2", "claude-sonnet-4-5");
+        let frames = stream.error_frames(&mut sse, &mut progress, "connection reset");
+        let text: Vec<_> = frames
+            .iter()
+            .map(|f| std::str::from_utf8(f).unwrap())
+            .collect();
+        assert!(text[0].starts_with("event: message_start\n"));
+        assert!(text[1].contains("\"stop_reason\":\"error\""));
+        assert!(text[2].starts_with("event: error\n"));
+    }
+}
@@ -0,0 +1,365 @@
+//! Gemini API endpoint
+//!
+//! `POST /v1beta/models/{model}:generateContent` (and `:streamGenerateContent`,
+//! `:countTokens`) lets clients built on Google's SDKs use the proxy. The body
+//! is translated into a Converse request with
+//! [`converse_gemini::from_gemini_request`], routed like any other request by
+//! the model routing table, and the backend's answer is translated back, so a
+//! Gemini client can reach Claude on Bedrock as well as Gemini itself.
+//!
+//! Streams are always sent as SSE, the `alt=sse` form every Google SDK asks
+//! for. Errors use Gemini's `{"error": {"code", "message", "status"}}` shape.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::api::dry_run::{self, DryRunReport};
+use crate::api::sse::{SseEncoder, SseResponse};
+use crate::converters::converse_gemini::{self, ConverseGeminiChunks};
+use crate::error::ErrorTaxonomy;
+use crate::middleware::{with_taxonomy, ApiKeyInfo, RequestMetrics, StreamMetrics};
+use crate::schemas::gemini::{GeminiError, GeminiErrorDetail, GeminiRequest};
+use crate::server::state::AppState;
+use crate::services::{
+    estimate_tokens, Backend, BedrockError, BedrockStreamError, ConverseRequest, ErrorClass,
+    LedgerEntry, RoutingOutcome,
+};
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Gemini-style API error
+#[derive(Debug)]
+pub struct GeminiApiError {
+    pub status: StatusCode,
+    pub error: GeminiError,
+    /// Error code and retryability, when known from the backend failure
+    pub taxonomy: Option<ErrorTaxonomy>,
+}
+
+impl GeminiApiError {
+    fn new(status: StatusCode, rpc_status: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            error: GeminiError {
+                error: GeminiErrorDetail {
+                    code: i32::from(status.as_u16()),
+                    message: message.into(),
+                    status: rpc_status.to_string(),
+                    details: Vec::new(),
+                },
+            },
+            taxonomy: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "INVALID_ARGUMENT", message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "PERMISSION_DENIED", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "NOT_FOUND", message)
+    }
+
+    pub fn rate_limited(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, "RESOURCE_EXHAUSTED", message)
+    }
+
+    pub fn internal_error(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL", message)
+    }
+
+    pub fn service_unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", message)
+    }
+
+    pub fn from_bedrock_error(err: &BedrockError) -> Self {
+        let message = err.client_message();
+        let error = match err.class() {
+            ErrorClass::InvalidRequest => Self::bad_request(message),
+            ErrorClass::PermissionDenied => Self::forbidden(message),
+            ErrorClass::RateLimited => Self::rate_limited(message),
+            ErrorClass::Overloaded => Self::service_unavailable(message),
+            ErrorClass::Server => Self::internal_error(message),
+        };
+        Self {
+            taxonomy: Some(ErrorTaxonomy::from_bedrock_error(err)),
+            ..error
+        }
+    }
+}
+
+impl IntoResponse for GeminiApiError {
+    fn into_response(self) -> Response {
+        with_taxonomy((self.status, Json(self.error)).into_response(), self.taxonomy)
+    }
+}
+
+// ============================================================================
+// Handler Implementation
+// ============================================================================
+
+/// Method named after the colon in `models/{model}:{method}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeminiMethod {
+    GenerateContent,
+    StreamGenerateContent,
+    CountTokens,
+}
+
+/// Split a `{model}:{method}` path segment
+fn parse_model_method(segment: &str) -> Option<(&str, GeminiMethod)> {
+    let (model, method) = segment.rsplit_once(':')?;
+    let method = match method {
+        "generateContent" => GeminiMethod::GenerateContent,
+        "streamGenerateContent" => GeminiMethod::StreamGenerateContent,
+        "countTokens" => GeminiMethod::CountTokens,
+        _ => return None,
+    };
+    (!model.is_empty()).then_some((model, method))
+}
+
+/// POST /v1beta/models/{model}:{method} - Gemini generateContent API
+///
+/// With `x-dry-run: true` the request is routed and converted but not sent.
+pub async fn generate_content(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
+    Path(segment): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<GeminiRequest>,
+) -> Result<Response, GeminiApiError> {
+    let start_time = Instant::now();
+    let request_id = Uuid::new_v4().to_string();
+
+    let (model, method) = parse_model_method(&segment).ok_or_else(|| {
+        GeminiApiError::not_found(format!(
+            "Unknown method in 'models/{}'; expected generateContent, streamGenerateContent or countTokens",
+            segment
+        ))
+    })?;
+
+    if !key_info.is_model_allowed(model) {
+        return Err(GeminiApiError::forbidden(format!(
+            "API key is not allowed to use model '{}'",
+            model
+        )));
+    }
+
+    let target = state.resolve_backend(model);
+    let backend = state.backend_for(&target).ok_or_else(|| {
+        GeminiApiError::bad_request(format!(
+            "Model '{}' is routed to backend '{}', which is not available for this endpoint",
+            model, target
+        ))
+    })?;
+    let backend_model = backend.resolve_model_id(model);
+    let converse_request = converse_gemini::from_gemini_request(&backend_model, &request)
+        .map_err(|e| GeminiApiError::bad_request(format!("Request conversion error: {}", e)))?;
+
+    tracing::info!(
+        request_id = %request_id,
+        model = %model,
+        backend = %target,
+        method = ?method,
+        content_count = request.contents.len(),
+        "Processing Gemini request"
+    );
+
+    let metrics = metrics.map(|Extension(metrics)| metrics);
+    if let Some(ref metrics) = metrics {
+        metrics.set_route(model, &target);
+        metrics.set_region(&state.backend_region(&target));
+    }
+
+    if method == GeminiMethod::CountTokens {
+        let total_tokens = backend
+            .count_tokens(&converse_request)
+            .await
+            .map_err(|e| GeminiApiError::from_bedrock_error(&e))?;
+        return Ok(Json(json!({ "totalTokens": total_tokens })).into_response());
+    }
+
+    let stream = method == GeminiMethod::StreamGenerateContent;
+
+    // Dry runs stop after conversion, before anything reaches a backend
+    if dry_run::requested(&headers) {
+        let report = DryRunReport::new(
+            &request_id,
+            &target,
+            model,
+            backend_model,
+            estimate_tokens(&converse_request),
+        )
+        .with_max_output_tokens(
+            request
+                .generation_config
+                .as_ref()
+                .and_then(|c| c.max_output_tokens),
+        )
+        .with_stream(stream);
+        return Ok(Json(report).into_response());
+    }
+
+    // Reported usage is reconciled against the backend's own metrics
+    let usage_entry = state.usage_ledger.entry(backend.as_ref(), &backend_model);
+
+    if stream {
+        let sse = create_gemini_streaming_response(
+            backend.as_ref(),
+            converse_request,
+            &request_id,
+            usage_entry,
+            metrics.as_ref().map(RequestMetrics::stream),
+        )
+        .await?;
+        state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
+        return Ok(sse
+            .buffered(&state.settings.stream_backpressure, state.stream_buffers.clone())
+            .into_response());
+    }
+
+    let output = backend.converse(converse_request).await.map_err(|e| {
+        tracing::error!(error = %e, backend = backend.name(), "Converse call failed");
+        GeminiApiError::from_bedrock_error(&e)
+    })?;
+    state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
+
+    let response = converse_gemini::to_gemini_response(&output, model);
+    let (input_tokens, output_tokens) = response
+        .usage_metadata
+        .as_ref()
+        .map_or((0, 0), |u| (u.prompt_token_count, u.candidates_token_count));
+    if let Some(entry) = usage_entry {
+        entry.record(input_tokens, output_tokens);
+    }
+    if let Some(metrics) = metrics {
+        metrics.record_tokens(input_tokens, output_tokens);
+    }
+
+    tracing::info!(
+        request_id = %request_id,
+        model = %model,
+        backend_model = %backend_model,
+        input_tokens = input_tokens,
+        output_tokens = output_tokens,
+        duration_ms = start_time.elapsed().as_millis(),
+        backend = backend.name(),
+        "Gemini request completed successfully"
+    );
+
+    Ok(Json(response).into_response())
+}
+
+// ============================================================================
+// Streaming Response Handler
+// ============================================================================
+
+/// Stream a Converse backend's answer as Gemini SSE chunks
+async fn create_gemini_streaming_response(
+    backend: &dyn Backend,
+    request: ConverseRequest,
+    request_id: &str,
+    usage_entry: Option<LedgerEntry>,
+    mut metrics: Option<StreamMetrics>,
+) -> Result<SseResponse, GeminiApiError> {
+    let mut stream_response = backend.converse_stream(request).await.map_err(|e| {
+        tracing::error!(error = %e, backend = backend.name(), "ConverseStream call failed");
+        GeminiApiError::from_bedrock_error(&e)
+    })?;
+    let req_id = request_id.to_string();
+
+    let frames = async_stream::stream! {
+        let mut sse = SseEncoder::new();
+        let mut chunks = ConverseGeminiChunks::new();
+
+        loop {
+            match stream_response.recv().await {
+                Ok(Some(event)) => {
+                    if let Some(chunk) = chunks.event_chunk(&event) {
+                        if let Some(ref mut metrics) = metrics {
+                            metrics.record_output();
+                        }
+                        yield sse.data(&chunk);
+                    }
+                }
+                Ok(None) => {
+                    if let Some(chunk) = chunks.finish_chunk() {
+                        yield sse.data(&chunk);
+                    }
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!(request_id = %req_id, error = %e, "Gemini stream failed");
+                    yield sse.data(&stream_error(&e).error);
+                    break;
+                }
+            }
+        }
+
+        let (input_tokens, output_tokens) = chunks.usage();
+        if let Some(entry) = usage_entry {
+            entry.record(input_tokens, output_tokens);
+        }
+        if let Some(mut metrics) = metrics {
+            metrics.record_tokens(input_tokens, output_tokens);
+        }
+        tracing::debug!(
+            request_id = %req_id,
+            input_tokens = input_tokens,
+            output_tokens = output_tokens,
+            "Gemini stream completed"
+        );
+    };
+
+    Ok(SseResponse::new(frames))
+}
+
+/// Gemini error for a failure after the stream started
+fn stream_error(err: &BedrockStreamError) -> GeminiApiError {
+    match err {
+        BedrockStreamError::Backend(inner) => GeminiApiError::from_bedrock_error(inner),
+        other => GeminiApiError::internal_error(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model_method() {
+        assert_eq!(
+            parse_model_method("gemini-2.0-flash:generateContent"),
+            Some(("gemini-2.0-flash", GeminiMethod::GenerateContent))
+        );
+        assert_eq!(
+            parse_model_method("claude-sonnet-4-5:streamGenerateContent"),
+            Some(("claude-sonnet-4-5", GeminiMethod::StreamGenerateContent))
+        );
+        assert_eq!(parse_model_method("gemini-2.0-flash:embedContent"), None);
+        assert_eq!(parse_model_method("gemini-2.0-flash"), None);
+        assert_eq!(parse_model_method(":generateContent"), None);
+    }
+
+    #[test]
+    fn test_errors_use_rpc_status() {
+        let err = GeminiApiError::from_bedrock_error(&BedrockError::Throttled("slow down".to_string()));
+        assert_eq!(err.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.error.error.status, "RESOURCE_EXHAUSTED");
+        assert_eq!(err.error.error.code, 429);
+    }
+}
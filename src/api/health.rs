@@ -3,7 +3,12 @@
 //! This module provides health check endpoints for monitoring
 //! and container orchestration (Kubernetes, ECS, etc.)
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde::Serialize;
 
 use crate::server::state::AppState;
@@ -111,6 +116,19 @@ pub async fn liveness() -> Json<LivenessResponse> {
     Json(LivenessResponse { alive: true })
 }
 
+/// Prometheus metrics endpoint
+///
+/// Returns request counts, latency histograms, token counters and streaming
+/// time to first token in the Prometheus text format.
+///
+/// GET /metrics
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, state.metrics.content_type())],
+        state.metrics.render(),
+    )
+}
+
 /// Response for PTC health check endpoint
 #[derive(Serialize)]
 pub struct PtcHealthResponse {
@@ -143,7 +161,12 @@ pub async fn ptc_health(State(state): State<AppState>) -> (StatusCode, Json<PtcH
         );
     }
 
-    // Check PTC service health
+    ptc_service_health(&state).await
+}
+
+/// Report Docker and session status of the running PTC service
+#[cfg(feature = "ptc")]
+async fn ptc_service_health(state: &AppState) -> (StatusCode, Json<PtcHealthResponse>) {
     match &state.ptc_service {
         Some(ptc) => {
             let health = ptc.health_check().await;
@@ -185,3 +208,18 @@ pub async fn ptc_health(State(state): State<AppState>) -> (StatusCode, Json<PtcH
         ),
     }
 }
+
+/// PTC is enabled in the settings but this build lacks the `ptc` feature
+#[cfg(not(feature = "ptc"))]
+async fn ptc_service_health(_state: &AppState) -> (StatusCode, Json<PtcHealthResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(PtcHealthResponse {
+            status: "not_compiled".to_string(),
+            docker: "not_checked".to_string(),
+            docker_version: None,
+            active_sessions: 0,
+            ptc_enabled: true,
+        }),
+    )
+}
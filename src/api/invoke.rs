@@ -0,0 +1,260 @@
+//! Unified inference endpoint
+//!
+//! `POST /v1/invoke` accepts an Anthropic Messages or OpenAI Chat Completions
+//! request and dispatches it to the matching pipeline, so clients in
+//! different ecosystems can share one URL. The format comes from the
+//! `x-api-format` header when present; otherwise it is inferred from the body
+//! shape (see [`ApiFormat::detect`]). Responses and errors are in the format
+//! of the detected API.
+//!
+//! Gemini `generateContent` bodies are recognized but not served here: they
+//! name the model in the URL, so they go to `/v1beta/models/{model}:generateContent`.
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::Value;
+
+use crate::api::chat_completions::{self, OpenAIApiError};
+use crate::api::messages::{self, ApiError};
+use crate::middleware::{ApiKeyInfo, RequestMetrics};
+use crate::schemas::anthropic::MessageRequest;
+use crate::schemas::openai::ChatCompletionRequest;
+use crate::server::state::AppState;
+
+/// Header naming the request format explicitly
+pub const API_FORMAT_HEADER: &str = "x-api-format";
+
+/// Inbound request format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiFormat {
+    /// Anthropic Messages API
+    Anthropic,
+    /// OpenAI Chat Completions API
+    OpenAI,
+    /// Gemini generateContent API
+    Gemini,
+}
+
+/// Top-level fields only the Anthropic Messages API uses
+const ANTHROPIC_FIELDS: &[&str] = &[
+    "system",
+    "stop_sequences",
+    "top_k",
+    "thinking",
+    "container",
+    "anthropic_version",
+];
+
+/// Top-level fields only the OpenAI Chat Completions API uses
+const OPENAI_FIELDS: &[&str] = &[
+    "max_completion_tokens",
+    "n",
+    "response_format",
+    "stream_options",
+    "frequency_penalty",
+    "presence_penalty",
+    "logprobs",
+    "logit_bias",
+    "stop",
+    "user",
+    "store",
+];
+
+impl ApiFormat {
+    /// Parse a format name (`anthropic`, `openai` or `gemini`)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "anthropic" | "messages" => Some(Self::Anthropic),
+            "openai" | "chat" | "chat_completions" => Some(Self::OpenAI),
+            "gemini" => Some(Self::Gemini),
+            _ => None,
+        }
+    }
+
+    /// Infer the format of a request body
+    ///
+    /// Gemini requests carry `contents` instead of `messages`. Between the
+    /// other two, fields, roles and content parts only one API defines
+    /// decide; an `anthropic-version` header or an ambiguous body (a plain
+    /// user message fits both) counts as Anthropic.
+    pub fn detect(headers: &HeaderMap, body: &Value) -> Self {
+        if body.get("contents").is_some() && body.get("messages").is_none() {
+            return Self::Gemini;
+        }
+        if headers.contains_key("anthropic-version") {
+            return Self::Anthropic;
+        }
+
+        let has_any = |fields: &[&str]| fields.iter().any(|f| body.get(*f).is_some());
+        if has_any(ANTHROPIC_FIELDS) {
+            return Self::Anthropic;
+        }
+        if has_any(OPENAI_FIELDS) {
+            return Self::OpenAI;
+        }
+
+        let tools = body.get("tools").and_then(Value::as_array);
+        if tools.is_some_and(|tools| tools.iter().any(|t| t.get("function").is_some())) {
+            return Self::OpenAI;
+        }
+        if tools.is_some_and(|tools| tools.iter().any(|t| t.get("input_schema").is_some())) {
+            return Self::Anthropic;
+        }
+
+        let messages = body.get("messages").and_then(Value::as_array);
+        if messages.is_some_and(|messages| messages.iter().any(is_openai_message)) {
+            return Self::OpenAI;
+        }
+        Self::Anthropic
+    }
+}
+
+/// Whether a message uses a role or content part only OpenAI defines
+fn is_openai_message(message: &Value) -> bool {
+    let role = message.get("role").and_then(Value::as_str);
+    if matches!(role, Some("system" | "developer" | "tool" | "function")) {
+        return true;
+    }
+    if message.get("tool_calls").is_some() || message.get("name").is_some() {
+        return true;
+    }
+    message
+        .get("content")
+        .and_then(Value::as_array)
+        .is_some_and(|parts| {
+            parts.iter().any(|part| {
+                matches!(
+                    part.get("type").and_then(Value::as_str),
+                    Some("image_url" | "input_audio" | "file")
+                )
+            })
+        })
+}
+
+/// POST /v1/invoke - Dispatch an Anthropic or OpenAI request by its shape
+pub async fn invoke(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let format = match headers.get(API_FORMAT_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(ApiFormat::parse) {
+            Some(format) => format,
+            None => {
+                return ApiError::bad_request(format!(
+                    "Invalid {} value: expected anthropic, openai or gemini",
+                    API_FORMAT_HEADER
+                ))
+                .into_response()
+            }
+        },
+        None => ApiFormat::detect(&headers, &body),
+    };
+
+    tracing::debug!(format = ?format, "Dispatching unified invoke request");
+
+    match format {
+        ApiFormat::Anthropic => match serde_json::from_value::<MessageRequest>(body) {
+            Ok(request) => {
+                messages::create_message(
+                    State(state),
+                    Extension(key_info),
+                    metrics,
+                    headers,
+                    Json(request),
+                )
+                .await
+                .into_response()
+            }
+            Err(e) => ApiError::bad_request(format!("Invalid Anthropic Messages request: {}", e))
+                .into_response(),
+        },
+        ApiFormat::OpenAI => match serde_json::from_value::<ChatCompletionRequest>(body) {
+            Ok(request) => chat_completions::chat_completions(
+                State(state),
+                Extension(key_info),
+                metrics,
+                headers,
+                Json(request),
+            )
+            .await
+            .into_response(),
+            Err(e) => OpenAIApiError::bad_request(format!(
+                "Invalid OpenAI Chat Completions request: {}",
+                e
+            ))
+            .into_response(),
+        },
+        ApiFormat::Gemini => {
+            ApiError::bad_request(
+                "Gemini generateContent requests are served on /v1beta/models/{model}:generateContent",
+            )
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_api_format() {
+        let none = HeaderMap::new();
+        let detect = |body: Value| ApiFormat::detect(&none, &body);
+
+        let plain = json!({"model": "m", "messages": [{"role": "user", "content": "hi"}]});
+        assert_eq!(detect(plain), ApiFormat::Anthropic);
+        assert_eq!(
+            detect(json!({"contents": [{"parts": [{"text": "hi"}]}]})),
+            ApiFormat::Gemini
+        );
+        assert_eq!(
+            detect(json!({"model": "m", "system": "be brief", "messages": []})),
+            ApiFormat::Anthropic
+        );
+        assert_eq!(
+            detect(json!({"model": "m", "max_completion_tokens": 10, "messages": []})),
+            ApiFormat::OpenAI
+        );
+        assert_eq!(
+            detect(json!({"model": "m", "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": "hi"}
+            ]})),
+            ApiFormat::OpenAI
+        );
+        assert_eq!(
+            detect(json!({"model": "m", "messages": [], "tools": [
+                {"type": "function", "function": {"name": "f", "parameters": {}}}
+            ]})),
+            ApiFormat::OpenAI
+        );
+        assert_eq!(
+            detect(
+                json!({"model": "m", "messages": [{"role": "user", "content": [
+                    {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+                ]}]})
+            ),
+            ApiFormat::OpenAI
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+        let openai_shaped = json!({"model": "m", "n": 1, "messages": []});
+        assert_eq!(
+            ApiFormat::detect(&headers, &openai_shaped),
+            ApiFormat::Anthropic
+        );
+
+        assert_eq!(ApiFormat::parse("OpenAI"), Some(ApiFormat::OpenAI));
+        assert_eq!(ApiFormat::parse("bedrock"), None);
+    }
+}
@@ -0,0 +1,438 @@
+//! Self-service child key endpoint
+//!
+//! A stored API key holding the `child_keys` scope can mint child keys via
+//! `POST /v1/keys`. Children never exceed their parent: scopes and model
+//! restrictions must be a subset of the parent's (including the trial model
+//! list for trial parents), the rate limit and expiry are capped at the
+//! parent's, the IP allowlist is inherited unchanged, and the monthly budget
+//! is a slice of the parent's remaining month-to-date budget. The slice is
+//! moved out of the parent's budget with a compare-and-swap on the budget that
+//! was checked, so a parent and its children together never spend more than
+//! the parent was given, even when children are minted concurrently.
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::config::TrialConfig;
+use crate::db::models::{ApiKey, TRIAL_SERVICE_TIER};
+use crate::db::StorageError;
+use crate::error::ApiError;
+use crate::middleware::auth::{extract_api_key_from_headers, ApiKeyInfo, SCOPE_CHILD_KEYS};
+use crate::server::state::AppState;
+use crate::services::provider::model_matches_pattern;
+
+/// Request body for `POST /v1/keys`
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateChildKeyRequest {
+    /// Human-readable name for the child key
+    pub name: String,
+
+    /// Scopes for the child (must be held by the parent)
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// Monthly budget slice in USD (required when the parent has a budget)
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+
+    /// Rate limit (defaults to, and capped at, the parent's)
+    #[serde(default)]
+    pub rate_limit: Option<i32>,
+
+    /// Model patterns (defaults to the parent's; must be a subset if the parent is restricted)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+
+    /// Lifetime in days (capped at the parent's expiry)
+    #[serde(default)]
+    pub expires_in_days: Option<i64>,
+}
+
+/// Model patterns the parent is actually limited to
+///
+/// Trial keys without an explicit list are restricted to the trial models at
+/// authentication time, so the same list bounds their children.
+fn effective_allowed_models<'a>(parent: &'a ApiKey, trial: &'a TrialConfig) -> &'a [String] {
+    if parent.service_tier == TRIAL_SERVICE_TIER && parent.allowed_models.is_empty() {
+        &trial.allowed_models
+    } else {
+        &parent.allowed_models
+    }
+}
+
+/// Build a child key from its parent, enforcing the inheritance rules
+pub fn build_child_key(
+    parent: &ApiKey,
+    request: &CreateChildKeyRequest,
+    trial: &TrialConfig,
+    now: i64,
+) -> Result<ApiKey, ApiError> {
+    if request.name.trim().is_empty() {
+        return Err(ApiError::InvalidRequest("'name' is required".to_string()));
+    }
+
+    let parent_has_all_scopes = parent.scopes.iter().any(|s| s == "*");
+    if let Some(scope) = request
+        .scopes
+        .iter()
+        .find(|s| !parent_has_all_scopes && !parent.scopes.contains(s))
+    {
+        return Err(ApiError::Forbidden(format!(
+            "Cannot grant scope '{}' not held by the parent key",
+            scope
+        )));
+    }
+
+    let monthly_budget = match parent.monthly_budget {
+        Some(budget) => {
+            let remaining = (budget - parent.budget_used_mtd).max(0.0);
+            let Some(slice) = request.monthly_budget else {
+                return Err(ApiError::InvalidRequest(format!(
+                    "'monthly_budget' is required; the parent key has ${:.2} remaining",
+                    remaining
+                )));
+            };
+            if slice <= 0.0 || slice > remaining {
+                return Err(ApiError::InvalidRequest(format!(
+                    "'monthly_budget' must be positive and at most the parent's remaining budget (${:.2})",
+                    remaining
+                )));
+            }
+            Some(slice)
+        }
+        None => match request.monthly_budget {
+            Some(slice) if slice <= 0.0 => {
+                return Err(ApiError::InvalidRequest(
+                    "'monthly_budget' must be positive".to_string(),
+                ))
+            }
+            other => other,
+        },
+    };
+
+    let rate_limit = match request.rate_limit {
+        Some(limit) if limit <= 0 => {
+            return Err(ApiError::InvalidRequest(
+                "'rate_limit' must be positive".to_string(),
+            ))
+        }
+        Some(limit) if parent.rate_limit > 0 => limit.min(parent.rate_limit),
+        Some(limit) => limit,
+        None => parent.rate_limit,
+    };
+
+    // A child pattern is allowed if every model it matches is matched by one
+    // of the parent's patterns
+    let parent_models = effective_allowed_models(parent, trial);
+    let allowed_models = if request.allowed_models.is_empty() {
+        parent_models.to_vec()
+    } else if parent_models.is_empty() {
+        request.allowed_models.clone()
+    } else if let Some(model) = request.allowed_models.iter().find(|m| {
+        !parent_models
+            .iter()
+            .any(|pattern| model_matches_pattern(m, pattern))
+    }) {
+        return Err(ApiError::Forbidden(format!(
+            "Cannot allow model pattern '{}' not allowed for the parent key",
+            model
+        )));
+    } else {
+        request.allowed_models.clone()
+    };
+
+    let requested_expiry = match request.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err(ApiError::InvalidRequest(
+                "'expires_in_days' must be positive".to_string(),
+            ))
+        }
+        Some(days) => Some(now + days * 86_400),
+        None => None,
+    };
+    let expires_at = match (parent.expires_at, requested_expiry) {
+        (Some(parent_exp), Some(exp)) => Some(parent_exp.min(exp)),
+        (parent_exp, exp) => exp.or(parent_exp),
+    };
+
+    Ok(ApiKey {
+        api_key: format!("sk-{}", uuid::Uuid::new_v4()),
+        user_id: parent.user_id.clone(),
+        name: request.name.trim().to_string(),
+        created_at: now,
+        updated_at: None,
+        is_active: true,
+        rate_limit,
+        service_tier: parent.service_tier.clone(),
+        metadata: HashMap::new(),
+        owner_name: parent.owner_name.clone(),
+        role: None,
+        monthly_budget,
+        budget_used: 0.0,
+        budget_used_mtd: 0.0,
+        budget_mtd_month: None,
+        deactivated_reason: None,
+        tpm_limit: parent.tpm_limit,
+        scopes: request.scopes.clone(),
+        expires_at,
+        allowed_models,
+        allowed_ips: parent.allowed_ips.clone(),
+        parent_key: Some(parent.api_key.clone()),
+        last_used_at: None,
+        request_count: 0,
+        recent_source_ips: Vec::new(),
+        ptc_max_iterations: parent.ptc_max_iterations,
+        ptc_max_tokens_per_iteration: parent.ptc_max_tokens_per_iteration,
+        ptc_session_token_budget: parent.ptc_session_token_budget,
+        inference_preset: parent.inference_preset.clone(),
+    })
+}
+
+/// POST /v1/keys - Mint a child key from the calling key
+pub async fn create_child_key(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    headers: HeaderMap,
+    Json(request): Json<CreateChildKeyRequest>,
+) -> Result<(StatusCode, Json<ApiKey>), ApiError> {
+    if !key_info.has_scope(SCOPE_CHILD_KEYS) {
+        return Err(ApiError::Forbidden(format!(
+            "API key is missing the required '{}' scope.",
+            SCOPE_CHILD_KEYS
+        )));
+    }
+
+    let map_err = |e: StorageError| ApiError::DatabaseError(e.to_string());
+
+    // The parent must be a stored key (not the master or an ephemeral key)
+    let parent = match extract_api_key_from_headers(&headers) {
+        Some(raw_key) => state.storage.get_api_key(&raw_key).await.map_err(map_err)?,
+        None => None,
+    }
+    .ok_or_else(|| {
+        ApiError::Forbidden("Child keys can only be minted from a stored API key".to_string())
+    })?;
+
+    let child = build_child_key(&parent, &request, &state.settings.trial, Utc::now().timestamp())?;
+
+    // Move the child's slice out of the parent's budget before the child
+    // exists. The write only applies to the budget read above, so concurrent
+    // mints cannot both spend the same remaining balance.
+    let reserved = parent.monthly_budget.zip(parent_budget_after(&parent, &child));
+    if let Some((budget, after)) = reserved {
+        let swapped = state
+            .storage
+            .swap_monthly_budget(&parent.api_key, budget, after)
+            .await
+            .map_err(map_err)?;
+        if !swapped {
+            return Err(ApiError::InvalidRequest(
+                "The parent key's budget changed while minting the child key; retry the request"
+                    .to_string(),
+            ));
+        }
+    }
+    if let Err(e) = state.storage.create_api_key(&child).await {
+        if let Some((budget, after)) = reserved {
+            let restored = state
+                .storage
+                .swap_monthly_budget(&parent.api_key, after, budget)
+                .await;
+            if !matches!(restored, Ok(true)) {
+                tracing::error!(
+                    error = ?restored.err(),
+                    parent = %key_info.api_key,
+                    slice = ?child.monthly_budget,
+                    "Failed to return a child key's budget slice to its parent"
+                );
+            }
+        }
+        return Err(map_err(e));
+    }
+
+    tracing::info!(
+        user_id = %child.user_id,
+        parent = %key_info.api_key,
+        name = %child.name,
+        monthly_budget = ?child.monthly_budget,
+        "Minted child API key"
+    );
+
+    Ok((StatusCode::CREATED, Json(child)))
+}
+
+/// Parent's monthly budget once the child's slice is moved out (None = unchanged)
+pub fn parent_budget_after(parent: &ApiKey, child: &ApiKey) -> Option<f64> {
+    match (parent.monthly_budget, child.monthly_budget) {
+        (Some(budget), Some(slice)) => Some((budget - slice).max(0.0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    fn parent() -> ApiKey {
+        let item = HashMap::from([
+            ("api_key".to_string(), AttributeValue::S("sk-parent".to_string())),
+            ("user_id".to_string(), AttributeValue::S("platform".to_string())),
+            ("is_active".to_string(), AttributeValue::Bool(true)),
+            ("rate_limit".to_string(), AttributeValue::N("50".to_string())),
+            ("monthly_budget".to_string(), AttributeValue::N("100".to_string())),
+            ("budget_used_mtd".to_string(), AttributeValue::N("40".to_string())),
+            ("expires_at".to_string(), AttributeValue::N((10 * 86_400).to_string())),
+            (
+                "scopes".to_string(),
+                AttributeValue::Ss(vec![SCOPE_CHILD_KEYS.to_string(), "backend_override".to_string()]),
+            ),
+        ]);
+        ApiKey::from_dynamodb(&item).unwrap()
+    }
+
+    #[test]
+    fn test_child_inherits_parent_limits() {
+        let mut unbudgeted = parent();
+        unbudgeted.monthly_budget = None;
+        let child = build_child_key(
+            &unbudgeted,
+            &CreateChildKeyRequest {
+                name: "app".to_string(),
+                ..Default::default()
+            },
+            &TrialConfig::default(),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(child.parent_key.as_deref(), Some("sk-parent"));
+        assert_eq!(child.user_id, "platform");
+        assert_eq!(child.monthly_budget, None);
+        assert_eq!(parent_budget_after(&unbudgeted, &child), None);
+        assert_eq!(child.rate_limit, 50);
+        assert_eq!(child.expires_at, Some(10 * 86_400));
+        assert!(child.scopes.is_empty());
+    }
+
+    #[test]
+    fn test_child_capped_by_parent() {
+        let child = build_child_key(
+            &parent(),
+            &CreateChildKeyRequest {
+                name: "app".to_string(),
+                scopes: vec!["backend_override".to_string()],
+                monthly_budget: Some(20.0),
+                rate_limit: Some(500),
+                expires_in_days: Some(30),
+                ..Default::default()
+            },
+            &TrialConfig::default(),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(child.scopes, vec!["backend_override"]);
+        assert_eq!(child.monthly_budget, Some(20.0));
+        // The slice comes out of the parent's budget
+        assert_eq!(parent_budget_after(&parent(), &child), Some(80.0));
+        assert_eq!(child.rate_limit, 50);
+        assert_eq!(child.expires_at, Some(10 * 86_400));
+    }
+
+    #[test]
+    fn test_child_cannot_escalate() {
+        let trial = TrialConfig::default();
+        let scope_escalation = CreateChildKeyRequest {
+            name: "app".to_string(),
+            scopes: vec!["admin".to_string()],
+            monthly_budget: Some(10.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_child_key(&parent(), &scope_escalation, &trial, 0),
+            Err(ApiError::Forbidden(_))
+        ));
+
+        let over_budget = CreateChildKeyRequest {
+            name: "app".to_string(),
+            monthly_budget: Some(61.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_child_key(&parent(), &over_budget, &trial, 0),
+            Err(ApiError::InvalidRequest(_))
+        ));
+
+        let no_slice = CreateChildKeyRequest {
+            name: "app".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_child_key(&parent(), &no_slice, &trial, 0),
+            Err(ApiError::InvalidRequest(_))
+        ));
+
+        let mut restricted = parent();
+        restricted.allowed_models = vec!["claude-3-5-haiku-*".to_string()];
+        let model_escalation = CreateChildKeyRequest {
+            name: "app".to_string(),
+            allowed_models: vec!["claude-opus-*".to_string()],
+            monthly_budget: Some(10.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_child_key(&restricted, &model_escalation, &trial, 0),
+            Err(ApiError::Forbidden(_))
+        ));
+
+        // Broader patterns than the parent's are escalation too
+        let broader = CreateChildKeyRequest {
+            allowed_models: vec!["claude-*".to_string()],
+            ..model_escalation
+        };
+        assert!(matches!(
+            build_child_key(&restricted, &broader, &trial, 0),
+            Err(ApiError::Forbidden(_))
+        ));
+
+        // Trial parents are bound by the trial model list
+        let mut trial_parent = parent();
+        trial_parent.service_tier = TRIAL_SERVICE_TIER.to_string();
+        assert!(matches!(
+            build_child_key(&trial_parent, &broader, &trial, 0),
+            Err(ApiError::Forbidden(_))
+        ));
+        let inherited = build_child_key(&trial_parent, &small_child(), &trial, 0).unwrap();
+        assert_eq!(inherited.allowed_models, trial.allowed_models);
+    }
+
+    #[test]
+    fn test_child_patterns_within_parent_patterns() {
+        let mut restricted = parent();
+        restricted.allowed_models = vec!["claude-3-5-haiku-*".to_string()];
+        let request = CreateChildKeyRequest {
+            allowed_models: vec![
+                "claude-3-5-haiku-20241022".to_string(),
+                "claude-3-5-haiku-2024*".to_string(),
+            ],
+            ..small_child()
+        };
+        let child = build_child_key(&restricted, &request, &TrialConfig::default(), 0).unwrap();
+        assert_eq!(child.allowed_models, request.allowed_models);
+    }
+
+    fn small_child() -> CreateChildKeyRequest {
+        CreateChildKeyRequest {
+            name: "app".to_string(),
+            monthly_budget: Some(10.0),
+            ..Default::default()
+        }
+    }
+}
@@ -4,76 +4,64 @@
 //! It handles request conversion, Bedrock/Gemini API calls, and response conversion.
 //! Supports both streaming and non-streaming responses using the Converse API or Gemini API.
 
-use aws_sdk_bedrockruntime::types::{
-    ContentBlock as SdkContentBlock, ConversationRole, ConverseStreamOutput,
-    InferenceConfiguration, Message as SdkMessage, SystemContentBlock, Tool as SdkTool,
-    ToolConfiguration, ToolInputSchema as SdkToolInputSchema, ToolResultContentBlock,
-    ToolResultStatus, ToolSpecification, ToolUseBlock,
-};
+use aws_sdk_bedrockruntime::types::{ContentBlock as SdkContentBlock, ConverseStreamOutput};
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    response::{
-        sse::{Event, Sse},
-        IntoResponse, Response,
-    },
+    extract::{Extension, Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
-use std::convert::Infallible;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::converters::{
-    AnthropicToGeminiConverter, ConversionError, GeminiToAnthropicConverter,
+use crate::api::claude_code;
+use crate::api::client_profile::{ClientProfile, CLIENT_PROFILE_HEADER};
+use crate::api::dry_run::{self, DryRunReport};
+use crate::api::extra_fields::{self, ExtraFieldsError, BEDROCK_EXTRA_FIELDS_HEADER};
+#[cfg(feature = "gemini")]
+use crate::api::gemini_stream::GeminiMessageStream;
+use crate::api::sse::{
+    EmptyObject, MessageStreamEvent, SseEncoder, SseResponse, StreamContentBlock, StreamDelta,
+    StreamMessageStart, StreamOutputUsage, StreamProgress, StreamStopDelta, CANCELLED_STOP_REASON,
+    ERROR_STOP_REASON, OUTPUT_LIMIT_STOP_REASON,
 };
+use crate::converters::bedrock_sdk::{self, document_to_json};
+use crate::converters::warnings::{self as conversion_warnings, CONVERSION_WARNINGS_HEADER};
+use crate::converters::{AnthropicToBedrockConverter, ApiFormat, ConversionError};
+#[cfg(feature = "gemini")]
+use crate::converters::GeminiToAnthropicConverter;
 use crate::schemas::anthropic::{
-    ContentBlock, ErrorResponse, Message, MessageContent, MessageRequest, MessageResponse,
-    StopReason, SystemContent, ToolResultValue, Usage,
+    Container, ContentBlock, ErrorResponse, MessageContent, MessageRequest,
+    MessageResponse, StopReason, SystemContent, ToolResultValue, Usage,
+};
+use crate::error::ErrorTaxonomy;
+use crate::middleware::{
+    with_taxonomy, ApiKeyInfo, ModelDowngrade, RequestMetrics, BUDGET_DOWNGRADED_FROM_HEADER,
+    SCOPE_BACKEND_OVERRIDE,
 };
 use crate::server::state::AppState;
-use crate::services::{BedrockError, ConverseRequest};
+use crate::services::{
+    coalescing_key, count_prompt, model_capabilities, prompt_dumps, pt_governor, select_tier, stream_assembly, Backend, BackendTarget, BedrockError, BedrockStreamError, ConverseRequest,
+    EffectiveTier, ErrorClass, PriorityError, PromptTemplateError, RequestCoalescer, RequestPriority, FLAG_EVAL_CAPTURE, FLAG_PROMPT_CACHING, FLAG_PTC, RequestedTier, RoutingOutcome, TierDecision, TokenBreakdown, BACKEND_OVERRIDE_HEADER, PRIORITY_HEADER,
+};
 use crate::utils::{truncate_str, ToolNameMapper};
 
-// ============================================================================
-// Backend Selection
-// ============================================================================
-
-/// Backend type for API requests
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Backend {
-    Bedrock,
-    Gemini,
-}
-
-/// Determine which backend to use based on model name and availability
-fn select_backend(state: &AppState, model: &str) -> Backend {
-    // Check if model explicitly requests Gemini
-    if model.starts_with("gemini-") {
-        if state.is_gemini_available() {
-            return Backend::Gemini;
-        }
-        // Fall back to Bedrock if Gemini not available
-        tracing::warn!(
-            model = %model,
-            "Gemini model requested but Gemini service not available, falling back to Bedrock"
-        );
-    }
-
-    Backend::Bedrock
-}
-
 // ============================================================================
 // Error Types
 // ============================================================================
 
+/// Seconds clients are asked to wait after Bedrock throttles a running stream
+const STREAM_RETRY_AFTER_SECS: u64 = 5;
+
 /// API error response with HTTP status code
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ApiError {
     pub status: StatusCode,
     pub error_type: String,
     pub message: String,
+    /// Error code and retryability, when known from the backend failure
+    pub taxonomy: Option<ErrorTaxonomy>,
 }
 
 impl ApiError {
@@ -82,6 +70,7 @@ impl ApiError {
             status: StatusCode::BAD_REQUEST,
             error_type: "invalid_request_error".to_string(),
             message: message.into(),
+            taxonomy: None,
         }
     }
 
@@ -90,6 +79,25 @@ impl ApiError {
             status: StatusCode::UNAUTHORIZED,
             error_type: "authentication_error".to_string(),
             message: message.into(),
+            taxonomy: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            error_type: "permission_error".to_string(),
+            message: message.into(),
+            taxonomy: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            error_type: "not_found_error".to_string(),
+            message: message.into(),
+            taxonomy: None,
         }
     }
 
@@ -98,6 +106,7 @@ impl ApiError {
             status: StatusCode::TOO_MANY_REQUESTS,
             error_type: "rate_limit_error".to_string(),
             message: message.into(),
+            taxonomy: None,
         }
     }
 
@@ -106,6 +115,7 @@ impl ApiError {
             status: StatusCode::INTERNAL_SERVER_ERROR,
             error_type: "api_error".to_string(),
             message: message.into(),
+            taxonomy: None,
         }
     }
 
@@ -114,21 +124,54 @@ impl ApiError {
             status: StatusCode::SERVICE_UNAVAILABLE,
             error_type: "overloaded_error".to_string(),
             message: message.into(),
+            taxonomy: None,
+        }
+    }
+
+    /// Anthropic's "overloaded" status (529), used when Bedrock lacks capacity
+    pub fn overloaded(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::from_u16(529).unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
+            error_type: "overloaded_error".to_string(),
+            message: message.into(),
+            taxonomy: None,
         }
     }
 
     pub fn from_bedrock_error(err: &BedrockError) -> Self {
+        let message = err.client_message();
+        let error = match err.class() {
+            ErrorClass::InvalidRequest => Self::bad_request(message),
+            ErrorClass::PermissionDenied => Self::forbidden(message),
+            ErrorClass::RateLimited => Self::rate_limited(message),
+            ErrorClass::Overloaded => Self::overloaded(message),
+            ErrorClass::Server => Self::internal_error(message),
+        };
+        Self {
+            taxonomy: Some(ErrorTaxonomy::from_bedrock_error(err)),
+            ..error
+        }
+    }
+
+    /// Error for a failure after the stream has started
+    ///
+    /// The 200 status has already been sent, so the error event is all the
+    /// client sees. Throttling is reported as `overloaded_error`, which the
+    /// Anthropic SDKs retry, with a hint on how long to back off.
+    pub fn from_stream_error(err: &BedrockStreamError) -> Self {
         match err {
-            BedrockError::Throttled(msg) => Self::rate_limited(msg),
-            BedrockError::ValidationError(msg) => Self::bad_request(msg),
-            BedrockError::ModelNotFound(msg) => Self::bad_request(format!("Model not found: {}", msg)),
-            BedrockError::AccessDenied(msg) => Self::unauthorized(msg),
-            BedrockError::ServiceUnavailable(msg) => Self::service_unavailable(msg),
-            BedrockError::InternalError(msg) => Self::internal_error(msg),
-            BedrockError::Serialization(msg) => Self::bad_request(format!("Serialization error: {}", msg)),
-            BedrockError::Deserialization(msg) => Self::internal_error(format!("Response error: {}", msg)),
-            BedrockError::ApiError { message, .. } => Self::internal_error(message),
-            BedrockError::Unknown(msg) => Self::internal_error(msg),
+            BedrockStreamError::Backend(err) => match err.class() {
+                ErrorClass::RateLimited | ErrorClass::Overloaded => Self {
+                    taxonomy: Some(ErrorTaxonomy::from_bedrock_error(err)),
+                    ..Self::overloaded(format!(
+                        "{} Please retry the request after {} seconds.",
+                        err.client_message(),
+                        STREAM_RETRY_AFTER_SECS
+                    ))
+                },
+                _ => Self::from_bedrock_error(err),
+            },
+            other => Self::internal_error(other.to_string()),
         }
     }
 
@@ -147,7 +190,7 @@ impl ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let error_response = ErrorResponse::new(&self.error_type, &self.message);
-        (self.status, Json(error_response)).into_response()
+        with_taxonomy((self.status, Json(error_response)).into_response(), self.taxonomy)
     }
 }
 
@@ -158,7 +201,8 @@ impl IntoResponse for ApiError {
 /// Enum to represent either a JSON response or an SSE stream
 pub enum MessageApiResponse {
     Json(Json<MessageResponse>),
-    Stream(Sse<std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>),
+    Stream(SseResponse),
+    DryRun(Json<DryRunReport>),
 }
 
 impl IntoResponse for MessageApiResponse {
@@ -166,6 +210,7 @@ impl IntoResponse for MessageApiResponse {
         match self {
             MessageApiResponse::Json(json) => json.into_response(),
             MessageApiResponse::Stream(sse) => sse.into_response(),
+            MessageApiResponse::DryRun(report) => report.into_response(),
         }
     }
 }
@@ -179,91 +224,546 @@ impl IntoResponse for MessageApiResponse {
 /// This endpoint accepts Anthropic Messages API requests, converts them to Bedrock or Gemini format,
 /// calls the appropriate backend API, and returns the response in Anthropic format.
 ///
-/// Supports both streaming and non-streaming responses.
+/// Supports both streaming and non-streaming responses. With `x-dry-run: true`
+/// the request is routed and converted but not sent; see [`dry_run`]. For
+/// keys with the `content_offload` scope, oversized blocks of non-streaming
+/// responses are offloaded to S3 (see
+/// [`ContentOffloader`](crate::services::ContentOffloader)). With request
+/// coalescing enabled, identical non-streaming requests in flight at the
+/// same time share one backend call (see [`RequestCoalescer`]).
 pub async fn create_message(
     State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
+    Json(request): Json<MessageRequest>,
+) -> Result<(HeaderMap, MessageApiResponse), ApiError> {
+    if !state.settings.features.enable_request_coalescing
+        || request.stream
+        || dry_run::requested(&headers)
+    {
+        return send_and_offload(state, key_info, metrics, headers, request).await;
+    }
+
+    let key = request_coalescing_key(&key_info, &headers, &request);
+    let coalescer = state.message_coalescer.clone();
+    let (result, joined) = coalescer
+        .run(key, async move {
+            match send_and_offload(state, key_info, metrics, headers, request).await? {
+                (headers, MessageApiResponse::Json(Json(message))) => Ok((headers, message)),
+                // Only streams and dry runs answer otherwise, and neither is coalesced
+                _ => Err(ApiError::internal_error("Unexpected response to a non-streaming request")),
+            }
+        })
+        .await;
+    if joined {
+        tracing::debug!("Answered by an identical request already in flight");
+    }
+    result.map(|(headers, message)| (headers, MessageApiResponse::Json(Json(message))))
+}
+
+/// Coalescer for non-streaming `/v1/messages` requests, shared through [`AppState`]
+pub type MessageCoalescer = RequestCoalescer<Result<(HeaderMap, MessageResponse), ApiError>>;
+
+/// Headers that change how a request body is handled
+const COALESCING_HEADERS: [&str; 7] = [
+    "anthropic-beta",
+    "anthropic-version",
+    "user-agent",
+    BACKEND_OVERRIDE_HEADER,
+    BEDROCK_EXTRA_FIELDS_HEADER,
+    CLIENT_PROFILE_HEADER,
+    PRIORITY_HEADER,
+];
+
+/// Requests with the same key are identical for coalescing: same API key
+/// (by its full-key hash, not the truncated display form), same handling
+/// headers and the same body
+pub(crate) fn request_coalescing_key(
+    key_info: &ApiKeyInfo,
+    headers: &HeaderMap,
+    request: &impl Serialize,
+) -> u64 {
+    let header_values: Vec<String> = COALESCING_HEADERS
+        .iter()
+        .map(|name| {
+            headers
+                .get_all(*name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+    let mut scope = vec![key_info.user_id.as_str(), key_info.key_id.as_str()];
+    scope.extend(header_values.iter().map(String::as_str));
+    let body = serde_json::to_value(request).unwrap_or_default();
+    coalescing_key(&scope, &body)
+}
+
+/// [`send_message`], then offload oversized blocks for keys that opted in
+async fn send_and_offload(
+    state: AppState,
+    key_info: ApiKeyInfo,
+    metrics: Option<Extension<RequestMetrics>>,
+    headers: HeaderMap,
+    request: MessageRequest,
+) -> Result<(HeaderMap, MessageApiResponse), ApiError> {
+    let offloader = state.content_offloader_for(&key_info);
+    let (response_headers, mut response) =
+        send_message(State(state), Extension(key_info), metrics, headers, Json(request)).await?;
+    if let (Some(offloader), MessageApiResponse::Json(Json(ref mut message))) = (offloader, &mut response) {
+        offloader.offload_blocks(&message.id, &mut message.content).await;
+    }
+    Ok((response_headers, response))
+}
+
+/// [`create_message`] without content offloading
+///
+/// The agent runner sends its model turns through here, since the
+/// conversation it continues needs the full content.
+pub async fn send_message(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    metrics: Option<Extension<RequestMetrics>>,
     headers: HeaderMap,
     Json(mut request): Json<MessageRequest>,
-) -> Result<MessageApiResponse, ApiError> {
+) -> Result<(HeaderMap, MessageApiResponse), ApiError> {
     let start_time = Instant::now();
     let request_id = Uuid::new_v4().to_string();
 
-    // Inject prompt cache breakpoints if enabled
-    if state.settings.features.prompt_caching_enabled {
+    // Expand a stored prompt template ahead of the request's own prompt
+    if let Some(prompt) = request.prompt.take() {
+        state
+            .prompt_templates
+            .render(&prompt.id, prompt.version, &prompt.variables)
+            .map_err(|e| match e {
+                PromptTemplateError::NotFound(_) | PromptTemplateError::VersionNotFound { .. } => {
+                    ApiError::not_found(e.to_string())
+                }
+                _ => ApiError::bad_request(e.to_string()),
+            })?
+            .apply_to(&mut request);
+    }
+
+    // Operator presets fill in parameters the client left out
+    if let Some(preset) = state
+        .inference_presets
+        .resolve(key_info.inference_preset.as_deref(), &request.model)
+    {
+        let applied = preset.params.apply_to_message(&mut request);
+        if !applied.is_empty() {
+            tracing::debug!(
+                request_id = %request_id,
+                presets = ?preset.names,
+                ?applied,
+                "Applied inference preset defaults"
+            );
+        }
+    }
+
+    // Claude Code's transcripts are reshaped into turns Converse accepts
+    let profile = ClientProfile::from_headers(&headers, &state.settings.client_compat);
+    if profile == ClientProfile::ClaudeCode {
+        let fixes = claude_code::normalize_request(&mut request);
+        if !fixes.is_empty() {
+            tracing::debug!(request_id = %request_id, ?fixes, "Normalized Claude Code request");
+        }
+    }
+
+    // With prompt caching enabled for this key, the client's own breakpoints
+    // are checked like Anthropic does; requests without any get injected ones
+    if state.feature_flags.is_enabled(FLAG_PROMPT_CACHING, &key_info) {
+        crate::services::prompt_cache::validate_cache_breakpoints(&request)
+            .map_err(ApiError::bad_request)?;
         crate::services::prompt_cache::inject_cache_breakpoints(&mut request);
     }
 
-    // Determine which backend to use
-    let backend = select_backend(&state, &request.model);
+    // Deprecated models are announced in headers; sunset ones may run on their successor
+    let deprecation = state.model_deprecations.check(&request.model, chrono::Utc::now());
+    if let Some(successor) = deprecation.as_ref().and_then(|d| d.remap_to.as_ref()) {
+        tracing::info!(
+            request_id = %request_id,
+            from = %request.model,
+            to = %successor,
+            "Remapping sunset model to its successor"
+        );
+        request.model = successor.clone();
+    }
+
+    // Keys near their budget are moved to a cheaper model class when enabled
+    let downgrade =
+        ModelDowngrade::evaluate(&key_info, &request.model, &state.settings.budget_downgrade);
+    if let Some(ref downgrade) = downgrade {
+        tracing::info!(
+            request_id = %request_id,
+            from = %downgrade.from,
+            to = %downgrade.to,
+            used_percent = downgrade.used_percent,
+            "Downgrading model to stay within budget"
+        );
+        request.model = downgrade.to.clone();
+    }
+
+    // Trial and restricted keys may only call their allowed models
+    if !key_info.is_model_allowed(&request.model) {
+        return Err(ApiError::forbidden(format!(
+            "API key is not allowed to use model '{}'",
+            request.model
+        )));
+    }
+
+    // Extract beta headers for feature flags
+    let betas = model_capabilities::parse_betas(
+        headers
+            .get_all("anthropic-beta")
+            .iter()
+            .filter_map(|v| v.to_str().ok()),
+    );
+
+    // Determine which backend to use (scoped keys may force one per request)
+    let backend = match headers.get(BACKEND_OVERRIDE_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(spec) => {
+            if !key_info.has_scope(SCOPE_BACKEND_OVERRIDE) {
+                return Err(ApiError::forbidden(format!(
+                    "API key is not allowed to use the {} header",
+                    BACKEND_OVERRIDE_HEADER
+                )));
+            }
+            let target = BackendTarget::parse(spec).ok_or_else(|| {
+                ApiError::bad_request(format!("Invalid {} value: {}", BACKEND_OVERRIDE_HEADER, spec))
+            })?;
+            // An unknown profile in the header is a client error, not a misconfiguration
+            if let BackendTarget::Bedrock { profile: Some(ref name) } = target {
+                if state.backend_for(&target).is_none() {
+                    return Err(ApiError::bad_request(format!(
+                        "Unknown Bedrock profile in {}: {}",
+                        BACKEND_OVERRIDE_HEADER, name
+                    )));
+                }
+            }
+            target
+        }
+        None if model_capabilities::has_beta(&betas, model_capabilities::CONTEXT_1M_BETA) => {
+            state.resolve_long_context_backend(&request.model)
+        }
+        None => state.resolve_backend(&request.model),
+    };
+
+    // High priority needs a scope; low priority keeps off provisioned capacity
+    let priority = RequestPriority::from_headers(&headers, &key_info).map_err(|e| match e {
+        PriorityError::Invalid(_) => ApiError::bad_request(e.to_string()),
+        PriorityError::NotAllowed(_) => ApiError::forbidden(e.to_string()),
+    })?;
 
     tracing::info!(
         request_id = %request_id,
         model = %request.model,
-        backend = ?backend,
+        backend = %backend,
+        priority = priority.as_str(),
         message_count = request.messages.len(),
         max_tokens = request.max_tokens,
         stream = request.stream,
         "Processing messages request"
     );
 
-    // Print prompts if enabled (for debugging)
-    if state.settings.print_prompts {
+    let metrics = metrics.map(|Extension(metrics)| metrics);
+    if let Some(ref metrics) = metrics {
+        metrics.set_route(&request.model, &backend);
+        metrics.set_region(&state.backend_region(&backend));
+    }
+
+    // Extra Bedrock fields need a scope and must stay within the allowlist
+    request.bedrock_extra_fields = extra_fields::from_request(
+        &headers,
+        request.bedrock_extra_fields.take(),
+        &key_info,
+        &state.settings.bedrock_extra_fields,
+    )
+    .map_err(|e| match e {
+        ExtraFieldsError::Invalid(_) => ApiError::bad_request(e.to_string()),
+        _ => ApiError::forbidden(e.to_string()),
+    })?;
+
+    // Dump or print prompts if enabled (for debugging)
+    if let Some(ref dir) = state.settings.print_prompts_dir {
+        prompt_dumps::spawn_dump(dir, &request_id, ApiFormat::Anthropic, &backend, &request);
+    } else if state.settings.print_prompts {
         print_request_prompts(&request_id, &request);
     }
 
-    // Extract beta headers for feature flags
-    let _beta_header = headers
-        .get("anthropic-beta")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
+    // Dry runs stop after conversion, before anything reaches a backend
+    if dry_run::requested(&headers) {
+        let report = dry_run_report(&state, &backend, &request, &betas, &request_id)?
+            .with_cost_estimate(&state, &key_info)
+            .await;
+        return Ok((HeaderMap::new(), MessageApiResponse::DryRun(Json(report))));
+    }
+
+    // Explicit container reuse keeps a previous code-execution sandbox alive
+    let container = match request.container.as_deref() {
+        Some(_) if state.is_ptc_enabled() && !state.feature_flags.is_enabled(FLAG_PTC, &key_info) => {
+            return Err(ApiError::forbidden("Code execution is not enabled for this API key"));
+        }
+        Some(container_id) => Some(reuse_container(&state, container_id).await?),
+        None => None,
+    };
+
+    // Neither Converse nor Gemini can be told to stop at one tool call, so
+    // extra tool_use blocks are dropped from the response
+    let single_tool_use = request
+        .tool_choice
+        .as_ref()
+        .is_some_and(|choice| choice.disables_parallel_tool_use());
+
+    let tap = state
+        .request_tap
+        .start(&request_id, "messages", &request.model, &key_info, request.stream);
+    // Keys opted in to evaluation have a sample of their conversations copied
+    let mut eval = state
+        .eval_tee
+        .as_ref()
+        .filter(|_| state.feature_flags.is_enabled(FLAG_EVAL_CAPTURE, &key_info))
+        .and_then(|tee| tee.capture(&request_id, "messages", &request.model, &key_info, &request, request.stream));
+    let progress = StreamProgress::new()
+        .with_usage_config(state.settings.stream_usage.clone())
+        .with_tap(tap.clone())
+        .with_eval_capture(if request.stream { eval.take() } else { None })
+        .with_container(container.clone())
+        .with_quirks(profile.quirks())
+        .with_metrics(metrics.as_ref().filter(|_| request.stream).map(RequestMetrics::stream))
+        .with_single_tool_use(single_tool_use)
+        .with_priority(priority)
+        .with_output_ceiling(state.output_watchdog.ceiling(
+            &key_info.api_key,
+            &key_info.user_id,
+            &request.model,
+        ))
+        .with_generation(request.stream.then(|| {
+            let message_id = format!("msg_{}", Uuid::new_v4().simple());
+            state.generations.register(message_id, &key_info.user_id)
+        }));
+
+    // Gemini is called natively unless PII tokenization needs the Converse path
+    let native_gemini = backend == BackendTarget::Gemini && !state.settings.pii_tokenization.enabled;
+
+    // Fields the Converse conversion drops are reported rather than lost silently
+    let warnings = match backend {
+        BackendTarget::Gemini if native_gemini => Vec::new(),
+        _ => AnthropicToBedrockConverter::conversion_warnings(&request),
+    };
+    conversion_warnings::log_warnings(&request_id, &warnings);
 
     // Route to appropriate backend
-    match backend {
-        Backend::Gemini => {
-            handle_gemini_request(&state, &request, &request_id, start_time).await
+    let mut result = match backend {
+        BackendTarget::Gemini if native_gemini => {
+            state.priority_metrics.record(priority, EffectiveTier::OnDemand, Duration::ZERO);
+            handle_gemini_request(&state, &request, &request_id, start_time, progress).await
+        }
+        target => match state.backend_for(&target) {
+            Some(backend) => {
+                handle_backend_request(&state, backend.as_ref(), request, &betas, &request_id, start_time, progress)
+                    .await
+            }
+            None => Err(match target {
+                BackendTarget::Bedrock { profile } => ApiError::internal_error(format!(
+                    "Bedrock profile '{}' is not configured",
+                    profile.unwrap_or_default()
+                )),
+                other => ApiError::bad_request(format!(
+                    "Model '{}' is routed to backend '{}', which is not available for this endpoint",
+                    request.model, other
+                )),
+            }),
+        },
+    };
+
+    // Streams echo the container in their final message_delta
+    if let Ok(MessageApiResponse::Json(Json(ref mut response))) = result {
+        response.container = container;
+        if single_tool_use {
+            response.keep_first_tool_use();
         }
-        Backend::Bedrock => {
-            handle_bedrock_request(&state, &request, &request_id, start_time).await
+    }
+
+    // Slow clients read streams through a bounded buffer
+    let result = result.map(|response| match response {
+        MessageApiResponse::Stream(sse) => MessageApiResponse::Stream(
+            sse.buffered(&state.settings.stream_backpressure, state.stream_buffers.clone()),
+        ),
+        json => json,
+    });
+
+    // Streams report to the tap and evaluation sink themselves when they end
+    if let (Some(capture), Ok(MessageApiResponse::Json(Json(response)))) = (eval, &result) {
+        capture.finish(response, response.usage.input_tokens, response.usage.output_tokens);
+    }
+    if let (Some(metrics), Ok(MessageApiResponse::Json(Json(response)))) = (metrics, &result) {
+        metrics.record_tokens(response.usage.input_tokens, response.usage.output_tokens);
+    }
+    if let Some(tap) = tap {
+        match &result {
+            Ok(MessageApiResponse::Json(Json(response))) => tap.complete(
+                200,
+                response.usage.input_tokens,
+                Some(response.usage.output_tokens),
+            ),
+            Ok(MessageApiResponse::Stream(_) | MessageApiResponse::DryRun(_)) => {}
+            Err(e) => tap.complete(e.status.as_u16(), 0, None),
         }
     }
+
+    let mut response_headers = HeaderMap::new();
+    if let Some(value) = conversion_warnings::header_value(&warnings)
+        .and_then(|v| HeaderValue::from_str(&v).ok())
+    {
+        response_headers.insert(CONVERSION_WARNINGS_HEADER, value);
+    }
+    if let Some(value) = downgrade.as_ref().and_then(ModelDowngrade::header_value) {
+        response_headers.insert(BUDGET_DOWNGRADED_FROM_HEADER, value);
+    }
+    if let Some(ref deprecation) = deprecation {
+        response_headers.extend(deprecation.headers());
+    }
+    result.map(|response| (response_headers, response))
+}
+
+/// Validate a requested code-execution container and extend its lifetime
+#[cfg(feature = "ptc")]
+async fn reuse_container(state: &AppState, container_id: &str) -> Result<Container, ApiError> {
+    use crate::services::PtcError;
+
+    let ptc_service = state.ptc_service.as_ref().ok_or_else(|| {
+        ApiError::bad_request("The container parameter requires code execution, which is not enabled")
+    })?;
+
+    ptc_service
+        .reuse_container(container_id)
+        .await
+        .map_err(|e| match e {
+            PtcError::SessionNotFound(_) | PtcError::SessionExpired(_) => ApiError::bad_request(
+                format!("Container '{}' was not found or has expired", container_id),
+            ),
+            other => ApiError::internal_error(format!("Container error: {}", other)),
+        })
+}
+
+/// Builds without the `ptc` feature have no containers to reuse
+#[cfg(not(feature = "ptc"))]
+async fn reuse_container(_state: &AppState, _container_id: &str) -> Result<Container, ApiError> {
+    Err(ApiError::bad_request(
+        "The container parameter requires code execution, which is not enabled",
+    ))
 }
 
-/// Handle request using Bedrock backend
-async fn handle_bedrock_request(
+/// Handle request using a Converse backend (Bedrock or an adapted provider)
+async fn handle_backend_request(
     state: &AppState,
-    request: &MessageRequest,
+    backend: &dyn Backend,
+    mut request: MessageRequest,
+    betas: &[String],
     request_id: &str,
     start_time: Instant,
+    progress: StreamProgress,
 ) -> Result<MessageApiResponse, ApiError> {
-    let bedrock_model = state.bedrock.get_bedrock_model_id(&request.model);
+    let priority = progress.priority();
+    let requested_tier = match RequestedTier::from_anthropic(request.service_tier.as_deref())
+        .map_err(ApiError::bad_request)?
+    {
+        _ if !priority.allows_provisioned() => RequestedTier::Standard,
+        requested => requested,
+    };
+    let backend_model = backend.resolve_model_id(&request.model);
+    let TierDecision { tier, model_id: bedrock_model } = if backend.capabilities().service_tiers {
+        select_tier(
+            requested_tier,
+            &state.settings.provisioned_throughput,
+            &request.model,
+            &backend_model,
+        )
+    } else {
+        TierDecision { tier: EffectiveTier::OnDemand, model_id: backend_model }
+    };
 
     tracing::debug!(
         request_id = %request_id,
+        backend = backend.name(),
         bedrock_model = %bedrock_model,
-        "Routing to Bedrock backend"
+        service_tier = tier.anthropic_name(),
+        "Routing to Converse backend"
     );
 
+    // Reported usage is reconciled against the backend's own metrics
+    let usage_entry = state.usage_ledger.entry(backend, &bedrock_model);
+
+    // Reject long-output requests the model cannot serve rather than letting them fail upstream
+    model_capabilities::validate_output_request(&request.model, &bedrock_model, request.max_tokens, betas)
+        .map_err(ApiError::bad_request)?;
+    model_capabilities::validate_context_request(&request.model, &bedrock_model, None, betas)
+        .map_err(ApiError::bad_request)?;
+
     // Build Converse request (returns mapper for restoring long tool names)
-    let (converse_request, tool_name_mapper) = build_converse_request(state, request)?;
+    let (mut converse_request, tool_name_mapper) = build_converse_request(&mut request, betas)?;
+    converse_request.model_id = bedrock_model.clone();
+
+    // Smooth bursts on provisioned capacity instead of drawing throttles
+    let reserved = if tier == EffectiveTier::Provisioned && state.pt_governor.is_limited(&bedrock_model) {
+        let tokens = pt_governor::reservation(&converse_request);
+        let waited = state.pt_governor.reserve(&bedrock_model, tokens, priority).await.map_err(|wait| {
+            ApiError::rate_limited(format!(
+                "Provisioned throughput for model '{}' is at capacity; retry in {}s",
+                request.model,
+                wait.as_secs().max(1)
+            ))
+        })?;
+        Some((tokens, waited))
+    } else {
+        None
+    };
+    state.priority_metrics.record(priority, tier, reserved.map_or(Duration::ZERO, |(_, waited)| waited));
+    let reserved = reserved.map(|(tokens, _)| tokens);
 
     // Handle streaming vs non-streaming
     if request.stream {
-        let sse_stream = create_streaming_response(state, converse_request, request_id, &request.model, &bedrock_model, tool_name_mapper).await?;
+        let progress = progress.with_usage_entry(usage_entry);
+        let sse_stream = create_streaming_response(backend, converse_request, request_id, &request.model, tier.anthropic_name(), tool_name_mapper, progress).await?;
+        state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
         return Ok(MessageApiResponse::Stream(sse_stream));
     }
 
     // Non-streaming response using Converse API
-    let converse_output = state
-        .bedrock
-        .converse(converse_request)
-        .await
+    // Configured models and large max_tokens stream upstream even here
+    let force_stream = state.settings.stream_assembly.applies_to(
+        &request.model,
+        &bedrock_model,
+        Some(request.max_tokens),
+    );
+    let converse_output = if force_stream {
+        stream_assembly::converse_assembled(backend, converse_request).await
+    } else {
+        backend.converse(converse_request).await
+    };
+    let converse_output = converse_output
         .map_err(|e| {
-            tracing::error!(error = %e, "Bedrock Converse API call failed");
+            tracing::error!(error = %e, backend = backend.name(), "Converse call failed");
+            if let Some(reserved) = reserved {
+                state.pt_governor.refund(&bedrock_model, reserved);
+            }
             ApiError::from_bedrock_error(&e)
         })?;
+    state.routing_metrics.record(&RoutingOutcome::first_attempt(backend.name()));
 
     // Convert Converse response to Anthropic format (restore original tool names)
-    let response = convert_converse_response(converse_output, &request.model, &tool_name_mapper)?;
+    let mut response = convert_converse_response(converse_output, &request.model, &tool_name_mapper)?;
+    response.usage.service_tier = Some(tier.anthropic_name().to_string());
+
+    if let Some(reserved) = reserved {
+        let used = (response.usage.input_tokens + response.usage.output_tokens).max(0) as u64;
+        state.pt_governor.refund(&bedrock_model, reserved.saturating_sub(used));
+    }
+    if let Some(entry) = usage_entry {
+        entry.record(response.usage.input_tokens, response.usage.output_tokens);
+    }
 
     let duration_ms = start_time.elapsed().as_millis();
 
@@ -275,25 +775,28 @@ async fn handle_bedrock_request(
         output_tokens = response.usage.output_tokens,
         stop_reason = ?response.stop_reason,
         duration_ms = duration_ms,
-        "Bedrock request completed successfully"
+        backend = backend.name(),
+        "Converse request completed successfully"
     );
 
     Ok(MessageApiResponse::Json(Json(response)))
 }
 
 /// Handle request using Gemini backend
+#[cfg(feature = "gemini")]
 async fn handle_gemini_request(
     state: &AppState,
     request: &MessageRequest,
     request_id: &str,
     start_time: Instant,
+    progress: StreamProgress,
 ) -> Result<MessageApiResponse, ApiError> {
     let gemini_service = state.gemini_service.as_ref().ok_or_else(|| {
         ApiError::internal_error("Gemini service not available")
     })?;
 
     // Convert Anthropic request to Gemini format
-    let converter = AnthropicToGeminiConverter::new();
+    let converter = state.converters.anthropic_to_gemini();
     let (gemini_model, gemini_request) = converter
         .convert_request(request)
         .map_err(|e| ApiError::bad_request(format!("Request conversion error: {}", e)))?;
@@ -306,24 +809,27 @@ async fn handle_gemini_request(
 
     // Handle streaming vs non-streaming
     if request.stream {
-        let sse_stream = create_gemini_streaming_response(
+        let (sse_stream, routing) = create_gemini_streaming_response(
             gemini_service.clone(),
             &gemini_model,
             gemini_request,
             request_id,
             &request.model,
+            progress,
         ).await?;
+        state.routing_metrics.record(&routing);
         return Ok(MessageApiResponse::Stream(sse_stream));
     }
 
     // Non-streaming response
-    let gemini_response = gemini_service
+    let (gemini_response, routing) = gemini_service
         .generate_content(&gemini_model, &gemini_request)
         .await
         .map_err(|e| {
             tracing::error!(error = %e, "Gemini API call failed");
             ApiError::internal_error(format!("Gemini API error: {}", e))
         })?;
+    state.routing_metrics.record(&routing);
 
     // Convert Gemini response to Anthropic format
     let response_converter = GeminiToAnthropicConverter::new();
@@ -340,6 +846,8 @@ async fn handle_gemini_request(
         input_tokens = response.usage.input_tokens,
         output_tokens = response.usage.output_tokens,
         stop_reason = ?response.stop_reason,
+        attempts = routing.attempts,
+        added_latency_ms = routing.added_latency_ms,
         duration_ms = duration_ms,
         "Gemini request completed successfully"
     );
@@ -347,327 +855,117 @@ async fn handle_gemini_request(
     Ok(MessageApiResponse::Json(Json(response)))
 }
 
-// ============================================================================
-// Request Building
-// ============================================================================
+/// Builds without the `gemini` feature have no Gemini client; requests
+/// routed to Gemini (by the routing table or `x-llm-backend`) are rejected
+#[cfg(not(feature = "gemini"))]
+async fn handle_gemini_request(
+    _state: &AppState,
+    _request: &MessageRequest,
+    _request_id: &str,
+    _start_time: Instant,
+    _progress: StreamProgress,
+) -> Result<MessageApiResponse, ApiError> {
+    Err(ApiError::bad_request(
+        "The Gemini backend is not available: this build lacks the `gemini` feature",
+    ))
+}
 
-/// Build a Converse request from Anthropic MessageRequest
-///
-/// Returns the ConverseRequest and a ToolNameMapper for restoring long tool names in responses.
-fn build_converse_request(
+/// Route and convert a request without calling the backend
+fn dry_run_report(
     state: &AppState,
+    target: &BackendTarget,
     request: &MessageRequest,
-) -> Result<(ConverseRequest, ToolNameMapper), ApiError> {
-    let model_id = state.bedrock.get_bedrock_model_id(&request.model);
-
-    // Convert messages
-    let messages = convert_messages_to_sdk(&request.messages)?;
-
-    // Build inference config
-    let mut inference_config = InferenceConfiguration::builder()
-        .max_tokens(request.max_tokens);
-
-    if let Some(temp) = request.temperature {
-        inference_config = inference_config.temperature(temp);
-    }
-    if let Some(top_p) = request.top_p {
-        inference_config = inference_config.top_p(top_p);
-    }
-    if let Some(ref stop_seqs) = request.stop_sequences {
-        inference_config = inference_config.set_stop_sequences(Some(stop_seqs.clone()));
-    }
-
-    let mut converse_req = ConverseRequest::new(model_id)
-        .with_messages(messages)
-        .with_inference_config(inference_config.build());
-
-    // Convert system prompt
-    if let Some(ref system) = request.system {
-        let system_blocks = convert_system_to_sdk(system);
-        converse_req = converse_req.with_system(system_blocks);
-    }
-
-    // Convert tools with name mapping for long names
-    let mut tool_name_mapper = ToolNameMapper::new();
-    if let Some(ref tools) = request.tools {
-        if !tools.is_empty() {
-            let tool_config = convert_tools_to_sdk(tools, &mut tool_name_mapper)?;
-            converse_req = converse_req.with_tool_config(tool_config);
+    betas: &[String],
+    request_id: &str,
+) -> Result<DryRunReport, ApiError> {
+    let (backend_model, estimated_input_tokens, warnings) = match target {
+        BackendTarget::Gemini => {
+            let (gemini_model, gemini_request) = state
+                .converters
+                .anthropic_to_gemini()
+                .convert_request(request)
+                .map_err(|e| ApiError::bad_request(format!("Request conversion error: {}", e)))?;
+            (gemini_model, dry_run::estimate_json_tokens(&gemini_request), Vec::new())
         }
-    }
-
-    // Handle extended thinking via additional fields
-    if let Some(ref thinking) = request.thinking {
-        let mut thinking_map = std::collections::HashMap::new();
-        thinking_map.insert("type".to_string(), aws_smithy_types::Document::String(thinking.thinking_type.clone()));
-        if let Some(budget) = thinking.budget_tokens {
-            thinking_map.insert("budget_tokens".to_string(), aws_smithy_types::Document::Number(
-                aws_smithy_types::Number::PosInt(budget as u64)
-            ));
+        target => {
+            let backend = state.backend_for(target).ok_or_else(|| {
+                ApiError::bad_request(format!(
+                    "Model '{}' is routed to backend '{}', which is not available for this endpoint",
+                    request.model, target
+                ))
+            })?;
+            let backend_model = backend.resolve_model_id(&request.model);
+            model_capabilities::validate_output_request(
+                &request.model,
+                &backend_model,
+                request.max_tokens,
+                betas,
+            )
+            .map_err(ApiError::bad_request)?;
+            let (converse_request, _) = build_converse_request(&mut request.clone(), betas)?;
+            let estimated = crate::services::estimate_tokens(&converse_request);
+            model_capabilities::validate_context_request(
+                &request.model,
+                &backend_model,
+                Some(estimated),
+                betas,
+            )
+            .map_err(ApiError::bad_request)?;
+            (backend_model, estimated, AnthropicToBedrockConverter::conversion_warnings(request))
         }
+    };
 
-        let additional = aws_smithy_types::Document::Object(std::collections::HashMap::from([
-            ("thinking".to_string(), aws_smithy_types::Document::Object(thinking_map)),
-        ]));
-        converse_req = converse_req.with_additional_fields(additional);
-    }
-
-    Ok((converse_req, tool_name_mapper))
-}
-
-/// Convert Anthropic messages to SDK messages
-fn convert_messages_to_sdk(messages: &[Message]) -> Result<Vec<SdkMessage>, ApiError> {
-    let mut sdk_messages = Vec::new();
-
-    for msg in messages {
-        let role = match msg.role.as_str() {
-            "user" => ConversationRole::User,
-            "assistant" => ConversationRole::Assistant,
-            _ => {
-                return Err(ApiError::bad_request(format!(
-                    "Invalid role: {}",
-                    msg.role
-                )));
-            }
-        };
-
-        let content_blocks = convert_content_to_sdk(&msg.content)?;
-
-        let sdk_msg = SdkMessage::builder()
-            .role(role)
-            .set_content(Some(content_blocks))
-            .build()
-            .map_err(|e| ApiError::bad_request(format!("Failed to build message: {}", e)))?;
-
-        sdk_messages.push(sdk_msg);
-    }
-
-    Ok(sdk_messages)
-}
-
-/// Convert Anthropic content to SDK content blocks
-fn convert_content_to_sdk(content: &MessageContent) -> Result<Vec<SdkContentBlock>, ApiError> {
-    match content {
-        MessageContent::Text(text) => Ok(vec![SdkContentBlock::Text(text.clone())]),
-        MessageContent::Blocks(blocks) => {
-            let mut sdk_blocks = Vec::new();
-            for block in blocks {
-                if let Some(sdk_block) = convert_content_block_to_sdk(block)? {
-                    sdk_blocks.push(sdk_block);
-                }
-            }
-            Ok(sdk_blocks)
-        }
-    }
+    Ok(DryRunReport::new(request_id, target, &request.model, backend_model, estimated_input_tokens)
+        .with_max_output_tokens(Some(request.max_tokens))
+        .with_stream(request.stream)
+        .with_warnings(warnings))
 }
 
-/// Convert a single content block to SDK format
-fn convert_content_block_to_sdk(block: &ContentBlock) -> Result<Option<SdkContentBlock>, ApiError> {
-    match block {
-        ContentBlock::Text { text, .. } => Ok(Some(SdkContentBlock::Text(text.clone()))),
-
-        ContentBlock::Image { source, .. } => {
-            use aws_sdk_bedrockruntime::types::{ImageBlock, ImageFormat, ImageSource};
-            use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-
-            let bytes = BASE64
-                .decode(&source.data)
-                .map_err(|e| ApiError::bad_request(format!("Invalid base64: {}", e)))?;
-
-            let format = match source.media_type.as_str() {
-                "image/png" => ImageFormat::Png,
-                "image/jpeg" => ImageFormat::Jpeg,
-                "image/gif" => ImageFormat::Gif,
-                "image/webp" => ImageFormat::Webp,
-                _ => ImageFormat::Png,
-            };
-
-            let image = ImageBlock::builder()
-                .format(format)
-                .source(ImageSource::Bytes(aws_sdk_bedrockruntime::primitives::Blob::new(bytes)))
-                .build()
-                .map_err(|e| ApiError::bad_request(format!("Failed to build image: {}", e)))?;
-
-            Ok(Some(SdkContentBlock::Image(image)))
-        }
-
-        ContentBlock::ToolUse { id, name, input, .. } => {
-            let tool_use = ToolUseBlock::builder()
-                .tool_use_id(id)
-                .name(name)
-                .input(json_to_document(input))
-                .build()
-                .map_err(|e| ApiError::bad_request(format!("Failed to build tool use: {}", e)))?;
-
-            Ok(Some(SdkContentBlock::ToolUse(tool_use)))
-        }
-
-        ContentBlock::ToolResult {
-            tool_use_id,
-            content,
-            is_error,
-            ..
-        } => {
-            use aws_sdk_bedrockruntime::types::ToolResultBlock;
-
-            let result_content = match content {
-                ToolResultValue::Text(text) => vec![ToolResultContentBlock::Text(text.clone())],
-                ToolResultValue::Blocks(blocks) => {
-                    let mut result_blocks = Vec::new();
-                    for b in blocks {
-                        if let ContentBlock::Text { text, .. } = b {
-                            result_blocks.push(ToolResultContentBlock::Text(text.clone()));
-                        }
-                    }
-                    result_blocks
-                }
-            };
-
-            let status = if is_error.unwrap_or(false) {
-                ToolResultStatus::Error
-            } else {
-                ToolResultStatus::Success
-            };
-
-            let tool_result = ToolResultBlock::builder()
-                .tool_use_id(tool_use_id)
-                .set_content(Some(result_content))
-                .status(status)
-                .build()
-                .map_err(|e| ApiError::bad_request(format!("Failed to build tool result: {}", e)))?;
-
-            Ok(Some(SdkContentBlock::ToolResult(tool_result)))
-        }
-
-        ContentBlock::Document { source, .. } => {
-            use aws_sdk_bedrockruntime::types::{DocumentBlock, DocumentFormat, DocumentSource};
-            use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-
-            let bytes = BASE64
-                .decode(&source.data)
-                .map_err(|e| ApiError::bad_request(format!("Invalid base64: {}", e)))?;
-
-            let format = match source.media_type.as_str() {
-                "application/pdf" => DocumentFormat::Pdf,
-                "text/plain" => DocumentFormat::Txt,
-                "text/html" => DocumentFormat::Html,
-                "text/csv" => DocumentFormat::Csv,
-                _ => DocumentFormat::Pdf,
-            };
-
-            let doc = DocumentBlock::builder()
-                .format(format)
-                .name("document")
-                .source(DocumentSource::Bytes(aws_sdk_bedrockruntime::primitives::Blob::new(bytes)))
-                .build()
-                .map_err(|e| ApiError::bad_request(format!("Failed to build document: {}", e)))?;
-
-            Ok(Some(SdkContentBlock::Document(doc)))
-        }
-
-        // Skip thinking blocks
-        ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => Ok(None),
-
-        // Skip server tool blocks
-        ContentBlock::ServerToolUse { .. } | ContentBlock::ServerToolResult { .. } => Ok(None),
-    }
-}
+// ============================================================================
+// Request Building
+// ============================================================================
 
-/// Convert system content to SDK format
-fn convert_system_to_sdk(system: &SystemContent) -> Vec<SystemContentBlock> {
-    match system {
-        SystemContent::Text(text) => vec![SystemContentBlock::Text(text.clone())],
-        SystemContent::Messages(messages) => messages
-            .iter()
-            .map(|m| SystemContentBlock::Text(m.text.clone()))
-            .collect(),
-    }
-}
+/// Build a Converse request from Anthropic MessageRequest
+///
+/// The request goes through the Anthropic converter and the shared SDK
+/// materialization, like the OpenAI path in `api::chat_completions`. Media
+/// payloads are moved out of `request` and decoded in place. Returns the
+/// ConverseRequest and a ToolNameMapper for restoring long tool names in
+/// responses.
+fn build_converse_request(
+    request: &mut MessageRequest,
+    betas: &[String],
+) -> Result<(ConverseRequest, ToolNameMapper), ApiError> {
+    let mut bedrock_request = AnthropicToBedrockConverter::new()
+        .convert_request_in_place(request)
+        .map_err(|e| ApiError::from_conversion_error(&e))?;
 
-/// Convert tools to SDK ToolConfiguration
-fn convert_tools_to_sdk(
-    tools: &[serde_json::Value],
-    tool_name_mapper: &mut ToolNameMapper,
-) -> Result<ToolConfiguration, ApiError> {
-    let mut sdk_tools = Vec::new();
-
-    for tool in tools {
-        // Skip code execution tools
-        if tool.get("type").and_then(|v| v.as_str()) == Some("code_execution_20250825") {
-            continue;
+    let mut tool_name_mapper = ToolNameMapper::new();
+    bedrock_sdk::shorten_tool_names(&mut bedrock_request, &mut tool_name_mapper);
+
+    // Bedrock-supported betas go alongside the converter's additional fields
+    let bedrock_betas = model_capabilities::bedrock_betas(betas);
+    if !bedrock_betas.is_empty() {
+        let fields = bedrock_request
+            .additional_model_request_fields
+            .get_or_insert_with(|| serde_json::json!({}));
+        if let Some(fields) = fields.as_object_mut() {
+            fields.insert("anthropic_beta".to_string(), serde_json::json!(bedrock_betas));
         }
-
-        let original_name = tool
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| ApiError::bad_request("Tool missing name"))?;
-
-        // Use the mapper to get a short name if needed (Bedrock has 64 char limit)
-        let name = tool_name_mapper.get_or_create_short_name(original_name);
-
-        let description = tool
-            .get("description")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        let input_schema = tool
-            .get("input_schema")
-            .cloned()
-            .unwrap_or(serde_json::json!({"type": "object", "properties": {}}));
-
-        let tool_spec = ToolSpecification::builder()
-            .name(&name)
-            .description(description)
-            .input_schema(SdkToolInputSchema::Json(json_to_document(&input_schema)))
-            .build()
-            .map_err(|e| ApiError::bad_request(format!("Failed to build tool spec: {}", e)))?;
-
-        sdk_tools.push(SdkTool::ToolSpec(tool_spec));
     }
 
-    if tool_name_mapper.has_mappings() {
-        tracing::info!(
-            mapping_count = tool_name_mapper.mapping_count(),
-            "Tool names were shortened due to Bedrock's 64 character limit"
-        );
+    // Client-supplied fields are merged over the converted ones
+    if let Some(ref extra) = request.bedrock_extra_fields {
+        let fields = bedrock_request
+            .additional_model_request_fields
+            .get_or_insert_with(|| serde_json::json!({}));
+        extra_fields::merge(fields, extra);
     }
 
-    Ok(ToolConfiguration::builder()
-        .set_tools(Some(sdk_tools))
-        .build()
-        .map_err(|e| ApiError::bad_request(format!("Failed to build tool config: {}", e)))?)
-}
+    let converse_req = bedrock_sdk::to_converse_request(bedrock_request)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
 
-/// Convert serde_json::Value to aws_smithy_types::Document
-fn json_to_document(value: &serde_json::Value) -> aws_smithy_types::Document {
-    match value {
-        serde_json::Value::Null => aws_smithy_types::Document::Null,
-        serde_json::Value::Bool(b) => aws_smithy_types::Document::Bool(*b),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                if i >= 0 {
-                    aws_smithy_types::Document::Number(aws_smithy_types::Number::PosInt(i as u64))
-                } else {
-                    aws_smithy_types::Document::Number(aws_smithy_types::Number::NegInt(i))
-                }
-            } else if let Some(f) = n.as_f64() {
-                aws_smithy_types::Document::Number(aws_smithy_types::Number::Float(f))
-            } else {
-                aws_smithy_types::Document::Null
-            }
-        }
-        serde_json::Value::String(s) => aws_smithy_types::Document::String(s.clone()),
-        serde_json::Value::Array(arr) => {
-            aws_smithy_types::Document::Array(arr.iter().map(json_to_document).collect())
-        }
-        serde_json::Value::Object(obj) => {
-            let map: std::collections::HashMap<String, aws_smithy_types::Document> = obj
-                .iter()
-                .map(|(k, v)| (k.clone(), json_to_document(v)))
-                .collect();
-            aws_smithy_types::Document::Object(map)
-        }
-    }
+    Ok((converse_req, tool_name_mapper))
 }
 
 // ============================================================================
@@ -711,11 +1009,13 @@ fn convert_converse_response(
         output_tokens: u.output_tokens(),
         cache_creation_input_tokens: None,
         cache_read_input_tokens: None,
+        service_tier: None,
     }).unwrap_or(Usage {
         input_tokens: 0,
         output_tokens: 0,
         cache_creation_input_tokens: None,
         cache_read_input_tokens: None,
+        service_tier: None,
     });
 
     Ok(MessageResponse {
@@ -727,6 +1027,7 @@ fn convert_converse_response(
         stop_reason,
         stop_sequence: None,
         usage,
+        container: None,
     })
 }
 
@@ -754,91 +1055,86 @@ fn convert_sdk_content_to_anthropic(
     }
 }
 
-/// Convert aws_smithy_types::Document to serde_json::Value
-fn document_to_json(doc: &aws_smithy_types::Document) -> serde_json::Value {
-    match doc {
-        aws_smithy_types::Document::Null => serde_json::Value::Null,
-        aws_smithy_types::Document::Bool(b) => serde_json::Value::Bool(*b),
-        aws_smithy_types::Document::Number(n) => match n {
-            aws_smithy_types::Number::PosInt(i) => serde_json::json!(*i),
-            aws_smithy_types::Number::NegInt(i) => serde_json::json!(*i),
-            aws_smithy_types::Number::Float(f) => serde_json::json!(*f),
-        },
-        aws_smithy_types::Document::String(s) => serde_json::Value::String(s.clone()),
-        aws_smithy_types::Document::Array(arr) => {
-            serde_json::Value::Array(arr.iter().map(document_to_json).collect())
-        }
-        aws_smithy_types::Document::Object(obj) => {
-            let map: serde_json::Map<String, serde_json::Value> = obj
-                .iter()
-                .map(|(k, v)| (k.clone(), document_to_json(v)))
-                .collect();
-            serde_json::Value::Object(map)
-        }
-    }
-}
-
 // ============================================================================
 // Streaming Response Handler
 // ============================================================================
 
 /// Create a streaming response using SSE with ConverseStream API
 async fn create_streaming_response(
-    state: &AppState,
+    backend: &dyn Backend,
     request: ConverseRequest,
     request_id: &str,
     original_model: &str,
-    bedrock_model: &str,
+    service_tier: &'static str,
     tool_name_mapper: ToolNameMapper,
-) -> Result<Sse<std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>, ApiError>
-{
+    mut progress: StreamProgress,
+) -> Result<SseResponse, ApiError> {
+    let bedrock_model_id = request.model_id.clone();
+
     // Get streaming response from Bedrock
-    let mut stream_response = state
-        .bedrock
+    let mut stream_response = backend
         .converse_stream(request)
         .await
         .map_err(|e| {
-            tracing::error!(error = %e, "Bedrock ConverseStream API call failed");
+            tracing::error!(error = %e, backend = backend.name(), "ConverseStream call failed");
             ApiError::from_bedrock_error(&e)
         })?;
 
     let model_id = original_model.to_string();
-    let bedrock_model_id = bedrock_model.to_string();
     let req_id = request_id.to_string();
     // Clone mapper for use in the async stream
     let mapper = tool_name_mapper;
 
     // Create the SSE stream
     let stream = async_stream::stream! {
-        let message_id = format!("msg_{}", Uuid::new_v4().to_string().replace("-", ""));
+        let mut sse = SseEncoder::new();
+        let message_id = progress
+            .generation_id()
+            .map_or_else(|| format!("msg_{}", Uuid::new_v4().simple()), str::to_string);
         let mut total_input_tokens: i32 = 0;
         let mut total_output_tokens: i32 = 0;
-        let mut stop_reason = "end_turn".to_string();
+        let mut stop_reason = "end_turn";
+        let mut final_status = StatusCode::OK;
+        let mut stream_failed = false;
+        let mut cancelled = false;
 
         tracing::debug!(request_id = %req_id, "Starting SSE stream");
 
         // Emit message_start event first
-        let message_start_data = serde_json::json!({
-            "type": "message_start",
-            "message": {
-                "id": message_id,
-                "type": "message",
-                "role": "assistant",
-                "content": [],
-                "model": model_id,
-                "stop_reason": null,
-                "stop_sequence": null,
-                "usage": {
-                    "input_tokens": 0,
-                    "output_tokens": 0
-                }
-            }
-        });
-        yield Ok(Event::default().event("message_start").data(message_start_data.to_string()));
+        let mut message_start = StreamMessageStart::new(&message_id, &model_id);
+        message_start.usage.service_tier = Some(service_tier);
+        yield sse.message_event(&MessageStreamEvent::MessageStart { message: message_start });
+        if let Some(frame) = progress.ping_after_start(&mut sse) {
+            yield frame;
+        }
 
         // Process Bedrock ConverseStream events
         loop {
-            match stream_response.recv().await {
+            if progress.output_ceiling_reached() {
+                // Runaway generation: end as if max_tokens was hit
+                for frame in progress.close_open_blocks(&mut sse) {
+                    yield frame;
+                }
+                stop_reason = OUTPUT_LIMIT_STOP_REASON;
+                total_output_tokens = progress.output_tokens();
+                break;
+            }
+            let received = tokio::select! {
+                received = stream_response.recv() => Some(received),
+                _ = progress.cancelled() => None,
+            };
+            let Some(received) = received else {
+                // Stopped through the cancel endpoint: end with the output so far
+                tracing::info!(request_id = %req_id, message_id = %message_id, "Stream cancelled");
+                for frame in progress.close_open_blocks(&mut sse) {
+                    yield frame;
+                }
+                stop_reason = CANCELLED_STOP_REASON;
+                total_output_tokens = progress.output_tokens();
+                cancelled = true;
+                break;
+            };
+            match received {
                 Ok(Some(event)) => {
                     match event {
                         ConverseStreamOutput::MessageStart(start_event) => {
@@ -847,78 +1143,82 @@ async fn create_streaming_response(
                         }
 
                         ConverseStreamOutput::ContentBlockStart(block_start) => {
-                            let index = block_start.content_block_index();
+                            let upstream_index = block_start.content_block_index();
+                            if matches!(block_start.start(), Some(aws_sdk_bedrockruntime::types::ContentBlockStart::ToolUse(_)))
+                                && progress.skip_tool_use(upstream_index)
+                            {
+                                continue;
+                            }
+                            let index = progress.client_index(upstream_index);
+                            progress.block_started(index);
 
                             // Determine content block type
-                            let content_block = if let Some(start) = block_start.start() {
-                                match start {
-                                    aws_sdk_bedrockruntime::types::ContentBlockStart::ToolUse(tool_start) => {
-                                        // Restore original tool name if it was shortened
-                                        let original_name = mapper.restore_original_name(tool_start.name());
-                                        serde_json::json!({
-                                            "type": "tool_use",
-                                            "id": tool_start.tool_use_id(),
-                                            "name": original_name,
-                                            "input": {}
-                                        })
-                                    }
-                                    _ => serde_json::json!({"type": "text", "text": ""})
+                            let frame = match block_start.start() {
+                                Some(aws_sdk_bedrockruntime::types::ContentBlockStart::ToolUse(tool_start)) => {
+                                    // Restore original tool name if it was shortened
+                                    let original_name = mapper.restore_original_name(tool_start.name());
+                                    sse.message_event(&MessageStreamEvent::ContentBlockStart {
+                                        index,
+                                        content_block: StreamContentBlock::ToolUse {
+                                            id: tool_start.tool_use_id(),
+                                            name: &original_name,
+                                            input: EmptyObject {},
+                                        },
+                                    })
                                 }
-                            } else {
-                                serde_json::json!({"type": "text", "text": ""})
+                                _ => sse.message_event(&MessageStreamEvent::ContentBlockStart {
+                                    index,
+                                    content_block: StreamContentBlock::Text { text: "" },
+                                }),
                             };
-
-                            let data = serde_json::json!({
-                                "type": "content_block_start",
-                                "index": index,
-                                "content_block": content_block
-                            });
-                            yield Ok(Event::default().event("content_block_start").data(data.to_string()));
+                            yield frame;
                         }
 
                         ConverseStreamOutput::ContentBlockDelta(block_delta) => {
-                            let index = block_delta.content_block_index();
+                            if progress.is_skipped(block_delta.content_block_index()) {
+                                continue;
+                            }
+                            let index = progress.client_index(block_delta.content_block_index());
 
                             if let Some(delta) = block_delta.delta() {
-                                let delta_json = match delta {
+                                let delta = match delta {
                                     aws_sdk_bedrockruntime::types::ContentBlockDelta::Text(text) => {
-                                        serde_json::json!({"type": "text_delta", "text": text})
+                                        StreamDelta::TextDelta { text }
                                     }
                                     aws_sdk_bedrockruntime::types::ContentBlockDelta::ToolUse(tool_delta) => {
-                                        serde_json::json!({
-                                            "type": "input_json_delta",
-                                            "partial_json": tool_delta.input()
-                                        })
+                                        StreamDelta::InputJsonDelta { partial_json: tool_delta.input() }
                                     }
                                     _ => continue,
                                 };
+                                match &delta {
+                                    StreamDelta::TextDelta { text } => progress.record_output(text),
+                                    StreamDelta::InputJsonDelta { partial_json } => progress.record_output(partial_json),
+                                }
 
-                                let data = serde_json::json!({
-                                    "type": "content_block_delta",
-                                    "index": index,
-                                    "delta": delta_json
-                                });
-                                yield Ok(Event::default().event("content_block_delta").data(data.to_string()));
+                                yield sse.message_event(&MessageStreamEvent::ContentBlockDelta { index, delta });
+                                if let Some(frame) = progress.interim_usage(&mut sse) {
+                                    yield frame;
+                                }
                             }
                         }
 
                         ConverseStreamOutput::ContentBlockStop(block_stop) => {
-                            let index = block_stop.content_block_index();
-                            let data = serde_json::json!({
-                                "type": "content_block_stop",
-                                "index": index
-                            });
-                            yield Ok(Event::default().event("content_block_stop").data(data.to_string()));
+                            if progress.is_skipped(block_stop.content_block_index()) {
+                                continue;
+                            }
+                            let index = progress.client_index(block_stop.content_block_index());
+                            progress.block_stopped(index);
+                            yield sse.message_event(&MessageStreamEvent::ContentBlockStop { index });
                         }
 
                         ConverseStreamOutput::MessageStop(stop_event) => {
                             // stop_reason() returns &StopReason directly (not an Option)
                             stop_reason = match stop_event.stop_reason() {
-                                aws_sdk_bedrockruntime::types::StopReason::EndTurn => "end_turn".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::MaxTokens => "max_tokens".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::StopSequence => "stop_sequence".to_string(),
-                                aws_sdk_bedrockruntime::types::StopReason::ToolUse => "tool_use".to_string(),
-                                _ => "end_turn".to_string(),
+                                aws_sdk_bedrockruntime::types::StopReason::EndTurn => "end_turn",
+                                aws_sdk_bedrockruntime::types::StopReason::MaxTokens => "max_tokens",
+                                aws_sdk_bedrockruntime::types::StopReason::StopSequence => "stop_sequence",
+                                aws_sdk_bedrockruntime::types::StopReason::ToolUse => "tool_use",
+                                _ => "end_turn",
                             };
                         }
 
@@ -926,6 +1226,7 @@ async fn create_streaming_response(
                             if let Some(usage) = metadata_event.usage() {
                                 total_input_tokens = usage.input_tokens();
                                 total_output_tokens = usage.output_tokens();
+                                progress.set_output_tokens(total_output_tokens);
                             }
                         }
 
@@ -941,39 +1242,34 @@ async fn create_streaming_response(
                 }
                 Err(e) => {
                     tracing::error!(request_id = %req_id, error = %e, "Stream error");
-                    let error_data = serde_json::json!({
-                        "type": "error",
-                        "error": {
-                            "type": "api_error",
-                            "message": e.to_string()
-                        }
-                    });
-                    yield Ok(Event::default()
-                        .event("error")
-                        .data(error_data.to_string()));
+                    // Close open blocks and report partial usage before the error
+                    let api_error = ApiError::from_stream_error(&e);
+                    for frame in progress.error_frames(&mut sse, &api_error.error_type, &api_error.message) {
+                        yield frame;
+                    }
+                    stop_reason = ERROR_STOP_REASON;
+                    total_output_tokens = progress.output_tokens();
+                    final_status = api_error.status;
+                    stream_failed = true;
                     break;
                 }
             }
         }
 
-        // Emit message_delta with final usage
-        let message_delta_data = serde_json::json!({
-            "type": "message_delta",
-            "delta": {
-                "stop_reason": stop_reason,
-                "stop_sequence": null
-            },
-            "usage": {
-                "output_tokens": total_output_tokens
-            }
-        });
-        yield Ok(Event::default().event("message_delta").data(message_delta_data.to_string()));
+        if !stream_failed {
+            // Emit message_delta with final usage
+            yield sse.message_event(&MessageStreamEvent::MessageDelta {
+                delta: StreamStopDelta {
+                    stop_reason: Some(stop_reason),
+                    stop_sequence: None,
+                    container: progress.container(),
+                },
+                usage: StreamOutputUsage { output_tokens: total_output_tokens },
+            });
 
-        // Emit message_stop event
-        let message_stop_data = serde_json::json!({
-            "type": "message_stop"
-        });
-        yield Ok(Event::default().event("message_stop").data(message_stop_data.to_string()));
+            // Emit message_stop event
+            yield sse.message_event(&MessageStreamEvent::MessageStop);
+        }
 
         tracing::info!(
             request_id = %req_id,
@@ -984,163 +1280,113 @@ async fn create_streaming_response(
             stop_reason = %stop_reason,
             "Streaming response completed"
         );
+        progress.record_usage(total_input_tokens, total_output_tokens);
+        progress.finish_eval(total_input_tokens, total_output_tokens);
+
+        if let Some(tap) = progress.tap() {
+            if cancelled {
+                tap.cancel(total_input_tokens, total_output_tokens);
+            } else {
+                tap.complete(final_status.as_u16(), total_input_tokens, Some(total_output_tokens));
+            }
+        }
     };
 
-    Ok(Sse::new(Box::pin(stream)))
+    Ok(SseResponse::new(stream))
 }
 
 /// Create a streaming response using SSE with Gemini API
+#[cfg(feature = "gemini")]
 async fn create_gemini_streaming_response(
     gemini_service: std::sync::Arc<crate::services::GeminiService>,
     gemini_model: &str,
     gemini_request: crate::schemas::gemini::GeminiRequest,
     request_id: &str,
     original_model: &str,
-) -> Result<Sse<std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>>, ApiError>
-{
-    let (mut stream_response, credential_name) = gemini_service
+    mut progress: StreamProgress,
+) -> Result<(SseResponse, RoutingOutcome), ApiError> {
+    let (mut stream_response, routing) = gemini_service
         .generate_content_stream(gemini_model, &gemini_request)
         .await
         .map_err(|e| {
             tracing::error!(error = %e, "Gemini stream API call failed");
             ApiError::internal_error(format!("Gemini API error: {}", e))
         })?;
+    let credential_name = routing.credential.clone().unwrap_or_default();
 
     let model_id = original_model.to_string();
     let gemini_model_id = gemini_model.to_string();
     let req_id = request_id.to_string();
-    let converter = GeminiToAnthropicConverter::new();
     let gemini_service_clone = gemini_service.clone();
     let cred_name = credential_name.clone();
 
     let stream = async_stream::stream! {
-        let message_id = format!("msg_{}", Uuid::new_v4().to_string().replace("-", ""));
-        let mut total_input_tokens: i32 = 0;
-        let mut total_output_tokens: i32 = 0;
-        let mut stop_reason = "end_turn".to_string();
-        let mut content_block_started = false;
+        let mut sse = SseEncoder::new();
+        let message_id = progress
+            .generation_id()
+            .map_or_else(|| format!("msg_{}", Uuid::new_v4().simple()), str::to_string);
+        let mut events = GeminiMessageStream::new(message_id.clone(), model_id.clone());
+        let stop_reason;
         let mut stream_error = false;
+        let mut cancelled = false;
 
         tracing::debug!(request_id = %req_id, "Starting Gemini SSE stream");
 
-        // Emit message_start event
-        let message_start_data = serde_json::json!({
-            "type": "message_start",
-            "message": {
-                "id": message_id,
-                "type": "message",
-                "role": "assistant",
-                "content": [],
-                "model": model_id,
-                "stop_reason": null,
-                "stop_sequence": null,
-                "usage": {
-                    "input_tokens": 0,
-                    "output_tokens": 0
+        // Process Gemini stream events; message_start waits for the first
+        // chunk so it can carry the prompt token count
+        loop {
+            if progress.output_ceiling_reached() {
+                for frame in events.truncate_frames(&mut sse, &mut progress) {
+                    yield frame;
                 }
+                stop_reason = OUTPUT_LIMIT_STOP_REASON;
+                break;
             }
-        });
-        yield Ok(Event::default().event("message_start").data(message_start_data.to_string()));
-
-        // Process Gemini stream events
-        loop {
-            match stream_response.recv().await {
+            let received = tokio::select! {
+                received = stream_response.recv() => Some(received),
+                _ = progress.cancelled() => None,
+            };
+            let Some(received) = received else {
+                // Stopped through the cancel endpoint: end with the output so far
+                tracing::info!(request_id = %req_id, message_id = %message_id, "Gemini stream cancelled");
+                for frame in events.cancel_frames(&mut sse, &mut progress) {
+                    yield frame;
+                }
+                stop_reason = CANCELLED_STOP_REASON;
+                cancelled = true;
+                break;
+            };
+            match received {
                 Ok(Some(chunk)) => {
-                    // Convert chunk using the converter
-                    match converter.convert_stream_chunk(&chunk) {
-                        Ok((text_delta, finish_reason_opt)) => {
-                            // Emit content block start if this is the first text
-                            if text_delta.is_some() && !content_block_started {
-                                content_block_started = true;
-                                let start_data = serde_json::json!({
-                                    "type": "content_block_start",
-                                    "index": 0,
-                                    "content_block": {"type": "text", "text": ""}
-                                });
-                                yield Ok(Event::default().event("content_block_start").data(start_data.to_string()));
-                            }
-
-                            // Emit text delta
-                            if let Some(text) = text_delta {
-                                let delta_data = serde_json::json!({
-                                    "type": "content_block_delta",
-                                    "index": 0,
-                                    "delta": {"type": "text_delta", "text": text}
-                                });
-                                yield Ok(Event::default().event("content_block_delta").data(delta_data.to_string()));
-                            }
-
-                            // Check for finish reason
-                            if let Some(reason) = finish_reason_opt {
-                                stop_reason = match reason {
-                                    StopReason::EndTurn => "end_turn".to_string(),
-                                    StopReason::MaxTokens => "max_tokens".to_string(),
-                                    StopReason::StopSequence => "stop_sequence".to_string(),
-                                    StopReason::ToolUse => "tool_use".to_string(),
-                                };
-                            }
-
-                            // Update usage if available
-                            if let Some(usage) = chunk.usage_metadata {
-                                total_input_tokens = usage.prompt_token_count;
-                                total_output_tokens = usage.candidates_token_count;
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!(request_id = %req_id, error = %e, "Failed to convert stream chunk");
-                        }
+                    for frame in events.chunk_frames(&chunk, &mut sse, &mut progress) {
+                        yield frame;
                     }
                 }
                 Ok(None) => {
                     // Stream ended
                     tracing::debug!(request_id = %req_id, "Gemini stream ended");
+                    stop_reason = events.stop_reason();
+                    for frame in events.finish_frames(&mut sse, &mut progress) {
+                        yield frame;
+                    }
                     break;
                 }
                 Err(e) => {
                     stream_error = true;
                     tracing::error!(request_id = %req_id, error = %e, "Gemini stream error");
-                    let error_data = serde_json::json!({
-                        "type": "error",
-                        "error": {
-                            "type": "api_error",
-                            "message": e.to_string()
-                        }
-                    });
-                    yield Ok(Event::default()
-                        .event("error")
-                        .data(error_data.to_string()));
+                    let message = e.to_string();
+                    // Close open blocks and report partial usage before the error
+                    for frame in events.error_frames(&mut sse, &mut progress, &message) {
+                        yield frame;
+                    }
+                    stop_reason = ERROR_STOP_REASON;
                     break;
                 }
             }
         }
 
-        // Emit content block stop if we started one
-        if content_block_started {
-            let stop_data = serde_json::json!({
-                "type": "content_block_stop",
-                "index": 0
-            });
-            yield Ok(Event::default().event("content_block_stop").data(stop_data.to_string()));
-        }
-
-        // Emit message_delta with final usage
-        let message_delta_data = serde_json::json!({
-            "type": "message_delta",
-            "delta": {
-                "stop_reason": stop_reason,
-                "stop_sequence": null
-            },
-            "usage": {
-                "output_tokens": total_output_tokens
-            }
-        });
-        yield Ok(Event::default().event("message_delta").data(message_delta_data.to_string()));
-
-        // Emit message_stop event
-        let message_stop_data = serde_json::json!({
-            "type": "message_stop"
-        });
-        yield Ok(Event::default().event("message_stop").data(message_stop_data.to_string()));
+        let total_input_tokens = events.input_tokens();
+        let total_output_tokens = progress.output_tokens();
 
         // Record success or failure for the credential
         if stream_error {
@@ -1160,9 +1406,50 @@ async fn create_gemini_streaming_response(
             stream_error = stream_error,
             "Gemini streaming response completed"
         );
+        progress.finish_eval(total_input_tokens, total_output_tokens);
+
+        if let Some(tap) = progress.tap() {
+            if cancelled {
+                tap.cancel(total_input_tokens, total_output_tokens);
+            } else {
+                let status = if stream_error { StatusCode::INTERNAL_SERVER_ERROR } else { StatusCode::OK };
+                tap.complete(status.as_u16(), total_input_tokens, Some(total_output_tokens));
+            }
+        }
     };
 
-    Ok(Sse::new(Box::pin(stream)))
+    Ok((SseResponse::new(stream), routing))
+}
+
+// ============================================================================
+// Cancel Endpoint
+// ============================================================================
+
+/// POST /v1/messages/{message_id}/cancel - Stop an in-flight streamed message
+///
+/// The stream ends with a `cancelled` stop reason and the output tokens
+/// generated so far. Keys can only cancel their own messages (the master key
+/// can cancel any).
+pub async fn cancel_message(
+    State(state): State<AppState>,
+    Extension(key_info): Extension<ApiKeyInfo>,
+    Path(message_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let owner = (!key_info.is_master).then_some(key_info.user_id.as_str());
+    if !state.generations.cancel(&message_id, owner) {
+        return Err(ApiError::not_found(format!(
+            "No in-flight message found with id '{}'",
+            message_id
+        )));
+    }
+
+    tracing::info!(message_id = %message_id, user_id = %key_info.user_id, "Message cancellation requested");
+
+    Ok(Json(serde_json::json!({
+        "type": "message_cancellation",
+        "id": message_id,
+        "cancelled": true,
+    })))
 }
 
 // ============================================================================
@@ -1184,14 +1471,23 @@ pub struct CountTokensRequest {
 #[derive(Debug, Clone, Serialize)]
 pub struct CountTokensResponse {
     pub input_tokens: i32,
+    /// Per-block counts; absent when the backend counted the prompt itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<TokenBreakdown>,
 }
 
 /// POST /v1/messages/count_tokens - Count tokens in a message
 ///
-/// This endpoint estimates the number of tokens that would be used by a request.
-/// Note: Bedrock doesn't provide a direct token counting API, so this returns an estimate.
+/// Requests routed to a backend with a tokenizer endpoint (Gemini
+/// `countTokens`) are counted there. Other backends have no token counting
+/// API, so the prompt is counted locally with the tokenizer for the model's
+/// family (see [`crate::services::token_counting`]) and the response carries
+/// a `breakdown` of the system, message and tool counts. Counts over the model's context window (1M with the `context-1m`
+/// beta on eligible models) are rejected the way the Messages API would
+/// reject them.
 pub async fn count_tokens(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<CountTokensRequest>,
 ) -> Result<Json<CountTokensResponse>, ApiError> {
     tracing::debug!(
@@ -1200,32 +1496,97 @@ pub async fn count_tokens(
         "Counting tokens"
     );
 
-    // Simple estimation: ~4 characters per token (rough estimate)
-    let mut char_count = 0;
+    let betas = model_capabilities::parse_betas(
+        headers
+            .get_all("anthropic-beta")
+            .iter()
+            .filter_map(|v| v.to_str().ok()),
+    );
 
-    for message in &request.messages {
-        if let Some(content) = message.get("content") {
-            char_count += content.to_string().len();
+    let target = if model_capabilities::has_beta(&betas, model_capabilities::CONTEXT_1M_BETA) {
+        state.resolve_long_context_backend(&request.model)
+    } else {
+        state.resolve_backend(&request.model)
+    };
+    let native = state
+        .backend_for(&target)
+        .filter(|backend| backend.capabilities().native_token_counting);
+    let profile = ClientProfile::from_headers(&headers, &state.settings.client_compat);
+    let (input_tokens, breakdown) = match native {
+        Some(backend) => {
+            let count = count_tokens_natively(
+                &state,
+                backend.as_ref(),
+                &target,
+                &request,
+                &betas,
+                profile,
+            )
+            .await?;
+            (count, None)
         }
-    }
-
-    if let Some(ref system) = request.system {
-        char_count += system.to_string().len();
-    }
-
-    if let Some(ref tools) = request.tools {
-        for tool in tools {
-            char_count += tool.to_string().len();
+        None => {
+            let breakdown = count_prompt(
+                &request.model,
+                request.system.as_ref(),
+                &request.messages,
+                request.tools.as_deref(),
+            );
+            (breakdown.total(), Some(breakdown))
         }
-    }
+    };
 
-    let estimated_tokens = (char_count / 4).max(1) as i32;
+    model_capabilities::validate_context_request(&request.model, "", Some(input_tokens), &betas)
+        .map_err(ApiError::bad_request)?;
 
     Ok(Json(CountTokensResponse {
-        input_tokens: estimated_tokens,
+        input_tokens,
+        breakdown,
     }))
 }
 
+/// Messages request carrying the prompt of a count_tokens request
+fn count_tokens_message_request(
+    request: &CountTokensRequest,
+) -> Result<MessageRequest, ApiError> {
+    serde_json::from_value(serde_json::json!({
+        "model": request.model,
+        "max_tokens": 1,
+        "messages": request.messages,
+        "system": request.system,
+        "tools": request.tools,
+    }))
+    .map_err(|e| ApiError::bad_request(format!("Invalid count_tokens request: {}", e)))
+}
+
+/// Count prompt tokens through the backend's tokenizer endpoint
+async fn count_tokens_natively(
+    state: &AppState,
+    backend: &dyn Backend,
+    target: &BackendTarget,
+    request: &CountTokensRequest,
+    betas: &[String],
+    profile: ClientProfile,
+) -> Result<i32, ApiError> {
+    let mut message_request = count_tokens_message_request(request)?;
+    if profile == ClientProfile::ClaudeCode {
+        claude_code::normalize_request(&mut message_request);
+    }
+    let (mut converse_request, _) = build_converse_request(&mut message_request, betas)?;
+    converse_request.model_id = match target {
+        BackendTarget::Gemini => state
+            .converters
+            .anthropic_to_gemini()
+            .get_gemini_model(&request.model),
+        _ => backend.resolve_model_id(&request.model),
+    };
+
+    backend.count_tokens(&converse_request).await.map_err(|e| {
+        tracing::error!(error = %e, backend = backend.name(), "Token counting failed");
+        ApiError::from_bedrock_error(&e)
+    })
+}
+
 // ============================================================================
 // Debug Utilities
 // ============================================================================
@@ -1351,6 +1712,129 @@ fn print_request_prompts(request_id: &str, request: &MessageRequest) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::{ReplayBackend, StreamRecording};
+
+    const INTERLEAVED_TOOL_USE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/interleaved_tool_use.json"
+    );
+    const GUARDRAIL_STOP: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/guardrail_stop.json"
+    );
+    const THROTTLED_MID_STREAM: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/throttled_mid_stream.json"
+    );
+
+    /// Replay a recorded backend stream through the handler and return the
+    /// `(event, payload)` pairs sent to the client
+    async fn replay(fixture: &str) -> Vec<(String, serde_json::Value)> {
+        let recording = StreamRecording::load(std::path::Path::new(fixture)).unwrap();
+        let response = create_streaming_response(
+            &ReplayBackend::new(recording),
+            ConverseRequest::new("anthropic.claude-sonnet-4-5"),
+            "req_1",
+            "claude-sonnet-4-5",
+            "standard",
+            ToolNameMapper::new(),
+            StreamProgress::new(),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        std::str::from_utf8(&body)
+            .unwrap()
+            .split("\n\n")
+            .filter_map(|frame| frame.strip_prefix("event: ")?.split_once("\ndata: "))
+            .map(|(event, data)| (event.to_string(), serde_json::from_str(data).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_interleaved_tool_use() {
+        let events = replay(INTERLEAVED_TOOL_USE).await;
+
+        let starts: Vec<_> = events
+            .iter()
+            .filter(|(event, _)| event == "content_block_start")
+            .map(|(_, data)| (data["index"].as_i64().unwrap(), data["content_block"]["type"].clone()))
+            .collect();
+        assert_eq!(starts, vec![(0, "text".into()), (1, "tool_use".into()), (2, "tool_use".into())]);
+
+        // Deltas arrive interleaved but each block's input is complete JSON
+        let input = |index: i64| -> serde_json::Value {
+            let json: String = events
+                .iter()
+                .filter(|(event, data)| event == "content_block_delta" && data["index"] == index)
+                .map(|(_, data)| data["delta"]["partial_json"].as_str().unwrap().to_string())
+                .collect();
+            serde_json::from_str(&json).unwrap()
+        };
+        assert_eq!(input(1), serde_json::json!({"city": "Paris"}));
+        assert_eq!(input(2), serde_json::json!({"city": "Tokyo"}));
+
+        let (event, delta) = &events[events.len() - 2];
+        assert_eq!(event, "message_delta");
+        assert_eq!(delta["delta"]["stop_reason"], "tool_use");
+        assert_eq!(delta["usage"]["output_tokens"], 58);
+        assert_eq!(events.last().unwrap().0, "message_stop");
+    }
+
+    #[tokio::test]
+    async fn test_replayed_guardrail_stop_matches_non_streaming() {
+        let events = replay(GUARDRAIL_STOP).await;
+
+        let (_, delta) = &events[events.len() - 2];
+        assert_eq!(delta["delta"]["stop_reason"], "end_turn");
+
+        // The assembled (non-streaming) response reports the same stop reason
+        let recording = StreamRecording::load(std::path::Path::new(GUARDRAIL_STOP)).unwrap();
+        let output = ReplayBackend::new(recording)
+            .converse(ConverseRequest::new("anthropic.claude-sonnet-4-5"))
+            .await
+            .unwrap();
+        let response = convert_converse_response(output, "claude-sonnet-4-5", &ToolNameMapper::new())
+            .unwrap();
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_mid_stream_error_ends_cleanly() {
+        let events = replay(THROTTLED_MID_STREAM).await;
+
+        let names: Vec<_> = events.iter().map(|(event, _)| event.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["message_start", "content_block_start", "content_block_delta", "content_block_stop", "message_delta", "error"]
+        );
+        assert_eq!(events[4].1["delta"]["stop_reason"], ERROR_STOP_REASON);
+        assert_eq!(events[5].1["error"]["type"], "overloaded_error");
+        let message = events[5].1["error"]["message"].as_str().unwrap();
+        assert!(message.ends_with("Please retry the request after 5 seconds."));
+    }
+
+    #[test]
+    fn test_coalescing_key_uses_full_key() {
+        // Two keys sharing the first 8 characters display the same
+        let key_a = ApiKeyInfo::master("sk-shared-prefix-aaaaaaaa");
+        let key_b = ApiKeyInfo::master("sk-shared-prefix-bbbbbbbb");
+        assert_eq!(key_a.api_key, key_b.api_key);
+
+        let body = serde_json::json!({"model": "claude-sonnet-4-5"});
+        let headers = HeaderMap::new();
+        assert_ne!(
+            request_coalescing_key(&key_a, &headers, &body),
+            request_coalescing_key(&key_b, &headers, &body)
+        );
+        assert_eq!(
+            request_coalescing_key(&key_a, &headers, &body),
+            request_coalescing_key(&ApiKeyInfo::master("sk-shared-prefix-aaaaaaaa"), &headers, &body)
+        );
+    }
 
     #[test]
     fn test_api_error_status_codes() {
@@ -1362,26 +1846,20 @@ mod tests {
     }
 
     #[test]
-    fn test_json_to_document() {
-        let json = serde_json::json!({"key": "value", "num": 42});
-        let doc = json_to_document(&json);
-
-        if let aws_smithy_types::Document::Object(map) = doc {
-            assert!(map.contains_key("key"));
-            assert!(map.contains_key("num"));
-        } else {
-            panic!("Expected Document::Object");
-        }
-    }
-
-    #[test]
-    fn test_document_to_json() {
-        let doc = aws_smithy_types::Document::Object(std::collections::HashMap::from([
-            ("key".to_string(), aws_smithy_types::Document::String("value".to_string())),
-        ]));
-        let json = document_to_json(&doc);
+    fn test_bedrock_error_status_mapping() {
+        let status = |err: BedrockError| {
+            let api_err = ApiError::from_bedrock_error(&err);
+            (api_err.status.as_u16(), api_err.error_type)
+        };
 
-        assert_eq!(json["key"], "value");
+        assert_eq!(status(BedrockError::Throttled("t".into())), (429, "rate_limit_error".into()));
+        assert_eq!(status(BedrockError::ServiceUnavailable("t".into())), (529, "overloaded_error".into()));
+        assert_eq!(status(BedrockError::ModelEndOfLife("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::ValidationError("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::ModelNotFound("t".into())), (400, "invalid_request_error".into()));
+        assert_eq!(status(BedrockError::AccessDenied("t".into())), (403, "permission_error".into()));
+        assert_eq!(status(BedrockError::InternalError("t".into())), (500, "api_error".into()));
+        assert_eq!(status(BedrockError::Unknown("t".into())), (500, "api_error".into()));
     }
 
     #[test]
@@ -1390,4 +1868,37 @@ mod tests {
         let estimated_tokens = (char_count / 4).max(1);
         assert_eq!(estimated_tokens, 100);
     }
+
+    #[test]
+    fn test_count_tokens_request_conversion() {
+        let request: CountTokensRequest = serde_json::from_value(serde_json::json!({
+            "model": "gemini-2.0-flash",
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": "x".repeat(400)}]
+        }))
+        .unwrap();
+        let breakdown = count_prompt(
+            &request.model,
+            request.system.as_ref(),
+            &request.messages,
+            request.tools.as_deref(),
+        );
+        assert_eq!(breakdown.messages.len(), 1);
+        assert!(breakdown.system > 0);
+        assert_eq!(breakdown.tools, 0);
+
+        let mut message_request = count_tokens_message_request(&request).unwrap();
+        assert_eq!(message_request.model, "gemini-2.0-flash");
+        assert_eq!(message_request.messages.len(), 1);
+        assert!(message_request.system.is_some());
+        let (converse_request, _) = build_converse_request(&mut message_request, &[]).unwrap();
+        assert_eq!(converse_request.messages.len(), 1);
+
+        let invalid: CountTokensRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-haiku",
+            "messages": [{"role": "user"}]
+        }))
+        .unwrap();
+        assert!(count_tokens_message_request(&invalid).is_err());
+    }
 }
@@ -1,9 +1,27 @@
 //! API endpoint handlers module
 //!
-//! Contains all HTTP endpoint handler implementations.
+//! Contains all HTTP endpoint handler implementations. The admin endpoints
+//! need the `admin-ui` feature and the Gemini stream adapter needs `gemini`.
 
+#[cfg(feature = "admin-ui")]
+pub mod admin;
+pub mod agents;
+pub mod capabilities;
 pub mod chat_completions;
+pub mod claude_code;
+pub mod client_profile;
+pub mod debug;
+pub mod dry_run;
+pub mod embeddings;
 pub mod event_logging;
+pub mod extra_fields;
+#[cfg(feature = "gemini")]
+pub mod gemini_stream;
+pub mod generate_content;
 pub mod health;
+pub mod invoke;
+pub mod keys;
 pub mod messages;
 pub mod models;
+pub mod prompts;
+pub mod sse;
@@ -0,0 +1,52 @@
+//! Prompt template rendering endpoint
+//!
+//! `POST /v1/prompts/{name}/render` renders a stored template with the
+//! supplied variables and returns the resulting system prompt and messages,
+//! so clients can preview exactly what a `prompt` reference in a
+//! `/v1/messages` request expands to. Templates are managed under
+//! `/admin/prompts`.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::error::ApiError;
+use crate::server::state::AppState;
+use crate::services::{PromptTemplateError, RenderedPrompt};
+
+/// Request body for rendering a template
+#[derive(Debug, Default, Deserialize)]
+pub struct RenderPromptRequest {
+    /// Template version (latest when omitted)
+    #[serde(default)]
+    pub version: Option<u32>,
+
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// Map a template error onto an API error
+pub fn template_error(err: PromptTemplateError) -> ApiError {
+    match err {
+        PromptTemplateError::NotFound(_) | PromptTemplateError::VersionNotFound { .. } => {
+            ApiError::NotFound(err.to_string())
+        }
+        _ => ApiError::InvalidRequest(err.to_string()),
+    }
+}
+
+/// POST /v1/prompts/:name/render - Render a stored prompt template
+pub async fn render_prompt(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<RenderPromptRequest>,
+) -> Result<Json<RenderedPrompt>, ApiError> {
+    state
+        .prompt_templates
+        .render(&name, body.version, &body.variables)
+        .map(Json)
+        .map_err(template_error)
+}
@@ -0,0 +1,1158 @@
+//! Server-sent event encoding
+//!
+//! Streaming handlers describe each event with the borrowed, typed structs in
+//! this module and serialize them straight into a per-stream [`SseEncoder`]
+//! buffer, instead of building a `serde_json::Value`, stringifying it and
+//! copying the string into an `axum` SSE event for every token.
+//!
+//! Each encoded frame is split off the encoder's `BytesMut` and frozen. Once
+//! hyper has written a frame and dropped it, the next `reserve` reclaims the
+//! same allocation, so a long stream settles on a single buffer.
+
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use bytes::{BufMut, BytesMut};
+use futures::stream::{Stream, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::api::client_profile::StreamQuirks;
+use crate::config::{StreamBackpressureConfig, StreamUsageConfig};
+use crate::middleware::StreamMetrics;
+use crate::schemas::anthropic::Container;
+use crate::schemas::openai::{ChatRole, CompletionUsage};
+use crate::services::stream_buffers::{self, StreamBufferMetrics};
+use crate::services::{
+    EvalCapture, GenerationGuard, LedgerEntry, OutputCeiling, RequestPriority, TapHandle,
+    CHARS_PER_TOKEN,
+};
+
+/// Initial encoder capacity; most frames are well under this
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Minimum free space reserved before encoding each frame
+const FRAME_RESERVE: usize = 256;
+
+// ============================================================================
+// Encoder and Response
+// ============================================================================
+
+/// Encodes SSE frames into a reusable buffer
+#[derive(Debug)]
+pub struct SseEncoder {
+    buf: BytesMut,
+}
+
+impl Default for SseEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SseEncoder {
+    /// Create an encoder with the default capacity
+    pub fn new() -> Self {
+        Self {
+            buf: BytesMut::with_capacity(INITIAL_CAPACITY),
+        }
+    }
+
+    /// Encode an Anthropic stream event (`event:` line named after its type)
+    pub fn message_event(&mut self, event: &MessageStreamEvent<'_>) -> Bytes {
+        self.event(event.name(), event)
+    }
+
+    /// Encode a named event with a JSON payload
+    pub fn event<T: Serialize + ?Sized>(&mut self, name: &str, data: &T) -> Bytes {
+        self.frame(Some(name), |buf| serde_json::to_writer(buf.writer(), data))
+    }
+
+    /// Encode an unnamed event with a JSON payload
+    pub fn data<T: Serialize + ?Sized>(&mut self, data: &T) -> Bytes {
+        self.frame(None, |buf| serde_json::to_writer(buf.writer(), data))
+    }
+
+    /// Encode an SSE comment line (`: <text>`), which clients skip by default
+    ///
+    /// The text must not contain newlines.
+    pub fn comment(&mut self, text: &str) -> Bytes {
+        self.buf.reserve(text.len() + 4);
+        self.buf.put_slice(b": ");
+        self.buf.put_slice(text.as_bytes());
+        self.buf.put_slice(b"\n\n");
+        self.buf.split().freeze()
+    }
+
+    /// Encode an unnamed event with a literal payload (e.g. `[DONE]`)
+    ///
+    /// The payload must not contain newlines.
+    pub fn raw_data(&mut self, data: &str) -> Bytes {
+        self.frame(None, |buf| {
+            buf.put_slice(data.as_bytes());
+            Ok(())
+        })
+    }
+
+    fn frame(
+        &mut self,
+        name: Option<&str>,
+        write_data: impl FnOnce(&mut BytesMut) -> serde_json::Result<()>,
+    ) -> Bytes {
+        self.buf.reserve(FRAME_RESERVE);
+
+        if let Some(name) = name {
+            self.buf.put_slice(b"event: ");
+            self.buf.put_slice(name.as_bytes());
+            self.buf.put_u8(b'\n');
+        }
+        self.buf.put_slice(b"data: ");
+        // Compact JSON never contains a raw newline, so one data line suffices
+        if let Err(e) = write_data(&mut self.buf) {
+            tracing::error!(error = %e, "Failed to serialize SSE event");
+            self.buf.clear();
+            return Bytes::new();
+        }
+        self.buf.put_slice(b"\n\n");
+
+        self.buf.split().freeze()
+    }
+}
+
+/// Boxed stream of encoded SSE frames
+pub type SseFrames = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+
+/// `text/event-stream` response over pre-encoded frames
+pub struct SseResponse(SseFrames);
+
+impl SseResponse {
+    /// Wrap a stream of frames produced by an [`SseEncoder`]
+    pub fn new(frames: impl Stream<Item = Bytes> + Send + 'static) -> Self {
+        Self(Box::pin(frames))
+    }
+
+    /// Produce the frames ahead of the client through a bounded buffer
+    /// (see [`stream_buffers`])
+    pub fn buffered(self, config: &StreamBackpressureConfig, metrics: Arc<StreamBufferMetrics>) -> Self {
+        Self(stream_buffers::buffered(self.0, config, metrics))
+    }
+}
+
+impl IntoResponse for SseResponse {
+    fn into_response(self) -> Response {
+        (
+            [
+                (header::CONTENT_TYPE, "text/event-stream"),
+                (header::CACHE_CONTROL, "no-cache"),
+            ],
+            Body::from_stream(self.0.map(Ok::<_, Infallible>)),
+        )
+            .into_response()
+    }
+}
+
+// ============================================================================
+// Anthropic Stream Events
+// ============================================================================
+
+/// Anthropic Messages API stream event (borrowed)
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageStreamEvent<'a> {
+    MessageStart {
+        message: StreamMessageStart<'a>,
+    },
+    ContentBlockStart {
+        index: i32,
+        content_block: StreamContentBlock<'a>,
+    },
+    ContentBlockDelta {
+        index: i32,
+        delta: StreamDelta<'a>,
+    },
+    ContentBlockStop {
+        index: i32,
+    },
+    MessageDelta {
+        delta: StreamStopDelta<'a>,
+        usage: StreamOutputUsage,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: StreamError<'a>,
+    },
+}
+
+impl MessageStreamEvent<'_> {
+    /// SSE event name (same as the `type` field)
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageStreamEvent::MessageStart { .. } => "message_start",
+            MessageStreamEvent::ContentBlockStart { .. } => "content_block_start",
+            MessageStreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+            MessageStreamEvent::ContentBlockStop { .. } => "content_block_stop",
+            MessageStreamEvent::MessageDelta { .. } => "message_delta",
+            MessageStreamEvent::MessageStop => "message_stop",
+            MessageStreamEvent::Ping => "ping",
+            MessageStreamEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Partial message sent in `message_start`
+#[derive(Debug, Serialize)]
+pub struct StreamMessageStart<'a> {
+    pub id: &'a str,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub role: &'static str,
+    pub content: [(); 0],
+    pub model: &'a str,
+    pub stop_reason: Option<&'a str>,
+    pub stop_sequence: Option<&'a str>,
+    pub usage: StreamUsage,
+}
+
+impl<'a> StreamMessageStart<'a> {
+    /// Empty assistant message with zero usage
+    pub fn new(id: &'a str, model: &'a str) -> Self {
+        Self {
+            id,
+            message_type: "message",
+            role: "assistant",
+            content: [],
+            model,
+            stop_reason: None,
+            stop_sequence: None,
+            usage: StreamUsage::default(),
+        }
+    }
+}
+
+/// Token usage in `message_start`
+#[derive(Debug, Default, Serialize)]
+pub struct StreamUsage {
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<&'static str>,
+}
+
+/// Token usage in `message_delta`
+#[derive(Debug, Serialize)]
+pub struct StreamOutputUsage {
+    pub output_tokens: i32,
+}
+
+/// Content block opened by `content_block_start`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamContentBlock<'a> {
+    Text { text: &'a str },
+    ToolUse { id: &'a str, name: &'a str, input: EmptyObject },
+}
+
+/// Serializes as `{}`
+#[derive(Debug, Default, Serialize)]
+pub struct EmptyObject {}
+
+/// Delta carried by `content_block_delta`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamDelta<'a> {
+    TextDelta { text: &'a str },
+    InputJsonDelta { partial_json: &'a str },
+}
+
+/// Stop information in `message_delta` (no stop reason in interim usage updates)
+#[derive(Debug, Serialize)]
+pub struct StreamStopDelta<'a> {
+    pub stop_reason: Option<&'a str>,
+    pub stop_sequence: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<&'a Container>,
+}
+
+/// Error body of an `error` event
+#[derive(Debug, Serialize)]
+pub struct StreamError<'a> {
+    #[serde(rename = "type")]
+    pub error_type: &'a str,
+    pub message: &'a str,
+}
+
+/// Stop reason sent in `message_delta` when a stream fails after partial output
+pub const ERROR_STOP_REASON: &str = "error";
+
+/// Stop reason of a stream stopped through a cancel endpoint
+pub const CANCELLED_STOP_REASON: &str = "cancelled";
+
+/// Stop reason of a stream cut off by the output watchdog
+pub const OUTPUT_LIMIT_STOP_REASON: &str = "max_tokens";
+
+/// OpenAI finish reason of a stream cut off by the output watchdog
+pub const OUTPUT_LIMIT_FINISH_REASON: &str = "length";
+
+/// Tracks open content blocks and output of a stream
+///
+/// If the backend fails mid-stream, [`StreamProgress::error_frames`] produces
+/// a well-formed ending instead of a bare `error` event: every open block is
+/// closed, a `message_delta` carries [`ERROR_STOP_REASON`] and the usage seen
+/// so far, and the `error` event comes last.
+///
+/// Output is also forwarded to the request's tap handle and evaluation
+/// capture, if either is open.
+/// Streams registered as generations can be stopped through the cancel
+/// endpoints; [`StreamProgress::cancelled`] resolves when that happens.
+/// Client [`StreamQuirks`] adjust the error ending and the initial `ping`.
+/// With an output ceiling, [`StreamProgress::output_ceiling_reached`] tells
+/// the stream loop when to stop with a truncated ending.
+#[derive(Debug, Default)]
+pub struct StreamProgress {
+    open_blocks: Vec<i32>,
+    output_chars: usize,
+    reported_output_tokens: Option<i32>,
+    last_usage_report: i32,
+    usage_config: StreamUsageConfig,
+    tap: Option<TapHandle>,
+    container: Option<Container>,
+    generation: Option<GenerationGuard>,
+    quirks: StreamQuirks,
+    single_tool_use: bool,
+    priority: RequestPriority,
+    /// Upstream indices of tool_use blocks dropped under `single_tool_use`
+    skipped_blocks: Vec<i32>,
+    tool_use_seen: bool,
+    output_ceiling: Option<OutputCeiling>,
+    usage_entry: Option<LedgerEntry>,
+    eval: Option<EvalCapture>,
+    metrics: Option<StreamMetrics>,
+}
+
+impl StreamProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the interim usage reporting configuration
+    pub fn with_usage_config(mut self, config: StreamUsageConfig) -> Self {
+        self.usage_config = config;
+        self
+    }
+
+    /// Forward progress to a request tap
+    pub fn with_tap(mut self, tap: Option<TapHandle>) -> Self {
+        self.tap = tap;
+        self
+    }
+
+    /// Tap handle of the request, if one is open
+    pub fn tap(&self) -> Option<&TapHandle> {
+        self.tap.as_ref()
+    }
+
+    /// Set the code-execution container echoed in the final `message_delta`
+    pub fn with_container(mut self, container: Option<Container>) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Code-execution container of the request, if any
+    pub fn container(&self) -> Option<&Container> {
+        self.container.as_ref()
+    }
+
+    /// Apply a client profile's formatting quirks
+    pub fn with_quirks(mut self, quirks: StreamQuirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Formatting quirks of the requesting client
+    pub fn quirks(&self) -> StreamQuirks {
+        self.quirks
+    }
+
+    /// `ping` frame to send after `message_start`, if the client expects one
+    pub fn ping_after_start(&self, sse: &mut SseEncoder) -> Option<Bytes> {
+        self.quirks
+            .ping_after_start
+            .then(|| sse.message_event(&MessageStreamEvent::Ping))
+    }
+
+    /// Register the stream as a cancellable generation
+    pub fn with_generation(mut self, generation: Option<GenerationGuard>) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// ID the stream is registered under, if cancellable
+    pub fn generation_id(&self) -> Option<&str> {
+        self.generation.as_ref().map(GenerationGuard::id)
+    }
+
+    /// Wait until the stream is cancelled (never, if not registered)
+    pub async fn cancelled(&self) {
+        match self.generation {
+            Some(ref generation) => generation.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Stop the stream once its output reaches the watchdog ceiling
+    pub fn with_output_ceiling(mut self, ceiling: Option<OutputCeiling>) -> Self {
+        self.output_ceiling = ceiling;
+        self
+    }
+
+    /// Whether output has reached the ceiling; records the trip once
+    pub fn output_ceiling_reached(&mut self) -> bool {
+        let output_tokens = self.output_tokens();
+        match self.output_ceiling {
+            Some(ref ceiling) if output_tokens >= ceiling.max_tokens() => {
+                ceiling.trip(output_tokens);
+                self.output_ceiling = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add the stream's final usage to the reconciliation ledger
+    pub fn with_usage_entry(mut self, entry: Option<LedgerEntry>) -> Self {
+        self.usage_entry = entry;
+        self
+    }
+
+    /// Record the usage reported to the client (only the first call counts)
+    pub fn record_usage(&mut self, input_tokens: i32, output_tokens: i32) {
+        if let Some(entry) = self.usage_entry.take() {
+            entry.record(input_tokens, output_tokens);
+        }
+        if let Some(mut metrics) = self.metrics.take() {
+            metrics.record_tokens(input_tokens, output_tokens);
+        }
+    }
+
+    /// Report time to first token, tokens and duration to `/metrics`
+    pub fn with_metrics(mut self, metrics: Option<StreamMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Copy the streamed output to the evaluation sink
+    pub fn with_eval_capture(mut self, capture: Option<EvalCapture>) -> Self {
+        self.eval = capture;
+        self
+    }
+
+    /// Queue the evaluation record with the final usage (only the first call counts)
+    pub fn finish_eval(&mut self, input_tokens: i32, output_tokens: i32) {
+        if let Some(capture) = self.eval.take() {
+            capture.finish_stream(input_tokens, output_tokens);
+        }
+    }
+
+    /// Emit at most one `tool_use` block (`disable_parallel_tool_use`)
+    pub fn with_single_tool_use(mut self, single_tool_use: bool) -> Self {
+        self.single_tool_use = single_tool_use;
+        self
+    }
+
+    /// Set the request's `x-priority` class
+    pub fn with_priority(mut self, priority: RequestPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Priority class of the request
+    pub fn priority(&self) -> RequestPriority {
+        self.priority
+    }
+
+    /// Whether tool_use blocks after the first are dropped
+    pub fn single_tool_use(&self) -> bool {
+        self.single_tool_use
+    }
+
+    /// Whether the upstream tool_use block starting at `index` must be dropped
+    ///
+    /// The first tool_use block is always kept; later ones are remembered so
+    /// their deltas and stop events can be skipped with [`Self::is_skipped`].
+    pub fn skip_tool_use(&mut self, index: i32) -> bool {
+        if !self.single_tool_use {
+            return false;
+        }
+        if std::mem::replace(&mut self.tool_use_seen, true) {
+            self.skipped_blocks.push(index);
+            return true;
+        }
+        false
+    }
+
+    /// Whether the upstream block at `index` was dropped
+    pub fn is_skipped(&self, index: i32) -> bool {
+        self.skipped_blocks.contains(&index)
+    }
+
+    /// Index to send the client for an upstream block, closing the gaps
+    /// left by dropped blocks
+    pub fn client_index(&self, index: i32) -> i32 {
+        index - self.skipped_blocks.iter().filter(|&&i| i < index).count() as i32
+    }
+
+    /// Record a `content_block_start`
+    pub fn block_started(&mut self, index: i32) {
+        if !self.open_blocks.contains(&index) {
+            self.open_blocks.push(index);
+        }
+    }
+
+    /// Record a `content_block_stop`
+    pub fn block_stopped(&mut self, index: i32) {
+        self.open_blocks.retain(|&i| i != index);
+    }
+
+    /// Whether any content block is still open
+    pub fn has_open_blocks(&self) -> bool {
+        !self.open_blocks.is_empty()
+    }
+
+    /// Record streamed text or tool input
+    pub fn record_output(&mut self, text: &str) {
+        self.output_chars += text.len();
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.record_output();
+        }
+        if let Some(ref tap) = self.tap {
+            tap.record_output(text);
+        }
+        if let Some(ref mut capture) = self.eval {
+            capture.record_output(text);
+        }
+    }
+
+    /// Record the backend's output token count
+    pub fn set_output_tokens(&mut self, tokens: i32) {
+        self.reported_output_tokens = Some(tokens);
+        if let Some(ref tap) = self.tap {
+            tap.set_output_tokens(tokens);
+        }
+    }
+
+    /// Output tokens so far: the backend's count, or an estimate before it arrives
+    pub fn output_tokens(&self) -> i32 {
+        self.reported_output_tokens
+            .unwrap_or_else(|| self.output_chars.div_ceil(CHARS_PER_TOKEN) as i32)
+    }
+
+    /// Interim usage frame, if `interval_tokens` more tokens have streamed
+    ///
+    /// Sent as an SSE comment or as a usage-only `message_delta` (cumulative
+    /// `output_tokens`, null stop reason) depending on the configured mode.
+    pub fn interim_usage(&mut self, sse: &mut SseEncoder) -> Option<Bytes> {
+        let config = &self.usage_config;
+        let output_tokens = self.output_tokens();
+        if config.interval_tokens <= 0
+            || output_tokens < self.last_usage_report + config.interval_tokens
+        {
+            return None;
+        }
+        self.last_usage_report = output_tokens;
+
+        let usage = StreamOutputUsage { output_tokens };
+        if config.uses_message_delta() {
+            Some(sse.message_event(&MessageStreamEvent::MessageDelta {
+                delta: StreamStopDelta { stop_reason: None, stop_sequence: None, container: None },
+                usage,
+            }))
+        } else {
+            let json = serde_json::to_string(&usage).unwrap_or_default();
+            Some(sse.comment(&format!("usage {}", json)))
+        }
+    }
+
+    /// `content_block_stop` frames for every open block
+    pub fn close_open_blocks(&mut self, sse: &mut SseEncoder) -> Vec<Bytes> {
+        self.open_blocks
+            .drain(..)
+            .map(|index| sse.message_event(&MessageStreamEvent::ContentBlockStop { index }))
+            .collect()
+    }
+
+    /// Frames ending a stream that failed after partial output
+    pub fn error_frames(
+        &mut self,
+        sse: &mut SseEncoder,
+        error_type: &str,
+        message: &str,
+    ) -> Vec<Bytes> {
+        if self.quirks.bare_stream_errors {
+            self.open_blocks.clear();
+            return vec![sse.message_event(&MessageStreamEvent::Error {
+                error: StreamError { error_type, message },
+            })];
+        }
+
+        let mut frames = self.close_open_blocks(sse);
+        frames.push(sse.message_event(&MessageStreamEvent::MessageDelta {
+            delta: StreamStopDelta {
+                stop_reason: Some(ERROR_STOP_REASON),
+                stop_sequence: None,
+                container: self.container.as_ref(),
+            },
+            usage: StreamOutputUsage { output_tokens: self.output_tokens() },
+        }));
+        frames.push(sse.message_event(&MessageStreamEvent::Error {
+            error: StreamError { error_type, message },
+        }));
+        frames
+    }
+}
+
+// ============================================================================
+// OpenAI Stream Chunks
+// ============================================================================
+
+/// OpenAI `chat.completion.chunk` (borrowed counterpart of `ChatCompletionChunk`)
+#[derive(Debug, Serialize)]
+pub struct ChunkRef<'a> {
+    pub id: &'a str,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: &'a str,
+    pub choices: &'a [ChunkChoiceRef<'a>],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<&'a CompletionUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<&'a str>,
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Chunk carrying the given choices and no usage
+    pub fn new(id: &'a str, created: i64, model: &'a str, choices: &'a [ChunkChoiceRef<'a>]) -> Self {
+        Self {
+            id,
+            object: "chat.completion.chunk",
+            created,
+            model,
+            choices,
+            usage: None,
+            service_tier: None,
+            system_fingerprint: None,
+        }
+    }
+}
+
+/// Streaming choice
+#[derive(Debug, Serialize)]
+pub struct ChunkChoiceRef<'a> {
+    pub index: i32,
+    pub delta: ChunkDeltaRef<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<&'a serde_json::Value>,
+}
+
+impl<'a> ChunkChoiceRef<'a> {
+    /// First choice with the given delta
+    pub fn delta(delta: ChunkDeltaRef<'a>) -> Self {
+        Self {
+            index: 0,
+            delta,
+            finish_reason: None,
+            logprobs: None,
+        }
+    }
+
+    /// Attach token log probabilities for this chunk's content
+    pub fn with_logprobs(mut self, logprobs: Option<&'a serde_json::Value>) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+
+    /// First choice with an empty delta and a finish reason
+    pub fn finish(reason: &'a str) -> Self {
+        Self {
+            index: 0,
+            delta: ChunkDeltaRef::default(),
+            finish_reason: Some(reason),
+            logprobs: None,
+        }
+    }
+}
+
+/// Delta content in streaming
+#[derive(Debug, Default, Serialize)]
+pub struct ChunkDeltaRef<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<ChatRole>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<[ToolCallDeltaRef<'a>; 1]>,
+}
+
+/// Tool call delta in streaming
+#[derive(Debug, Serialize)]
+pub struct ToolCallDeltaRef<'a> {
+    pub index: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<&'a str>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub tool_type: Option<&'static str>,
+    pub function: FunctionCallDeltaRef<'a>,
+}
+
+/// Function call delta in streaming
+#[derive(Debug, Serialize)]
+pub struct FunctionCallDeltaRef<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<&'a str>,
+}
+
+/// Orders streamed tool calls the way OpenAI sends them
+///
+/// Bedrock may interleave the argument deltas of parallel tool calls. OpenAI
+/// never does, and client parsers (LangChain's among them) assume each call's
+/// chunks are contiguous, numbered from 0, and that only the first carries
+/// the id and name. Calls that open while an earlier one is still streaming
+/// are held back until it stops.
+#[derive(Debug, Default)]
+pub struct ToolCallSequencer {
+    calls: Vec<SequencedCall>,
+    block_to_call: HashMap<i32, usize>,
+    /// First call not yet fully sent
+    active: usize,
+}
+
+#[derive(Debug)]
+struct SequencedCall {
+    id: String,
+    name: String,
+    pending: String,
+    header_sent: bool,
+    sent_arguments: bool,
+    stopped: bool,
+}
+
+/// A tool call delta ready to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCallDelta {
+    pub index: i32,
+    /// Id and name, on the first delta of each call only
+    pub header: Option<(String, String)>,
+    pub arguments: String,
+}
+
+impl ToolCallDelta {
+    pub fn to_delta_ref(&self) -> ToolCallDeltaRef<'_> {
+        ToolCallDeltaRef {
+            index: self.index,
+            id: self.header.as_ref().map(|(id, _)| id.as_str()),
+            tool_type: self.header.is_some().then_some("function"),
+            function: FunctionCallDeltaRef {
+                name: self.header.as_ref().map(|(_, name)| name.as_str()),
+                arguments: Some(self.arguments.as_str()),
+            },
+        }
+    }
+}
+
+impl ToolCallSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether any tool call was started
+    pub fn has_calls(&self) -> bool {
+        !self.calls.is_empty()
+    }
+
+    /// A tool use block started
+    pub fn start(&mut self, block_index: i32, id: &str, name: &str) -> Vec<ToolCallDelta> {
+        self.block_to_call.insert(block_index, self.calls.len());
+        self.calls.push(SequencedCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            pending: String::new(),
+            header_sent: false,
+            sent_arguments: false,
+            stopped: false,
+        });
+        self.drain()
+    }
+
+    /// Argument text for a tool use block; unknown blocks are ignored
+    pub fn arguments(&mut self, block_index: i32, arguments: &str) -> Vec<ToolCallDelta> {
+        match self.block_to_call.get(&block_index) {
+            Some(&call) => {
+                self.calls[call].pending.push_str(arguments);
+                self.drain()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// A content block stopped
+    pub fn stop(&mut self, block_index: i32) -> Vec<ToolCallDelta> {
+        match self.block_to_call.get(&block_index) {
+            Some(&call) => {
+                self.calls[call].stopped = true;
+                self.drain()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Send everything still held back, before the finish reason
+    pub fn finish(&mut self) -> Vec<ToolCallDelta> {
+        for call in &mut self.calls {
+            call.stopped = true;
+        }
+        self.drain()
+    }
+
+    fn drain(&mut self) -> Vec<ToolCallDelta> {
+        let mut deltas = Vec::new();
+        while let Some(call) = self.calls.get_mut(self.active) {
+            let index = self.active as i32;
+            if !call.header_sent {
+                call.header_sent = true;
+                deltas.push(ToolCallDelta {
+                    index,
+                    header: Some((call.id.clone(), call.name.clone())),
+                    arguments: String::new(),
+                });
+            }
+            if !call.pending.is_empty() {
+                call.sent_arguments = true;
+                deltas.push(ToolCallDelta {
+                    index,
+                    header: None,
+                    arguments: std::mem::take(&mut call.pending),
+                });
+            }
+            if !call.stopped {
+                break;
+            }
+            // A call without parameters still gets parseable arguments
+            if !call.sent_arguments {
+                deltas.push(ToolCallDelta {
+                    index,
+                    header: None,
+                    arguments: "{}".to_string(),
+                });
+            }
+            self.active += 1;
+        }
+        deltas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(frame: &[u8], expected_event: Option<&str>) -> serde_json::Value {
+        let text = std::str::from_utf8(frame).unwrap();
+        let text = text.strip_suffix("\n\n").expect("frame terminator");
+        let data = match expected_event {
+            Some(name) => text
+                .strip_prefix(&format!("event: {}\n", name))
+                .expect("event line"),
+            None => text,
+        };
+        serde_json::from_str(data.strip_prefix("data: ").expect("data line")).unwrap()
+    }
+
+    #[test]
+    fn test_message_events_match_anthropic_shape() {
+        let mut encoder = SseEncoder::new();
+
+        let start = encoder.message_event(&MessageStreamEvent::MessageStart {
+            message: StreamMessageStart::new("msg_1", "claude-sonnet-4-5"),
+        });
+        assert_eq!(
+            payload(&start, Some("message_start")),
+            serde_json::json!({
+                "type": "message_start",
+                "message": {
+                    "id": "msg_1", "type": "message", "role": "assistant", "content": [],
+                    "model": "claude-sonnet-4-5", "stop_reason": null, "stop_sequence": null,
+                    "usage": {"input_tokens": 0, "output_tokens": 0}
+                }
+            })
+        );
+
+        let tool = encoder.message_event(&MessageStreamEvent::ContentBlockStart {
+            index: 1,
+            content_block: StreamContentBlock::ToolUse {
+                id: "toolu_1",
+                name: "get_weather",
+                input: EmptyObject {},
+            },
+        });
+        assert_eq!(
+            payload(&tool, Some("content_block_start")),
+            serde_json::json!({
+                "type": "content_block_start", "index": 1,
+                "content_block": {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {}}
+            })
+        );
+
+        let delta = encoder.message_event(&MessageStreamEvent::ContentBlockDelta {
+            index: 0,
+            delta: StreamDelta::TextDelta { text: "line one\nline \"two\"" },
+        });
+        assert_eq!(delta.iter().filter(|&&b| b == b'\n').count(), 3);
+        assert_eq!(
+            payload(&delta, Some("content_block_delta"))["delta"]["text"],
+            "line one\nline \"two\""
+        );
+
+        let stop = encoder.message_event(&MessageStreamEvent::MessageDelta {
+            delta: StreamStopDelta { stop_reason: Some("end_turn"), stop_sequence: None, container: None },
+            usage: StreamOutputUsage { output_tokens: 12 },
+        });
+        assert_eq!(
+            payload(&stop, Some("message_delta")),
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": "end_turn", "stop_sequence": null},
+                "usage": {"output_tokens": 12}
+            })
+        );
+
+        let end = encoder.message_event(&MessageStreamEvent::MessageStop);
+        assert_eq!(&end[..], b"event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+    }
+
+    #[test]
+    fn test_openai_chunks_and_done() {
+        let mut encoder = SseEncoder::new();
+
+        let chunk = encoder.data(&ChunkRef::new(
+            "chatcmpl-1",
+            1700000000,
+            "gpt-4o",
+            &[ChunkChoiceRef::delta(ChunkDeltaRef {
+                tool_calls: Some([ToolCallDeltaRef {
+                    index: 0,
+                    id: Some("call_1"),
+                    tool_type: Some("function"),
+                    function: FunctionCallDeltaRef { name: Some("lookup"), arguments: None },
+                }]),
+                ..Default::default()
+            })],
+        ));
+        assert_eq!(
+            payload(&chunk, None),
+            serde_json::json!({
+                "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "delta": {"tool_calls": [
+                    {"index": 0, "id": "call_1", "type": "function", "function": {"name": "lookup"}}
+                ]}}]
+            })
+        );
+
+        assert_eq!(&encoder.raw_data("[DONE]")[..], b"data: [DONE]\n\n");
+    }
+
+    #[test]
+    fn test_buffer_reused_after_frames_dropped() {
+        let mut encoder = SseEncoder::new();
+        let base = encoder.buf.as_ptr() as usize;
+
+        // Far more data than the initial capacity, but each frame is dropped
+        // (as hyper does once written) before the next is encoded
+        for i in 0..200 {
+            let frame = encoder.data(&StreamOutputUsage { output_tokens: i });
+            let ptr = frame.as_ptr() as usize;
+            assert!(ptr >= base && ptr + frame.len() <= base + INITIAL_CAPACITY);
+        }
+    }
+
+    #[test]
+    fn test_error_frames_close_open_blocks() {
+        let mut encoder = SseEncoder::new();
+        let mut progress = StreamProgress::new();
+        progress.block_started(0);
+        progress.record_output("Hello, wor");
+        progress.block_stopped(0);
+        progress.block_started(1);
+        progress.record_output("{\"city\":");
+
+        let frames = progress.error_frames(&mut encoder, "overloaded_error", "Bedrock is overloaded");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            payload(&frames[0], Some("content_block_stop")),
+            serde_json::json!({"type": "content_block_stop", "index": 1})
+        );
+        assert_eq!(
+            payload(&frames[1], Some("message_delta")),
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": "error", "stop_sequence": null},
+                "usage": {"output_tokens": 5}
+            })
+        );
+        assert_eq!(
+            payload(&frames[2], Some("error")),
+            serde_json::json!({
+                "type": "error",
+                "error": {"type": "overloaded_error", "message": "Bedrock is overloaded"}
+            })
+        );
+        assert!(!progress.has_open_blocks());
+
+        // The backend's count wins once reported
+        progress.set_output_tokens(12);
+        assert_eq!(progress.output_tokens(), 12);
+    }
+
+    #[test]
+    fn test_client_quirks() {
+        let mut encoder = SseEncoder::new();
+        assert!(StreamProgress::new().ping_after_start(&mut encoder).is_none());
+
+        let mut progress = StreamProgress::new().with_quirks(StreamQuirks {
+            ping_after_start: true,
+            bare_stream_errors: true,
+            ..StreamQuirks::default()
+        });
+        let ping = progress.ping_after_start(&mut encoder).unwrap();
+        assert_eq!(payload(&ping, Some("ping")), serde_json::json!({"type": "ping"}));
+
+        progress.block_started(0);
+        let frames = progress.error_frames(&mut encoder, "api_error", "Stream failed");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(
+            payload(&frames[0], Some("error")),
+            serde_json::json!({
+                "type": "error",
+                "error": {"type": "api_error", "message": "Stream failed"}
+            })
+        );
+        assert!(!progress.has_open_blocks());
+    }
+
+    #[test]
+    fn test_interim_usage_reports() {
+        let mut encoder = SseEncoder::new();
+        let mut progress = StreamProgress::new().with_usage_config(StreamUsageConfig {
+            interval_tokens: 10,
+            mode: "comment".to_string(),
+        });
+
+        progress.record_output(&"x".repeat(36));
+        assert!(progress.interim_usage(&mut encoder).is_none());
+
+        progress.record_output("xxxx");
+        let frame = progress.interim_usage(&mut encoder).unwrap();
+        assert_eq!(&frame[..], b": usage {\"output_tokens\":10}\n\n");
+        assert!(progress.interim_usage(&mut encoder).is_none());
+
+        let mut progress = StreamProgress::new().with_usage_config(StreamUsageConfig {
+            interval_tokens: 10,
+            mode: "message_delta".to_string(),
+        });
+        progress.record_output(&"x".repeat(80));
+        let frame = progress.interim_usage(&mut encoder).unwrap();
+        assert_eq!(
+            payload(&frame, Some("message_delta")),
+            serde_json::json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": null, "stop_sequence": null},
+                "usage": {"output_tokens": 20}
+            })
+        );
+
+        let mut disabled = StreamProgress::new().with_usage_config(StreamUsageConfig {
+            interval_tokens: 0,
+            mode: "message_delta".to_string(),
+        });
+        disabled.record_output(&"x".repeat(400));
+        assert!(disabled.interim_usage(&mut encoder).is_none());
+    }
+
+    #[test]
+    fn test_single_tool_use_skips_later_blocks() {
+        let mut progress = StreamProgress::new().with_single_tool_use(true);
+        assert!(!progress.skip_tool_use(1));
+        assert!(progress.skip_tool_use(2));
+        assert!(progress.is_skipped(2));
+        assert!(!progress.is_skipped(1));
+        // Blocks after a dropped one close the gap
+        assert_eq!(progress.client_index(3), 2);
+
+        let mut unrestricted = StreamProgress::new();
+        assert!(!unrestricted.skip_tool_use(1));
+        assert!(!unrestricted.skip_tool_use(2));
+        assert_eq!(unrestricted.client_index(3), 3);
+    }
+
+    #[test]
+    fn test_output_ceiling_trips_once() {
+        let watchdog = Arc::new(crate::services::OutputWatchdog::new(
+            &crate::config::OutputWatchdogConfig { max_output_tokens: 10 },
+        ));
+        let mut progress = StreamProgress::new()
+            .with_output_ceiling(watchdog.ceiling("sk-a", "team-a", "claude"));
+        progress.record_output(&"x".repeat(36));
+        assert!(!progress.output_ceiling_reached());
+        progress.record_output("xxxx");
+        assert!(progress.output_ceiling_reached());
+        assert!(!progress.output_ceiling_reached());
+        assert_eq!(watchdog.trips()[0].count, 1);
+
+        let mut unlimited = StreamProgress::new();
+        unlimited.record_output(&"x".repeat(400));
+        assert!(!unlimited.output_ceiling_reached());
+    }
+
+    #[test]
+    fn test_tool_call_sequencer_holds_back_later_calls() {
+        type Sent = (i32, Option<String>, String);
+        let sent = |deltas: Vec<ToolCallDelta>| -> Vec<Sent> {
+            deltas
+                .into_iter()
+                .map(|d| (d.index, d.header.map(|(id, _)| id), d.arguments))
+                .collect()
+        };
+        let expect = |index: i32, id: Option<&str>, arguments: &str| -> Sent {
+            (index, id.map(str::to_string), arguments.to_string())
+        };
+        let mut calls = ToolCallSequencer::new();
+
+        assert_eq!(sent(calls.start(1, "a", "f")), vec![expect(0, Some("a"), "")]);
+        assert!(calls.start(2, "b", "g").is_empty());
+        assert!(calls.arguments(2, "{\"x\": 1}").is_empty());
+        assert_eq!(sent(calls.arguments(1, "{}")), vec![expect(0, None, "{}")]);
+        // Deltas for blocks that are not tool calls are ignored
+        assert!(calls.arguments(0, "text").is_empty());
+        assert_eq!(
+            sent(calls.stop(1)),
+            vec![expect(1, Some("b"), ""), expect(1, None, "{\"x\": 1}")]
+        );
+        assert!(calls.finish().is_empty());
+
+        // A call that never streamed arguments gets an empty object
+        let mut calls = ToolCallSequencer::new();
+        calls.start(0, "c", "h");
+        assert_eq!(sent(calls.finish()), vec![expect(0, None, "{}")]);
+        assert!(calls.has_calls());
+    }
+}
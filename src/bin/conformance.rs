@@ -0,0 +1,108 @@
+//! CLI tool to run converter conformance cases
+//!
+//! Usage:
+//!   cargo run --bin conformance
+//!   cargo run --bin conformance -- recorded/production.jsonl
+//!
+//! Record goldens for new cases (or accept an intended change):
+//!   cargo run --bin conformance -- --bless
+
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use llm_api_converter::conformance::{self, run_case};
+
+/// Run converter round trips and diff them against golden output
+#[derive(Parser, Debug)]
+#[command(name = "conformance")]
+#[command(about = "Run converter round trips and diff them against golden output")]
+struct Args {
+    /// Case file (.json / .jsonl) or directory of case files
+    #[arg(default_value = "tests/fixtures/conformance")]
+    path: PathBuf,
+
+    /// Only run cases whose name contains this string
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Record the current output as the goldens instead of diffing
+    #[arg(long)]
+    bless: bool,
+
+    /// Print each report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    let selected = |name: &str| args.filter.as_deref().map_or(true, |f| name.contains(f));
+
+    let mut files = conformance::load(&args.path)?;
+    let (mut passed, mut failed, mut unverified) = (0, 0, 0);
+
+    for file in &mut files {
+        let mut blessed = 0;
+        for case in file.cases.iter_mut().filter(|c| selected(&c.name)) {
+            if args.bless {
+                match conformance::bless(case) {
+                    Ok(()) => blessed += 1,
+                    Err(e) => {
+                        failed += 1;
+                        println!("ERROR {} ({}): {}", case.name, case.pipeline, e);
+                    }
+                }
+                continue;
+            }
+
+            let report = run_case(case);
+            if args.json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else if let Some(ref error) = report.error {
+                println!("ERROR {} ({}): {}", report.name, report.pipeline, error);
+            } else if !report.differences.is_empty() {
+                println!("DRIFT {} ({})", report.name, report.pipeline);
+                for d in &report.differences {
+                    println!(
+                        "  {}{}: expected {} got {}",
+                        d.stage,
+                        d.path,
+                        d.expected
+                            .as_ref()
+                            .map_or("<absent>".to_string(), |v| v.to_string()),
+                        d.actual
+                            .as_ref()
+                            .map_or("<absent>".to_string(), |v| v.to_string()),
+                    );
+                }
+            } else if !report.verified {
+                println!("NOGOLD {} ({})", report.name, report.pipeline);
+            }
+
+            match (report.passed(), report.verified) {
+                (false, _) => failed += 1,
+                (true, true) => passed += 1,
+                (true, false) => unverified += 1,
+            }
+        }
+
+        if blessed > 0 {
+            file.save()?;
+            println!("Blessed {} case(s) in {}", blessed, file.path.display());
+        }
+    }
+
+    if !args.bless {
+        println!(
+            "{} passed, {} failed, {} without goldens",
+            passed, failed, unverified
+        );
+    }
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
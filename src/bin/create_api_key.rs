@@ -35,6 +35,38 @@ struct Args {
     #[arg(long)]
     monthly_budget: Option<f64>,
 
+    /// Create a trial key that expires after this many days
+    #[arg(long)]
+    trial_days: Option<i64>,
+
+    /// Allowed model patterns, comma-separated (e.g., "claude-3-5-haiku-*")
+    #[arg(long, value_delimiter = ',')]
+    allowed_models: Vec<String>,
+
+    /// CIDR ranges the key may be used from, comma-separated (e.g., "10.0.0.0/8")
+    #[arg(long, value_delimiter = ',')]
+    allowed_ips: Vec<String>,
+
+    /// Permission scopes, comma-separated (e.g., "backend_override")
+    #[arg(long, value_delimiter = ',')]
+    scopes: Vec<String>,
+
+    /// Code-execution iterations per PTC session (optional)
+    #[arg(long)]
+    ptc_max_iterations: Option<u32>,
+
+    /// Output token cap for each model turn of a PTC session (optional)
+    #[arg(long)]
+    ptc_max_tokens_per_iteration: Option<u32>,
+
+    /// Total tokens a PTC session may use (optional)
+    #[arg(long)]
+    ptc_session_token_budget: Option<u64>,
+
+    /// Inference preset applied to the key's requests (optional)
+    #[arg(long)]
+    inference_preset: Option<String>,
+
     /// DynamoDB table name
     #[arg(long, default_value = "anthropic-proxy-api-keys")]
     table_name: String,
@@ -58,6 +90,12 @@ async fn main() -> Result<()> {
     // Generate API key
     let api_key = format!("sk-{}", Uuid::new_v4());
     let created_at = Utc::now().timestamp();
+    let expires_at = args.trial_days.map(|days| created_at + days * 86_400);
+    let service_tier = if expires_at.is_some() {
+        "trial".to_string()
+    } else {
+        args.service_tier.clone()
+    };
 
     // Configure AWS SDK
     let region_str = args
@@ -85,7 +123,7 @@ async fn main() -> Result<()> {
     item.insert("created_at".to_string(), AttributeValue::N(created_at.to_string()));
     item.insert("is_active".to_string(), AttributeValue::Bool(true));
     item.insert("rate_limit".to_string(), AttributeValue::N(args.rate_limit.to_string()));
-    item.insert("service_tier".to_string(), AttributeValue::S(args.service_tier.clone()));
+    item.insert("service_tier".to_string(), AttributeValue::S(service_tier.clone()));
     item.insert("budget_used".to_string(), AttributeValue::N("0".to_string()));
     item.insert("budget_used_mtd".to_string(), AttributeValue::N("0".to_string()));
 
@@ -93,6 +131,35 @@ async fn main() -> Result<()> {
         item.insert("monthly_budget".to_string(), AttributeValue::N(budget.to_string()));
     }
 
+    if !args.scopes.is_empty() {
+        item.insert("scopes".to_string(), AttributeValue::Ss(args.scopes.clone()));
+    }
+
+    if let Some(expires_at) = expires_at {
+        item.insert("expires_at".to_string(), AttributeValue::N(expires_at.to_string()));
+    }
+
+    if !args.allowed_models.is_empty() {
+        item.insert("allowed_models".to_string(), AttributeValue::Ss(args.allowed_models.clone()));
+    }
+
+    if !args.allowed_ips.is_empty() {
+        item.insert("allowed_ips".to_string(), AttributeValue::Ss(args.allowed_ips.clone()));
+    }
+
+    if let Some(n) = args.ptc_max_iterations {
+        item.insert("ptc_max_iterations".to_string(), AttributeValue::N(n.to_string()));
+    }
+    if let Some(n) = args.ptc_max_tokens_per_iteration {
+        item.insert("ptc_max_tokens_per_iteration".to_string(), AttributeValue::N(n.to_string()));
+    }
+    if let Some(n) = args.ptc_session_token_budget {
+        item.insert("ptc_session_token_budget".to_string(), AttributeValue::N(n.to_string()));
+    }
+    if let Some(ref preset) = args.inference_preset {
+        item.insert("inference_preset".to_string(), AttributeValue::S(preset.clone()));
+    }
+
     // Put item into DynamoDB
     dynamodb_client
         .put_item()
@@ -106,10 +173,19 @@ async fn main() -> Result<()> {
     println!("User ID: {}", args.user_id);
     println!("Name: {}", args.name);
     println!("Rate Limit: {} requests/minute", args.rate_limit);
-    println!("Service Tier: {}", args.service_tier);
+    println!("Service Tier: {}", service_tier);
+    if let Some(expires_at) = expires_at {
+        println!("Expires At: {} (unix)", expires_at);
+    }
     if let Some(budget) = args.monthly_budget {
         println!("Monthly Budget: ${:.2}", budget);
     }
+    if !args.allowed_ips.is_empty() {
+        println!("Allowed IPs: {}", args.allowed_ips.join(", "));
+    }
+    if !args.scopes.is_empty() {
+        println!("Scopes: {}", args.scopes.join(", "));
+    }
     println!("\nUse this key with:");
     println!("  ANTHROPIC_API_KEY=\"{}\"", api_key);
 
@@ -81,6 +81,16 @@ async fn main() -> Result<()> {
             "model_id",
             ScalarAttributeType::S,
         ),
+        (
+            format!("{}-feature-flags", args.prefix),
+            "name",
+            ScalarAttributeType::S,
+        ),
+        (
+            format!("{}-inference-presets", args.prefix),
+            "name",
+            ScalarAttributeType::S,
+        ),
     ];
 
     println!("\n🚀 Setting up DynamoDB tables...\n");
@@ -1,13 +1,21 @@
 //! AWS SDK configuration
 //!
 //! This module provides AWS SDK configuration for Bedrock and DynamoDB clients,
-//! supporting custom endpoints for local development and testing.
+//! supporting custom endpoints for local development and testing, and per-client
+//! tuning of timeouts, retries and the HTTP connection pool.
 
-use aws_config::{meta::region::RegionProviderChain, BehaviorVersion, Region, SdkConfig};
+use aws_config::{
+    meta::region::RegionProviderChain, retry::RetryConfig, timeout::TimeoutConfig,
+    BehaviorVersion, ConfigLoader, Region, SdkConfig,
+};
 use aws_sdk_bedrockruntime::Client as BedrockRuntimeClient;
+#[cfg(feature = "dynamodb")]
 use aws_sdk_dynamodb::Client as DynamoDbSdkClient;
+use aws_smithy_http_client::tls::{rustls_provider::CryptoMode, Provider as TlsProvider};
+use aws_smithy_runtime_api::client::http::SharedHttpClient;
+use std::time::Duration;
 
-use crate::config::Settings;
+use crate::config::{AwsClientConfig, Settings};
 
 /// AWS configuration builder
 ///
@@ -32,21 +40,30 @@ impl<'a> AwsConfigBuilder<'a> {
     /// - Region configuration from settings
     /// - Credential chain (env vars, instance profile, etc.)
     pub async fn build_sdk_config(&self) -> SdkConfig {
-        let region_provider = RegionProviderChain::first_try(Region::new(self.settings.aws_region.clone()))
-            .or_default_provider();
+        self.region_loader().load().await
+    }
 
-        aws_config::defaults(BehaviorVersion::latest())
-            .region(region_provider)
+    /// Build SDK configuration tuned for one client
+    pub async fn build_tuned_sdk_config(&self, client_config: &AwsClientConfig) -> SdkConfig {
+        apply_client_config(self.region_loader(), client_config)
             .load()
             .await
     }
 
+    fn region_loader(&self) -> ConfigLoader {
+        let region_provider = RegionProviderChain::first_try(Region::new(self.settings.aws_region.clone()))
+            .or_default_provider();
+
+        aws_config::defaults(BehaviorVersion::latest()).region(region_provider)
+    }
+
     /// Create a DynamoDB client with optional custom endpoint
     ///
     /// If `DYNAMODB_ENDPOINT_URL` is set in settings, the client will use
     /// that endpoint (useful for DynamoDB Local or LocalStack).
+    #[cfg(feature = "dynamodb")]
     pub async fn build_dynamodb_client(&self) -> DynamoDbSdkClient {
-        let sdk_config = self.build_sdk_config().await;
+        let sdk_config = self.build_tuned_sdk_config(&self.settings.dynamodb_client).await;
 
         if let Some(endpoint_url) = &self.settings.dynamodb_endpoint_url {
             tracing::info!(endpoint = %endpoint_url, "Using custom DynamoDB endpoint");
@@ -66,7 +83,7 @@ impl<'a> AwsConfigBuilder<'a> {
     /// If `BEDROCK_ENDPOINT_URL` is set in settings, the client will use
     /// that endpoint (useful for testing with mocks).
     pub async fn build_bedrock_client(&self) -> BedrockRuntimeClient {
-        let sdk_config = self.build_sdk_config().await;
+        let sdk_config = self.build_tuned_sdk_config(&self.settings.bedrock_client).await;
 
         if let Some(endpoint_url) = &self.settings.bedrock_endpoint_url {
             tracing::info!(endpoint = %endpoint_url, "Using custom Bedrock endpoint");
@@ -82,12 +99,51 @@ impl<'a> AwsConfigBuilder<'a> {
     }
 }
 
+/// Apply timeout, retry and connection pool tuning to an SDK config loader
+pub fn apply_client_config(loader: ConfigLoader, config: &AwsClientConfig) -> ConfigLoader {
+    let mut timeouts =
+        TimeoutConfig::builder().connect_timeout(Duration::from_millis(config.connect_timeout_ms));
+    if let Some(ms) = config.read_timeout_ms {
+        timeouts = timeouts.read_timeout(Duration::from_millis(ms));
+    }
+
+    let retry = if config.is_adaptive_retry() {
+        RetryConfig::adaptive()
+    } else {
+        RetryConfig::standard()
+    }
+    .with_max_attempts(config.max_attempts.max(1));
+
+    let loader = loader.timeout_config(timeouts.build()).retry_config(retry);
+    if config.has_http_overrides() {
+        loader.http_client(build_http_client(config))
+    } else {
+        loader
+    }
+}
+
+/// Build an HTTPS client with the configured connection pool settings
+fn build_http_client(config: &AwsClientConfig) -> SharedHttpClient {
+    let mut builder = aws_smithy_http_client::Builder::new();
+    if let Some(max_idle) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(secs) = config.pool_idle_timeout_secs {
+        builder = builder.pool_idle_timeout(Duration::from_secs(secs));
+    }
+
+    builder
+        .tls_provider(TlsProvider::Rustls(CryptoMode::AwsLc))
+        .build_https()
+}
+
 /// Build AWS SDK config from settings (convenience function)
 pub async fn build_aws_config(settings: &Settings) -> SdkConfig {
     AwsConfigBuilder::new(settings).build_sdk_config().await
 }
 
 /// Create a DynamoDB client from settings (convenience function)
+#[cfg(feature = "dynamodb")]
 pub async fn create_dynamodb_client(settings: &Settings) -> DynamoDbSdkClient {
     AwsConfigBuilder::new(settings).build_dynamodb_client().await
 }
@@ -105,6 +161,7 @@ pub async fn create_bedrock_client_with_profile(
     profile: Option<&str>,
     region: &str,
     endpoint_url: Option<&str>,
+    client_config: &AwsClientConfig,
 ) -> BedrockRuntimeClient {
     let region = Region::new(region.to_string());
 
@@ -119,7 +176,7 @@ pub async fn create_bedrock_client_with_profile(
         aws_config::defaults(BehaviorVersion::latest()).region(region)
     };
 
-    let sdk_config = config_loader.load().await;
+    let sdk_config = apply_client_config(config_loader, client_config).load().await;
 
     if let Some(endpoint) = endpoint_url {
         let bedrock_config = aws_sdk_bedrockruntime::config::Builder::from(&sdk_config)
@@ -145,6 +202,7 @@ mod tests {
         assert_eq!(config.region().unwrap().as_ref(), "us-east-1");
     }
 
+    #[cfg(feature = "dynamodb")]
     #[tokio::test]
     async fn test_dynamodb_client_creation() {
         let settings = Settings::default();
@@ -159,6 +217,32 @@ mod tests {
         // Client created successfully
     }
 
+    #[tokio::test]
+    async fn test_client_tuning_applied() {
+        let tuning = AwsClientConfig {
+            connect_timeout_ms: 500,
+            read_timeout_ms: Some(30_000),
+            retry_mode: "adaptive".to_string(),
+            max_attempts: 5,
+            ..Default::default()
+        };
+        let config = apply_client_config(
+            aws_config::defaults(BehaviorVersion::latest()).region(Region::new("us-west-2")),
+            &tuning,
+        )
+        .load()
+        .await;
+
+        let timeouts = config.timeout_config().unwrap();
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_millis(500)));
+        assert_eq!(timeouts.read_timeout(), Some(Duration::from_secs(30)));
+
+        let retry = config.retry_config().unwrap();
+        assert_eq!(retry.mode(), aws_config::retry::RetryMode::Adaptive);
+        assert_eq!(retry.max_attempts(), 5);
+    }
+
+    #[cfg(feature = "dynamodb")]
     #[tokio::test]
     async fn test_custom_endpoint_dynamodb() {
         let mut settings = Settings::default();
@@ -7,10 +7,19 @@ pub mod aws;
 pub mod settings;
 
 pub use aws::{
-    build_aws_config, create_bedrock_client, create_bedrock_client_with_profile,
-    create_dynamodb_client, AwsConfigBuilder,
+    build_aws_config, create_bedrock_client, create_bedrock_client_with_profile, AwsConfigBuilder,
 };
+#[cfg(feature = "dynamodb")]
+pub use aws::create_dynamodb_client;
 pub use settings::{
-    BackendPoolConfig, BedrockConfig, BedrockProfileConfig, Environment, FeatureFlags,
-    GeminiConfig, PtcConfig, RateLimitConfig, Settings,
+    AnthropicConfig, AwsClientConfig, AzureOpenAIConfig, BackendPoolConfig, BedrockConfig, BedrockExtraFieldsConfig,
+    BedrockProfileConfig, BodyLimitConfig, BruteForceConfig, BudgetDowngradeConfig,
+    BudgetWarningConfig, ClientCompatConfig, CompletionStoreConfig, CompressionConfig, ContentOffloadConfig, Environment, EvalSinkConfig,
+    FeatureFlags, GeminiConfig, IpFilterConfig, KeyActivityConfig, McpConfig, ModelDeprecation,
+    ModelDeprecationConfig,
+    ModelRouteConfig, OutputWatchdogConfig, PiiTokenizationConfig, ProvisionedThroughputConfig,
+    PtcConfig, RateLimitConfig,
+    RoutingConfig, Settings, SlowClientPolicy, StorageConfig, StreamAssemblyConfig, StreamBackpressureConfig,
+    StreamUsageConfig, TemperatureScaling, TranscriptionConfig, TrialConfig,
+    UsageReconciliationConfig,
 };
@@ -3,7 +3,9 @@
 //! This module provides configuration management for the application,
 //! loading settings from environment variables with sensible defaults.
 
+use crate::services::provider::model_matches_pattern;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -57,6 +59,16 @@ pub struct RateLimitConfig {
     pub enabled: bool,
     pub requests_per_window: u32,
     pub window_seconds: u64,
+    /// Requests a key may make at once (default: its per-window limit)
+    pub burst: Option<u32>,
+    /// Where buckets live: "memory" (per replica) or "redis" (shared)
+    pub store: String,
+    /// Redis for the shared store, e.g. "redis://localhost:6379"
+    pub redis_url: Option<String>,
+    /// Prefix of the Redis bucket keys
+    pub redis_key_prefix: String,
+    /// Tokens (input and output) a key may use per minute (None = no limit)
+    pub tokens_per_minute: Option<u32>,
 }
 
 impl Default for RateLimitConfig {
@@ -65,6 +77,11 @@ impl Default for RateLimitConfig {
             enabled: true,
             requests_per_window: 100,
             window_seconds: 60,
+            burst: None,
+            store: "memory".to_string(),
+            redis_url: None,
+            redis_key_prefix: "ratelimit".to_string(),
+            tokens_per_minute: None,
         }
     }
 }
@@ -77,6 +94,8 @@ pub struct FeatureFlags {
     pub enable_extended_thinking: bool,
     pub enable_document_support: bool,
     pub prompt_caching_enabled: bool,
+    /// Collapse identical concurrent non-streaming requests into one upstream call
+    pub enable_request_coalescing: bool,
 }
 
 impl Default for FeatureFlags {
@@ -87,6 +106,7 @@ impl Default for FeatureFlags {
             enable_extended_thinking: true,
             enable_document_support: true,
             prompt_caching_enabled: true,
+            enable_request_coalescing: false,
         }
     }
 }
@@ -99,6 +119,8 @@ pub struct PtcConfig {
     pub execution_timeout_seconds: u64,
     pub memory_limit: String,
     pub network_disabled: bool,
+    /// Code executions from one assistant turn run at most this many at once
+    pub max_parallel_executions: usize,
 }
 
 impl Default for PtcConfig {
@@ -109,6 +131,7 @@ impl Default for PtcConfig {
             execution_timeout_seconds: 60,
             memory_limit: "256m".to_string(),
             network_disabled: true,
+            max_parallel_executions: 4,
         }
     }
 }
@@ -140,6 +163,70 @@ impl OpenAIConfig {
     }
 }
 
+/// How OpenAI's 0-2 temperature is mapped onto Bedrock's 0-1 range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureScaling {
+    /// Cap values above 1.0 (1.4 becomes 1.0)
+    #[default]
+    Clamp,
+    /// Halve the value so the whole range is preserved (1.4 becomes 0.7)
+    Linear,
+}
+
+impl TemperatureScaling {
+    /// Map an OpenAI temperature to the Bedrock range
+    pub fn apply(self, temperature: f32) -> f32 {
+        match self {
+            TemperatureScaling::Clamp => temperature.clamp(0.0, 1.0),
+            TemperatureScaling::Linear => (temperature / 2.0).clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl std::str::FromStr for TemperatureScaling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "clamp" => Ok(TemperatureScaling::Clamp),
+            "linear" | "scale" => Ok(TemperatureScaling::Linear),
+            _ => Err(anyhow::anyhow!("Invalid temperature scaling: {}", s)),
+        }
+    }
+}
+
+/// Audio transcription for OpenAI `input_audio` content parts
+///
+/// Uses an OpenAI-compatible `/audio/transcriptions` endpoint (OpenAI Whisper
+/// or a self-hosted Whisper server). Requests with audio are rejected while
+/// disabled.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TranscriptionConfig {
+    pub enabled: bool,
+    /// Base URL of the transcription API (without `/audio/transcriptions`)
+    pub base_url: String,
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    pub model: String,
+    pub timeout_seconds: u64,
+    /// Largest decoded audio part accepted
+    pub max_audio_bytes: usize,
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_url: "https://api.openai.com/v1".to_string(),
+            api_key: None,
+            model: "whisper-1".to_string(),
+            timeout_seconds: 60,
+            max_audio_bytes: 25 * 1024 * 1024,
+        }
+    }
+}
+
 /// DeepSeek API configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DeepSeekConfig {
@@ -170,7 +257,7 @@ impl DeepSeekConfig {
 /// Storage backend configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct StorageConfig {
-    /// Backend type: "dynamodb" or "sqlite"
+    /// Backend type: "dynamodb", "sqlite" or "memory"
     pub backend: String,
     /// Database URL for SQLite (e.g., "sqlite:///data/llm_proxy.db")
     pub database_url: Option<String>,
@@ -190,10 +277,18 @@ impl Default for StorageConfig {
 pub struct BackendPoolConfig {
     /// Load balance strategy: round_robin, weighted, random, failover
     pub strategy: String,
-    /// Maximum failures before disabling a credential
+    /// Consecutive failures that open a credential's circuit
     pub max_failures: u32,
-    /// Seconds to wait before retrying a disabled credential
+    /// Seconds a credential's circuit stays open before canary requests probe it
     pub retry_after_secs: u64,
+    /// Canary successes that close an open circuit again
+    pub probe_successes: u32,
+    /// Seconds a rate-limited (HTTP 429) credential sits out when the backend gives no delay
+    pub rate_limit_cooldown_secs: u64,
+    /// Credentials a throttled Bedrock request may try in total (1 = no failover)
+    pub failover_max_attempts: u32,
+    /// Seconds a credential sits out after a request failed over away from it
+    pub failover_cooldown_secs: u64,
     /// Health check interval in seconds
     pub health_check_interval_secs: u64,
 }
@@ -204,6 +299,10 @@ impl Default for BackendPoolConfig {
             strategy: "round_robin".to_string(),
             max_failures: 3,
             retry_after_secs: 300,
+            probe_successes: 2,
+            rate_limit_cooldown_secs: 60,
+            failover_max_attempts: 2,
+            failover_cooldown_secs: 30,
             health_check_interval_secs: 30,
         }
     }
@@ -257,42 +356,816 @@ impl GeminiConfig {
     }
 }
 
+/// Anthropic hosted API configuration (models routed to `anthropic`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnthropicConfig {
+    /// Enable the Anthropic backend
+    pub enabled: bool,
+    /// API keys (from ANTHROPIC_API_KEYS env, comma-separated)
+    #[serde(skip_serializing)]
+    pub api_keys: Vec<String>,
+    /// Base URL for the Messages API (default: https://api.anthropic.com/v1)
+    pub base_url: Option<String>,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_keys: Vec::new(),
+            base_url: None,
+            timeout_seconds: 600,
+        }
+    }
+}
+
+impl AnthropicConfig {
+    /// Check if Anthropic is enabled and has at least one API key configured
+    pub fn is_available(&self) -> bool {
+        self.enabled && !self.api_keys.is_empty()
+    }
+}
+
+/// Azure OpenAI configuration (models routed to `azure`)
+///
+/// Each endpoint is one Azure OpenAI resource. Resources authenticate with
+/// the API key at the same position in `api_keys` (a single key is shared by
+/// every endpoint), or with the Entra ID service principal when no keys are
+/// set.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AzureOpenAIConfig {
+    /// Enable the Azure OpenAI backend
+    pub enabled: bool,
+    /// Resource endpoints (from AZURE_OPENAI_ENDPOINTS env, comma-separated)
+    pub endpoints: Vec<String>,
+    /// Resource keys (from AZURE_OPENAI_API_KEYS env, comma-separated)
+    #[serde(skip_serializing)]
+    pub api_keys: Vec<String>,
+    /// Entra ID tenant for service principal auth
+    pub tenant_id: Option<String>,
+    /// Entra ID application (client) ID
+    pub client_id: Option<String>,
+    /// Entra ID client secret
+    #[serde(skip_serializing)]
+    pub client_secret: Option<String>,
+    /// Data-plane `api-version` query parameter
+    pub api_version: String,
+    /// Model name -> deployment name
+    /// (from AZURE_OPENAI_DEPLOYMENTS env, format: model=deployment,...)
+    pub deployments: HashMap<String, String>,
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for AzureOpenAIConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoints: Vec::new(),
+            api_keys: Vec::new(),
+            tenant_id: None,
+            client_id: None,
+            client_secret: None,
+            api_version: "2024-10-21".to_string(),
+            deployments: HashMap::new(),
+            timeout_seconds: 600,
+        }
+    }
+}
+
+impl AzureOpenAIConfig {
+    /// Check if Azure OpenAI is enabled with endpoints and a way to authenticate
+    pub fn is_available(&self) -> bool {
+        self.enabled
+            && !self.endpoints.is_empty()
+            && (!self.api_keys.is_empty() || self.uses_entra_id())
+    }
+
+    /// Whether a complete Entra ID service principal is configured
+    pub fn uses_entra_id(&self) -> bool {
+        self.tenant_id.is_some() && self.client_id.is_some() && self.client_secret.is_some()
+    }
+
+    /// API key for the endpoint at `index`, if keys are configured
+    pub fn api_key_for(&self, index: usize) -> Option<&str> {
+        match self.api_keys.as_slice() {
+            [] => None,
+            [key] => Some(key),
+            keys => keys.get(index).map(String::as_str),
+        }
+    }
+}
+
 /// AWS Bedrock configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct BedrockConfig {
-    /// Multiple profiles (from BEDROCK_PROFILES env, format: profile:region,profile:region)
-    #[serde(skip_serializing)]
-    pub profiles: Vec<BedrockProfileConfig>,
+pub struct BedrockConfig {
+    /// Multiple profiles (from BEDROCK_PROFILES env, format: profile:region,profile:region)
+    #[serde(skip_serializing)]
+    pub profiles: Vec<BedrockProfileConfig>,
+    /// Credentials the default Bedrock backend spreads requests over, one
+    /// cached client each (from BEDROCK_CREDENTIAL_POOL, same format as BEDROCK_PROFILES)
+    #[serde(skip_serializing)]
+    pub credential_pool: Vec<BedrockProfileConfig>,
+}
+
+impl Default for BedrockConfig {
+    fn default() -> Self {
+        Self {
+            profiles: Vec::new(),
+            credential_pool: Vec::new(),
+        }
+    }
+}
+
+/// AWS SDK client tuning
+///
+/// Loaded separately for the Bedrock (`BEDROCK_CLIENT_*`) and DynamoDB
+/// (`DYNAMODB_CLIENT_*`) clients. Pool settings left unset keep the SDK's
+/// default HTTP client.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AwsClientConfig {
+    /// TCP/TLS connect timeout in milliseconds
+    pub connect_timeout_ms: u64,
+    /// Time to wait for the first response byte in milliseconds (None = no limit)
+    pub read_timeout_ms: Option<u64>,
+    /// Retry mode: "standard" or "adaptive" (client-side rate limiting when throttled)
+    pub retry_mode: String,
+    /// Maximum attempts per request, including the first
+    pub max_attempts: u32,
+    /// Maximum idle pooled connections per host (None = unlimited)
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle pooled connection is kept before closing
+    pub pool_idle_timeout_secs: Option<u64>,
+}
+
+impl AwsClientConfig {
+    /// Load client tuning from `<prefix>*` environment variables
+    fn from_env(prefix: &str) -> Self {
+        let var = |name: &str| env::var(format!("{}{}", prefix, name)).ok();
+        let defaults = Self::default();
+        Self {
+            connect_timeout_ms: var("CONNECT_TIMEOUT_MS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.connect_timeout_ms),
+            read_timeout_ms: var("READ_TIMEOUT_MS").and_then(|v| v.parse().ok()),
+            retry_mode: var("RETRY_MODE")
+                .map(|v| v.trim().to_lowercase())
+                .unwrap_or(defaults.retry_mode),
+            max_attempts: var("MAX_ATTEMPTS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_attempts),
+            pool_max_idle_per_host: var("POOL_MAX_IDLE_PER_HOST").and_then(|v| v.parse().ok()),
+            pool_idle_timeout_secs: var("POOL_IDLE_TIMEOUT_SECS").and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// Whether adaptive retry mode is selected
+    pub fn is_adaptive_retry(&self) -> bool {
+        self.retry_mode == "adaptive"
+    }
+
+    /// Whether any connection pool setting is overridden
+    pub fn has_http_overrides(&self) -> bool {
+        self.pool_max_idle_per_host.is_some() || self.pool_idle_timeout_secs.is_some()
+    }
+}
+
+impl Default for AwsClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 3100,
+            read_timeout_ms: None,
+            retry_mode: "standard".to_string(),
+            max_attempts: 3,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_secs: None,
+        }
+    }
+}
+
+/// Single Bedrock profile configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BedrockProfileConfig {
+    pub name: String,
+    pub region: String,
+    pub profile: Option<String>,
+    #[serde(skip_serializing)]
+    pub access_key_id: Option<String>,
+    #[serde(skip_serializing)]
+    pub secret_access_key: Option<String>,
+    pub weight: u32,
+}
+
+impl BedrockConfig {
+    /// Check if multiple profiles are configured
+    pub fn has_multiple_profiles(&self) -> bool {
+        self.profiles.len() > 1
+    }
+}
+
+/// Trial key configuration
+///
+/// Applied to keys with the "trial" service tier.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrialConfig {
+    /// Model patterns trial keys may use when the key has no explicit list
+    pub allowed_models: Vec<String>,
+    /// Maximum requests per window for trial keys
+    pub rate_limit: u32,
+    /// Default monthly budget (USD) for newly minted trial keys
+    pub monthly_budget: f64,
+    /// Default trial duration in days
+    pub duration_days: i64,
+}
+
+impl Default for TrialConfig {
+    fn default() -> Self {
+        Self {
+            allowed_models: vec![
+                "claude-3-5-haiku-*".to_string(),
+                "claude-3-haiku-*".to_string(),
+            ],
+            rate_limit: 10,
+            monthly_budget: 5.0,
+            duration_days: 14,
+        }
+    }
+}
+
+/// Budget soft-cap warning configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BudgetWarningConfig {
+    pub enabled: bool,
+    /// Spend thresholds as percentages of the monthly budget
+    pub thresholds: Vec<u32>,
+}
+
+impl Default for BudgetWarningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            thresholds: vec![50, 80, 95],
+        }
+    }
+}
+
+/// Budget-aware model downgrade configuration
+///
+/// Once a key's month-to-date spend reaches `threshold_percent` of its
+/// monthly budget, requests for a model matching a rule's pattern run on the
+/// rule's cheaper model instead, stretching the remaining budget.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BudgetDowngradeConfig {
+    pub enabled: bool,
+    /// Spend, as a percentage of the monthly budget, at which downgrades start
+    pub threshold_percent: u32,
+    /// (model pattern, cheaper model) pairs, first match wins
+    /// (from BUDGET_DOWNGRADE_MODELS env, format: pattern=model,...)
+    pub rules: Vec<(String, String)>,
+}
+
+impl Default for BudgetDowngradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_percent: 90,
+            rules: parse_downgrade_rules(DEFAULT_BUDGET_DOWNGRADE_MODELS),
+        }
+    }
+}
+
+/// Opus to Sonnet, Sonnet to Haiku
+const DEFAULT_BUDGET_DOWNGRADE_MODELS: &str = "claude-opus-*=claude-sonnet-4-20250514,\
+     claude-sonnet-*=claude-3-5-haiku-20241022,\
+     claude-3-7-sonnet-*=claude-3-5-haiku-20241022,\
+     claude-3-5-sonnet-*=claude-3-5-haiku-20241022";
+
+/// Deprecation schedule of a model alias
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModelDeprecation {
+    /// Model pattern, exact or with a trailing `*` (e.g. "claude-2*")
+    pub model: String,
+    /// When the model was (or will be) deprecated
+    pub deprecated_at: DateTime<Utc>,
+    /// When the model stops being served
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset_at: Option<DateTime<Utc>>,
+    /// Model clients should move to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub successor: Option<String>,
+    /// Run requests on the successor once the sunset date has passed
+    #[serde(default)]
+    pub remap_after_sunset: bool,
+}
+
+/// Model deprecation schedules
+///
+/// Requests for a deprecated model get `Deprecation` and `Sunset` response
+/// headers. Schedules from the environment seed the registry, which can be
+/// changed at runtime through `/admin/model-deprecations`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModelDeprecationConfig {
+    /// Schedules loaded at startup
+    /// (from MODEL_DEPRECATIONS env, format: pattern=deprecated|sunset|successor,...)
+    pub schedules: Vec<ModelDeprecation>,
+}
+
+/// Key hygiene tracking configuration
+///
+/// Per-key activity (last used, request count, recent source IPs) is buffered
+/// in memory and flushed to the api_keys table periodically.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeyActivityConfig {
+    pub enabled: bool,
+    /// How often buffered activity is written back, in seconds
+    pub flush_interval_secs: u64,
+    /// Number of distinct recent source IPs kept per key
+    pub max_recent_ips: usize,
+}
+
+impl Default for KeyActivityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flush_interval_secs: 60,
+            max_recent_ips: 5,
+        }
+    }
+}
+
+/// Authentication brute-force protection configuration
+///
+/// Failed authentications are counted per source IP and per presented key
+/// prefix; crossing `max_failures` within `window_secs` locks the source out
+/// for an exponentially growing period.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BruteForceConfig {
+    pub enabled: bool,
+    /// Failures allowed within the window before a lockout
+    pub max_failures: u32,
+    /// Failure counting window in seconds
+    pub window_secs: u64,
+    /// First lockout duration in seconds (doubles with each repeat lockout)
+    pub base_lockout_secs: u64,
+    /// Upper bound for the lockout duration in seconds
+    pub max_lockout_secs: u64,
+}
+
+impl Default for BruteForceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_failures: 5,
+            window_secs: 300,
+            base_lockout_secs: 30,
+            max_lockout_secs: 3600,
+        }
+    }
+}
+
+/// Client IP filtering configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IpFilterConfig {
+    /// Derive the client IP from X-Forwarded-For (deployments behind load balancers)
+    pub trust_forwarded_for: bool,
+    /// CIDR ranges of the proxies whose X-Forwarded-For is honored (from TRUSTED_PROXIES)
+    pub trusted_proxies: Vec<String>,
+    /// Number of trusted proxies appending to X-Forwarded-For
+    pub trusted_proxy_hops: usize,
+    /// CIDR ranges rejected for every key (from IP_DENYLIST, comma-separated)
+    pub denylist: Vec<String>,
+}
+
+impl Default for IpFilterConfig {
+    fn default() -> Self {
+        Self {
+            trust_forwarded_for: false,
+            trusted_proxies: Vec::new(),
+            trusted_proxy_hops: 1,
+            denylist: Vec::new(),
+        }
+    }
+}
+
+/// Request body size limits (bytes), enforced per endpoint
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BodyLimitConfig {
+    /// Limit for endpoints without a specific setting
+    pub default_bytes: usize,
+    /// Limit for POST /v1/messages and /v1/messages/count_tokens
+    pub messages_bytes: usize,
+    /// Limit for POST /v1/chat/completions
+    pub chat_completions_bytes: usize,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            default_bytes: 1024 * 1024,
+            messages_bytes: 32 * 1024 * 1024,
+            chat_completions_bytes: 32 * 1024 * 1024,
+        }
+    }
+}
+
+/// Request and response body compression
+///
+/// Request bodies may be sent with `Content-Encoding: gzip` or `zstd`; the
+/// body limits apply to the decompressed size. Responses other than SSE
+/// streams are compressed when the client accepts gzip or zstd and the body
+/// is larger than `min_response_bytes`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompressionConfig {
+    pub request_decompression: bool,
+    pub response_compression: bool,
+    pub min_response_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            request_decompression: true,
+            response_compression: true,
+            min_response_bytes: 1024,
+        }
+    }
+}
+
+/// Interim usage reporting during streams
+///
+/// Long generations only learn their final token count from the closing
+/// metadata event. With reporting enabled, Anthropic streams emit the running
+/// (estimated) output token count every `interval_tokens` tokens.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamUsageConfig {
+    /// Output tokens between reports (0 disables)
+    pub interval_tokens: i32,
+    /// "comment" (SSE comment line, ignored by clients that don't parse it)
+    /// or "message_delta" (usage-only `message_delta` events)
+    pub mode: String,
+}
+
+impl StreamUsageConfig {
+    /// Whether reports are sent as `message_delta` events
+    pub fn uses_message_delta(&self) -> bool {
+        self.mode.eq_ignore_ascii_case("message_delta")
+    }
+}
+
+impl Default for StreamUsageConfig {
+    fn default() -> Self {
+        Self {
+            interval_tokens: 1000,
+            mode: "comment".to_string(),
+        }
+    }
+}
+
+/// What a stream does when its client stops reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlowClientPolicy {
+    /// Stop reading from the backend until the client catches up
+    #[default]
+    Pause,
+    /// End the stream once the buffer has stayed full for the timeout
+    Disconnect,
+}
+
+impl std::str::FromStr for SlowClientPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pause" => Ok(SlowClientPolicy::Pause),
+            "disconnect" => Ok(SlowClientPolicy::Disconnect),
+            _ => Err(anyhow::anyhow!("Invalid slow client policy: {}", s)),
+        }
+    }
+}
+
+/// Buffering between the backend stream and the client connection
+///
+/// Frames are read from the backend into a bounded buffer and written to the
+/// client from it, so a slow client holds at most `buffer_frames` frames in
+/// memory. When the buffer is full the policy decides whether the backend
+/// read waits or the stream is ended.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamBackpressureConfig {
+    /// Frames buffered per stream (0 writes straight through, unbuffered)
+    pub buffer_frames: usize,
+    pub slow_client_policy: SlowClientPolicy,
+    /// How long a full buffer is tolerated under the `disconnect` policy
+    pub slow_client_timeout_seconds: u64,
+}
+
+impl StreamBackpressureConfig {
+    pub fn slow_client_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.slow_client_timeout_seconds)
+    }
+}
+
+impl Default for StreamBackpressureConfig {
+    fn default() -> Self {
+        Self {
+            buffer_frames: 64,
+            slow_client_policy: SlowClientPolicy::default(),
+            slow_client_timeout_seconds: 30,
+        }
+    }
+}
+
+/// Stream-and-assemble mode for non-streaming requests
+///
+/// Non-streaming requests for matching models are sent upstream through
+/// ConverseStream and assembled into one response before replying. Bedrock
+/// cuts off non-streaming calls that run too long, so requests asking for a
+/// large `max_tokens` can be upgraded the same way without client changes.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StreamAssemblyConfig {
+    /// Model patterns (client name or backend model ID, `*` suffix allowed)
+    /// from STREAM_ASSEMBLE_MODELS
+    pub models: Vec<String>,
+    /// Model pattern -> max_tokens above which requests are force-streamed
+    /// (from FORCE_STREAM_MAX_TOKENS env, format: pattern=tokens,...; the
+    /// first matching pattern wins)
+    pub max_tokens_thresholds: Vec<(String, i32)>,
+}
+
+impl StreamAssemblyConfig {
+    /// Whether a non-streaming request for this model should stream upstream
+    pub fn applies_to(&self, model: &str, backend_model: &str, max_tokens: Option<i32>) -> bool {
+        let matches = |pattern: &str| {
+            model_matches_pattern(model, pattern) || model_matches_pattern(backend_model, pattern)
+        };
+
+        if self.models.iter().any(|pattern| matches(pattern)) {
+            return true;
+        }
+
+        let threshold = self
+            .max_tokens_thresholds
+            .iter()
+            .find(|(pattern, _)| matches(pattern))
+            .map(|(_, threshold)| *threshold);
+        matches!((max_tokens, threshold), (Some(max_tokens), Some(threshold)) if max_tokens > threshold)
+    }
+}
+
+/// Client compatibility profiles
+///
+/// Streams are formatted for a client profile chosen by the
+/// `x-client-profile` header, then by the User-Agent (when detection is on),
+/// then by `default_profile`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClientCompatConfig {
+    /// Pick a profile from the User-Agent when no header is sent
+    pub detect_user_agent: bool,
+    /// Profile used when none is requested or detected
+    pub default_profile: String,
+}
+
+impl Default for ClientCompatConfig {
+    fn default() -> Self {
+        Self {
+            detect_user_agent: true,
+            default_profile: "canonical".to_string(),
+        }
+    }
+}
+
+/// Output-length watchdog
+///
+/// Streams are stopped once their output passes `max_output_tokens`,
+/// whatever `max_tokens` the client asked for. 0 disables the watchdog.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OutputWatchdogConfig {
+    pub max_output_tokens: i32,
+}
+
+/// Token usage reconciliation against Bedrock invocation metrics
+///
+/// Every `interval_secs`, the token usage the proxy reported over the last
+/// `window_secs` (ending `lag_secs` ago, as CloudWatch lags behind) is
+/// compared per model and region with CloudWatch; drift beyond
+/// `drift_threshold_percent` is logged.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UsageReconciliationConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub window_secs: u64,
+    pub lag_secs: u64,
+    pub drift_threshold_percent: f64,
+}
+
+impl Default for UsageReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 3600,
+            window_secs: 3600,
+            lag_secs: 600,
+            drift_threshold_percent: 5.0,
+        }
+    }
+}
+
+/// Copies of completed conversations for offline evaluation
+///
+/// Keys with the `eval_capture` feature flag have `sample_rate` of their
+/// requests (prompt, final output and metadata) queued in memory and written
+/// in batches of up to `batch_size`, at least every `flush_interval_secs`, to
+/// an SQS queue and/or an S3 prefix. Records beyond `queue_capacity` are
+/// dropped rather than slowing requests down.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EvalSinkConfig {
+    pub enabled: bool,
+    pub sample_rate: f64,
+    pub sqs_queue_url: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_prefix: String,
+    pub batch_size: usize,
+    pub flush_interval_secs: u64,
+    pub queue_capacity: usize,
+}
+
+impl Default for EvalSinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_rate: 1.0,
+            sqs_queue_url: None,
+            s3_bucket: None,
+            s3_prefix: "eval".to_string(),
+            batch_size: 50,
+            flush_interval_secs: 10,
+            queue_capacity: 10_000,
+        }
+    }
+}
+
+/// Offloading of oversized response content to S3
+///
+/// For keys with the `content_offload` scope, text and tool result blocks
+/// larger than `threshold_bytes` are uploaded under `prefix` and replaced
+/// with their first `preview_chars` characters and a presigned URL valid for
+/// `url_ttl_secs`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContentOffloadConfig {
+    pub enabled: bool,
+    pub bucket: Option<String>,
+    pub prefix: String,
+    pub threshold_bytes: usize,
+    pub preview_chars: usize,
+    pub url_ttl_secs: u64,
+}
+
+impl Default for ContentOffloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bucket: None,
+            prefix: "offload".to_string(),
+            threshold_bytes: 256 * 1024,
+            preview_chars: 2000,
+            url_ttl_secs: 3600,
+        }
+    }
+}
+
+/// Outbound connections to the MCP servers named in agent runs
+///
+/// Servers must be https URLs resolving to public addresses. With
+/// `allowed_hosts` set, only those hosts (exact names, or `*.example.com`
+/// for subdomains) may be used; empty allows any public host.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct McpConfig {
+    pub allowed_hosts: Vec<String>,
+}
+
+/// Placeholder tokens for PII in requests sent upstream
+///
+/// Values of the listed `kinds` (email, phone, ssn, credit_card, ip_address)
+/// are replaced with stable tokens like `[EMAIL_1]` before a request reaches
+/// a backend, and the tokens in the response are replaced back. The mapping
+/// lives only for the duration of the backend call.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PiiTokenizationConfig {
+    pub enabled: bool,
+    pub kinds: Vec<String>,
+}
+
+impl Default for PiiTokenizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kinds: ["email", "phone", "ssn", "credit_card", "ip_address"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Client-supplied Bedrock `additionalModelRequestFields`
+///
+/// Keys with the `bedrock_extra_fields` scope may send fields under these
+/// dotted paths (e.g. "thinking" or "inference.top_k"); "*" allows any path.
+/// With no paths configured every extra field is rejected.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BedrockExtraFieldsConfig {
+    /// (from BEDROCK_EXTRA_FIELDS_ALLOWED_PATHS env, comma-separated)
+    pub allowed_paths: Vec<String>,
+}
+
+impl BedrockExtraFieldsConfig {
+    /// Whether a dotted field path is at or below an allowed path
+    pub fn allows(&self, path: &str) -> bool {
+        self.allowed_paths.iter().any(|allowed| {
+            allowed == "*"
+                || path == allowed
+                || path
+                    .strip_prefix(allowed.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+/// Stored chat completions (`store: true`)
+///
+/// Stored completions are kept in memory and can be retrieved by completion
+/// ID until they are older than the retention period. A retention of zero
+/// (or disabling the store) serves `store: true` requests without storing them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompletionStoreConfig {
+    pub enabled: bool,
+    /// Seconds a stored completion is kept
+    pub retention_seconds: i64,
+    /// Most completions kept; the oldest are evicted beyond this
+    pub max_entries: usize,
 }
 
-impl Default for BedrockConfig {
+impl Default for CompletionStoreConfig {
     fn default() -> Self {
         Self {
-            profiles: Vec::new(),
+            enabled: true,
+            retention_seconds: 30 * 24 * 60 * 60,
+            max_entries: 10_000,
         }
     }
 }
 
-/// Single Bedrock profile configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct BedrockProfileConfig {
-    pub name: String,
-    pub region: String,
-    pub profile: Option<String>,
-    #[serde(skip_serializing)]
-    pub access_key_id: Option<String>,
-    #[serde(skip_serializing)]
-    pub secret_access_key: Option<String>,
-    pub weight: u32,
+/// Provisioned throughput models
+///
+/// Requests whose `service_tier` allows it are sent to the provisioned model
+/// ARN instead of the on-demand model ID.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProvisionedThroughputConfig {
+    /// Client model name or Bedrock model ID -> provisioned model ARN
+    /// (from PROVISIONED_THROUGHPUT_MODELS env, format: model=arn,...)
+    pub models: HashMap<String, String>,
+    /// Purchased capacity in tokens per minute, keyed like `models` or by ARN
+    /// (from PROVISIONED_THROUGHPUT_TPM env, format: model=tokens,...)
+    pub tokens_per_minute: HashMap<String, u64>,
+    /// Longest a request may wait for capacity before getting a 429
+    /// (from PROVISIONED_THROUGHPUT_MAX_WAIT_MS env)
+    pub max_wait_ms: u64,
 }
 
-impl BedrockConfig {
-    /// Check if multiple profiles are configured
-    pub fn has_multiple_profiles(&self) -> bool {
-        self.profiles.len() > 1
+impl ProvisionedThroughputConfig {
+    /// Provisioned ARN for a model, looked up by client name then Bedrock ID
+    pub fn arn_for(&self, model: &str, bedrock_model: &str) -> Option<&str> {
+        self.models
+            .get(model)
+            .or_else(|| self.models.get(bedrock_model))
+            .map(String::as_str)
     }
 }
 
+/// Model routing configuration
+///
+/// Maps model name patterns to backends. Rules are evaluated in order and the
+/// first match wins; unmatched models keep the legacy behaviour (`gemini-*` to
+/// Gemini, everything else to the default Bedrock client).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RoutingConfig {
+    /// Ordered routing rules (from MODEL_ROUTES env, format: pattern=backend,...)
+    pub routes: Vec<ModelRouteConfig>,
+    /// Backend for requests using the 1M-context beta (from LONG_CONTEXT_BACKEND env)
+    pub long_context_backend: Option<String>,
+}
+
+/// Single model routing rule
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelRouteConfig {
+    /// Model pattern, exact or with a trailing `*` (e.g. "claude-*")
+    pub pattern: String,
+    /// Backend spec: "bedrock", "bedrock:<profile>", "gemini", "azure" or "passthrough"
+    pub backend: String,
+}
+
 /// Main application settings
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Settings {
@@ -314,6 +1187,8 @@ pub struct Settings {
     pub aws_secret_access_key: Option<String>,
     pub dynamodb_endpoint_url: Option<String>,
     pub bedrock_endpoint_url: Option<String>,
+    pub bedrock_client: AwsClientConfig,
+    pub dynamodb_client: AwsClientConfig,
 
     // DynamoDB table names
     pub dynamodb_api_keys_table: String,
@@ -321,15 +1196,50 @@ pub struct Settings {
     pub dynamodb_usage_stats_table: String,
     pub dynamodb_model_mapping_table: String,
     pub dynamodb_model_pricing_table: String,
+    pub dynamodb_feature_flags_table: String,
+    pub dynamodb_inference_presets_table: String,
 
     // Authentication
     pub require_api_key: bool,
     #[serde(skip_serializing)]
     pub master_api_key: Option<String>,
+    /// Credential for the `/admin/api-keys` endpoints (sent as `x-admin-key`)
+    #[serde(skip_serializing)]
+    pub admin_api_key: Option<String>,
 
     // Rate limiting
     pub rate_limit: RateLimitConfig,
 
+    // Budget soft-cap warnings
+    pub budget_warnings: BudgetWarningConfig,
+
+    // Budget-aware model downgrade
+    pub budget_downgrade: BudgetDowngradeConfig,
+
+    // Model deprecation and sunset schedules
+    pub model_deprecations: ModelDeprecationConfig,
+
+    // Client-supplied additionalModelRequestFields
+    pub bedrock_extra_fields: BedrockExtraFieldsConfig,
+
+    // Trial key restrictions
+    pub trial: TrialConfig,
+
+    // Key hygiene tracking
+    pub key_activity: KeyActivityConfig,
+
+    // Auth brute-force protection
+    pub brute_force: BruteForceConfig,
+
+    // Client IP allow/deny filtering
+    pub ip_filter: IpFilterConfig,
+
+    // Request body size limits
+    pub body_limits: BodyLimitConfig,
+
+    // gzip/zstd request bodies and compressed responses
+    pub compression: CompressionConfig,
+
     // Feature flags
     pub features: FeatureFlags,
 
@@ -342,6 +1252,12 @@ pub struct Settings {
     // Gemini configuration
     pub gemini: GeminiConfig,
 
+    // Anthropic hosted API configuration
+    pub anthropic: AnthropicConfig,
+
+    // Azure OpenAI configuration
+    pub azure_openai: AzureOpenAIConfig,
+
     // OpenAI configuration
     pub openai: OpenAIConfig,
 
@@ -354,21 +1270,50 @@ pub struct Settings {
     // Bedrock multi-profile configuration
     pub bedrock: BedrockConfig,
 
+    // Model pattern -> backend routing table
+    pub routing: RoutingConfig,
+
+    // Provisioned throughput used for the `service_tier` hint
+    pub provisioned_throughput: ProvisionedThroughputConfig,
+
+    // Chat completions stored with `store: true`
+    pub completion_store: CompletionStoreConfig,
+
+    // Transcription of audio content parts
+    pub transcription: TranscriptionConfig,
+
     // Model mapping (Anthropic model ID -> Bedrock model ID)
     pub default_model_mapping: HashMap<String, String>,
 
+    // OpenAI temperature mapping for Converse backends
+    pub openai_temperature_scaling: TemperatureScaling,
+
     // Streaming configuration
     pub streaming_timeout_seconds: u64,
+    pub stream_usage: StreamUsageConfig,
+    pub stream_backpressure: StreamBackpressureConfig,
+    pub client_compat: ClientCompatConfig,
+    pub stream_assembly: StreamAssemblyConfig,
+    pub output_watchdog: OutputWatchdogConfig,
+    pub usage_reconciliation: UsageReconciliationConfig,
+    pub eval_sink: EvalSinkConfig,
+    pub content_offload: ContentOffloadConfig,
+    pub mcp: McpConfig,
+    pub pii_tokenization: PiiTokenizationConfig,
 
     // Debug options
     /// Print all request prompts to stdout
     #[serde(default)]
     pub print_prompts: bool,
 
-    /// Ephemeral API key (generated at startup, not stored in DynamoDB)
-    /// This is used for simple local development without DynamoDB
-    #[serde(skip)]
-    pub ephemeral_api_key: Option<String>,
+    /// Write each request and its conversion as JSON into this directory
+    /// instead of printing prompts
+    #[serde(default)]
+    pub print_prompts_dir: Option<String>,
+
+    /// Log every backend request and response payload at debug level
+    #[serde(default)]
+    pub log_backend_payloads: bool,
 }
 
 impl Settings {
@@ -398,6 +1343,8 @@ impl Settings {
             aws_secret_access_key: env::var("AWS_SECRET_ACCESS_KEY").ok(),
             dynamodb_endpoint_url: env::var("DYNAMODB_ENDPOINT_URL").ok(),
             bedrock_endpoint_url: env::var("BEDROCK_ENDPOINT_URL").ok(),
+            bedrock_client: AwsClientConfig::from_env("BEDROCK_CLIENT_"),
+            dynamodb_client: AwsClientConfig::from_env("DYNAMODB_CLIENT_"),
 
             // DynamoDB table names
             dynamodb_api_keys_table: env_or_default(
@@ -420,12 +1367,21 @@ impl Settings {
                 "DYNAMODB_MODEL_PRICING_TABLE",
                 "anthropic-proxy-model-pricing",
             ),
+            dynamodb_feature_flags_table: env_or_default(
+                "DYNAMODB_FEATURE_FLAGS_TABLE",
+                "anthropic-proxy-feature-flags",
+            ),
+            dynamodb_inference_presets_table: env_or_default(
+                "DYNAMODB_INFERENCE_PRESETS_TABLE",
+                "anthropic-proxy-inference-presets",
+            ),
 
             // Authentication
             require_api_key: env_or_default("REQUIRE_API_KEY", "true")
                 .parse()
                 .unwrap_or(true),
             master_api_key: env::var("MASTER_API_KEY").ok(),
+            admin_api_key: env::var("ADMIN_API_KEY").ok().filter(|k| !k.is_empty()),
 
             // Rate limiting
             rate_limit: RateLimitConfig {
@@ -438,6 +1394,151 @@ impl Settings {
                 window_seconds: env_or_default("RATE_LIMIT_WINDOW_SECONDS", "60")
                     .parse()
                     .unwrap_or(60),
+                burst: env::var("RATE_LIMIT_BURST").ok().and_then(|v| v.parse().ok()),
+                store: env_or_default("RATE_LIMIT_STORE", "memory").to_lowercase(),
+                redis_url: env::var("REDIS_URL").ok().filter(|v| !v.is_empty()),
+                redis_key_prefix: env_or_default("RATE_LIMIT_REDIS_PREFIX", "ratelimit"),
+                tokens_per_minute: env::var("RATE_LIMIT_TOKENS_PER_MINUTE")
+                    .ok()
+                    .and_then(|v| v.parse().ok()),
+            },
+
+            // Budget soft-cap warnings
+            budget_warnings: BudgetWarningConfig {
+                enabled: env_or_default("BUDGET_WARNINGS_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                thresholds: env_or_default("BUDGET_WARNING_THRESHOLDS", "50,80,95")
+                    .split(',')
+                    .filter_map(|t| t.trim().parse().ok())
+                    .collect(),
+            },
+
+            // Budget-aware model downgrade
+            budget_downgrade: BudgetDowngradeConfig {
+                enabled: env_or_default("BUDGET_DOWNGRADE_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                threshold_percent: env_or_default("BUDGET_DOWNGRADE_THRESHOLD", "90")
+                    .parse()
+                    .unwrap_or(90),
+                rules: parse_downgrade_rules(&env_or_default(
+                    "BUDGET_DOWNGRADE_MODELS",
+                    DEFAULT_BUDGET_DOWNGRADE_MODELS,
+                )),
+            },
+
+            // Model deprecation schedules
+            model_deprecations: ModelDeprecationConfig {
+                schedules: parse_model_deprecations(
+                    &env_or_default("MODEL_DEPRECATIONS", ""),
+                    env_or_default("MODEL_SUNSET_REMAP", "false")
+                        .parse()
+                        .unwrap_or(false),
+                ),
+            },
+
+            // Client-supplied additionalModelRequestFields
+            bedrock_extra_fields: BedrockExtraFieldsConfig {
+                allowed_paths: parse_comma_separated_env("BEDROCK_EXTRA_FIELDS_ALLOWED_PATHS"),
+            },
+
+            // Trial key restrictions
+            trial: TrialConfig {
+                allowed_models: env_or_default(
+                    "TRIAL_ALLOWED_MODELS",
+                    "claude-3-5-haiku-*,claude-3-haiku-*",
+                )
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+                rate_limit: env_or_default("TRIAL_RATE_LIMIT", "10")
+                    .parse()
+                    .unwrap_or(10),
+                monthly_budget: env_or_default("TRIAL_MONTHLY_BUDGET", "5.0")
+                    .parse()
+                    .unwrap_or(5.0),
+                duration_days: env_or_default("TRIAL_DURATION_DAYS", "14")
+                    .parse()
+                    .unwrap_or(14),
+            },
+
+            // Key hygiene tracking
+            key_activity: KeyActivityConfig {
+                enabled: env_or_default("KEY_ACTIVITY_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                flush_interval_secs: env_or_default("KEY_ACTIVITY_FLUSH_INTERVAL_SECS", "60")
+                    .parse()
+                    .unwrap_or(60),
+                max_recent_ips: env_or_default("KEY_ACTIVITY_MAX_RECENT_IPS", "5")
+                    .parse()
+                    .unwrap_or(5),
+            },
+
+            // Auth brute-force protection
+            brute_force: BruteForceConfig {
+                enabled: env_or_default("AUTH_BRUTE_FORCE_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                max_failures: env_or_default("AUTH_MAX_FAILURES", "5")
+                    .parse()
+                    .unwrap_or(5),
+                window_secs: env_or_default("AUTH_FAILURE_WINDOW_SECS", "300")
+                    .parse()
+                    .unwrap_or(300),
+                base_lockout_secs: env_or_default("AUTH_LOCKOUT_BASE_SECS", "30")
+                    .parse()
+                    .unwrap_or(30),
+                max_lockout_secs: env_or_default("AUTH_LOCKOUT_MAX_SECS", "3600")
+                    .parse()
+                    .unwrap_or(3600),
+            },
+
+            // Client IP allow/deny filtering
+            ip_filter: IpFilterConfig {
+                trust_forwarded_for: env_or_default("TRUST_FORWARDED_FOR", "false")
+                    .parse()
+                    .unwrap_or(false),
+                trusted_proxies: env_or_default("TRUSTED_PROXIES", "")
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+                trusted_proxy_hops: env_or_default("TRUSTED_PROXY_HOPS", "1")
+                    .parse()
+                    .unwrap_or(1),
+                denylist: env_or_default("IP_DENYLIST", "")
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            },
+
+            // Request body size limits
+            body_limits: BodyLimitConfig {
+                default_bytes: env_or_default("BODY_LIMIT_DEFAULT_BYTES", "1048576")
+                    .parse()
+                    .unwrap_or(1024 * 1024),
+                messages_bytes: env_or_default("BODY_LIMIT_MESSAGES_BYTES", "33554432")
+                    .parse()
+                    .unwrap_or(32 * 1024 * 1024),
+                chat_completions_bytes: env_or_default("BODY_LIMIT_CHAT_COMPLETIONS_BYTES", "33554432")
+                    .parse()
+                    .unwrap_or(32 * 1024 * 1024),
+            },
+
+            compression: CompressionConfig {
+                request_decompression: env_or_default("REQUEST_DECOMPRESSION_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                response_compression: env_or_default("RESPONSE_COMPRESSION_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                min_response_bytes: env_or_default("RESPONSE_COMPRESSION_MIN_BYTES", "1024")
+                    .parse()
+                    .unwrap_or(1024),
             },
 
             // Feature flags
@@ -457,6 +1558,9 @@ impl Settings {
                 prompt_caching_enabled: env_or_default("PROMPT_CACHING_ENABLED", "true")
                     .parse()
                     .unwrap_or(false),
+                enable_request_coalescing: env_or_default("ENABLE_REQUEST_COALESCING", "false")
+                    .parse()
+                    .unwrap_or(false),
             },
 
             // PTC configuration
@@ -472,6 +1576,9 @@ impl Settings {
                 network_disabled: env_or_default("PTC_NETWORK_DISABLED", "true")
                     .parse()
                     .unwrap_or(true),
+                max_parallel_executions: env_or_default("PTC_MAX_PARALLEL_EXECUTIONS", "4")
+                    .parse()
+                    .unwrap_or(4),
             },
 
             // Backend pool configuration (load balancing)
@@ -483,6 +1590,18 @@ impl Settings {
                 retry_after_secs: env_or_default("BACKEND_RETRY_AFTER_SECS", "300")
                     .parse()
                     .unwrap_or(300),
+                probe_successes: env_or_default("BACKEND_PROBE_SUCCESSES", "2")
+                    .parse()
+                    .unwrap_or(2),
+                rate_limit_cooldown_secs: env_or_default("BACKEND_RATE_LIMIT_COOLDOWN_SECS", "60")
+                    .parse()
+                    .unwrap_or(60),
+                failover_max_attempts: env_or_default("BACKEND_FAILOVER_MAX_ATTEMPTS", "2")
+                    .parse()
+                    .unwrap_or(2),
+                failover_cooldown_secs: env_or_default("BACKEND_FAILOVER_COOLDOWN_SECS", "30")
+                    .parse()
+                    .unwrap_or(30),
                 health_check_interval_secs: env_or_default("BACKEND_HEALTH_CHECK_INTERVAL_SECS", "30")
                     .parse()
                     .unwrap_or(30),
@@ -501,6 +1620,35 @@ impl Settings {
                     .unwrap_or(120),
             },
 
+            // Anthropic hosted API configuration
+            anthropic: AnthropicConfig {
+                enabled: env_or_default("ANTHROPIC_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                api_keys: parse_comma_separated_env("ANTHROPIC_API_KEYS"),
+                base_url: env::var("ANTHROPIC_BASE_URL").ok(),
+                timeout_seconds: env_or_default("ANTHROPIC_TIMEOUT_SECONDS", "600")
+                    .parse()
+                    .unwrap_or(600),
+            },
+
+            // Azure OpenAI configuration
+            azure_openai: AzureOpenAIConfig {
+                enabled: env_or_default("AZURE_OPENAI_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                endpoints: parse_comma_separated_env("AZURE_OPENAI_ENDPOINTS"),
+                api_keys: parse_comma_separated_env("AZURE_OPENAI_API_KEYS"),
+                tenant_id: env::var("AZURE_OPENAI_TENANT_ID").ok().filter(|s| !s.is_empty()),
+                client_id: env::var("AZURE_OPENAI_CLIENT_ID").ok().filter(|s| !s.is_empty()),
+                client_secret: env::var("AZURE_OPENAI_CLIENT_SECRET").ok().filter(|s| !s.is_empty()),
+                api_version: env_or_default("AZURE_OPENAI_API_VERSION", "2024-10-21"),
+                deployments: parse_azure_deployments(&env_or_default("AZURE_OPENAI_DEPLOYMENTS", "")),
+                timeout_seconds: env_or_default("AZURE_OPENAI_TIMEOUT_SECONDS", "600")
+                    .parse()
+                    .unwrap_or(600),
+            },
+
             // OpenAI configuration
             openai: OpenAIConfig {
                 enabled: env_or_default("OPENAI_ENABLED", "false")
@@ -533,24 +1681,194 @@ impl Settings {
 
             // Bedrock multi-profile configuration
             bedrock: BedrockConfig {
-                profiles: parse_bedrock_profiles(),
+                profiles: parse_bedrock_profiles("BEDROCK_PROFILES"),
+                credential_pool: parse_bedrock_profiles("BEDROCK_CREDENTIAL_POOL"),
+            },
+
+            // Model routing table
+            routing: RoutingConfig {
+                routes: parse_model_routes(),
+                long_context_backend: env::var("LONG_CONTEXT_BACKEND")
+                    .ok()
+                    .filter(|s| !s.trim().is_empty()),
+            },
+
+            // Provisioned throughput models
+            provisioned_throughput: ProvisionedThroughputConfig {
+                models: parse_provisioned_models(),
+                tokens_per_minute: parse_provisioned_tpm(),
+                max_wait_ms: env_or_default("PROVISIONED_THROUGHPUT_MAX_WAIT_MS", "2000")
+                    .parse()
+                    .unwrap_or(2000),
+            },
+
+            // Stored chat completions
+            completion_store: CompletionStoreConfig {
+                enabled: env_or_default("COMPLETION_STORE_ENABLED", "true")
+                    .parse()
+                    .unwrap_or(true),
+                retention_seconds: env_or_default("COMPLETION_STORE_RETENTION_SECONDS", "2592000")
+                    .parse()
+                    .unwrap_or(2_592_000),
+                max_entries: env_or_default("COMPLETION_STORE_MAX_ENTRIES", "10000")
+                    .parse()
+                    .unwrap_or(10_000),
+            },
+
+            // Audio transcription (defaults to the OpenAI key when unset)
+            transcription: TranscriptionConfig {
+                enabled: env_or_default("TRANSCRIPTION_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                base_url: env_or_default("TRANSCRIPTION_BASE_URL", "https://api.openai.com/v1"),
+                api_key: env::var("TRANSCRIPTION_API_KEY")
+                    .or_else(|_| env::var("OPENAI_API_KEY"))
+                    .ok(),
+                model: env_or_default("TRANSCRIPTION_MODEL", "whisper-1"),
+                timeout_seconds: env_or_default("TRANSCRIPTION_TIMEOUT_SECONDS", "60")
+                    .parse()
+                    .unwrap_or(60),
+                max_audio_bytes: env_or_default("TRANSCRIPTION_MAX_AUDIO_BYTES", "26214400")
+                    .parse()
+                    .unwrap_or(26_214_400),
             },
 
             // Model mapping - load default mappings
             default_model_mapping: Self::load_default_model_mapping(),
+            openai_temperature_scaling: env_or_default("OPENAI_TEMPERATURE_SCALING", "clamp")
+                .parse()
+                .context("Invalid OPENAI_TEMPERATURE_SCALING value")?,
 
             // Streaming
             streaming_timeout_seconds: env_or_default("STREAMING_TIMEOUT_SECONDS", "300")
                 .parse()
                 .unwrap_or(300),
+            stream_usage: StreamUsageConfig {
+                interval_tokens: env_or_default("STREAM_USAGE_INTERVAL_TOKENS", "1000")
+                    .parse()
+                    .unwrap_or(1000),
+                mode: env_or_default("STREAM_USAGE_MODE", "comment"),
+            },
+            stream_backpressure: StreamBackpressureConfig {
+                buffer_frames: env_or_default("STREAM_BUFFER_FRAMES", "64")
+                    .parse()
+                    .unwrap_or(64),
+                slow_client_policy: env_or_default("SLOW_CLIENT_POLICY", "pause")
+                    .parse()
+                    .context("Invalid SLOW_CLIENT_POLICY value")?,
+                slow_client_timeout_seconds: env_or_default("SLOW_CLIENT_TIMEOUT_SECONDS", "30")
+                    .parse()
+                    .unwrap_or(30),
+            },
+            client_compat: ClientCompatConfig {
+                detect_user_agent: env_or_default("CLIENT_PROFILE_DETECTION", "true")
+                    .parse()
+                    .unwrap_or(true),
+                default_profile: env_or_default("CLIENT_PROFILE_DEFAULT", "canonical"),
+            },
+            stream_assembly: StreamAssemblyConfig {
+                models: parse_comma_separated_env("STREAM_ASSEMBLE_MODELS"),
+                max_tokens_thresholds: parse_force_stream_thresholds(
+                    &env::var("FORCE_STREAM_MAX_TOKENS").unwrap_or_default(),
+                ),
+            },
+            output_watchdog: OutputWatchdogConfig {
+                max_output_tokens: env_or_default("OUTPUT_WATCHDOG_MAX_TOKENS", "0")
+                    .parse()
+                    .unwrap_or(0),
+            },
+            usage_reconciliation: UsageReconciliationConfig {
+                enabled: env_or_default("USAGE_RECONCILIATION_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                interval_secs: env_or_default("USAGE_RECONCILIATION_INTERVAL_SECS", "3600")
+                    .parse()
+                    .unwrap_or(3600),
+                window_secs: env_or_default("USAGE_RECONCILIATION_WINDOW_SECS", "3600")
+                    .parse()
+                    .unwrap_or(3600),
+                lag_secs: env_or_default("USAGE_RECONCILIATION_LAG_SECS", "600")
+                    .parse()
+                    .unwrap_or(600),
+                drift_threshold_percent: env_or_default(
+                    "USAGE_RECONCILIATION_DRIFT_THRESHOLD_PERCENT",
+                    "5",
+                )
+                .parse()
+                .unwrap_or(5.0),
+            },
+            eval_sink: EvalSinkConfig {
+                enabled: env_or_default("EVAL_SINK_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                sample_rate: env_or_default("EVAL_SINK_SAMPLE_RATE", "1.0")
+                    .parse::<f64>()
+                    .map(|rate| rate.clamp(0.0, 1.0))
+                    .unwrap_or(1.0),
+                sqs_queue_url: env::var("EVAL_SINK_SQS_QUEUE_URL").ok().filter(|v| !v.is_empty()),
+                s3_bucket: env::var("EVAL_SINK_S3_BUCKET").ok().filter(|v| !v.is_empty()),
+                s3_prefix: env_or_default("EVAL_SINK_S3_PREFIX", "eval")
+                    .trim_matches('/')
+                    .to_string(),
+                batch_size: env_or_default("EVAL_SINK_BATCH_SIZE", "50")
+                    .parse()
+                    .unwrap_or(50),
+                flush_interval_secs: env_or_default("EVAL_SINK_FLUSH_INTERVAL_SECS", "10")
+                    .parse()
+                    .unwrap_or(10),
+                queue_capacity: env_or_default("EVAL_SINK_QUEUE_CAPACITY", "10000")
+                    .parse()
+                    .unwrap_or(10_000),
+            },
+            content_offload: ContentOffloadConfig {
+                enabled: env_or_default("CONTENT_OFFLOAD_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                bucket: env::var("CONTENT_OFFLOAD_BUCKET").ok().filter(|v| !v.is_empty()),
+                prefix: env_or_default("CONTENT_OFFLOAD_PREFIX", "offload")
+                    .trim_matches('/')
+                    .to_string(),
+                threshold_bytes: env_or_default("CONTENT_OFFLOAD_THRESHOLD_BYTES", "262144")
+                    .parse()
+                    .unwrap_or(256 * 1024),
+                preview_chars: env_or_default("CONTENT_OFFLOAD_PREVIEW_CHARS", "2000")
+                    .parse()
+                    .unwrap_or(2000),
+                // Presigned URLs are valid for at most seven days
+                url_ttl_secs: env_or_default("CONTENT_OFFLOAD_URL_TTL_SECS", "3600")
+                    .parse::<u64>()
+                    .map(|secs| secs.clamp(1, 7 * 24 * 3600))
+                    .unwrap_or(3600),
+            },
+            mcp: McpConfig {
+                allowed_hosts: env_or_default("MCP_ALLOWED_HOSTS", "")
+                    .split(',')
+                    .map(|s| s.trim().to_ascii_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            },
+            pii_tokenization: PiiTokenizationConfig {
+                enabled: env_or_default("PII_TOKENIZATION_ENABLED", "false")
+                    .parse()
+                    .unwrap_or(false),
+                kinds: env_or_default(
+                    "PII_TOKENIZATION_KINDS",
+                    "email,phone,ssn,credit_card,ip_address",
+                )
+                .split(',')
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            },
 
             // Debug options
             print_prompts: env_or_default("PRINT_PROMPTS", "false")
                 .parse()
                 .unwrap_or(false),
-
-            // Ephemeral API key (will be generated later if needed)
-            ephemeral_api_key: None,
+            print_prompts_dir: env::var("PRINT_PROMPTS_DIR").ok().filter(|d| !d.is_empty()),
+            log_backend_payloads: env_or_default("LOG_BACKEND_PAYLOADS", "false")
+                .parse()
+                .unwrap_or(false),
         };
 
         // Validate settings
@@ -574,6 +1892,20 @@ impl Settings {
             if self.rate_limit.window_seconds == 0 {
                 anyhow::bail!("Rate limit window_seconds must be > 0");
             }
+            if self.rate_limit.burst == Some(0) {
+                anyhow::bail!("Rate limit burst must be > 0");
+            }
+            if self.rate_limit.tokens_per_minute == Some(0) {
+                anyhow::bail!("Rate limit tokens_per_minute must be > 0");
+            }
+            match self.rate_limit.store.as_str() {
+                "memory" => {}
+                "redis" if self.rate_limit.redis_url.is_none() => {
+                    anyhow::bail!("RATE_LIMIT_STORE=redis requires REDIS_URL");
+                }
+                "redis" => {}
+                other => anyhow::bail!("Unknown RATE_LIMIT_STORE '{}' (expected memory or redis)", other),
+            }
         }
 
         // Validate PTC settings if enabled
@@ -750,40 +2082,63 @@ impl Default for Settings {
             aws_secret_access_key: None,
             dynamodb_endpoint_url: None,
             bedrock_endpoint_url: None,
+            bedrock_client: AwsClientConfig::default(),
+            dynamodb_client: AwsClientConfig::default(),
             dynamodb_api_keys_table: "anthropic-proxy-api-keys".to_string(),
             dynamodb_usage_table: "anthropic-proxy-usage".to_string(),
             dynamodb_usage_stats_table: "anthropic-proxy-usage-stats".to_string(),
             dynamodb_model_mapping_table: "anthropic-proxy-model-mapping".to_string(),
             dynamodb_model_pricing_table: "anthropic-proxy-model-pricing".to_string(),
+            dynamodb_feature_flags_table: "anthropic-proxy-feature-flags".to_string(),
+            dynamodb_inference_presets_table: "anthropic-proxy-inference-presets".to_string(),
             require_api_key: true,
             master_api_key: None,
+            admin_api_key: None,
             rate_limit: RateLimitConfig::default(),
+            budget_warnings: BudgetWarningConfig::default(),
+            budget_downgrade: BudgetDowngradeConfig::default(),
+            model_deprecations: ModelDeprecationConfig::default(),
+            bedrock_extra_fields: BedrockExtraFieldsConfig::default(),
+            trial: TrialConfig::default(),
+            key_activity: KeyActivityConfig::default(),
+            brute_force: BruteForceConfig::default(),
+            ip_filter: IpFilterConfig::default(),
+            body_limits: BodyLimitConfig::default(),
+            compression: CompressionConfig::default(),
             features: FeatureFlags::default(),
             ptc: PtcConfig::default(),
             backend_pool: BackendPoolConfig::default(),
             gemini: GeminiConfig::default(),
+            anthropic: AnthropicConfig::default(),
+            azure_openai: AzureOpenAIConfig::default(),
             openai: OpenAIConfig::default(),
             deepseek: DeepSeekConfig::default(),
             storage: StorageConfig::default(),
             bedrock: BedrockConfig::default(),
+            routing: RoutingConfig::default(),
+            provisioned_throughput: ProvisionedThroughputConfig::default(),
+            completion_store: CompletionStoreConfig::default(),
+            transcription: TranscriptionConfig::default(),
             default_model_mapping: Self::load_default_model_mapping(),
+            openai_temperature_scaling: TemperatureScaling::default(),
             streaming_timeout_seconds: 300,
+            stream_usage: StreamUsageConfig::default(),
+            stream_backpressure: StreamBackpressureConfig::default(),
+            client_compat: ClientCompatConfig::default(),
+            stream_assembly: StreamAssemblyConfig::default(),
+            output_watchdog: OutputWatchdogConfig::default(),
+            usage_reconciliation: UsageReconciliationConfig::default(),
+            eval_sink: EvalSinkConfig::default(),
+            content_offload: ContentOffloadConfig::default(),
+            mcp: McpConfig::default(),
+            pii_tokenization: PiiTokenizationConfig::default(),
             print_prompts: false,
-            ephemeral_api_key: None,
+            print_prompts_dir: None,
+            log_backend_payloads: false,
         }
     }
 }
 
-impl Settings {
-    /// Generate and set an ephemeral API key
-    /// Returns the generated key
-    pub fn generate_ephemeral_key(&mut self) -> String {
-        let key = format!("sk-{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
-        self.ephemeral_api_key = Some(key.clone());
-        key
-    }
-}
-
 /// Helper function to get environment variable with default
 fn env_or_default(key: &str, default: &str) -> String {
     env::var(key).unwrap_or_else(|_| default.to_string())
@@ -801,10 +2156,10 @@ fn parse_comma_separated_env(key: &str) -> Vec<String> {
         .unwrap_or_default()
 }
 
-/// Parse BEDROCK_PROFILES environment variable
+/// Parse a profile list such as BEDROCK_PROFILES from the environment
 /// Format: "profile1:region1,profile2:region2" or "name1=profile1:region1,name2=profile2:region2"
-fn parse_bedrock_profiles() -> Vec<BedrockProfileConfig> {
-    let profiles_str = match env::var("BEDROCK_PROFILES") {
+fn parse_bedrock_profiles(var: &str) -> Vec<BedrockProfileConfig> {
+    let profiles_str = match env::var(var) {
         Ok(s) if !s.is_empty() => s,
         _ => return Vec::new(),
     };
@@ -830,7 +2185,8 @@ fn parse_bedrock_profiles() -> Vec<BedrockProfileConfig> {
             let parts: Vec<&str> = profile_region.splitn(2, ':').collect();
             if parts.len() < 2 {
                 tracing::warn!(
-                    "Invalid BEDROCK_PROFILES entry: {}. Expected format: profile:region",
+                    "Invalid {} entry: {}. Expected format: profile:region",
+                    var,
                     entry
                 );
                 return None;
@@ -848,6 +2204,141 @@ fn parse_bedrock_profiles() -> Vec<BedrockProfileConfig> {
         .collect()
 }
 
+/// Parse MODEL_ROUTES environment variable
+/// Format: "claude-*=bedrock:east,gemini-*=gemini,gpt-4o=azure"
+/// Parse BUDGET_DOWNGRADE_MODELS (format: pattern=model,pattern2=model2)
+fn parse_downgrade_rules(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (pattern, model) = entry.trim().split_once('=')?;
+            let (pattern, model) = (pattern.trim(), model.trim());
+            (!pattern.is_empty() && !model.is_empty())
+                .then(|| (pattern.to_string(), model.to_string()))
+        })
+        .collect()
+}
+
+/// Parse MODEL_DEPRECATIONS
+/// Format: "claude-2.1=2024-07-21|2025-01-21|claude-3-5-haiku-20241022,..."
+/// where the sunset date and successor are optional. Dates are YYYY-MM-DD
+/// (midnight UTC) or RFC 3339.
+fn parse_model_deprecations(value: &str, remap_after_sunset: bool) -> Vec<ModelDeprecation> {
+    fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|d| d.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+                Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+            })
+    }
+
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (model, schedule) = entry.trim().split_once('=')?;
+            let mut parts = schedule.split('|').map(str::trim);
+            let deprecated_at = parse_date(parts.next()?)?;
+            let sunset_at = parts.next().filter(|s| !s.is_empty()).and_then(parse_date);
+            let successor = parts.next().filter(|s| !s.is_empty()).map(str::to_string);
+            let model = model.trim();
+            (!model.is_empty()).then(|| ModelDeprecation {
+                model: model.to_string(),
+                deprecated_at,
+                sunset_at,
+                remap_after_sunset: remap_after_sunset && successor.is_some(),
+                successor,
+            })
+        })
+        .collect()
+}
+
+/// Parse PROVISIONED_THROUGHPUT_MODELS (format: model=arn,model2=arn2)
+fn parse_provisioned_models() -> HashMap<String, String> {
+    env::var("PROVISIONED_THROUGHPUT_MODELS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (model, arn) = entry.trim().split_once('=')?;
+            let (model, arn) = (model.trim(), arn.trim());
+            (!model.is_empty() && !arn.is_empty()).then(|| (model.to_string(), arn.to_string()))
+        })
+        .collect()
+}
+
+/// Parse AZURE_OPENAI_DEPLOYMENTS (format: model=deployment,model2=deployment2)
+fn parse_azure_deployments(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (model, deployment) = entry.trim().split_once('=')?;
+            let (model, deployment) = (model.trim(), deployment.trim());
+            (!model.is_empty() && !deployment.is_empty())
+                .then(|| (model.to_string(), deployment.to_string()))
+        })
+        .collect()
+}
+
+fn parse_provisioned_tpm() -> HashMap<String, u64> {
+    env::var("PROVISIONED_THROUGHPUT_TPM")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (model, tokens) = entry.trim().split_once('=')?;
+            let tokens = tokens.trim().parse().ok().filter(|t| *t > 0)?;
+            let model = model.trim();
+            (!model.is_empty()).then(|| (model.to_string(), tokens))
+        })
+        .collect()
+}
+
+fn parse_force_stream_thresholds(spec: &str) -> Vec<(String, i32)> {
+    spec.split(',')
+        .filter_map(|entry| {
+            let (pattern, tokens) = entry.trim().split_once('=')?;
+            let tokens = tokens.trim().parse().ok()?;
+            let pattern = pattern.trim();
+            (!pattern.is_empty()).then(|| (pattern.to_string(), tokens))
+        })
+        .collect()
+}
+
+fn parse_model_routes() -> Vec<ModelRouteConfig> {
+    match env::var("MODEL_ROUTES") {
+        Ok(s) if !s.is_empty() => parse_model_routes_str(&s),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_model_routes_str(routes_str: &str) -> Vec<ModelRouteConfig> {
+    routes_str
+        .split(',')
+        .filter_map(|entry| {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                return None;
+            }
+
+            match entry.split_once('=') {
+                Some((pattern, backend)) if !pattern.trim().is_empty() && !backend.trim().is_empty() => {
+                    Some(ModelRouteConfig {
+                        pattern: pattern.trim().to_string(),
+                        backend: backend.trim().to_lowercase(),
+                    })
+                }
+                _ => {
+                    tracing::warn!(
+                        "Invalid MODEL_ROUTES entry: {}. Expected format: pattern=backend",
+                        entry
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -874,6 +2365,75 @@ mod tests {
         assert!(settings.default_model_mapping.contains_key("claude-3-5-sonnet-20241022"));
     }
 
+    #[test]
+    fn test_parse_model_routes() {
+        let routes = parse_model_routes_str("claude-*=bedrock:east, gemini-*=Gemini,,bogus");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].pattern, "claude-*");
+        assert_eq!(routes[0].backend, "bedrock:east");
+        assert_eq!(routes[1].backend, "gemini");
+    }
+
+    #[test]
+    fn test_parse_model_deprecations() {
+        let schedules = parse_model_deprecations(
+            "claude-2.1=2024-07-21|2025-01-21|claude-3-5-haiku-20241022, \
+             claude-instant-*=2024-03-01T12:00:00Z, bogus=someday, =2024-01-01",
+            true,
+        );
+
+        assert_eq!(schedules.len(), 2);
+        assert_eq!(schedules[0].model, "claude-2.1");
+        assert_eq!(schedules[0].deprecated_at.to_rfc3339(), "2024-07-21T00:00:00+00:00");
+        assert_eq!(
+            schedules[0].sunset_at.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2025-01-21T00:00:00+00:00")
+        );
+        assert_eq!(schedules[0].successor.as_deref(), Some("claude-3-5-haiku-20241022"));
+        assert!(schedules[0].remap_after_sunset);
+        assert_eq!(schedules[1].sunset_at, None);
+        assert!(!schedules[1].remap_after_sunset);
+    }
+
+    #[test]
+    fn test_force_stream_thresholds() {
+        let config = StreamAssemblyConfig {
+            models: vec![],
+            max_tokens_thresholds: parse_force_stream_thresholds(
+                "claude-opus-*=4096, *=16000, bad, x=abc",
+            ),
+        };
+
+        assert_eq!(config.max_tokens_thresholds.len(), 2);
+        assert!(config.applies_to("claude-opus-4", "anthropic.claude-opus-4", Some(8192)));
+        assert!(!config.applies_to("claude-sonnet-4", "anthropic.claude-sonnet-4", Some(8192)));
+        assert!(config.applies_to("claude-sonnet-4", "anthropic.claude-sonnet-4", Some(32000)));
+        assert!(!config.applies_to("claude-opus-4", "anthropic.claude-opus-4", None));
+    }
+
+    #[test]
+    fn test_azure_openai_config() {
+        let mut config = AzureOpenAIConfig {
+            enabled: true,
+            endpoints: vec![
+                "https://a.openai.azure.com".to_string(),
+                "https://b.openai.azure.com".to_string(),
+            ],
+            deployments: parse_azure_deployments("gpt-4o=prod-4o, bad, gpt-4o-mini = mini"),
+            ..Default::default()
+        };
+        assert!(!config.is_available());
+        assert_eq!(config.deployments.len(), 2);
+        assert_eq!(config.deployments["gpt-4o-mini"], "mini");
+
+        config.api_keys = vec!["shared".to_string()];
+        assert!(config.is_available());
+        assert_eq!(config.api_key_for(1), Some("shared"));
+        config.api_keys.push("second".to_string());
+        assert_eq!(config.api_key_for(1), Some("second"));
+        assert_eq!(config.api_key_for(2), None);
+    }
+
     #[test]
     fn test_server_addr() {
         let settings = Settings::default();
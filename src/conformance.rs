@@ -0,0 +1,476 @@
+//! Converter conformance harness
+//!
+//! A conformance case is a client request and the backend response it got,
+//! together with golden copies of what the converters produced for them: the
+//! backend request and the client response. Running a case replays both
+//! halves of the round trip through the current converters and diffs the
+//! output against the goldens, so a converter refactor can show it changes
+//! nothing a client or backend would see.
+//!
+//! Cases are JSON files (one case each) or JSON Lines files (one case per
+//! line, e.g. recorded production traffic). A case without goldens is
+//! recorded with [`bless`]; the `conformance` binary does that with
+//! `--bless`. Fields that differ on every run (response IDs, timestamps,
+//! generated tool call IDs) are never compared.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::converters::{
+    AnthropicToBedrockConverter, BedrockToAnthropicConverter, BedrockToOpenAIConverter,
+    GeminiToAnthropicConverter, GeminiToOpenAIConverter, OpenAIToGeminiConverter, SharedConverters,
+};
+use crate::schemas::anthropic::MessageRequest;
+use crate::schemas::bedrock::BedrockConverseResponse;
+use crate::schemas::gemini::GeminiResponse;
+use crate::schemas::openai::ChatCompletionRequest;
+
+/// Paths that differ on every run of an Anthropic-format response
+const ANTHROPIC_VOLATILE: &[&str] = &["/id", "/content/*/id"];
+
+/// Paths that differ on every run of an OpenAI-format response
+const OPENAI_VOLATILE: &[&str] = &["/id", "/created", "/choices/*/message/tool_calls/*/id"];
+
+/// Client API and backend a case runs through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pipeline {
+    /// Anthropic Messages -> Bedrock Converse -> Anthropic Messages
+    AnthropicBedrock,
+    /// Anthropic Messages -> Gemini -> Anthropic Messages
+    AnthropicGemini,
+    /// OpenAI Chat Completions -> Bedrock Converse -> OpenAI Chat Completions
+    OpenaiBedrock,
+    /// OpenAI Chat Completions -> Gemini -> OpenAI Chat Completions
+    OpenaiGemini,
+}
+
+impl Pipeline {
+    fn volatile_paths(self) -> &'static [&'static str] {
+        match self {
+            Pipeline::AnthropicBedrock | Pipeline::AnthropicGemini => ANTHROPIC_VOLATILE,
+            Pipeline::OpenaiBedrock | Pipeline::OpenaiGemini => OPENAI_VOLATILE,
+        }
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Pipeline::AnthropicBedrock => "anthropic_bedrock",
+            Pipeline::AnthropicGemini => "anthropic_gemini",
+            Pipeline::OpenaiBedrock => "openai_bedrock",
+            Pipeline::OpenaiGemini => "openai_gemini",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Golden converter output of a case
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Expected {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_request: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+}
+
+/// One recorded round trip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceCase {
+    pub name: String,
+    pub pipeline: Pipeline,
+    /// Client request, as sent to the proxy
+    pub request: Value,
+    /// Backend response body (Converse or generateContent), if recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_response: Option<Value>,
+    #[serde(default)]
+    pub expected: Expected,
+    /// Further JSON pointer paths to skip when diffing (`*` matches any
+    /// key or index)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+}
+
+/// What the current converters produce for a case
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actual {
+    pub backend_request: Value,
+    pub response: Option<Value>,
+}
+
+/// One field that differs from the golden output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Difference {
+    /// "backend_request" or "response"
+    pub stage: &'static str,
+    /// JSON pointer to the field
+    pub path: String,
+    /// Golden value (None = the field is new)
+    pub expected: Option<Value>,
+    /// Current value (None = the field is gone)
+    pub actual: Option<Value>,
+}
+
+/// Outcome of running one case
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseReport {
+    pub name: String,
+    pub pipeline: Pipeline,
+    /// Conversion failure, if the case could not be run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub differences: Vec<Difference>,
+    /// Whether the case has goldens to compare against
+    pub verified: bool,
+}
+
+impl CaseReport {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.differences.is_empty()
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Value, what: &str) -> Result<T, String> {
+    serde_json::from_value(value.clone()).map_err(|e| format!("{} does not parse: {}", what, e))
+}
+
+/// Run a case through the current converters
+pub fn convert(case: &ConformanceCase) -> Result<Actual, String> {
+    let converters = SharedConverters::new();
+    let conversion_error = |e: &dyn fmt::Display| format!("Conversion error: {}", e);
+
+    let (model, backend_request) = match case.pipeline {
+        Pipeline::AnthropicBedrock => {
+            let request: MessageRequest = parse(&case.request, "request")?;
+            let converted = AnthropicToBedrockConverter::new()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (request.model, to_json(&converted)?)
+        }
+        Pipeline::AnthropicGemini => {
+            let request: MessageRequest = parse(&case.request, "request")?;
+            let (_, converted) = converters
+                .anthropic_to_gemini()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (request.model, to_json(&converted)?)
+        }
+        Pipeline::OpenaiBedrock => {
+            let request: ChatCompletionRequest = parse(&case.request, "request")?;
+            let converted = converters
+                .openai_to_bedrock()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (request.model, to_json(&converted)?)
+        }
+        Pipeline::OpenaiGemini => {
+            let request: ChatCompletionRequest = parse(&case.request, "request")?;
+            let (_, converted) = OpenAIToGeminiConverter::new()
+                .convert_request(&request)
+                .map_err(|e| conversion_error(&e))?;
+            (request.model, to_json(&converted)?)
+        }
+    };
+
+    let response = match case.backend_response {
+        None => None,
+        Some(ref body) => Some(match case.pipeline {
+            Pipeline::AnthropicBedrock => {
+                let body: BedrockConverseResponse = parse(body, "backend_response")?;
+                to_json(
+                    &BedrockToAnthropicConverter::new()
+                        .convert_response(&body, &model)
+                        .map_err(|e| conversion_error(&e))?,
+                )?
+            }
+            Pipeline::AnthropicGemini => {
+                let body: GeminiResponse = parse(body, "backend_response")?;
+                to_json(
+                    &GeminiToAnthropicConverter::new()
+                        .convert_response(&body, &model)
+                        .map_err(|e| conversion_error(&e))?,
+                )?
+            }
+            Pipeline::OpenaiBedrock => {
+                let body: BedrockConverseResponse = parse(body, "backend_response")?;
+                to_json(
+                    &BedrockToOpenAIConverter::new()
+                        .convert_response(&body, &model)
+                        .map_err(|e| conversion_error(&e))?,
+                )?
+            }
+            Pipeline::OpenaiGemini => {
+                let body: GeminiResponse = parse(body, "backend_response")?;
+                to_json(
+                    &GeminiToOpenAIConverter::new()
+                        .convert_response(&body, &model)
+                        .map_err(|e| conversion_error(&e))?,
+                )?
+            }
+        }),
+    };
+
+    Ok(Actual {
+        backend_request,
+        response,
+    })
+}
+
+/// Run a case and diff the output against its goldens
+pub fn run_case(case: &ConformanceCase) -> CaseReport {
+    let mut report = CaseReport {
+        name: case.name.clone(),
+        pipeline: case.pipeline,
+        error: None,
+        differences: Vec::new(),
+        verified: case.expected.backend_request.is_some() || case.expected.response.is_some(),
+    };
+
+    let actual = match convert(case) {
+        Ok(actual) => actual,
+        Err(e) => {
+            report.error = Some(e);
+            return report;
+        }
+    };
+
+    let ignore: Vec<&str> = case
+        .pipeline
+        .volatile_paths()
+        .iter()
+        .copied()
+        .chain(case.ignore.iter().map(String::as_str))
+        .collect();
+    let mut diff = |stage, expected: Option<&Value>, actual: Option<&Value>| {
+        if let Some(expected) = expected {
+            let mut differences = Vec::new();
+            diff_values(
+                expected,
+                actual.unwrap_or(&Value::Null),
+                "",
+                &mut differences,
+            );
+            report.differences.extend(
+                differences
+                    .into_iter()
+                    .filter(|(path, _, _)| !ignore.iter().any(|p| path_matches(p, path)))
+                    .map(|(path, expected, actual)| Difference {
+                        stage,
+                        path,
+                        expected,
+                        actual,
+                    }),
+            );
+        }
+    };
+    diff(
+        "backend_request",
+        case.expected.backend_request.as_ref(),
+        Some(&actual.backend_request),
+    );
+    diff(
+        "response",
+        case.expected.response.as_ref(),
+        actual.response.as_ref(),
+    );
+
+    report
+}
+
+/// Record the current converter output as the case's goldens
+pub fn bless(case: &mut ConformanceCase) -> Result<(), String> {
+    let actual = convert(case)?;
+    case.expected = Expected {
+        backend_request: Some(actual.backend_request),
+        response: actual.response,
+    };
+    Ok(())
+}
+
+/// Collect the differences between two JSON values as
+/// (path, expected, actual) triples
+fn diff_values(
+    expected: &Value,
+    actual: &Value,
+    path: &str,
+    out: &mut Vec<(String, Option<Value>, Option<Value>)>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match (expected.get(key), actual.get(key)) {
+                    (Some(e), Some(a)) => diff_values(e, a, &child, out),
+                    (e, a) => out.push((child, e.cloned(), a.cloned())),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let child = format!("{}/{}", path, i);
+                match (expected.get(i), actual.get(i)) {
+                    (Some(e), Some(a)) => diff_values(e, a, &child, out),
+                    (e, a) => out.push((child, e.cloned(), a.cloned())),
+                }
+            }
+        }
+        (e, a) if e != a => out.push((path.to_string(), Some(e.clone()), Some(a.clone()))),
+        _ => {}
+    }
+}
+
+/// Whether a JSON pointer matches an ignore pattern
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    pattern.len() == path.len() && pattern.iter().zip(&path).all(|(p, s)| *p == "*" || p == s)
+}
+
+/// Cases stored in one file
+#[derive(Debug, Clone)]
+pub struct CaseFile {
+    pub path: PathBuf,
+    pub cases: Vec<ConformanceCase>,
+}
+
+impl CaseFile {
+    fn is_jsonl(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "jsonl")
+    }
+
+    /// Read a `.json` case or a `.jsonl` file of cases
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let cases = if Self::is_jsonl(path) {
+            text.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    serde_json::from_str(line)
+                        .with_context(|| format!("{}:{}", path.display(), i + 1))
+                })
+                .collect::<anyhow::Result<_>>()?
+        } else {
+            vec![serde_json::from_str(&text).with_context(|| path.display().to_string())?]
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            cases,
+        })
+    }
+
+    /// Write the cases back in the file's format
+    pub fn save(&self) -> anyhow::Result<()> {
+        let text = if Self::is_jsonl(&self.path) {
+            let mut text = String::new();
+            for case in &self.cases {
+                text.push_str(&serde_json::to_string(case)?);
+                text.push('\n');
+            }
+            text
+        } else {
+            let mut text = serde_json::to_string_pretty(&self.cases[0])?;
+            text.push('\n');
+            text
+        };
+        std::fs::write(&self.path, text).with_context(|| format!("writing {}", self.path.display()))
+    }
+}
+
+/// Load a case file, or every `.json`/`.jsonl` file in a directory
+pub fn load(path: &Path) -> anyhow::Result<Vec<CaseFile>> {
+    if !path.is_dir() {
+        return Ok(vec![CaseFile::load(path)?]);
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext == "json" || ext == "jsonl")
+        })
+        .collect();
+    paths.sort();
+    paths.iter().map(|p| CaseFile::load(p)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixtures() -> Vec<CaseFile> {
+        load(Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/conformance"
+        )))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fixtures_conform() {
+        let files = fixtures();
+        assert!(!files.is_empty());
+        for case in files.iter().flat_map(|f| &f.cases) {
+            let report = run_case(case);
+            assert!(report.passed(), "{}: {:?}", case.name, report);
+        }
+    }
+
+    #[test]
+    fn test_blessed_case_detects_drift() {
+        let files = fixtures();
+        let mut case = files
+            .iter()
+            .flat_map(|f| &f.cases)
+            .find(|c| c.pipeline == Pipeline::OpenaiGemini && c.backend_response.is_some())
+            .unwrap()
+            .clone();
+        bless(&mut case).unwrap();
+
+        // Volatile fields (id, created) differ between runs but are ignored
+        let report = run_case(&case);
+        assert!(report.verified);
+        assert!(report.passed(), "{:?}", report);
+
+        let response = case.expected.response.as_mut().unwrap();
+        response["choices"][0]["finish_reason"] = json!("length");
+        response["unexpected"] = json!(true);
+        let report = run_case(&case);
+        let paths: Vec<_> = report.differences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["/choices/0/finish_reason", "/unexpected"]);
+        assert_eq!(report.differences[1].actual, None);
+
+        case.ignore = vec!["/choices/*/finish_reason".to_string()];
+        assert_eq!(run_case(&case).differences.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_reports_added_and_removed_fields() {
+        let mut out = Vec::new();
+        diff_values(
+            &json!({"a": [1, 2], "b": {"c": "x"}}),
+            &json!({"a": [1], "b": {"c": "y", "d/e": 1}}),
+            "",
+            &mut out,
+        );
+        let paths: Vec<_> = out.iter().map(|(p, _, _)| p.as_str()).collect();
+        assert_eq!(paths, vec!["/a/1", "/b/c", "/b/d~1e"]);
+        assert!(path_matches("/content/*/id", "/content/3/id"));
+        assert!(!path_matches("/content/*/id", "/content/3/input/id"));
+    }
+}
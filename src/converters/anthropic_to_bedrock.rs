@@ -3,6 +3,7 @@
 //! This module handles the conversion of Anthropic Messages API requests
 //! to AWS Bedrock Converse API format.
 
+use crate::converters::warnings::ConversionWarning;
 use crate::schemas::anthropic::{
     CacheControl, ContentBlock, Message, MessageContent, MessageRequest, SystemContent, Tool,
     ToolChoice, ToolInputSchema, ToolResultValue,
@@ -14,8 +15,9 @@ use crate::schemas::bedrock::{
     BedrockToolConfig, BedrockToolInputSchema, BedrockToolResultData, BedrockToolSpec,
     BedrockToolUseData,
 };
+use crate::utils::{take_base64, validate_base64};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use thiserror::Error;
 
 // ============================================================================
@@ -44,6 +46,44 @@ pub enum ConversionError {
     UnsupportedFeature(String),
 }
 
+// ============================================================================
+// Document Names
+// ============================================================================
+
+/// Bedrock document name used when the client gives no title
+const DEFAULT_DOCUMENT_NAME: &str = "document";
+
+/// Bedrock's maximum document name length
+const MAX_DOCUMENT_NAME_LEN: usize = 200;
+
+/// Sanitize a title into a Bedrock document name
+///
+/// Bedrock allows ASCII alphanumerics, hyphens, parentheses, square brackets and
+/// single spaces. Other characters become spaces, runs of spaces collapse and
+/// the result is truncated; None if nothing usable remains.
+fn sanitize_document_name(title: &str) -> Option<String> {
+    let cleaned: String = title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '(' | ')' | '[' | ']') {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    let name: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_DOCUMENT_NAME_LEN)
+        .collect();
+    let name = name.trim_end().to_string();
+
+    (!name.is_empty()).then_some(name)
+}
+
 // ============================================================================
 // Converter Implementation
 // ============================================================================
@@ -140,72 +180,147 @@ impl AnthropicToBedrockConverter {
             bedrock_request.system = Some(self.convert_system(system));
         }
 
-        // Convert tools
+        // Convert tools (tools with input_examples go in additional fields instead)
         if let Some(ref tools) = request.tools {
-            if !tools.is_empty() {
-                // Check if any tools have input_examples
-                let has_input_examples = self.tools_have_input_examples(tools);
-
-                if has_input_examples {
-                    // When tools have input_examples, pass them through additionalModelRequestFields
-                    // in Anthropic format since Bedrock's standard toolSpec doesn't support inputExamples
-                    let mut fields = bedrock_request
-                        .additional_model_request_fields
-                        .unwrap_or_else(|| serde_json::json!({}));
-
-                    if let Some(obj) = fields.as_object_mut() {
-                        // Filter out code_execution tools and pass regular tools in Anthropic format
-                        let anthropic_tools: Vec<_> = tools
-                            .iter()
-                            .filter(|t| {
-                                !t.get("type")
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s == "code_execution_20250825")
-                                    .unwrap_or(false)
-                            })
-                            .cloned()
-                            .collect();
+            if !tools.is_empty() && !Self::tools_have_input_examples(tools) {
+                bedrock_request.tool_config = Some(self.convert_tool_config(tools, &request.tool_choice)?);
+            }
+        }
 
-                        obj.insert("tools".to_string(), serde_json::json!(anthropic_tools));
+        bedrock_request.additional_model_request_fields = Self::additional_model_request_fields(request);
 
-                        // Also pass tool_choice if specified
-                        if let Some(ref tc) = request.tool_choice {
-                            obj.insert("tool_choice".to_string(), self.tool_choice_to_json(tc));
-                        }
-                    }
+        Ok(bedrock_request)
+    }
 
-                    bedrock_request.additional_model_request_fields = Some(fields);
-                } else {
-                    // Standard tool config without input_examples
-                    bedrock_request.tool_config = Some(self.convert_tool_config(tools, &request.tool_choice)?);
+    /// Convert a request, moving its base64 media out instead of copying it
+    ///
+    /// Image and document payloads are decoded in place (reusing the encoded
+    /// string's allocation) and tool result images are moved into the IR, so
+    /// the encoded and decoded copies never coexist. Their `data` is left
+    /// empty in `request`; everything else matches [`Self::convert_request`].
+    pub fn convert_request_in_place(
+        &self,
+        request: &mut MessageRequest,
+    ) -> Result<BedrockConverseRequest, ConversionError> {
+        let mut media = TakenMedia::default();
+        for message in &mut request.messages {
+            if let MessageContent::Blocks(blocks) = &mut message.content {
+                media.take_from(blocks)?;
+            }
+        }
+
+        let mut converted = self.convert_request(request)?;
+        for block in converted.messages.iter_mut().flat_map(|m| m.content.iter_mut()) {
+            media.put_into(block);
+        }
+        Ok(converted)
+    }
+
+    /// Fields sent through `additionalModelRequestFields`:
+    /// - tools in Anthropic format (with `tool_choice`) when any tool has
+    ///   `input_examples`, since Bedrock's toolSpec has no inputExamples
+    /// - extended thinking
+    /// - `top_k`, which the Converse inference config lacks
+    pub fn additional_model_request_fields(request: &MessageRequest) -> Option<serde_json::Value> {
+        let mut fields = serde_json::Map::new();
+
+        if let Some(ref tools) = request.tools {
+            if Self::tools_have_input_examples(tools) {
+                // Code execution is handled by the proxy, not the model
+                let anthropic_tools: Vec<_> = tools
+                    .iter()
+                    .filter(|t| {
+                        !t.get("type")
+                            .and_then(|v| v.as_str())
+                            .map(|s| s == "code_execution_20250825")
+                            .unwrap_or(false)
+                    })
+                    .cloned()
+                    .collect();
+                fields.insert("tools".to_string(), serde_json::json!(anthropic_tools));
+
+                if let Some(ref tc) = request.tool_choice {
+                    fields.insert("tool_choice".to_string(), Self::tool_choice_to_json(tc));
                 }
             }
         }
 
-        // Handle extended thinking
         if let Some(ref thinking) = request.thinking {
-            let mut fields = bedrock_request
-                .additional_model_request_fields
-                .unwrap_or_else(|| serde_json::json!({}));
-
-            if let Some(obj) = fields.as_object_mut() {
-                obj.insert(
-                    "thinking".to_string(),
-                    serde_json::json!({
-                        "type": thinking.thinking_type,
-                        "budget_tokens": thinking.budget_tokens
-                    }),
-                );
+            let mut thinking_json = serde_json::json!({ "type": thinking.thinking_type });
+            if let Some(budget_tokens) = thinking.budget_tokens {
+                thinking_json["budget_tokens"] = serde_json::json!(budget_tokens);
             }
+            fields.insert("thinking".to_string(), thinking_json);
+        }
 
-            bedrock_request.additional_model_request_fields = Some(fields);
+        if let Some(top_k) = request.top_k {
+            fields.insert("top_k".to_string(), serde_json::json!(top_k));
         }
 
-        Ok(bedrock_request)
+        (!fields.is_empty()).then_some(serde_json::Value::Object(fields))
+    }
+
+    /// Fields of `request` that [`convert_request`](Self::convert_request)
+    /// drops rather than sending to Bedrock
+    pub fn conversion_warnings(request: &MessageRequest) -> Vec<ConversionWarning> {
+        let mut warnings = Vec::new();
+
+        if request.seed.is_some() {
+            warnings.push(ConversionWarning::ignored(
+                "seed",
+                "seed is not supported by Bedrock",
+            ));
+        }
+
+        for (i, message) in request.messages.iter().enumerate() {
+            let MessageContent::Blocks(ref blocks) = message.content else {
+                continue;
+            };
+            for (j, block) in blocks.iter().enumerate() {
+                let field = format!("messages[{}].content[{}]", i, j);
+                match block {
+                    ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => {
+                        warnings.push(ConversionWarning::dropped(
+                            field,
+                            "Thinking blocks from earlier turns are not sent to Bedrock",
+                        ));
+                    }
+                    ContentBlock::ServerToolUse { .. } | ContentBlock::ServerToolResult { .. } => {
+                        warnings.push(ConversionWarning::dropped(
+                            field,
+                            "Server tool blocks are not supported by Bedrock",
+                        ));
+                    }
+                    ContentBlock::ToolResult {
+                        content: ToolResultValue::Blocks(inner),
+                        ..
+                    } => {
+                        let skipped = inner
+                            .iter()
+                            .filter(|b| {
+                                !matches!(b, ContentBlock::Text { .. } | ContentBlock::Image { .. })
+                            })
+                            .count();
+                        if skipped > 0 {
+                            warnings.push(ConversionWarning::dropped(
+                                field,
+                                format!(
+                                    "{} tool result block(s) other than text and image were removed",
+                                    skipped
+                                ),
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        warnings
     }
 
     /// Check if any tools have input_examples defined.
-    fn tools_have_input_examples(&self, tools: &[serde_json::Value]) -> bool {
+    pub fn tools_have_input_examples(tools: &[serde_json::Value]) -> bool {
         tools.iter().any(|tool| {
             tool.get("input_examples")
                 .map(|v| !v.is_null() && v.as_array().map(|a| !a.is_empty()).unwrap_or(false))
@@ -214,10 +329,19 @@ impl AnthropicToBedrockConverter {
     }
 
     /// Convert ToolChoice to JSON for additionalModelRequestFields.
-    fn tool_choice_to_json(&self, tool_choice: &ToolChoice) -> serde_json::Value {
+    fn tool_choice_to_json(tool_choice: &ToolChoice) -> serde_json::Value {
         match tool_choice {
             ToolChoice::Auto(s) => serde_json::json!({"type": s}),
-            ToolChoice::Specific { name } => serde_json::json!({"type": "tool", "name": name}),
+            ToolChoice::Specific {
+                name,
+                disable_parallel_tool_use,
+            } => {
+                let mut json = serde_json::json!({"type": "tool", "name": name});
+                if let Some(disable) = disable_parallel_tool_use {
+                    json["disable_parallel_tool_use"] = serde_json::json!(disable);
+                }
+                json
+            }
             ToolChoice::Object(obj) => obj.clone(),
         }
     }
@@ -255,7 +379,41 @@ impl AnthropicToBedrockConverter {
         &self,
         messages: &[Message],
     ) -> Result<Vec<BedrockMessage>, ConversionError> {
-        messages.iter().map(|m| self.convert_message(m)).collect()
+        let mut messages = messages
+            .iter()
+            .map(|m| self.convert_message(m))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::dedupe_document_names(&mut messages);
+        Ok(messages)
+    }
+
+    /// Make document names unique across the request, as Bedrock requires:
+    /// repeated names get a ` (2)`, ` (3)`, ... suffix.
+    fn dedupe_document_names(messages: &mut [BedrockMessage]) {
+        let mut seen = HashSet::new();
+        let documents = messages
+            .iter_mut()
+            .flat_map(|m| m.content.iter_mut())
+            .filter_map(|block| match block {
+                BedrockContentBlock::Document { document, .. } => Some(document),
+                _ => None,
+            });
+
+        for document in documents {
+            let mut name = document.name.clone();
+            let mut n = 1;
+            while !seen.insert(name.to_ascii_lowercase()) {
+                n += 1;
+                let suffix = format!(" ({})", n);
+                let base: String = document
+                    .name
+                    .chars()
+                    .take(MAX_DOCUMENT_NAME_LEN - suffix.len())
+                    .collect();
+                name = format!("{}{}", base.trim_end(), suffix);
+            }
+            document.name = name;
+        }
     }
 
     /// Convert a single Anthropic message to Bedrock message.
@@ -330,8 +488,12 @@ impl AnthropicToBedrockConverter {
                 Ok(Some(BedrockContentBlock::Image { image, cache_point }))
             }
 
-            ContentBlock::Document { source, cache_control } => {
-                let document = self.convert_document(source)?;
+            ContentBlock::Document {
+                source,
+                title,
+                cache_control,
+            } => {
+                let document = self.convert_document(source, title.as_deref())?;
                 let cache_point = Self::convert_cache_control(cache_control);
                 Ok(Some(BedrockContentBlock::Document {
                     document,
@@ -398,9 +560,13 @@ impl AnthropicToBedrockConverter {
     }
 
     /// Convert an Anthropic document source to Bedrock document data.
+    ///
+    /// The name is the sanitized title, or "document" without one; names are
+    /// made unique per request by `dedupe_document_names`.
     fn convert_document(
         &self,
         source: &crate::schemas::anthropic::DocumentSource,
+        title: Option<&str>,
     ) -> Result<BedrockDocumentData, ConversionError> {
         // Decode base64 data
         let bytes = BASE64
@@ -417,8 +583,11 @@ impl AnthropicToBedrockConverter {
 
         Ok(BedrockDocumentData {
             format,
-            name: "document".to_string(), // Default name
+            name: title
+                .and_then(sanitize_document_name)
+                .unwrap_or_else(|| DEFAULT_DOCUMENT_NAME.to_string()),
             source: BedrockDocumentSource { bytes },
+            title: title.map(str::to_string),
         })
     }
 
@@ -439,11 +608,15 @@ impl AnthropicToBedrockConverter {
                             converted.push(serde_json::json!({"text": text}));
                         }
                         ContentBlock::Image { source, .. } => {
-                            let image = self.convert_image(source)?;
+                            // Bedrock's JSON form carries bytes as base64, so the
+                            // payload is validated and passed through undecoded
+                            validate_base64(&source.data)
+                                .map_err(|e| ConversionError::Base64DecodeError(e.to_string()))?;
+                            let format = source.media_type.split('/').nth(1).unwrap_or("png");
                             converted.push(serde_json::json!({
                                 "image": {
-                                    "format": image.format,
-                                    "source": {"bytes": image.source.bytes}
+                                    "format": format,
+                                    "source": {"bytes": source.data}
                                 }
                             }));
                         }
@@ -507,8 +680,8 @@ impl AnthropicToBedrockConverter {
             config = config.with_stop_sequences(stop_sequences.clone());
         }
 
-        // Note: top_k is Anthropic-specific and not directly supported in Bedrock Converse API
-        // It can be passed via additional_model_request_fields if needed
+        // top_k is not part of the Converse inference config; it is sent in
+        // additional_model_request_fields
 
         config
     }
@@ -625,7 +798,9 @@ impl AnthropicToBedrockConverter {
             ToolChoice::Auto(s) if s == "any" => BedrockToolChoice::Any {
                 any: serde_json::json!({}),
             },
-            ToolChoice::Specific { name } => BedrockToolChoice::Tool {
+            // Converse has no parallel-tool-use switch; the proxy trims extra
+            // tool calls from the response instead
+            ToolChoice::Specific { name, .. } => BedrockToolChoice::Tool {
                 tool: BedrockToolChoiceTool { name: name.clone() },
             },
             ToolChoice::Object(obj) => {
@@ -656,6 +831,66 @@ impl Default for AnthropicToBedrockConverter {
     }
 }
 
+/// Base64 payloads moved out of a request by `convert_request_in_place`
+#[derive(Default)]
+struct TakenMedia {
+    /// Decoded image and document bytes, in block order
+    decoded: VecDeque<Vec<u8>>,
+    /// Tool result images, still base64 (Bedrock's JSON form), in block order
+    tool_result_images: VecDeque<String>,
+}
+
+impl TakenMedia {
+    fn take_from(&mut self, blocks: &mut [ContentBlock]) -> Result<(), ConversionError> {
+        let decode_error = |e: base64::DecodeError| ConversionError::Base64DecodeError(e.to_string());
+        for block in blocks {
+            match block {
+                ContentBlock::Image { source, .. } => {
+                    self.decoded.push_back(take_base64(&mut source.data).map_err(decode_error)?);
+                }
+                ContentBlock::Document { source, .. } => {
+                    self.decoded.push_back(take_base64(&mut source.data).map_err(decode_error)?);
+                }
+                ContentBlock::ToolResult {
+                    content: ToolResultValue::Blocks(inner),
+                    ..
+                } => {
+                    for inner in inner {
+                        if let ContentBlock::Image { source, .. } = inner {
+                            validate_base64(&source.data).map_err(decode_error)?;
+                            self.tool_result_images.push_back(std::mem::take(&mut source.data));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the payloads back into the converted blocks (converted in the same order)
+    fn put_into(&mut self, block: &mut BedrockContentBlock) {
+        match block {
+            BedrockContentBlock::Image { image, .. } => {
+                image.source.bytes = self.decoded.pop_front().unwrap_or_default();
+            }
+            BedrockContentBlock::Document { document, .. } => {
+                document.source.bytes = self.decoded.pop_front().unwrap_or_default();
+            }
+            BedrockContentBlock::ToolResult { tool_result, .. } => {
+                for bytes in tool_result
+                    .content
+                    .iter_mut()
+                    .filter_map(|value| value.pointer_mut("/image/source/bytes"))
+                {
+                    *bytes = self.tool_result_images.pop_front().unwrap_or_default().into();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -663,7 +898,7 @@ impl Default for AnthropicToBedrockConverter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schemas::anthropic::{ImageSource, Message, SystemMessage};
+    use crate::schemas::anthropic::{ImageSource, Message, SystemMessage, ThinkingConfig};
 
     #[test]
     fn test_converter_creation() {
@@ -848,6 +1083,7 @@ mod tests {
         // Specific tool
         let choice = ToolChoice::Specific {
             name: "get_weather".to_string(),
+            disable_parallel_tool_use: None,
         };
         let result = converter.convert_tool_choice(&choice);
         if let BedrockToolChoice::Tool { tool } = result {
@@ -889,6 +1125,33 @@ mod tests {
         assert!(result.is_none()); // Thinking blocks should be skipped
     }
 
+    #[test]
+    fn test_conversion_warnings_for_dropped_blocks() {
+        let request = MessageRequest::new(
+            "claude-3-5-sonnet-20241022",
+            vec![
+                Message::user("Hello"),
+                Message::with_blocks(
+                    "assistant",
+                    vec![
+                        ContentBlock::Thinking {
+                            thinking: "Let me think...".to_string(),
+                            signature: Some("sig".to_string()),
+                        },
+                        ContentBlock::text("Hi"),
+                    ],
+                ),
+            ],
+            1024,
+        );
+
+        let warnings = AnthropicToBedrockConverter::conversion_warnings(&request);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, crate::converters::WarningKind::Dropped);
+        assert_eq!(warnings[0].field, "messages[1].content[0]");
+    }
+
     #[test]
     fn test_full_request_conversion() {
         let converter = AnthropicToBedrockConverter::new();
@@ -928,6 +1191,83 @@ mod tests {
         assert!(!result.source.bytes.is_empty());
     }
 
+    #[test]
+    fn test_tool_result_image_passes_base64_through() {
+        let converter = AnthropicToBedrockConverter::new();
+        let png_data = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+        let block = ContentBlock::ToolResult {
+            tool_use_id: "tool_789".to_string(),
+            content: ToolResultValue::Blocks(vec![ContentBlock::Image {
+                source: ImageSource {
+                    source_type: "base64".to_string(),
+                    media_type: "image/png".to_string(),
+                    data: png_data.to_string(),
+                },
+                cache_control: None,
+            }]),
+            is_error: None,
+            cache_control: None,
+        };
+
+        let result = converter.convert_content_block(&block).unwrap();
+        if let Some(BedrockContentBlock::ToolResult { tool_result, .. }) = result {
+            assert_eq!(tool_result.content[0]["image"]["format"], "png");
+            assert_eq!(tool_result.content[0]["image"]["source"]["bytes"], png_data);
+        } else {
+            panic!("Expected ToolResult block");
+        }
+    }
+
+    #[test]
+    fn test_convert_request_in_place_matches_convert_request() {
+        let png_data = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+        let mut request: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-sonnet",
+            "max_tokens": 1024,
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": png_data}},
+                    {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjQ="}},
+                    {"type": "text", "text": "Describe these"}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "tool_1", "name": "screenshot", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "tool_1", "content": [
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": png_data}}
+                    ]}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let converter = AnthropicToBedrockConverter::new();
+        let expected = converter.convert_request(&request).unwrap();
+        let converted = converter.convert_request_in_place(&mut request).unwrap();
+        assert_eq!(converted.messages, expected.messages);
+
+        // The payloads were moved out of the request
+        let MessageContent::Blocks(ref blocks) = request.messages[0].content else {
+            panic!("Expected blocks");
+        };
+        assert!(matches!(&blocks[0], ContentBlock::Image { source, .. } if source.data.is_empty()));
+
+        let mut invalid: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-sonnet",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": [
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "not base64!"}}
+            ]}]
+        }))
+        .unwrap();
+        assert!(matches!(
+            converter.convert_request_in_place(&mut invalid),
+            Err(ConversionError::Base64DecodeError(_))
+        ));
+    }
+
     #[test]
     fn test_error_tool_result_conversion() {
         let converter = AnthropicToBedrockConverter::new();
@@ -962,12 +1302,64 @@ mod tests {
             data: pdf_data.to_string(),
         };
 
-        let result = converter.convert_document(&source).unwrap();
+        let result = converter.convert_document(&source, None).unwrap();
         assert_eq!(result.format, "pdf");
         assert!(!result.source.bytes.is_empty());
         assert_eq!(result.name, "document");
     }
 
+    #[test]
+    fn test_document_names() {
+        use crate::schemas::anthropic::DocumentSource;
+
+        let converter = AnthropicToBedrockConverter::new();
+        let document = |title: Option<&str>| ContentBlock::Document {
+            source: DocumentSource {
+                source_type: "base64".to_string(),
+                media_type: "application/pdf".to_string(),
+                data: "JVBERi0xLjQK".to_string(),
+            },
+            title: title.map(str::to_string),
+            cache_control: None,
+        };
+
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: MessageContent::Blocks(vec![
+                    document(Some("Q3 report.pdf")),
+                    document(Some("Q3 report/pdf")),
+                    document(None),
+                ]),
+            },
+            Message {
+                role: "user".to_string(),
+                content: MessageContent::Blocks(vec![document(None), document(Some("***"))]),
+            },
+        ];
+
+        let result = converter.convert_messages(&messages).unwrap();
+        let names: Vec<_> = result
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|block| match block {
+                BedrockContentBlock::Document { document, .. } => Some(document.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec!["Q3 report pdf", "Q3 report pdf (2)", "document", "document (2)", "document (3)"]
+        );
+
+        if let BedrockContentBlock::Document { document, .. } = &result[0].content[1] {
+            assert_eq!(document.title.as_deref(), Some("Q3 report/pdf"));
+        }
+
+        assert_eq!(sanitize_document_name(&"a".repeat(300)).unwrap().len(), MAX_DOCUMENT_NAME_LEN);
+        assert_eq!(sanitize_document_name("  [draft]  notes_v2 "), Some("[draft] notes v2".to_string()));
+    }
+
     #[test]
     fn test_invalid_base64_error() {
         let converter = AnthropicToBedrockConverter::new();
@@ -1044,8 +1436,6 @@ mod tests {
 
     #[test]
     fn test_tools_have_input_examples() {
-        let converter = AnthropicToBedrockConverter::new();
-
         // Tools without input_examples
         let tools_without = vec![serde_json::json!({
             "name": "get_weather",
@@ -1055,7 +1445,7 @@ mod tests {
                 "properties": {}
             }
         })];
-        assert!(!converter.tools_have_input_examples(&tools_without));
+        assert!(!AnthropicToBedrockConverter::tools_have_input_examples(&tools_without));
 
         // Tools with empty input_examples
         let tools_empty = vec![serde_json::json!({
@@ -1067,7 +1457,7 @@ mod tests {
             },
             "input_examples": []
         })];
-        assert!(!converter.tools_have_input_examples(&tools_empty));
+        assert!(!AnthropicToBedrockConverter::tools_have_input_examples(&tools_empty));
 
         // Tools with input_examples
         let tools_with = vec![serde_json::json!({
@@ -1084,7 +1474,7 @@ mod tests {
                 {"location": "Tokyo, Japan"}
             ]
         })];
-        assert!(converter.tools_have_input_examples(&tools_with));
+        assert!(AnthropicToBedrockConverter::tools_have_input_examples(&tools_with));
 
         // Mixed - one tool has examples
         let tools_mixed = vec![
@@ -1100,28 +1490,32 @@ mod tests {
                 "input_examples": [{"arg": "value"}]
             }),
         ];
-        assert!(converter.tools_have_input_examples(&tools_mixed));
+        assert!(AnthropicToBedrockConverter::tools_have_input_examples(&tools_mixed));
     }
 
     #[test]
     fn test_tool_choice_to_json() {
-        let converter = AnthropicToBedrockConverter::new();
-
         // Auto
         let auto = ToolChoice::Auto("auto".to_string());
-        let json = converter.tool_choice_to_json(&auto);
+        let json = AnthropicToBedrockConverter::tool_choice_to_json(&auto);
         assert_eq!(json["type"], "auto");
 
         // Any
         let any = ToolChoice::Auto("any".to_string());
-        let json = converter.tool_choice_to_json(&any);
+        let json = AnthropicToBedrockConverter::tool_choice_to_json(&any);
         assert_eq!(json["type"], "any");
 
         // Specific
-        let specific = ToolChoice::Specific { name: "get_weather".to_string() };
-        let json = converter.tool_choice_to_json(&specific);
+        let specific = ToolChoice::Specific { name: "get_weather".to_string(), disable_parallel_tool_use: None };
+        let json = AnthropicToBedrockConverter::tool_choice_to_json(&specific);
         assert_eq!(json["type"], "tool");
         assert_eq!(json["name"], "get_weather");
+        assert!(json.get("disable_parallel_tool_use").is_none());
+
+        // Specific with parallel tool use disabled
+        let single = ToolChoice::Specific { name: "get_weather".to_string(), disable_parallel_tool_use: Some(true) };
+        let json = AnthropicToBedrockConverter::tool_choice_to_json(&single);
+        assert_eq!(json["disable_parallel_tool_use"], true);
     }
 
     #[test]
@@ -1210,6 +1604,26 @@ mod tests {
         assert_eq!(tool_config.tools[0].tool_spec.name, "get_weather");
     }
 
+    #[test]
+    fn test_additional_fields_thinking_and_top_k() {
+        let mut request = MessageRequest::new("claude-3-sonnet", vec![Message::user("Hi")], 1024);
+        assert!(AnthropicToBedrockConverter::additional_model_request_fields(&request).is_none());
+
+        request.top_k = Some(40);
+        request.thinking = Some(ThinkingConfig {
+            thinking_type: "enabled".to_string(),
+            budget_tokens: Some(2048),
+        });
+        let fields = AnthropicToBedrockConverter::additional_model_request_fields(&request).unwrap();
+        assert_eq!(
+            fields,
+            serde_json::json!({
+                "thinking": {"type": "enabled", "budget_tokens": 2048},
+                "top_k": 40
+            })
+        );
+    }
+
     #[test]
     fn test_multi_turn_tool_use_conversation() {
         let converter = AnthropicToBedrockConverter::new();
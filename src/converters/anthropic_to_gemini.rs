@@ -255,6 +255,9 @@ impl AnthropicToGeminiConverter {
             max_output_tokens: Some(request.max_tokens),
             stop_sequences: request.stop_sequences.clone(),
             candidate_count: None,
+            // Gemini seeds are 32-bit; wrapping keeps larger seeds deterministic
+            seed: request.seed.map(|seed| seed as i32),
+            ..Default::default()
         }
     }
 
@@ -327,7 +330,7 @@ impl AnthropicToGeminiConverter {
                     },
                 }))
             }
-            Some(ToolChoice::Specific { name }) => Ok(Some(ToolConfig {
+            Some(ToolChoice::Specific { name, .. }) => Ok(Some(ToolConfig {
                 function_calling_config: FunctionCallingConfig {
                     mode: "ANY".to_string(),
                     allowed_function_names: Some(vec![name.clone()]),
@@ -0,0 +1,710 @@
+//! Bedrock SDK materialization
+//!
+//! Both Bedrock request paths share one intermediate representation: the
+//! Anthropic and OpenAI converters produce a [`BedrockConverseRequest`], and
+//! this module turns it into the AWS SDK types sent by
+//! [`BedrockService`](crate::services::BedrockService). Handlers no longer
+//! build SDK types themselves, so a conversion fix in a converter (document
+//! names, tool result images, ...) applies to every endpoint.
+//!
+//! Image and document bytes are moved out of the IR, not copied, and tool
+//! result images (still base64 in the IR) are decoded in place.
+
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::types::{
+    AnyToolChoice, AutoToolChoice, CachePointBlock, CachePointType, ContentBlock, ConversationRole,
+    DocumentBlock, DocumentFormat, DocumentSource, ImageBlock, ImageFormat, ImageSource,
+    InferenceConfiguration, Message, SpecificToolChoice, SystemContentBlock, Tool, ToolChoice,
+    ToolConfiguration, ToolInputSchema, ToolResultBlock, ToolResultContentBlock, ToolResultStatus,
+    ToolSpecification, ToolUseBlock,
+};
+use aws_smithy_types::{Document, Number};
+use std::collections::HashMap;
+use thiserror::Error;
+
+use crate::schemas::bedrock::{
+    BedrockCachePoint, BedrockContentBlock, BedrockConverseRequest, BedrockDocumentData,
+    BedrockImageData, BedrockInferenceConfig, BedrockMessage, BedrockSystemMessage,
+    BedrockToolChoice, BedrockToolConfig, BedrockToolResultData, BedrockToolUseData,
+};
+use crate::services::ConverseRequest;
+use crate::utils::{take_base64, ToolNameMapper};
+
+/// Errors building SDK types from the IR
+#[derive(Debug, Error)]
+pub enum SdkConversionError {
+    #[error("Invalid role: {0}")]
+    InvalidRole(String),
+
+    #[error("Invalid base64: {0}")]
+    Base64DecodeError(String),
+
+    #[error("Failed to build {0}: {1}")]
+    Build(&'static str, String),
+}
+
+type SdkResult<T> = Result<T, SdkConversionError>;
+
+fn build_error(
+    what: &'static str,
+) -> impl FnOnce(aws_smithy_types::error::operation::BuildError) -> SdkConversionError {
+    move |e| SdkConversionError::Build(what, e.to_string())
+}
+
+/// Materialize a converted request as an SDK Converse request
+pub fn to_converse_request(request: BedrockConverseRequest) -> SdkResult<ConverseRequest> {
+    let messages = request
+        .messages
+        .into_iter()
+        .map(to_sdk_message)
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    let mut converse = ConverseRequest::new(request.model_id)
+        .with_messages(messages)
+        .with_inference_config(to_sdk_inference_config(request.inference_config));
+
+    if let Some(system) = request.system {
+        let system = to_sdk_system(system)?;
+        if !system.is_empty() {
+            converse = converse.with_system(system);
+        }
+    }
+
+    if let Some(tool_config) = request.tool_config {
+        if !tool_config.tools.is_empty() {
+            converse = converse.with_tool_config(to_sdk_tool_config(tool_config)?);
+        }
+    }
+
+    if let Some(ref fields) = request.additional_model_request_fields {
+        converse = converse.with_additional_fields(json_to_document(fields));
+    }
+
+    Ok(converse)
+}
+
+/// Shorten tool names over Bedrock's 64 character limit
+///
+/// Tool specs, tool choice and tool use blocks in the history are renamed
+/// consistently; `mapper` restores the original names in responses.
+pub fn shorten_tool_names(request: &mut BedrockConverseRequest, mapper: &mut ToolNameMapper) {
+    if let Some(ref mut tool_config) = request.tool_config {
+        for tool in &mut tool_config.tools {
+            tool.tool_spec.name = mapper.get_or_create_short_name(&tool.tool_spec.name);
+        }
+        if let Some(BedrockToolChoice::Tool { ref mut tool }) = tool_config.tool_choice {
+            tool.name = mapper.get_or_create_short_name(&tool.name);
+        }
+    }
+
+    for message in &mut request.messages {
+        for block in &mut message.content {
+            if let BedrockContentBlock::ToolUse { tool_use, .. } = block {
+                tool_use.name = mapper.get_or_create_short_name(&tool_use.name);
+            }
+        }
+    }
+
+    if mapper.has_mappings() {
+        tracing::info!(
+            mapping_count = mapper.mapping_count(),
+            "Tool names were shortened due to Bedrock's 64 character limit"
+        );
+    }
+}
+
+// ============================================================================
+// Messages and Content
+// ============================================================================
+
+fn to_sdk_message(message: BedrockMessage) -> SdkResult<Message> {
+    let role = match message.role.as_str() {
+        "user" => ConversationRole::User,
+        "assistant" => ConversationRole::Assistant,
+        other => return Err(SdkConversionError::InvalidRole(other.to_string())),
+    };
+
+    let mut content = Vec::with_capacity(message.content.len());
+    for block in message.content {
+        let (block, cache_point) = to_sdk_content_block(block)?;
+        content.push(block);
+        if let Some(cache_point) = cache_point {
+            content.push(ContentBlock::CachePoint(to_sdk_cache_point(&cache_point)?));
+        }
+    }
+
+    Message::builder()
+        .role(role)
+        .set_content(Some(content))
+        .build()
+        .map_err(build_error("message"))
+}
+
+/// SDK block and the cache point that follows it, if any
+fn to_sdk_content_block(
+    block: BedrockContentBlock,
+) -> SdkResult<(ContentBlock, Option<BedrockCachePoint>)> {
+    Ok(match block {
+        BedrockContentBlock::Text { text, cache_point } => (ContentBlock::Text(text), cache_point),
+        BedrockContentBlock::Image { image, cache_point } => {
+            (ContentBlock::Image(to_sdk_image(image)?), cache_point)
+        }
+        BedrockContentBlock::Document {
+            document,
+            cache_point,
+        } => (
+            ContentBlock::Document(to_sdk_document(document)?),
+            cache_point,
+        ),
+        BedrockContentBlock::ToolUse {
+            tool_use,
+            cache_point,
+        } => (
+            ContentBlock::ToolUse(to_sdk_tool_use(tool_use)?),
+            cache_point,
+        ),
+        BedrockContentBlock::ToolResult {
+            tool_result,
+            cache_point,
+        } => (
+            ContentBlock::ToolResult(to_sdk_tool_result(tool_result)?),
+            cache_point,
+        ),
+    })
+}
+
+fn to_sdk_image(image: BedrockImageData) -> SdkResult<ImageBlock> {
+    ImageBlock::builder()
+        .format(image_format(&image.format))
+        .source(ImageSource::Bytes(Blob::new(image.source.bytes)))
+        .build()
+        .map_err(build_error("image"))
+}
+
+fn to_sdk_document(document: BedrockDocumentData) -> SdkResult<DocumentBlock> {
+    DocumentBlock::builder()
+        .format(document_format(&document.format))
+        .name(document.name)
+        .source(DocumentSource::Bytes(Blob::new(document.source.bytes)))
+        .build()
+        .map_err(build_error("document"))
+}
+
+fn to_sdk_tool_use(tool_use: BedrockToolUseData) -> SdkResult<ToolUseBlock> {
+    ToolUseBlock::builder()
+        .tool_use_id(tool_use.tool_use_id)
+        .name(tool_use.name)
+        .input(json_to_document(&tool_use.input))
+        .build()
+        .map_err(build_error("tool use"))
+}
+
+fn to_sdk_tool_result(tool_result: BedrockToolResultData) -> SdkResult<ToolResultBlock> {
+    let content = tool_result
+        .content
+        .into_iter()
+        .filter_map(|value| to_sdk_tool_result_content(value).transpose())
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    let status = match tool_result.status.as_deref() {
+        Some("error") => ToolResultStatus::Error,
+        _ => ToolResultStatus::Success,
+    };
+
+    ToolResultBlock::builder()
+        .tool_use_id(tool_result.tool_use_id)
+        .set_content(Some(content))
+        .status(status)
+        .build()
+        .map_err(build_error("tool result"))
+}
+
+/// Tool result content in its JSON form (`{"text": ...}`, `{"image": ...}`
+/// or `{"json": ...}`); other shapes are skipped
+///
+/// Image bytes are taken out of the value and decoded in place.
+fn to_sdk_tool_result_content(
+    mut value: serde_json::Value,
+) -> SdkResult<Option<ToolResultContentBlock>> {
+    if let Some(text) = value.get("text").and_then(|v| v.as_str()) {
+        return Ok(Some(ToolResultContentBlock::Text(text.to_string())));
+    }
+
+    if let Some(image) = value.get_mut("image") {
+        let format = image_format(image.get("format").and_then(|v| v.as_str()).unwrap_or("png"));
+        let bytes = match image.pointer_mut("/source/bytes") {
+            Some(serde_json::Value::String(data)) => take_base64(data)
+                .map_err(|e| SdkConversionError::Base64DecodeError(e.to_string()))?,
+            _ => Vec::new(),
+        };
+        let image = ImageBlock::builder()
+            .format(format)
+            .source(ImageSource::Bytes(Blob::new(bytes)))
+            .build()
+            .map_err(build_error("image"))?;
+        return Ok(Some(ToolResultContentBlock::Image(image)));
+    }
+
+    if let Some(json) = value.get("json") {
+        return Ok(Some(ToolResultContentBlock::Json(json_to_document(json))));
+    }
+
+    Ok(None)
+}
+
+fn to_sdk_cache_point(cache_point: &BedrockCachePoint) -> SdkResult<CachePointBlock> {
+    CachePointBlock::builder()
+        .r#type(CachePointType::from(cache_point.cache_type.as_str()))
+        .build()
+        .map_err(build_error("cache point"))
+}
+
+/// Image format from a media subtype (`png`, `jpeg`, `jpg`, ...)
+fn image_format(format: &str) -> ImageFormat {
+    match format {
+        "jpeg" | "jpg" => ImageFormat::Jpeg,
+        "gif" => ImageFormat::Gif,
+        "webp" => ImageFormat::Webp,
+        _ => ImageFormat::Png,
+    }
+}
+
+/// Document format from a media subtype (`pdf`, `plain`, `csv`, ...)
+fn document_format(format: &str) -> DocumentFormat {
+    match format {
+        "plain" | "txt" => DocumentFormat::Txt,
+        "html" => DocumentFormat::Html,
+        "csv" => DocumentFormat::Csv,
+        "markdown" | "md" => DocumentFormat::Md,
+        _ => DocumentFormat::Pdf,
+    }
+}
+
+// ============================================================================
+// System, Inference and Tools
+// ============================================================================
+
+fn to_sdk_system(system: Vec<BedrockSystemMessage>) -> SdkResult<Vec<SystemContentBlock>> {
+    let mut blocks = Vec::with_capacity(system.len());
+    for message in system {
+        blocks.push(SystemContentBlock::Text(message.text));
+        if let Some(ref cache_point) = message.cache_point {
+            blocks.push(SystemContentBlock::CachePoint(to_sdk_cache_point(
+                cache_point,
+            )?));
+        }
+    }
+    Ok(blocks)
+}
+
+fn to_sdk_inference_config(config: BedrockInferenceConfig) -> InferenceConfiguration {
+    InferenceConfiguration::builder()
+        .max_tokens(config.max_tokens)
+        .set_temperature(config.temperature)
+        .set_top_p(config.top_p)
+        .set_stop_sequences(config.stop_sequences)
+        .build()
+}
+
+fn to_sdk_tool_config(tool_config: BedrockToolConfig) -> SdkResult<ToolConfiguration> {
+    let mut tools = Vec::with_capacity(tool_config.tools.len());
+    for tool in tool_config.tools {
+        let spec = ToolSpecification::builder()
+            .name(tool.tool_spec.name)
+            .description(tool.tool_spec.description)
+            .input_schema(ToolInputSchema::Json(json_to_document(
+                &tool.tool_spec.input_schema.json,
+            )))
+            .build()
+            .map_err(build_error("tool spec"))?;
+        tools.push(Tool::ToolSpec(spec));
+        if let Some(ref cache_point) = tool.cache_point {
+            tools.push(Tool::CachePoint(to_sdk_cache_point(cache_point)?));
+        }
+    }
+
+    let tool_choice = match tool_config.tool_choice {
+        Some(BedrockToolChoice::Auto { .. }) => {
+            Some(ToolChoice::Auto(AutoToolChoice::builder().build()))
+        }
+        Some(BedrockToolChoice::Any { .. }) => {
+            Some(ToolChoice::Any(AnyToolChoice::builder().build()))
+        }
+        Some(BedrockToolChoice::Tool { tool }) => Some(ToolChoice::Tool(
+            SpecificToolChoice::builder()
+                .name(tool.name)
+                .build()
+                .map_err(build_error("tool choice"))?,
+        )),
+        None => None,
+    };
+
+    ToolConfiguration::builder()
+        .set_tools(Some(tools))
+        .set_tool_choice(tool_choice)
+        .build()
+        .map_err(build_error("tool config"))
+}
+
+// ============================================================================
+// JSON <-> Document
+// ============================================================================
+
+/// Convert serde_json::Value to aws_smithy_types::Document
+pub fn json_to_document(value: &serde_json::Value) -> Document {
+    match value {
+        serde_json::Value::Null => Document::Null,
+        serde_json::Value::Bool(b) => Document::Bool(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                if i >= 0 {
+                    Document::Number(Number::PosInt(i as u64))
+                } else {
+                    Document::Number(Number::NegInt(i))
+                }
+            } else if let Some(u) = n.as_u64() {
+                Document::Number(Number::PosInt(u))
+            } else if let Some(f) = n.as_f64() {
+                Document::Number(Number::Float(f))
+            } else {
+                Document::Null
+            }
+        }
+        serde_json::Value::String(s) => Document::String(s.clone()),
+        serde_json::Value::Array(arr) => {
+            Document::Array(arr.iter().map(json_to_document).collect())
+        }
+        serde_json::Value::Object(obj) => Document::Object(
+            obj.iter()
+                .map(|(k, v)| (k.clone(), json_to_document(v)))
+                .collect::<HashMap<_, _>>(),
+        ),
+    }
+}
+
+/// Convert aws_smithy_types::Document to serde_json::Value
+pub fn document_to_json(doc: &Document) -> serde_json::Value {
+    match doc {
+        Document::Null => serde_json::Value::Null,
+        Document::Bool(b) => serde_json::Value::Bool(*b),
+        Document::Number(n) => match n {
+            Number::PosInt(i) => serde_json::json!(*i),
+            Number::NegInt(i) => serde_json::json!(*i),
+            Number::Float(f) => serde_json::json!(*f),
+        },
+        Document::String(s) => serde_json::Value::String(s.clone()),
+        Document::Array(arr) => {
+            serde_json::Value::Array(arr.iter().map(document_to_json).collect())
+        }
+        Document::Object(obj) => serde_json::Value::Object(
+            obj.iter()
+                .map(|(k, v)| (k.clone(), document_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converters::{AnthropicToBedrockConverter, OpenAIToBedrockConverter};
+    use crate::schemas::anthropic::MessageRequest;
+    use crate::schemas::openai::ChatCompletionRequest;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    // 1x1 transparent PNG
+    const PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+    fn long_tool_name() -> String {
+        format!("mcp__server__{}", "x".repeat(70))
+    }
+
+    #[test]
+    fn test_anthropic_request_parity() {
+        let name = long_tool_name();
+        let request: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet-20241022",
+            "max_tokens": 512,
+            "temperature": 0.5,
+            "system": "You are helpful",
+            "tools": [{"name": name, "description": "Look up", "input_schema": {"type": "object"}}],
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": PNG}}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": name, "input": {"q": 1}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "boom", "is_error": true}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let mut ir = AnthropicToBedrockConverter::new()
+            .convert_request(&request)
+            .unwrap();
+        let mut mapper = ToolNameMapper::new();
+        shorten_tool_names(&mut ir, &mut mapper);
+        let converse = to_converse_request(ir).unwrap();
+
+        assert_eq!(
+            converse.system,
+            Some(vec![SystemContentBlock::Text(
+                "You are helpful".to_string()
+            )])
+        );
+        let inference = converse.inference_config.as_ref().unwrap();
+        assert_eq!(inference.max_tokens(), Some(512));
+        assert_eq!(inference.temperature(), Some(0.5));
+
+        let roles: Vec<_> = converse.messages.iter().map(|m| m.role().clone()).collect();
+        assert_eq!(
+            roles,
+            vec![
+                ConversationRole::User,
+                ConversationRole::Assistant,
+                ConversationRole::User
+            ]
+        );
+
+        let content = converse.messages[0].content();
+        assert_eq!(content[0], ContentBlock::Text("What is this?".to_string()));
+        let ContentBlock::Image(image) = &content[1] else {
+            panic!("Expected image block");
+        };
+        assert_eq!(image.format(), &ImageFormat::Png);
+        assert_eq!(
+            image.source(),
+            Some(&ImageSource::Bytes(Blob::new(BASE64.decode(PNG).unwrap())))
+        );
+
+        // Tool spec and tool use history share the shortened name
+        let tools = converse.tool_config.as_ref().unwrap().tools();
+        let Tool::ToolSpec(spec) = &tools[0] else {
+            panic!("Expected tool spec");
+        };
+        assert!(spec.name().len() <= 64);
+        assert_eq!(mapper.restore_original_name(spec.name()), name);
+        let ContentBlock::ToolUse(tool_use) = &converse.messages[1].content()[0] else {
+            panic!("Expected tool use block");
+        };
+        assert_eq!(tool_use.name(), spec.name());
+        assert_eq!(
+            document_to_json(tool_use.input()),
+            serde_json::json!({"q": 1})
+        );
+
+        let ContentBlock::ToolResult(tool_result) = &converse.messages[2].content()[0] else {
+            panic!("Expected tool result block");
+        };
+        assert_eq!(tool_result.status(), Some(&ToolResultStatus::Error));
+        assert_eq!(
+            tool_result.content(),
+            &[ToolResultContentBlock::Text("boom".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_openai_request_parity() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4",
+            "max_tokens": 256,
+            "temperature": 1.5,
+            "stop": ["END"],
+            "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object"}}}],
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "Weather here?"},
+                    {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", PNG)}}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"}
+            ]
+        }))
+        .unwrap();
+
+        let mut ir = OpenAIToBedrockConverter::new()
+            .convert_request(&request)
+            .unwrap();
+        ir.model_id = "anthropic.claude-3-5-sonnet-20241022-v2:0".to_string();
+        let converse = to_converse_request(ir).unwrap();
+
+        assert_eq!(
+            converse.model_id,
+            "anthropic.claude-3-5-sonnet-20241022-v2:0"
+        );
+        assert_eq!(
+            converse.system,
+            Some(vec![SystemContentBlock::Text("Be brief".to_string())])
+        );
+        let inference = converse.inference_config.as_ref().unwrap();
+        assert_eq!(inference.max_tokens(), Some(256));
+        assert_eq!(inference.temperature(), Some(1.0));
+        assert_eq!(inference.stop_sequences(), &["END".to_string()]);
+
+        assert_eq!(converse.messages.len(), 3);
+        let ContentBlock::Image(image) = &converse.messages[0].content()[1] else {
+            panic!("Expected image block");
+        };
+        assert_eq!(
+            image.source(),
+            Some(&ImageSource::Bytes(Blob::new(BASE64.decode(PNG).unwrap())))
+        );
+
+        let ContentBlock::ToolUse(tool_use) = &converse.messages[1].content()[0] else {
+            panic!("Expected tool use block");
+        };
+        assert_eq!(tool_use.tool_use_id(), "call_1");
+        assert_eq!(
+            document_to_json(tool_use.input()),
+            serde_json::json!({"city": "Paris"})
+        );
+
+        assert_eq!(converse.messages[2].role(), &ConversationRole::User);
+        let ContentBlock::ToolResult(tool_result) = &converse.messages[2].content()[0] else {
+            panic!("Expected tool result block");
+        };
+        assert_eq!(tool_result.status(), Some(&ToolResultStatus::Success));
+        assert_eq!(
+            tool_result.content(),
+            &[ToolResultContentBlock::Text("Sunny".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_and_cache_points() {
+        let request: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet-20241022",
+            "max_tokens": 128,
+            "system": [{"type": "text", "text": "Long instructions", "cache_control": {"type": "ephemeral"}}],
+            "tools": [{"name": "lookup", "input_schema": {"type": "object"}}],
+            "tool_choice": {"type": "tool", "name": "lookup"},
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .unwrap();
+
+        let ir = AnthropicToBedrockConverter::new()
+            .convert_request(&request)
+            .unwrap();
+        let converse = to_converse_request(ir).unwrap();
+
+        let system = converse.system.unwrap();
+        assert_eq!(system.len(), 2);
+        assert!(matches!(system[1], SystemContentBlock::CachePoint(_)));
+
+        let tool_config = converse.tool_config.unwrap();
+        let Some(ToolChoice::Tool(choice)) = tool_config.tool_choice() else {
+            panic!("Expected specific tool choice");
+        };
+        assert_eq!(choice.name(), "lookup");
+    }
+
+    #[test]
+    fn test_tool_result_images_reach_sdk() {
+        // Screenshot-style tool results: text and an image in one result
+        let request: MessageRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet-20241022",
+            "max_tokens": 128,
+            "messages": [
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "screenshot", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [
+                        {"type": "text", "text": "Captured"},
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": PNG}}
+                    ]}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let ir = AnthropicToBedrockConverter::new()
+            .convert_request(&request)
+            .unwrap();
+        let converse = to_converse_request(ir).unwrap();
+
+        let ContentBlock::ToolResult(tool_result) = &converse.messages[1].content()[0] else {
+            panic!("Expected tool result block");
+        };
+        let content = tool_result.content();
+        assert_eq!(content.len(), 2);
+        assert_eq!(
+            content[0],
+            ToolResultContentBlock::Text("Captured".to_string())
+        );
+        let ToolResultContentBlock::Image(image) = &content[1] else {
+            panic!("Expected tool result image");
+        };
+        assert_eq!(image.format(), &ImageFormat::Jpeg);
+        assert_eq!(
+            image.source(),
+            Some(&ImageSource::Bytes(Blob::new(BASE64.decode(PNG).unwrap())))
+        );
+    }
+
+    #[test]
+    fn test_tool_result_content() {
+        let image = serde_json::json!({"image": {"format": "png", "source": {"bytes": PNG}}});
+        assert!(matches!(
+            to_sdk_tool_result_content(image).unwrap(),
+            Some(ToolResultContentBlock::Image(_))
+        ));
+
+        let json = serde_json::json!({"json": {"ok": true}});
+        assert!(matches!(
+            to_sdk_tool_result_content(json).unwrap(),
+            Some(ToolResultContentBlock::Json(_))
+        ));
+
+        assert!(to_sdk_tool_result_content(serde_json::json!({"other": 1}))
+            .unwrap()
+            .is_none());
+
+        let invalid = serde_json::json!({"image": {"format": "png", "source": {"bytes": "!!"}}});
+        assert!(to_sdk_tool_result_content(invalid).is_err());
+    }
+
+    #[test]
+    fn test_invalid_role() {
+        let mut ir = BedrockConverseRequest::new("model", vec![], 16);
+        ir.messages.push(BedrockMessage {
+            role: "system".to_string(),
+            content: vec![BedrockContentBlock::text("x")],
+        });
+        assert!(matches!(
+            to_converse_request(ir),
+            Err(SdkConversionError::InvalidRole(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_to_document() {
+        let json = serde_json::json!({"key": "value", "num": 42});
+        let doc = json_to_document(&json);
+
+        if let Document::Object(map) = doc {
+            assert!(map.contains_key("key"));
+            assert!(map.contains_key("num"));
+        } else {
+            panic!("Expected Document::Object");
+        }
+    }
+
+    #[test]
+    fn test_document_to_json() {
+        let doc = Document::Object(HashMap::from([(
+            "key".to_string(),
+            Document::String("value".to_string()),
+        )]));
+        let json = document_to_json(&doc);
+
+        assert_eq!(json["key"], "value");
+    }
+}
@@ -109,6 +109,7 @@ impl BedrockToAnthropicConverter {
             stop_reason: Some(stop_reason),
             stop_sequence: None,
             usage,
+            container: None,
         })
     }
 
@@ -164,6 +165,7 @@ impl BedrockToAnthropicConverter {
                         media_type,
                         data,
                     },
+                    title: document.title.clone(),
                     cache_control: None,
                 })
             }
@@ -225,6 +227,7 @@ impl BedrockToAnthropicConverter {
             output_tokens: bedrock_usage.output_tokens,
             cache_creation_input_tokens: None,
             cache_read_input_tokens: None,
+            service_tier: None,
         }
     }
 
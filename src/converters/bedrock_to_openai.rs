@@ -9,9 +9,10 @@ use crate::schemas::bedrock::{
 };
 use crate::schemas::openai::{
     AssistantMessage, ChatCompletionChunk, ChatCompletionResponse, ChatRole, Choice,
-    ChunkChoice, ChunkDelta, CompletionUsage, FunctionCall, ToolCall, ToolCallDelta,
+    ChunkChoice, ChunkDelta, CompletionUsage, ContentPart, FunctionCall, ToolCall, ToolCallDelta,
     FunctionCallDelta, current_timestamp, generate_completion_id,
 };
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use thiserror::Error;
 
 // ============================================================================
@@ -58,7 +59,7 @@ pub struct StreamingState {
 /// Converter for Bedrock Converse API responses to OpenAI Chat Completions API format.
 ///
 /// This converter handles the transformation of:
-/// - Content (text, tool_calls)
+/// - Content (text, tool_calls, images)
 /// - Stop reasons → finish_reason
 /// - Token usage
 /// - Streaming events → OpenAI chunks
@@ -122,7 +123,8 @@ impl BedrockToOpenAIConverter {
             .unwrap_or_else(|| original_model_id.to_string());
 
         // Convert content blocks to OpenAI format
-        let (content, tool_calls) = self.convert_content_blocks(&response.output.message.content)?;
+        let (content, tool_calls, images) =
+            self.convert_content_blocks(&response.output.message.content)?;
 
         // Convert stop reason
         let finish_reason = self.convert_stop_reason(&response.stop_reason);
@@ -138,6 +140,11 @@ impl BedrockToOpenAIConverter {
             } else {
                 Some(tool_calls)
             },
+            images: if images.is_empty() {
+                None
+            } else {
+                Some(images)
+            },
         };
 
         Ok(ChatCompletionResponse {
@@ -153,6 +160,8 @@ impl BedrockToOpenAIConverter {
             }],
             usage,
             system_fingerprint: None,
+            service_tier: None,
+            audio_transcriptions: None,
         })
     }
 
@@ -161,13 +170,14 @@ impl BedrockToOpenAIConverter {
     // ========================================================================
 
     /// Convert Bedrock content blocks to OpenAI format.
-    /// Returns (text_content, tool_calls).
+    /// Returns (text_content, tool_calls, images).
     fn convert_content_blocks(
         &self,
         blocks: &[BedrockContentBlock],
-    ) -> Result<(String, Vec<ToolCall>), OpenAIResponseConversionError> {
+    ) -> Result<(String, Vec<ToolCall>, Vec<ContentPart>), OpenAIResponseConversionError> {
         let mut text_parts = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut images = Vec::new();
 
         for block in blocks {
             match block {
@@ -186,9 +196,15 @@ impl BedrockToOpenAIConverter {
                     };
                     tool_calls.push(tool_call);
                 }
-                // Images and documents in responses are not supported by OpenAI API
-                BedrockContentBlock::Image { .. } | BedrockContentBlock::Document { .. } => {
-                    // Skip - OpenAI doesn't return images/documents in completions
+                // Generated images (e.g. code execution plots) become base64 image parts
+                BedrockContentBlock::Image { image, .. } => {
+                    images.push(ContentPart::image_base64(
+                        &format!("image/{}", image.format),
+                        &BASE64.encode(&image.source.bytes),
+                    ));
+                }
+                BedrockContentBlock::Document { .. } => {
+                    // Skip - OpenAI doesn't return documents in completions
                 }
                 BedrockContentBlock::ToolResult { .. } => {
                     // Tool results shouldn't appear in assistant responses
@@ -197,7 +213,7 @@ impl BedrockToOpenAIConverter {
         }
 
         let content = text_parts.join("");
-        Ok((content, tool_calls))
+        Ok((content, tool_calls, images))
     }
 
     // ========================================================================
@@ -471,7 +487,8 @@ impl Default for BedrockToOpenAIConverter {
 mod tests {
     use super::*;
     use crate::schemas::bedrock::{
-        BedrockOutput, BedrockOutputMessage, BedrockToolUseData,
+        BedrockImageData, BedrockImageSource, BedrockOutput, BedrockOutputMessage,
+        BedrockToolUseData,
     };
 
     #[test]
@@ -574,6 +591,55 @@ mod tests {
         assert!(tool_calls[0].function.arguments.contains("San Francisco"));
     }
 
+    #[test]
+    fn test_image_response_conversion() {
+        let converter = BedrockToOpenAIConverter::new();
+
+        let bedrock_response = BedrockConverseResponse {
+            output: BedrockOutput {
+                message: BedrockOutputMessage {
+                    role: "assistant".to_string(),
+                    content: vec![
+                        BedrockContentBlock::text("Here is the plot."),
+                        BedrockContentBlock::Image {
+                            image: BedrockImageData {
+                                format: "png".to_string(),
+                                source: BedrockImageSource {
+                                    bytes: b"\x89PNG".to_vec(),
+                                },
+                            },
+                            cache_point: None,
+                        },
+                    ],
+                },
+            },
+            stop_reason: "end_turn".to_string(),
+            usage: BedrockTokenUsage::new(10, 5),
+            metrics: None,
+        };
+
+        let result = converter
+            .convert_response(&bedrock_response, "gpt-4")
+            .unwrap();
+
+        let message = &result.choices[0].message;
+        assert_eq!(message.content, Some("Here is the plot.".to_string()));
+        let images = message.images.as_ref().unwrap();
+        assert_eq!(images.len(), 1);
+        match &images[0] {
+            ContentPart::ImageUrl { image_url } => {
+                assert_eq!(
+                    image_url.url,
+                    format!("data:image/png;base64,{}", BASE64.encode(b"\x89PNG"))
+                );
+            }
+            other => panic!("unexpected part {:?}", other),
+        }
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["choices"][0]["message"]["images"][0]["type"], "image_url");
+    }
+
     #[test]
     fn test_mixed_content_conversion() {
         let converter = BedrockToOpenAIConverter::new();
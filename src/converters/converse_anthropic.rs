@@ -0,0 +1,663 @@
+//! Converse <-> Anthropic adapter
+//!
+//! Lets [`AnthropicService`](crate::services::AnthropicService) serve the
+//! Converse contract of the [`Backend`](crate::services::Backend) trait:
+//! requests built for Bedrock are translated to Messages API bodies for
+//! api.anthropic.com, and responses and stream events come back as the SDK
+//! Converse types.
+//!
+//! Bedrock passes `additionalModelRequestFields` to Claude as extra Messages
+//! API fields, so the adapter merges them (thinking, `top_k`, Anthropic-format
+//! tools) into the body unchanged, except `anthropic_beta`, which the hosted
+//! API takes as the `anthropic-beta` header.
+
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ContentBlockDelta, ContentBlockDeltaEvent, ContentBlockStart,
+    ContentBlockStartEvent, ContentBlockStopEvent, ConversationRole, ConverseStreamMetadataEvent,
+    ConverseStreamOutput, DocumentSource, ImageSource, Message, MessageStartEvent,
+    MessageStopEvent, ReasoningContentBlock, ReasoningContentBlockDelta, ReasoningTextBlock,
+    StopReason, SystemContentBlock, TokenUsage, Tool as SdkTool, ToolChoice, ToolInputSchema,
+    ToolResultContentBlock, ToolResultStatus, ToolUseBlock, ToolUseBlockDelta, ToolUseBlockStart,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+
+use super::bedrock_sdk::{document_to_json, json_to_document, SdkConversionError};
+use crate::schemas::anthropic::StreamEvent;
+use crate::services::ConverseRequest;
+
+type SdkResult<T> = Result<T, SdkConversionError>;
+
+/// `max_tokens` for requests that leave it unset; the Messages API requires it
+pub const DEFAULT_MAX_TOKENS: i32 = 4096;
+
+fn build_error(
+    what: &'static str,
+) -> impl FnOnce(aws_smithy_types::error::operation::BuildError) -> SdkConversionError {
+    move |e| SdkConversionError::Build(what, e.to_string())
+}
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// A Messages API call: the body and the betas for the `anthropic-beta` header
+#[derive(Debug, Clone)]
+pub struct AnthropicRequest {
+    pub body: Value,
+    pub betas: Vec<String>,
+}
+
+/// Translate a Converse request into a Messages API body
+///
+/// `model` is the Anthropic model ID; `stream` sets the body's `stream` flag.
+pub fn to_anthropic_request(
+    request: &ConverseRequest,
+    model: &str,
+    stream: bool,
+) -> SdkResult<AnthropicRequest> {
+    let mut messages = Vec::with_capacity(request.messages.len());
+    for message in &request.messages {
+        let role = match message.role() {
+            ConversationRole::User => "user",
+            ConversationRole::Assistant => "assistant",
+            other => return Err(SdkConversionError::InvalidRole(other.as_str().to_string())),
+        };
+        let mut content = Vec::with_capacity(message.content().len());
+        for block in message.content() {
+            match block {
+                // A cache point marks the end of the block before it
+                ContentBlock::CachePoint(_) => mark_cached(&mut content),
+                block => content.extend(content_block(block)),
+            }
+        }
+        messages.push(json!({ "role": role, "content": content }));
+    }
+
+    let config = request.inference_config.as_ref();
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": config.and_then(|c| c.max_tokens()).unwrap_or(DEFAULT_MAX_TOKENS),
+    });
+    if stream {
+        body["stream"] = json!(true);
+    }
+    if let Some(config) = config {
+        if let Some(temperature) = config.temperature() {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = config.top_p() {
+            body["top_p"] = json!(top_p);
+        }
+        if !config.stop_sequences().is_empty() {
+            body["stop_sequences"] = json!(config.stop_sequences());
+        }
+    }
+
+    if let Some(ref system) = request.system {
+        let mut blocks = Vec::with_capacity(system.len());
+        for block in system {
+            match block {
+                SystemContentBlock::Text(text) => blocks.push(json!({ "type": "text", "text": text })),
+                SystemContentBlock::CachePoint(_) => mark_cached(&mut blocks),
+                _ => {}
+            }
+        }
+        if !blocks.is_empty() {
+            body["system"] = Value::Array(blocks);
+        }
+    }
+
+    if let Some(ref tool_config) = request.tool_config {
+        let mut tools = Vec::with_capacity(tool_config.tools().len());
+        for tool in tool_config.tools() {
+            match tool {
+                SdkTool::ToolSpec(spec) => {
+                    let input_schema = match spec.input_schema() {
+                        Some(ToolInputSchema::Json(schema)) => document_to_json(schema),
+                        _ => json!({ "type": "object" }),
+                    };
+                    let mut tool = json!({ "name": spec.name(), "input_schema": input_schema });
+                    if let Some(description) = spec.description() {
+                        tool["description"] = json!(description);
+                    }
+                    tools.push(tool);
+                }
+                SdkTool::CachePoint(_) => mark_cached(&mut tools),
+                _ => {}
+            }
+        }
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools);
+        }
+        if let Some(choice) = tool_config.tool_choice() {
+            body["tool_choice"] = match choice {
+                ToolChoice::Any(_) => json!({ "type": "any" }),
+                ToolChoice::Tool(tool) => json!({ "type": "tool", "name": tool.name() }),
+                _ => json!({ "type": "auto" }),
+            };
+        }
+    }
+
+    let mut betas = Vec::new();
+    if let Some(Value::Object(fields)) = request
+        .additional_model_request_fields
+        .as_ref()
+        .map(document_to_json)
+    {
+        for (key, value) in fields {
+            if key == "anthropic_beta" {
+                betas.extend(
+                    value
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|beta| beta.as_str().map(str::to_string)),
+                );
+            } else {
+                body[key] = value;
+            }
+        }
+    }
+
+    Ok(AnthropicRequest { body, betas })
+}
+
+/// Messages API content block for a Converse block, if it has one
+fn content_block(block: &ContentBlock) -> Option<Value> {
+    match block {
+        ContentBlock::Text(text) => Some(json!({ "type": "text", "text": text })),
+        ContentBlock::Image(image) => match image.source() {
+            Some(ImageSource::Bytes(bytes)) => Some(json!({
+                "type": "image",
+                "source": {
+                    "type": "base64",
+                    "media_type": format!("image/{}", image.format().as_str()),
+                    "data": BASE64.encode(bytes.as_ref()),
+                },
+            })),
+            _ => None,
+        },
+        ContentBlock::Document(document) => {
+            let Some(DocumentSource::Bytes(bytes)) = document.source() else {
+                return None;
+            };
+            let source = match document.format().as_str() {
+                "pdf" => json!({
+                    "type": "base64",
+                    "media_type": "application/pdf",
+                    "data": BASE64.encode(bytes.as_ref()),
+                }),
+                "txt" | "md" | "csv" | "html" => json!({
+                    "type": "text",
+                    "media_type": "text/plain",
+                    "data": String::from_utf8_lossy(bytes.as_ref()),
+                }),
+                // Office formats have no Messages API document source
+                other => {
+                    tracing::warn!(format = other, "Dropping document the Anthropic API cannot read");
+                    return None;
+                }
+            };
+            Some(json!({ "type": "document", "source": source, "title": document.name() }))
+        }
+        ContentBlock::ToolUse(tool_use) => Some(json!({
+            "type": "tool_use",
+            "id": tool_use.tool_use_id(),
+            "name": tool_use.name(),
+            "input": document_to_json(tool_use.input()),
+        })),
+        ContentBlock::ToolResult(tool_result) => {
+            let content: Vec<Value> = tool_result
+                .content()
+                .iter()
+                .filter_map(|block| match block {
+                    ToolResultContentBlock::Text(text) => Some(json!({ "type": "text", "text": text })),
+                    ToolResultContentBlock::Json(value) => Some(json!({
+                        "type": "text",
+                        "text": document_to_json(value).to_string(),
+                    })),
+                    ToolResultContentBlock::Image(image) => {
+                        content_block(&ContentBlock::Image(image.clone()))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let mut result = json!({
+                "type": "tool_result",
+                "tool_use_id": tool_result.tool_use_id(),
+                "content": content,
+            });
+            if tool_result.status() == Some(&ToolResultStatus::Error) {
+                result["is_error"] = json!(true);
+            }
+            Some(result)
+        }
+        ContentBlock::ReasoningContent(ReasoningContentBlock::ReasoningText(reasoning)) => {
+            Some(json!({
+                "type": "thinking",
+                "thinking": reasoning.text(),
+                "signature": reasoning.signature().unwrap_or_default(),
+            }))
+        }
+        ContentBlock::ReasoningContent(ReasoningContentBlock::RedactedContent(data)) => Some(json!({
+            "type": "redacted_thinking",
+            "data": BASE64.encode(data.as_ref()),
+        })),
+        _ => None,
+    }
+}
+
+/// Put an ephemeral cache breakpoint on the last block of a list
+fn mark_cached(blocks: &mut [Value]) {
+    if let Some(Value::Object(block)) = blocks.last_mut() {
+        block.insert("cache_control".to_string(), json!({ "type": "ephemeral" }));
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// Translate a Messages API response into a Converse output
+pub fn to_converse_output(response: &Value) -> SdkResult<ConverseOutput> {
+    let mut content = Vec::new();
+    for block in response["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => content.push(ContentBlock::Text(str_field(block, "text"))),
+            Some("tool_use") => {
+                let tool_use = ToolUseBlock::builder()
+                    .tool_use_id(str_field(block, "id"))
+                    .name(str_field(block, "name"))
+                    .input(json_to_document(&block["input"]))
+                    .build()
+                    .map_err(build_error("tool use"))?;
+                content.push(ContentBlock::ToolUse(tool_use));
+            }
+            Some("thinking") => {
+                let reasoning = ReasoningTextBlock::builder()
+                    .text(str_field(block, "thinking"))
+                    .set_signature(block["signature"].as_str().map(str::to_string))
+                    .build()
+                    .map_err(build_error("reasoning"))?;
+                content.push(ContentBlock::ReasoningContent(
+                    ReasoningContentBlock::ReasoningText(reasoning),
+                ));
+            }
+            Some("redacted_thinking") => {
+                let data = BASE64
+                    .decode(str_field(block, "data"))
+                    .map_err(|e| SdkConversionError::Base64DecodeError(e.to_string()))?;
+                content.push(ContentBlock::ReasoningContent(
+                    ReasoningContentBlock::RedactedContent(Blob::new(data)),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let message = Message::builder()
+        .role(ConversationRole::Assistant)
+        .set_content(Some(content))
+        .build()
+        .map_err(build_error("message"))?;
+
+    ConverseOutput::builder()
+        .output(aws_sdk_bedrockruntime::types::ConverseOutput::Message(
+            message,
+        ))
+        .stop_reason(stop_reason(response["stop_reason"].as_str()))
+        .usage(token_usage(&Usage::from_json(&response["usage"]))?)
+        .build()
+        .map_err(build_error("converse output"))
+}
+
+fn str_field(value: &Value, field: &str) -> String {
+    value[field].as_str().unwrap_or_default().to_string()
+}
+
+/// Converse stop reason for a Messages API stop reason
+fn stop_reason(stop_reason: Option<&str>) -> StopReason {
+    match stop_reason {
+        Some("max_tokens") => StopReason::MaxTokens,
+        Some("stop_sequence") => StopReason::StopSequence,
+        Some("tool_use") => StopReason::ToolUse,
+        Some("refusal") => StopReason::ContentFiltered,
+        _ => StopReason::EndTurn,
+    }
+}
+
+/// Token counts reported across a response or stream
+#[derive(Debug, Clone, Copy, Default)]
+struct Usage {
+    input_tokens: i32,
+    output_tokens: i32,
+    cache_read_input_tokens: Option<i32>,
+    cache_creation_input_tokens: Option<i32>,
+}
+
+impl Usage {
+    fn from_json(usage: &Value) -> Self {
+        let mut parsed = Self::default();
+        parsed.update(usage);
+        parsed
+    }
+
+    /// Take the counts present in a `usage` object, keeping the others
+    fn update(&mut self, usage: &Value) {
+        let count = |field: &str| usage[field].as_i64().map(|n| n as i32);
+        if let Some(n) = count("input_tokens") {
+            self.input_tokens = n;
+        }
+        if let Some(n) = count("output_tokens") {
+            self.output_tokens = n;
+        }
+        if let Some(n) = count("cache_read_input_tokens") {
+            self.cache_read_input_tokens = Some(n);
+        }
+        if let Some(n) = count("cache_creation_input_tokens") {
+            self.cache_creation_input_tokens = Some(n);
+        }
+    }
+}
+
+fn token_usage(usage: &Usage) -> SdkResult<TokenUsage> {
+    TokenUsage::builder()
+        .input_tokens(usage.input_tokens)
+        .output_tokens(usage.output_tokens)
+        .total_tokens(usage.input_tokens + usage.output_tokens)
+        .set_cache_read_input_tokens(usage.cache_read_input_tokens)
+        .set_cache_write_input_tokens(usage.cache_creation_input_tokens)
+        .build()
+        .map_err(build_error("token usage"))
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Turns Messages API stream events into ConverseStream events
+///
+/// The two streams line up block for block, so indexes carry over. The stop
+/// reason and output tokens arrive in `message_delta`; they are held until
+/// `message_stop`, which becomes Converse's `MessageStop` and `Metadata`.
+#[derive(Debug, Default)]
+pub struct AnthropicConverseEvents {
+    stop_reason: Option<String>,
+    usage: Usage,
+}
+
+impl AnthropicConverseEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for one stream event
+    ///
+    /// `error` events are the caller's to report; they produce nothing here.
+    pub fn event_events(&mut self, event: &StreamEvent) -> SdkResult<Vec<ConverseStreamOutput>> {
+        let mut events = Vec::new();
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.usage.update(&message["usage"]);
+                events.push(ConverseStreamOutput::MessageStart(
+                    MessageStartEvent::builder()
+                        .role(ConversationRole::Assistant)
+                        .build()
+                        .map_err(build_error("message start"))?,
+                ));
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                let start = match content_block["type"].as_str() {
+                    Some("tool_use") => Some(ContentBlockStart::ToolUse(
+                        ToolUseBlockStart::builder()
+                            .tool_use_id(str_field(content_block, "id"))
+                            .name(str_field(content_block, "name"))
+                            .build()
+                            .map_err(build_error("tool use start"))?,
+                    )),
+                    _ => None,
+                };
+                events.push(block_start(*index, start)?);
+                // Text blocks may open with text already in them
+                if let Some(text) = content_block["text"].as_str().filter(|t| !t.is_empty()) {
+                    events.push(block_delta(*index, ContentBlockDelta::Text(text.to_string()))?);
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let delta = match delta["type"].as_str() {
+                    Some("text_delta") => ContentBlockDelta::Text(str_field(delta, "text")),
+                    Some("input_json_delta") => ContentBlockDelta::ToolUse(
+                        ToolUseBlockDelta::builder()
+                            .input(str_field(delta, "partial_json"))
+                            .build()
+                            .map_err(build_error("tool use delta"))?,
+                    ),
+                    Some("thinking_delta") => ContentBlockDelta::ReasoningContent(
+                        ReasoningContentBlockDelta::Text(str_field(delta, "thinking")),
+                    ),
+                    Some("signature_delta") => ContentBlockDelta::ReasoningContent(
+                        ReasoningContentBlockDelta::Signature(str_field(delta, "signature")),
+                    ),
+                    _ => return Ok(events),
+                };
+                events.push(block_delta(*index, delta)?);
+            }
+            StreamEvent::ContentBlockStop { index } => events.push(block_stop(*index)?),
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(reason) = delta["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(usage) = usage {
+                    self.usage.update(usage);
+                }
+            }
+            StreamEvent::MessageStop => {
+                events.push(ConverseStreamOutput::MessageStop(
+                    MessageStopEvent::builder()
+                        .stop_reason(stop_reason(self.stop_reason.as_deref()))
+                        .build()
+                        .map_err(build_error("message stop"))?,
+                ));
+                events.push(ConverseStreamOutput::Metadata(
+                    ConverseStreamMetadataEvent::builder()
+                        .usage(token_usage(&self.usage)?)
+                        .build(),
+                ));
+            }
+            StreamEvent::Ping | StreamEvent::Error { .. } => {}
+        }
+        Ok(events)
+    }
+}
+
+fn block_start(index: i32, start: Option<ContentBlockStart>) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStartEvent::builder()
+        .content_block_index(index)
+        .set_start(start)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStart)
+        .map_err(build_error("content block start"))
+}
+
+fn block_delta(index: i32, delta: ContentBlockDelta) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockDeltaEvent::builder()
+        .content_block_index(index)
+        .delta(delta)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockDelta)
+        .map_err(build_error("content block delta"))
+}
+
+fn block_stop(index: i32) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStopEvent::builder()
+        .content_block_index(index)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStop)
+        .map_err(build_error("content block stop"))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{
+        CachePointBlock, CachePointType, InferenceConfiguration, ToolConfiguration,
+        ToolResultBlock, ToolSpecification,
+    };
+
+    fn message(role: ConversationRole, content: Vec<ContentBlock>) -> Message {
+        Message::builder()
+            .role(role)
+            .set_content(Some(content))
+            .build()
+            .unwrap()
+    }
+
+    fn cache_point() -> CachePointBlock {
+        CachePointBlock::builder()
+            .r#type(CachePointType::Default)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_request_maps_tools_cache_points_and_betas() {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("toolu_1")
+            .name("get_weather")
+            .input(json_to_document(&json!({"city": "Paris"})))
+            .build()
+            .unwrap();
+        let tool_result = ToolResultBlock::builder()
+            .tool_use_id("toolu_1")
+            .content(ToolResultContentBlock::Text("sunny".to_string()))
+            .status(ToolResultStatus::Error)
+            .build()
+            .unwrap();
+        let tool = ToolSpecification::builder()
+            .name("get_weather")
+            .input_schema(ToolInputSchema::Json(json_to_document(&json!({"type": "object"}))))
+            .build()
+            .unwrap();
+
+        let mut request = ConverseRequest::new("claude-sonnet-4-5");
+        request.messages = vec![
+            message(
+                ConversationRole::User,
+                vec![
+                    ContentBlock::Text("Weather?".to_string()),
+                    ContentBlock::CachePoint(cache_point()),
+                ],
+            ),
+            message(ConversationRole::Assistant, vec![ContentBlock::ToolUse(tool_use)]),
+            message(ConversationRole::User, vec![ContentBlock::ToolResult(tool_result)]),
+        ];
+        request.system = Some(vec![SystemContentBlock::Text("Be brief".to_string())]);
+        request.inference_config = Some(InferenceConfiguration::builder().temperature(0.5).build());
+        request.tool_config = Some(
+            ToolConfiguration::builder()
+                .tools(SdkTool::ToolSpec(tool))
+                .tools(SdkTool::CachePoint(cache_point()))
+                .build()
+                .unwrap(),
+        );
+        request.additional_model_request_fields = Some(json_to_document(&json!({
+            "anthropic_beta": ["interleaved-thinking-2025-05-14"],
+            "thinking": {"type": "enabled", "budget_tokens": 2048},
+        })));
+
+        let converted = to_anthropic_request(&request, "claude-sonnet-4-5", true).unwrap();
+        let body = converted.body;
+
+        assert_eq!(converted.betas, vec!["interleaved-thinking-2025-05-14"]);
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["system"][0]["text"], "Be brief");
+        assert_eq!(body["thinking"]["budget_tokens"], 2048);
+        assert!(body.get("anthropic_beta").is_none());
+        assert_eq!(body["messages"][0]["content"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(body["messages"][0]["content"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][1]["content"][0]["input"]["city"], "Paris");
+        assert_eq!(body["messages"][2]["content"][0]["is_error"], true);
+        assert_eq!(body["tools"][0]["cache_control"]["type"], "ephemeral");
+    }
+
+    #[test]
+    fn test_response_with_tool_use() {
+        let response = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "Look it up", "signature": "sig"},
+                {"type": "text", "text": "Checking"},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 12, "output_tokens": 7, "cache_read_input_tokens": 4}
+        });
+
+        let output = to_converse_output(&response).unwrap();
+        assert_eq!(output.stop_reason(), &StopReason::ToolUse);
+        let usage = output.usage().unwrap();
+        assert_eq!((usage.input_tokens(), usage.output_tokens()), (12, 7));
+        assert_eq!(usage.cache_read_input_tokens(), Some(4));
+
+        let message = output.output().unwrap().as_message().unwrap();
+        assert_eq!(message.content().len(), 3);
+        let tool_use = message.content()[2].as_tool_use().unwrap();
+        assert_eq!(tool_use.tool_use_id(), "toolu_1");
+        assert_eq!(document_to_json(tool_use.input())["city"], "Paris");
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let recording = [
+            r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"lookup","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"q\":1}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut adapter = AnthropicConverseEvents::new();
+        let events: Vec<ConverseStreamOutput> = recording
+            .iter()
+            .flat_map(|line| {
+                let event: StreamEvent = serde_json::from_str(line).unwrap();
+                adapter.event_events(&event).unwrap()
+            })
+            .collect();
+
+        assert_eq!(events.len(), 9);
+        let Some(ConverseStreamOutput::ContentBlockStart(start)) = events.get(4) else {
+            panic!("expected tool use start");
+        };
+        assert_eq!(start.start().unwrap().as_tool_use().unwrap().name(), "lookup");
+        let Some(ConverseStreamOutput::MessageStop(stop)) = events.get(7) else {
+            panic!("expected message stop");
+        };
+        assert_eq!(stop.stop_reason(), &StopReason::ToolUse);
+        let Some(ConverseStreamOutput::Metadata(metadata)) = events.get(8) else {
+            panic!("expected metadata");
+        };
+        let usage = metadata.usage().unwrap();
+        assert_eq!((usage.input_tokens(), usage.output_tokens()), (10, 9));
+    }
+}
@@ -0,0 +1,1242 @@
+//! Converse <-> Gemini adapter
+//!
+//! Lets [`GeminiService`](crate::services::GeminiService) serve the Converse
+//! contract of the [`Backend`](crate::services::Backend) trait: requests built
+//! for Bedrock are translated to `generateContent` bodies, and Gemini
+//! responses and stream chunks come back as the SDK Converse types, so a
+//! handler written against Bedrock works unchanged.
+//!
+//! The inbound direction serves Gemini clients: [`from_gemini_request`] turns
+//! a `generateContent` body into a Converse request for any backend, and
+//! [`to_gemini_response`] and [`ConverseGeminiChunks`] translate the result
+//! back.
+
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::types::{
+    AnyToolChoice, AutoToolChoice, ContentBlock, ContentBlockDelta, ContentBlockDeltaEvent,
+    ContentBlockStart, ContentBlockStartEvent, ContentBlockStopEvent, ConversationRole,
+    ConverseStreamMetadataEvent, ConverseStreamOutput, DocumentBlock, DocumentFormat,
+    DocumentSource, ImageBlock, ImageFormat, ImageSource, InferenceConfiguration, Message,
+    MessageStartEvent, MessageStopEvent, SpecificToolChoice, StopReason, SystemContentBlock,
+    TokenUsage, Tool as SdkTool, ToolChoice, ToolConfiguration, ToolInputSchema, ToolResultBlock,
+    ToolResultContentBlock, ToolResultStatus, ToolSpecification, ToolUseBlock, ToolUseBlockDelta,
+    ToolUseBlockStart,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use super::bedrock_sdk::{document_to_json, json_to_document, SdkConversionError};
+use crate::schemas::gemini::{
+    finish_reason, Candidate, FunctionCall, FunctionCallingConfig, FunctionDeclaration,
+    FunctionResponse, GeminiContent, GeminiRequest, GeminiResponse, GenerationConfig, InlineData,
+    Part, StreamChunk, Tool, ToolConfig, UsageMetadata,
+};
+use crate::services::ConverseRequest;
+
+type SdkResult<T> = Result<T, SdkConversionError>;
+
+fn build_error(
+    what: &'static str,
+) -> impl FnOnce(aws_smithy_types::error::operation::BuildError) -> SdkConversionError {
+    move |e| SdkConversionError::Build(what, e.to_string())
+}
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// Translate a Converse request into a Gemini `generateContent` body
+pub fn to_gemini_request(request: &ConverseRequest) -> SdkResult<GeminiRequest> {
+    // Gemini names function responses by function, Converse by tool use id
+    let mut tool_names: HashMap<&str, &str> = HashMap::new();
+    let mut contents = Vec::with_capacity(request.messages.len());
+
+    for message in &request.messages {
+        let role = match message.role() {
+            ConversationRole::User => "user",
+            ConversationRole::Assistant => "model",
+            other => return Err(SdkConversionError::InvalidRole(other.as_str().to_string())),
+        };
+
+        let mut parts = Vec::with_capacity(message.content().len());
+        for block in message.content() {
+            match block {
+                ContentBlock::Text(text) => parts.push(Part::text(text)),
+                ContentBlock::Image(image) => {
+                    if let Some(ImageSource::Bytes(bytes)) = image.source() {
+                        parts.push(Part::inline_data(
+                            format!("image/{}", image.format().as_str()),
+                            BASE64.encode(bytes.as_ref()),
+                        ));
+                    }
+                }
+                ContentBlock::Document(document) => {
+                    if let Some(DocumentSource::Bytes(bytes)) = document.source() {
+                        parts.push(Part::inline_data(
+                            document_mime_type(document.format().as_str()),
+                            BASE64.encode(bytes.as_ref()),
+                        ));
+                    }
+                }
+                ContentBlock::ToolUse(tool_use) => {
+                    tool_names.insert(tool_use.tool_use_id(), tool_use.name());
+                    parts.push(Part {
+                        text: None,
+                        inline_data: None,
+                        function_call: Some(FunctionCall {
+                            name: tool_use.name().to_string(),
+                            args: document_to_json(tool_use.input()),
+                        }),
+                        function_response: None,
+                    });
+                }
+                ContentBlock::ToolResult(tool_result) => {
+                    let name = tool_names
+                        .get(tool_result.tool_use_id())
+                        .copied()
+                        .unwrap_or(tool_result.tool_use_id());
+                    let result = tool_result_value(tool_result.content());
+                    let response = match tool_result.status() {
+                        Some(ToolResultStatus::Error) => serde_json::json!({ "error": result }),
+                        _ => serde_json::json!({ "result": result }),
+                    };
+                    parts.push(Part {
+                        text: None,
+                        inline_data: None,
+                        function_call: None,
+                        function_response: Some(FunctionResponse {
+                            name: name.to_string(),
+                            response,
+                        }),
+                    });
+                }
+                // Cache points and reasoning have no Gemini equivalent
+                _ => {}
+            }
+        }
+
+        contents.push(GeminiContent {
+            role: Some(role.to_string()),
+            parts,
+        });
+    }
+
+    let system_instruction = request.system.as_ref().and_then(|system| {
+        let text = system
+            .iter()
+            .filter_map(|block| block.as_text().ok().cloned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!text.is_empty()).then(|| GeminiContent::system(text))
+    });
+
+    let generation_config = request
+        .inference_config
+        .as_ref()
+        .map(|config| GenerationConfig {
+            temperature: config.temperature(),
+            top_p: config.top_p(),
+            top_k: None,
+            max_output_tokens: config.max_tokens(),
+            stop_sequences: (!config.stop_sequences().is_empty())
+                .then(|| config.stop_sequences().to_vec()),
+            candidate_count: None,
+            ..Default::default()
+        });
+
+    let (tools, tool_config) = match request.tool_config.as_ref() {
+        Some(tool_config) => {
+            let function_declarations = tool_config
+                .tools()
+                .iter()
+                .filter_map(|tool| match tool {
+                    SdkTool::ToolSpec(spec) => Some(FunctionDeclaration {
+                        name: spec.name().to_string(),
+                        description: spec.description().unwrap_or_default().to_string(),
+                        parameters: match spec.input_schema() {
+                            Some(ToolInputSchema::Json(schema)) => {
+                                Some(gemini_schema(document_to_json(schema)))
+                            }
+                            _ => None,
+                        },
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let tool_config = tool_config.tool_choice().map(|choice| {
+                let (mode, allowed) = match choice {
+                    ToolChoice::Any(_) => ("ANY", None),
+                    ToolChoice::Tool(tool) => ("ANY", Some(vec![tool.name().to_string()])),
+                    _ => ("AUTO", None),
+                };
+                ToolConfig {
+                    function_calling_config: FunctionCallingConfig {
+                        mode: mode.to_string(),
+                        allowed_function_names: allowed,
+                    },
+                }
+            });
+            let tools = (!function_declarations.is_empty()).then(|| {
+                vec![Tool {
+                    function_declarations,
+                }]
+            });
+            (tools, tool_config)
+        }
+        None => (None, None),
+    };
+
+    Ok(GeminiRequest {
+        contents,
+        system_instruction,
+        generation_config,
+        safety_settings: None,
+        tools,
+        tool_config,
+    })
+}
+
+/// Tool result content as the value Gemini receives in `functionResponse`
+fn tool_result_value(content: &[ToolResultContentBlock]) -> serde_json::Value {
+    if let [ToolResultContentBlock::Json(json)] = content {
+        return document_to_json(json);
+    }
+    let text = content
+        .iter()
+        .filter_map(|block| match block {
+            ToolResultContentBlock::Text(text) => Some(text.clone()),
+            ToolResultContentBlock::Json(json) => Some(document_to_json(json).to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::Value::String(text)
+}
+
+/// Drop JSON Schema keywords Gemini's OpenAPI subset rejects
+fn gemini_schema(mut schema: serde_json::Value) -> serde_json::Value {
+    match &mut schema {
+        serde_json::Value::Object(map) => {
+            map.remove("$schema");
+            map.remove("additionalProperties");
+            for value in map.values_mut() {
+                *value = gemini_schema(value.take());
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for value in items.iter_mut() {
+                *value = gemini_schema(value.take());
+            }
+        }
+        _ => {}
+    }
+    schema
+}
+
+fn document_mime_type(format: &str) -> &'static str {
+    match format {
+        "txt" => "text/plain",
+        "html" => "text/html",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        _ => "application/pdf",
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// Translate a Gemini response into a Converse output
+pub fn to_converse_output(response: &GeminiResponse) -> SdkResult<ConverseOutput> {
+    let mut content = Vec::new();
+    let mut finish_reason = None;
+
+    if let Some(candidate) = response.candidates.first() {
+        for part in &candidate.content.parts {
+            if let Some(ref text) = part.text {
+                content.push(ContentBlock::Text(text.clone()));
+            }
+            if let Some(ref function_call) = part.function_call {
+                let tool_use = ToolUseBlock::builder()
+                    .tool_use_id(tool_use_id())
+                    .name(&function_call.name)
+                    .input(json_to_document(&function_call.args))
+                    .build()
+                    .map_err(build_error("tool use"))?;
+                content.push(ContentBlock::ToolUse(tool_use));
+            }
+        }
+        finish_reason = candidate.finish_reason.as_deref();
+    }
+
+    let used_tools = content
+        .iter()
+        .any(|block| matches!(block, ContentBlock::ToolUse(_)));
+    let message = Message::builder()
+        .role(ConversationRole::Assistant)
+        .set_content(Some(content))
+        .build()
+        .map_err(build_error("message"))?;
+
+    ConverseOutput::builder()
+        .output(aws_sdk_bedrockruntime::types::ConverseOutput::Message(
+            message,
+        ))
+        .stop_reason(stop_reason(finish_reason, used_tools))
+        .usage(token_usage(response.usage_metadata.as_ref())?)
+        .build()
+        .map_err(build_error("converse output"))
+}
+
+/// Converse stop reason for a Gemini finish reason
+///
+/// Gemini reports STOP after function calls; Converse callers expect tool_use.
+fn stop_reason(finish_reason: Option<&str>, used_tools: bool) -> StopReason {
+    match finish_reason {
+        _ if used_tools => StopReason::ToolUse,
+        Some("MAX_TOKENS") => StopReason::MaxTokens,
+        Some("SAFETY") | Some("RECITATION") | Some("PROHIBITED_CONTENT") => {
+            StopReason::ContentFiltered
+        }
+        _ => StopReason::EndTurn,
+    }
+}
+
+fn token_usage(usage: Option<&UsageMetadata>) -> SdkResult<TokenUsage> {
+    let usage = usage.cloned().unwrap_or_default();
+    TokenUsage::builder()
+        .input_tokens(usage.prompt_token_count)
+        .output_tokens(usage.candidates_token_count)
+        .total_tokens(usage.prompt_token_count + usage.candidates_token_count)
+        .build()
+        .map_err(build_error("token usage"))
+}
+
+fn tool_use_id() -> String {
+    format!("toolu_{}", Uuid::new_v4().simple())
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Turns Gemini stream chunks into ConverseStream events
+///
+/// Gemini streams text as repeated text parts and each function call as one
+/// complete part. Text accumulates into one content block until a function
+/// call arrives; every function call is its own start/delta/stop block. The
+/// closing `MessageStop` and `Metadata` events come from
+/// [`finish_events`](Self::finish_events) once the chunk stream ends, since
+/// Gemini repeats usage on every chunk.
+#[derive(Debug, Default)]
+pub struct GeminiConverseEvents {
+    started: bool,
+    next_index: i32,
+    open_text: Option<i32>,
+    used_tools: bool,
+    finish_reason: Option<String>,
+    usage: Option<UsageMetadata>,
+}
+
+impl GeminiConverseEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for one stream chunk
+    pub fn chunk_events(&mut self, chunk: &StreamChunk) -> SdkResult<Vec<ConverseStreamOutput>> {
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            events.push(ConverseStreamOutput::MessageStart(
+                MessageStartEvent::builder()
+                    .role(ConversationRole::Assistant)
+                    .build()
+                    .map_err(build_error("message start"))?,
+            ));
+        }
+
+        if let Some(usage) = chunk.usage_metadata.as_ref() {
+            self.usage = Some(usage.clone());
+        }
+
+        let Some(candidate) = chunk.candidates.first() else {
+            return Ok(events);
+        };
+
+        for part in &candidate.content.parts {
+            if let Some(ref text) = part.text {
+                if text.is_empty() {
+                    continue;
+                }
+                let index = match self.open_text {
+                    Some(index) => index,
+                    None => {
+                        let index = self.open_block();
+                        self.open_text = Some(index);
+                        events.push(block_start(index, None)?);
+                        index
+                    }
+                };
+                events.push(block_delta(index, ContentBlockDelta::Text(text.clone()))?);
+            }
+
+            if let Some(ref function_call) = part.function_call {
+                self.close_text(&mut events)?;
+                self.used_tools = true;
+                let index = self.open_block();
+                let start = ToolUseBlockStart::builder()
+                    .tool_use_id(tool_use_id())
+                    .name(&function_call.name)
+                    .build()
+                    .map_err(build_error("tool use start"))?;
+                events.push(block_start(index, Some(ContentBlockStart::ToolUse(start)))?);
+                let delta = ToolUseBlockDelta::builder()
+                    .input(function_call.args.to_string())
+                    .build()
+                    .map_err(build_error("tool use delta"))?;
+                events.push(block_delta(index, ContentBlockDelta::ToolUse(delta))?);
+                events.push(block_stop(index)?);
+            }
+        }
+
+        if let Some(ref reason) = candidate.finish_reason {
+            self.finish_reason = Some(reason.clone());
+        }
+
+        Ok(events)
+    }
+
+    /// Closing events once Gemini ends the stream
+    pub fn finish_events(&mut self) -> SdkResult<Vec<ConverseStreamOutput>> {
+        let mut events = Vec::new();
+        self.close_text(&mut events)?;
+        events.push(ConverseStreamOutput::MessageStop(
+            MessageStopEvent::builder()
+                .stop_reason(stop_reason(self.finish_reason.as_deref(), self.used_tools))
+                .build()
+                .map_err(build_error("message stop"))?,
+        ));
+        events.push(ConverseStreamOutput::Metadata(
+            ConverseStreamMetadataEvent::builder()
+                .usage(token_usage(self.usage.as_ref())?)
+                .build(),
+        ));
+        Ok(events)
+    }
+
+    fn open_block(&mut self) -> i32 {
+        let index = self.next_index;
+        self.next_index += 1;
+        index
+    }
+
+    fn close_text(&mut self, events: &mut Vec<ConverseStreamOutput>) -> SdkResult<()> {
+        if let Some(index) = self.open_text.take() {
+            events.push(block_stop(index)?);
+        }
+        Ok(())
+    }
+}
+
+fn block_start(index: i32, start: Option<ContentBlockStart>) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStartEvent::builder()
+        .content_block_index(index)
+        .set_start(start)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStart)
+        .map_err(build_error("content block start"))
+}
+
+fn block_delta(index: i32, delta: ContentBlockDelta) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockDeltaEvent::builder()
+        .content_block_index(index)
+        .delta(delta)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockDelta)
+        .map_err(build_error("content block delta"))
+}
+
+fn block_stop(index: i32) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStopEvent::builder()
+        .content_block_index(index)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStop)
+        .map_err(build_error("content block stop"))
+}
+
+// ============================================================================
+// Inbound Request
+// ============================================================================
+
+/// Translate a Gemini `generateContent` body into a Converse request
+///
+/// Gemini matches function responses to calls by name, Converse by id, so
+/// each call gets a fresh id and the next response with that name claims it.
+/// Consecutive contents with the same role are merged, since Converse
+/// requires roles to alternate.
+pub fn from_gemini_request(model_id: &str, request: &GeminiRequest) -> SdkResult<ConverseRequest> {
+    let mut pending_calls: HashMap<&str, VecDeque<String>> = HashMap::new();
+    let mut turns: Vec<(ConversationRole, Vec<ContentBlock>)> = Vec::new();
+    let mut document_count = 0;
+
+    for content in &request.contents {
+        // Older clients send function responses with the "function" role
+        let role = match content.role.as_deref() {
+            None | Some("user") | Some("function") => ConversationRole::User,
+            Some("model") => ConversationRole::Assistant,
+            Some(other) => return Err(SdkConversionError::InvalidRole(other.to_string())),
+        };
+
+        let mut blocks = Vec::with_capacity(content.parts.len());
+        for part in &content.parts {
+            if let Some(ref text) = part.text {
+                if !text.is_empty() {
+                    blocks.push(ContentBlock::Text(text.clone()));
+                }
+            }
+            if let Some(ref data) = part.inline_data {
+                blocks.push(inline_data_block(data, &mut document_count)?);
+            }
+            if let Some(ref call) = part.function_call {
+                let id = tool_use_id();
+                pending_calls
+                    .entry(call.name.as_str())
+                    .or_default()
+                    .push_back(id.clone());
+                let tool_use = ToolUseBlock::builder()
+                    .tool_use_id(id)
+                    .name(&call.name)
+                    .input(json_to_document(&call.args))
+                    .build()
+                    .map_err(build_error("tool use"))?;
+                blocks.push(ContentBlock::ToolUse(tool_use));
+            }
+            if let Some(ref response) = part.function_response {
+                let id = pending_calls
+                    .get_mut(response.name.as_str())
+                    .and_then(VecDeque::pop_front)
+                    .unwrap_or_else(tool_use_id);
+                blocks.push(ContentBlock::ToolResult(tool_result(&id, &response.response)?));
+            }
+        }
+        if blocks.is_empty() {
+            continue;
+        }
+
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let messages = turns
+        .into_iter()
+        .map(|(role, blocks)| {
+            Message::builder()
+                .role(role)
+                .set_content(Some(blocks))
+                .build()
+                .map_err(build_error("message"))
+        })
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    let mut converse = ConverseRequest::new(model_id).with_messages(messages);
+
+    let system = request
+        .system_instruction
+        .iter()
+        .flat_map(|instruction| &instruction.parts)
+        .filter_map(|part| part.text.clone())
+        .filter(|text| !text.is_empty())
+        .map(SystemContentBlock::Text)
+        .collect::<Vec<_>>();
+    if !system.is_empty() {
+        converse = converse.with_system(system);
+    }
+
+    if let Some(ref config) = request.generation_config {
+        converse = converse.with_inference_config(
+            InferenceConfiguration::builder()
+                .set_max_tokens(config.max_output_tokens)
+                .set_temperature(config.temperature)
+                .set_top_p(config.top_p)
+                .set_stop_sequences(config.stop_sequences.clone())
+                .build(),
+        );
+    }
+
+    if let Some(tool_config) = converse_tool_config(request)? {
+        converse = converse.with_tool_config(tool_config);
+    }
+
+    Ok(converse)
+}
+
+/// Image or document block for an inline data part
+fn inline_data_block(data: &InlineData, document_count: &mut usize) -> SdkResult<ContentBlock> {
+    let bytes = BASE64
+        .decode(&data.data)
+        .map_err(|e| SdkConversionError::Base64DecodeError(e.to_string()))?;
+
+    if let Some(subtype) = data.mime_type.strip_prefix("image/") {
+        let format = match subtype {
+            "jpeg" | "jpg" => ImageFormat::Jpeg,
+            "gif" => ImageFormat::Gif,
+            "webp" => ImageFormat::Webp,
+            _ => ImageFormat::Png,
+        };
+        let image = ImageBlock::builder()
+            .format(format)
+            .source(ImageSource::Bytes(Blob::new(bytes)))
+            .build()
+            .map_err(build_error("image"))?;
+        return Ok(ContentBlock::Image(image));
+    }
+
+    let format = match data.mime_type.as_str() {
+        "text/plain" => DocumentFormat::Txt,
+        "text/html" => DocumentFormat::Html,
+        "text/csv" => DocumentFormat::Csv,
+        "text/markdown" => DocumentFormat::Md,
+        _ => DocumentFormat::Pdf,
+    };
+    // Converse requires a unique name per document
+    *document_count += 1;
+    let document = DocumentBlock::builder()
+        .format(format)
+        .name(format!("document-{}", document_count))
+        .source(DocumentSource::Bytes(Blob::new(bytes)))
+        .build()
+        .map_err(build_error("document"))?;
+    Ok(ContentBlock::Document(document))
+}
+
+/// Tool result for a `functionResponse` payload
+///
+/// `{"error": ...}` payloads, the shape [`to_gemini_request`] produces for
+/// failed tools, come back as error results.
+fn tool_result(tool_use_id: &str, response: &serde_json::Value) -> SdkResult<ToolResultBlock> {
+    let (value, status) = match response.get("error") {
+        Some(error) if response.as_object().is_some_and(|o| o.len() == 1) => {
+            (error, ToolResultStatus::Error)
+        }
+        _ => (response, ToolResultStatus::Success),
+    };
+    let content = match value {
+        serde_json::Value::String(text) => ToolResultContentBlock::Text(text.clone()),
+        serde_json::Value::Object(_) => ToolResultContentBlock::Json(json_to_document(value)),
+        // Converse only takes objects as JSON content
+        other => ToolResultContentBlock::Json(json_to_document(
+            &serde_json::json!({ "result": other }),
+        )),
+    };
+    ToolResultBlock::builder()
+        .tool_use_id(tool_use_id)
+        .content(content)
+        .status(status)
+        .build()
+        .map_err(build_error("tool result"))
+}
+
+/// Converse tool configuration from Gemini tools and `toolConfig`
+///
+/// Converse has no equivalent of mode `NONE`; the tools are kept so history
+/// with function calls stays valid, and the model chooses freely.
+fn converse_tool_config(request: &GeminiRequest) -> SdkResult<Option<ToolConfiguration>> {
+    let mut tools = Vec::new();
+    for declaration in request.tools.iter().flatten().flat_map(|t| &t.function_declarations) {
+        let schema = declaration
+            .parameters
+            .clone()
+            .map(json_schema)
+            .unwrap_or_else(|| serde_json::json!({"type": "object", "properties": {}}));
+        let spec = ToolSpecification::builder()
+            .name(&declaration.name)
+            .set_description(
+                (!declaration.description.is_empty()).then(|| declaration.description.clone()),
+            )
+            .input_schema(ToolInputSchema::Json(json_to_document(&schema)))
+            .build()
+            .map_err(build_error("tool spec"))?;
+        tools.push(SdkTool::ToolSpec(spec));
+    }
+    if tools.is_empty() {
+        return Ok(None);
+    }
+
+    let tool_choice = match request.tool_config.as_ref().map(|c| &c.function_calling_config) {
+        Some(config) if config.mode.eq_ignore_ascii_case("ANY") => {
+            match config.allowed_function_names.as_deref() {
+                Some([name]) => Some(ToolChoice::Tool(
+                    SpecificToolChoice::builder()
+                        .name(name)
+                        .build()
+                        .map_err(build_error("tool choice"))?,
+                )),
+                _ => Some(ToolChoice::Any(AnyToolChoice::builder().build())),
+            }
+        }
+        Some(_) => Some(ToolChoice::Auto(AutoToolChoice::builder().build())),
+        None => None,
+    };
+
+    ToolConfiguration::builder()
+        .set_tools(Some(tools))
+        .set_tool_choice(tool_choice)
+        .build()
+        .map(Some)
+        .map_err(build_error("tool config"))
+}
+
+/// Lowercase the OpenAPI type names (`OBJECT`, `STRING`) Gemini accepts
+fn json_schema(mut schema: serde_json::Value) -> serde_json::Value {
+    match &mut schema {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    serde_json::Value::String(name) if key == "type" => {
+                        *name = name.to_ascii_lowercase();
+                    }
+                    _ => *value = json_schema(value.take()),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for value in items.iter_mut() {
+                *value = json_schema(value.take());
+            }
+        }
+        _ => {}
+    }
+    schema
+}
+
+// ============================================================================
+// Inbound Response
+// ============================================================================
+
+/// Translate a Converse output into a Gemini `generateContent` response
+pub fn to_gemini_response(output: &ConverseOutput, model: &str) -> GeminiResponse {
+    let mut parts = Vec::new();
+    if let Some(aws_sdk_bedrockruntime::types::ConverseOutput::Message(message)) = output.output() {
+        for block in message.content() {
+            match block {
+                ContentBlock::Text(text) => parts.push(Part::text(text)),
+                ContentBlock::ToolUse(tool_use) => parts.push(function_call_part(
+                    tool_use.name(),
+                    document_to_json(tool_use.input()),
+                )),
+                // Reasoning and citations have no Gemini equivalent
+                _ => {}
+            }
+        }
+    }
+
+    GeminiResponse {
+        candidates: vec![model_candidate(parts, Some(output.stop_reason()))],
+        usage_metadata: output.usage().map(usage_metadata),
+        model_version: Some(model.to_string()),
+    }
+}
+
+/// Gemini finish reason for a Converse stop reason
+fn gemini_finish_reason(stop_reason: &StopReason) -> &'static str {
+    match stop_reason {
+        StopReason::EndTurn | StopReason::StopSequence | StopReason::ToolUse => finish_reason::STOP,
+        StopReason::MaxTokens => finish_reason::MAX_TOKENS,
+        StopReason::ContentFiltered | StopReason::GuardrailIntervened => finish_reason::SAFETY,
+        _ => finish_reason::OTHER,
+    }
+}
+
+fn model_candidate(parts: Vec<Part>, stop_reason: Option<&StopReason>) -> Candidate {
+    Candidate {
+        content: GeminiContent {
+            role: Some("model".to_string()),
+            parts,
+        },
+        finish_reason: stop_reason.map(|reason| gemini_finish_reason(reason).to_string()),
+        safety_ratings: None,
+        citation_metadata: None,
+        index: Some(0),
+        logprobs_result: None,
+    }
+}
+
+fn function_call_part(name: &str, args: serde_json::Value) -> Part {
+    Part {
+        text: None,
+        inline_data: None,
+        function_call: Some(FunctionCall {
+            name: name.to_string(),
+            args,
+        }),
+        function_response: None,
+    }
+}
+
+fn usage_metadata(usage: &TokenUsage) -> UsageMetadata {
+    UsageMetadata {
+        prompt_token_count: usage.input_tokens(),
+        candidates_token_count: usage.output_tokens(),
+        total_token_count: usage.total_tokens(),
+    }
+}
+
+/// Turns ConverseStream events into Gemini stream chunks
+///
+/// Text deltas become one chunk each. Tool input arrives in pieces, so a
+/// function call is sent as one complete part when its block stops. The
+/// finish reason and usage go out in a last chunk once `Metadata` arrives
+/// (see [`finish_chunk`](Self::finish_chunk) for streams that end early).
+#[derive(Debug, Default)]
+pub struct ConverseGeminiChunks {
+    /// Open tool use blocks: index -> (name, accumulated input JSON)
+    tool_uses: HashMap<i32, (String, String)>,
+    stop_reason: Option<StopReason>,
+    usage: Option<UsageMetadata>,
+    finished: bool,
+}
+
+impl ConverseGeminiChunks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Usage reported so far (prompt, candidates)
+    pub fn usage(&self) -> (i32, i32) {
+        self.usage.as_ref().map_or((0, 0), |u| {
+            (u.prompt_token_count, u.candidates_token_count)
+        })
+    }
+
+    /// Chunk for one Converse event, if the event carries anything to send
+    pub fn event_chunk(&mut self, event: &ConverseStreamOutput) -> Option<StreamChunk> {
+        match event {
+            ConverseStreamOutput::ContentBlockStart(start) => {
+                if let Some(ContentBlockStart::ToolUse(tool_use)) = start.start() {
+                    self.tool_uses.insert(
+                        start.content_block_index(),
+                        (tool_use.name().to_string(), String::new()),
+                    );
+                }
+                None
+            }
+            ConverseStreamOutput::ContentBlockDelta(delta) => match delta.delta() {
+                Some(ContentBlockDelta::Text(text)) if !text.is_empty() => {
+                    Some(parts_chunk(vec![Part::text(text)]))
+                }
+                Some(ContentBlockDelta::ToolUse(tool_use)) => {
+                    if let Some((_, input)) = self.tool_uses.get_mut(&delta.content_block_index()) {
+                        input.push_str(tool_use.input());
+                    }
+                    None
+                }
+                _ => None,
+            },
+            ConverseStreamOutput::ContentBlockStop(stop) => {
+                let (name, input) = self.tool_uses.remove(&stop.content_block_index())?;
+                let args = serde_json::from_str(&input)
+                    .unwrap_or_else(|_| serde_json::Value::Object(Default::default()));
+                Some(parts_chunk(vec![function_call_part(&name, args)]))
+            }
+            ConverseStreamOutput::MessageStop(stop) => {
+                self.stop_reason = Some(stop.stop_reason().clone());
+                None
+            }
+            ConverseStreamOutput::Metadata(metadata) => {
+                self.usage = metadata.usage().map(usage_metadata);
+                self.finish_chunk()
+            }
+            _ => None,
+        }
+    }
+
+    /// Final chunk with the finish reason and usage, unless already sent
+    pub fn finish_chunk(&mut self) -> Option<StreamChunk> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let stop_reason = self.stop_reason.clone().unwrap_or(StopReason::EndTurn);
+        Some(StreamChunk {
+            candidates: vec![model_candidate(Vec::new(), Some(&stop_reason))],
+            usage_metadata: self.usage.clone(),
+        })
+    }
+}
+
+fn parts_chunk(parts: Vec<Part>) -> StreamChunk {
+    StreamChunk {
+        candidates: vec![model_candidate(parts, None)],
+        usage_metadata: None,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+// Fixtures are decoded with the Gemini service's SSE decoder
+#[cfg(all(test, feature = "gemini"))]
+mod tests {
+    use super::*;
+    use crate::services::GeminiSseDecoder;
+
+    fn decode(recording: &str) -> Vec<StreamChunk> {
+        let mut decoder = GeminiSseDecoder::new();
+        decoder.push(recording.as_bytes());
+        let mut chunks = Vec::new();
+        while let Some(chunk) = decoder.next_chunk() {
+            chunks.push(chunk);
+        }
+        chunks.extend(decoder.finish());
+        chunks
+    }
+
+    fn replay(recording: &str) -> Vec<ConverseStreamOutput> {
+        let mut adapter = GeminiConverseEvents::new();
+        let mut events = Vec::new();
+        for chunk in decode(recording) {
+            events.extend(adapter.chunk_events(&chunk).unwrap());
+        }
+        events.extend(adapter.finish_events().unwrap());
+        events
+    }
+
+    fn kinds(events: &[ConverseStreamOutput]) -> Vec<&'static str> {
+        events
+            .iter()
+            .map(|event| match event {
+                ConverseStreamOutput::MessageStart(_) => "message_start",
+                ConverseStreamOutput::ContentBlockStart(_) => "block_start",
+                ConverseStreamOutput::ContentBlockDelta(_) => "block_delta",
+                ConverseStreamOutput::ContentBlockStop(_) => "block_stop",
+                ConverseStreamOutput::MessageStop(_) => "message_stop",
+                ConverseStreamOutput::Metadata(_) => "metadata",
+                _ => "other",
+            })
+            .collect()
+    }
+
+    fn final_stop_and_usage(events: &[ConverseStreamOutput]) -> (StopReason, i32, i32) {
+        let stop = events.iter().find_map(|event| match event {
+            ConverseStreamOutput::MessageStop(stop) => Some(stop.stop_reason().clone()),
+            _ => None,
+        });
+        let usage = events.iter().find_map(|event| match event {
+            ConverseStreamOutput::Metadata(metadata) => metadata
+                .usage()
+                .map(|usage| (usage.input_tokens(), usage.output_tokens())),
+            _ => None,
+        });
+        let (input, output) = usage.unwrap();
+        (stop.unwrap(), input, output)
+    }
+
+    #[test]
+    fn test_request_maps_tool_round_trip() {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("toolu_1")
+            .name("get_weather")
+            .input(json_to_document(&serde_json::json!({"city": "Paris"})))
+            .build()
+            .unwrap();
+        let tool_result = ToolResultBlock::builder()
+            .tool_use_id("toolu_1")
+            .content(ToolResultContentBlock::Text("18C".to_string()))
+            .build()
+            .unwrap();
+        let spec = ToolSpecification::builder()
+            .name("get_weather")
+            .input_schema(ToolInputSchema::Json(json_to_document(
+                &serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {"city": {"type": "string"}}
+                }),
+            )))
+            .build()
+            .unwrap();
+
+        let request = ConverseRequest::new("gemini-2.0-flash")
+            .with_messages(vec![
+                Message::builder()
+                    .role(ConversationRole::User)
+                    .content(ContentBlock::Text("Weather?".to_string()))
+                    .build()
+                    .unwrap(),
+                Message::builder()
+                    .role(ConversationRole::Assistant)
+                    .content(ContentBlock::ToolUse(tool_use))
+                    .build()
+                    .unwrap(),
+                Message::builder()
+                    .role(ConversationRole::User)
+                    .content(ContentBlock::ToolResult(tool_result))
+                    .build()
+                    .unwrap(),
+            ])
+            .with_system(vec![SystemContentBlock::Text("Be brief".to_string())])
+            .with_inference_config(InferenceConfiguration::builder().max_tokens(256).build())
+            .with_tool_config(
+                ToolConfiguration::builder()
+                    .tools(SdkTool::ToolSpec(spec))
+                    .tool_choice(ToolChoice::Any(AnyToolChoice::builder().build()))
+                    .build()
+                    .unwrap(),
+            );
+
+        let gemini = to_gemini_request(&request).unwrap();
+
+        assert_eq!(gemini.contents.len(), 3);
+        assert_eq!(gemini.contents[1].role.as_deref(), Some("model"));
+        let response = gemini.contents[2].parts[0]
+            .function_response
+            .as_ref()
+            .unwrap();
+        assert_eq!(response.name, "get_weather");
+        assert_eq!(response.response["result"], "18C");
+        assert_eq!(
+            gemini.system_instruction.unwrap().parts[0].text.as_deref(),
+            Some("Be brief")
+        );
+        assert_eq!(
+            gemini.generation_config.unwrap().max_output_tokens,
+            Some(256)
+        );
+        let parameters = gemini.tools.unwrap()[0].function_declarations[0]
+            .parameters
+            .clone()
+            .unwrap();
+        assert!(parameters.get("$schema").is_none());
+        assert!(parameters.get("additionalProperties").is_none());
+        assert_eq!(
+            gemini.tool_config.unwrap().function_calling_config.mode,
+            "ANY"
+        );
+    }
+
+    #[test]
+    fn test_response_with_function_call_is_tool_use() {
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 4, "totalTokenCount": 16}
+        }))
+        .unwrap();
+
+        let output = to_converse_output(&response).unwrap();
+
+        assert_eq!(output.stop_reason(), &StopReason::ToolUse);
+        assert_eq!(output.usage().unwrap().input_tokens(), 12);
+        let Some(aws_sdk_bedrockruntime::types::ConverseOutput::Message(message)) = output.output()
+        else {
+            panic!("expected a message");
+        };
+        let ContentBlock::ToolUse(tool_use) = &message.content()[0] else {
+            panic!("expected a tool use");
+        };
+        assert_eq!(tool_use.name(), "get_weather");
+        assert!(tool_use.tool_use_id().starts_with("toolu_"));
+    }
+
+    #[test]
+    fn test_text_stream_events() {
+        let events = replay(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/text_stream.sse"
+        )));
+
+        assert_eq!(
+            kinds(&events),
+            vec![
+                "message_start",
+                "block_start",
+                "block_delta",
+                "block_delta",
+                "block_delta",
+                "block_stop",
+                "message_stop",
+                "metadata",
+            ]
+        );
+        assert_eq!(final_stop_and_usage(&events), (StopReason::EndTurn, 9, 17));
+    }
+
+    #[test]
+    fn test_function_call_stream_events() {
+        let events = replay(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/function_call_stream.sse"
+        )));
+
+        assert_eq!(
+            kinds(&events),
+            vec![
+                "message_start",
+                "block_start",
+                "block_delta",
+                "block_stop",
+                "block_start",
+                "block_delta",
+                "block_stop",
+                "block_start",
+                "block_delta",
+                "block_stop",
+                "message_stop",
+                "metadata",
+            ]
+        );
+        let tool_starts = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    ConverseStreamOutput::ContentBlockStart(start)
+                        if matches!(start.start(), Some(ContentBlockStart::ToolUse(_)))
+                )
+            })
+            .count();
+        assert_eq!(tool_starts, 2);
+        assert_eq!(final_stop_and_usage(&events), (StopReason::ToolUse, 42, 23));
+    }
+
+    #[test]
+    fn test_max_tokens_stream_events() {
+        let events = replay(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/max_tokens_stream.sse"
+        )));
+
+        assert_eq!(final_stop_and_usage(&events), (StopReason::MaxTokens, 5, 8));
+    }
+
+    #[test]
+    fn test_inbound_request_pairs_function_responses_with_calls() {
+        let request: GeminiRequest = serde_json::from_value(serde_json::json!({
+            "systemInstruction": {"parts": [{"text": "Be brief"}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": "Weather?"}]},
+                {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}
+                ]},
+                {"role": "function", "parts": [
+                    {"functionResponse": {"name": "get_weather", "response": {"temp": 18}}}
+                ]},
+                {"role": "user", "parts": [{"text": "Thanks"}]}
+            ],
+            "generationConfig": {"maxOutputTokens": 128, "temperature": 0.2},
+            "tools": [{"functionDeclarations": [{
+                "name": "get_weather",
+                "description": "Look up weather",
+                "parameters": {"type": "OBJECT", "properties": {"city": {"type": "STRING"}}}
+            }]}],
+            "toolConfig": {"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["get_weather"]}}
+        }))
+        .unwrap();
+
+        let converse = from_gemini_request("anthropic.claude", &request).unwrap();
+
+        // The function response and the following user turn share one message
+        assert_eq!(converse.messages.len(), 3);
+        let ContentBlock::ToolUse(tool_use) = &converse.messages[1].content()[0] else {
+            panic!("expected a tool use");
+        };
+        let ContentBlock::ToolResult(tool_result) = &converse.messages[2].content()[0] else {
+            panic!("expected a tool result");
+        };
+        assert_eq!(tool_result.tool_use_id(), tool_use.tool_use_id());
+        assert_eq!(converse.messages[2].content().len(), 2);
+        assert_eq!(converse.inference_config.unwrap().max_tokens(), Some(128));
+        assert_eq!(converse.system.unwrap().len(), 1);
+
+        let tool_config = converse.tool_config.unwrap();
+        assert!(matches!(tool_config.tool_choice(), Some(ToolChoice::Tool(_))));
+        let SdkTool::ToolSpec(spec) = &tool_config.tools()[0] else {
+            panic!("expected a tool spec");
+        };
+        let Some(ToolInputSchema::Json(schema)) = spec.input_schema() else {
+            panic!("expected a JSON schema");
+        };
+        let schema = document_to_json(schema);
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["city"]["type"], "string");
+    }
+
+    #[test]
+    fn test_inbound_response_and_chunks() {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("toolu_1")
+            .name("get_weather")
+            .input(json_to_document(&serde_json::json!({"city": "Paris"})))
+            .build()
+            .unwrap();
+        let output = ConverseOutput::builder()
+            .output(aws_sdk_bedrockruntime::types::ConverseOutput::Message(
+                Message::builder()
+                    .role(ConversationRole::Assistant)
+                    .content(ContentBlock::Text("Checking".to_string()))
+                    .content(ContentBlock::ToolUse(tool_use))
+                    .build()
+                    .unwrap(),
+            ))
+            .stop_reason(StopReason::ToolUse)
+            .usage(
+                TokenUsage::builder()
+                    .input_tokens(10)
+                    .output_tokens(5)
+                    .total_tokens(15)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let response = to_gemini_response(&output, "claude-sonnet");
+        let candidate = &response.candidates[0];
+        assert_eq!(candidate.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(candidate.content.parts[0].text.as_deref(), Some("Checking"));
+        assert_eq!(
+            candidate.content.parts[1].function_call.as_ref().unwrap().args["city"],
+            "Paris"
+        );
+        assert_eq!(response.usage_metadata.unwrap().total_token_count, 15);
+
+        let mut chunks = ConverseGeminiChunks::new();
+        let start = ToolUseBlockStart::builder()
+            .tool_use_id("toolu_1")
+            .name("get_weather")
+            .build()
+            .unwrap();
+        assert!(chunks
+            .event_chunk(&block_start(0, Some(ContentBlockStart::ToolUse(start))).unwrap())
+            .is_none());
+        for piece in ["{\"city\":", "\"Paris\"}"] {
+            let delta = ToolUseBlockDelta::builder().input(piece).build().unwrap();
+            let event = block_delta(0, ContentBlockDelta::ToolUse(delta)).unwrap();
+            assert!(chunks.event_chunk(&event).is_none());
+        }
+        let call = chunks.event_chunk(&block_stop(0).unwrap()).unwrap();
+        assert_eq!(
+            call.candidates[0].content.parts[0]
+                .function_call
+                .as_ref()
+                .unwrap()
+                .args["city"],
+            "Paris"
+        );
+        let last = chunks.finish_chunk().unwrap();
+        assert_eq!(last.candidates[0].finish_reason.as_deref(), Some("STOP"));
+        assert!(chunks.finish_chunk().is_none());
+    }
+}
@@ -0,0 +1,623 @@
+//! Converse <-> OpenAI Chat Completions adapter
+//!
+//! Lets [`AzureOpenAIService`](crate::services::AzureOpenAIService) serve the
+//! Converse contract of the [`Backend`](crate::services::Backend) trait:
+//! requests built for Bedrock are translated to Chat Completions bodies for an
+//! Azure OpenAI deployment, and responses and stream chunks come back as the
+//! SDK Converse types.
+//!
+//! Converse puts tool results inside user messages; Chat Completions wants
+//! one `tool` message per result, straight after the assistant's tool calls,
+//! so each user message is split into its tool messages followed by whatever
+//! else it carries. Reasoning blocks and cache points have no Chat
+//! Completions equivalent and are dropped.
+
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ContentBlockDelta, ContentBlockDeltaEvent, ContentBlockStart,
+    ContentBlockStartEvent, ContentBlockStopEvent, ConversationRole, ConverseStreamMetadataEvent,
+    ConverseStreamOutput, DocumentSource, ImageSource, Message, MessageStartEvent,
+    MessageStopEvent, StopReason, SystemContentBlock, TokenUsage, Tool as SdkTool, ToolChoice,
+    ToolInputSchema, ToolResultContentBlock, ToolUseBlock, ToolUseBlockDelta, ToolUseBlockStart,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::bedrock_sdk::{document_to_json, json_to_document, SdkConversionError};
+use crate::services::ConverseRequest;
+
+type SdkResult<T> = Result<T, SdkConversionError>;
+
+fn build_error(
+    what: &'static str,
+) -> impl FnOnce(aws_smithy_types::error::operation::BuildError) -> SdkConversionError {
+    move |e| SdkConversionError::Build(what, e.to_string())
+}
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// Translate a Converse request into a Chat Completions body
+///
+/// Azure selects the model from the deployment in the URL, so the body has
+/// no `model`. Streaming bodies ask for the final usage chunk.
+pub fn to_openai_request(request: &ConverseRequest, stream: bool) -> SdkResult<Value> {
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+
+    if let Some(ref system) = request.system {
+        let text: Vec<&str> = system
+            .iter()
+            .filter_map(|block| match block {
+                SystemContentBlock::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !text.is_empty() {
+            messages.push(json!({ "role": "system", "content": text.join("\n\n") }));
+        }
+    }
+
+    for message in &request.messages {
+        match message.role() {
+            ConversationRole::User => messages.extend(user_messages(message.content())),
+            ConversationRole::Assistant => messages.push(assistant_message(message.content())),
+            other => return Err(SdkConversionError::InvalidRole(other.as_str().to_string())),
+        }
+    }
+
+    let mut body = json!({ "messages": messages });
+    if stream {
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({ "include_usage": true });
+    }
+    if let Some(ref config) = request.inference_config {
+        if let Some(max_tokens) = config.max_tokens() {
+            body["max_completion_tokens"] = json!(max_tokens);
+        }
+        if let Some(temperature) = config.temperature() {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = config.top_p() {
+            body["top_p"] = json!(top_p);
+        }
+        if !config.stop_sequences().is_empty() {
+            body["stop"] = json!(config.stop_sequences());
+        }
+    }
+
+    if let Some(ref tool_config) = request.tool_config {
+        let tools: Vec<Value> = tool_config
+            .tools()
+            .iter()
+            .filter_map(|tool| match tool {
+                SdkTool::ToolSpec(spec) => {
+                    let parameters = match spec.input_schema() {
+                        Some(ToolInputSchema::Json(schema)) => document_to_json(schema),
+                        _ => json!({ "type": "object" }),
+                    };
+                    let mut function = json!({ "name": spec.name(), "parameters": parameters });
+                    if let Some(description) = spec.description() {
+                        function["description"] = json!(description);
+                    }
+                    Some(json!({ "type": "function", "function": function }))
+                }
+                _ => None,
+            })
+            .collect();
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools);
+        }
+        if let Some(choice) = tool_config.tool_choice() {
+            body["tool_choice"] = match choice {
+                ToolChoice::Any(_) => json!("required"),
+                ToolChoice::Tool(tool) => {
+                    json!({ "type": "function", "function": { "name": tool.name() } })
+                }
+                _ => json!("auto"),
+            };
+        }
+    }
+
+    Ok(body)
+}
+
+/// A user turn as its `tool` messages followed by a `user` message
+fn user_messages(content: &[ContentBlock]) -> Vec<Value> {
+    let mut messages = Vec::new();
+    let mut parts = Vec::new();
+    for block in content {
+        match block {
+            ContentBlock::ToolResult(tool_result) => {
+                let text: Vec<String> = tool_result
+                    .content()
+                    .iter()
+                    .filter_map(|block| match block {
+                        ToolResultContentBlock::Text(text) => Some(text.clone()),
+                        ToolResultContentBlock::Json(value) => Some(document_to_json(value).to_string()),
+                        _ => None,
+                    })
+                    .collect();
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_result.tool_use_id(),
+                    "content": text.join("\n"),
+                }));
+            }
+            block => parts.extend(content_part(block)),
+        }
+    }
+    if !parts.is_empty() {
+        messages.push(json!({ "role": "user", "content": parts }));
+    }
+    messages
+}
+
+/// An assistant turn as one message with its text and tool calls
+fn assistant_message(content: &[ContentBlock]) -> Value {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in content {
+        match block {
+            ContentBlock::Text(t) => text.push_str(t),
+            ContentBlock::ToolUse(tool_use) => tool_calls.push(json!({
+                "id": tool_use.tool_use_id(),
+                "type": "function",
+                "function": {
+                    "name": tool_use.name(),
+                    "arguments": document_to_json(tool_use.input()).to_string(),
+                },
+            })),
+            _ => {}
+        }
+    }
+
+    let mut message = json!({ "role": "assistant" });
+    message["content"] = if text.is_empty() && !tool_calls.is_empty() {
+        Value::Null
+    } else {
+        json!(text)
+    };
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    message
+}
+
+/// Chat Completions content part for a Converse block, if it has one
+fn content_part(block: &ContentBlock) -> Option<Value> {
+    match block {
+        ContentBlock::Text(text) => Some(json!({ "type": "text", "text": text })),
+        ContentBlock::Image(image) => match image.source() {
+            Some(ImageSource::Bytes(bytes)) => Some(json!({
+                "type": "image_url",
+                "image_url": {
+                    "url": format!(
+                        "data:image/{};base64,{}",
+                        image.format().as_str(),
+                        BASE64.encode(bytes.as_ref())
+                    ),
+                },
+            })),
+            _ => None,
+        },
+        ContentBlock::Document(document) => {
+            let Some(DocumentSource::Bytes(bytes)) = document.source() else {
+                return None;
+            };
+            match document.format().as_str() {
+                "txt" | "md" | "csv" | "html" => Some(json!({
+                    "type": "text",
+                    "text": format!(
+                        "{}:\n{}",
+                        document.name(),
+                        String::from_utf8_lossy(bytes.as_ref())
+                    ),
+                })),
+                other => {
+                    tracing::warn!(format = other, "Dropping document Azure OpenAI cannot read");
+                    None
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// Translate a Chat Completions response into a Converse output
+pub fn to_converse_output(response: &Value) -> SdkResult<ConverseOutput> {
+    let choice = &response["choices"][0];
+    let message = &choice["message"];
+
+    let mut content = Vec::new();
+    if let Some(text) = message["content"].as_str().filter(|t| !t.is_empty()) {
+        content.push(ContentBlock::Text(text.to_string()));
+    }
+    for tool_call in message["tool_calls"].as_array().into_iter().flatten() {
+        let function = &tool_call["function"];
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id(str_field(tool_call, "id"))
+            .name(str_field(function, "name"))
+            .input(json_to_document(&parse_arguments(function["arguments"].as_str())))
+            .build()
+            .map_err(build_error("tool use"))?;
+        content.push(ContentBlock::ToolUse(tool_use));
+    }
+
+    let message = Message::builder()
+        .role(ConversationRole::Assistant)
+        .set_content(Some(content))
+        .build()
+        .map_err(build_error("message"))?;
+
+    ConverseOutput::builder()
+        .output(aws_sdk_bedrockruntime::types::ConverseOutput::Message(
+            message,
+        ))
+        .stop_reason(stop_reason(choice["finish_reason"].as_str()))
+        .usage(token_usage(&response["usage"])?)
+        .build()
+        .map_err(build_error("converse output"))
+}
+
+fn str_field(value: &Value, field: &str) -> String {
+    value[field].as_str().unwrap_or_default().to_string()
+}
+
+/// Tool call arguments as JSON; models occasionally emit invalid JSON, which
+/// is kept as a string rather than failing the response
+fn parse_arguments(arguments: Option<&str>) -> Value {
+    match arguments.unwrap_or_default().trim() {
+        "" => json!({}),
+        arguments => serde_json::from_str(arguments).unwrap_or_else(|_| json!(arguments)),
+    }
+}
+
+/// Converse stop reason for a Chat Completions finish reason
+fn stop_reason(finish_reason: Option<&str>) -> StopReason {
+    match finish_reason {
+        Some("length") => StopReason::MaxTokens,
+        Some("tool_calls") | Some("function_call") => StopReason::ToolUse,
+        Some("content_filter") => StopReason::ContentFiltered,
+        _ => StopReason::EndTurn,
+    }
+}
+
+fn token_usage(usage: &Value) -> SdkResult<TokenUsage> {
+    let count = |value: &Value| value.as_i64().unwrap_or_default() as i32;
+    let input_tokens = count(&usage["prompt_tokens"]);
+    let output_tokens = count(&usage["completion_tokens"]);
+    TokenUsage::builder()
+        .input_tokens(input_tokens)
+        .output_tokens(output_tokens)
+        .total_tokens(input_tokens + output_tokens)
+        .set_cache_read_input_tokens(
+            usage["prompt_tokens_details"]["cached_tokens"]
+                .as_i64()
+                .map(|n| n as i32),
+        )
+        .build()
+        .map_err(build_error("token usage"))
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Turns Chat Completions stream chunks into ConverseStream events
+///
+/// Text deltas share one block; each tool call gets its own block, opened
+/// when its `id` and name first appear and fed argument fragments by the
+/// call's `index`. Blocks are closed when the next one opens or the stream
+/// ends. The usage chunk comes after the finish reason, so the stop and
+/// metadata events wait for [`finish_events`](Self::finish_events).
+#[derive(Debug, Default)]
+pub struct OpenAIConverseEvents {
+    started: bool,
+    next_index: i32,
+    open_block: Option<i32>,
+    open_text: bool,
+    /// OpenAI tool call index -> Converse block index
+    tool_blocks: HashMap<i64, i32>,
+    finish_reason: Option<String>,
+    usage: Value,
+}
+
+impl OpenAIConverseEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for one stream chunk
+    pub fn chunk_events(&mut self, chunk: &Value) -> SdkResult<Vec<ConverseStreamOutput>> {
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            events.push(ConverseStreamOutput::MessageStart(
+                MessageStartEvent::builder()
+                    .role(ConversationRole::Assistant)
+                    .build()
+                    .map_err(build_error("message start"))?,
+            ));
+        }
+
+        if chunk["usage"].is_object() {
+            self.usage = chunk["usage"].clone();
+        }
+
+        let Some(choice) = chunk["choices"].as_array().and_then(|c| c.first()) else {
+            return Ok(events);
+        };
+        let delta = &choice["delta"];
+
+        if let Some(text) = delta["content"].as_str().filter(|t| !t.is_empty()) {
+            let index = match self.open_block.filter(|_| self.open_text) {
+                Some(index) => index,
+                None => {
+                    let index = self.start_block(&mut events, None)?;
+                    self.open_text = true;
+                    index
+                }
+            };
+            events.push(block_delta(index, ContentBlockDelta::Text(text.to_string()))?);
+        }
+
+        for tool_call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let call_index = tool_call["index"].as_i64().unwrap_or_default();
+            let index = match self.tool_blocks.get(&call_index) {
+                Some(index) => *index,
+                None => {
+                    let start = ToolUseBlockStart::builder()
+                        .tool_use_id(str_field(tool_call, "id"))
+                        .name(str_field(&tool_call["function"], "name"))
+                        .build()
+                        .map_err(build_error("tool use start"))?;
+                    let index = self.start_block(&mut events, Some(ContentBlockStart::ToolUse(start)))?;
+                    self.tool_blocks.insert(call_index, index);
+                    index
+                }
+            };
+            if let Some(arguments) = tool_call["function"]["arguments"].as_str().filter(|a| !a.is_empty()) {
+                let delta = ToolUseBlockDelta::builder()
+                    .input(arguments)
+                    .build()
+                    .map_err(build_error("tool use delta"))?;
+                events.push(block_delta(index, ContentBlockDelta::ToolUse(delta))?);
+            }
+        }
+
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+
+        Ok(events)
+    }
+
+    /// Closing events once the stream sends `[DONE]` or ends
+    pub fn finish_events(&mut self) -> SdkResult<Vec<ConverseStreamOutput>> {
+        let mut events = Vec::new();
+        self.close_block(&mut events)?;
+        events.push(ConverseStreamOutput::MessageStop(
+            MessageStopEvent::builder()
+                .stop_reason(stop_reason(self.finish_reason.as_deref()))
+                .build()
+                .map_err(build_error("message stop"))?,
+        ));
+        events.push(ConverseStreamOutput::Metadata(
+            ConverseStreamMetadataEvent::builder()
+                .usage(token_usage(&self.usage)?)
+                .build(),
+        ));
+        Ok(events)
+    }
+
+    /// Close the open block and start the next one
+    fn start_block(
+        &mut self,
+        events: &mut Vec<ConverseStreamOutput>,
+        start: Option<ContentBlockStart>,
+    ) -> SdkResult<i32> {
+        self.close_block(events)?;
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open_block = Some(index);
+        events.push(block_start(index, start)?);
+        Ok(index)
+    }
+
+    fn close_block(&mut self, events: &mut Vec<ConverseStreamOutput>) -> SdkResult<()> {
+        self.open_text = false;
+        if let Some(index) = self.open_block.take() {
+            events.push(block_stop(index)?);
+        }
+        Ok(())
+    }
+}
+
+fn block_start(index: i32, start: Option<ContentBlockStart>) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStartEvent::builder()
+        .content_block_index(index)
+        .set_start(start)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStart)
+        .map_err(build_error("content block start"))
+}
+
+fn block_delta(index: i32, delta: ContentBlockDelta) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockDeltaEvent::builder()
+        .content_block_index(index)
+        .delta(delta)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockDelta)
+        .map_err(build_error("content block delta"))
+}
+
+fn block_stop(index: i32) -> SdkResult<ConverseStreamOutput> {
+    ContentBlockStopEvent::builder()
+        .content_block_index(index)
+        .build()
+        .map(ConverseStreamOutput::ContentBlockStop)
+        .map_err(build_error("content block stop"))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{
+        AnyToolChoice, InferenceConfiguration, ToolConfiguration, ToolResultBlock,
+        ToolSpecification,
+    };
+
+    fn message(role: ConversationRole, content: Vec<ContentBlock>) -> Message {
+        Message::builder()
+            .role(role)
+            .set_content(Some(content))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_request_splits_tool_results_into_tool_messages() {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("call_1")
+            .name("get_weather")
+            .input(json_to_document(&json!({"city": "Paris"})))
+            .build()
+            .unwrap();
+        let tool_result = ToolResultBlock::builder()
+            .tool_use_id("call_1")
+            .content(ToolResultContentBlock::Text("sunny".to_string()))
+            .build()
+            .unwrap();
+        let tool = ToolSpecification::builder()
+            .name("get_weather")
+            .input_schema(ToolInputSchema::Json(json_to_document(&json!({"type": "object"}))))
+            .build()
+            .unwrap();
+
+        let mut request = ConverseRequest::new("gpt-4o");
+        request.messages = vec![
+            message(ConversationRole::User, vec![ContentBlock::Text("Weather?".to_string())]),
+            message(ConversationRole::Assistant, vec![ContentBlock::ToolUse(tool_use)]),
+            message(
+                ConversationRole::User,
+                vec![
+                    ContentBlock::ToolResult(tool_result),
+                    ContentBlock::Text("And tomorrow?".to_string()),
+                ],
+            ),
+        ];
+        request.system = Some(vec![SystemContentBlock::Text("Be brief".to_string())]);
+        request.inference_config = Some(InferenceConfiguration::builder().max_tokens(256).build());
+        request.tool_config = Some(
+            ToolConfiguration::builder()
+                .tools(SdkTool::ToolSpec(tool))
+                .tool_choice(ToolChoice::Any(AnyToolChoice::builder().build()))
+                .build()
+                .unwrap(),
+        );
+
+        let body = to_openai_request(&request, true).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[2]["content"], Value::Null);
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], r#"{"city":"Paris"}"#);
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+        assert_eq!(messages[4]["content"][0]["text"], "And tomorrow?");
+        assert_eq!(body["max_completion_tokens"], 256);
+        assert_eq!(body["tool_choice"], "required");
+        assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(body["stream_options"]["include_usage"], true);
+        assert!(body.get("model").is_none());
+    }
+
+    #[test]
+    fn test_response_with_tool_calls() {
+        let response = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {
+                "prompt_tokens": 20,
+                "completion_tokens": 8,
+                "prompt_tokens_details": {"cached_tokens": 16}
+            }
+        });
+
+        let output = to_converse_output(&response).unwrap();
+        assert_eq!(output.stop_reason(), &StopReason::ToolUse);
+        let usage = output.usage().unwrap();
+        assert_eq!((usage.input_tokens(), usage.output_tokens()), (20, 8));
+        assert_eq!(usage.cache_read_input_tokens(), Some(16));
+
+        let message = output.output().unwrap().as_message().unwrap();
+        assert_eq!(message.content().len(), 1);
+        let tool_use = message.content()[0].as_tool_use().unwrap();
+        assert_eq!(tool_use.tool_use_id(), "call_1");
+        assert_eq!(document_to_json(tool_use.input())["city"], "Paris");
+    }
+
+    #[test]
+    fn test_stream_chunks() {
+        let recording = [
+            r#"{"choices":[],"prompt_filter_results":[]}"#,
+            r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":""},"finish_reason":null}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"Checking"},"finish_reason":null}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"q\":1}"}}]},"finish_reason":null}]}"#,
+            r#"{"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":10,"completion_tokens":9}}"#,
+        ];
+
+        let mut adapter = OpenAIConverseEvents::new();
+        let mut events: Vec<ConverseStreamOutput> = recording
+            .iter()
+            .flat_map(|line| {
+                let chunk: Value = serde_json::from_str(line).unwrap();
+                adapter.chunk_events(&chunk).unwrap()
+            })
+            .collect();
+        events.extend(adapter.finish_events().unwrap());
+
+        // start, text start/delta/stop, tool start/delta/stop, stop, metadata
+        assert_eq!(events.len(), 9);
+        let Some(ConverseStreamOutput::ContentBlockStart(start)) = events.get(4) else {
+            panic!("expected tool use start");
+        };
+        assert_eq!(start.content_block_index(), 1);
+        assert_eq!(start.start().unwrap().as_tool_use().unwrap().name(), "lookup");
+        let Some(ConverseStreamOutput::MessageStop(stop)) = events.get(7) else {
+            panic!("expected message stop");
+        };
+        assert_eq!(stop.stop_reason(), &StopReason::ToolUse);
+        let Some(ConverseStreamOutput::Metadata(metadata)) = events.get(8) else {
+            panic!("expected metadata");
+        };
+        let usage = metadata.usage().unwrap();
+        assert_eq!((usage.input_tokens(), usage.output_tokens()), (10, 9));
+    }
+}
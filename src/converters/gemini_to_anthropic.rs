@@ -53,7 +53,12 @@ impl GeminiToAnthropicConverter {
             .ok_or_else(|| GeminiToAnthropicError::MissingContent("No candidates".to_string()))?;
 
         let content = self.convert_content(candidate)?;
-        let stop_reason = self.convert_finish_reason(candidate.finish_reason.as_deref());
+        // Gemini reports STOP after function calls; Anthropic clients expect tool_use
+        let stop_reason = if content.iter().any(|b| matches!(b, ContentBlock::ToolUse { .. })) {
+            StopReason::ToolUse
+        } else {
+            self.convert_finish_reason(candidate.finish_reason.as_deref())
+        };
         let usage = self.convert_usage(response.usage_metadata.as_ref());
 
         Ok(MessageResponse {
@@ -65,6 +70,7 @@ impl GeminiToAnthropicConverter {
             stop_reason: Some(stop_reason),
             stop_sequence: None,
             usage,
+            container: None,
         })
     }
 
@@ -97,7 +103,7 @@ impl GeminiToAnthropicConverter {
     }
 
     /// Convert Gemini finish reason to Anthropic stop reason
-    fn convert_finish_reason(&self, finish_reason: Option<&str>) -> StopReason {
+    pub fn convert_finish_reason(&self, finish_reason: Option<&str>) -> StopReason {
         match finish_reason {
             Some("STOP") => StopReason::EndTurn,
             Some("MAX_TOKENS") => StopReason::MaxTokens,
@@ -116,12 +122,14 @@ impl GeminiToAnthropicConverter {
                 output_tokens: u.candidates_token_count,
                 cache_creation_input_tokens: None,
                 cache_read_input_tokens: None,
+                service_tier: None,
             },
             None => Usage {
                 input_tokens: 0,
                 output_tokens: 0,
                 cache_creation_input_tokens: None,
                 cache_read_input_tokens: None,
+                service_tier: None,
             },
         }
     }
@@ -3,10 +3,13 @@
 //! This module handles the conversion of Google Gemini API responses
 //! to OpenAI Chat Completions API format.
 
-use crate::schemas::gemini::{Candidate, GeminiResponse, StreamChunk, UsageMetadata};
+use crate::schemas::gemini::{
+    Candidate, GeminiResponse, LogprobsCandidate, LogprobsResult, StreamChunk, UsageMetadata,
+};
 use crate::schemas::openai::{
     AssistantMessage, ChatCompletionChunk, ChatCompletionResponse, ChatRole, Choice, ChunkChoice,
-    ChunkDelta, CompletionUsage, FunctionCall, FunctionCallDelta, ToolCall, ToolCallDelta,
+    ChunkDelta, CompletionUsage, ContentPart, FunctionCall, FunctionCallDelta, ToolCall,
+    ToolCallDelta,
 };
 use thiserror::Error;
 use uuid::Uuid;
@@ -48,13 +51,18 @@ impl GeminiToOpenAIConverter {
         response: &GeminiResponse,
         model: &str,
     ) -> Result<ChatCompletionResponse, GeminiToOpenAIError> {
-        let candidate = response
-            .candidates
-            .first()
-            .ok_or_else(|| GeminiToOpenAIError::MissingContent("No candidates".to_string()))?;
+        if response.candidates.is_empty() {
+            return Err(GeminiToOpenAIError::MissingContent("No candidates".to_string()));
+        }
 
-        let message = self.convert_candidate_to_message(candidate)?;
-        let finish_reason = self.convert_finish_reason(candidate.finish_reason.as_deref());
+        // One choice per candidate (more than one with candidateCount); usage
+        // already covers all of them
+        let choices = response
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(i, candidate)| self.convert_candidate_to_choice(candidate, i as i32))
+            .collect::<Result<Vec<_>, _>>()?;
         let usage = self.convert_usage(response.usage_metadata.as_ref());
 
         let id = format!("chatcmpl-{}", Uuid::new_v4().to_string().replace("-", ""));
@@ -64,14 +72,33 @@ impl GeminiToOpenAIConverter {
             object: "chat.completion".to_string(),
             created: chrono::Utc::now().timestamp(),
             model: model.to_string(),
-            choices: vec![Choice {
-                index: 0,
-                message,
-                finish_reason: Some(finish_reason),
-                logprobs: None,
-            }],
+            choices,
             usage,
             system_fingerprint: None,
+            service_tier: None,
+            audio_transcriptions: None,
+        })
+    }
+
+    /// Convert a Gemini candidate to an OpenAI choice
+    fn convert_candidate_to_choice(
+        &self,
+        candidate: &Candidate,
+        position: i32,
+    ) -> Result<Choice, GeminiToOpenAIError> {
+        let message = self.convert_candidate_to_message(candidate)?;
+        // Gemini reports STOP after function calls
+        let finish_reason = if message.tool_calls.is_some() {
+            "tool_calls".to_string()
+        } else {
+            self.convert_finish_reason(candidate.finish_reason.as_deref())
+        };
+
+        Ok(Choice {
+            index: candidate.index.unwrap_or(position),
+            message,
+            finish_reason: Some(finish_reason),
+            logprobs: candidate.logprobs_result.as_ref().map(convert_logprobs),
         })
     }
 
@@ -82,12 +109,22 @@ impl GeminiToOpenAIConverter {
     ) -> Result<AssistantMessage, GeminiToOpenAIError> {
         let mut text_parts = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut images = Vec::new();
 
         for part in &candidate.content.parts {
             if let Some(ref text) = part.text {
                 text_parts.push(text.clone());
             }
 
+            if let Some(ref inline_data) = part.inline_data {
+                if inline_data.mime_type.starts_with("image/") {
+                    images.push(ContentPart::image_base64(
+                        &inline_data.mime_type,
+                        &inline_data.data,
+                    ));
+                }
+            }
+
             if let Some(ref function_call) = part.function_call {
                 let call_id = format!("call_{}", Uuid::new_v4().to_string().replace("-", ""));
                 tool_calls.push(ToolCall {
@@ -116,11 +153,16 @@ impl GeminiToOpenAIConverter {
             } else {
                 Some(tool_calls)
             },
+            images: if images.is_empty() {
+                None
+            } else {
+                Some(images)
+            },
         })
     }
 
     /// Convert Gemini finish reason to OpenAI finish reason string
-    fn convert_finish_reason(&self, finish_reason: Option<&str>) -> String {
+    pub fn convert_finish_reason(&self, finish_reason: Option<&str>) -> String {
         match finish_reason {
             Some("STOP") => "stop".to_string(),
             Some("MAX_TOKENS") => "length".to_string(),
@@ -165,8 +207,11 @@ impl GeminiToOpenAIConverter {
         };
 
         let mut finish_reason = None;
+        let mut logprobs = None;
 
         if let Some(candidate) = chunk.candidates.first() {
+            logprobs = candidate.logprobs_result.as_ref().map(convert_logprobs);
+
             // Extract text delta
             for part in &candidate.content.parts {
                 if let Some(ref text) = part.text {
@@ -211,7 +256,7 @@ impl GeminiToOpenAIConverter {
                 index: 0,
                 delta,
                 finish_reason,
-                logprobs: None,
+                logprobs,
             }],
             system_fingerprint: None,
             usage: None,
@@ -243,6 +288,37 @@ impl GeminiToOpenAIConverter {
     }
 }
 
+/// Convert Gemini log probabilities to an OpenAI `logprobs` object
+///
+/// Gemini reports tokens as text only, so `bytes` is the token's UTF-8 bytes.
+pub fn convert_logprobs(result: &LogprobsResult) -> serde_json::Value {
+    let token = |candidate: &LogprobsCandidate| {
+        serde_json::json!({
+            "token": candidate.token,
+            "logprob": candidate.log_probability,
+            "bytes": candidate.token.as_bytes(),
+        })
+    };
+
+    let content: Vec<serde_json::Value> = result
+        .chosen_candidates
+        .iter()
+        .enumerate()
+        .map(|(i, chosen)| {
+            let mut entry = token(chosen);
+            let top: Vec<serde_json::Value> = result
+                .top_candidates
+                .get(i)
+                .map(|step| step.candidates.iter().map(token).collect())
+                .unwrap_or_default();
+            entry["top_logprobs"] = serde_json::Value::Array(top);
+            entry
+        })
+        .collect();
+
+    serde_json::json!({ "content": content })
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -251,6 +327,48 @@ impl GeminiToOpenAIConverter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_convert_multiple_candidates() {
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [
+                {"content": {"role": "model", "parts": [{"text": "Red"}]}, "finishReason": "STOP", "index": 0},
+                {"content": {"role": "model", "parts": [{"text": "Blue"}]}, "finishReason": "MAX_TOKENS", "index": 1}
+            ],
+            "usageMetadata": {"promptTokenCount": 5, "candidatesTokenCount": 8, "totalTokenCount": 13}
+        }))
+        .unwrap();
+
+        let result = GeminiToOpenAIConverter::new()
+            .convert_response(&response, "gemini-2.5-flash")
+            .unwrap();
+
+        assert_eq!(result.choices.len(), 2);
+        assert_eq!(result.choices[1].index, 1);
+        assert_eq!(result.choices[1].message.content.as_deref(), Some("Blue"));
+        assert_eq!(result.choices[1].finish_reason.as_deref(), Some("length"));
+        assert_eq!(result.usage.completion_tokens, 8);
+    }
+
+    #[test]
+    fn test_convert_logprobs() {
+        let result: LogprobsResult = serde_json::from_value(serde_json::json!({
+            "topCandidates": [{"candidates": [
+                {"token": "Hi", "logProbability": -0.1},
+                {"token": "Hello", "logProbability": -2.5}
+            ]}],
+            "chosenCandidates": [{"token": "Hi", "logProbability": -0.1}]
+        }))
+        .unwrap();
+
+        let logprobs = convert_logprobs(&result);
+
+        let first = &logprobs["content"][0];
+        assert_eq!(first["token"], "Hi");
+        assert_eq!(first["bytes"], serde_json::json!([72, 105]));
+        assert_eq!(first["top_logprobs"].as_array().unwrap().len(), 2);
+        assert_eq!(first["top_logprobs"][1]["token"], "Hello");
+    }
+
     #[test]
     fn test_convert_finish_reason() {
         let converter = GeminiToOpenAIConverter::new();
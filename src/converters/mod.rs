@@ -6,6 +6,17 @@
 //! - OpenAI <-> Bedrock
 //! - OpenAI <-> Gemini
 //!
+//! Bedrock requests from both converters are turned into AWS SDK types by
+//! [`bedrock_sdk`]. [`converse_gemini`], [`converse_anthropic`] and
+//! [`converse_openai`] adapt those SDK types to Gemini, the hosted Anthropic
+//! API and Azure OpenAI for the `Backend` trait.
+//! Fields they cannot carry over unchanged are reported as
+//! [`warnings::ConversionWarning`]s.
+//!
+//! Each converter also implements the [`Converter`] trait, and
+//! [`ConverterRegistry`] looks them up by (source, target) [`ApiFormat`]; see
+//! [`registry`] for the semver-stable embedding API.
+//!
 //! # Usage
 //!
 //! ```rust,ignore
@@ -23,12 +34,19 @@
 
 pub mod anthropic_to_bedrock;
 pub mod anthropic_to_gemini;
+pub mod bedrock_sdk;
 pub mod bedrock_to_anthropic;
 pub mod bedrock_to_openai;
+pub mod converse_anthropic;
+pub mod converse_gemini;
+pub mod converse_openai;
 pub mod gemini_to_anthropic;
 pub mod gemini_to_openai;
 pub mod openai_to_bedrock;
 pub mod openai_to_gemini;
+pub mod registry;
+pub mod shared;
+pub mod warnings;
 
 // Re-export Anthropic <-> Bedrock converters
 pub use anthropic_to_bedrock::AnthropicToBedrockConverter;
@@ -46,9 +64,22 @@ pub use openai_to_bedrock::OpenAIToBedrockConverter;
 pub use gemini_to_openai::GeminiToOpenAIConverter;
 pub use openai_to_gemini::OpenAIToGeminiConverter;
 
+// Converter trait and (source, target) registry
+pub use registry::{
+    ApiFormat, ConversionContext, Converter, ConverterError, ConverterRegistry, GeminiCall,
+    JsonConverter,
+};
+
+// Shared instances for AppState
+pub use shared::SharedConverters;
+
+// Warnings about fields that could not be converted unchanged
+pub use warnings::{ConversionWarning, WarningKind, CONVERSION_WARNINGS_HEADER};
+
 // Re-export error types
 pub use anthropic_to_bedrock::ConversionError;
 pub use anthropic_to_gemini::AnthropicToGeminiError;
+pub use bedrock_sdk::SdkConversionError;
 pub use bedrock_to_anthropic::ResponseConversionError;
 pub use bedrock_to_openai::OpenAIResponseConversionError;
 pub use gemini_to_anthropic::GeminiToAnthropicError;
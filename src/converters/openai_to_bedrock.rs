@@ -2,7 +2,16 @@
 //!
 //! This module handles the conversion of OpenAI Chat Completions API requests
 //! to AWS Bedrock Converse API format.
-
+//!
+//! IDE agents (Cursor, Continue) send transcripts the OpenAI API accepts but
+//! Converse does not: empty assistant messages, one `tool` message per
+//! parallel call, and user turns holding only an image. Blank text is
+//! dropped, same-role turns are merged with tool results first, and
+//! image-only turns get a short text block so they convert instead of being
+//! rejected.
+
+use crate::config::TemperatureScaling;
+use crate::converters::warnings::ConversionWarning;
 use crate::schemas::bedrock::{
     BedrockContentBlock, BedrockConverseRequest, BedrockImageData, BedrockImageSource,
     BedrockInferenceConfig, BedrockMessage, BedrockSystemMessage, BedrockTool,
@@ -12,10 +21,15 @@ use crate::schemas::bedrock::{
 use crate::schemas::openai::{
     ChatCompletionRequest, ChatMessage, ChatRole, ContentPart, MessageContent, Tool, ToolChoice,
 };
+use crate::utils::{split_data_url, validate_base64};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use std::collections::HashMap;
 use thiserror::Error;
 
+/// Text added to user turns that carry only images, which some Converse
+/// models reject
+const IMAGE_ONLY_TEXT: &str = "(image attached)";
+
 // ============================================================================
 // Error Types
 // ============================================================================
@@ -45,6 +59,43 @@ pub enum OpenAIConversionError {
     InvalidImageUrl(String),
 }
 
+// ============================================================================
+// Penalty Parameters
+// ============================================================================
+
+/// Native request fields a Bedrock model family uses for OpenAI's penalties
+///
+/// Claude has no penalty parameters; for families listed here the values are
+/// passed through `additionalModelRequestFields`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct PenaltyMapping {
+    /// Substring identifying the model family in a Bedrock model ID
+    pub model_family: &'static str,
+    pub presence_penalty: &'static str,
+    pub frequency_penalty: &'static str,
+}
+
+/// Model families with native penalty parameters
+pub const PENALTY_MAPPINGS: &[PenaltyMapping] = &[
+    PenaltyMapping {
+        model_family: "cohere.command-r",
+        presence_penalty: "presence_penalty",
+        frequency_penalty: "frequency_penalty",
+    },
+    PenaltyMapping {
+        model_family: "ai21.jamba",
+        presence_penalty: "presence_penalty",
+        frequency_penalty: "frequency_penalty",
+    },
+];
+
+/// Penalty mapping for a Bedrock model ID, if its family supports penalties
+pub fn penalty_mapping(model_id: &str) -> Option<&'static PenaltyMapping> {
+    PENALTY_MAPPINGS
+        .iter()
+        .find(|mapping| model_id.contains(mapping.model_family))
+}
+
 // ============================================================================
 // Converter Implementation
 // ============================================================================
@@ -61,6 +112,8 @@ pub enum OpenAIConversionError {
 pub struct OpenAIToBedrockConverter {
     /// Model ID mapping from OpenAI to Bedrock format
     model_mapping: HashMap<String, String>,
+    /// How temperatures above Bedrock's 0-1 range are mapped
+    temperature_scaling: TemperatureScaling,
 }
 
 impl OpenAIToBedrockConverter {
@@ -127,12 +180,24 @@ impl OpenAIToBedrockConverter {
             "anthropic.claude-3-5-sonnet-20241022-v2:0".to_string(),
         );
 
-        Self { model_mapping }
+        Self {
+            model_mapping,
+            temperature_scaling: TemperatureScaling::default(),
+        }
     }
 
     /// Create a converter with custom model mappings.
     pub fn with_model_mapping(model_mapping: HashMap<String, String>) -> Self {
-        Self { model_mapping }
+        Self {
+            model_mapping,
+            temperature_scaling: TemperatureScaling::default(),
+        }
+    }
+
+    /// Set how OpenAI temperatures (0-2) are mapped to Bedrock's 0-1 range.
+    pub fn with_temperature_scaling(mut self, scaling: TemperatureScaling) -> Self {
+        self.temperature_scaling = scaling;
+        self
     }
 
     /// Add a model mapping.
@@ -140,6 +205,11 @@ impl OpenAIToBedrockConverter {
         self.model_mapping.insert(openai_id, bedrock_id);
     }
 
+    /// Check whether a model ID has an explicit mapping
+    pub fn has_model_mapping(&self, openai_id: &str) -> bool {
+        self.model_mapping.contains_key(openai_id)
+    }
+
     // ========================================================================
     // Main Conversion Entry Point
     // ========================================================================
@@ -164,11 +234,15 @@ impl OpenAIToBedrockConverter {
             .or(request.max_tokens)
             .unwrap_or(4096) as i32;
 
+        // Penalties go in native fields for model families that have them
+        let penalty_fields = Self::penalty_fields(request, &model_id);
+
         // Create base request
         let mut bedrock_request = BedrockConverseRequest::new(model_id, messages, max_tokens);
 
         // Convert inference config
         bedrock_request.inference_config = self.convert_inference_config(request, max_tokens);
+        bedrock_request.additional_model_request_fields = penalty_fields;
 
         // Convert system prompt
         if !system_messages.is_empty() {
@@ -186,6 +260,85 @@ impl OpenAIToBedrockConverter {
         Ok(bedrock_request)
     }
 
+    /// `presence_penalty`/`frequency_penalty` as native fields of `model_id`
+    pub fn penalty_fields(
+        request: &ChatCompletionRequest,
+        model_id: &str,
+    ) -> Option<serde_json::Value> {
+        let mapping = penalty_mapping(model_id)?;
+        let mut fields = serde_json::Map::new();
+        if let Some(penalty) = request.presence_penalty {
+            fields.insert(mapping.presence_penalty.to_string(), serde_json::json!(penalty));
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            fields.insert(mapping.frequency_penalty.to_string(), serde_json::json!(penalty));
+        }
+        (!fields.is_empty()).then_some(serde_json::Value::Object(fields))
+    }
+
+    /// Parameters of `request` that [`convert_request`](Self::convert_request)
+    /// drops or adjusts because the Converse API has no equivalent
+    pub fn conversion_warnings(&self, request: &ChatCompletionRequest) -> Vec<ConversionWarning> {
+        let mut warnings = Vec::new();
+
+        if let Some(temperature) = request.temperature {
+            let mapped = self.temperature_scaling.apply(temperature);
+            if mapped != temperature {
+                let message = format!(
+                    "Temperature {} was mapped to {} for Bedrock's 0-1 range",
+                    temperature, mapped
+                );
+                warnings.push(match self.temperature_scaling {
+                    TemperatureScaling::Linear if (0.0..=2.0).contains(&temperature) => {
+                        ConversionWarning::scaled("temperature", message)
+                    }
+                    _ => ConversionWarning::clamped("temperature", message),
+                });
+            }
+        }
+
+        let penalties_mapped = penalty_mapping(&self.convert_model_id(&request.model)).is_some();
+        let ignored = [
+            (
+                "presence_penalty",
+                request.presence_penalty.is_some() && !penalties_mapped,
+            ),
+            (
+                "frequency_penalty",
+                request.frequency_penalty.is_some() && !penalties_mapped,
+            ),
+            ("seed", request.seed.is_some()),
+            ("logprobs", request.logprobs == Some(true)),
+            ("top_logprobs", request.top_logprobs.is_some()),
+            (
+                "response_format",
+                request
+                    .response_format
+                    .as_ref()
+                    .is_some_and(|f| f.format_type != "text"),
+            ),
+        ];
+        for (param, present) in ignored {
+            if present {
+                warnings.push(ConversionWarning::ignored(
+                    param,
+                    format!("{} is not supported by Bedrock", param),
+                ));
+            }
+        }
+
+        for (i, tool) in request.tools.iter().flatten().enumerate() {
+            if tool.tool_type != "function" {
+                warnings.push(ConversionWarning::dropped(
+                    format!("tools[{}]", i),
+                    format!("Unsupported tool type '{}' was skipped", tool.tool_type),
+                ));
+            }
+        }
+
+        warnings
+    }
+
     // ========================================================================
     // Model ID Conversion
     // ========================================================================
@@ -244,11 +397,32 @@ impl OpenAIToBedrockConverter {
         &self,
         messages: &[&ChatMessage],
     ) -> Result<Vec<BedrockMessage>, OpenAIConversionError> {
-        let mut result = Vec::new();
+        let mut result: Vec<BedrockMessage> = Vec::new();
 
         for message in messages {
-            if let Some(converted) = self.convert_message(message)? {
-                result.push(converted);
+            let Some(converted) = self.convert_message(message)? else {
+                continue;
+            };
+            // Parallel tool results, and turns left adjacent by a dropped empty
+            // assistant message, become one turn since Converse alternates roles
+            match result.last_mut() {
+                Some(previous) if previous.role == converted.role => {
+                    previous.content.extend(converted.content)
+                }
+                _ => result.push(converted),
+            }
+        }
+
+        for message in result.iter_mut().filter(|m| m.role == "user") {
+            message
+                .content
+                .sort_by_key(|block| !matches!(block, BedrockContentBlock::ToolResult { .. }));
+            if message
+                .content
+                .iter()
+                .all(|block| matches!(block, BedrockContentBlock::Image { .. }))
+            {
+                message.content.push(BedrockContentBlock::text(IMAGE_ONLY_TEXT));
             }
         }
 
@@ -297,7 +471,7 @@ impl OpenAIToBedrockConverter {
                 // Add text content if present
                 if let Some(ref content) = message.content {
                     let text = content.to_string_content();
-                    if !text.is_empty() {
+                    if !text.trim().is_empty() {
                         blocks.push(BedrockContentBlock::text(&text));
                     }
                 }
@@ -320,8 +494,9 @@ impl OpenAIToBedrockConverter {
             }
         }
 
-        // Handle regular content
+        // Handle regular content (blank text, as in empty assistant messages, is dropped)
         match &message.content {
+            Some(MessageContent::Text(text)) if text.trim().is_empty() => Ok(vec![]),
             Some(MessageContent::Text(text)) => Ok(vec![BedrockContentBlock::text(text)]),
             Some(MessageContent::Parts(parts)) => self.convert_content_parts(parts),
             None => Ok(vec![]),
@@ -337,6 +512,7 @@ impl OpenAIToBedrockConverter {
 
         for part in parts {
             match part {
+                ContentPart::Text { text } if text.trim().is_empty() => {}
                 ContentPart::Text { text } => {
                     blocks.push(BedrockContentBlock::text(text));
                 }
@@ -347,6 +523,11 @@ impl OpenAIToBedrockConverter {
                         cache_point: None,
                     });
                 }
+                ContentPart::InputAudio { .. } => {
+                    return Err(OpenAIConversionError::UnsupportedFeature(
+                        "input_audio parts must be transcribed before conversion".to_string(),
+                    ));
+                }
             }
         }
 
@@ -371,23 +552,9 @@ impl OpenAIToBedrockConverter {
     /// Convert a data URL to Bedrock image data.
     fn convert_data_url(&self, url: &str) -> Result<BedrockImageData, OpenAIConversionError> {
         // Parse data URL: data:image/png;base64,<data>
-        let parts: Vec<&str> = url.splitn(2, ',').collect();
-        if parts.len() != 2 {
-            return Err(OpenAIConversionError::InvalidImageUrl(
-                "Invalid data URL format".to_string(),
-            ));
-        }
-
-        let metadata = parts[0];
-        let data = parts[1];
-
-        // Extract media type
-        let media_type = metadata
-            .strip_prefix("data:")
-            .and_then(|s| s.split(';').next())
-            .ok_or_else(|| {
-                OpenAIConversionError::InvalidImageUrl("Could not parse media type".to_string())
-            })?;
+        let (media_type, data) = split_data_url(url).ok_or_else(|| {
+            OpenAIConversionError::InvalidImageUrl("Invalid data URL format".to_string())
+        })?;
 
         // Extract format from media type
         let format = media_type.split('/').nth(1).unwrap_or("png").to_string();
@@ -412,15 +579,32 @@ impl OpenAIToBedrockConverter {
             OpenAIConversionError::MissingField("tool_call_id for tool message".to_string())
         })?;
 
-        let content_text = message
-            .content
-            .as_ref()
-            .map(|c| c.to_string_content())
-            .unwrap_or_default();
+        // Image parts (e.g. screenshots) are kept alongside the text, in
+        // Bedrock's JSON form with base64 bytes
+        let content = match &message.content {
+            Some(MessageContent::Parts(parts))
+                if parts.iter().any(|p| matches!(p, ContentPart::ImageUrl { .. })) =>
+            {
+                let mut content = Vec::new();
+                for part in parts {
+                    match part {
+                        ContentPart::Text { text } => content.push(serde_json::json!({"text": text})),
+                        ContentPart::ImageUrl { image_url } => {
+                            content.push(self.convert_tool_result_image(&image_url.url)?)
+                        }
+                        ContentPart::InputAudio { .. } => {}
+                    }
+                }
+                content
+            }
+            content => vec![serde_json::json!({
+                "text": content.as_ref().map(|c| c.to_string_content()).unwrap_or_default()
+            })],
+        };
 
         let tool_result = BedrockToolResultData {
             tool_use_id: tool_use_id.clone(),
-            content: vec![serde_json::json!({"text": content_text})],
+            content,
             status: Some("success".to_string()),
         };
 
@@ -430,6 +614,21 @@ impl OpenAIToBedrockConverter {
         }])
     }
 
+    /// Convert a tool result image data URL to Bedrock's JSON image form.
+    fn convert_tool_result_image(&self, url: &str) -> Result<serde_json::Value, OpenAIConversionError> {
+        let (media_type, data) = split_data_url(url).ok_or_else(|| {
+            OpenAIConversionError::InvalidImageUrl("Tool result images must be base64 data URLs".to_string())
+        })?;
+        validate_base64(data).map_err(|e| OpenAIConversionError::Base64DecodeError(e.to_string()))?;
+
+        Ok(serde_json::json!({
+            "image": {
+                "format": media_type.split('/').nth(1).unwrap_or("png"),
+                "source": {"bytes": data}
+            }
+        }))
+    }
+
     // ========================================================================
     // System Message Conversion
     // ========================================================================
@@ -461,8 +660,7 @@ impl OpenAIToBedrockConverter {
 
         if let Some(temperature) = request.temperature {
             // OpenAI temperature range is 0-2, Bedrock expects 0-1
-            // We clamp to 0-1 for safety
-            config = config.with_temperature(temperature.min(1.0).max(0.0));
+            config = config.with_temperature(self.temperature_scaling.apply(temperature));
         }
 
         if let Some(top_p) = request.top_p {
@@ -564,7 +762,7 @@ impl Default for OpenAIToBedrockConverter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schemas::openai::{FunctionCall, FunctionDef, StopSequence, ToolCall};
+    use crate::schemas::openai::{FunctionCall, FunctionDef, ImageUrl, StopSequence, ToolCall};
 
     #[test]
     fn test_converter_creation() {
@@ -679,6 +877,9 @@ mod tests {
             n: None,
             logprobs: None,
             top_logprobs: None,
+            service_tier: None,
+            store: None,
+            metadata: None,
         };
 
         let result = converter.convert_request(&request).unwrap();
@@ -801,6 +1002,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tool_result_image_conversion() {
+        let converter = OpenAIToBedrockConverter::new();
+        let png_data = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+        let message = ChatMessage {
+            role: ChatRole::Tool,
+            content: Some(MessageContent::Parts(vec![
+                ContentPart::Text {
+                    text: "Screenshot".to_string(),
+                },
+                ContentPart::ImageUrl {
+                    image_url: ImageUrl {
+                        url: format!("data:image/png;base64,{}", png_data),
+                        detail: None,
+                    },
+                },
+            ])),
+            name: None,
+            tool_calls: None,
+            tool_call_id: Some("call_123".to_string()),
+        };
+
+        let result = converter.convert_message_content(&message).unwrap();
+        if let BedrockContentBlock::ToolResult { tool_result, .. } = &result[0] {
+            assert_eq!(tool_result.content[0]["text"], "Screenshot");
+            assert_eq!(tool_result.content[1]["image"]["format"], "png");
+            assert_eq!(tool_result.content[1]["image"]["source"]["bytes"], png_data);
+        } else {
+            panic!("Expected ToolResult block");
+        }
+    }
+
     #[test]
     fn test_data_url_image_conversion() {
         let converter = OpenAIToBedrockConverter::new();
@@ -850,6 +1084,9 @@ mod tests {
             n: None,
             logprobs: None,
             top_logprobs: None,
+            service_tier: None,
+            store: None,
+            metadata: None,
         };
 
         let config = converter.convert_inference_config(&request, 100);
@@ -860,6 +1097,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_penalties_mapped_for_supporting_models() {
+        let converter = OpenAIToBedrockConverter::new();
+        let request = |model: &str| -> ChatCompletionRequest {
+            serde_json::from_value(serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Hello"}],
+                "presence_penalty": 0.5,
+                "frequency_penalty": 0.25
+            }))
+            .unwrap()
+        };
+
+        let cohere = request("cohere.command-r-plus-v1:0");
+        let result = converter.convert_request(&cohere).unwrap();
+        assert_eq!(
+            result.additional_model_request_fields,
+            Some(serde_json::json!({"presence_penalty": 0.5, "frequency_penalty": 0.25}))
+        );
+        assert!(converter.conversion_warnings(&cohere).is_empty());
+
+        // Claude has no penalties: nothing is sent and both are reported
+        let claude = request("gpt-4");
+        let result = converter.convert_request(&claude).unwrap();
+        assert_eq!(result.additional_model_request_fields, None);
+        let warnings = converter.conversion_warnings(&claude);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].field, "presence_penalty");
+    }
+
     #[test]
     fn test_temperature_clamping() {
         let converter = OpenAIToBedrockConverter::new();
@@ -885,11 +1152,26 @@ mod tests {
             n: None,
             logprobs: None,
             top_logprobs: None,
+            service_tier: None,
+            store: None,
+            metadata: None,
         };
 
         let config = converter.convert_inference_config(&request, 100);
 
         assert_eq!(config.temperature, Some(1.0));
+
+        let warnings = converter.conversion_warnings(&request);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, crate::converters::WarningKind::Clamped);
+        assert_eq!(warnings[0].field, "temperature");
+
+        // Linear scaling keeps the relative temperature instead
+        let converter = converter.with_temperature_scaling(TemperatureScaling::Linear);
+        let config = converter.convert_inference_config(&request, 100);
+        assert_eq!(config.temperature, Some(0.75));
+        let warnings = converter.conversion_warnings(&request);
+        assert_eq!(warnings[0].kind, crate::converters::WarningKind::Scaled);
     }
 
     #[test]
@@ -951,10 +1233,118 @@ mod tests {
             n: None,
             logprobs: None,
             top_logprobs: None,
+            service_tier: None,
+            store: None,
+            metadata: None,
         };
 
         let result = converter.convert_request(&request).unwrap();
 
         assert_eq!(result.inference_config.max_tokens, 2000);
     }
+
+    const CURSOR_AGENT: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/openai_clients/cursor_agent.json"
+    );
+    const CONTINUE_TOOLS: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/openai_clients/continue_tools.json"
+    );
+
+    /// Convert a recorded IDE request and check roles alternate without blank text
+    fn convert_fixture(fixture: &str) -> BedrockConverseRequest {
+        let recorded: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(fixture).unwrap()).unwrap();
+        let request: ChatCompletionRequest =
+            serde_json::from_value(recorded["body"].clone()).unwrap();
+        let result = OpenAIToBedrockConverter::new().convert_request(&request).unwrap();
+
+        for (i, message) in result.messages.iter().enumerate() {
+            assert_eq!(message.role, if i % 2 == 0 { "user" } else { "assistant" });
+            for block in &message.content {
+                if let BedrockContentBlock::Text { text, .. } = block {
+                    assert!(!text.trim().is_empty(), "blank text in turn {}", i);
+                }
+            }
+        }
+        result
+    }
+
+    fn tool_result_ids(message: &BedrockMessage) -> Vec<&str> {
+        message
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                BedrockContentBlock::ToolResult { tool_result, .. } => {
+                    Some(tool_result.tool_use_id.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_agent_fixture() {
+        let result = convert_fixture(CURSOR_AGENT);
+
+        // `max_tokens: null` falls back to the default
+        assert_eq!(result.inference_config.max_tokens, 4096);
+        assert_eq!(result.messages.len(), 5);
+
+        // The screenshot and the query that followed it are one turn
+        assert!(matches!(result.messages[0].content[0], BedrockContentBlock::Image { .. }));
+        assert!(result.messages[0].content[1].is_text());
+
+        // Parallel tool results share a turn
+        assert_eq!(
+            tool_result_ids(&result.messages[2]),
+            vec!["toolu_vrtx_01A", "toolu_vrtx_01B"]
+        );
+
+        // The empty assistant message is gone and the image-only turn gets text
+        assert_eq!(result.messages[3].content.len(), 1);
+        let last = &result.messages[4].content;
+        assert!(matches!(last[0], BedrockContentBlock::Image { .. }));
+        assert!(matches!(&last[1], BedrockContentBlock::Text { text, .. } if text == IMAGE_ONLY_TEXT));
+    }
+
+    #[test]
+    fn test_continue_tools_fixture() {
+        let result = convert_fixture(CONTINUE_TOOLS);
+        assert_eq!(result.messages.len(), 5);
+
+        // Blank text beside the tool call is dropped
+        assert_eq!(result.messages[1].content.len(), 1);
+        assert!(matches!(result.messages[1].content[0], BedrockContentBlock::ToolUse { .. }));
+
+        // Array content of a tool message becomes the result text
+        let BedrockContentBlock::ToolResult { tool_result, .. } = &result.messages[2].content[0] else {
+            panic!("Expected ToolResult block");
+        };
+        assert_eq!(tool_result.tool_use_id, "call_8c1f");
+        assert_eq!(tool_result.content[0]["text"], "main.py\nimport sys\n\nprint(sys.argv[1:])");
+    }
+
+    #[test]
+    fn test_tool_results_lead_merged_user_turn() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": "{}"}}
+                ]},
+                {"role": "user", "content": "also check hidden ones"},
+                {"role": "tool", "tool_call_id": "call_1", "content": "README.md"}
+            ]
+        }))
+        .unwrap();
+        let result = OpenAIToBedrockConverter::new().convert_request(&request).unwrap();
+
+        assert_eq!(result.messages.len(), 3);
+        assert_eq!(tool_result_ids(&result.messages[2]), vec!["call_1"]);
+        assert!(matches!(result.messages[2].content[0], BedrockContentBlock::ToolResult { .. }));
+        assert!(result.messages[2].content[1].is_text());
+    }
 }
@@ -10,7 +10,7 @@ use crate::schemas::gemini::{
 use crate::schemas::openai::{
     ChatCompletionRequest, ChatMessage, ChatRole, ContentPart, MessageContent, Tool, ToolChoice,
 };
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crate::utils::{split_data_url, validate_base64};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -138,6 +138,9 @@ impl OpenAIToGeminiConverter {
     }
 
     /// Convert OpenAI messages to Gemini contents
+    ///
+    /// Gemini matches function responses to calls by function name, so tool
+    /// messages are named after the call their `tool_call_id` refers to.
     fn convert_messages(
         &self,
         messages: &[&ChatMessage],
@@ -150,6 +153,20 @@ impl OpenAIToGeminiConverter {
             }
         }
 
+        let function_names: HashMap<&str, &str> = messages
+            .iter()
+            .filter_map(|m| m.tool_calls.as_ref())
+            .flatten()
+            .map(|call| (call.id.as_str(), call.function.name.as_str()))
+            .collect();
+        for part in contents.iter_mut().flat_map(|c| c.parts.iter_mut()) {
+            if let Some(ref mut response) = part.function_response {
+                if let Some(name) = function_names.get(response.name.as_str()) {
+                    response.name = name.to_string();
+                }
+            }
+        }
+
         Ok(contents)
     }
 
@@ -242,6 +259,16 @@ impl OpenAIToGeminiConverter {
                     let (media_type, data) = self.convert_image_url(&image_url.url)?;
                     result.push(Part::inline_data(&media_type, &data));
                 }
+                ContentPart::InputAudio { input_audio } => {
+                    // Gemini accepts audio natively
+                    let media_type = input_audio.media_type().ok_or_else(|| {
+                        OpenAIToGeminiError::InvalidContent(format!(
+                            "Unsupported audio format '{}'",
+                            input_audio.format
+                        ))
+                    })?;
+                    result.push(Part::inline_data(media_type, &input_audio.data));
+                }
             }
         }
 
@@ -264,28 +291,12 @@ impl OpenAIToGeminiConverter {
     /// Convert a data URL to media type and base64 data
     fn convert_data_url(&self, url: &str) -> Result<(String, String), OpenAIToGeminiError> {
         // Parse data URL: data:image/png;base64,<data>
-        let parts: Vec<&str> = url.splitn(2, ',').collect();
-        if parts.len() != 2 {
-            return Err(OpenAIToGeminiError::InvalidImageUrl(
-                "Invalid data URL format".to_string(),
-            ));
-        }
-
-        let metadata = parts[0];
-        let data = parts[1];
-
-        // Extract media type
-        let media_type = metadata
-            .strip_prefix("data:")
-            .and_then(|s| s.split(';').next())
-            .ok_or_else(|| {
-                OpenAIToGeminiError::InvalidImageUrl("Could not parse media type".to_string())
-            })?;
+        let (media_type, data) = split_data_url(url).ok_or_else(|| {
+            OpenAIToGeminiError::InvalidImageUrl("Invalid data URL format".to_string())
+        })?;
 
-        // Verify base64 is valid
-        BASE64
-            .decode(data)
-            .map_err(|e| OpenAIToGeminiError::Base64DecodeError(e.to_string()))?;
+        // Verify base64 is valid (without decoding into a buffer)
+        validate_base64(data).map_err(|e| OpenAIToGeminiError::Base64DecodeError(e.to_string()))?;
 
         Ok((media_type.to_string(), data.to_string()))
     }
@@ -351,7 +362,13 @@ impl OpenAIToGeminiConverter {
             top_k: None, // OpenAI doesn't have top_k
             max_output_tokens: Some(max_tokens),
             stop_sequences: request.stop.as_ref().map(|s| s.to_vec()),
-            candidate_count: None,
+            candidate_count: request.n.filter(|&n| n > 1),
+            presence_penalty: request.presence_penalty,
+            frequency_penalty: request.frequency_penalty,
+            response_logprobs: request.logprobs.filter(|&enabled| enabled),
+            logprobs: request.top_logprobs.filter(|_| request.logprobs == Some(true)),
+            // Gemini seeds are 32-bit; wrapping keeps larger seeds deterministic
+            seed: request.seed.map(|seed| seed as i32),
         }
     }
 
@@ -484,6 +501,40 @@ mod tests {
         assert_eq!(result.parts[0].text, Some("Hello".to_string()));
     }
 
+    #[test]
+    fn test_function_response_named_after_call() {
+        use crate::schemas::openai::{FunctionCall, ToolCall};
+
+        let converter = OpenAIToGeminiConverter::new();
+        let assistant = ChatMessage {
+            role: ChatRole::Assistant,
+            content: None,
+            name: None,
+            tool_calls: Some(vec![ToolCall {
+                id: "call_abc".to_string(),
+                tool_type: "function".to_string(),
+                function: FunctionCall {
+                    name: "get_weather".to_string(),
+                    arguments: r#"{"city":"Paris"}"#.to_string(),
+                },
+            }]),
+            tool_call_id: None,
+        };
+        let tool = ChatMessage {
+            role: ChatRole::Tool,
+            content: Some(MessageContent::Text("Sunny".to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: Some("call_abc".to_string()),
+        };
+
+        let contents = converter.convert_messages(&[&assistant, &tool]).unwrap();
+
+        let response = contents[1].parts[0].function_response.as_ref().unwrap();
+        assert_eq!(response.name, "get_weather");
+        assert_eq!(response.response["result"], "Sunny");
+    }
+
     #[test]
     fn test_convert_generation_config() {
         let converter = OpenAIToGeminiConverter::new();
@@ -508,6 +559,9 @@ mod tests {
             n: None,
             logprobs: None,
             top_logprobs: None,
+            service_tier: None,
+            store: None,
+            metadata: None,
         };
 
         let config = converter.convert_generation_config(&request);
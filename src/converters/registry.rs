@@ -0,0 +1,534 @@
+//! Converter trait and registry
+//!
+//! Every converter in this module implements [`Converter`]: it turns one
+//! format's request or response into another format, identified by its
+//! (source, target) [`ApiFormat`] pair. Requests go from a client format to a
+//! backend format, responses the other way, so the pair alone says which
+//! direction a converter runs in.
+//!
+//! [`ConverterRegistry`] holds converters behind that key and runs them on
+//! JSON bodies, which lets other services embed the conversion logic without
+//! the server:
+//!
+//! ```rust,ignore
+//! use llm_api_converter::converters::{ApiFormat, ConversionContext, ConverterRegistry};
+//!
+//! let registry = ConverterRegistry::with_builtin();
+//! let converse = registry.convert_json(
+//!     ApiFormat::Anthropic,
+//!     ApiFormat::Bedrock,
+//!     &anthropic_request,
+//!     &ConversionContext::new(),
+//! )?;
+//! ```
+//!
+//! # Stability
+//!
+//! [`Converter`], [`ConverterRegistry`], [`ApiFormat`], [`ConversionContext`]
+//! and [`ConverterError`] are the crate's public conversion API and follow
+//! semver: breaking changes to them only land in a major release. The enums
+//! and the context are `#[non_exhaustive]` so formats and options can be
+//! added in minor releases. The concrete converter types remain available
+//! but their inherent methods are not covered by this guarantee.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+use super::{
+    AnthropicToBedrockConverter, AnthropicToGeminiConverter, BedrockToAnthropicConverter,
+    BedrockToOpenAIConverter, GeminiToAnthropicConverter, GeminiToOpenAIConverter,
+    OpenAIToBedrockConverter, OpenAIToGeminiConverter,
+};
+use crate::schemas::anthropic::{MessageRequest, MessageResponse};
+use crate::schemas::bedrock::{BedrockConverseRequest, BedrockConverseResponse};
+use crate::schemas::gemini::{GeminiRequest, GeminiResponse};
+use crate::schemas::openai::{ChatCompletionRequest, ChatCompletionResponse};
+
+/// Wire format of a request or response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ApiFormat {
+    /// Anthropic Messages API
+    Anthropic,
+    /// OpenAI Chat Completions API
+    #[serde(rename = "openai")]
+    OpenAI,
+    /// AWS Bedrock Converse API
+    Bedrock,
+    /// Google Gemini generateContent API
+    Gemini,
+}
+
+impl fmt::Display for ApiFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ApiFormat::Anthropic => "anthropic",
+            ApiFormat::OpenAI => "openai",
+            ApiFormat::Bedrock => "bedrock",
+            ApiFormat::Gemini => "gemini",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Per-call options for a conversion
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ConversionContext {
+    /// Model name the client asked for, echoed in converted responses
+    pub model: Option<String>,
+}
+
+impl ConversionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the client's model name (used by response conversions)
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or_default()
+    }
+}
+
+/// Gemini request together with the Gemini model it is sent to
+///
+/// The model is part of the Gemini URL rather than the body, so request
+/// conversions to Gemini return both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiCall {
+    pub model: String,
+    pub request: GeminiRequest,
+}
+
+/// Errors from the conversion API
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ConverterError {
+    /// No converter is registered for the pair
+    #[error("No converter from {from} to {to}")]
+    Unsupported { from: ApiFormat, to: ApiFormat },
+
+    /// The input is not a valid body of the source format
+    #[error("Invalid {format} input: {message}")]
+    InvalidInput { format: ApiFormat, message: String },
+
+    /// The converter rejected the input
+    #[error(transparent)]
+    Conversion(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The output could not be serialized
+    #[error("Failed to serialize {format} output: {message}")]
+    Serialize { format: ApiFormat, message: String },
+}
+
+/// A conversion from one API format to another
+pub trait Converter: Send + Sync {
+    /// Body this converter reads
+    type Input: DeserializeOwned;
+    /// Body this converter produces
+    type Output: Serialize;
+    /// Why a conversion failed
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Format of the input
+    fn source(&self) -> ApiFormat;
+
+    /// Format of the output
+    fn target(&self) -> ApiFormat;
+
+    /// Convert one body
+    fn convert(
+        &self,
+        input: &Self::Input,
+        context: &ConversionContext,
+    ) -> Result<Self::Output, Self::Error>;
+}
+
+/// Type-erased [`Converter`] working on JSON, as stored in the registry
+pub trait JsonConverter: Send + Sync {
+    fn source(&self) -> ApiFormat;
+    fn target(&self) -> ApiFormat;
+    fn convert_json(
+        &self,
+        input: &Value,
+        context: &ConversionContext,
+    ) -> Result<Value, ConverterError>;
+}
+
+impl<C: Converter> JsonConverter for C {
+    fn source(&self) -> ApiFormat {
+        Converter::source(self)
+    }
+
+    fn target(&self) -> ApiFormat {
+        Converter::target(self)
+    }
+
+    fn convert_json(
+        &self,
+        input: &Value,
+        context: &ConversionContext,
+    ) -> Result<Value, ConverterError> {
+        let input: C::Input =
+            serde_json::from_value(input.clone()).map_err(|e| ConverterError::InvalidInput {
+                format: Converter::source(self),
+                message: e.to_string(),
+            })?;
+        let output = self
+            .convert(&input, context)
+            .map_err(|e| ConverterError::Conversion(Box::new(e)))?;
+        serde_json::to_value(&output).map_err(|e| ConverterError::Serialize {
+            format: Converter::target(self),
+            message: e.to_string(),
+        })
+    }
+}
+
+/// Converters keyed by (source, target) format
+#[derive(Clone, Default)]
+pub struct ConverterRegistry {
+    converters: HashMap<(ApiFormat, ApiFormat), Arc<dyn JsonConverter>>,
+}
+
+impl fmt::Debug for ConverterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConverterRegistry")
+            .field("pairs", &self.pairs())
+            .finish()
+    }
+}
+
+impl ConverterRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry holding every built-in converter
+    pub fn with_builtin() -> Self {
+        Self::new()
+            .with_converter(AnthropicToBedrockConverter::new())
+            .with_converter(BedrockToAnthropicConverter::new())
+            .with_converter(AnthropicToGeminiConverter::new())
+            .with_converter(GeminiToAnthropicConverter::new())
+            .with_converter(OpenAIToBedrockConverter::new())
+            .with_converter(BedrockToOpenAIConverter::new())
+            .with_converter(OpenAIToGeminiConverter::new())
+            .with_converter(GeminiToOpenAIConverter::new())
+    }
+
+    /// Add a converter, replacing any registered for the same pair
+    pub fn with_converter<C: Converter + 'static>(mut self, converter: C) -> Self {
+        self.register(converter);
+        self
+    }
+
+    /// Add a converter, replacing any registered for the same pair
+    pub fn register<C: Converter + 'static>(&mut self, converter: C) {
+        let key = (Converter::source(&converter), Converter::target(&converter));
+        self.converters.insert(key, Arc::new(converter));
+    }
+
+    /// Converter for a pair, if one is registered
+    pub fn get(&self, source: ApiFormat, target: ApiFormat) -> Option<Arc<dyn JsonConverter>> {
+        self.converters.get(&(source, target)).cloned()
+    }
+
+    /// Registered (source, target) pairs, sorted
+    pub fn pairs(&self) -> Vec<(ApiFormat, ApiFormat)> {
+        let mut pairs: Vec<_> = self.converters.keys().copied().collect();
+        pairs.sort_by_key(|(source, target)| (source.to_string(), target.to_string()));
+        pairs
+    }
+
+    /// Convert a JSON body from `source` to `target` format
+    pub fn convert_json(
+        &self,
+        source: ApiFormat,
+        target: ApiFormat,
+        input: &Value,
+        context: &ConversionContext,
+    ) -> Result<Value, ConverterError> {
+        self.get(source, target)
+            .ok_or(ConverterError::Unsupported {
+                from: source,
+                to: target,
+            })?
+            .convert_json(input, context)
+    }
+}
+
+// ============================================================================
+// Built-in converters
+// ============================================================================
+
+impl Converter for AnthropicToBedrockConverter {
+    type Input = MessageRequest;
+    type Output = BedrockConverseRequest;
+    type Error = super::ConversionError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Anthropic
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Bedrock
+    }
+
+    fn convert(
+        &self,
+        input: &MessageRequest,
+        _context: &ConversionContext,
+    ) -> Result<BedrockConverseRequest, Self::Error> {
+        self.convert_request(input)
+    }
+}
+
+impl Converter for BedrockToAnthropicConverter {
+    type Input = BedrockConverseResponse;
+    type Output = MessageResponse;
+    type Error = super::ResponseConversionError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Bedrock
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Anthropic
+    }
+
+    fn convert(
+        &self,
+        input: &BedrockConverseResponse,
+        context: &ConversionContext,
+    ) -> Result<MessageResponse, Self::Error> {
+        self.convert_response(input, context.model())
+    }
+}
+
+impl Converter for AnthropicToGeminiConverter {
+    type Input = MessageRequest;
+    type Output = GeminiCall;
+    type Error = super::AnthropicToGeminiError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Anthropic
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Gemini
+    }
+
+    fn convert(
+        &self,
+        input: &MessageRequest,
+        _context: &ConversionContext,
+    ) -> Result<GeminiCall, Self::Error> {
+        let (model, request) = self.convert_request(input)?;
+        Ok(GeminiCall { model, request })
+    }
+}
+
+impl Converter for GeminiToAnthropicConverter {
+    type Input = GeminiResponse;
+    type Output = MessageResponse;
+    type Error = super::GeminiToAnthropicError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Gemini
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Anthropic
+    }
+
+    fn convert(
+        &self,
+        input: &GeminiResponse,
+        context: &ConversionContext,
+    ) -> Result<MessageResponse, Self::Error> {
+        self.convert_response(input, context.model())
+    }
+}
+
+impl Converter for OpenAIToBedrockConverter {
+    type Input = ChatCompletionRequest;
+    type Output = BedrockConverseRequest;
+    type Error = super::OpenAIConversionError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::OpenAI
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Bedrock
+    }
+
+    fn convert(
+        &self,
+        input: &ChatCompletionRequest,
+        _context: &ConversionContext,
+    ) -> Result<BedrockConverseRequest, Self::Error> {
+        self.convert_request(input)
+    }
+}
+
+impl Converter for BedrockToOpenAIConverter {
+    type Input = BedrockConverseResponse;
+    type Output = ChatCompletionResponse;
+    type Error = super::OpenAIResponseConversionError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Bedrock
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::OpenAI
+    }
+
+    fn convert(
+        &self,
+        input: &BedrockConverseResponse,
+        context: &ConversionContext,
+    ) -> Result<ChatCompletionResponse, Self::Error> {
+        self.convert_response(input, context.model())
+    }
+}
+
+impl Converter for OpenAIToGeminiConverter {
+    type Input = ChatCompletionRequest;
+    type Output = GeminiCall;
+    type Error = super::OpenAIToGeminiError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::OpenAI
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::Gemini
+    }
+
+    fn convert(
+        &self,
+        input: &ChatCompletionRequest,
+        _context: &ConversionContext,
+    ) -> Result<GeminiCall, Self::Error> {
+        let (model, request) = self.convert_request(input)?;
+        Ok(GeminiCall { model, request })
+    }
+}
+
+impl Converter for GeminiToOpenAIConverter {
+    type Input = GeminiResponse;
+    type Output = ChatCompletionResponse;
+    type Error = super::GeminiToOpenAIError;
+
+    fn source(&self) -> ApiFormat {
+        ApiFormat::Gemini
+    }
+
+    fn target(&self) -> ApiFormat {
+        ApiFormat::OpenAI
+    }
+
+    fn convert(
+        &self,
+        input: &GeminiResponse,
+        context: &ConversionContext,
+    ) -> Result<ChatCompletionResponse, Self::Error> {
+        self.convert_response(input, context.model())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_pairs() {
+        let registry = ConverterRegistry::with_builtin();
+        assert_eq!(registry.pairs().len(), 8);
+        assert!(registry
+            .get(ApiFormat::Anthropic, ApiFormat::Bedrock)
+            .is_some());
+        assert!(registry
+            .get(ApiFormat::Bedrock, ApiFormat::Gemini)
+            .is_none());
+    }
+
+    #[test]
+    fn test_convert_json_round_trip() {
+        let registry = ConverterRegistry::with_builtin();
+        let request = json!({
+            "model": "claude-sonnet-4-5-20250929",
+            "max_tokens": 256,
+            "messages": [{"role": "user", "content": "Hello"}]
+        });
+
+        let converse = registry
+            .convert_json(
+                ApiFormat::Anthropic,
+                ApiFormat::Bedrock,
+                &request,
+                &ConversionContext::new(),
+            )
+            .unwrap();
+        assert_eq!(converse["messages"][0]["content"][0]["text"], "Hello");
+
+        let response = json!({
+            "output": {"message": {"role": "assistant", "content": [{"text": "Hi"}]}},
+            "stopReason": "end_turn",
+            "usage": {"inputTokens": 3, "outputTokens": 1, "totalTokens": 4}
+        });
+        let context = ConversionContext::new().with_model("claude-sonnet-4-5-20250929");
+        let message = registry
+            .convert_json(
+                ApiFormat::Bedrock,
+                ApiFormat::Anthropic,
+                &response,
+                &context,
+            )
+            .unwrap();
+        assert_eq!(message["model"], "claude-sonnet-4-5-20250929");
+        assert_eq!(message["content"][0]["text"], "Hi");
+    }
+
+    #[test]
+    fn test_unsupported_and_invalid_input() {
+        let registry = ConverterRegistry::with_builtin();
+        let context = ConversionContext::new();
+
+        let err = registry
+            .convert_json(ApiFormat::Gemini, ApiFormat::Bedrock, &json!({}), &context)
+            .unwrap_err();
+        assert!(matches!(err, ConverterError::Unsupported { .. }));
+
+        let err = registry
+            .convert_json(
+                ApiFormat::OpenAI,
+                ApiFormat::Gemini,
+                &json!({"x": 1}),
+                &context,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ConverterError::InvalidInput {
+                format: ApiFormat::OpenAI,
+                ..
+            }
+        ));
+    }
+}
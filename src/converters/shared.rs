@@ -0,0 +1,127 @@
+//! Shared converter instances
+//!
+//! Converters that carry model-mapping tables are built once and shared across
+//! requests instead of being rebuilt (and their `HashMap`s repopulated) in
+//! every handler. Mapping changes are applied by building a new converter and
+//! swapping it in; requests already holding the previous `Arc` finish with the
+//! mappings they started with.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::config::TemperatureScaling;
+use crate::db::models::ModelMapping;
+
+use super::{AnthropicToGeminiConverter, OpenAIToBedrockConverter, OpenAIToGeminiConverter};
+
+/// Converter instances shared through `AppState`
+#[derive(Debug)]
+pub struct SharedConverters {
+    openai_to_bedrock: RwLock<Arc<OpenAIToBedrockConverter>>,
+    anthropic_to_gemini: Arc<AnthropicToGeminiConverter>,
+    openai_to_gemini: Arc<OpenAIToGeminiConverter>,
+    /// Kept so reloaded OpenAI converters map temperatures the same way
+    temperature_scaling: TemperatureScaling,
+    /// Incremented every time the mappings are replaced
+    generation: AtomicU64,
+}
+
+impl Default for SharedConverters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedConverters {
+    /// Create shared converters with the built-in model mappings
+    pub fn new() -> Self {
+        Self::with_temperature_scaling(TemperatureScaling::default())
+    }
+
+    /// Create shared converters whose OpenAI -> Bedrock converter maps
+    /// temperatures with `scaling`
+    pub fn with_temperature_scaling(scaling: TemperatureScaling) -> Self {
+        Self {
+            openai_to_bedrock: RwLock::new(Arc::new(
+                OpenAIToBedrockConverter::new().with_temperature_scaling(scaling),
+            )),
+            anthropic_to_gemini: Arc::new(AnthropicToGeminiConverter::new()),
+            openai_to_gemini: Arc::new(OpenAIToGeminiConverter::new()),
+            temperature_scaling: scaling,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the current OpenAI -> Bedrock converter
+    pub fn openai_to_bedrock(&self) -> Arc<OpenAIToBedrockConverter> {
+        self.openai_to_bedrock
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Get the Anthropic -> Gemini converter
+    pub fn anthropic_to_gemini(&self) -> Arc<AnthropicToGeminiConverter> {
+        self.anthropic_to_gemini.clone()
+    }
+
+    /// Get the OpenAI -> Gemini converter
+    pub fn openai_to_gemini(&self) -> Arc<OpenAIToGeminiConverter> {
+        self.openai_to_gemini.clone()
+    }
+
+    /// Number of times the mappings have been replaced
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Replace the DB-backed model mappings (invalidation hook)
+    ///
+    /// The built-in mappings are kept; stored mappings override them.
+    pub fn apply_model_mappings(&self, mappings: &[ModelMapping]) {
+        let mut converter =
+            OpenAIToBedrockConverter::new().with_temperature_scaling(self.temperature_scaling);
+        for mapping in mappings {
+            converter.add_model_mapping(
+                mapping.anthropic_model_id.clone(),
+                mapping.bedrock_model_id.clone(),
+            );
+        }
+
+        *self
+            .openai_to_bedrock
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Arc::new(converter);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_model_mappings_swaps_converter() {
+        let shared = SharedConverters::new();
+        let before = shared.openai_to_bedrock();
+        let default_gpt4 = before.convert_model_id("gpt-4");
+
+        shared.apply_model_mappings(&[ModelMapping {
+            anthropic_model_id: "gpt-4".to_string(),
+            bedrock_model_id: "anthropic.claude-sonnet-4-20250514-v1:0".to_string(),
+        }]);
+
+        assert_eq!(shared.generation(), 1);
+        assert_eq!(
+            shared.openai_to_bedrock().convert_model_id("gpt-4"),
+            "anthropic.claude-sonnet-4-20250514-v1:0"
+        );
+        // Built-in mappings survive a reload
+        assert_eq!(
+            shared.openai_to_bedrock().convert_model_id("gpt-4-turbo"),
+            before.convert_model_id("gpt-4-turbo")
+        );
+        // In-flight holders keep the converter they started with
+        assert_eq!(before.convert_model_id("gpt-4"), default_gpt4);
+    }
+}
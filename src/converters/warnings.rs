@@ -0,0 +1,101 @@
+//! Conversion warnings
+//!
+//! Not everything a client sends has an equivalent on the Converse API.
+//! Rather than silently dropping or adjusting those fields, converters report
+//! a [`ConversionWarning`] for each one. Handlers log the warnings and return
+//! them to the client as a JSON array in the [`CONVERSION_WARNINGS_HEADER`]
+//! response header.
+
+use serde::Serialize;
+
+/// Response header carrying the warnings as a JSON array
+pub const CONVERSION_WARNINGS_HEADER: &str = "x-conversion-warnings";
+
+/// What happened to a request field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The field or content was removed from the upstream request
+    Dropped,
+    /// The value was adjusted to fit the upstream range
+    Clamped,
+    /// The value was rescaled to the upstream range, keeping its relative position
+    Scaled,
+    /// The parameter has no upstream equivalent and had no effect
+    Ignored,
+}
+
+/// A field the converter could not carry over unchanged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConversionWarning {
+    pub kind: WarningKind,
+    /// Request field the warning is about, e.g. "temperature" or "messages[2].content[0]"
+    pub field: String,
+    pub message: String,
+}
+
+impl ConversionWarning {
+    pub fn dropped(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(WarningKind::Dropped, field, message)
+    }
+
+    pub fn clamped(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(WarningKind::Clamped, field, message)
+    }
+
+    pub fn scaled(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(WarningKind::Scaled, field, message)
+    }
+
+    pub fn ignored(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(WarningKind::Ignored, field, message)
+    }
+
+    fn new(kind: WarningKind, field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Compact JSON for the response header; `None` when there is nothing to report
+pub fn header_value(warnings: &[ConversionWarning]) -> Option<String> {
+    if warnings.is_empty() {
+        return None;
+    }
+    serde_json::to_string(warnings).ok()
+}
+
+/// Log each warning against the request it came from
+pub fn log_warnings(request_id: &str, warnings: &[ConversionWarning]) {
+    for warning in warnings {
+        tracing::warn!(
+            request_id = %request_id,
+            kind = ?warning.kind,
+            field = %warning.field,
+            "Conversion warning: {}",
+            warning.message
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_value() {
+        assert_eq!(header_value(&[]), None);
+
+        let warnings = vec![ConversionWarning::clamped(
+            "temperature",
+            "Clamped from 1.5 to 1.0",
+        )];
+        assert_eq!(
+            header_value(&warnings).unwrap(),
+            r#"[{"kind":"clamped","field":"temperature","message":"Clamped from 1.5 to 1.0"}]"#
+        );
+    }
+}
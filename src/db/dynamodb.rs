@@ -60,6 +60,16 @@ impl DynamoDbClient {
         &self.settings.dynamodb_model_pricing_table
     }
 
+    /// Get the feature flags table name
+    pub fn feature_flags_table(&self) -> &str {
+        &self.settings.dynamodb_feature_flags_table
+    }
+
+    /// Get the inference presets table name
+    pub fn inference_presets_table(&self) -> &str {
+        &self.settings.dynamodb_inference_presets_table
+    }
+
     /// Check if the DynamoDB connection is healthy
     ///
     /// Performs a simple list_tables operation to verify connectivity.
@@ -3,9 +3,13 @@
 use std::sync::Arc;
 
 use crate::db::models::{ApiKey, UsageRecord};
-use crate::db::repositories::{ApiKeyRepository, ModelMappingRepository, UsageRepository};
-use crate::db::storage::{StorageBackend, StorageError};
+use crate::db::repositories::{
+    ApiKeyError, ApiKeyRepository, InferencePresetError, InferencePresetRepository,
+    ModelMappingRepository, UsageRepository,
+};
+use crate::db::storage::{KeyQuota, StorageBackend, StorageError};
 use crate::db::DynamoDbClient;
+use crate::services::InferencePreset;
 
 /// DynamoDB implementation of StorageBackend.
 ///
@@ -14,6 +18,7 @@ pub struct DynamoDbBackend {
     api_keys: ApiKeyRepository,
     usage: UsageRepository,
     model_mapping: ModelMappingRepository,
+    inference_presets: InferencePresetRepository,
     client: Arc<DynamoDbClient>,
 }
 
@@ -23,6 +28,7 @@ impl DynamoDbBackend {
             api_keys: ApiKeyRepository::new(client.clone()),
             usage: UsageRepository::new(client.clone()),
             model_mapping: ModelMappingRepository::new(client.clone()),
+            inference_presets: InferencePresetRepository::new(client.clone()),
             client,
         }
     }
@@ -33,6 +39,22 @@ impl DynamoDbBackend {
     }
 }
 
+/// Map a repository error, keeping NotFound distinguishable
+fn key_error(err: ApiKeyError) -> StorageError {
+    match err {
+        ApiKeyError::NotFound => StorageError::NotFound,
+        other => StorageError::Query(other.to_string()),
+    }
+}
+
+/// Map a repository error, keeping unreadable definitions distinguishable
+fn preset_error(err: InferencePresetError) -> StorageError {
+    match err {
+        InferencePresetError::Parse(e) => StorageError::Parse(e),
+        other => StorageError::Query(other.to_string()),
+    }
+}
+
 #[async_trait::async_trait]
 impl StorageBackend for DynamoDbBackend {
     async fn validate_api_key(&self, key: &str) -> Result<Option<ApiKey>, StorageError> {
@@ -49,6 +71,13 @@ impl StorageBackend for DynamoDbBackend {
             .map_err(|e| StorageError::Query(e.to_string()))
     }
 
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        self.api_keys
+            .create_api_key(key)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))
+    }
+
     async fn increment_budget_used(&self, key: &str, amount: f64) -> Result<bool, StorageError> {
         self.api_keys
             .increment_budget_used(key, amount)
@@ -67,6 +96,72 @@ impl StorageBackend for DynamoDbBackend {
             .map_err(|e| StorageError::Query(e.to_string()))
     }
 
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, StorageError> {
+        self.api_keys.list_api_keys().await.map_err(key_error)
+    }
+
+    async fn set_api_key_quota(&self, key: &str, quota: &KeyQuota) -> Result<ApiKey, StorageError> {
+        self.api_keys
+            .set_quota(key, quota)
+            .await
+            .map_err(key_error)?
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn swap_monthly_budget(
+        &self,
+        key: &str,
+        expected: f64,
+        budget: f64,
+    ) -> Result<bool, StorageError> {
+        self.api_keys
+            .swap_monthly_budget(key, expected, budget)
+            .await
+            .map_err(key_error)
+    }
+
+    async fn extend_api_key(&self, key: &str, expires_at: i64) -> Result<ApiKey, StorageError> {
+        self.api_keys
+            .extend_api_key(key, expires_at)
+            .await
+            .map_err(key_error)?
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn upgrade_api_key(
+        &self,
+        key: &str,
+        service_tier: &str,
+        rate_limit: Option<i32>,
+        monthly_budget: Option<f64>,
+    ) -> Result<ApiKey, StorageError> {
+        self.api_keys
+            .upgrade_api_key(key, service_tier, rate_limit, monthly_budget)
+            .await
+            .map_err(key_error)?
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn record_key_activity(
+        &self,
+        key: &str,
+        last_used_at: i64,
+        request_delta: i64,
+        recent_source_ips: &[String],
+    ) -> Result<(), StorageError> {
+        self.api_keys
+            .record_activity(key, last_used_at, request_delta, recent_source_ips)
+            .await
+            .map_err(key_error)
+    }
+
+    async fn rotate_api_key(&self, old_key: &str, replacement: &ApiKey) -> Result<(), StorageError> {
+        self.api_keys
+            .rotate_api_key(old_key, replacement)
+            .await
+            .map_err(key_error)
+    }
+
     async fn record_usage(&self, record: &UsageRecord) -> Result<(), StorageError> {
         self.usage
             .record_usage(record)
@@ -101,6 +196,21 @@ impl StorageBackend for DynamoDbBackend {
             .map_err(|e| StorageError::Query(e.to_string()))
     }
 
+    async fn list_inference_presets(&self) -> Result<Vec<InferencePreset>, StorageError> {
+        self.inference_presets.list_all().await.map_err(preset_error)
+    }
+
+    async fn put_inference_preset(&self, preset: &InferencePreset) -> Result<(), StorageError> {
+        self.inference_presets.put(preset).await.map_err(preset_error)
+    }
+
+    async fn delete_inference_preset(&self, name: &str) -> Result<(), StorageError> {
+        match self.inference_presets.delete(name).await.map_err(preset_error)? {
+            true => Ok(()),
+            false => Err(StorageError::NotFound),
+        }
+    }
+
     async fn health_check(&self) -> bool {
         self.client.health_check().await
     }
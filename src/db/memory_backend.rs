@@ -0,0 +1,362 @@
+//! In-memory storage backend.
+//!
+//! Keeps keys, usage, model mappings and inference presets in process
+//! memory, for local development, tests and single-instance deployments that
+//! seed their keys through the admin API at startup. Nothing survives a restart, and
+//! replicas do not share state.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use chrono::Utc;
+
+use crate::db::models::{ApiKey, UsageRecord};
+use crate::db::storage::{KeyQuota, StorageBackend, StorageError};
+use crate::services::InferencePreset;
+
+/// In-memory implementation of StorageBackend.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    api_keys: RwLock<HashMap<String, ApiKey>>,
+    usage: RwLock<Vec<UsageRecord>>,
+    model_mappings: RwLock<HashMap<String, String>>,
+    inference_presets: RwLock<HashMap<String, InferencePreset>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `change` to a stored key and return the updated key
+    fn update_key(&self, key: &str, change: impl FnOnce(&mut ApiKey)) -> Result<ApiKey, StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        let api_key = keys.get_mut(key).ok_or(StorageError::NotFound)?;
+        change(api_key);
+        api_key.updated_at = Some(Utc::now().timestamp());
+        Ok(api_key.clone())
+    }
+}
+
+fn poisoned<T>(_: T) -> StorageError {
+    StorageError::Connection("in-memory store lock poisoned".to_string())
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for MemoryBackend {
+    async fn validate_api_key(&self, key: &str) -> Result<Option<ApiKey>, StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        let Some(api_key) = keys.get_mut(key) else {
+            return Ok(None);
+        };
+        if api_key.is_active {
+            return Ok(Some(api_key.clone()));
+        }
+
+        // Auto-reactivate if budget_exceeded and new month
+        let current_month = Utc::now().format("%Y-%m").to_string();
+        if api_key.is_budget_exceeded()
+            && api_key.budget_mtd_month.as_deref() != Some(&current_month)
+        {
+            api_key.is_active = true;
+            api_key.budget_used_mtd = 0.0;
+            api_key.budget_mtd_month = Some(current_month);
+            api_key.deactivated_reason = None;
+            api_key.updated_at = Some(Utc::now().timestamp());
+            return Ok(Some(api_key.clone()));
+        }
+
+        Ok(None)
+    }
+
+    async fn get_api_key(&self, key: &str) -> Result<Option<ApiKey>, StorageError> {
+        Ok(self.api_keys.read().map_err(poisoned)?.get(key).cloned())
+    }
+
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        if keys.contains_key(&key.api_key) {
+            return Err(StorageError::Query("API key already exists".to_string()));
+        }
+        keys.insert(key.api_key.clone(), key.clone());
+        Ok(())
+    }
+
+    async fn increment_budget_used(&self, key: &str, amount: f64) -> Result<bool, StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        let api_key = keys.get_mut(key).ok_or(StorageError::NotFound)?;
+
+        let current_month = Utc::now().format("%Y-%m").to_string();
+        if api_key.budget_mtd_month.as_deref() != Some(&current_month) {
+            api_key.budget_used_mtd = 0.0;
+            api_key.budget_mtd_month = Some(current_month);
+        }
+        api_key.budget_used += amount;
+        api_key.budget_used_mtd += amount;
+        api_key.updated_at = Some(Utc::now().timestamp());
+
+        let budget_exceeded = api_key
+            .monthly_budget
+            .is_some_and(|budget| api_key.budget_used_mtd >= budget);
+        if budget_exceeded {
+            api_key.is_active = false;
+            api_key.deactivated_reason = Some("budget_exceeded".to_string());
+        }
+        Ok(budget_exceeded)
+    }
+
+    async fn deactivate_api_key(
+        &self,
+        key: &str,
+        reason: Option<&str>,
+    ) -> Result<(), StorageError> {
+        if let Some(api_key) = self.api_keys.write().map_err(poisoned)?.get_mut(key) {
+            api_key.is_active = false;
+            api_key.deactivated_reason = reason.map(str::to_string);
+            api_key.updated_at = Some(Utc::now().timestamp());
+        }
+        Ok(())
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, StorageError> {
+        Ok(self.api_keys.read().map_err(poisoned)?.values().cloned().collect())
+    }
+
+    async fn set_api_key_quota(&self, key: &str, quota: &KeyQuota) -> Result<ApiKey, StorageError> {
+        self.update_key(key, |api_key| {
+            if let Some(rate_limit) = quota.rate_limit {
+                api_key.rate_limit = rate_limit;
+            }
+            if let Some(tpm_limit) = quota.tpm_limit {
+                api_key.tpm_limit = tpm_limit;
+            }
+            if let Some(budget) = quota.monthly_budget {
+                api_key.monthly_budget = budget;
+            }
+        })
+    }
+
+    async fn swap_monthly_budget(
+        &self,
+        key: &str,
+        expected: f64,
+        budget: f64,
+    ) -> Result<bool, StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        match keys.get_mut(key) {
+            Some(api_key) if api_key.monthly_budget == Some(expected) => {
+                api_key.monthly_budget = Some(budget);
+                api_key.updated_at = Some(Utc::now().timestamp());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn extend_api_key(&self, key: &str, expires_at: i64) -> Result<ApiKey, StorageError> {
+        self.update_key(key, |api_key| api_key.expires_at = Some(expires_at))
+    }
+
+    async fn upgrade_api_key(
+        &self,
+        key: &str,
+        service_tier: &str,
+        rate_limit: Option<i32>,
+        monthly_budget: Option<f64>,
+    ) -> Result<ApiKey, StorageError> {
+        self.update_key(key, |api_key| {
+            api_key.service_tier = service_tier.to_string();
+            api_key.expires_at = None;
+            api_key.allowed_models.clear();
+            if let Some(rate_limit) = rate_limit {
+                api_key.rate_limit = rate_limit;
+            }
+            if monthly_budget.is_some() {
+                api_key.monthly_budget = monthly_budget;
+            }
+        })
+    }
+
+    async fn record_key_activity(
+        &self,
+        key: &str,
+        last_used_at: i64,
+        request_delta: i64,
+        recent_source_ips: &[String],
+    ) -> Result<(), StorageError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        let api_key = keys.get_mut(key).ok_or(StorageError::NotFound)?;
+        api_key.last_used_at = Some(last_used_at);
+        api_key.request_count += request_delta;
+        api_key.recent_source_ips = recent_source_ips.to_vec();
+        Ok(())
+    }
+
+    async fn record_usage(&self, record: &UsageRecord) -> Result<(), StorageError> {
+        self.usage.write().map_err(poisoned)?.push(record.clone());
+        Ok(())
+    }
+
+    async fn get_usage_by_api_key(
+        &self,
+        key: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+        limit: Option<i32>,
+    ) -> Result<Vec<UsageRecord>, StorageError> {
+        let usage = self.usage.read().map_err(poisoned)?;
+        // Newest first, like the DynamoDB and SQLite queries
+        let records = usage
+            .iter()
+            .rev()
+            .filter(|r| r.api_key == key)
+            .filter(|r| start.map_or(true, |start| r.timestamp.as_str() >= start))
+            .filter(|r| end.map_or(true, |end| r.timestamp.as_str() <= end))
+            .take(limit.map_or(usize::MAX, |n| n.max(0) as usize))
+            .cloned()
+            .collect();
+        Ok(records)
+    }
+
+    async fn get_model_mapping(&self, model_id: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.model_mappings.read().map_err(poisoned)?.get(model_id).cloned())
+    }
+
+    async fn set_model_mapping(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.model_mappings
+            .write()
+            .map_err(poisoned)?
+            .insert(from.to_string(), to.to_string());
+        Ok(())
+    }
+
+    async fn list_inference_presets(&self) -> Result<Vec<InferencePreset>, StorageError> {
+        Ok(self.inference_presets.read().map_err(poisoned)?.values().cloned().collect())
+    }
+
+    async fn put_inference_preset(&self, preset: &InferencePreset) -> Result<(), StorageError> {
+        self.inference_presets
+            .write()
+            .map_err(poisoned)?
+            .insert(preset.name.clone(), preset.clone());
+        Ok(())
+    }
+
+    async fn delete_inference_preset(&self, name: &str) -> Result<(), StorageError> {
+        self.inference_presets
+            .write()
+            .map_err(poisoned)?
+            .remove(name)
+            .map(|_| ())
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(api_key: &str, monthly_budget: Option<f64>) -> ApiKey {
+        serde_json::from_value(serde_json::json!({
+            "api_key": api_key,
+            "user_id": "test_user",
+            "name": "Test Key",
+            "created_at": 1_700_000_000,
+            "is_active": true,
+            "rate_limit": 100,
+            "service_tier": "default",
+            "monthly_budget": monthly_budget
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_key_lifecycle() {
+        let backend = MemoryBackend::new();
+        backend.create_api_key(&key("sk-test", Some(1.0))).await.unwrap();
+        assert!(backend.create_api_key(&key("sk-test", None)).await.is_err());
+
+        assert!(backend.validate_api_key("sk-test").await.unwrap().is_some());
+        assert!(backend.validate_api_key("sk-missing").await.unwrap().is_none());
+
+        assert!(!backend.increment_budget_used("sk-test", 0.4).await.unwrap());
+        assert!(backend.increment_budget_used("sk-test", 0.7).await.unwrap());
+        let stored = backend.get_api_key("sk-test").await.unwrap().unwrap();
+        assert!(!stored.is_active);
+        assert_eq!(stored.deactivated_reason.as_deref(), Some("budget_exceeded"));
+        // Same month: stays deactivated
+        assert!(backend.validate_api_key("sk-test").await.unwrap().is_none());
+
+        assert!(matches!(
+            backend.increment_budget_used("sk-missing", 1.0).await,
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_swap_monthly_budget() {
+        let backend = MemoryBackend::new();
+        backend.create_api_key(&key("sk-parent", Some(100.0))).await.unwrap();
+
+        assert!(backend.swap_monthly_budget("sk-parent", 100.0, 80.0).await.unwrap());
+        // A second writer that read the old budget loses
+        assert!(!backend.swap_monthly_budget("sk-parent", 100.0, 70.0).await.unwrap());
+        assert!(!backend.swap_monthly_budget("sk-missing", 100.0, 70.0).await.unwrap());
+
+        let stored = backend.get_api_key("sk-parent").await.unwrap().unwrap();
+        assert_eq!(stored.monthly_budget, Some(80.0));
+    }
+
+    #[tokio::test]
+    async fn test_usage_and_mappings() {
+        let backend = MemoryBackend::new();
+        for (i, api_key) in ["sk-a", "sk-b", "sk-a"].iter().enumerate() {
+            let record: UsageRecord = serde_json::from_value(serde_json::json!({
+                "api_key": api_key,
+                "timestamp": format!("2025-01-0{}T00:00:00Z", i + 1),
+                "request_id": format!("req-{}", i),
+                "model": "claude-sonnet-4-5",
+                "input_tokens": 10,
+                "output_tokens": 5,
+                "success": true
+            }))
+            .unwrap();
+            backend.record_usage(&record).await.unwrap();
+        }
+
+        let usage = backend.get_usage_by_api_key("sk-a", None, None, None).await.unwrap();
+        let ids: Vec<_> = usage.iter().map(|r| r.request_id.as_str()).collect();
+        assert_eq!(ids, ["req-2", "req-0"]);
+        let recent = backend
+            .get_usage_by_api_key("sk-a", Some("2025-01-02T00:00:00Z"), None, Some(5))
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+
+        backend.set_model_mapping("claude-x", "anthropic.claude-x-v1:0").await.unwrap();
+        assert_eq!(
+            backend.get_model_mapping("claude-x").await.unwrap().as_deref(),
+            Some("anthropic.claude-x-v1:0")
+        );
+        assert!(backend.health_check().await);
+    }
+
+    #[tokio::test]
+    async fn test_inference_presets() {
+        let backend = MemoryBackend::new();
+        let preset: InferencePreset =
+            serde_json::from_value(serde_json::json!({"name": "support-bot", "top_p": 0.9})).unwrap();
+
+        backend.put_inference_preset(&preset).await.unwrap();
+        assert_eq!(backend.list_inference_presets().await.unwrap(), vec![preset]);
+        backend.delete_inference_preset("support-bot").await.unwrap();
+        assert!(matches!(
+            backend.delete_inference_preset("support-bot").await,
+            Err(StorageError::NotFound)
+        ));
+    }
+}
@@ -1,24 +1,35 @@
 //! Database module
 //!
-//! Contains storage backend abstraction and implementations.
+//! Contains storage backend abstraction and implementations. The DynamoDB
+//! client, backend and repositories need the `dynamodb` feature, the SQLite
+//! backend needs `sqlite`; the in-memory backend is always available.
 
+#[cfg(feature = "dynamodb")]
 pub mod dynamodb;
+#[cfg(feature = "dynamodb")]
 pub mod dynamodb_backend;
+pub mod memory_backend;
 pub mod models;
+#[cfg(feature = "dynamodb")]
 pub mod repositories;
 pub mod storage;
 
 #[cfg(feature = "sqlite")]
 pub mod sqlite_backend;
 
+#[cfg(feature = "dynamodb")]
 pub use dynamodb::DynamoDbClient;
+#[cfg(feature = "dynamodb")]
 pub use dynamodb_backend::DynamoDbBackend;
-pub use models::{ApiKey, ModelMapping, ModelPricing, UsageRecord, UsageStats};
+pub use memory_backend::MemoryBackend;
+pub use models::{ApiKey, FeatureFlag, ModelMapping, ModelPricing, UsageRecord, UsageStats};
+#[cfg(feature = "dynamodb")]
 pub use repositories::{
-    ApiKeyError, ApiKeyRepository, ModelMappingError, ModelMappingRepository, UsageError,
-    UsageRepository,
+    ApiKeyError, ApiKeyRepository, FeatureFlagError, FeatureFlagRepository, InferencePresetError,
+    InferencePresetRepository, ModelMappingError, ModelMappingRepository, ModelPricingError,
+    ModelPricingRepository, UsageError, UsageRepository,
 };
-pub use storage::{StorageBackend, StorageError};
+pub use storage::{KeyQuota, StorageBackend, StorageError, StorageKind};
 
 #[cfg(feature = "sqlite")]
 pub use sqlite_backend::SqliteBackend;
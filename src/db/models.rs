@@ -2,10 +2,13 @@
 //!
 //! This module defines the data structures for DynamoDB tables.
 
+#[cfg(feature = "dynamodb")]
 use aws_sdk_dynamodb::types::AttributeValue;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::services::RoutingOutcome;
+
 /// API key model for authentication and rate limiting.
 ///
 /// Stored in the api_keys table with `api_key` as partition key.
@@ -71,20 +74,167 @@ pub struct ApiKey {
     /// Tokens per minute limit
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tpm_limit: Option<i32>,
+
+    /// Permission scopes granted to this key (e.g., "backend_override")
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// Unix timestamp after which the key is rejected (trial keys)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    /// Model patterns this key may use (empty = all models)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+
+    /// CIDR ranges this key may be used from (empty = any address)
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+
+    /// Parent key this key was minted from (self-service child keys)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_key: Option<String>,
+
+    /// Unix timestamp of the most recent authenticated request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+
+    /// Total authenticated requests made with this key
+    #[serde(default)]
+    pub request_count: i64,
+
+    /// Most recent distinct source IPs, newest first
+    #[serde(default)]
+    pub recent_source_ips: Vec<String>,
+
+    /// Code-execution iterations per PTC session (service default if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ptc_max_iterations: Option<u32>,
+
+    /// Output token cap for each model turn of a PTC session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ptc_max_tokens_per_iteration: Option<u32>,
+
+    /// Total tokens (input + output) a PTC session may use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ptc_session_token_budget: Option<u64>,
+
+    /// Inference preset applied to this key's requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inference_preset: Option<String>,
 }
 
+/// Stored key as shown in admin listings (secret redacted)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeySummary {
+    pub key_id: String,
+    pub key_prefix: String,
+    pub user_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_name: Option<String>,
+    pub service_tier: String,
+    pub is_active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated_reason: Option<String>,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    pub rate_limit: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpm_limit: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<f64>,
+    pub budget_used_mtd: f64,
+    pub scopes: Vec<String>,
+    pub allowed_models: Vec<String>,
+    pub allowed_ips: Vec<String>,
+    /// Key ID of the parent key (self-service child keys)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_key_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+    pub request_count: i64,
+    pub recent_source_ips: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inference_preset: Option<String>,
+}
+
+/// Service tier assigned to trial keys
+pub const TRIAL_SERVICE_TIER: &str = "trial";
+
 impl ApiKey {
     /// Check if the key is valid (active and not deactivated)
     pub fn is_valid(&self) -> bool {
         self.is_active
     }
 
+    /// Opaque identifier for the key, safe to put in URLs and logs
+    pub fn key_id(&self) -> String {
+        Self::id_for(&self.api_key)
+    }
+
+    /// Listing view of the key with the secret redacted
+    pub fn summary(&self) -> ApiKeySummary {
+        ApiKeySummary {
+            key_id: self.key_id(),
+            key_prefix: format!("{}...", crate::utils::truncate_str(&self.api_key, 8)),
+            user_id: self.user_id.clone(),
+            name: self.name.clone(),
+            owner_name: self.owner_name.clone(),
+            service_tier: self.service_tier.clone(),
+            is_active: self.is_active,
+            deactivated_reason: self.deactivated_reason.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            rate_limit: self.rate_limit,
+            tpm_limit: self.tpm_limit,
+            monthly_budget: self.monthly_budget,
+            budget_used_mtd: self.budget_used_mtd,
+            scopes: self.scopes.clone(),
+            allowed_models: self.allowed_models.clone(),
+            allowed_ips: self.allowed_ips.clone(),
+            parent_key_id: self.parent_key.as_deref().map(Self::id_for),
+            last_used_at: self.last_used_at,
+            request_count: self.request_count,
+            recent_source_ips: self.recent_source_ips.clone(),
+            inference_preset: self.inference_preset.clone(),
+        }
+    }
+
+    /// Opaque identifier for a key value (`key_` + 16 hex digits of its hash)
+    pub fn id_for(api_key: &str) -> String {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        api_key.hash(&mut hasher);
+        format!("key_{:016x}", hasher.finish())
+    }
+
     /// Check if the key was deactivated due to budget exceeded
     pub fn is_budget_exceeded(&self) -> bool {
         self.deactivated_reason.as_deref() == Some("budget_exceeded")
     }
 
+    /// Check if the key has expired at the given Unix timestamp
+    pub fn is_expired_at(&self, now: i64) -> bool {
+        self.expires_at.map(|expires_at| now >= expires_at).unwrap_or(false)
+    }
+
+    /// Check if this is a trial key
+    pub fn is_trial(&self) -> bool {
+        self.service_tier == TRIAL_SERVICE_TIER
+    }
+
+    /// Check if the key has not been used since the cutoff timestamp
+    ///
+    /// Keys that were never used count from their creation time.
+    pub fn is_unused_since(&self, cutoff: i64) -> bool {
+        self.last_used_at.unwrap_or(self.created_at) < cutoff
+    }
+
     /// Parse from DynamoDB item
+    #[cfg(feature = "dynamodb")]
     pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
         Some(Self {
             api_key: get_string(item, "api_key")?,
@@ -104,8 +254,100 @@ impl ApiKey {
             budget_mtd_month: get_string(item, "budget_mtd_month"),
             deactivated_reason: get_string(item, "deactivated_reason"),
             tpm_limit: get_number(item, "tpm_limit").map(|n| n as i32),
+            scopes: get_string_list(item, "scopes"),
+            expires_at: get_number(item, "expires_at"),
+            allowed_models: get_string_list(item, "allowed_models"),
+            allowed_ips: get_string_list(item, "allowed_ips"),
+            parent_key: get_string(item, "parent_key"),
+            last_used_at: get_number(item, "last_used_at"),
+            request_count: get_number(item, "request_count").unwrap_or(0),
+            recent_source_ips: get_string_list(item, "recent_source_ips"),
+            ptc_max_iterations: get_number(item, "ptc_max_iterations").map(|n| n as u32),
+            ptc_max_tokens_per_iteration: get_number(item, "ptc_max_tokens_per_iteration")
+                .map(|n| n as u32),
+            ptc_session_token_budget: get_number(item, "ptc_session_token_budget").map(|n| n as u64),
+            inference_preset: get_string(item, "inference_preset"),
         })
     }
+
+    /// Convert to DynamoDB item
+    #[cfg(feature = "dynamodb")]
+    pub fn to_dynamodb(&self) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+        item.insert("api_key".to_string(), AttributeValue::S(self.api_key.clone()));
+        item.insert("user_id".to_string(), AttributeValue::S(self.user_id.clone()));
+        item.insert("name".to_string(), AttributeValue::S(self.name.clone()));
+        item.insert("created_at".to_string(), AttributeValue::N(self.created_at.to_string()));
+        item.insert("is_active".to_string(), AttributeValue::Bool(self.is_active));
+        item.insert("rate_limit".to_string(), AttributeValue::N(self.rate_limit.to_string()));
+        item.insert("service_tier".to_string(), AttributeValue::S(self.service_tier.clone()));
+        item.insert("budget_used".to_string(), AttributeValue::N(self.budget_used.to_string()));
+        item.insert("budget_used_mtd".to_string(), AttributeValue::N(self.budget_used_mtd.to_string()));
+
+        if let Some(updated_at) = self.updated_at {
+            item.insert("updated_at".to_string(), AttributeValue::N(updated_at.to_string()));
+        }
+        if let Some(ref owner_name) = self.owner_name {
+            item.insert("owner_name".to_string(), AttributeValue::S(owner_name.clone()));
+        }
+        if let Some(ref role) = self.role {
+            item.insert("role".to_string(), AttributeValue::S(role.clone()));
+        }
+        if let Some(budget) = self.monthly_budget {
+            item.insert("monthly_budget".to_string(), AttributeValue::N(budget.to_string()));
+        }
+        if let Some(ref month) = self.budget_mtd_month {
+            item.insert("budget_mtd_month".to_string(), AttributeValue::S(month.clone()));
+        }
+        if let Some(tpm_limit) = self.tpm_limit {
+            item.insert("tpm_limit".to_string(), AttributeValue::N(tpm_limit.to_string()));
+        }
+        if !self.scopes.is_empty() {
+            item.insert("scopes".to_string(), AttributeValue::Ss(self.scopes.clone()));
+        }
+        if let Some(expires_at) = self.expires_at {
+            item.insert("expires_at".to_string(), AttributeValue::N(expires_at.to_string()));
+        }
+        if !self.allowed_models.is_empty() {
+            item.insert("allowed_models".to_string(), AttributeValue::Ss(self.allowed_models.clone()));
+        }
+        if !self.allowed_ips.is_empty() {
+            item.insert("allowed_ips".to_string(), AttributeValue::Ss(self.allowed_ips.clone()));
+        }
+        if let Some(ref parent_key) = self.parent_key {
+            item.insert("parent_key".to_string(), AttributeValue::S(parent_key.clone()));
+        }
+        if let Some(ref preset) = self.inference_preset {
+            item.insert("inference_preset".to_string(), AttributeValue::S(preset.clone()));
+        }
+        if let Some(last_used_at) = self.last_used_at {
+            item.insert("last_used_at".to_string(), AttributeValue::N(last_used_at.to_string()));
+        }
+        if self.request_count > 0 {
+            item.insert("request_count".to_string(), AttributeValue::N(self.request_count.to_string()));
+        }
+        if !self.recent_source_ips.is_empty() {
+            item.insert(
+                "recent_source_ips".to_string(),
+                AttributeValue::L(
+                    self.recent_source_ips
+                        .iter()
+                        .map(|ip| AttributeValue::S(ip.clone()))
+                        .collect(),
+                ),
+            );
+        }
+        for (name, value) in [
+            ("ptc_max_iterations", self.ptc_max_iterations.map(u64::from)),
+            ("ptc_max_tokens_per_iteration", self.ptc_max_tokens_per_iteration.map(u64::from)),
+            ("ptc_session_token_budget", self.ptc_session_token_budget),
+        ] {
+            if let Some(value) = value {
+                item.insert(name.to_string(), AttributeValue::N(value.to_string()));
+            }
+        }
+        item
+    }
 }
 
 /// Usage record for tracking API usage per request.
@@ -149,10 +391,35 @@ pub struct UsageRecord {
     /// Error message if the request failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
+
+    /// Request metadata (OpenAI `metadata` tags)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+
+    /// Backend that served the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+
+    /// Upstream attempts it took, including the one that succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<i64>,
+
+    /// Latency added by failed attempts, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_latency_ms: Option<i64>,
 }
 
 impl UsageRecord {
+    /// Record how the request was routed
+    pub fn with_routing(mut self, routing: &RoutingOutcome) -> Self {
+        self.backend = Some(routing.backend.clone());
+        self.attempts = Some(i64::from(routing.attempts));
+        self.added_latency_ms = Some(routing.added_latency_ms as i64);
+        self
+    }
+
     /// Convert to DynamoDB item
+    #[cfg(feature = "dynamodb")]
     pub fn to_dynamodb(&self) -> HashMap<String, AttributeValue> {
         let mut item = HashMap::new();
         item.insert("api_key".to_string(), AttributeValue::S(self.api_key.clone()));
@@ -171,11 +438,29 @@ impl UsageRecord {
         if let Some(ref error_message) = self.error_message {
             item.insert("error_message".to_string(), AttributeValue::S(error_message.clone()));
         }
+        if !self.metadata.is_empty() {
+            let metadata = self
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), AttributeValue::S(v.clone())))
+                .collect();
+            item.insert("metadata".to_string(), AttributeValue::M(metadata));
+        }
+        if let Some(ref backend) = self.backend {
+            item.insert("backend".to_string(), AttributeValue::S(backend.clone()));
+        }
+        if let Some(attempts) = self.attempts {
+            item.insert("attempts".to_string(), AttributeValue::N(attempts.to_string()));
+        }
+        if let Some(added_latency_ms) = self.added_latency_ms {
+            item.insert("added_latency_ms".to_string(), AttributeValue::N(added_latency_ms.to_string()));
+        }
 
         item
     }
 
     /// Parse from DynamoDB item
+    #[cfg(feature = "dynamodb")]
     pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
         Some(Self {
             api_key: get_string(item, "api_key")?,
@@ -189,6 +474,10 @@ impl UsageRecord {
             success: get_bool(item, "success").unwrap_or(false),
             duration_ms: get_number(item, "duration_ms"),
             error_message: get_string(item, "error_message"),
+            metadata: get_string_map(item, "metadata"),
+            backend: get_string(item, "backend"),
+            attempts: get_number(item, "attempts"),
+            added_latency_ms: get_number(item, "added_latency_ms"),
         })
     }
 }
@@ -221,6 +510,7 @@ pub struct UsageStats {
     pub last_aggregated_timestamp: Option<String>,
 }
 
+#[cfg(feature = "dynamodb")]
 impl UsageStats {
     /// Parse from DynamoDB item
     pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
@@ -250,6 +540,7 @@ pub struct ModelMapping {
 
 impl ModelMapping {
     /// Parse from DynamoDB item
+    #[cfg(feature = "dynamodb")]
     pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
         Some(Self {
             anthropic_model_id: get_string(item, "anthropic_model_id")?,
@@ -258,6 +549,7 @@ impl ModelMapping {
     }
 
     /// Convert to DynamoDB item
+    #[cfg(feature = "dynamodb")]
     pub fn to_dynamodb(&self) -> HashMap<String, AttributeValue> {
         let mut item = HashMap::new();
         item.insert(
@@ -304,6 +596,24 @@ pub struct ModelPricing {
     pub status: String,
 }
 
+impl ModelPricing {
+    /// Rates used for models without a pricing entry (Claude Sonnet list
+    /// prices: $3 input, $15 output, $0.30 cache read, $3.75 cache write)
+    pub fn fallback(model_id: impl Into<String>) -> Self {
+        Self {
+            model_id: model_id.into(),
+            provider: String::new(),
+            display_name: String::new(),
+            input_price: 3.0,
+            output_price: 15.0,
+            cache_read_price: 0.30,
+            cache_write_price: 3.75,
+            status: "active".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "dynamodb")]
 impl ModelPricing {
     /// Parse from DynamoDB item
     pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
@@ -320,29 +630,171 @@ impl ModelPricing {
     }
 }
 
+/// Runtime feature flag, evaluated per API key and tenant.
+///
+/// Stored in the feature_flags table with `name` as partition key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureFlag {
+    /// Flag name (partition key)
+    pub name: String,
+
+    /// Whether the flag is on for keys that no rule below matches
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Share of tenants (0-100) the flag is gradually rolled out to
+    #[serde(default)]
+    pub rollout_percent: u8,
+
+    /// API keys the flag is always on for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_keys: Vec<String>,
+
+    /// API keys the flag is always off for (wins over every other rule)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_keys: Vec<String>,
+
+    /// Tenants (key owner user IDs) the flag is on for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_tenants: Vec<String>,
+
+    /// What the flag controls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Last update timestamp (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+impl FeatureFlag {
+    /// Whether the flag is on for a key owned by `tenant`
+    ///
+    /// Key denials win, then key and tenant grants, then the rollout
+    /// bucket, then the flag's default.
+    pub fn evaluate(&self, api_key: &str, tenant: &str) -> bool {
+        if self.disabled_keys.iter().any(|k| k == api_key) {
+            return false;
+        }
+        if self.enabled_keys.iter().any(|k| k == api_key)
+            || self.enabled_tenants.iter().any(|t| t == tenant)
+        {
+            return true;
+        }
+        if self.rollout_percent > 0 && self.rollout_bucket(tenant) < self.rollout_percent {
+            return true;
+        }
+        self.enabled
+    }
+
+    /// Stable 0-99 bucket of a tenant for this flag
+    ///
+    /// FNV-1a over the flag and tenant names, so a tenant keeps its bucket
+    /// across restarts and raising the percentage only adds tenants.
+    pub fn rollout_bucket(&self, tenant: &str) -> u8 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.name.bytes().chain([b':']).chain(tenant.bytes()) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        (hash % 100) as u8
+    }
+}
+
+#[cfg(feature = "dynamodb")]
+impl FeatureFlag {
+    /// Parse from DynamoDB item
+    pub fn from_dynamodb(item: &HashMap<String, AttributeValue>) -> Option<Self> {
+        Some(Self {
+            name: get_string(item, "name")?,
+            enabled: get_bool(item, "enabled").unwrap_or(false),
+            rollout_percent: get_number(item, "rollout_percent").unwrap_or(0).clamp(0, 100) as u8,
+            enabled_keys: get_string_list(item, "enabled_keys"),
+            disabled_keys: get_string_list(item, "disabled_keys"),
+            enabled_tenants: get_string_list(item, "enabled_tenants"),
+            description: get_string(item, "description"),
+            updated_at: get_number(item, "updated_at"),
+        })
+    }
+
+    /// Convert to DynamoDB item
+    pub fn to_dynamodb(&self) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+        item.insert("name".to_string(), AttributeValue::S(self.name.clone()));
+        item.insert("enabled".to_string(), AttributeValue::Bool(self.enabled));
+        item.insert("rollout_percent".to_string(), AttributeValue::N(self.rollout_percent.to_string()));
+        // String sets cannot be empty
+        for (name, values) in [
+            ("enabled_keys", &self.enabled_keys),
+            ("disabled_keys", &self.disabled_keys),
+            ("enabled_tenants", &self.enabled_tenants),
+        ] {
+            if !values.is_empty() {
+                item.insert(name.to_string(), AttributeValue::Ss(values.clone()));
+            }
+        }
+        if let Some(ref description) = self.description {
+            item.insert("description".to_string(), AttributeValue::S(description.clone()));
+        }
+        if let Some(updated_at) = self.updated_at {
+            item.insert("updated_at".to_string(), AttributeValue::N(updated_at.to_string()));
+        }
+        item
+    }
+}
+
 // Helper functions for parsing DynamoDB AttributeValues
 
+#[cfg(feature = "dynamodb")]
 fn get_string(item: &HashMap<String, AttributeValue>, key: &str) -> Option<String> {
     item.get(key).and_then(|v| v.as_s().ok()).map(|s| s.to_string())
 }
 
+#[cfg(feature = "dynamodb")]
 fn get_number(item: &HashMap<String, AttributeValue>, key: &str) -> Option<i64> {
     item.get(key)
         .and_then(|v| v.as_n().ok())
         .and_then(|n| n.parse().ok())
 }
 
+#[cfg(feature = "dynamodb")]
 fn get_number_f64(item: &HashMap<String, AttributeValue>, key: &str) -> Option<f64> {
     item.get(key)
         .and_then(|v| v.as_n().ok())
         .and_then(|n| n.parse().ok())
 }
 
+#[cfg(feature = "dynamodb")]
 fn get_bool(item: &HashMap<String, AttributeValue>, key: &str) -> Option<bool> {
     item.get(key).and_then(|v| v.as_bool().ok()).copied()
 }
 
-#[cfg(test)]
+/// Read a string set (SS) or list of strings (L) attribute
+#[cfg(feature = "dynamodb")]
+fn get_string_list(item: &HashMap<String, AttributeValue>, key: &str) -> Vec<String> {
+    match item.get(key) {
+        Some(AttributeValue::Ss(values)) => values.clone(),
+        Some(AttributeValue::L(values)) => values
+            .iter()
+            .filter_map(|v| v.as_s().ok().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Read a map (M) attribute of string values
+#[cfg(feature = "dynamodb")]
+fn get_string_map(item: &HashMap<String, AttributeValue>, key: &str) -> HashMap<String, String> {
+    match item.get(key) {
+        Some(AttributeValue::M(values)) => values
+            .iter()
+            .filter_map(|(k, v)| v.as_s().ok().map(|s| (k.clone(), s.to_string())))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+#[cfg(all(test, feature = "dynamodb"))]
 mod tests {
     use super::*;
 
@@ -366,6 +818,18 @@ mod tests {
             budget_mtd_month: None,
             deactivated_reason: None,
             tpm_limit: None,
+            scopes: Vec::new(),
+            expires_at: None,
+            allowed_models: Vec::new(),
+            allowed_ips: Vec::new(),
+            parent_key: None,
+            last_used_at: None,
+            request_count: 0,
+            recent_source_ips: Vec::new(),
+            ptc_max_iterations: None,
+            ptc_max_tokens_per_iteration: None,
+            ptc_session_token_budget: None,
+            inference_preset: None,
         };
 
         assert!(key.is_valid());
@@ -391,12 +855,101 @@ mod tests {
             budget_mtd_month: Some("2024-01".to_string()),
             deactivated_reason: Some("budget_exceeded".to_string()),
             tpm_limit: None,
+            scopes: Vec::new(),
+            expires_at: None,
+            allowed_models: Vec::new(),
+            allowed_ips: Vec::new(),
+            parent_key: None,
+            last_used_at: None,
+            request_count: 0,
+            recent_source_ips: Vec::new(),
+            ptc_max_iterations: None,
+            ptc_max_tokens_per_iteration: None,
+            ptc_session_token_budget: None,
+            inference_preset: None,
         };
 
         assert!(!key.is_valid());
         assert!(key.is_budget_exceeded());
     }
 
+    #[test]
+    fn test_api_key_expiry() {
+        let item = HashMap::from([
+            ("api_key".to_string(), AttributeValue::S("sk-trial".to_string())),
+            ("user_id".to_string(), AttributeValue::S("user1".to_string())),
+            ("is_active".to_string(), AttributeValue::Bool(true)),
+            ("service_tier".to_string(), AttributeValue::S("trial".to_string())),
+            ("expires_at".to_string(), AttributeValue::N("1000".to_string())),
+            (
+                "allowed_models".to_string(),
+                AttributeValue::Ss(vec!["claude-3-5-haiku-*".to_string()]),
+            ),
+        ]);
+        let key = ApiKey::from_dynamodb(&item).unwrap();
+
+        assert!(key.is_trial());
+        assert!(!key.is_expired_at(999));
+        assert!(key.is_expired_at(1000));
+        assert_eq!(key.allowed_models, vec!["claude-3-5-haiku-*"]);
+        assert_eq!(key.parent_key, None);
+    }
+
+    #[test]
+    fn test_api_key_ptc_budget_round_trip() {
+        let mut key = ApiKey::from_dynamodb(&HashMap::from([
+            ("api_key".to_string(), AttributeValue::S("sk-ptc".to_string())),
+            ("user_id".to_string(), AttributeValue::S("user1".to_string())),
+            ("ptc_max_iterations".to_string(), AttributeValue::N("5".to_string())),
+        ]))
+        .unwrap();
+        assert_eq!(key.ptc_max_iterations, Some(5));
+        assert_eq!(key.ptc_session_token_budget, None);
+
+        key.ptc_session_token_budget = Some(200_000);
+        let item = key.to_dynamodb();
+        assert!(!item.contains_key("ptc_max_tokens_per_iteration"));
+        let key = ApiKey::from_dynamodb(&item).unwrap();
+        assert_eq!(key.ptc_max_iterations, Some(5));
+        assert_eq!(key.ptc_session_token_budget, Some(200_000));
+    }
+
+    #[test]
+    fn test_api_key_unused_since() {
+        let mut key = ApiKey::from_dynamodb(&HashMap::from([
+            ("api_key".to_string(), AttributeValue::S("sk-old".to_string())),
+            ("user_id".to_string(), AttributeValue::S("user1".to_string())),
+            ("created_at".to_string(), AttributeValue::N("100".to_string())),
+        ]))
+        .unwrap();
+
+        // Never used: measured from creation
+        assert!(key.is_unused_since(101));
+        assert!(!key.is_unused_since(100));
+
+        key.last_used_at = Some(500);
+        assert!(!key.is_unused_since(400));
+        assert!(key.is_unused_since(501));
+    }
+
+    #[test]
+    fn test_api_key_dynamodb_roundtrip() {
+        let item = HashMap::from([
+            ("api_key".to_string(), AttributeValue::S("sk-child".to_string())),
+            ("user_id".to_string(), AttributeValue::S("team-a".to_string())),
+            ("is_active".to_string(), AttributeValue::Bool(true)),
+            ("monthly_budget".to_string(), AttributeValue::N("25".to_string())),
+            ("scopes".to_string(), AttributeValue::Ss(vec!["child_keys".to_string()])),
+            ("parent_key".to_string(), AttributeValue::S("sk-parent".to_string())),
+        ]);
+        let key = ApiKey::from_dynamodb(&item).unwrap();
+        let roundtrip = ApiKey::from_dynamodb(&key.to_dynamodb()).unwrap();
+
+        assert_eq!(roundtrip.parent_key.as_deref(), Some("sk-parent"));
+        assert_eq!(roundtrip.monthly_budget, Some(25.0));
+        assert_eq!(roundtrip.scopes, vec!["child_keys"]);
+    }
+
     #[test]
     fn test_usage_record_to_dynamodb() {
         let record = UsageRecord {
@@ -411,10 +964,66 @@ mod tests {
             success: true,
             duration_ms: Some(500),
             error_message: None,
-        };
+            metadata: HashMap::from([("team".to_string(), "search".to_string())]),
+            backend: None,
+            attempts: None,
+            added_latency_ms: None,
+        }
+        .with_routing(
+            &RoutingOutcome::first_attempt("gemini")
+                .with_retries(1, std::time::Duration::from_millis(120)),
+        );
 
         let item = record.to_dynamodb();
         assert_eq!(item.get("api_key").unwrap().as_s().unwrap(), "sk-test");
         assert_eq!(item.get("input_tokens").unwrap().as_n().unwrap(), "100");
+
+        let parsed = UsageRecord::from_dynamodb(&item).unwrap();
+        assert_eq!(parsed.metadata, record.metadata);
+        assert_eq!(parsed.backend.as_deref(), Some("gemini"));
+        assert_eq!(parsed.attempts, Some(2));
+        assert_eq!(parsed.added_latency_ms, Some(120));
+    }
+
+    #[test]
+    fn test_feature_flag_evaluation() {
+        let mut flag = FeatureFlag {
+            name: "semantic_cache".to_string(),
+            enabled_keys: vec!["sk-beta".to_string()],
+            disabled_keys: vec!["sk-opt-out".to_string()],
+            enabled_tenants: vec!["team-a".to_string()],
+            ..Default::default()
+        };
+        assert!(flag.evaluate("sk-beta", "team-z"));
+        assert!(flag.evaluate("sk-other", "team-a"));
+        assert!(!flag.evaluate("sk-opt-out", "team-a"));
+        assert!(!flag.evaluate("sk-other", "team-z"));
+
+        // Raising the rollout keeps earlier tenants in
+        flag.rollout_percent = 100;
+        assert!(flag.evaluate("sk-other", "team-z"));
+        let bucket = flag.rollout_bucket("team-z");
+        flag.rollout_percent = bucket + 1;
+        assert!(flag.evaluate("sk-other", "team-z"));
+        flag.rollout_percent = bucket;
+        assert!(!flag.evaluate("sk-other", "team-z"));
+
+        flag.enabled = true;
+        assert!(flag.evaluate("sk-other", "team-z"));
+        assert!(!flag.evaluate("sk-opt-out", "team-z"));
+    }
+
+    #[test]
+    fn test_feature_flag_dynamodb_roundtrip() {
+        let flag = FeatureFlag {
+            name: "ptc".to_string(),
+            rollout_percent: 25,
+            enabled_tenants: vec!["team-a".to_string()],
+            description: Some("Programmatic tool calling".to_string()),
+            ..Default::default()
+        };
+        let item = flag.to_dynamodb();
+        assert!(!item.contains_key("enabled_keys"));
+        assert_eq!(FeatureFlag::from_dynamodb(&item), Some(flag));
     }
 }
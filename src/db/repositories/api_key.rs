@@ -7,7 +7,9 @@ use chrono::Utc;
 use std::sync::Arc;
 
 use crate::db::models::ApiKey;
+pub use crate::db::storage::KeyQuota;
 use crate::db::DynamoDbClient;
+use crate::utils::redact_key;
 
 /// Repository for API key operations
 #[derive(Clone)]
@@ -83,6 +85,106 @@ impl ApiKeyRepository {
         }
     }
 
+    /// Store a new API key
+    ///
+    /// Fails if a key with the same value already exists.
+    pub async fn create_api_key(&self, key: &ApiKey) -> Result<(), ApiKeyError> {
+        self.client
+            .client()
+            .put_item()
+            .table_name(self.client.api_keys_table())
+            .set_item(Some(key.to_dynamodb()))
+            .condition_expression("attribute_not_exists(api_key)")
+            .send()
+            .await
+            .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
+
+        tracing::info!(
+            api_key = %redact_key(&key.api_key),
+            user_id = %key.user_id,
+            parent = ?key.parent_key.as_deref().map(redact_key),
+            "Created API key"
+        );
+
+        Ok(())
+    }
+
+    /// List all API keys
+    ///
+    /// Scans the whole table; intended for admin tooling, not the request path.
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .client()
+                .scan()
+                .table_name(self.client.api_keys_table())
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
+
+            keys.extend(result.items().iter().filter_map(ApiKey::from_dynamodb));
+
+            match result.last_evaluated_key {
+                Some(key) if !key.is_empty() => start_key = Some(key),
+                _ => break,
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Record buffered activity for an API key
+    ///
+    /// Sets the last-used timestamp and recent source IPs and adds
+    /// `request_delta` to the request count. Keys that no longer exist are
+    /// left untouched.
+    pub async fn record_activity(
+        &self,
+        api_key: &str,
+        last_used_at: i64,
+        request_delta: i64,
+        recent_source_ips: &[String],
+    ) -> Result<(), ApiKeyError> {
+        let ips = recent_source_ips
+            .iter()
+            .map(|ip| AttributeValue::S(ip.clone()))
+            .collect();
+
+        let result = self
+            .client
+            .client()
+            .update_item()
+            .table_name(self.client.api_keys_table())
+            .key("api_key", AttributeValue::S(api_key.to_string()))
+            .update_expression(
+                "SET last_used_at = :last_used_at, recent_source_ips = :ips \
+                 ADD request_count :delta",
+            )
+            .condition_expression("attribute_exists(api_key)")
+            .expression_attribute_values(":last_used_at", AttributeValue::N(last_used_at.to_string()))
+            .expression_attribute_values(":ips", AttributeValue::L(ips))
+            .expression_attribute_values(":delta", AttributeValue::N(request_delta.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e)
+                if e.as_service_error()
+                    .map(|se| se.is_conditional_check_failed_exception())
+                    .unwrap_or(false) =>
+            {
+                Err(ApiKeyError::NotFound)
+            }
+            Err(e) => Err(ApiKeyError::DynamoDb(e.to_string())),
+        }
+    }
+
     /// Reactivate an API key for a new month
     async fn reactivate_for_new_month(
         &self,
@@ -111,7 +213,7 @@ impl ApiKeyRepository {
             .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
 
         tracing::info!(
-            api_key = %&api_key[..20.min(api_key.len())],
+            api_key = %redact_key(api_key),
             month = %current_month,
             "Auto-reactivated key for new month"
         );
@@ -178,7 +280,7 @@ impl ApiKeyRepository {
                 .expression_attribute_values(":reason", AttributeValue::S("budget_exceeded".to_string()));
 
             tracing::warn!(
-                api_key = %&api_key[..20.min(api_key.len())],
+                api_key = %redact_key(api_key),
                 budget_used_mtd = new_budget_used_mtd,
                 monthly_budget = ?key.monthly_budget,
                 "Deactivating key due to budget exceeded"
@@ -194,6 +296,216 @@ impl ApiKeyRepository {
         Ok(budget_exceeded)
     }
 
+    /// Set a new expiry timestamp on an API key (trial extension)
+    pub async fn extend_api_key(
+        &self,
+        api_key: &str,
+        expires_at: i64,
+    ) -> Result<Option<ApiKey>, ApiKeyError> {
+        if self.get_api_key(api_key).await?.is_none() {
+            return Err(ApiKeyError::NotFound);
+        }
+
+        let now = Utc::now().timestamp();
+
+        self.client
+            .client()
+            .update_item()
+            .table_name(self.client.api_keys_table())
+            .key("api_key", AttributeValue::S(api_key.to_string()))
+            .update_expression("SET expires_at = :expires_at, updated_at = :updated_at")
+            .expression_attribute_values(":expires_at", AttributeValue::N(expires_at.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(now.to_string()))
+            .send()
+            .await
+            .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
+
+        self.get_api_key(api_key).await
+    }
+
+    /// Upgrade an API key to a new service tier
+    ///
+    /// Clears the trial expiry and model restrictions; optionally updates the
+    /// rate limit and monthly budget.
+    pub async fn upgrade_api_key(
+        &self,
+        api_key: &str,
+        service_tier: &str,
+        rate_limit: Option<i32>,
+        monthly_budget: Option<f64>,
+    ) -> Result<Option<ApiKey>, ApiKeyError> {
+        if self.get_api_key(api_key).await?.is_none() {
+            return Err(ApiKeyError::NotFound);
+        }
+
+        let now = Utc::now().timestamp();
+        let mut set_expr = "SET service_tier = :tier, updated_at = :updated_at".to_string();
+
+        let mut request = self
+            .client
+            .client()
+            .update_item()
+            .table_name(self.client.api_keys_table())
+            .key("api_key", AttributeValue::S(api_key.to_string()))
+            .expression_attribute_values(":tier", AttributeValue::S(service_tier.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(now.to_string()));
+
+        if let Some(rate_limit) = rate_limit {
+            set_expr.push_str(", rate_limit = :rate_limit");
+            request = request.expression_attribute_values(":rate_limit", AttributeValue::N(rate_limit.to_string()));
+        }
+
+        if let Some(budget) = monthly_budget {
+            set_expr.push_str(", monthly_budget = :budget");
+            request = request.expression_attribute_values(":budget", AttributeValue::N(budget.to_string()));
+        }
+
+        request
+            .update_expression(format!("{} REMOVE expires_at, allowed_models", set_expr))
+            .send()
+            .await
+            .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
+
+        tracing::info!(
+            api_key = %redact_key(api_key),
+            service_tier = %service_tier,
+            "Upgraded API key"
+        );
+
+        self.get_api_key(api_key).await
+    }
+
+    /// Change an API key's rate limit, tokens-per-minute limit or monthly budget
+    ///
+    /// Fields left as None are unchanged; `Some(None)` removes a limit.
+    pub async fn set_quota(
+        &self,
+        api_key: &str,
+        quota: &KeyQuota,
+    ) -> Result<Option<ApiKey>, ApiKeyError> {
+        if self.get_api_key(api_key).await?.is_none() {
+            return Err(ApiKeyError::NotFound);
+        }
+
+        let now = Utc::now().timestamp();
+        let mut set_expr = "SET updated_at = :updated_at".to_string();
+        let mut removed = Vec::new();
+
+        let mut request = self
+            .client
+            .client()
+            .update_item()
+            .table_name(self.client.api_keys_table())
+            .key("api_key", AttributeValue::S(api_key.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(now.to_string()));
+
+        if let Some(rate_limit) = quota.rate_limit {
+            set_expr.push_str(", rate_limit = :rate_limit");
+            request = request.expression_attribute_values(":rate_limit", AttributeValue::N(rate_limit.to_string()));
+        }
+
+        match quota.tpm_limit {
+            Some(Some(tpm_limit)) => {
+                set_expr.push_str(", tpm_limit = :tpm_limit");
+                request = request.expression_attribute_values(":tpm_limit", AttributeValue::N(tpm_limit.to_string()));
+            }
+            Some(None) => removed.push("tpm_limit"),
+            None => {}
+        }
+
+        match quota.monthly_budget {
+            Some(Some(budget)) => {
+                set_expr.push_str(", monthly_budget = :budget");
+                request = request.expression_attribute_values(":budget", AttributeValue::N(budget.to_string()));
+            }
+            Some(None) => removed.push("monthly_budget"),
+            None => {}
+        }
+
+        if !removed.is_empty() {
+            set_expr = format!("{} REMOVE {}", set_expr, removed.join(", "));
+        }
+
+        request
+            .update_expression(set_expr)
+            .send()
+            .await
+            .map_err(|e| ApiKeyError::DynamoDb(e.to_string()))?;
+
+        tracing::info!(
+            api_key = %redact_key(api_key),
+            quota = ?quota,
+            "Updated API key quota"
+        );
+
+        self.get_api_key(api_key).await
+    }
+
+    /// Set the monthly budget only if it still equals `expected`
+    ///
+    /// Returns `false` when the condition fails: the key is gone or its
+    /// budget was changed by another writer since it was read.
+    pub async fn swap_monthly_budget(
+        &self,
+        api_key: &str,
+        expected: f64,
+        budget: f64,
+    ) -> Result<bool, ApiKeyError> {
+        let result = self
+            .client
+            .client()
+            .update_item()
+            .table_name(self.client.api_keys_table())
+            .key("api_key", AttributeValue::S(api_key.to_string()))
+            .update_expression("SET monthly_budget = :budget, updated_at = :updated_at")
+            .condition_expression("monthly_budget = :expected")
+            .expression_attribute_values(":budget", AttributeValue::N(budget.to_string()))
+            .expression_attribute_values(":expected", AttributeValue::N(expected.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(Utc::now().timestamp().to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => {
+                tracing::info!(
+                    api_key = %redact_key(api_key),
+                    monthly_budget = budget,
+                    "Updated API key monthly budget"
+                );
+                Ok(true)
+            }
+            Err(e)
+                if e.as_service_error()
+                    .map(|se| se.is_conditional_check_failed_exception())
+                    .unwrap_or(false) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(ApiKeyError::DynamoDb(e.to_string())),
+        }
+    }
+
+    /// Replace an API key with a new one carrying the same settings
+    ///
+    /// Stores `replacement` first, then deactivates the old key, so a failed
+    /// rotation never leaves the caller without a working key.
+    pub async fn rotate_api_key(
+        &self,
+        old_key: &str,
+        replacement: &ApiKey,
+    ) -> Result<(), ApiKeyError> {
+        self.create_api_key(replacement).await?;
+        self.deactivate_api_key(old_key, Some("rotated")).await?;
+
+        tracing::info!(
+            old_key = %redact_key(old_key),
+            new_key = %redact_key(&replacement.api_key),
+            "Rotated API key"
+        );
+
+        Ok(())
+    }
+
     /// Deactivate an API key
     pub async fn deactivate_api_key(
         &self,
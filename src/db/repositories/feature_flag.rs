@@ -0,0 +1,77 @@
+//! Feature flag repository
+//!
+//! Data access layer for runtime feature flags.
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::sync::Arc;
+
+use crate::db::models::FeatureFlag;
+use crate::db::DynamoDbClient;
+
+/// Repository for feature flag operations
+#[derive(Clone)]
+pub struct FeatureFlagRepository {
+    client: Arc<DynamoDbClient>,
+}
+
+impl FeatureFlagRepository {
+    /// Create a new feature flag repository
+    pub fn new(client: Arc<DynamoDbClient>) -> Self {
+        Self { client }
+    }
+
+    /// Store a flag, replacing any previous definition
+    pub async fn put(&self, flag: &FeatureFlag) -> Result<(), FeatureFlagError> {
+        self.client
+            .client()
+            .put_item()
+            .table_name(self.client.feature_flags_table())
+            .set_item(Some(flag.to_dynamodb()))
+            .send()
+            .await
+            .map_err(|e| FeatureFlagError::DynamoDb(e.to_string()))?;
+
+        tracing::debug!(name = %flag.name, "Stored feature flag");
+        Ok(())
+    }
+
+    /// Delete a flag
+    pub async fn delete(&self, name: &str) -> Result<(), FeatureFlagError> {
+        self.client
+            .client()
+            .delete_item()
+            .table_name(self.client.feature_flags_table())
+            .key("name", AttributeValue::S(name.to_string()))
+            .send()
+            .await
+            .map_err(|e| FeatureFlagError::DynamoDb(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// List all flags
+    pub async fn list_all(&self) -> Result<Vec<FeatureFlag>, FeatureFlagError> {
+        let result = self
+            .client
+            .client()
+            .scan()
+            .table_name(self.client.feature_flags_table())
+            .send()
+            .await
+            .map_err(|e| FeatureFlagError::DynamoDb(e.to_string()))?;
+
+        Ok(result
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(FeatureFlag::from_dynamodb)
+            .collect())
+    }
+}
+
+/// Errors that can occur during feature flag operations
+#[derive(Debug, thiserror::Error)]
+pub enum FeatureFlagError {
+    #[error("DynamoDB error: {0}")]
+    DynamoDb(String),
+}
@@ -0,0 +1,90 @@
+//! Inference preset repository
+//!
+//! Data access layer for operator-defined inference presets. Each item holds
+//! the preset's name and its JSON definition.
+
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use std::sync::Arc;
+
+use crate::db::DynamoDbClient;
+use crate::services::InferencePreset;
+
+/// Repository for inference preset operations
+#[derive(Clone)]
+pub struct InferencePresetRepository {
+    client: Arc<DynamoDbClient>,
+}
+
+impl InferencePresetRepository {
+    /// Create a new inference preset repository
+    pub fn new(client: Arc<DynamoDbClient>) -> Self {
+        Self { client }
+    }
+
+    /// Store a preset, replacing any previous definition
+    pub async fn put(&self, preset: &InferencePreset) -> Result<(), InferencePresetError> {
+        let definition = serde_json::to_string(preset)
+            .map_err(|e| InferencePresetError::Parse(e.to_string()))?;
+
+        self.client
+            .client()
+            .put_item()
+            .table_name(self.client.inference_presets_table())
+            .item("name", AttributeValue::S(preset.name.clone()))
+            .item("preset", AttributeValue::S(definition))
+            .send()
+            .await
+            .map_err(|e| InferencePresetError::DynamoDb(e.to_string()))?;
+
+        tracing::debug!(name = %preset.name, "Stored inference preset");
+        Ok(())
+    }
+
+    /// Delete a preset; returns whether it existed
+    pub async fn delete(&self, name: &str) -> Result<bool, InferencePresetError> {
+        let result = self
+            .client
+            .client()
+            .delete_item()
+            .table_name(self.client.inference_presets_table())
+            .key("name", AttributeValue::S(name.to_string()))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|e| InferencePresetError::DynamoDb(e.to_string()))?;
+
+        Ok(result.attributes.is_some_and(|item| !item.is_empty()))
+    }
+
+    /// List all presets
+    pub async fn list_all(&self) -> Result<Vec<InferencePreset>, InferencePresetError> {
+        let result = self
+            .client
+            .client()
+            .scan()
+            .table_name(self.client.inference_presets_table())
+            .send()
+            .await
+            .map_err(|e| InferencePresetError::DynamoDb(e.to_string()))?;
+
+        result
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|item| item.get("preset").and_then(|v| v.as_s().ok()))
+            .map(|definition| {
+                serde_json::from_str(definition).map_err(|e| InferencePresetError::Parse(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Errors that can occur during inference preset operations
+#[derive(Debug, thiserror::Error)]
+pub enum InferencePresetError {
+    #[error("DynamoDB error: {0}")]
+    DynamoDb(String),
+
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
@@ -3,9 +3,15 @@
 //! Data access objects for DynamoDB tables.
 
 pub mod api_key;
+pub mod feature_flag;
+pub mod inference_preset;
 pub mod model_mapping;
+pub mod model_pricing;
 pub mod usage;
 
-pub use api_key::{ApiKeyError, ApiKeyRepository};
+pub use api_key::{ApiKeyError, ApiKeyRepository, KeyQuota};
+pub use feature_flag::{FeatureFlagError, FeatureFlagRepository};
+pub use inference_preset::{InferencePresetError, InferencePresetRepository};
 pub use model_mapping::{ModelMappingError, ModelMappingRepository};
+pub use model_pricing::{ModelPricingError, ModelPricingRepository};
 pub use usage::{UsageError, UsageRepository};
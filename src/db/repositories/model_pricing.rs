@@ -0,0 +1,46 @@
+//! Model pricing repository
+//!
+//! Data access layer for per-model token prices.
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::sync::Arc;
+
+use crate::db::models::ModelPricing;
+use crate::db::DynamoDbClient;
+
+/// Repository for model pricing lookups
+#[derive(Clone)]
+pub struct ModelPricingRepository {
+    client: Arc<DynamoDbClient>,
+}
+
+impl ModelPricingRepository {
+    /// Create a new model pricing repository
+    pub fn new(client: Arc<DynamoDbClient>) -> Self {
+        Self { client }
+    }
+
+    /// Get the pricing for a model ID
+    ///
+    /// Returns None if the model has no pricing entry.
+    pub async fn get_pricing(&self, model_id: &str) -> Result<Option<ModelPricing>, ModelPricingError> {
+        let result = self
+            .client
+            .client()
+            .get_item()
+            .table_name(self.client.model_pricing_table())
+            .key("model_id", AttributeValue::S(model_id.to_string()))
+            .send()
+            .await
+            .map_err(|e| ModelPricingError::DynamoDb(e.to_string()))?;
+
+        Ok(result.item.as_ref().and_then(ModelPricing::from_dynamodb))
+    }
+}
+
+/// Errors that can occur during model pricing operations
+#[derive(Debug, thiserror::Error)]
+pub enum ModelPricingError {
+    #[error("DynamoDB error: {0}")]
+    DynamoDb(String),
+}
@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use crate::db::models::{UsageRecord, UsageStats};
 use crate::db::DynamoDbClient;
+use crate::utils::redact_key;
 
 /// Repository for usage tracking operations
 #[derive(Clone)]
@@ -32,7 +33,7 @@ impl UsageRepository {
             .map_err(|e| UsageError::DynamoDb(e.to_string()))?;
 
         tracing::debug!(
-            api_key = %&record.api_key[..20.min(record.api_key.len())],
+            api_key = %redact_key(&record.api_key),
             request_id = %record.request_id,
             model = %record.model,
             input_tokens = record.input_tokens,
@@ -11,7 +11,31 @@ use chrono::Utc;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 
 use crate::db::models::{ApiKey, UsageRecord};
-use crate::db::storage::{StorageBackend, StorageError};
+use crate::db::storage::{KeyQuota, StorageBackend, StorageError};
+use crate::services::InferencePreset;
+
+/// Columns added after their table was first released, with their definitions
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves an existing table as it is, so
+/// databases created by an older release get these through `ALTER TABLE`.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("api_keys", "scopes", "TEXT"),
+    ("api_keys", "expires_at", "INTEGER"),
+    ("api_keys", "allowed_models", "TEXT"),
+    ("api_keys", "allowed_ips", "TEXT"),
+    ("api_keys", "parent_key", "TEXT"),
+    ("api_keys", "last_used_at", "INTEGER"),
+    ("api_keys", "request_count", "INTEGER NOT NULL DEFAULT 0"),
+    ("api_keys", "recent_source_ips", "TEXT"),
+    ("api_keys", "ptc_max_iterations", "INTEGER"),
+    ("api_keys", "ptc_max_tokens_per_iteration", "INTEGER"),
+    ("api_keys", "ptc_session_token_budget", "INTEGER"),
+    ("api_keys", "inference_preset", "TEXT"),
+    ("usage_records", "metadata", "TEXT"),
+    ("usage_records", "backend", "TEXT"),
+    ("usage_records", "attempts", "INTEGER"),
+    ("usage_records", "added_latency_ms", "INTEGER"),
+];
 
 /// SQLite implementation of StorageBackend.
 pub struct SqliteBackend {
@@ -53,7 +77,19 @@ impl SqliteBackend {
                 budget_used_mtd REAL NOT NULL DEFAULT 0.0,
                 budget_mtd_month TEXT,
                 deactivated_reason TEXT,
-                tpm_limit INTEGER
+                tpm_limit INTEGER,
+                scopes TEXT,
+                expires_at INTEGER,
+                allowed_models TEXT,
+                allowed_ips TEXT,
+                parent_key TEXT,
+                last_used_at INTEGER,
+                request_count INTEGER NOT NULL DEFAULT 0,
+                recent_source_ips TEXT,
+                ptc_max_iterations INTEGER,
+                ptc_max_tokens_per_iteration INTEGER,
+                ptc_session_token_budget INTEGER,
+                inference_preset TEXT
             )"#,
             r#"CREATE TABLE IF NOT EXISTS usage_records (
                 api_key TEXT NOT NULL,
@@ -67,12 +103,20 @@ impl SqliteBackend {
                 success INTEGER NOT NULL DEFAULT 0,
                 duration_ms INTEGER,
                 error_message TEXT,
+                metadata TEXT,
+                backend TEXT,
+                attempts INTEGER,
+                added_latency_ms INTEGER,
                 PRIMARY KEY (api_key, timestamp)
             )"#,
             r#"CREATE TABLE IF NOT EXISTS model_mappings (
                 source_model_id TEXT PRIMARY KEY,
                 target_model_id TEXT NOT NULL
             )"#,
+            r#"CREATE TABLE IF NOT EXISTS inference_presets (
+                name TEXT PRIMARY KEY,
+                preset TEXT NOT NULL
+            )"#,
         ];
 
         for query in &queries {
@@ -82,6 +126,29 @@ impl SqliteBackend {
                 .map_err(|e| StorageError::Query(e.to_string()))?;
         }
 
+        self.add_missing_columns().await
+    }
+
+    /// Add the [`ADDED_COLUMNS`] a database created by an older release lacks
+    async fn add_missing_columns(&self) -> Result<(), StorageError> {
+        for (table, column, definition) in ADDED_COLUMNS {
+            let existing = sqlx::query("SELECT 1 FROM pragma_table_info(?) WHERE name = ?")
+                .bind(table)
+                .bind(column)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| StorageError::Query(e.to_string()))?;
+            if existing.is_some() {
+                continue;
+            }
+
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| StorageError::Query(e.to_string()))?;
+            tracing::info!(table = %table, column = %column, "Added column to SQLite table");
+        }
+
         Ok(())
     }
 
@@ -105,7 +172,57 @@ impl SqliteBackend {
             budget_mtd_month: row.get("budget_mtd_month"),
             deactivated_reason: row.get("deactivated_reason"),
             tpm_limit: row.get("tpm_limit"),
+            scopes: Self::get_list(row, "scopes"),
+            expires_at: row.try_get::<Option<i64>, _>("expires_at").ok().flatten(),
+            allowed_models: Self::get_list(row, "allowed_models"),
+            allowed_ips: Self::get_list(row, "allowed_ips"),
+            parent_key: row.try_get::<Option<String>, _>("parent_key").ok().flatten(),
+            last_used_at: row.try_get::<Option<i64>, _>("last_used_at").ok().flatten(),
+            request_count: row.try_get::<i64, _>("request_count").unwrap_or(0),
+            recent_source_ips: Self::get_list(row, "recent_source_ips"),
+            ptc_max_iterations: row
+                .try_get::<Option<i64>, _>("ptc_max_iterations")
+                .ok()
+                .flatten()
+                .map(|n| n as u32),
+            ptc_max_tokens_per_iteration: row
+                .try_get::<Option<i64>, _>("ptc_max_tokens_per_iteration")
+                .ok()
+                .flatten()
+                .map(|n| n as u32),
+            ptc_session_token_budget: row
+                .try_get::<Option<i64>, _>("ptc_session_token_budget")
+                .ok()
+                .flatten()
+                .map(|n| n as u64),
+            inference_preset: row
+                .try_get::<Option<String>, _>("inference_preset")
+                .ok()
+                .flatten(),
+        }
+    }
+
+    /// Read a comma-separated TEXT column (tolerates databases created before the column existed)
+    fn get_list(row: &sqlx::sqlite::SqliteRow, column: &str) -> Vec<String> {
+        use sqlx::Row;
+        row.try_get::<Option<String>, _>(column)
+            .ok()
+            .flatten()
+            .map(|s| {
+                s.split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The stored key after an UPDATE, or NotFound if the UPDATE matched no row
+    async fn updated_key(&self, key: &str, rows_affected: u64) -> Result<ApiKey, StorageError> {
+        if rows_affected == 0 {
+            return Err(StorageError::NotFound);
         }
+        self.get_api_key(key).await?.ok_or(StorageError::NotFound)
     }
 
     fn row_to_usage(row: &sqlx::sqlite::SqliteRow) -> UsageRecord {
@@ -122,6 +239,16 @@ impl SqliteBackend {
             success: row.get::<i32, _>("success") != 0,
             duration_ms: row.get("duration_ms"),
             error_message: row.get("error_message"),
+            // JSON object; tolerates databases created before the column existed
+            metadata: row
+                .try_get::<Option<String>, _>("metadata")
+                .ok()
+                .flatten()
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            backend: row.try_get("backend").ok().flatten(),
+            attempts: row.try_get("attempts").ok().flatten(),
+            added_latency_ms: row.try_get("added_latency_ms").ok().flatten(),
         }
     }
 }
@@ -179,6 +306,52 @@ impl StorageBackend for SqliteBackend {
         Ok(row.as_ref().map(Self::row_to_api_key))
     }
 
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError> {
+        let list = |items: &[String]| (!items.is_empty()).then(|| items.join(","));
+        sqlx::query(
+            "INSERT INTO api_keys (api_key, user_id, name, created_at, updated_at, is_active, \
+             rate_limit, service_tier, owner_name, role, monthly_budget, budget_used, \
+             budget_used_mtd, budget_mtd_month, deactivated_reason, tpm_limit, scopes, \
+             expires_at, allowed_models, allowed_ips, parent_key, last_used_at, request_count, \
+             recent_source_ips, ptc_max_iterations, ptc_max_tokens_per_iteration, \
+             ptc_session_token_budget, inference_preset) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&key.api_key)
+        .bind(&key.user_id)
+        .bind(&key.name)
+        .bind(key.created_at)
+        .bind(key.updated_at)
+        .bind(key.is_active as i32)
+        .bind(key.rate_limit)
+        .bind(&key.service_tier)
+        .bind(&key.owner_name)
+        .bind(&key.role)
+        .bind(key.monthly_budget)
+        .bind(key.budget_used)
+        .bind(key.budget_used_mtd)
+        .bind(&key.budget_mtd_month)
+        .bind(&key.deactivated_reason)
+        .bind(key.tpm_limit)
+        .bind(list(&key.scopes))
+        .bind(key.expires_at)
+        .bind(list(&key.allowed_models))
+        .bind(list(&key.allowed_ips))
+        .bind(&key.parent_key)
+        .bind(key.last_used_at)
+        .bind(key.request_count)
+        .bind(list(&key.recent_source_ips))
+        .bind(key.ptc_max_iterations.map(i64::from))
+        .bind(key.ptc_max_tokens_per_iteration.map(i64::from))
+        .bind(key.ptc_session_token_budget.map(|n| n as i64))
+        .bind(&key.inference_preset)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        Ok(())
+    }
+
     async fn increment_budget_used(&self, key: &str, amount: f64) -> Result<bool, StorageError> {
         let api_key = self
             .get_api_key(key)
@@ -253,11 +426,130 @@ impl StorageBackend for SqliteBackend {
         Ok(())
     }
 
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, StorageError> {
+        let rows = sqlx::query("SELECT * FROM api_keys")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_api_key).collect())
+    }
+
+    async fn set_api_key_quota(&self, key: &str, quota: &KeyQuota) -> Result<ApiKey, StorageError> {
+        // Each flag says whether the column changes; the new value may be
+        // NULL, which removes the limit
+        let result = sqlx::query(
+            "UPDATE api_keys SET \
+             rate_limit = COALESCE(?, rate_limit), \
+             tpm_limit = CASE WHEN ? THEN ? ELSE tpm_limit END, \
+             monthly_budget = CASE WHEN ? THEN ? ELSE monthly_budget END, \
+             updated_at = ? WHERE api_key = ?",
+        )
+        .bind(quota.rate_limit)
+        .bind(quota.tpm_limit.is_some())
+        .bind(quota.tpm_limit.flatten())
+        .bind(quota.monthly_budget.is_some())
+        .bind(quota.monthly_budget.flatten())
+        .bind(Utc::now().timestamp())
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        self.updated_key(key, result.rows_affected()).await
+    }
+
+    async fn swap_monthly_budget(
+        &self,
+        key: &str,
+        expected: f64,
+        budget: f64,
+    ) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET monthly_budget = ?, updated_at = ? \
+             WHERE api_key = ? AND monthly_budget = ?",
+        )
+        .bind(budget)
+        .bind(Utc::now().timestamp())
+        .bind(key)
+        .bind(expected)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn extend_api_key(&self, key: &str, expires_at: i64) -> Result<ApiKey, StorageError> {
+        let result = sqlx::query("UPDATE api_keys SET expires_at = ?, updated_at = ? WHERE api_key = ?")
+            .bind(expires_at)
+            .bind(Utc::now().timestamp())
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        self.updated_key(key, result.rows_affected()).await
+    }
+
+    async fn upgrade_api_key(
+        &self,
+        key: &str,
+        service_tier: &str,
+        rate_limit: Option<i32>,
+        monthly_budget: Option<f64>,
+    ) -> Result<ApiKey, StorageError> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET service_tier = ?, \
+             rate_limit = COALESCE(?, rate_limit), \
+             monthly_budget = COALESCE(?, monthly_budget), \
+             expires_at = NULL, allowed_models = NULL, updated_at = ? \
+             WHERE api_key = ?",
+        )
+        .bind(service_tier)
+        .bind(rate_limit)
+        .bind(monthly_budget)
+        .bind(Utc::now().timestamp())
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        self.updated_key(key, result.rows_affected()).await
+    }
+
+    async fn record_key_activity(
+        &self,
+        key: &str,
+        last_used_at: i64,
+        request_delta: i64,
+        recent_source_ips: &[String],
+    ) -> Result<(), StorageError> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET last_used_at = ?, recent_source_ips = ?, \
+             request_count = request_count + ? WHERE api_key = ?",
+        )
+        .bind(last_used_at)
+        .bind((!recent_source_ips.is_empty()).then(|| recent_source_ips.join(",")))
+        .bind(request_delta)
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
     async fn record_usage(&self, record: &UsageRecord) -> Result<(), StorageError> {
         sqlx::query(
             "INSERT INTO usage_records (api_key, timestamp, request_id, model, \
              input_tokens, output_tokens, cached_tokens, cache_write_tokens, \
-             success, duration_ms, error_message) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             success, duration_ms, error_message, metadata, backend, attempts, \
+             added_latency_ms) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&record.api_key)
         .bind(&record.timestamp)
@@ -270,6 +562,13 @@ impl StorageBackend for SqliteBackend {
         .bind(record.success as i32)
         .bind(record.duration_ms)
         .bind(&record.error_message)
+        .bind(
+            (!record.metadata.is_empty())
+                .then(|| serde_json::to_string(&record.metadata).unwrap_or_default()),
+        )
+        .bind(&record.backend)
+        .bind(record.attempts)
+        .bind(record.added_latency_ms)
         .execute(&self.pool)
         .await
         .map_err(|e| StorageError::Query(e.to_string()))?;
@@ -341,6 +640,47 @@ impl StorageBackend for SqliteBackend {
         Ok(())
     }
 
+    async fn list_inference_presets(&self) -> Result<Vec<InferencePreset>, StorageError> {
+        let rows = sqlx::query("SELECT preset FROM inference_presets")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        rows.iter()
+            .map(|row| {
+                use sqlx::Row;
+                serde_json::from_str(&row.get::<String, _>("preset"))
+                    .map_err(|e| StorageError::Parse(e.to_string()))
+            })
+            .collect()
+    }
+
+    async fn put_inference_preset(&self, preset: &InferencePreset) -> Result<(), StorageError> {
+        let json =
+            serde_json::to_string(preset).map_err(|e| StorageError::Parse(e.to_string()))?;
+        sqlx::query("INSERT OR REPLACE INTO inference_presets (name, preset) VALUES (?, ?)")
+            .bind(&preset.name)
+            .bind(json)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_inference_preset(&self, name: &str) -> Result<(), StorageError> {
+        let result = sqlx::query("DELETE FROM inference_presets WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StorageError::Query(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
     async fn health_check(&self) -> bool {
         sqlx::query("SELECT 1")
             .fetch_one(&self.pool)
@@ -371,6 +711,35 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_create_api_key_round_trip() {
+        let backend = create_test_backend().await;
+        let key: ApiKey = serde_json::from_value(serde_json::json!({
+            "api_key": "sk-created",
+            "user_id": "user-1",
+            "name": "Created",
+            "created_at": 1_700_000_000,
+            "is_active": true,
+            "rate_limit": 100,
+            "service_tier": "default",
+            "scopes": ["backend_override", "priority"],
+            "allowed_models": ["claude-sonnet-4-5"],
+            "tpm_limit": 50_000,
+            "ptc_session_token_budget": 200_000,
+            "inference_preset": "support-bot"
+        }))
+        .unwrap();
+        backend.create_api_key(&key).await.unwrap();
+
+        let stored = backend.get_api_key("sk-created").await.unwrap().unwrap();
+        assert_eq!(stored.scopes, key.scopes);
+        assert_eq!(stored.allowed_models, key.allowed_models);
+        assert_eq!(stored.tpm_limit, Some(50_000));
+        assert_eq!(stored.ptc_session_token_budget, Some(200_000));
+        assert_eq!(stored.inference_preset.as_deref(), Some("support-bot"));
+        assert!(backend.create_api_key(&key).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_health_check() {
         let backend = create_test_backend().await;
@@ -419,6 +788,10 @@ mod tests {
             success: true,
             duration_ms: Some(500),
             error_message: None,
+            metadata: std::collections::HashMap::from([("team".to_string(), "search".to_string())]),
+            backend: Some("bedrock".to_string()),
+            attempts: Some(1),
+            added_latency_ms: Some(0),
         };
 
         backend.record_usage(&record).await.unwrap();
@@ -430,6 +803,8 @@ mod tests {
         assert_eq!(records.len(), 1);
         assert_eq!(records[0].input_tokens, 100);
         assert_eq!(records[0].model, "claude-3-sonnet");
+        assert_eq!(records[0].metadata, record.metadata);
+        assert_eq!(records[0].backend.as_deref(), Some("bedrock"));
     }
 
     #[tokio::test]
@@ -451,6 +826,84 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_inference_presets() {
+        let backend = create_test_backend().await;
+        let preset: InferencePreset = serde_json::from_value(serde_json::json!({
+            "name": "support-bot",
+            "temperature": 0.2,
+            "models": ["claude-sonnet-*"]
+        }))
+        .unwrap();
+
+        backend.put_inference_preset(&preset).await.unwrap();
+        assert_eq!(backend.list_inference_presets().await.unwrap(), vec![preset]);
+
+        backend.delete_inference_preset("support-bot").await.unwrap();
+        assert!(backend.list_inference_presets().await.unwrap().is_empty());
+        assert!(matches!(
+            backend.delete_inference_preset("support-bot").await,
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_migrates_older_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("proxy.db").display());
+
+        // The api_keys table as the first release created it
+        let pool = SqlitePoolOptions::new().connect(&url).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE api_keys (
+                api_key TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                name TEXT NOT NULL DEFAULT '',
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER,
+                is_active INTEGER NOT NULL DEFAULT 1,
+                rate_limit INTEGER NOT NULL DEFAULT 100,
+                service_tier TEXT NOT NULL DEFAULT 'default',
+                owner_name TEXT,
+                role TEXT,
+                monthly_budget REAL,
+                budget_used REAL NOT NULL DEFAULT 0.0,
+                budget_used_mtd REAL NOT NULL DEFAULT 0.0,
+                budget_mtd_month TEXT,
+                deactivated_reason TEXT,
+                tpm_limit INTEGER
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO api_keys (api_key, user_id, created_at) VALUES ('sk-old', 'user-1', 1700000000)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+
+        let backend = SqliteBackend::new(&url).await.unwrap();
+        let stored = backend.get_api_key("sk-old").await.unwrap().unwrap();
+        assert_eq!(stored.request_count, 0);
+        assert!(stored.inference_preset.is_none());
+
+        let quota = KeyQuota { tpm_limit: Some(Some(1000)), ..Default::default() };
+        backend.set_api_key_quota("sk-old", &quota).await.unwrap();
+        backend
+            .record_key_activity("sk-old", 1_700_000_100, 1, &["10.0.0.1".to_string()])
+            .await
+            .unwrap();
+        let stored = backend.get_api_key("sk-old").await.unwrap().unwrap();
+        assert_eq!(stored.request_count, 1);
+
+        // Running the migrations again changes nothing
+        drop(backend);
+        assert!(SqliteBackend::new(&url).await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_increment_budget() {
         let backend = create_test_backend().await;
@@ -1,13 +1,16 @@
 //! Storage backend trait abstraction.
 //!
 //! Defines a provider-agnostic storage interface so the application can use
-//! DynamoDB, SQLite, PostgreSQL, or other backends interchangeably.
+//! DynamoDB, SQLite, PostgreSQL, or other backends interchangeably. The
+//! backend is picked with `STORAGE_BACKEND` (see [`StorageKind`]).
 
 use crate::db::models::{ApiKey, UsageRecord};
+use crate::services::InferencePreset;
 
 /// Unified storage backend trait.
 ///
-/// All storage operations (API keys, usage, model mappings) go through this trait,
+/// All storage operations (API keys, usage, model mappings, inference presets)
+/// go through this trait,
 /// enabling the application to work with different database backends.
 #[async_trait::async_trait]
 pub trait StorageBackend: Send + Sync {
@@ -19,6 +22,9 @@ pub trait StorageBackend: Send + Sync {
     /// Get an API key without validation logic.
     async fn get_api_key(&self, key: &str) -> Result<Option<ApiKey>, StorageError>;
 
+    /// Store a new API key. Fails if a key with the same value exists.
+    async fn create_api_key(&self, key: &ApiKey) -> Result<(), StorageError>;
+
     /// Increment budget usage. Returns `true` if budget was exceeded (key deactivated).
     async fn increment_budget_used(&self, key: &str, amount: f64) -> Result<bool, StorageError>;
 
@@ -26,6 +32,65 @@ pub trait StorageBackend: Send + Sync {
     async fn deactivate_api_key(&self, key: &str, reason: Option<&str>)
         -> Result<(), StorageError>;
 
+    /// List all API keys (admin tooling, not the request path).
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, StorageError>;
+
+    /// Change a key's rate limit, TPM limit or monthly budget; returns the updated key.
+    ///
+    /// Fails with [`StorageError::NotFound`] for unknown keys.
+    async fn set_api_key_quota(&self, key: &str, quota: &KeyQuota) -> Result<ApiKey, StorageError>;
+
+    /// Set a key's monthly budget only if it still equals `expected`.
+    ///
+    /// Returns `false`, writing nothing, if the key is gone or its budget
+    /// changed since it was read.
+    async fn swap_monthly_budget(
+        &self,
+        key: &str,
+        expected: f64,
+        budget: f64,
+    ) -> Result<bool, StorageError>;
+
+    /// Set a new expiry timestamp on a key; returns the updated key.
+    async fn extend_api_key(&self, key: &str, expires_at: i64) -> Result<ApiKey, StorageError>;
+
+    /// Move a key to a new service tier, clearing its trial expiry and model
+    /// restrictions; returns the updated key.
+    async fn upgrade_api_key(
+        &self,
+        key: &str,
+        service_tier: &str,
+        rate_limit: Option<i32>,
+        monthly_budget: Option<f64>,
+    ) -> Result<ApiKey, StorageError>;
+
+    /// Record buffered activity: last-used time, recent source IPs, and
+    /// `request_delta` more requests. Fails with NotFound for unknown keys.
+    async fn record_key_activity(
+        &self,
+        key: &str,
+        last_used_at: i64,
+        request_delta: i64,
+        recent_source_ips: &[String],
+    ) -> Result<(), StorageError>;
+
+    /// Revoke a key. Fails with NotFound for unknown keys.
+    async fn revoke_api_key(&self, key: &str) -> Result<(), StorageError> {
+        if self.get_api_key(key).await?.is_none() {
+            return Err(StorageError::NotFound);
+        }
+        self.deactivate_api_key(key, Some("revoked")).await
+    }
+
+    /// Replace a key with `replacement`
+    ///
+    /// Stores the replacement first, then deactivates the old key, so a
+    /// failed rotation never leaves the caller without a working key.
+    async fn rotate_api_key(&self, old_key: &str, replacement: &ApiKey) -> Result<(), StorageError> {
+        self.create_api_key(replacement).await?;
+        self.deactivate_api_key(old_key, Some("rotated")).await
+    }
+
     // ── Usage operations ────────────────────────────────────────────
 
     /// Record a usage event.
@@ -48,12 +113,72 @@ pub trait StorageBackend: Send + Sync {
     /// Set a model mapping.
     async fn set_model_mapping(&self, from: &str, to: &str) -> Result<(), StorageError>;
 
+    // ── Inference preset operations ─────────────────────────────────
+
+    /// List all stored inference presets.
+    async fn list_inference_presets(&self) -> Result<Vec<InferencePreset>, StorageError>;
+
+    /// Store an inference preset, replacing any with the same name.
+    async fn put_inference_preset(&self, preset: &InferencePreset) -> Result<(), StorageError>;
+
+    /// Delete an inference preset (NotFound if there is none by that name).
+    async fn delete_inference_preset(&self, name: &str) -> Result<(), StorageError>;
+
     // ── Health ──────────────────────────────────────────────────────
 
     /// Check if the storage backend is healthy / reachable.
     async fn health_check(&self) -> bool;
 }
 
+/// Quota changes for [`StorageBackend::set_api_key_quota`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyQuota {
+    /// Requests per window
+    pub rate_limit: Option<i32>,
+    /// Tokens per minute (`Some(None)` removes the limit)
+    pub tpm_limit: Option<Option<i32>>,
+    /// Monthly budget in USD (`Some(None)` removes the limit)
+    pub monthly_budget: Option<Option<f64>>,
+}
+
+impl KeyQuota {
+    /// Whether the quota changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.rate_limit.is_none() && self.tpm_limit.is_none() && self.monthly_budget.is_none()
+    }
+}
+
+/// Storage backend selected in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    /// DynamoDB tables (the default)
+    DynamoDb,
+    /// SQLite database at `DATABASE_URL` (needs the `sqlite` feature)
+    Sqlite,
+    /// Process memory; nothing survives a restart
+    Memory,
+}
+
+impl StorageKind {
+    /// Parse a `STORAGE_BACKEND` value
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "dynamodb" | "dynamo" => Some(Self::DynamoDb),
+            "sqlite" => Some(Self::Sqlite),
+            "memory" | "in-memory" | "in_memory" => Some(Self::Memory),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DynamoDb => "dynamodb",
+            Self::Sqlite => "sqlite",
+            Self::Memory => "memory",
+        }
+    }
+}
+
 /// Errors from storage operations.
 #[derive(Debug, thiserror::Error)]
 pub enum StorageError {
@@ -2,6 +2,8 @@
 //!
 //! Contains custom error types and conversions.
 
+mod taxonomy;
 mod types;
 
+pub use taxonomy::{ErrorTaxonomy, ERROR_CODE_HEADER, ERROR_RETRYABLE_HEADER};
 pub use types::ApiError;
@@ -0,0 +1,80 @@
+//! Machine-readable error classification
+//!
+//! Every error response carries an error code and whether retrying the same
+//! request may succeed, in the `x-error-code` and `x-error-retryable`
+//! headers and as `error_code` and `retryable` in the body's `error` object,
+//! whichever API format the body is in. Client middleware can then apply one
+//! retry policy across providers.
+//!
+//! Backend failures are classified from the [`BedrockError`] they map to
+//! (Gemini, Anthropic and Azure errors are mapped onto it too); every other
+//! error is classified from its status code.
+
+use axum::http::StatusCode;
+
+use crate::services::BedrockError;
+
+/// Header naming the error code
+pub const ERROR_CODE_HEADER: &str = "x-error-code";
+
+/// Header saying whether the request may be retried (`true` or `false`)
+pub const ERROR_RETRYABLE_HEADER: &str = "x-error-retryable";
+
+/// Error code and retryability of an error response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorTaxonomy {
+    pub code: &'static str,
+    pub retryable: bool,
+}
+
+impl ErrorTaxonomy {
+    pub fn from_bedrock_error(err: &BedrockError) -> Self {
+        Self {
+            code: err.code(),
+            retryable: err.is_retryable(),
+        }
+    }
+
+    /// Classification of an error that only has its status to go on
+    ///
+    /// Timeouts, conflicts, rate limits and server-side failures are
+    /// retryable, as the Anthropic and OpenAI SDKs treat them.
+    pub fn from_status(status: StatusCode) -> Self {
+        let code = match status.as_u16() {
+            400 | 422 => "invalid_request",
+            401 => "authentication_failed",
+            403 => "permission_denied",
+            404 => "not_found",
+            408 | 504 => "timeout",
+            409 => "conflict",
+            413 => "request_too_large",
+            415 => "unsupported_media_type",
+            429 => "rate_limited",
+            502 => "bad_gateway",
+            503 | 529 => "overloaded",
+            500..=599 => "internal_error",
+            _ => "client_error",
+        };
+        let retryable = matches!(status.as_u16(), 408 | 409 | 429 | 500 | 502..=504 | 529);
+        Self { code, retryable }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taxonomy() {
+        let throttled = ErrorTaxonomy::from_bedrock_error(&BedrockError::Throttled("slow down".into()));
+        assert_eq!(throttled, ErrorTaxonomy { code: "throttled", retryable: true });
+        let retired = ErrorTaxonomy::from_bedrock_error(&BedrockError::ModelEndOfLife("old".into()));
+        assert_eq!(retired, ErrorTaxonomy { code: "model_end_of_life", retryable: false });
+
+        assert!(ErrorTaxonomy::from_status(StatusCode::TOO_MANY_REQUESTS).retryable);
+        assert!(ErrorTaxonomy::from_status(StatusCode::from_u16(529).unwrap()).retryable);
+        assert!(!ErrorTaxonomy::from_status(StatusCode::NOT_IMPLEMENTED).retryable);
+        let invalid = ErrorTaxonomy::from_status(StatusCode::BAD_REQUEST);
+        assert_eq!(invalid, ErrorTaxonomy { code: "invalid_request", retryable: false });
+    }
+}
@@ -16,6 +16,9 @@ pub enum ApiError {
     #[error("Forbidden: {0}")]
     Forbidden(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
@@ -45,6 +48,11 @@ impl IntoResponse for ApiError {
                 "forbidden_error",
                 msg,
             ),
+            ApiError::NotFound(msg) => (
+                StatusCode::NOT_FOUND,
+                "not_found_error",
+                msg,
+            ),
             ApiError::RateLimitExceeded => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "rate_limit_error",
@@ -1,19 +1,26 @@
 //! Anthropic-Bedrock API Proxy library
 
-// Public modules
+// Public modules (the gateway layers need the `dynamodb` feature; without it
+// this is a converter-only library)
+#[cfg(feature = "dynamodb")]
 pub mod api;
 pub mod config;
+pub mod conformance;
 pub mod converters;
 pub mod db;
 pub mod error;
 pub mod logging;
+#[cfg(feature = "dynamodb")]
 pub mod middleware;
 pub mod schemas;
+#[cfg(feature = "dynamodb")]
 pub mod server;
 pub mod services;
 pub mod utils;
 
 // Re-export commonly used types
 pub use config::Settings;
+pub use converters::{ApiFormat, ConversionContext, Converter, ConverterRegistry};
 pub use error::ApiError;
+#[cfg(feature = "dynamodb")]
 pub use server::App;
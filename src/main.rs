@@ -40,10 +40,28 @@ struct Args {
     #[arg(long)]
     print_prompts: bool,
 
+    /// Write each request and its conversion as a JSON file into this directory
+    /// (one file per request ID) instead of printing prompts
+    #[arg(long)]
+    print_prompts_dir: Option<PathBuf>,
+
     /// Log file path for JSON logs (enables file logging with 10MB rotation)
     /// Example: --log-file /var/log/proxy/app.log
     #[arg(long)]
     log_file: Option<PathBuf>,
+
+    /// Number of ephemeral API keys to mint at startup (one per local client)
+    #[arg(long, default_value_t = 1)]
+    ephemeral_keys: usize,
+
+    /// Lifetime of startup ephemeral keys in seconds (default: until shutdown)
+    #[arg(long)]
+    ephemeral_ttl: Option<i64>,
+
+    /// Comma-separated scopes granted to startup ephemeral keys
+    /// Example: --ephemeral-scopes admin,backend_override
+    #[arg(long, value_delimiter = ',')]
+    ephemeral_scopes: Vec<String>,
 }
 
 #[tokio::main]
@@ -70,23 +88,13 @@ async fn main() -> Result<()> {
     if args.print_prompts {
         settings.print_prompts = true;
     }
-
-    // Generate ephemeral API key for development
-    let ephemeral_key = settings.generate_ephemeral_key();
+    if let Some(dir) = args.print_prompts_dir {
+        settings.print_prompts_dir = Some(dir.to_string_lossy().into_owned());
+    }
 
     // Initialize tracing subscriber with JSON output
     init_tracing(&settings.log_level, args.log_file.as_ref());
 
-    // Print ephemeral API key to console
-    println!("\n{}", "=".repeat(60));
-    println!("  Ephemeral API Key (valid for this session only):");
-    println!("  {}", ephemeral_key);
-    println!("{}\n", "=".repeat(60));
-    println!("  Usage:");
-    println!("    export ANTHROPIC_API_KEY=\"{}\"", ephemeral_key);
-    println!("    export ANTHROPIC_BASE_URL=\"http://{}:{}\"\n", settings.host, settings.port);
-    println!("{}\n", "=".repeat(60));
-
     tracing::info!(
         app_name = %settings.app_name,
         version = %settings.app_version,
@@ -96,9 +104,43 @@ async fn main() -> Result<()> {
         "Starting application"
     );
 
+    let host = settings.host.clone();
+    let port = settings.port;
+
     // Build the application
     let app = App::new(settings).await?;
 
+    // Mint ephemeral API keys for development (valid for this session only)
+    let ephemeral_keys: Vec<_> = (0..args.ephemeral_keys)
+        .map(|i| {
+            app.state().ephemeral_keys.mint(
+                Some(format!("startup-{}", i + 1)),
+                args.ephemeral_ttl,
+                args.ephemeral_scopes.clone(),
+            )
+        })
+        .collect();
+
+    if let Some(first) = ephemeral_keys.first() {
+        // Print ephemeral API keys to console
+        println!("\n{}", "=".repeat(60));
+        println!("  Ephemeral API Keys (valid for this session only):");
+        for key in &ephemeral_keys {
+            let expiry = key
+                .expires_at
+                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                .map(|dt| format!("expires {}", dt.to_rfc3339()))
+                .unwrap_or_else(|| "no expiry".to_string());
+            println!("  [{}] {} ({})", key.id, key.key, expiry);
+        }
+        println!("{}\n", "=".repeat(60));
+        println!("  Usage:");
+        println!("    export ANTHROPIC_API_KEY=\"{}\"", first.key);
+        println!("    export ANTHROPIC_BASE_URL=\"http://{}:{}\"\n", host, port);
+        println!("  Mint, list or revoke more keys at runtime via /admin/ephemeral-keys");
+        println!("{}\n", "=".repeat(60));
+    }
+
     // Run the server with graceful shutdown
     app.run_with_graceful_shutdown().await?;
 
@@ -1,29 +1,55 @@
 //! Authentication middleware
 //!
 //! This module provides API key authentication for the Anthropic-Bedrock proxy.
-//! It validates API keys against DynamoDB and supports a master key for admin access.
+//! It validates API keys against the storage backend and supports a master key for admin access.
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{MatchedPath, State},
+    http::{HeaderMap, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::sync::Arc;
 
-use crate::config::Settings;
-use crate::db::repositories::{ApiKeyError, ApiKeyRepository};
-use crate::db::DynamoDbClient;
+use crate::config::{Settings, TrialConfig};
+use crate::db::models::{ApiKey, TRIAL_SERVICE_TIER};
+use crate::db::{StorageBackend, StorageError};
 use crate::schemas::anthropic::ErrorResponse;
-use crate::utils::truncate_str;
+use crate::services::provider::model_matches_pattern;
+use crate::middleware::brute_force::AuthFailureGuard;
+use crate::services::{EphemeralKey, EphemeralKeyManager, KeyActivityTracker, PtcBudget};
+use crate::utils::{client_ip, IpCidr, TrustedProxies};
+use crate::utils::redact_key;
 
 // ============================================================================
 // API Key Info
 // ============================================================================
 
+/// Scope allowing a key to force a backend via the `x-llm-backend` header
+pub const SCOPE_BACKEND_OVERRIDE: &str = "backend_override";
+
+/// Scope granting access to the `/admin` endpoints
+pub const SCOPE_ADMIN: &str = "admin";
+
+/// Scope allowing a key to mint child keys via `POST /v1/keys`
+pub const SCOPE_CHILD_KEYS: &str = "child_keys";
+
+/// Scope allowing a key to send high-priority requests via `x-priority`
+pub const SCOPE_PRIORITY: &str = "priority";
+
+/// Scope allowing a key to send `additionalModelRequestFields` via `x-bedrock-extra-fields`
+pub const SCOPE_BEDROCK_EXTRA_FIELDS: &str = "bedrock_extra_fields";
+
+/// Scope opting a key in to having oversized response content offloaded to S3
+pub const SCOPE_CONTENT_OFFLOAD: &str = "content_offload";
+
+/// Header carrying the admin key for the `/admin/api-keys` endpoints
+pub const ADMIN_KEY_HEADER: &str = "x-admin-key";
+
 /// Information about the authenticated API key
 ///
 /// This struct is injected into request extensions after successful authentication.
@@ -32,6 +58,10 @@ pub struct ApiKeyInfo {
     /// The API key string (truncated for security in logs)
     pub api_key: String,
 
+    /// Opaque identifier hashed from the full key (see `ApiKey::key_id`)
+    #[serde(default)]
+    pub key_id: String,
+
     /// The user ID associated with this key
     pub user_id: String,
 
@@ -41,6 +71,10 @@ pub struct ApiKeyInfo {
     /// Custom rate limit for this key (requests per minute)
     pub rate_limit: Option<u32>,
 
+    /// Custom token rate limit for this key (tokens per minute)
+    #[serde(default)]
+    pub tpm_limit: Option<u32>,
+
     /// Service tier (affects pricing multiplier)
     pub service_tier: String,
 
@@ -49,6 +83,26 @@ pub struct ApiKeyInfo {
 
     /// Current month-to-date budget usage
     pub budget_used_mtd: f64,
+
+    /// Permission scopes granted to this key
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// Unix timestamp when the key expires (trial keys)
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+
+    /// Model patterns this key may use (empty = all models)
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+
+    /// Iteration and token limits for PTC sessions started with this key
+    #[serde(default)]
+    pub ptc_budget: PtcBudget,
+
+    /// Inference preset applied to this key's requests
+    #[serde(default)]
+    pub inference_preset: Option<String>,
 }
 
 impl ApiKeyInfo {
@@ -56,35 +110,114 @@ impl ApiKeyInfo {
     pub fn master(api_key: &str) -> Self {
         Self {
             api_key: Self::truncate_key(api_key),
+            key_id: ApiKey::id_for(api_key),
             user_id: "master".to_string(),
             is_master: true,
             rate_limit: None, // No rate limit for master key
+            tpm_limit: None,
             service_tier: "master".to_string(),
             monthly_budget: None,
             budget_used_mtd: 0.0,
+            scopes: Vec::new(),
+            expires_at: None,
+            allowed_models: Vec::new(),
+            ptc_budget: PtcBudget::default(),
+            inference_preset: None,
+        }
+    }
+
+    /// Create placeholder ApiKeyInfo used when authentication is disabled
+    pub fn anonymous() -> Self {
+        Self {
+            api_key: "disabled".to_string(),
+            key_id: String::new(),
+            user_id: "anonymous".to_string(),
+            is_master: false,
+            rate_limit: None,
+            tpm_limit: None,
+            service_tier: "default".to_string(),
+            monthly_budget: None,
+            budget_used_mtd: 0.0,
+            scopes: Vec::new(),
+            expires_at: None,
+            allowed_models: Vec::new(),
+            ptc_budget: PtcBudget::default(),
+            inference_preset: None,
         }
     }
 
-    /// Create ApiKeyInfo from a validated DynamoDB API key
-    pub fn from_db_key(key: &crate::db::models::ApiKey) -> Self {
+    /// Create ApiKeyInfo for an in-memory ephemeral key
+    pub fn ephemeral(key: &EphemeralKey) -> Self {
+        Self {
+            api_key: Self::truncate_key(&key.key),
+            key_id: ApiKey::id_for(&key.key),
+            user_id: format!("ephemeral:{}", key.id),
+            is_master: false,
+            rate_limit: None,
+            tpm_limit: None,
+            service_tier: "default".to_string(),
+            monthly_budget: None,
+            budget_used_mtd: 0.0,
+            scopes: key.scopes.clone(),
+            expires_at: key.expires_at,
+            allowed_models: Vec::new(),
+            ptc_budget: PtcBudget::default(),
+            inference_preset: None,
+        }
+    }
+
+    /// Create ApiKeyInfo from a validated stored API key
+    pub fn from_db_key(key: &ApiKey) -> Self {
         Self {
             api_key: Self::truncate_key(&key.api_key),
+            key_id: key.key_id(),
             user_id: key.user_id.clone(),
             is_master: false,
             rate_limit: if key.rate_limit > 0 { Some(key.rate_limit as u32) } else { None },
+            tpm_limit: key.tpm_limit.filter(|&limit| limit > 0).map(|limit| limit as u32),
             service_tier: key.service_tier.clone(),
             monthly_budget: key.monthly_budget,
             budget_used_mtd: key.budget_used_mtd,
+            scopes: key.scopes.clone(),
+            expires_at: key.expires_at,
+            allowed_models: key.allowed_models.clone(),
+            ptc_budget: PtcBudget::from_db_key(key),
+            inference_preset: key.inference_preset.clone(),
         }
     }
 
     /// Truncate API key for safe logging (show first 8 chars + ...)
     fn truncate_key(key: &str) -> String {
-        if key.chars().count() > 12 {
-            format!("{}...", truncate_str(key, 8))
-        } else {
-            key.to_string()
+        redact_key(key)
+    }
+
+    /// Check if this key holds a scope (the master key holds every scope)
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.is_master || self.scopes.iter().any(|s| s == scope || s == "*")
+    }
+
+    /// Check if this key may call the given model
+    pub fn is_model_allowed(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| model_matches_pattern(model, pattern))
+    }
+
+    /// Apply trial-tier restrictions (model list and reduced rate limit)
+    pub fn apply_trial_limits(&mut self, trial: &TrialConfig) {
+        if self.service_tier != TRIAL_SERVICE_TIER {
+            return;
         }
+        if self.allowed_models.is_empty() {
+            self.allowed_models = trial.allowed_models.clone();
+        }
+        self.rate_limit = Some(
+            self.rate_limit
+                .map(|limit| limit.min(trial.rate_limit))
+                .unwrap_or(trial.rate_limit),
+        );
     }
 
     /// Check if rate limiting should be bypassed
@@ -111,6 +244,22 @@ pub enum AuthError {
     InvalidApiKey,
     /// API key is inactive (deactivated)
     InactiveKey { reason: Option<String> },
+    /// API key has passed its expiry timestamp
+    ExpiredKey { expired_at: i64 },
+    /// Authenticated key lacks the scope required for the endpoint
+    InsufficientScope { scope: &'static str },
+    /// Source is locked out after repeated authentication failures
+    TooManyFailures { retry_after_secs: u64 },
+    /// Source IP is on the global denylist
+    IpDenied,
+    /// Source IP is outside the key's allowlist
+    IpNotAllowed,
+    /// No `x-admin-key` header on a key management request
+    MissingAdminKey,
+    /// `x-admin-key` does not match the configured admin key
+    InvalidAdminKey,
+    /// Neither `ADMIN_API_KEY` nor `MASTER_API_KEY` is set
+    AdminKeyNotConfigured,
     /// Internal error during authentication
     InternalError(String),
 }
@@ -139,6 +288,68 @@ impl IntoResponse for AuthError {
                 };
                 (StatusCode::FORBIDDEN, "permission_error", msg)
             }
+            AuthError::ExpiredKey { expired_at } => {
+                let expired = chrono::DateTime::from_timestamp(expired_at, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|| expired_at.to_string());
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(ErrorResponse::new(
+                        "expired_key_error",
+                        &format!("API key expired at {}. Contact your administrator to extend or upgrade it.", expired),
+                    )),
+                ).into_response();
+            }
+            AuthError::InsufficientScope { scope } => {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(ErrorResponse::new(
+                        "permission_error",
+                        &format!("API key is missing the required '{}' scope.", scope),
+                    )),
+                ).into_response();
+            }
+            AuthError::TooManyFailures { retry_after_secs } => {
+                let mut response = (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(ErrorResponse::new(
+                        "rate_limit_error",
+                        &format!(
+                            "Too many failed authentication attempts. Retry after {} seconds.",
+                            retry_after_secs
+                        ),
+                    )),
+                ).into_response();
+                if let Ok(value) = retry_after_secs.to_string().parse() {
+                    response.headers_mut().insert("retry-after", value);
+                }
+                return response;
+            }
+            AuthError::IpDenied => (
+                StatusCode::FORBIDDEN,
+                "permission_error",
+                "Requests from this IP address are not allowed.",
+            ),
+            AuthError::IpNotAllowed => (
+                StatusCode::FORBIDDEN,
+                "permission_error",
+                "API key is not allowed from this IP address.",
+            ),
+            AuthError::MissingAdminKey => (
+                StatusCode::UNAUTHORIZED,
+                "authentication_error",
+                "Missing admin key. Include the 'x-admin-key' header in your request.",
+            ),
+            AuthError::InvalidAdminKey => (
+                StatusCode::UNAUTHORIZED,
+                "authentication_error",
+                "Invalid admin key.",
+            ),
+            AuthError::AdminKeyNotConfigured => (
+                StatusCode::FORBIDDEN,
+                "permission_error",
+                "API key management is disabled. Set ADMIN_API_KEY to enable it.",
+            ),
             AuthError::InternalError(msg) => {
                 tracing::error!(error = %msg, "Authentication internal error");
                 (
@@ -162,16 +373,84 @@ impl IntoResponse for AuthError {
 #[derive(Clone)]
 pub struct AuthState {
     pub settings: Arc<Settings>,
-    pub api_key_repo: ApiKeyRepository,
+    pub storage: Arc<dyn StorageBackend>,
+    pub ephemeral_keys: Arc<EphemeralKeyManager>,
+    pub key_activity: Option<Arc<KeyActivityTracker>>,
+    pub failure_guard: Option<Arc<AuthFailureGuard>>,
+    /// Parsed global IP denylist
+    pub ip_denylist: Arc<Vec<IpCidr>>,
+    /// Proxies whose X-Forwarded-For is honored (None = always use the socket peer)
+    pub trusted_proxies: Option<Arc<TrustedProxies>>,
 }
 
 impl AuthState {
-    pub fn new(settings: Arc<Settings>, dynamodb: Arc<DynamoDbClient>) -> Self {
+    pub fn new(
+        settings: Arc<Settings>,
+        storage: Arc<dyn StorageBackend>,
+        ephemeral_keys: Arc<EphemeralKeyManager>,
+    ) -> Self {
+        let ip_denylist = Arc::new(IpCidr::parse_list(&settings.ip_filter.denylist));
+        let ip_filter = &settings.ip_filter;
+        let trusted_proxies = ip_filter.trust_forwarded_for.then(|| {
+            if ip_filter.trusted_proxies.is_empty() {
+                tracing::warn!(
+                    "TRUST_FORWARDED_FOR is set but TRUSTED_PROXIES is empty; X-Forwarded-For will be ignored"
+                );
+            }
+            Arc::new(TrustedProxies {
+                networks: IpCidr::parse_list(&ip_filter.trusted_proxies),
+                hops: ip_filter.trusted_proxy_hops,
+            })
+        });
         Self {
             settings,
-            api_key_repo: ApiKeyRepository::new(dynamodb),
+            storage,
+            ephemeral_keys,
+            key_activity: None,
+            failure_guard: None,
+            ip_denylist,
+            trusted_proxies,
+        }
+    }
+
+    /// Client IP for a request: the socket peer, or the forwarded address from a trusted proxy
+    fn source_ip<B>(&self, request: &Request<B>) -> Option<IpAddr> {
+        client_ip(request, self.trusted_proxies.as_deref())
+    }
+
+    /// Record a failed authentication as a security event and count it towards lockout
+    fn record_auth_failure(&self, ip: Option<IpAddr>, api_key: &str, reason: &'static str) {
+        tracing::warn!(
+            target: "security",
+            event = "auth_failure",
+            reason = reason,
+            ip = ?ip,
+            key = %ApiKeyInfo::truncate_key(api_key),
+            "Authentication failed"
+        );
+        if let Some(ref guard) = self.failure_guard {
+            guard.record_failure(ip, Some(api_key));
         }
     }
+
+    /// Reset failure counters after a successful authentication
+    fn record_auth_success(&self, ip: Option<IpAddr>, api_key: &str) {
+        if let Some(ref guard) = self.failure_guard {
+            guard.record_success(ip, Some(api_key));
+        }
+    }
+
+    /// Lock out sources that repeatedly fail authentication
+    pub fn with_failure_guard(mut self, guard: Option<Arc<AuthFailureGuard>>) -> Self {
+        self.failure_guard = guard;
+        self
+    }
+
+    /// Record per-key activity (last used, source IPs) for stored keys
+    pub fn with_key_activity(mut self, tracker: Option<Arc<KeyActivityTracker>>) -> Self {
+        self.key_activity = tracker;
+        self
+    }
 }
 
 /// Middleware to require API key authentication
@@ -179,31 +458,37 @@ impl AuthState {
 /// This middleware:
 /// 1. Extracts the `x-api-key` header from the request
 /// 2. Checks if it matches the master key (if configured)
-/// 3. Validates the key against DynamoDB
+/// 3. Validates the key against the storage backend
 /// 4. Injects `ApiKeyInfo` into request extensions on success
 ///
 /// # Errors
 /// - 401 Unauthorized: Missing or invalid API key
-/// - 403 Forbidden: API key is inactive
+/// - 403 Forbidden: API key is inactive, or the source IP is denylisted /
+///   outside the key's allowlist
+/// - 429 Too Many Requests: Source locked out after repeated failures
 /// - 500 Internal Server Error: Database error
 pub async fn require_api_key(
     State(auth_state): State<AuthState>,
     mut request: Request<Body>,
     next: Next,
 ) -> Result<Response, AuthError> {
+    // Global denylist applies even when authentication is disabled
+    let source_ip = auth_state.source_ip(&request);
+    if let Some(ip) = source_ip.filter(|ip| IpCidr::any_contains(&auth_state.ip_denylist, *ip)) {
+        tracing::warn!(
+            target: "security",
+            event = "ip_denied",
+            ip = %ip,
+            "Request from denylisted IP rejected"
+        );
+        return Err(AuthError::IpDenied);
+    }
+
     // Check if authentication is required
     if !auth_state.settings.require_api_key {
         tracing::debug!("API key authentication disabled, skipping");
         // Inject a placeholder ApiKeyInfo for disabled auth
-        request.extensions_mut().insert(ApiKeyInfo {
-            api_key: "disabled".to_string(),
-            user_id: "anonymous".to_string(),
-            is_master: false,
-            rate_limit: None,
-            service_tier: "default".to_string(),
-            monthly_budget: None,
-            budget_used_mtd: 0.0,
-        });
+        request.extensions_mut().insert(ApiKeyInfo::anonymous());
         return Ok(next.run(request).await);
     }
 
@@ -229,51 +514,104 @@ pub async fn require_api_key(
         return Err(AuthError::MissingApiKey);
     };
 
+    // Reject locked-out sources before touching the key store
+    if let Some(ref guard) = auth_state.failure_guard {
+        if let Some(remaining) = guard.check(source_ip, Some(&api_key)) {
+            tracing::warn!(
+                target: "security",
+                event = "auth_blocked",
+                ip = ?source_ip,
+                key = %ApiKeyInfo::truncate_key(&api_key),
+                retry_after_secs = remaining.as_secs(),
+                "Authentication attempt rejected during lockout"
+            );
+            return Err(AuthError::TooManyFailures {
+                retry_after_secs: remaining.as_secs().max(1),
+            });
+        }
+    }
+
     // Check if it's the master key
     if let Some(ref master_key) = auth_state.settings.master_api_key {
         if api_key == *master_key {
             tracing::debug!(key = %ApiKeyInfo::truncate_key(&api_key), "Master key authenticated");
+            auth_state.record_auth_success(source_ip, &api_key);
             request.extensions_mut().insert(ApiKeyInfo::master(&api_key));
             return Ok(next.run(request).await);
         }
     }
 
-    // Check if it's the ephemeral key (generated at startup)
-    if let Some(ref ephemeral_key) = auth_state.settings.ephemeral_api_key {
-        if api_key == *ephemeral_key {
-            tracing::debug!(key = %ApiKeyInfo::truncate_key(&api_key), "Ephemeral key authenticated");
-            request.extensions_mut().insert(ApiKeyInfo {
-                api_key: ApiKeyInfo::truncate_key(&api_key),
-                user_id: "ephemeral".to_string(),
-                is_master: false,
-                rate_limit: None,
-                service_tier: "default".to_string(),
-                monthly_budget: None,
-                budget_used_mtd: 0.0,
-            });
-            return Ok(next.run(request).await);
-        }
+    // Check if it's an ephemeral key (minted at startup or via the admin API)
+    if let Some(ephemeral_key) = auth_state.ephemeral_keys.validate(&api_key) {
+        tracing::debug!(
+            key = %ApiKeyInfo::truncate_key(&api_key),
+            id = %ephemeral_key.id,
+            "Ephemeral key authenticated"
+        );
+        auth_state.record_auth_success(source_ip, &api_key);
+        request.extensions_mut().insert(ApiKeyInfo::ephemeral(&ephemeral_key));
+        return Ok(next.run(request).await);
     }
 
-    // Validate against DynamoDB
+    // Validate against the storage backend
     let validation_result = auth_state
-        .api_key_repo
+        .storage
         .validate_api_key(&api_key)
         .await
         .map_err(|e| match e {
-            ApiKeyError::DynamoDb(msg) => AuthError::InternalError(msg),
-            ApiKeyError::NotFound => AuthError::InvalidApiKey,
-            ApiKeyError::ParseError(msg) => AuthError::InternalError(msg),
+            StorageError::NotFound => {
+                auth_state.record_auth_failure(source_ip, &api_key, "invalid_key");
+                AuthError::InvalidApiKey
+            }
+            other => AuthError::InternalError(other.to_string()),
         })?;
 
     match validation_result {
         Some(db_key) if db_key.is_active => {
+            if let Some(expired_at) = db_key
+                .expires_at
+                .filter(|_| db_key.is_expired_at(chrono::Utc::now().timestamp()))
+            {
+                tracing::warn!(
+                    key = %ApiKeyInfo::truncate_key(&api_key),
+                    user_id = %db_key.user_id,
+                    expired_at = expired_at,
+                    "Expired API key used"
+                );
+                return Err(AuthError::ExpiredKey { expired_at });
+            }
+
             tracing::debug!(
                 key = %ApiKeyInfo::truncate_key(&api_key),
                 user_id = %db_key.user_id,
                 "API key authenticated"
             );
-            request.extensions_mut().insert(ApiKeyInfo::from_db_key(&db_key));
+            if !db_key.allowed_ips.is_empty() {
+                let allowlist = IpCidr::parse_list(&db_key.allowed_ips);
+                let allowed = source_ip
+                    .map(|ip| IpCidr::any_contains(&allowlist, ip))
+                    .unwrap_or(false);
+                if !allowed {
+                    tracing::warn!(
+                        target: "security",
+                        event = "ip_not_allowed",
+                        ip = ?source_ip,
+                        key = %ApiKeyInfo::truncate_key(&api_key),
+                        user_id = %db_key.user_id,
+                        "API key used from outside its IP allowlist"
+                    );
+                    return Err(AuthError::IpNotAllowed);
+                }
+            }
+
+            auth_state.record_auth_success(source_ip, &api_key);
+            if let Some(ref tracker) = auth_state.key_activity {
+                tracker.record(&api_key, source_ip);
+            }
+
+            let mut key_info = ApiKeyInfo::from_db_key(&db_key);
+            key_info.apply_trial_limits(&auth_state.settings.trial);
+            request.extensions_mut().insert(key_info);
             Ok(next.run(request).await)
         }
         Some(db_key) => {
@@ -290,11 +628,111 @@ pub async fn require_api_key(
         }
         None => {
             tracing::warn!(key = %ApiKeyInfo::truncate_key(&api_key), "Invalid API key");
+            auth_state.record_auth_failure(source_ip, &api_key, "invalid_key");
             Err(AuthError::InvalidApiKey)
         }
     }
 }
 
+/// Middleware to restrict a route group to admin keys
+///
+/// Must run after `require_api_key`. The master key and keys holding the
+/// `admin` scope are allowed through; everything else gets 403.
+pub async fn require_admin(request: Request<Body>, next: Next) -> Result<Response, AuthError> {
+    let is_admin = request
+        .extensions()
+        .get::<ApiKeyInfo>()
+        .map(|info| info.has_scope(SCOPE_ADMIN))
+        .unwrap_or(false);
+
+    if !is_admin {
+        tracing::warn!(route = route_template(&request), "Non-admin key attempted admin access");
+        return Err(AuthError::InsufficientScope { scope: SCOPE_ADMIN });
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Middleware guarding the API key management endpoints
+///
+/// Key management uses its own credential rather than an API key: the
+/// `x-admin-key` header must match `ADMIN_API_KEY` (or `MASTER_API_KEY` when
+/// no admin key is set). Failures count towards the brute-force lockout like
+/// any other authentication attempt.
+///
+/// # Errors
+/// - 401 Unauthorized: Missing or wrong admin key
+/// - 403 Forbidden: No admin key configured, or the source IP is denylisted
+/// - 429 Too Many Requests: Source locked out after repeated failures
+pub async fn require_admin_key(
+    State(auth_state): State<AuthState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let source_ip = auth_state.source_ip(&request);
+    if source_ip.is_some_and(|ip| IpCidr::any_contains(&auth_state.ip_denylist, ip)) {
+        return Err(AuthError::IpDenied);
+    }
+
+    let settings = &auth_state.settings;
+    let Some(expected) = settings.admin_api_key.as_ref().or(settings.master_api_key.as_ref()) else {
+        return Err(AuthError::AdminKeyNotConfigured);
+    };
+    let Some(admin_key) = request
+        .headers()
+        .get(ADMIN_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return Err(AuthError::MissingAdminKey);
+    };
+
+    if let Some(ref guard) = auth_state.failure_guard {
+        if let Some(remaining) = guard.check(source_ip, Some(admin_key)) {
+            return Err(AuthError::TooManyFailures {
+                retry_after_secs: remaining.as_secs().max(1),
+            });
+        }
+    }
+
+    if !keys_match(admin_key, expected) {
+        auth_state.record_auth_failure(source_ip, admin_key, "invalid_admin_key");
+        return Err(AuthError::InvalidAdminKey);
+    }
+    auth_state.record_auth_success(source_ip, admin_key);
+
+    tracing::info!(
+        target: "security",
+        event = "admin_key_request",
+        ip = ?source_ip,
+        method = %request.method(),
+        route = route_template(&request),
+        "Admin key authenticated"
+    );
+    Ok(next.run(request).await)
+}
+
+/// Matched route template (e.g. `/admin/api-keys/:key_id`) for logging
+///
+/// Admin paths can carry identifiers, so the concrete path is never logged.
+fn route_template<B>(request: &Request<B>) -> &str {
+    request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or("-")
+}
+
+/// Compare secrets in time independent of where they differ
+fn keys_match(provided: &str, expected: &str) -> bool {
+    let (provided, expected) = (provided.as_bytes(), expected.as_bytes());
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 // ============================================================================
 // Extension Extraction
 // ============================================================================
@@ -308,23 +746,33 @@ pub fn get_api_key_info<B>(request: &Request<B>) -> Option<&ApiKeyInfo> {
 
 /// Extract API key from request headers
 ///
-/// Supports both `x-api-key` (Anthropic style) and `Authorization: Bearer` (OpenAI style).
-/// Returns None if no API key is found.
+/// Supports `x-api-key` (Anthropic style), `Authorization: Bearer` (OpenAI style)
+/// and `x-goog-api-key` (Gemini style). Returns None if no API key is found.
 pub fn extract_api_key<B>(request: &Request<B>) -> Option<String> {
-    request
-        .headers()
+    extract_api_key_from_headers(request.headers())
+}
+
+/// Extract API key from headers (x-api-key, Authorization: Bearer or x-goog-api-key)
+pub fn extract_api_key_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
         .get("x-api-key")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string())
         .or_else(|| {
             // Try Authorization: Bearer <token> format (OpenAI style)
-            request
-                .headers()
+            headers
                 .get("authorization")
                 .and_then(|v| v.to_str().ok())
                 .and_then(|s| s.strip_prefix("Bearer "))
                 .map(|s| s.to_string())
         })
+        .or_else(|| {
+            // Google SDKs send the key in x-goog-api-key
+            headers
+                .get("x-goog-api-key")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        })
 }
 
 // ============================================================================
@@ -363,6 +811,17 @@ mod tests {
         assert_eq!(info.effective_rate_limit(100), 100);
     }
 
+    #[test]
+    fn test_has_scope() {
+        let mut info = ApiKeyInfo::anonymous();
+        assert!(!info.has_scope(SCOPE_BACKEND_OVERRIDE));
+
+        info.scopes = vec![SCOPE_BACKEND_OVERRIDE.to_string()];
+        assert!(info.has_scope(SCOPE_BACKEND_OVERRIDE));
+
+        assert!(ApiKeyInfo::master("key").has_scope(SCOPE_BACKEND_OVERRIDE));
+    }
+
     #[test]
     fn test_auth_error_status_codes() {
         // Test that error types map to correct status codes
@@ -377,5 +836,135 @@ mod tests {
         let inactive = AuthError::InactiveKey { reason: None };
         let response = inactive.into_response();
         assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let expired = AuthError::ExpiredKey { expired_at: 0 };
+        let response = expired.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let denied = AuthError::IpNotAllowed;
+        let response = denied.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let locked = AuthError::TooManyFailures { retry_after_secs: 30 };
+        let response = locked.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get("retry-after").unwrap(), "30");
+
+        assert_eq!(AuthError::InvalidAdminKey.into_response().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(AuthError::AdminKeyNotConfigured.into_response().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match("sk-admin-secret", "sk-admin-secret"));
+        assert!(!keys_match("sk-admin-secreT", "sk-admin-secret"));
+        assert!(!keys_match("sk-admin", "sk-admin-secret"));
+        assert!(!keys_match("", "sk-admin-secret"));
+    }
+
+    #[test]
+    fn test_trial_limits() {
+        let mut info = ApiKeyInfo::anonymous();
+        info.service_tier = TRIAL_SERVICE_TIER.to_string();
+        info.rate_limit = Some(100);
+        info.apply_trial_limits(&TrialConfig::default());
+
+        assert_eq!(info.rate_limit, Some(10));
+        assert!(info.is_model_allowed("claude-3-5-haiku-20241022"));
+        assert!(!info.is_model_allowed("claude-opus-4-5-20251101"));
+
+        // Non-trial keys are untouched
+        let mut info = ApiKeyInfo::anonymous();
+        info.apply_trial_limits(&TrialConfig::default());
+        assert!(info.is_model_allowed("claude-opus-4-5-20251101"));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_is_rejected() {
+        use crate::db::MemoryBackend;
+        use axum::{routing::get, Router};
+        use tower::ServiceExt;
+
+        let storage = Arc::new(MemoryBackend::new());
+        let key: crate::db::models::ApiKey = serde_json::from_value(serde_json::json!({
+            "api_key": "sk-revoke-me",
+            "user_id": "user-1",
+            "name": "Test Key",
+            "created_at": 1_700_000_000,
+            "is_active": true,
+            "rate_limit": 100,
+            "service_tier": "default"
+        }))
+        .unwrap();
+        storage.create_api_key(&key).await.unwrap();
+
+        let auth_state = AuthState::new(
+            Arc::new(Settings::default()),
+            storage.clone(),
+            Arc::new(EphemeralKeyManager::new()),
+        );
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(auth_state, require_api_key));
+        let request = || {
+            Request::get("/")
+                .header("x-api-key", "sk-revoke-me")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        storage.revoke_api_key("sk-revoke-me").await.unwrap();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(matches!(
+            storage.revoke_api_key("sk-missing").await,
+            Err(StorageError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lockout_ignores_spoofed_forwarded_for() {
+        use crate::config::BruteForceConfig;
+        use crate::db::MemoryBackend;
+        use axum::extract::ConnectInfo;
+        use axum::{routing::get, Router};
+        use std::net::SocketAddr;
+        use tower::ServiceExt;
+
+        let mut settings = Settings::default();
+        settings.ip_filter.trust_forwarded_for = true;
+        settings.ip_filter.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        let auth_state = AuthState::new(
+            Arc::new(settings),
+            Arc::new(MemoryBackend::new()),
+            Arc::new(EphemeralKeyManager::new()),
+        )
+        .with_failure_guard(Some(Arc::new(AuthFailureGuard::new(BruteForceConfig::default()))));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(auth_state, require_api_key));
+
+        // A different key and X-Forwarded-For each time, from one untrusted peer
+        let peer: SocketAddr = "203.0.113.9:40000".parse().unwrap();
+        let request = |i: usize| {
+            let mut request = Request::get("/")
+                .header("x-api-key", format!("sk-{:04}-guess-{}", i, "x".repeat(8)))
+                .header("x-forwarded-for", format!("198.51.100.{}", i))
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(ConnectInfo(peer));
+            request
+        };
+
+        let max_failures = BruteForceConfig::default().max_failures as usize;
+        for i in 0..max_failures {
+            let response = app.clone().oneshot(request(i)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let response = app.oneshot(request(max_failures)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
     }
 }
@@ -0,0 +1,99 @@
+//! Request body size limits
+//!
+//! Rejects oversized requests with 413 as early as possible: a declared
+//! `Content-Length` above the limit is refused before any of the body is read,
+//! and chunked bodies are cut off by axum's `DefaultBodyLimit` once they cross
+//! the limit while being buffered. Both paths return the same error, which
+//! states the limit for the endpoint. Compressed bodies are measured once
+//! decompressed (see [`compression`](super::compression)).
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header::CONTENT_LENGTH, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::schemas::anthropic::ErrorResponse;
+
+/// Middleware enforcing a maximum request body size (in bytes)
+///
+/// Pair with `DefaultBodyLimit::max(limit)` on the same routes so bodies
+/// without a `Content-Length` are bounded too.
+pub async fn enforce_body_limit(
+    State(limit): State<usize>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    if let Some(length) = declared.filter(|len| *len > limit as u64) {
+        tracing::warn!(
+            path = %request.uri().path(),
+            content_length = length,
+            limit = limit,
+            "Rejecting oversized request body"
+        );
+        return body_too_large(limit);
+    }
+
+    let response = next.run(request).await;
+
+    // Chunked bodies hit DefaultBodyLimit while buffering; normalize its error
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return body_too_large(limit);
+    }
+
+    response
+}
+
+/// Build the 413 response for a given limit
+pub fn body_too_large(limit: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ErrorResponse::new(
+            "request_too_large",
+            &format!(
+                "Request body exceeds the maximum size of {} for this endpoint.",
+                format_bytes(limit)
+            ),
+        )),
+    )
+        .into_response()
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const MB: usize = 1024 * 1024;
+    const KB: usize = 1024;
+    if bytes >= MB && bytes % MB == 0 {
+        format!("{} MB ({} bytes)", bytes / MB, bytes)
+    } else if bytes >= KB && bytes % KB == 0 {
+        format!("{} KB ({} bytes)", bytes / KB, bytes)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(32 * 1024 * 1024), "32 MB (33554432 bytes)");
+        assert_eq!(format_bytes(512 * 1024), "512 KB (524288 bytes)");
+        assert_eq!(format_bytes(1000), "1000 bytes");
+    }
+
+    #[test]
+    fn test_body_too_large_status() {
+        let response = body_too_large(1024);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
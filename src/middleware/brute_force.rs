@@ -0,0 +1,233 @@
+//! Authentication brute-force protection
+//!
+//! Tracks failed authentications per source IP and per presented key prefix.
+//! The source IP is the socket peer, or the address reported by a trusted
+//! proxy (see `utils::client_ip`), so clients cannot reset their counter by
+//! sending a different `X-Forwarded-For`.
+//! Once a source exceeds the allowed failures within the window it is locked
+//! out, with the lockout doubling on each repeat offence. Locked-out requests
+//! are rejected before any DynamoDB lookup, so credential stuffing against the
+//! proxy is throttled instead of being amplified into backend traffic.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::BruteForceConfig;
+use crate::utils::truncate_str;
+
+/// Number of tracked sources above which stale entries are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Characters of the presented key used to group failures
+const KEY_PREFIX_CHARS: usize = 12;
+
+/// Failure state for one source (IP or key prefix)
+#[derive(Debug, Clone)]
+struct FailureRecord {
+    failures: u32,
+    window_start: Instant,
+    locked_until: Option<Instant>,
+    /// Lockouts served so far (drives the exponential backoff)
+    lockouts: u32,
+}
+
+impl FailureRecord {
+    fn new(now: Instant) -> Self {
+        Self {
+            failures: 0,
+            window_start: now,
+            locked_until: None,
+            lockouts: 0,
+        }
+    }
+}
+
+/// Tracks failed authentications and enforces lockouts
+#[derive(Debug)]
+pub struct AuthFailureGuard {
+    config: BruteForceConfig,
+    records: Mutex<HashMap<String, FailureRecord>>,
+}
+
+impl AuthFailureGuard {
+    /// Create a new guard
+    pub fn new(config: BruteForceConfig) -> Self {
+        Self {
+            config,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a request is currently locked out
+    ///
+    /// Returns the remaining lockout if either the IP or the key prefix is locked.
+    pub fn check(&self, ip: Option<IpAddr>, api_key: Option<&str>) -> Option<Duration> {
+        self.check_at(&Self::sources(ip, api_key), Instant::now())
+    }
+
+    /// Record a failed authentication
+    ///
+    /// Returns the lockout duration if this failure triggered a lockout.
+    pub fn record_failure(&self, ip: Option<IpAddr>, api_key: Option<&str>) -> Option<Duration> {
+        self.record_failure_at(&Self::sources(ip, api_key), Instant::now())
+    }
+
+    /// Clear the failure count after a successful authentication
+    ///
+    /// Only the failure counter is reset; an earlier lockout still counts
+    /// towards the backoff until the entry is pruned.
+    pub fn record_success(&self, ip: Option<IpAddr>, api_key: Option<&str>) {
+        let mut records = self.records.lock().unwrap();
+        for source in Self::sources(ip, api_key) {
+            if let Some(record) = records.get_mut(&source) {
+                record.failures = 0;
+            }
+        }
+    }
+
+    fn sources(ip: Option<IpAddr>, api_key: Option<&str>) -> Vec<String> {
+        let mut sources = Vec::with_capacity(2);
+        if let Some(ip) = ip {
+            sources.push(format!("ip:{}", ip));
+        }
+        if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+            sources.push(format!("key:{}", truncate_str(key, KEY_PREFIX_CHARS)));
+        }
+        sources
+    }
+
+    fn check_at(&self, sources: &[String], now: Instant) -> Option<Duration> {
+        let records = self.records.lock().unwrap();
+        sources
+            .iter()
+            .filter_map(|source| records.get(source)?.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+            .max()
+    }
+
+    fn record_failure_at(&self, sources: &[String], now: Instant) -> Option<Duration> {
+        let window = Duration::from_secs(self.config.window_secs);
+        let mut records = self.records.lock().unwrap();
+
+        if records.len() > PRUNE_THRESHOLD {
+            let stale_after = window.max(Duration::from_secs(self.config.max_lockout_secs));
+            records.retain(|_, r| {
+                r.locked_until.map(|u| u > now).unwrap_or(false)
+                    || now.duration_since(r.window_start) < stale_after
+            });
+        }
+
+        let mut triggered: Option<Duration> = None;
+        for source in sources {
+            let record = records
+                .entry(source.clone())
+                .or_insert_with(|| FailureRecord::new(now));
+
+            if now.duration_since(record.window_start) >= window {
+                record.failures = 0;
+                record.window_start = now;
+            }
+            record.failures += 1;
+
+            if record.failures >= self.config.max_failures.max(1) {
+                let lockout = self.lockout_duration(record.lockouts);
+                record.locked_until = Some(now + lockout);
+                record.lockouts += 1;
+                record.failures = 0;
+                record.window_start = now;
+
+                tracing::warn!(
+                    target: "security",
+                    event = "auth_lockout",
+                    source = %source,
+                    lockout_secs = lockout.as_secs(),
+                    lockout_count = record.lockouts,
+                    "Authentication lockout triggered"
+                );
+                triggered = Some(triggered.map_or(lockout, |d| d.max(lockout)));
+            }
+        }
+
+        triggered
+    }
+
+    fn lockout_duration(&self, previous_lockouts: u32) -> Duration {
+        let secs = self
+            .config
+            .base_lockout_secs
+            .saturating_mul(1u64 << previous_lockouts.min(32))
+            .min(self.config.max_lockout_secs);
+        Duration::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> AuthFailureGuard {
+        AuthFailureGuard::new(BruteForceConfig {
+            enabled: true,
+            max_failures: 3,
+            window_secs: 60,
+            base_lockout_secs: 10,
+            max_lockout_secs: 25,
+        })
+    }
+
+    #[test]
+    fn test_lockout_after_max_failures() {
+        let guard = guard();
+        let sources = AuthFailureGuard::sources(Some("203.0.113.9".parse().unwrap()), Some("sk-guess-0001"));
+        let now = Instant::now();
+
+        assert_eq!(guard.record_failure_at(&sources, now), None);
+        assert_eq!(guard.record_failure_at(&sources, now), None);
+        assert_eq!(guard.check_at(&sources, now), None);
+
+        let lockout = guard.record_failure_at(&sources, now).unwrap();
+        assert_eq!(lockout, Duration::from_secs(10));
+        assert!(guard.check_at(&sources, now + Duration::from_secs(5)).is_some());
+        assert!(guard.check_at(&sources, now + Duration::from_secs(10)).is_none());
+    }
+
+    #[test]
+    fn test_exponential_lockout_capped() {
+        let guard = guard();
+        assert_eq!(guard.lockout_duration(0), Duration::from_secs(10));
+        assert_eq!(guard.lockout_duration(1), Duration::from_secs(20));
+        assert_eq!(guard.lockout_duration(2), Duration::from_secs(25));
+        assert_eq!(guard.lockout_duration(40), Duration::from_secs(25));
+    }
+
+    #[test]
+    fn test_key_prefix_tracked_across_ips() {
+        let guard = guard();
+        let now = Instant::now();
+        for i in 0..3 {
+            let ip: IpAddr = format!("198.51.100.{}", i).parse().unwrap();
+            let sources = AuthFailureGuard::sources(Some(ip), Some("sk-target-key-aaaa"));
+            guard.record_failure_at(&sources, now);
+        }
+
+        // Same key prefix from a fresh IP is locked; an unrelated key is not
+        let fresh = AuthFailureGuard::sources(Some("192.0.2.1".parse().unwrap()), Some("sk-target-key-bbbb"));
+        assert!(guard.check_at(&fresh, now).is_some());
+        let other = AuthFailureGuard::sources(Some("192.0.2.1".parse().unwrap()), Some("sk-other"));
+        assert!(guard.check_at(&other, now).is_none());
+    }
+
+    #[test]
+    fn test_window_resets_failures() {
+        let guard = guard();
+        let sources = AuthFailureGuard::sources(Some("203.0.113.1".parse().unwrap()), None);
+        let now = Instant::now();
+
+        guard.record_failure_at(&sources, now);
+        guard.record_failure_at(&sources, now);
+        assert_eq!(guard.record_failure_at(&sources, now + Duration::from_secs(61)), None);
+    }
+}
@@ -0,0 +1,278 @@
+//! Budget soft-cap warning middleware
+//!
+//! When a key's month-to-date spend crosses one of the configured thresholds
+//! (50/80/95% of its monthly budget by default), responses carry warning headers
+//! and streaming responses start with an SSE comment, so clients can surface the
+//! impending cutoff before hard budget enforcement deactivates the key.
+//!
+//! With the downgrade policy enabled, a key past its downgrade threshold has
+//! requests for expensive models moved to a cheaper one ([`ModelDowngrade`])
+//! instead, and the response names the model originally requested.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+
+use crate::config::{BudgetDowngradeConfig, Settings};
+use crate::middleware::auth::ApiKeyInfo;
+use crate::services::provider::model_matches_pattern;
+
+/// Highest crossed threshold, as a percentage (e.g. "80")
+pub const BUDGET_WARNING_HEADER: &str = "x-budget-warning";
+
+/// Month-to-date spend as a percentage of the monthly budget
+pub const BUDGET_USED_PERCENT_HEADER: &str = "x-budget-used-percent";
+
+/// Remaining monthly budget in USD
+pub const BUDGET_REMAINING_HEADER: &str = "x-budget-remaining-usd";
+
+/// Model the client asked for, when the budget policy substituted another
+pub const BUDGET_DOWNGRADED_FROM_HEADER: &str = "x-budget-downgraded-from";
+
+/// A crossed budget threshold for the current key
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetWarning {
+    /// Highest threshold crossed (percent)
+    pub threshold: u32,
+    /// Month-to-date spend as a percentage of the budget
+    pub used_percent: f64,
+    /// Remaining budget in USD (never negative)
+    pub remaining_usd: f64,
+}
+
+impl BudgetWarning {
+    /// Evaluate the key's spend against the thresholds.
+    ///
+    /// Returns None for keys without a budget or below every threshold.
+    pub fn evaluate(key_info: &ApiKeyInfo, thresholds: &[u32]) -> Option<Self> {
+        let budget = key_info.monthly_budget.filter(|b| *b > 0.0)?;
+        let used_percent = key_info.budget_used_mtd / budget * 100.0;
+
+        let threshold = thresholds
+            .iter()
+            .copied()
+            .filter(|t| used_percent >= *t as f64)
+            .max()?;
+
+        Some(Self {
+            threshold,
+            used_percent,
+            remaining_usd: (budget - key_info.budget_used_mtd).max(0.0),
+        })
+    }
+
+    /// SSE comment line announcing the warning (ignored by spec-compliant parsers)
+    pub fn sse_comment(&self) -> String {
+        format!(
+            ": warning budget_threshold={} used_percent={:.1} remaining_usd={:.2}\n\n",
+            self.threshold, self.used_percent, self.remaining_usd
+        )
+    }
+
+    fn apply_headers(&self, response: &mut Response) {
+        let headers = response.headers_mut();
+        let values = [
+            (BUDGET_WARNING_HEADER, self.threshold.to_string()),
+            (BUDGET_USED_PERCENT_HEADER, format!("{:.1}", self.used_percent)),
+            (BUDGET_REMAINING_HEADER, format!("{:.2}", self.remaining_usd)),
+        ];
+        for (name, value) in values {
+            if let Ok(v) = HeaderValue::from_str(&value) {
+                headers.insert(name, v);
+            }
+        }
+    }
+}
+
+/// A cheaper model substituted for a key near its budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDowngrade {
+    /// Model the client requested
+    pub from: String,
+    /// Model the request runs on instead
+    pub to: String,
+    /// Month-to-date spend as a percentage of the budget
+    pub used_percent: f64,
+}
+
+impl ModelDowngrade {
+    /// Decide whether a request for `model` should be downgraded.
+    ///
+    /// Returns None when the policy is off, the key has no budget or is below
+    /// the threshold, no rule matches, or the key may not use the cheaper model.
+    pub fn evaluate(
+        key_info: &ApiKeyInfo,
+        model: &str,
+        config: &BudgetDowngradeConfig,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let budget = key_info.monthly_budget.filter(|b| *b > 0.0)?;
+        let used_percent = key_info.budget_used_mtd / budget * 100.0;
+        if used_percent < config.threshold_percent as f64 {
+            return None;
+        }
+
+        let (_, to) = config
+            .rules
+            .iter()
+            .find(|(pattern, _)| model_matches_pattern(model, pattern))?;
+        if to == model || !key_info.is_model_allowed(to) {
+            return None;
+        }
+
+        Some(Self {
+            from: model.to_string(),
+            to: to.clone(),
+            used_percent,
+        })
+    }
+
+    /// Value for the [`BUDGET_DOWNGRADED_FROM_HEADER`] response header
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        HeaderValue::from_str(&self.from).ok()
+    }
+}
+
+/// Middleware that attaches budget soft-cap warnings to responses
+///
+/// # Prerequisites
+/// - Auth middleware must run first to set `ApiKeyInfo` in extensions
+pub async fn budget_warnings(
+    State(settings): State<Arc<Settings>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !settings.budget_warnings.enabled {
+        return next.run(request).await;
+    }
+
+    let warning = request
+        .extensions()
+        .get::<ApiKeyInfo>()
+        .and_then(|info| BudgetWarning::evaluate(info, &settings.budget_warnings.thresholds));
+
+    let response = next.run(request).await;
+
+    let Some(warning) = warning else {
+        return response;
+    };
+
+    tracing::debug!(
+        threshold = warning.threshold,
+        used_percent = warning.used_percent,
+        "Budget soft-cap threshold crossed"
+    );
+
+    let is_event_stream = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("text/event-stream"))
+        .unwrap_or(false);
+
+    let mut response = if is_event_stream {
+        let (parts, body) = response.into_parts();
+        let comment = Bytes::from(warning.sse_comment());
+        let comment = stream::once(async move { Ok::<_, axum::Error>(comment) });
+        let body = Body::from_stream(comment.chain(body.into_data_stream()));
+        Response::from_parts(parts, body)
+    } else {
+        response
+    };
+
+    warning.apply_headers(&mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_with_budget(budget: Option<f64>, used: f64) -> ApiKeyInfo {
+        let mut info = ApiKeyInfo::anonymous();
+        info.monthly_budget = budget;
+        info.budget_used_mtd = used;
+        info
+    }
+
+    #[test]
+    fn test_no_warning_without_budget() {
+        let info = key_with_budget(None, 1000.0);
+        assert!(BudgetWarning::evaluate(&info, &[50, 80, 95]).is_none());
+    }
+
+    #[test]
+    fn test_highest_crossed_threshold() {
+        let info = key_with_budget(Some(100.0), 40.0);
+        assert!(BudgetWarning::evaluate(&info, &[50, 80, 95]).is_none());
+
+        let info = key_with_budget(Some(100.0), 85.0);
+        let warning = BudgetWarning::evaluate(&info, &[50, 80, 95]).unwrap();
+        assert_eq!(warning.threshold, 80);
+        assert!((warning.remaining_usd - 15.0).abs() < 1e-9);
+
+        let info = key_with_budget(Some(100.0), 120.0);
+        let warning = BudgetWarning::evaluate(&info, &[50, 80, 95]).unwrap();
+        assert_eq!(warning.threshold, 95);
+        assert_eq!(warning.remaining_usd, 0.0);
+    }
+
+    fn downgrade_config() -> BudgetDowngradeConfig {
+        BudgetDowngradeConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_downgrade_past_threshold() {
+        let config = downgrade_config();
+        let model = "claude-sonnet-4-5-20250929";
+
+        let info = key_with_budget(Some(100.0), 80.0);
+        assert!(ModelDowngrade::evaluate(&info, model, &config).is_none());
+
+        let info = key_with_budget(Some(100.0), 92.0);
+        let downgrade = ModelDowngrade::evaluate(&info, model, &config).unwrap();
+        assert_eq!(downgrade.from, model);
+        assert_eq!(downgrade.to, "claude-3-5-haiku-20241022");
+
+        // Already the cheap model, or no budget at all
+        assert!(ModelDowngrade::evaluate(&info, "claude-3-5-haiku-20241022", &config).is_none());
+        let info = key_with_budget(None, 1000.0);
+        assert!(ModelDowngrade::evaluate(&info, model, &config).is_none());
+    }
+
+    #[test]
+    fn test_downgrade_respects_policy_and_allowed_models() {
+        let info = key_with_budget(Some(100.0), 99.0);
+        let model = "claude-opus-4-20250514";
+        let disabled = BudgetDowngradeConfig::default();
+        assert!(ModelDowngrade::evaluate(&info, model, &disabled).is_none());
+
+        // A key that may not call the cheaper model keeps its own
+        let mut info = info;
+        info.allowed_models = vec!["claude-opus-*".to_string()];
+        assert!(ModelDowngrade::evaluate(&info, model, &downgrade_config()).is_none());
+    }
+
+    #[test]
+    fn test_sse_comment_format() {
+        let warning = BudgetWarning {
+            threshold: 50,
+            used_percent: 55.0,
+            remaining_usd: 45.0,
+        };
+        let comment = warning.sse_comment();
+        assert!(comment.starts_with(": warning budget_threshold=50"));
+        assert!(comment.ends_with("\n\n"));
+    }
+}
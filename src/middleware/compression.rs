@@ -0,0 +1,123 @@
+//! Request decompression and response compression
+//!
+//! Request bodies sent with `Content-Encoding: gzip` or `zstd` (large base64
+//! documents shrink a lot) are decompressed before they reach the handlers;
+//! other encodings are refused with 415. The decompression layer drops the
+//! `Content-Length` header, so the body limits are enforced by
+//! `DefaultBodyLimit` on the decompressed bytes as they are read, which also
+//! stops a small compressed body from expanding past the limit.
+//!
+//! Responses are compressed with gzip or zstd, as negotiated through
+//! `Accept-Encoding`, when they are larger than the configured minimum. SSE
+//! streams are left alone: compressing them would hold events back.
+
+use axum::Router;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+
+use crate::config::CompressionConfig;
+
+/// Apply the configured compression layers to a router
+pub fn with_compression<S>(router: Router<S>, config: &CompressionConfig) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let router = if config.response_compression {
+        // The default predicate already skips SSE, gRPC and images
+        let predicate = DefaultPredicate::new().and(SizeAbove::new(config.min_response_bytes));
+        router.layer(CompressionLayer::new().compress_when(predicate))
+    } else {
+        router
+    };
+
+    if config.request_decompression {
+        router.layer(RequestDecompressionLayer::new())
+    } else {
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::DefaultBodyLimit,
+        http::{header, Request, StatusCode},
+        response::IntoResponse,
+        routing::{get, post},
+    };
+    use tower::ServiceExt;
+
+    /// `{"text":"hello"}`, gzipped
+    const GZIP_HELLO: [u8; 36] = [
+        31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 171, 86, 42, 73, 173, 40, 81, 178, 82, 202, 72, 205,
+        201, 201, 87, 170, 5, 0, 60, 170, 220, 137, 16, 0, 0, 0,
+    ];
+
+    /// 4096 `a`s, gzipped to 40 bytes
+    const GZIP_4K: [u8; 40] = [
+        31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 237, 193, 1, 13, 0, 0, 0, 194, 160, 172, 239, 95, 194,
+        30, 14, 40, 0, 0, 0, 224, 221, 0, 115, 220, 153, 156, 0, 16, 0, 0,
+    ];
+
+    fn app() -> Router {
+        let router = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route("/large", get(|| async { "x".repeat(4096) }))
+            .route(
+                "/events",
+                get(|| async {
+                    ([(header::CONTENT_TYPE, "text/event-stream")], "data: x\n\n".repeat(512))
+                        .into_response()
+                }),
+            )
+            .layer(DefaultBodyLimit::max(1024));
+        with_compression(router, &CompressionConfig::default())
+    }
+
+    fn gzipped_post(body: &'static [u8]) -> Request<Body> {
+        Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_decompression_and_limit() {
+        let response = app().oneshot(gzipped_post(&GZIP_HELLO)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"{"text":"hello"}"#);
+
+        // 40 bytes on the wire, 4 KB once decompressed
+        let response = app().oneshot(gzipped_post(&GZIP_4K)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let request = Request::post("/echo")
+            .header(header::CONTENT_ENCODING, "br")
+            .body(Body::from("hello"))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_response_compression_skips_sse() {
+        let request = |path: &str| {
+            Request::get(path)
+                .header(header::ACCEPT_ENCODING, "zstd, gzip")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app().oneshot(request("/large")).await.unwrap();
+        let encoding = response.headers().get(header::CONTENT_ENCODING).unwrap();
+        assert!(encoding == "zstd" || encoding == "gzip");
+
+        let response = app().oneshot(request("/events")).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
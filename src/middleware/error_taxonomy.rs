@@ -0,0 +1,160 @@
+//! Error code and retryability on error responses
+//!
+//! [`tag_errors`] wraps every route, so errors returned by the handlers,
+//! the other middleware (authentication, rate limits, body limits) and the
+//! router itself are all tagged the same way; see [`crate::error::ErrorTaxonomy`].
+//! Handlers that know the backend failure behind an error put its
+//! classification in the response extensions; other errors are classified
+//! from their status.
+//!
+//! JSON bodies with an `error` object get `error_code` and `retryable` added
+//! to it. Other bodies, and SSE error events sent after a stream started,
+//! are left as they are.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+use crate::error::{ErrorTaxonomy, ERROR_CODE_HEADER, ERROR_RETRYABLE_HEADER};
+
+/// Largest error body rewritten; anything bigger only gets the headers
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Attach a classification to a response, for [`tag_errors`] to report
+pub fn with_taxonomy(mut response: Response, taxonomy: Option<ErrorTaxonomy>) -> Response {
+    if let Some(taxonomy) = taxonomy {
+        response.extensions_mut().insert(taxonomy);
+    }
+    response
+}
+
+/// Middleware adding the error code and retryability to error responses
+pub async fn tag_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+
+    let taxonomy = response
+        .extensions()
+        .get::<ErrorTaxonomy>()
+        .copied()
+        .unwrap_or_else(|| ErrorTaxonomy::from_status(status));
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert(ERROR_CODE_HEADER, HeaderValue::from_static(taxonomy.code));
+    parts.headers.insert(
+        ERROR_RETRYABLE_HEADER,
+        HeaderValue::from_static(if taxonomy.retryable { "true" } else { "false" }),
+    );
+
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to read error response body");
+            parts.headers.remove(header::CONTENT_LENGTH);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let body = match tag_body(&bytes, taxonomy) {
+        Some(tagged) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(tagged)
+        }
+        None => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// The body with the classification added to its `error` object, if it has one
+fn tag_body(bytes: &[u8], taxonomy: ErrorTaxonomy) -> Option<Vec<u8>> {
+    let mut body: Value = serde_json::from_slice(bytes).ok()?;
+    let error = body.get_mut("error")?.as_object_mut()?;
+    error
+        .entry("error_code")
+        .or_insert_with(|| taxonomy.code.into());
+    error
+        .entry("retryable")
+        .or_insert_with(|| taxonomy.retryable.into());
+    serde_json::to_vec(&body).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/throttled",
+                get(|| async {
+                    let response = (
+                        StatusCode::TOO_MANY_REQUESTS,
+                        Json(json!({"type": "error", "error": {"type": "rate_limit_error", "message": "slow down"}})),
+                    )
+                        .into_response();
+                    with_taxonomy(response, Some(ErrorTaxonomy { code: "throttled", retryable: true }))
+                }),
+            )
+            .route("/text", get(|| async { (StatusCode::BAD_REQUEST, "bad").into_response() }))
+            .route("/ok", get(|| async { Json(json!({"error": {}})) }))
+            .layer(axum::middleware::from_fn(tag_errors))
+    }
+
+    async fn get_path(path: &str) -> (axum::http::HeaderMap, Vec<u8>) {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (headers, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_are_tagged() {
+        let (headers, body) = get_path("/throttled").await;
+        assert_eq!(headers[ERROR_CODE_HEADER], "throttled");
+        assert_eq!(headers[ERROR_RETRYABLE_HEADER], "true");
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["error_code"], "throttled");
+        assert_eq!(body["error"]["retryable"], true);
+        assert_eq!(body["error"]["message"], "slow down");
+
+        // Classified by status, body untouched
+        let (headers, body) = get_path("/text").await;
+        assert_eq!(headers[ERROR_CODE_HEADER], "invalid_request");
+        assert_eq!(headers[ERROR_RETRYABLE_HEADER], "false");
+        assert_eq!(body, b"bad");
+
+        let (headers, body) = get_path("/ok").await;
+        assert!(headers.get(ERROR_CODE_HEADER).is_none());
+        assert_eq!(body, br#"{"error":{}}"#);
+
+        let (headers, _) = get_path("/missing").await;
+        assert_eq!(headers[ERROR_CODE_HEADER], "not_found");
+    }
+}
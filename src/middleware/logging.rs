@@ -50,7 +50,8 @@ impl std::fmt::Display for TraceId {
 /// Middleware to log HTTP requests and responses
 ///
 /// This middleware:
-/// - Generates or extracts a trace ID for request correlation
+/// - Generates or extracts a trace ID for request correlation and stores it
+///   in the request extensions
 /// - Logs request details (method, path, headers)
 /// - Logs response details (status, duration)
 /// - Adds trace ID to response headers
@@ -61,16 +62,17 @@ impl std::fmt::Display for TraceId {
 /// Router::new()
 ///     .layer(axum::middleware::from_fn(log_request))
 /// ```
-pub async fn log_request(request: Request, next: Next) -> Response<Body> {
+pub async fn log_request(mut request: Request, next: Next) -> Response<Body> {
     let start = Instant::now();
 
-    // Extract or generate trace ID
+    // Extract or generate trace ID; handlers and later log lines read it back
     let trace_id = extract_or_generate_trace_id(&request);
+    request.extensions_mut().insert(trace_id.clone());
 
     // Extract request details for logging
     let method = request.method().clone();
     let uri = request.uri().clone();
-    let path = uri.path().to_string();
+    let path = loggable_path(uri.path());
     let query = uri.query().map(|q| q.to_string());
     let version = format!("{:?}", request.version());
 
@@ -174,6 +176,29 @@ pub async fn log_request(request: Request, next: Next) -> Response<Body> {
     response
 }
 
+/// Request path as it is logged
+///
+/// Stored keys are addressed by key ID under `/admin/api-keys/`; anything
+/// else in that position (such as a key value sent by an old client) is
+/// replaced so it never reaches the logs.
+fn loggable_path(path: &str) -> String {
+    let Some(rest) = path.strip_prefix("/admin/api-keys/") else {
+        return path.to_string();
+    };
+    let (segment, tail) = rest.split_once('/').unwrap_or((rest, ""));
+    let is_key_id = segment
+        .strip_prefix("key_")
+        .is_some_and(|hex| hex.len() == 16 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if segment == "unused" || is_key_id {
+        return path.to_string();
+    }
+    if tail.is_empty() {
+        "/admin/api-keys/:redacted".to_string()
+    } else {
+        format!("/admin/api-keys/:redacted/{}", tail)
+    }
+}
+
 /// Extract trace ID from request headers or generate a new one
 fn extract_or_generate_trace_id(request: &Request) -> TraceId {
     // Try to extract from x-trace-id header first
@@ -227,4 +252,19 @@ mod tests {
         let trace_id = TraceId("test-trace-id".to_string());
         assert_eq!(format!("{}", trace_id), "test-trace-id");
     }
+
+    #[test]
+    fn test_loggable_path_redacts_key_values() {
+        assert_eq!(loggable_path("/v1/messages"), "/v1/messages");
+        assert_eq!(loggable_path("/admin/api-keys/unused"), "/admin/api-keys/unused");
+        assert_eq!(
+            loggable_path("/admin/api-keys/key_0123456789abcdef/rotate"),
+            "/admin/api-keys/key_0123456789abcdef/rotate"
+        );
+        assert_eq!(
+            loggable_path("/admin/api-keys/sk-3f2a9c1e-secret/quota"),
+            "/admin/api-keys/:redacted/quota"
+        );
+        assert_eq!(loggable_path("/admin/api-keys/sk-3f2a9c1e-secret"), "/admin/api-keys/:redacted");
+    }
 }
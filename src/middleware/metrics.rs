@@ -1 +1,554 @@
-// TODO: Implement in later phases
+//! Prometheus metrics middleware
+//!
+//! [`track_metrics`] wraps the API routes and records, per request:
+//!
+//! - `llm_requests_total` by model, backend, API key and status code
+//! - `llm_request_errors_total` by model, backend, API key and error type
+//! - `llm_request_duration_seconds`, a latency histogram (p50/p95/p99 come
+//!   from `histogram_quantile` over its buckets)
+//! - `llm_tokens_total` by model, backend, API key and direction
+//! - `llm_time_to_first_token_seconds` and `llm_output_tokens_per_second`
+//!   for streams, also labelled by backend region
+//!
+//! Handlers name the model and backend through the [`RequestMetrics`] handle
+//! the middleware puts in the request extensions; the API key comes from the
+//! authenticated [`ApiKeyInfo`], so the middleware sits inside the auth layer.
+//! A streamed response is timed by its [`StreamMetrics`] until the stream
+//! ends rather than until its headers are sent, and logs a "Stream completed"
+//! line with its trace ID, time to first token and throughput when it ends.
+//! `/metrics` serves the text exposition format; it needs an admin key since
+//! the labels name API keys (by their hashed key id).
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+use crate::middleware::auth::ApiKeyInfo;
+use crate::middleware::logging::TraceId;
+
+/// Label value when a handler never named the model or backend
+const UNKNOWN: &str = "unknown";
+
+const LABELS: &[&str] = &["model", "backend", "api_key_id"];
+
+/// Labels of the streaming latency metrics, which compare regions too
+const STREAM_LABELS: &[&str] = &["model", "backend", "api_key_id", "region"];
+
+/// Latency buckets in seconds, from fast errors to long generations
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Time-to-first-token buckets in seconds
+const TTFT_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 5.0, 10.0, 30.0];
+
+/// Output throughput buckets in tokens per second
+const TOKENS_PER_SECOND_BUCKETS: &[f64] = &[
+    5.0, 10.0, 20.0, 30.0, 40.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0, 500.0,
+];
+
+/// Labels shared by every gateway metric
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricLabels {
+    pub model: String,
+    pub backend: String,
+    pub api_key_id: String,
+    /// Backend region; only the streaming latency metrics carry it
+    pub region: String,
+}
+
+impl Default for MetricLabels {
+    fn default() -> Self {
+        Self {
+            model: UNKNOWN.to_string(),
+            backend: UNKNOWN.to_string(),
+            api_key_id: UNKNOWN.to_string(),
+            region: UNKNOWN.to_string(),
+        }
+    }
+}
+
+impl MetricLabels {
+    fn values(&self) -> [&str; 3] {
+        [&self.model, &self.backend, &self.api_key_id]
+    }
+
+    fn stream_values(&self) -> [&str; 4] {
+        [&self.model, &self.backend, &self.api_key_id, &self.region]
+    }
+}
+
+/// Gateway metrics and the registry `/metrics` renders
+pub struct GatewayMetrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    errors: IntCounterVec,
+    latency: HistogramVec,
+    ttft: HistogramVec,
+    tokens_per_second: HistogramVec,
+    tokens: IntCounterVec,
+}
+
+impl std::fmt::Debug for GatewayMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GatewayMetrics").finish_non_exhaustive()
+    }
+}
+
+impl Default for GatewayMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GatewayMetrics {
+    pub fn new() -> Self {
+        let labels_with = |extra: &'static str| {
+            let mut labels = LABELS.to_vec();
+            labels.push(extra);
+            labels
+        };
+
+        let requests = IntCounterVec::new(
+            Opts::new("llm_requests_total", "Requests by model, backend, API key and status"),
+            &labels_with("status"),
+        )
+        .expect("valid metric");
+        let errors = IntCounterVec::new(
+            Opts::new("llm_request_errors_total", "Failed requests by error type"),
+            &labels_with("error_type"),
+        )
+        .expect("valid metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "llm_request_duration_seconds",
+                "Request latency, to the end of the stream for streams",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            LABELS,
+        )
+        .expect("valid metric");
+        let ttft = HistogramVec::new(
+            HistogramOpts::new(
+                "llm_time_to_first_token_seconds",
+                "Time from request to first streamed output",
+            )
+            .buckets(TTFT_BUCKETS.to_vec()),
+            STREAM_LABELS,
+        )
+        .expect("valid metric");
+        let tokens_per_second = HistogramVec::new(
+            HistogramOpts::new(
+                "llm_output_tokens_per_second",
+                "Streamed output tokens per second after the first token",
+            )
+            .buckets(TOKENS_PER_SECOND_BUCKETS.to_vec()),
+            STREAM_LABELS,
+        )
+        .expect("valid metric");
+        let tokens = IntCounterVec::new(
+            Opts::new("llm_tokens_total", "Tokens processed, by direction (input or output)"),
+            &labels_with("direction"),
+        )
+        .expect("valid metric");
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(errors.clone()),
+            Box::new(latency.clone()),
+            Box::new(ttft.clone()),
+            Box::new(tokens_per_second.clone()),
+            Box::new(tokens.clone()),
+        ] {
+            registry.register(collector).expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            requests,
+            errors,
+            latency,
+            ttft,
+            tokens_per_second,
+            tokens,
+        }
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Content type of [`Self::render`]
+    pub fn content_type(&self) -> String {
+        TextEncoder::new().format_type().to_string()
+    }
+
+    /// Record a finished response; `duration` is None for streams, which
+    /// their [`StreamMetrics`] times instead
+    fn observe_response(&self, labels: &MetricLabels, status: StatusCode, duration: Option<Duration>) {
+        let [model, backend, key] = labels.values();
+        self.requests
+            .with_label_values(&[model, backend, key, status.as_str()])
+            .inc();
+        if let Some(error_type) = error_type(status) {
+            self.errors
+                .with_label_values(&[model, backend, key, error_type])
+                .inc();
+        }
+        if let Some(duration) = duration {
+            self.latency
+                .with_label_values(&labels.values())
+                .observe(duration.as_secs_f64());
+        }
+    }
+
+    fn observe_tokens(&self, labels: &MetricLabels, input_tokens: i32, output_tokens: i32) {
+        let [model, backend, key] = labels.values();
+        self.tokens
+            .with_label_values(&[model, backend, key, "input"])
+            .inc_by(input_tokens.max(0) as u64);
+        self.tokens
+            .with_label_values(&[model, backend, key, "output"])
+            .inc_by(output_tokens.max(0) as u64);
+    }
+}
+
+/// Error type label of a failed response, in the Anthropic error vocabulary
+fn error_type(status: StatusCode) -> Option<&'static str> {
+    let error_type = match status.as_u16() {
+        0..=399 => return None,
+        400 | 422 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        503 | 529 => "overloaded_error",
+        500..=599 => "api_error",
+        _ => "invalid_request_error",
+    };
+    Some(error_type)
+}
+
+/// Callback given a request's final usage (input, output tokens)
+struct UsageHook(Box<dyn FnOnce(i32, i32) + Send>);
+
+impl std::fmt::Debug for UsageHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UsageHook")
+    }
+}
+
+#[derive(Debug, Default)]
+struct RequestMetricsInner {
+    labels: MetricLabels,
+    tokens: Option<(i32, i32)>,
+    usage_hook: Option<UsageHook>,
+}
+
+/// Per-request handle handlers use to label the request's metrics
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    metrics: Arc<GatewayMetrics>,
+    start: Instant,
+    trace_id: Option<String>,
+    inner: Arc<Mutex<RequestMetricsInner>>,
+}
+
+impl RequestMetrics {
+    fn new(metrics: Arc<GatewayMetrics>, api_key_id: Option<String>, trace_id: Option<String>) -> Self {
+        let mut labels = MetricLabels::default();
+        if let Some(api_key_id) = api_key_id {
+            labels.api_key_id = api_key_id;
+        }
+        Self {
+            metrics,
+            start: Instant::now(),
+            trace_id,
+            inner: Arc::new(Mutex::new(RequestMetricsInner {
+                labels,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Name the model and backend serving the request
+    pub fn set_route(&self, model: &str, backend: impl ToString) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.labels.model = model.to_string();
+        inner.labels.backend = backend.to_string();
+    }
+
+    /// Name the region of the backend serving the request
+    pub fn set_region(&self, region: &str) {
+        self.inner.lock().unwrap().labels.region = region.to_string();
+    }
+
+    /// Record the token usage of a non-streaming response
+    pub fn record_tokens(&self, input_tokens: i32, output_tokens: i32) {
+        let hook = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tokens = Some((input_tokens, output_tokens));
+            inner.usage_hook.take()
+        };
+        if let Some(UsageHook(hook)) = hook {
+            hook(input_tokens, output_tokens);
+        }
+    }
+
+    /// Call `hook` with the request's usage once it is recorded, by
+    /// [`record_tokens`](Self::record_tokens) or by the response stream
+    ///
+    /// A request that never reports usage drops the hook uncalled.
+    pub fn on_usage(&self, hook: impl FnOnce(i32, i32) + Send + 'static) {
+        self.inner.lock().unwrap().usage_hook = Some(UsageHook(Box::new(hook)));
+    }
+
+    /// Timer for a streamed response, started with the request
+    pub fn stream(&self) -> StreamMetrics {
+        let (labels, usage_hook) = {
+            let mut inner = self.inner.lock().unwrap();
+            (inner.labels.clone(), inner.usage_hook.take())
+        };
+        StreamMetrics {
+            metrics: self.metrics.clone(),
+            labels,
+            trace_id: self.trace_id.clone(),
+            start: self.start,
+            first_output: None,
+            output_tokens: None,
+            usage_hook: Mutex::new(usage_hook),
+        }
+    }
+
+    fn labels(&self) -> MetricLabels {
+        self.inner.lock().unwrap().labels.clone()
+    }
+}
+
+/// Times a streamed response until the stream is dropped
+///
+/// Streams that end before any output (a request rejected before the stream
+/// starts drops its handle too) are timed by [`track_metrics`] instead.
+#[derive(Debug)]
+pub struct StreamMetrics {
+    metrics: Arc<GatewayMetrics>,
+    labels: MetricLabels,
+    trace_id: Option<String>,
+    start: Instant,
+    first_output: Option<Duration>,
+    output_tokens: Option<i32>,
+    usage_hook: Mutex<Option<UsageHook>>,
+}
+
+impl StreamMetrics {
+    /// Record streamed output; the first call sets the time to first token
+    pub fn record_output(&mut self) {
+        if self.first_output.is_none() {
+            let elapsed = self.start.elapsed();
+            self.first_output = Some(elapsed);
+            self.metrics
+                .ttft
+                .with_label_values(&self.labels.stream_values())
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// Record the stream's final usage
+    pub fn record_tokens(&mut self, input_tokens: i32, output_tokens: i32) {
+        self.metrics
+            .observe_tokens(&self.labels, input_tokens, output_tokens);
+        self.output_tokens = Some(output_tokens);
+        let hook = self.usage_hook.lock().unwrap().take();
+        if let Some(UsageHook(hook)) = hook {
+            hook(input_tokens, output_tokens);
+        }
+    }
+}
+
+/// Output tokens per second between the first output and the end of the
+/// stream, when there is a measurable span to divide by
+fn tokens_per_second(output_tokens: i32, first_output: Duration, total: Duration) -> Option<f64> {
+    let generation = total.saturating_sub(first_output).as_secs_f64();
+    (output_tokens > 0 && generation >= 0.001).then(|| output_tokens as f64 / generation)
+}
+
+impl Drop for StreamMetrics {
+    fn drop(&mut self) {
+        let Some(first_output) = self.first_output else {
+            return;
+        };
+        let total = self.start.elapsed();
+        self.metrics
+            .latency
+            .with_label_values(&self.labels.values())
+            .observe(total.as_secs_f64());
+
+        let rate = self
+            .output_tokens
+            .and_then(|output_tokens| tokens_per_second(output_tokens, first_output, total));
+        if let Some(rate) = rate {
+            self.metrics
+                .tokens_per_second
+                .with_label_values(&self.labels.stream_values())
+                .observe(rate);
+        }
+
+        tracing::info!(
+            trace_id = %self.trace_id.as_deref().unwrap_or("-"),
+            model = %self.labels.model,
+            backend = %self.labels.backend,
+            region = %self.labels.region,
+            ttft_ms = %format!("{:.2}", first_output.as_secs_f64() * 1000.0),
+            duration_ms = %format!("{:.2}", total.as_secs_f64() * 1000.0),
+            output_tokens = self.output_tokens.unwrap_or(0),
+            tokens_per_sec = %rate.map_or_else(|| "-".to_string(), |rate| format!("{:.1}", rate)),
+            "Stream completed"
+        );
+    }
+}
+
+/// Middleware recording request metrics
+///
+/// Runs inside authentication; see the module documentation.
+pub async fn track_metrics(
+    State(metrics): State<Arc<GatewayMetrics>>,
+    mut request: Request,
+    next: Next,
+) -> Response<Body> {
+    let api_key_id = request
+        .extensions()
+        .get::<ApiKeyInfo>()
+        .filter(|info| !info.key_id.is_empty())
+        .map(|info| info.key_id.clone());
+    let trace_id = request.extensions().get::<TraceId>().map(|id| id.to_string());
+    let handle = RequestMetrics::new(metrics.clone(), api_key_id, trace_id);
+    request.extensions_mut().insert(handle.clone());
+
+    let response = next.run(request).await;
+
+    let streaming = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let (labels, tokens) = {
+        let inner = handle.inner.lock().unwrap();
+        (inner.labels.clone(), inner.tokens)
+    };
+    metrics.observe_response(
+        &labels,
+        response.status(),
+        (!streaming).then(|| handle.start.elapsed()),
+    );
+    if let Some((input_tokens, output_tokens)) = tokens {
+        metrics.observe_tokens(&labels, input_tokens, output_tokens);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_metrics_render() {
+        let metrics = Arc::new(GatewayMetrics::new());
+        let handle = RequestMetrics::new(metrics.clone(), Some("key_00000000000000ab".to_string()), None);
+        handle.set_route("claude-sonnet-4-5", "bedrock");
+        handle.record_tokens(120, 30);
+
+        let labels = handle.labels();
+        metrics.observe_response(&labels, StatusCode::OK, Some(Duration::from_millis(800)));
+        metrics.observe_tokens(&labels, 120, 30);
+        metrics.observe_response(&labels, StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_millis(5)));
+
+        let text = metrics.render();
+        assert!(text.contains(
+            r#"llm_requests_total{api_key_id="key_00000000000000ab",backend="bedrock",model="claude-sonnet-4-5",status="200"} 1"#
+        ));
+        assert!(text.contains(r#"error_type="rate_limit_error""#));
+        assert!(text.contains(r#"direction="output",model="claude-sonnet-4-5"} 30"#));
+        assert!(text.contains("llm_request_duration_seconds_bucket"));
+    }
+
+    #[test]
+    fn test_stream_metrics() {
+        let metrics = Arc::new(GatewayMetrics::new());
+        let handle = RequestMetrics::new(metrics.clone(), None, Some("trace-1".to_string()));
+        handle.set_route("gpt-4o", "azure");
+        handle.set_region("global");
+
+        // Never started: left to the middleware
+        drop(handle.stream());
+
+        let mut stream = handle.stream();
+        stream.record_output();
+        stream.record_output();
+        stream.first_output = Some(Duration::ZERO);
+        stream.start -= Duration::from_secs(1);
+        stream.record_tokens(10, 50);
+        drop(stream);
+
+        // Usage reaches the hook whichever way it is recorded
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        for streamed in [false, true] {
+            let reported_to = reported.clone();
+            handle.on_usage(move |input, output| reported_to.lock().unwrap().push((input, output)));
+            match streamed {
+                false => handle.record_tokens(7, 3),
+                true => handle.stream().record_tokens(10, 5),
+            }
+        }
+        assert_eq!(*reported.lock().unwrap(), [(7, 3), (10, 5)]);
+
+        let text = metrics.render();
+        assert!(text.contains(
+            r#"llm_time_to_first_token_seconds_count{api_key_id="unknown",backend="azure",model="gpt-4o",region="global"} 1"#
+        ));
+        assert!(text.contains(
+            r#"llm_request_duration_seconds_count{api_key_id="unknown",backend="azure",model="gpt-4o"} 1"#
+        ));
+        // 50 tokens over one second
+        assert!(text.contains(
+            r#"llm_output_tokens_per_second_bucket{api_key_id="unknown",backend="azure",model="gpt-4o",region="global",le="50"} 1"#
+        ));
+        assert!(text.contains(
+            r#"llm_output_tokens_per_second_bucket{api_key_id="unknown",backend="azure",model="gpt-4o",region="global",le="40"} 0"#
+        ));
+    }
+
+    #[test]
+    fn test_tokens_per_second() {
+        let secs = Duration::from_secs_f64;
+        assert_eq!(tokens_per_second(100, secs(0.5), secs(2.5)), Some(50.0));
+        assert_eq!(tokens_per_second(0, secs(0.5), secs(2.5)), None);
+        // Everything arrived with the first output
+        assert_eq!(tokens_per_second(100, secs(0.5), secs(0.5)), None);
+    }
+
+    #[test]
+    fn test_error_type() {
+        assert_eq!(error_type(StatusCode::OK), None);
+        assert_eq!(error_type(StatusCode::BAD_REQUEST), Some("invalid_request_error"));
+        assert_eq!(error_type(StatusCode::from_u16(529).unwrap()), Some("overloaded_error"));
+        assert_eq!(error_type(StatusCode::BAD_GATEWAY), Some("api_error"));
+    }
+}
@@ -3,11 +3,34 @@
 //! Contains HTTP middleware for authentication, rate limiting, logging, and metrics.
 
 pub mod auth;
+pub mod body_limit;
+pub mod brute_force;
+pub mod budget;
+pub mod compression;
+pub mod error_taxonomy;
 pub mod logging;
 pub mod metrics;
 pub mod rate_limit;
+pub mod rate_limit_store;
 
 // Re-export commonly used items
-pub use auth::{require_api_key, ApiKeyInfo, AuthError, AuthState};
+pub use auth::{
+    require_admin, require_admin_key, require_api_key, ApiKeyInfo, AuthError, AuthState,
+    ADMIN_KEY_HEADER, SCOPE_ADMIN, SCOPE_BACKEND_OVERRIDE, SCOPE_BEDROCK_EXTRA_FIELDS,
+    SCOPE_CHILD_KEYS, SCOPE_CONTENT_OFFLOAD, SCOPE_PRIORITY,
+};
+pub use body_limit::enforce_body_limit;
+pub use brute_force::AuthFailureGuard;
+pub use budget::{
+    budget_warnings, BudgetWarning, ModelDowngrade, BUDGET_DOWNGRADED_FROM_HEADER,
+};
+pub use compression::with_compression;
+pub use error_taxonomy::{tag_errors, with_taxonomy};
 pub use logging::{log_request, TraceId, TRACE_ID_HEADER, REQUEST_ID_HEADER};
-pub use rate_limit::{rate_limit, RateLimitError, RateLimitState};
+pub use metrics::{track_metrics, GatewayMetrics, MetricLabels, RequestMetrics, StreamMetrics};
+pub use rate_limit::{rate_limit, RateLimitError, RateLimitKind, RateLimitState, TokenReservation};
+#[cfg(feature = "redis")]
+pub use rate_limit_store::RedisRateLimiterStore;
+pub use rate_limit_store::{
+    BucketSpec, MemoryRateLimiterStore, RateDecision, RateLimitStoreError, RateLimiterStore,
+};
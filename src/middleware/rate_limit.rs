@@ -1,7 +1,17 @@
 //! Rate limiting middleware
 //!
 //! This module provides token bucket rate limiting for the Anthropic-Bedrock proxy.
-//! Each API key gets its own rate limiter, cached in memory for efficiency.
+//! Each API key gets its own bucket, kept in memory or, with
+//! `RATE_LIMIT_STORE=redis`, in a store shared by all replicas (see
+//! [`rate_limit_store`](super::rate_limit_store)). While the shared store is
+//! unreachable, each replica falls back to its own in-memory buckets.
+//!
+//! With `RATE_LIMIT_TOKENS_PER_MINUTE` set, or a key's own `tpm_limit`, each
+//! key also has a bucket of tokens. A request body's estimated input tokens are taken from it before
+//! the request runs ([`estimate_input_tokens`]); once the handler reports the
+//! actual usage (through [`RequestMetrics`], at the end of the stream for
+//! streams) the estimate is swapped for the input and output tokens used.
+//! Requests that report no usage, such as failed ones, give the estimate back.
 
 use axum::{
     body::Body,
@@ -11,110 +21,256 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use governor::{
-    clock::{Clock, DefaultClock},
-    state::{InMemoryState, NotKeyed},
-    Quota, RateLimiter,
-};
-use moka::future::Cache;
-use std::num::NonZeroU32;
+use serde_json::Value;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::config::Settings;
 use crate::middleware::auth::ApiKeyInfo;
+use crate::middleware::body_limit::body_too_large;
+use crate::middleware::metrics::RequestMetrics;
+use crate::middleware::rate_limit_store::{
+    BucketSpec, MemoryRateLimiterStore, RateDecision, RateLimiterStore,
+};
 use crate::schemas::anthropic::ErrorResponse;
 
 // ============================================================================
 // Types
 // ============================================================================
 
-/// Type alias for our rate limiter instance
-type KeyedRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
-
 /// Rate limit state shared across requests
 #[derive(Clone)]
 pub struct RateLimitState {
     /// Application settings
     pub settings: Arc<Settings>,
 
-    /// Cache of rate limiters per API key
-    /// Key: API key (truncated), Value: rate limiter
-    pub limiters: Cache<String, Arc<KeyedRateLimiter>>,
+    /// In-process buckets, also used while the shared store is unreachable
+    pub local: Arc<MemoryRateLimiterStore>,
+
+    /// Buckets shared between replicas (None = in-process only)
+    pub shared: Option<Arc<dyn RateLimiterStore>>,
 }
 
 impl RateLimitState {
-    /// Create a new rate limit state
+    /// Create a new rate limit state with in-process buckets
     pub fn new(settings: Arc<Settings>) -> Self {
-        // Create a cache with 10,000 max entries and 10 minute TTL
-        let limiters = Cache::builder()
-            .max_capacity(10_000)
-            .time_to_idle(Duration::from_secs(600))
-            .build();
+        Self {
+            settings,
+            local: Arc::new(MemoryRateLimiterStore::new()),
+            shared: None,
+        }
+    }
 
-        Self { settings, limiters }
+    /// Keep buckets in a store shared between replicas
+    pub fn with_shared_store(mut self, store: Option<Arc<dyn RateLimiterStore>>) -> Self {
+        self.shared = store;
+        self
     }
 
-    /// Get or create a rate limiter for the given API key info
-    pub async fn get_limiter(&self, key_info: &ApiKeyInfo) -> Arc<KeyedRateLimiter> {
-        let cache_key = key_info.api_key.clone();
+    /// Bucket for a key: its own requests per window, the configured window,
+    /// and a burst of the configured size (default: the per-window limit)
+    pub fn bucket_for(&self, key_info: &ApiKeyInfo) -> BucketSpec {
+        let config = &self.settings.rate_limit;
+        let requests = key_info.effective_rate_limit(config.requests_per_window);
+        BucketSpec::new(
+            requests,
+            Duration::from_secs(config.window_seconds),
+            config.burst.unwrap_or(requests),
+        )
+    }
+
+    /// Bucket for a key's tokens, when a tokens-per-minute limit is set: the
+    /// key's own limit, or the configured default
+    pub fn token_bucket(&self, key_info: &ApiKeyInfo) -> Option<BucketSpec> {
+        let tokens_per_minute = key_info
+            .tpm_limit
+            .or(self.settings.rate_limit.tokens_per_minute)?;
+        Some(BucketSpec::new(tokens_per_minute, Duration::from_secs(60), tokens_per_minute))
+    }
 
-        // Try to get from cache
-        if let Some(limiter) = self.limiters.get(&cache_key).await {
-            return limiter;
+    /// Take a token from the key's bucket
+    pub async fn consume(&self, key_info: &ApiKeyInfo) -> RateDecision {
+        let key = format!("{}:{}", key_info.user_id, key_info.api_key);
+        let bucket = self.bucket_for(key_info);
+
+        if let Some(ref shared) = self.shared {
+            match shared.consume(&key, bucket).await {
+                Ok(decision) => return decision,
+                Err(e) => tracing::warn!(
+                    store = shared.name(),
+                    error = %e,
+                    "Shared rate limit store unavailable; using in-process buckets"
+                ),
+            }
+        }
+        match self.local.consume(&key, bucket).await {
+            Ok(decision) => decision,
+            // The in-process store cannot fail
+            Err(_) => RateDecision::Allowed { remaining: None },
         }
+    }
 
-        // Create new limiter
-        let rate_limit = key_info.effective_rate_limit(self.settings.rate_limit.requests_per_window);
-        let limiter = Arc::new(self.create_limiter(rate_limit));
+    /// Hold `estimated` tokens in the key's token bucket
+    ///
+    /// Fails with the time until the bucket holds them.
+    pub async fn reserve_tokens(
+        &self,
+        key_info: &ApiKeyInfo,
+        bucket: BucketSpec,
+        estimated: u32,
+    ) -> Result<TokenReservation, Duration> {
+        let key = format!("{}:{}:tokens", key_info.user_id, key_info.api_key);
+
+        let mut decision = None;
+        if let Some(ref shared) = self.shared {
+            match shared.consume_n(&key, bucket, estimated).await {
+                Ok(shared_decision) => decision = Some(shared_decision),
+                Err(e) => tracing::warn!(
+                    store = shared.name(),
+                    error = %e,
+                    "Shared rate limit store unavailable; using in-process buckets"
+                ),
+            }
+        }
+        let decision = match decision {
+            Some(decision) => decision,
+            None => self
+                .local
+                .consume_n(&key, bucket, estimated)
+                .await
+                .unwrap_or(RateDecision::Allowed { remaining: None }),
+        };
 
-        // Insert into cache
-        self.limiters.insert(cache_key, limiter.clone()).await;
+        match decision {
+            RateDecision::Allowed { remaining } => Ok(TokenReservation {
+                state: self.clone(),
+                key,
+                bucket,
+                held: estimated,
+                remaining,
+                settled: false,
+            }),
+            RateDecision::Limited { retry_after } => Err(retry_after),
+        }
+    }
 
-        limiter
+    /// Return tokens to (or take more from) a key's token bucket
+    async fn adjust_tokens(&self, key: &str, bucket: BucketSpec, delta: i64) {
+        if let Some(ref shared) = self.shared {
+            match shared.adjust(key, bucket, delta).await {
+                Ok(()) => return,
+                Err(e) => tracing::warn!(
+                    store = shared.name(),
+                    error = %e,
+                    "Shared rate limit store unavailable; using in-process buckets"
+                ),
+            }
+        }
+        let _ = self.local.adjust(key, bucket, delta).await;
     }
+}
 
-    /// Create a new rate limiter with the given requests per window
-    fn create_limiter(&self, requests_per_window: u32) -> KeyedRateLimiter {
-        let window_seconds = self.settings.rate_limit.window_seconds;
+/// Tokens a request holds in its key's token bucket
+///
+/// Starts as the request's input estimate; [`settle`](Self::settle) swaps it
+/// for the tokens actually used. A reservation dropped unsettled returns
+/// what it holds.
+pub struct TokenReservation {
+    state: RateLimitState,
+    key: String,
+    bucket: BucketSpec,
+    held: u32,
+    /// Whole tokens left in the bucket after the reservation, if known
+    pub remaining: Option<u32>,
+    settled: bool,
+}
 
-        // Convert to requests per second for governor
-        // If window is 60 seconds and limit is 100 requests, that's ~1.67 req/sec
-        // We use a quota that allows bursts up to the full window limit
+impl TokenReservation {
+    /// Replace the estimate with the tokens the request used
+    pub fn settle(mut self, used: u32) {
+        self.settled = true;
+        self.release(self.held as i64 - used as i64);
+    }
 
-        let quota = if window_seconds > 0 && requests_per_window > 0 {
-            // Allow bursts, replenish over the window period
-            let replenish_period = Duration::from_secs(window_seconds) / requests_per_window;
-            Quota::with_period(replenish_period)
-                .unwrap()
-                .allow_burst(NonZeroU32::new(requests_per_window).unwrap())
-        } else {
-            // Fallback: 100 requests per minute
-            Quota::per_minute(NonZeroU32::new(100).unwrap())
+    fn release(&self, delta: i64) {
+        if delta == 0 {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
         };
+        let (state, key, bucket) = (self.state.clone(), self.key.clone(), self.bucket);
+        runtime.spawn(async move { state.adjust_tokens(&key, bucket, delta).await });
+    }
+}
+
+impl Drop for TokenReservation {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.release(self.held as i64);
+        }
+    }
+}
 
-        RateLimiter::direct(quota)
+/// Rough input tokens of a request body (~4 characters per token)
+///
+/// Counts the strings of a JSON body, leaving out inline base64 data (image
+/// and document `data` fields, `data:` URLs), whose length says little about
+/// its token count. Bodies that aren't JSON count in full.
+pub fn estimate_input_tokens(body: &[u8]) -> u32 {
+    fn text_len(value: &Value) -> usize {
+        match value {
+            Value::String(s) if s.starts_with("data:") => 0,
+            Value::String(s) => s.len(),
+            Value::Array(items) => items.iter().map(text_len).sum(),
+            Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| key.as_str() != "data")
+                .map(|(_, value)| text_len(value))
+                .sum(),
+            _ => 0,
+        }
     }
+
+    let len = match serde_json::from_slice::<Value>(body) {
+        Ok(value) => text_len(&value),
+        Err(_) => body.len(),
+    };
+    u32::try_from(len.div_ceil(4)).unwrap_or(u32::MAX)
 }
 
 // ============================================================================
 // Rate Limit Errors
 // ============================================================================
 
+/// Which of a key's limits a request ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKind {
+    /// Requests per window
+    Requests,
+    /// Tokens per minute
+    Tokens,
+}
+
 /// Rate limit error with retry information
 #[derive(Debug)]
 pub struct RateLimitError {
     /// Seconds until the next request is allowed
     pub retry_after_seconds: u64,
+    pub kind: RateLimitKind,
 }
 
 impl IntoResponse for RateLimitError {
     fn into_response(self) -> Response {
+        let limit = match self.kind {
+            RateLimitKind::Requests => "Rate limit",
+            RateLimitKind::Tokens => "Token rate limit (tokens per minute)",
+        };
         let error_response = ErrorResponse::new(
             "rate_limit_error",
             &format!(
-                "Rate limit exceeded. Please retry after {} seconds.",
-                self.retry_after_seconds
+                "{} exceeded. Please retry after {} seconds.",
+                limit, self.retry_after_seconds
             ),
         );
 
@@ -143,12 +299,17 @@ impl IntoResponse for RateLimitError {
 ///
 /// This middleware:
 /// 1. Extracts `ApiKeyInfo` from request extensions (set by auth middleware)
-/// 2. Gets or creates a rate limiter for the API key
-/// 3. Checks if the request is allowed
-/// 4. Returns 429 Too Many Requests if rate limited
+/// 2. With a tokens-per-minute limit (the key's own or the default),
+///    reserves the body's estimated input tokens
+/// 3. Takes a token from the key's request bucket
+/// 4. Settles the reserved tokens once the handler reports usage
+/// 5. Returns 429 Too Many Requests if rate limited
 ///
 /// # Prerequisites
 /// - Auth middleware must run first to set `ApiKeyInfo` in extensions
+/// - Token usage is settled through the `RequestMetrics` handle, so the
+///   metrics middleware must run first too; without it the estimate is
+///   given back as soon as the response is ready
 ///
 /// # Headers
 /// On rate limit exceeded:
@@ -183,39 +344,90 @@ pub async fn rate_limit(
         return Ok(next.run(request).await);
     }
 
-    // Get rate limiter for this key
-    let limiter = rate_state.get_limiter(&key_info).await;
-
-    // Check rate limit
-    match limiter.check() {
-        Ok(_) => {
-            // Request allowed
-            let mut response = next.run(request).await;
+    // Hold the body's estimated input tokens in the key's token bucket. This
+    // runs before the request bucket, so a request turned away for tokens
+    // doesn't use up a request; one turned away for requests gives its
+    // tokens back when the reservation drops.
+    let mut request = request;
+    let mut reservation = None;
+    if let Some(bucket) = rate_state.token_bucket(&key_info) {
+        let limits = &rate_state.settings.body_limits;
+        let max_body = limits
+            .default_bytes
+            .max(limits.messages_bytes)
+            .max(limits.chat_completions_bytes);
+        let (parts, body) = request.into_parts();
+        let Ok(bytes) = axum::body::to_bytes(body, max_body).await else {
+            return Ok(body_too_large(max_body));
+        };
 
-            // Add rate limit info headers
-            add_rate_limit_headers(&mut response, &key_info, &rate_state.settings);
+        // Bodiless requests (listing models, fetching completions) use no tokens
+        if !bytes.is_empty() {
+            let estimated = estimate_input_tokens(&bytes);
+            let held = rate_state
+                .reserve_tokens(&key_info, bucket, estimated)
+                .await
+                .map_err(|retry_after| limited(&key_info, RateLimitKind::Tokens, retry_after))?;
+            reservation = Some(held);
+        }
+        request = Request::from_parts(parts, Body::from(bytes));
+    }
 
-            Ok(response)
+    // Take a token from this key's bucket
+    let remaining = match rate_state.consume(&key_info).await {
+        RateDecision::Allowed { remaining } => remaining,
+        RateDecision::Limited { retry_after } => {
+            return Err(limited(&key_info, RateLimitKind::Requests, retry_after));
         }
-        Err(not_until) => {
-            // Rate limited
-            let retry_after = not_until.wait_time_from(DefaultClock::default().now());
-            let retry_after_seconds = retry_after.as_secs().max(1);
-
-            tracing::warn!(
-                key = %key_info.api_key,
-                user_id = %key_info.user_id,
-                retry_after_seconds = retry_after_seconds,
-                "Rate limit exceeded"
-            );
+    };
 
-            Err(RateLimitError { retry_after_seconds })
+    // Settle the reserved tokens once the handler reports usage
+    let token_limit = reservation.map(|reservation| {
+        let limit = (reservation.bucket.capacity, reservation.remaining);
+        if let Some(metrics) = request.extensions().get::<RequestMetrics>() {
+            metrics.on_usage(move |input_tokens, output_tokens| {
+                reservation.settle((input_tokens.max(0) + output_tokens.max(0)) as u32)
+            });
         }
+        limit
+    });
+
+    let mut response = next.run(request).await;
+
+    // Add rate limit info headers
+    add_rate_limit_headers(&mut response, &key_info, &rate_state.settings, remaining);
+    if let Some((limit, remaining)) = token_limit {
+        add_token_limit_headers(&mut response, limit, remaining);
+    }
+
+    Ok(response)
+}
+
+/// Log and build the 429 for a request that ran into a limit
+fn limited(key_info: &ApiKeyInfo, kind: RateLimitKind, retry_after: Duration) -> RateLimitError {
+    let retry_after_seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+    tracing::warn!(
+        key = %key_info.api_key,
+        user_id = %key_info.user_id,
+        retry_after_seconds = retry_after_seconds,
+        limit = ?kind,
+        "Rate limit exceeded"
+    );
+
+    RateLimitError {
+        retry_after_seconds,
+        kind,
     }
 }
 
 /// Add rate limit information headers to response
-fn add_rate_limit_headers(response: &mut Response, key_info: &ApiKeyInfo, settings: &Settings) {
+fn add_rate_limit_headers(
+    response: &mut Response,
+    key_info: &ApiKeyInfo,
+    settings: &Settings,
+    remaining: Option<u32>,
+) {
     let headers = response.headers_mut();
 
     // X-RateLimit-Limit: Maximum requests per window
@@ -224,9 +436,23 @@ fn add_rate_limit_headers(response: &mut Response, key_info: &ApiKeyInfo, settin
         headers.insert("x-ratelimit-limit", v);
     }
 
-    // Note: Calculating remaining is complex with token bucket
-    // We'd need to query the limiter state which isn't directly exposed
-    // For now, we only add limit and reset headers
+    // X-RateLimit-Remaining: Whole tokens left, when the store reports them
+    // (the in-process governor buckets don't expose their state)
+    if let Some(v) = remaining.and_then(|r| r.to_string().parse().ok()) {
+        headers.insert("x-ratelimit-remaining", v);
+    }
+}
+
+/// Add token limit headers: the key's tokens per minute and, when the store
+/// reports it, what was left after the request's estimate
+fn add_token_limit_headers(response: &mut Response, limit: u32, remaining: Option<u32>) {
+    let headers = response.headers_mut();
+    if let Ok(v) = limit.to_string().parse() {
+        headers.insert("x-ratelimit-limit-tokens", v);
+    }
+    if let Some(v) = remaining.and_then(|r| r.to_string().parse().ok()) {
+        headers.insert("x-ratelimit-remaining-tokens", v);
+    }
 }
 
 // ============================================================================
@@ -237,28 +463,47 @@ fn add_rate_limit_headers(response: &mut Response, key_info: &ApiKeyInfo, settin
 mod tests {
     use super::*;
 
+    fn key_info(rate_limit: Option<u32>) -> ApiKeyInfo {
+        ApiKeyInfo {
+            api_key: "test-key".to_string(),
+            key_id: "key_test".to_string(),
+            user_id: "user-1".to_string(),
+            is_master: false,
+            rate_limit,
+            tpm_limit: None,
+            service_tier: "default".to_string(),
+            monthly_budget: None,
+            budget_used_mtd: 0.0,
+            scopes: Vec::new(),
+            expires_at: None,
+            allowed_models: Vec::new(),
+            ptc_budget: Default::default(),
+            inference_preset: None,
+        }
+    }
+
     #[test]
     fn test_rate_limit_state_creation() {
         let settings = Arc::new(Settings::default());
         let state = RateLimitState::new(settings);
-        assert_eq!(state.limiters.entry_count(), 0);
+        assert_eq!(state.local.entry_count(), 0);
+        assert!(state.shared.is_none());
     }
 
-    #[test]
-    fn test_create_limiter() {
+    #[tokio::test]
+    async fn test_first_request_allowed() {
         let settings = Arc::new(Settings::default());
         let state = RateLimitState::new(settings);
 
-        let limiter = state.create_limiter(100);
-
         // First request should be allowed
-        assert!(limiter.check().is_ok());
+        assert!(matches!(state.consume(&key_info(Some(100))).await, RateDecision::Allowed { .. }));
     }
 
     #[test]
     fn test_rate_limit_error_response() {
         let error = RateLimitError {
             retry_after_seconds: 30,
+            kind: RateLimitKind::Requests,
         };
 
         let response = error.into_response();
@@ -269,43 +514,137 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_limiter_caching() {
+    async fn test_bucket_shared_across_requests() {
         let settings = Arc::new(Settings::default());
         let state = RateLimitState::new(settings);
+        let key_info = key_info(Some(1));
 
-        let key_info = ApiKeyInfo {
-            api_key: "test-key".to_string(),
-            user_id: "user-1".to_string(),
-            is_master: false,
-            rate_limit: Some(50),
-            service_tier: "default".to_string(),
-            monthly_budget: None,
-            budget_used_mtd: 0.0,
-        };
-
-        // Get limiter twice
-        let limiter1 = state.get_limiter(&key_info).await;
-        let limiter2 = state.get_limiter(&key_info).await;
-
-        // Should be the same instance (Arc pointer comparison)
-        assert!(Arc::ptr_eq(&limiter1, &limiter2));
+        // Both requests use the key's one bucket
+        assert!(matches!(state.consume(&key_info).await, RateDecision::Allowed { .. }));
+        assert!(matches!(state.consume(&key_info).await, RateDecision::Limited { .. }));
     }
 
-    #[test]
-    fn test_burst_allowance() {
+    #[tokio::test]
+    async fn test_burst_allowance() {
         let mut settings = Settings::default();
         settings.rate_limit.requests_per_window = 10;
         settings.rate_limit.window_seconds = 60;
 
         let state = RateLimitState::new(Arc::new(settings));
-        let limiter = state.create_limiter(10);
+        let key_info = key_info(None);
 
         // Should allow burst of requests
         for i in 0..10 {
-            assert!(limiter.check().is_ok(), "Request {} should be allowed", i);
+            assert!(
+                matches!(state.consume(&key_info).await, RateDecision::Allowed { .. }),
+                "Request {} should be allowed",
+                i
+            );
         }
 
         // 11th request should be rate limited
-        assert!(limiter.check().is_err(), "Request 11 should be rate limited");
+        assert!(
+            matches!(state.consume(&key_info).await, RateDecision::Limited { .. }),
+            "Request 11 should be rate limited"
+        );
+    }
+
+    #[test]
+    fn test_configured_burst() {
+        let mut settings = Settings::default();
+        settings.rate_limit.requests_per_window = 60;
+        settings.rate_limit.window_seconds = 60;
+        settings.rate_limit.burst = Some(5);
+
+        let state = RateLimitState::new(Arc::new(settings));
+        let bucket = state.bucket_for(&key_info(None));
+        assert_eq!(bucket.capacity, 5);
+        assert_eq!(bucket.refill_period, Duration::from_secs(1));
+
+        // The key's own limit sets the refill rate
+        let bucket = state.bucket_for(&key_info(Some(120)));
+        assert_eq!(bucket.refill_period, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_estimate_input_tokens() {
+        let body = serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "x".repeat(400)},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "A".repeat(100_000)}},
+                {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", "A".repeat(100_000))}}
+            ]}]
+        });
+        let estimate = estimate_input_tokens(body.to_string().as_bytes());
+        assert!((100..150).contains(&estimate), "estimate {}", estimate);
+
+        assert_eq!(estimate_input_tokens(b"not json, 26 characters..."), 7);
+    }
+
+    #[tokio::test]
+    async fn test_token_reservation_settles_to_usage() {
+        let mut settings = Settings::default();
+        settings.rate_limit.tokens_per_minute = Some(1000);
+        let state = RateLimitState::new(Arc::new(settings));
+        let bucket = state.token_bucket(&key_info(None)).unwrap();
+        let key_info = key_info(None);
+
+        let reservation = state.reserve_tokens(&key_info, bucket, 800).await.unwrap();
+        assert_eq!(reservation.remaining, Some(200));
+        assert!(state.reserve_tokens(&key_info, bucket, 300).await.is_err());
+
+        // The request used less than estimated: the rest comes back
+        reservation.settle(100);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let reservation = state.reserve_tokens(&key_info, bucket, 300).await.unwrap();
+        assert!(reservation.remaining.unwrap() >= 600);
+
+        // Unsettled reservations give everything back
+        drop(reservation);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(state.reserve_tokens(&key_info, bucket, 900).await.is_ok());
+    }
+
+    #[test]
+    fn test_token_bucket_prefers_key_limit() {
+        let mut settings = Settings::default();
+        settings.rate_limit.tokens_per_minute = None;
+        let state = RateLimitState::new(Arc::new(settings));
+        let mut key_info = key_info(None);
+        assert!(state.token_bucket(&key_info).is_none());
+
+        key_info.tpm_limit = Some(5000);
+        assert_eq!(state.token_bucket(&key_info).unwrap().capacity, 5000);
+    }
+
+    #[tokio::test]
+    async fn test_token_limited_request_keeps_request_token() {
+        use axum::{routing::post, Extension, Router};
+        use tower::ServiceExt;
+
+        let mut settings = Settings::default();
+        settings.rate_limit.enabled = true;
+        settings.rate_limit.requests_per_window = 1;
+        settings.rate_limit.burst = None;
+        let state = RateLimitState::new(Arc::new(settings));
+        let mut key_info = key_info(None);
+        key_info.tpm_limit = Some(10);
+
+        let app = Router::new()
+            .route("/", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state, rate_limit))
+            .layer(Extension(key_info));
+        let send = |body: String| {
+            app.clone()
+                .oneshot(Request::post("/").body(Body::from(body)).unwrap())
+        };
+
+        // Over the token limit: turned away without spending the one request
+        let response = send("x".repeat(400)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let response = send("small".to_string()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
     }
 }
@@ -0,0 +1,469 @@
+//! Token bucket stores for rate limiting
+//!
+//! [`RateLimiterStore`] is where the per-key token buckets live. The
+//! in-process [`MemoryRateLimiterStore`] keeps them in each replica, so N
+//! replicas behind a load balancer let a key through N times over. With the
+//! `redis` feature, [`RedisRateLimiterStore`] keeps them in Redis, where a
+//! Lua script refills and takes a token in one atomic step, using the Redis
+//! server clock so replicas agree on time.
+//!
+//! Request buckets hold one token per request. Token-per-minute buckets are
+//! taken from in amounts ([`RateLimiterStore::consume_n`]) and later settled
+//! against actual usage ([`RateLimiterStore::adjust`]), so they may go into
+//! debt when a request uses more than it reserved.
+
+use async_trait::async_trait;
+use governor::{
+    clock::{Clock, DefaultClock},
+    state::{InMemoryState, NotKeyed},
+    Quota, RateLimiter,
+};
+use moka::future::Cache;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Shape of a key's bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketSpec {
+    /// Most tokens the bucket holds (the burst)
+    pub capacity: u32,
+    /// Time to refill one token
+    pub refill_period: Duration,
+}
+
+impl BucketSpec {
+    /// `requests` per `window`, with up to `burst` requests at once
+    pub fn new(requests: u32, window: Duration, burst: u32) -> Self {
+        Self {
+            capacity: burst.max(1),
+            refill_period: (window / requests.max(1)).max(Duration::from_micros(1)),
+        }
+    }
+}
+
+/// Result of taking a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    /// Token taken; `remaining` tokens are left if the store knows
+    Allowed { remaining: Option<u32> },
+    /// Bucket empty; the next token arrives after `retry_after`
+    Limited { retry_after: Duration },
+}
+
+/// The store could not be reached or answered garbage
+#[derive(Debug, Error)]
+#[error("Rate limit store error: {0}")]
+pub struct RateLimitStoreError(pub String);
+
+/// Where token buckets are kept
+#[async_trait]
+pub trait RateLimiterStore: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &'static str;
+
+    /// Atomically refill `key`'s bucket and take one token from it
+    async fn consume(&self, key: &str, bucket: BucketSpec) -> Result<RateDecision, RateLimitStoreError>;
+
+    /// Atomically refill `key`'s bucket and take `amount` tokens if it holds
+    /// them; an amount over the capacity goes through once the bucket is full
+    async fn consume_n(
+        &self,
+        key: &str,
+        bucket: BucketSpec,
+        amount: u32,
+    ) -> Result<RateDecision, RateLimitStoreError>;
+
+    /// Return `delta` tokens to `key`'s bucket (or take them, if negative)
+    /// without checking; the bucket never exceeds its capacity but may go
+    /// into debt
+    async fn adjust(&self, key: &str, bucket: BucketSpec, delta: i64) -> Result<(), RateLimitStoreError>;
+}
+
+// ============================================================================
+// In-process store
+// ============================================================================
+
+type DirectRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+struct LocalBucket {
+    spec: BucketSpec,
+    limiter: DirectRateLimiter,
+}
+
+/// A bucket counted in fractional tokens, which may go into debt
+#[derive(Debug)]
+struct Budget {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct LocalBudget {
+    spec: BucketSpec,
+    budget: Mutex<Budget>,
+}
+
+/// Per-key cache: 10,000 keys at most, idle entries dropped after 10 minutes
+fn key_cache<V: Clone + Send + Sync + 'static>() -> Cache<String, V> {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_idle(Duration::from_secs(600))
+        .build()
+}
+
+/// Buckets in this process (one governor limiter per key for requests,
+/// one [`Budget`] per key for amounts)
+pub struct MemoryRateLimiterStore {
+    buckets: Cache<String, Arc<LocalBucket>>,
+    budgets: Cache<String, Arc<LocalBudget>>,
+}
+
+impl Default for MemoryRateLimiterStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryRateLimiterStore {
+    pub fn new() -> Self {
+        Self {
+            buckets: key_cache(),
+            budgets: key_cache(),
+        }
+    }
+
+    /// Number of keys with a bucket
+    pub fn entry_count(&self) -> u64 {
+        self.buckets.entry_count()
+    }
+
+    /// The key's bucket, replaced if its limits changed
+    async fn bucket(&self, key: &str, spec: BucketSpec) -> Arc<LocalBucket> {
+        if let Some(bucket) = self.buckets.get(key).await.filter(|b| b.spec == spec) {
+            return bucket;
+        }
+        let bucket = Arc::new(LocalBucket {
+            spec,
+            limiter: create_limiter(spec),
+        });
+        self.buckets.insert(key.to_string(), bucket.clone()).await;
+        bucket
+    }
+
+    /// The key's amount bucket (starting full), replaced if its limits changed
+    async fn budget(&self, key: &str, spec: BucketSpec) -> Arc<LocalBudget> {
+        if let Some(budget) = self.budgets.get(key).await.filter(|b| b.spec == spec) {
+            return budget;
+        }
+        let budget = Arc::new(LocalBudget {
+            spec,
+            budget: Mutex::new(Budget {
+                tokens: spec.capacity as f64,
+                updated: Instant::now(),
+            }),
+        });
+        self.budgets.insert(key.to_string(), budget.clone()).await;
+        budget
+    }
+}
+
+/// Refill `budget` up to `now`, then take `amount` tokens from it, only if
+/// it holds them when `check` is set (`amount` may be negative)
+fn take_from_budget(spec: BucketSpec, budget: &mut Budget, now: Instant, amount: i64, check: bool) -> RateDecision {
+    let capacity = spec.capacity as f64;
+    let refill_secs = spec.refill_period.as_secs_f64();
+    let elapsed = now.saturating_duration_since(budget.updated).as_secs_f64();
+    budget.tokens = (budget.tokens + elapsed / refill_secs).min(capacity);
+    budget.updated = now;
+
+    let needed = (amount as f64).min(capacity);
+    if check && budget.tokens < needed {
+        return RateDecision::Limited {
+            retry_after: Duration::from_secs_f64((needed - budget.tokens) * refill_secs),
+        };
+    }
+    budget.tokens = (budget.tokens - amount as f64).min(capacity);
+    RateDecision::Allowed {
+        remaining: Some(budget.tokens.max(0.0) as u32),
+    }
+}
+
+fn create_limiter(spec: BucketSpec) -> DirectRateLimiter {
+    let quota = Quota::with_period(spec.refill_period)
+        .unwrap_or_else(|| Quota::per_minute(NonZeroU32::MIN))
+        .allow_burst(NonZeroU32::new(spec.capacity).unwrap_or(NonZeroU32::MIN));
+    RateLimiter::direct(quota)
+}
+
+#[async_trait]
+impl RateLimiterStore for MemoryRateLimiterStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn consume(&self, key: &str, spec: BucketSpec) -> Result<RateDecision, RateLimitStoreError> {
+        let bucket = self.bucket(key, spec).await;
+        Ok(match bucket.limiter.check() {
+            Ok(()) => RateDecision::Allowed { remaining: None },
+            Err(not_until) => RateDecision::Limited {
+                retry_after: not_until.wait_time_from(DefaultClock::default().now()),
+            },
+        })
+    }
+
+    async fn consume_n(
+        &self,
+        key: &str,
+        spec: BucketSpec,
+        amount: u32,
+    ) -> Result<RateDecision, RateLimitStoreError> {
+        let local = self.budget(key, spec).await;
+        let mut budget = local.budget.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(take_from_budget(spec, &mut budget, Instant::now(), amount as i64, true))
+    }
+
+    async fn adjust(&self, key: &str, spec: BucketSpec, delta: i64) -> Result<(), RateLimitStoreError> {
+        let local = self.budget(key, spec).await;
+        let mut budget = local.budget.lock().unwrap_or_else(PoisonError::into_inner);
+        take_from_budget(spec, &mut budget, Instant::now(), -delta, false);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Redis store
+// ============================================================================
+
+/// Refill from the elapsed time, then take a token if there is one
+///
+/// KEYS[1] = bucket, ARGV = capacity, microseconds per token.
+/// Returns {allowed (0/1), whole tokens left, microseconds until a token}.
+#[cfg(feature = "redis")]
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_us = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) / refill_us)
+local allowed, wait_us = 0, 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+else
+  wait_us = math.ceil((1 - tokens) * refill_us)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity * refill_us / 1000) + 1000)
+return {allowed, math.floor(tokens), wait_us}
+"#;
+
+/// Refill from the elapsed time, then take an amount (negative to return
+/// tokens), only if the bucket holds it when checking
+///
+/// KEYS[1] = bucket, ARGV = capacity, microseconds per token, amount,
+/// check (0/1). Returns {allowed (0/1), whole tokens left, microseconds
+/// until the amount is available}.
+#[cfg(feature = "redis")]
+const TOKEN_BUDGET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_us = tonumber(ARGV[2])
+local amount = tonumber(ARGV[3])
+local check = ARGV[4] == '1'
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) / refill_us)
+local needed = math.min(amount, capacity)
+local allowed, wait_us = 1, 0
+if check and tokens < needed then
+  allowed = 0
+  wait_us = math.ceil((needed - tokens) * refill_us)
+else
+  tokens = math.min(capacity, tokens - amount)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity * refill_us / 1000) + 1000)
+return {allowed, math.floor(math.max(tokens, 0)), wait_us}
+"#;
+
+/// Buckets in Redis, shared by every replica
+#[cfg(feature = "redis")]
+pub struct RedisRateLimiterStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+    budget_script: redis::Script,
+    key_prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisRateLimiterStore {
+    /// Connect to Redis; the connection reconnects on its own afterwards
+    pub async fn connect(url: &str, key_prefix: impl Into<String>) -> Result<Self, RateLimitStoreError> {
+        let client = redis::Client::open(url).map_err(|e| RateLimitStoreError(e.to_string()))?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(|e| RateLimitStoreError(e.to_string()))?;
+        Ok(Self {
+            connection,
+            script: redis::Script::new(TOKEN_BUCKET_SCRIPT),
+            budget_script: redis::Script::new(TOKEN_BUDGET_SCRIPT),
+            key_prefix: key_prefix.into(),
+        })
+    }
+
+    async fn run_budget_script(
+        &self,
+        key: &str,
+        spec: BucketSpec,
+        amount: i64,
+        check: bool,
+    ) -> Result<(i64, i64, i64), RateLimitStoreError> {
+        let mut connection = self.connection.clone();
+        self.budget_script
+            .key(format!("{}:{}", self.key_prefix, key))
+            .arg(spec.capacity)
+            .arg(spec.refill_period.as_micros() as u64)
+            .arg(amount)
+            .arg(check as u8)
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| RateLimitStoreError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RateLimiterStore for RedisRateLimiterStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn consume(&self, key: &str, spec: BucketSpec) -> Result<RateDecision, RateLimitStoreError> {
+        let mut connection = self.connection.clone();
+        let (allowed, remaining, wait_us): (i64, i64, i64) = self
+            .script
+            .key(format!("{}:{}", self.key_prefix, key))
+            .arg(spec.capacity)
+            .arg(spec.refill_period.as_micros() as u64)
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| RateLimitStoreError(e.to_string()))?;
+
+        Ok(if allowed == 1 {
+            RateDecision::Allowed {
+                remaining: Some(remaining.max(0) as u32),
+            }
+        } else {
+            RateDecision::Limited {
+                retry_after: Duration::from_micros(wait_us.max(0) as u64),
+            }
+        })
+    }
+
+    async fn consume_n(
+        &self,
+        key: &str,
+        spec: BucketSpec,
+        amount: u32,
+    ) -> Result<RateDecision, RateLimitStoreError> {
+        let (allowed, remaining, wait_us) = self.run_budget_script(key, spec, amount as i64, true).await?;
+        Ok(if allowed == 1 {
+            RateDecision::Allowed {
+                remaining: Some(remaining.max(0) as u32),
+            }
+        } else {
+            RateDecision::Limited {
+                retry_after: Duration::from_micros(wait_us.max(0) as u64),
+            }
+        })
+    }
+
+    async fn adjust(&self, key: &str, spec: BucketSpec, delta: i64) -> Result<(), RateLimitStoreError> {
+        self.run_budget_script(key, spec, -delta, false).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_spec() {
+        let spec = BucketSpec::new(100, Duration::from_secs(60), 100);
+        assert_eq!(spec.capacity, 100);
+        assert_eq!(spec.refill_period, Duration::from_millis(600));
+
+        // A burst of 0 would never let anything through
+        assert_eq!(BucketSpec::new(10, Duration::from_secs(1), 0).capacity, 1);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_burst_and_limit_change() {
+        let store = MemoryRateLimiterStore::new();
+        let spec = BucketSpec::new(10, Duration::from_secs(60), 3);
+
+        for i in 0..3 {
+            assert!(
+                matches!(store.consume("user:sk-a", spec).await.unwrap(), RateDecision::Allowed { .. }),
+                "request {} should be allowed",
+                i
+            );
+        }
+        match store.consume("user:sk-a", spec).await.unwrap() {
+            RateDecision::Limited { retry_after } => assert!(retry_after <= Duration::from_secs(6)),
+            other => panic!("expected limited, got {:?}", other),
+        }
+
+        // Other keys have their own bucket
+        assert!(matches!(store.consume("user:sk-b", spec).await.unwrap(), RateDecision::Allowed { .. }));
+        store.buckets.run_pending_tasks().await;
+        assert_eq!(store.entry_count(), 2);
+
+        // Raising the key's limit replaces its bucket
+        let raised = BucketSpec::new(100, Duration::from_secs(60), 100);
+        assert!(matches!(store.consume("user:sk-a", raised).await.unwrap(), RateDecision::Allowed { .. }));
+    }
+
+    #[test]
+    fn test_budget_amounts_and_debt() {
+        // 600 tokens per minute: one every 100ms
+        let spec = BucketSpec::new(600, Duration::from_secs(60), 600);
+        let start = Instant::now();
+        let mut budget = Budget {
+            tokens: 600.0,
+            updated: start,
+        };
+
+        assert_eq!(
+            take_from_budget(spec, &mut budget, start, 500, true),
+            RateDecision::Allowed { remaining: Some(100) }
+        );
+        match take_from_budget(spec, &mut budget, start, 300, true) {
+            RateDecision::Limited { retry_after } => assert_eq!(retry_after.as_secs_f64().round(), 20.0),
+            other => panic!("expected limited, got {:?}", other),
+        }
+
+        // Settling at more than was reserved puts the bucket into debt...
+        take_from_budget(spec, &mut budget, start, 400, false);
+        assert!(matches!(
+            take_from_budget(spec, &mut budget, start, 1, true),
+            RateDecision::Limited { .. }
+        ));
+        // ...which refills like anything else; returns never overfill it
+        let later = start + Duration::from_secs(60);
+        take_from_budget(spec, &mut budget, later, -10_000, false);
+        assert_eq!(budget.tokens, 600.0);
+
+        // More than the capacity goes through once the bucket is full
+        assert_eq!(
+            take_from_budget(spec, &mut budget, later, 1_000, true),
+            RateDecision::Allowed { remaining: Some(0) }
+        );
+    }
+}
@@ -216,6 +216,9 @@ pub enum ContentBlock {
     #[serde(rename = "document")]
     Document {
         source: DocumentSource,
+        /// Client-provided title, used for the Bedrock document name
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         cache_control: Option<CacheControl>,
     },
@@ -414,8 +417,29 @@ impl Default for CodeExecutionTool {
 #[serde(untagged)]
 pub enum ToolChoice {
     Auto(String),                      // "auto" or "any"
-    Specific { name: String },         // {"type": "tool", "name": "tool_name"}
-    Object(serde_json::Value),         // Generic object form
+    Specific {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    }, // {"type": "tool", "name": "tool_name"}
+    Object(serde_json::Value), // Generic object form
+}
+
+impl ToolChoice {
+    /// Whether the client asked for at most one tool call per response
+    pub fn disables_parallel_tool_use(&self) -> bool {
+        match self {
+            ToolChoice::Auto(_) => false,
+            ToolChoice::Specific {
+                disable_parallel_tool_use,
+                ..
+            } => disable_parallel_tool_use.unwrap_or(false),
+            ToolChoice::Object(obj) => obj
+                .get("disable_parallel_tool_use")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        }
+    }
 }
 
 // ============================================================================
@@ -527,6 +551,35 @@ pub struct MessageRequest {
     // PTC container for session reuse
     #[serde(skip_serializing_if = "Option::is_none")]
     pub container: Option<String>,
+
+    // Capacity hint: "auto" (default) or "standard_only"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+
+    // Proxy extension: sampling seed for backends that support one (Gemini)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    // Proxy extension: stored prompt template rendered ahead of the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptReference>,
+
+    // Proxy extension: merged into Bedrock additionalModelRequestFields
+    // (see `api::extra_fields`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bedrock_extra_fields: Option<serde_json::Value>,
+}
+
+/// Reference to a stored prompt template (`POST /admin/prompts/{name}`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptReference {
+    /// Template name
+    pub id: String,
+    /// Template version (latest when omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
 }
 
 fn default_max_tokens() -> i32 {
@@ -551,6 +604,10 @@ impl MessageRequest {
             thinking: None,
             metadata: None,
             container: None,
+            service_tier: None,
+            seed: None,
+            prompt: None,
+            bedrock_extra_fields: None,
         }
     }
 
@@ -586,6 +643,9 @@ pub struct Usage {
     pub cache_creation_input_tokens: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_read_input_tokens: Option<i32>,
+    /// Tier the request ran on ("standard" or "priority")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
 }
 
 impl Usage {
@@ -595,6 +655,7 @@ impl Usage {
             output_tokens,
             cache_creation_input_tokens: None,
             cache_read_input_tokens: None,
+            service_tier: None,
         }
     }
 }
@@ -607,6 +668,8 @@ pub enum StopReason {
     MaxTokens,
     StopSequence,
     ToolUse,
+    /// A PTC session ran out of its per-key iteration or token budget
+    BudgetExhausted,
 }
 
 impl std::fmt::Display for StopReason {
@@ -616,6 +679,7 @@ impl std::fmt::Display for StopReason {
             StopReason::MaxTokens => write!(f, "max_tokens"),
             StopReason::StopSequence => write!(f, "stop_sequence"),
             StopReason::ToolUse => write!(f, "tool_use"),
+            StopReason::BudgetExhausted => write!(f, "budget_exhausted"),
         }
     }
 }
@@ -634,6 +698,17 @@ pub struct MessageResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequence: Option<String>,
     pub usage: Usage,
+    /// Code-execution container the request ran in (echoed for reuse)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+}
+
+/// Code-execution container reference returned in responses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Container {
+    pub id: String,
+    /// RFC 3339 time after which the container is discarded if unused
+    pub expires_at: String,
 }
 
 impl MessageResponse {
@@ -653,6 +728,7 @@ impl MessageResponse {
             stop_reason: Some(StopReason::EndTurn),
             stop_sequence: None,
             usage,
+            container: None,
         }
     }
 
@@ -661,6 +737,18 @@ impl MessageResponse {
         self.stop_reason = Some(reason);
         self
     }
+
+    /// Drop every `tool_use` block after the first.
+    ///
+    /// Enforces `disable_parallel_tool_use` for backends that cannot be told
+    /// to stop at one tool call.
+    pub fn keep_first_tool_use(&mut self) {
+        let mut seen = false;
+        self.content.retain(|block| match block {
+            ContentBlock::ToolUse { .. } => !std::mem::replace(&mut seen, true),
+            _ => true,
+        });
+    }
 }
 
 // ============================================================================
@@ -922,5 +1010,42 @@ mod tests {
     fn test_stop_reason_display() {
         assert_eq!(StopReason::EndTurn.to_string(), "end_turn");
         assert_eq!(StopReason::ToolUse.to_string(), "tool_use");
+        assert_eq!(StopReason::BudgetExhausted.to_string(), "budget_exhausted");
+    }
+
+    #[test]
+    fn test_tool_choice_disable_parallel_tool_use() {
+        let choice: ToolChoice = serde_json::from_str(
+            r#"{"type": "tool", "name": "get_weather", "disable_parallel_tool_use": true}"#,
+        )
+        .unwrap();
+        assert!(choice.disables_parallel_tool_use());
+
+        let choice: ToolChoice =
+            serde_json::from_str(r#"{"type": "auto", "disable_parallel_tool_use": true}"#).unwrap();
+        assert!(choice.disables_parallel_tool_use());
+
+        let choice: ToolChoice = serde_json::from_str(r#"{"type": "tool", "name": "get_weather"}"#).unwrap();
+        assert!(!choice.disables_parallel_tool_use());
+    }
+
+    #[test]
+    fn test_keep_first_tool_use() {
+        let tool_use = |id: &str| ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "get_weather".to_string(),
+            input: serde_json::json!({}),
+            caller: None,
+        };
+        let mut response = MessageResponse::new(
+            "msg_1",
+            "claude",
+            vec![ContentBlock::text("Checking"), tool_use("a"), tool_use("b")],
+            Usage::new(10, 5),
+        );
+        response.keep_first_tool_use();
+
+        assert_eq!(response.content.len(), 2);
+        assert!(matches!(&response.content[1], ContentBlock::ToolUse { id, .. } if id == "a"));
     }
 }
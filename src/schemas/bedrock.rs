@@ -94,6 +94,9 @@ pub struct BedrockDocumentData {
     pub format: String, // "pdf"
     pub name: String,
     pub source: BedrockDocumentSource,
+    /// Original client title; `name` is its sanitized, de-duplicated form
+    #[serde(skip)]
+    pub title: Option<String>,
 }
 
 /// Tool use content in Bedrock format.
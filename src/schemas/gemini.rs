@@ -38,13 +38,14 @@ pub struct GeminiRequest {
 }
 
 /// Content block containing role and parts
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GeminiContent {
     /// Role: "user" or "model"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
 
     /// Content parts
+    #[serde(default)]
     pub parts: Vec<Part>,
 }
 
@@ -178,6 +179,26 @@ pub struct GenerationConfig {
     /// Candidate count (usually 1)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub candidate_count: Option<i32>,
+
+    /// Presence penalty (-2.0 - 2.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// Frequency penalty (-2.0 - 2.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// Return log probabilities of the chosen tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_logprobs: Option<bool>,
+
+    /// Number of top alternatives to return per token (with `response_logprobs`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<i32>,
+
+    /// Sampling seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i32>,
 }
 
 /// Safety setting
@@ -257,7 +278,8 @@ pub struct GeminiResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
-    /// The generated content
+    /// The generated content (absent when a candidate is blocked)
+    #[serde(default)]
     pub content: GeminiContent,
 
     /// Finish reason
@@ -275,6 +297,45 @@ pub struct Candidate {
     /// Index of this candidate
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index: Option<i32>,
+
+    /// Token log probabilities (when requested with `responseLogprobs`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs_result: Option<LogprobsResult>,
+}
+
+/// Log probabilities of a candidate's tokens
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogprobsResult {
+    /// Top alternatives at each decoding step
+    #[serde(default)]
+    pub top_candidates: Vec<TopCandidates>,
+
+    /// The token chosen at each decoding step
+    #[serde(default)]
+    pub chosen_candidates: Vec<LogprobsCandidate>,
+}
+
+/// Alternatives at one decoding step, by descending log probability
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopCandidates {
+    #[serde(default)]
+    pub candidates: Vec<LogprobsCandidate>,
+}
+
+/// A token and its log probability
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogprobsCandidate {
+    #[serde(default)]
+    pub token: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<i32>,
+
+    #[serde(default)]
+    pub log_probability: f32,
 }
 
 /// Safety rating
@@ -318,16 +379,21 @@ pub struct CitationSource {
 }
 
 /// Usage metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Counts are omitted when zero, e.g. candidates in the first stream chunk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageMetadata {
     /// Prompt token count
+    #[serde(default)]
     pub prompt_token_count: i32,
 
     /// Candidates token count
+    #[serde(default)]
     pub candidates_token_count: i32,
 
     /// Total token count
+    #[serde(default)]
     pub total_token_count: i32,
 }
 
@@ -340,6 +406,7 @@ pub struct UsageMetadata {
 #[serde(rename_all = "camelCase")]
 pub struct StreamChunk {
     /// Candidates (partial)
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
 
     /// Usage metadata (usually in final chunk)
@@ -368,7 +435,37 @@ pub struct GeminiErrorDetail {
     pub message: String,
 
     /// Error status
+    #[serde(default)]
     pub status: String,
+
+    /// Typed details (`google.rpc.RetryInfo`, `google.rpc.QuotaFailure`, ...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<serde_json::Value>,
+}
+
+impl GeminiErrorDetail {
+    /// Delay Gemini asks for before retrying, from a `RetryInfo` detail
+    ///
+    /// Sent with `RESOURCE_EXHAUSTED` when a key's quota runs out, e.g.
+    /// `{"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "34s"}`.
+    pub fn retry_delay(&self) -> Option<std::time::Duration> {
+        self.details
+            .iter()
+            .filter(|d| {
+                d.get("@type")
+                    .and_then(|t| t.as_str())
+                    .is_some_and(|t| t.ends_with("google.rpc.RetryInfo"))
+            })
+            .find_map(|d| {
+                d.get("retryDelay")?
+                    .as_str()?
+                    .strip_suffix('s')?
+                    .parse::<f64>()
+                    .ok()
+            })
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(std::time::Duration::from_secs_f64)
+    }
 }
 
 // ============================================================================
@@ -4,6 +4,7 @@
 //! compatibility layer.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // ============================================================================
 // Request Types
@@ -78,13 +79,25 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<i32>,
 
-    /// Log probabilities (not supported, ignored)
+    /// Log probabilities (returned by Gemini, ignored elsewhere)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<bool>,
 
-    /// Top log probabilities (not supported, ignored)
+    /// Top log probabilities per token (with `logprobs`)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_logprobs: Option<i32>,
+
+    /// Processing tier hint: "auto", "default", "flex" or "priority"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+
+    /// Store the completion for later retrieval by ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<bool>,
+
+    /// Key-value tags for the stored completion and usage records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
 }
 
 /// Stream options
@@ -182,6 +195,55 @@ pub enum ContentPart {
     /// Image URL content
     #[serde(rename = "image_url")]
     ImageUrl { image_url: ImageUrl },
+
+    /// Base64 audio content (transcribed before reaching Bedrock)
+    InputAudio { input_audio: InputAudio },
+}
+
+impl ContentPart {
+    /// Image part carrying base64 data as a data URL
+    pub fn image_base64(media_type: &str, data: &str) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: format!("data:{};base64,{}", media_type, data),
+                detail: None,
+            },
+        }
+    }
+}
+
+/// Audio input specification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputAudio {
+    /// Base64-encoded audio
+    pub data: String,
+
+    /// Audio format: "wav" or "mp3"
+    pub format: String,
+}
+
+impl InputAudio {
+    /// MIME type of the audio format, if supported
+    pub fn media_type(&self) -> Option<&'static str> {
+        match self.format.as_str() {
+            "wav" => Some("audio/wav"),
+            "mp3" => Some("audio/mpeg"),
+            _ => None,
+        }
+    }
+}
+
+/// Transcription substituted for an `input_audio` part (response extension)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AudioTranscription {
+    /// Index of the request message holding the audio
+    pub message_index: usize,
+
+    /// Index of the audio part within the message content
+    pub part_index: usize,
+
+    /// Transcribed text
+    pub text: String,
 }
 
 /// Image URL specification
@@ -314,6 +376,14 @@ pub struct ChatCompletionResponse {
     /// System fingerprint (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+
+    /// Tier the request was processed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+
+    /// Transcriptions of `input_audio` parts (proxy extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_transcriptions: Option<Vec<AudioTranscription>>,
 }
 
 /// Completion choice
@@ -328,7 +398,7 @@ pub struct Choice {
     /// Reason for stopping
     pub finish_reason: Option<String>,
 
-    /// Log probabilities (not supported)
+    /// Log probabilities (Gemini only; absent for other backends)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<serde_json::Value>,
 }
@@ -346,6 +416,11 @@ pub struct AssistantMessage {
     /// Tool calls
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+
+    /// Images the model produced, as `image_url` parts with base64 data URLs
+    /// (kept out of `content` so text-only clients still get a string)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<ContentPart>>,
 }
 
 /// Token usage statistics
@@ -416,7 +491,7 @@ pub struct ChunkChoice {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<String>,
 
-    /// Log probabilities (not supported)
+    /// Log probabilities (Gemini only; absent for other backends)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<serde_json::Value>,
 }
@@ -498,6 +573,102 @@ pub struct Model {
     pub owned_by: String,
 }
 
+// ============================================================================
+// Embeddings API Types
+// ============================================================================
+
+/// Create embeddings request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateEmbeddingRequest {
+    /// Model ID
+    pub model: String,
+
+    /// Text or texts to embed
+    pub input: EmbeddingInput,
+
+    /// "float" (default) or "base64"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding_format: Option<EmbeddingEncoding>,
+
+    /// Number of dimensions of the output embeddings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+
+    /// End-user ID (accepted and ignored)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Embedding input (single string or array of strings)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl EmbeddingInput {
+    /// Inputs as a list
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(text) => vec![text],
+            EmbeddingInput::Multiple(texts) => texts,
+        }
+    }
+}
+
+/// Encoding of returned embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingEncoding {
+    Float,
+    Base64,
+}
+
+/// Create embeddings response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateEmbeddingResponse {
+    /// Object type (always "list")
+    pub object: String,
+
+    /// One embedding per input, in input order
+    pub data: Vec<Embedding>,
+
+    /// Model used
+    pub model: String,
+
+    /// Token usage
+    pub usage: EmbeddingUsage,
+}
+
+/// A single embedding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Object type (always "embedding")
+    pub object: String,
+
+    /// Position of the input
+    pub index: u32,
+
+    /// The vector, as floats or base64 little-endian f32 bytes
+    pub embedding: EmbeddingVector,
+}
+
+/// Embedding vector in the requested encoding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+/// Embedding token usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: i32,
+    pub total_tokens: i32,
+}
+
 // ============================================================================
 // Error Types
 // ============================================================================
@@ -642,6 +813,19 @@ mod tests {
         matches!(choice, ToolChoice::Function { .. });
     }
 
+    #[test]
+    fn test_embedding_input() {
+        let single: CreateEmbeddingRequest =
+            serde_json::from_str(r#"{"model": "m", "input": "hello"}"#).unwrap();
+        assert_eq!(single.input.into_vec(), vec!["hello"]);
+        let multiple: CreateEmbeddingRequest = serde_json::from_str(
+            r#"{"model": "m", "input": ["a", "b"], "encoding_format": "base64"}"#,
+        )
+        .unwrap();
+        assert_eq!(multiple.encoding_format, Some(EmbeddingEncoding::Base64));
+        assert_eq!(multiple.input.into_vec(), vec!["a", "b"]);
+    }
+
     #[test]
     fn test_error_response() {
         let err = OpenAIErrorResponse::invalid_request("Invalid model");
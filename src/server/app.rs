@@ -36,7 +36,8 @@ impl App {
         tracing::info!("Starting server on {}", addr);
 
         let listener = tokio::net::TcpListener::bind(addr).await?;
-        axum::serve(listener, router).await?;
+        axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
+            .await?;
 
         Ok(())
     }
@@ -53,7 +54,7 @@ impl App {
 
         let listener = tokio::net::TcpListener::bind(addr).await?;
 
-        axum::serve(listener, router)
+        axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
             .with_graceful_shutdown(shutdown_signal())
             .await?;
 
@@ -66,6 +67,10 @@ impl App {
     /// Cleanup application resources
     async fn cleanup(&self) {
         tracing::info!("Cleaning up application resources");
+        if let Some(ref tracker) = self.state.key_activity {
+            let flushed = tracker.flush().await;
+            tracing::debug!(keys = flushed, "Flushed pending key activity");
+        }
         // TODO: Add cleanup for PTC containers in Phase 7
         // TODO: Add cleanup for any pending DynamoDB writes in Phase 2
     }
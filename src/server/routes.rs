@@ -4,19 +4,36 @@
 
 use axum::{
     body::Body,
+    extract::DefaultBodyLimit,
     http::Request,
     middleware,
     response::Response,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
+use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 
-use crate::api::{chat_completions, event_logging, health, messages, models};
+#[cfg(feature = "admin-ui")]
+use axum::routing::put;
+#[cfg(feature = "admin-ui")]
+use crate::api::admin;
+#[cfg(feature = "admin-ui")]
+use crate::middleware::auth::require_admin_key;
+use crate::api::{
+    agents, capabilities, chat_completions, debug, dry_run, embeddings, event_logging,
+    generate_content, health, invoke, keys, messages, models, prompts,
+};
 use crate::error::ApiError;
 use crate::middleware::{
-    auth::{extract_api_key, require_api_key, AuthState},
+    auth::{extract_api_key, require_admin, require_api_key, AuthState},
+    body_limit::enforce_body_limit,
+    brute_force::AuthFailureGuard,
+    budget::budget_warnings,
+    compression::with_compression,
+    error_taxonomy::tag_errors,
     logging::log_request,
+    metrics::track_metrics,
     rate_limit::{rate_limit, RateLimitState},
 };
 use crate::server::state::AppState;
@@ -30,63 +47,281 @@ pub fn create_router(state: AppState) -> Router {
         .route("/liveness", get(health::liveness));
 
     // Event logging routes (no authentication required - telemetry)
-    let event_logging_routes = Router::new()
-        .route("/batch", post(event_logging::batch_events));
+    let body_limits = state.settings.body_limits.clone();
+    let event_logging_routes = with_body_limit(
+        Router::new().route("/batch", post(event_logging::batch_events)),
+        body_limits.default_bytes,
+    );
 
     // Create middleware state
-    let auth_state = AuthState::new(state.settings.clone(), state.dynamodb.clone());
+    let auth_state = AuthState::new(
+        state.settings.clone(),
+        state.storage.clone(),
+        state.ephemeral_keys.clone(),
+    )
+    .with_key_activity(state.key_activity.clone())
+    .with_failure_guard(
+        state
+            .settings
+            .brute_force
+            .enabled
+            .then(|| Arc::new(AuthFailureGuard::new(state.settings.brute_force.clone()))),
+    );
     let auth_state_clone = auth_state.clone();
-    let rate_limit_state = RateLimitState::new(state.settings.clone());
+    let rate_limit_state = RateLimitState::new(state.settings.clone())
+        .with_shared_store(state.rate_limit_store.clone());
     let rate_limit_state_clone = rate_limit_state.clone();
 
-    // Anthropic API routes (POST /v1/messages)
+    // Anthropic API routes (POST /v1/messages and POST /v1/agents/run, plus
+    // POST /v1/invoke and POST /v1/estimate for any format)
     // Layer order: last added = outermost = runs first
     // So auth runs before rate_limit
     let anthropic_routes = Router::new()
         .route("/messages", post(messages::create_message))
         .route("/messages/count_tokens", post(messages::count_tokens))
+        .route("/messages/:message_id/cancel", post(messages::cancel_message))
+        .route("/invoke", post(invoke::invoke))
+        .route("/estimate", post(dry_run::estimate))
+        .route("/agents/run", post(agents::run_agent))
+        // Budget soft-cap warnings (runs after auth, uses ApiKeyInfo)
+        .layer(middleware::from_fn_with_state(
+            state.settings.clone(),
+            budget_warnings,
+        ))
         // Rate limiting layer (runs after auth, uses ApiKeyInfo)
         .layer(middleware::from_fn_with_state(
             rate_limit_state.clone(),
             rate_limit,
         ))
-        // Authentication layer (runs first, sets ApiKeyInfo in extensions)
+        // Request metrics (runs after auth, labels by key id)
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            track_metrics,
+        ))
+        // Authentication layer (sets ApiKeyInfo in extensions)
         .layer(middleware::from_fn_with_state(
             auth_state.clone(),
             require_api_key,
         ));
+    // Body size limit (runs first, rejects oversized bodies before auth)
+    let anthropic_routes = with_body_limit(anthropic_routes, body_limits.messages_bytes);
 
-    // OpenAI API routes (POST /v1/chat/completions, GET /v1/models)
+    // OpenAI API routes (POST /v1/chat/completions, POST /v1/embeddings, GET /v1/models)
     // Same authentication and rate limiting as Anthropic routes
     let openai_routes = Router::new()
-        .route("/chat/completions", post(chat_completions::chat_completions))
+        .route(
+            "/chat/completions",
+            post(chat_completions::chat_completions).get(chat_completions::list_stored_completions),
+        )
+        .route(
+            "/chat/completions/:completion_id",
+            get(chat_completions::get_stored_completion)
+                .delete(chat_completions::delete_stored_completion),
+        )
+        .route(
+            "/chat/completions/:completion_id/messages",
+            get(chat_completions::get_stored_completion_messages),
+        )
+        .route(
+            "/chat/completions/:completion_id/cancel",
+            post(chat_completions::cancel_chat_completion),
+        )
+        .route("/embeddings", post(embeddings::create_embeddings))
         .route("/models", get(models::list_models))
+        .route("/capabilities", get(capabilities::get_capabilities))
+        .route("/prompts/:name/render", post(prompts::render_prompt))
         .route("/models/:model_id", get(models::get_model))
+        // Budget soft-cap warnings
+        .layer(middleware::from_fn_with_state(
+            state.settings.clone(),
+            budget_warnings,
+        ))
         // Rate limiting layer
         .layer(middleware::from_fn_with_state(
             rate_limit_state_clone,
             rate_limit,
         ))
+        // Request metrics
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            track_metrics,
+        ))
         // Authentication layer
         .layer(middleware::from_fn_with_state(
             auth_state_clone,
             require_api_key,
         ));
+    let openai_routes = with_body_limit(openai_routes, body_limits.chat_completions_bytes);
+
+    // Gemini API routes (POST /v1beta/models/{model}:generateContent and friends)
+    let gemini_routes = Router::new()
+        .route("/models/:model_method", post(generate_content::generate_content))
+        .layer(middleware::from_fn_with_state(
+            state.settings.clone(),
+            budget_warnings,
+        ))
+        .layer(middleware::from_fn_with_state(
+            rate_limit_state.clone(),
+            rate_limit,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            track_metrics,
+        ))
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_api_key,
+        ));
+    let gemini_routes = with_body_limit(gemini_routes, body_limits.messages_bytes);
+
+    // Self-service key routes (POST /v1/keys, requires "child_keys" scope)
+    let key_routes = Router::new()
+        .route("/keys", post(keys::create_child_key))
+        // Rate limiting layer
+        .layer(middleware::from_fn_with_state(
+            rate_limit_state.clone(),
+            rate_limit,
+        ))
+        // Authentication layer
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_api_key,
+        ));
+    let key_routes = with_body_limit(key_routes, body_limits.default_bytes);
+
+    // Stored key lifecycle (separate admin key in x-admin-key)
+    #[cfg(feature = "admin-ui")]
+    let api_key_routes = Router::new()
+        .route("/api-keys", post(admin::create_api_key))
+        .route(
+            "/api-keys/:key_id",
+            get(admin::get_api_key).delete(admin::revoke_api_key),
+        )
+        .route("/api-keys/:key_id/rotate", post(admin::rotate_api_key))
+        .route("/api-keys/:key_id/quota", put(admin::set_api_key_quota))
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_admin_key,
+        ));
+    #[cfg(feature = "admin-ui")]
+    let api_key_routes = with_body_limit(api_key_routes, body_limits.default_bytes);
+
+    // Admin routes (master key or "admin" scope)
+    #[cfg(feature = "admin-ui")]
+    let admin_routes = Router::new()
+        .route("/api-keys", get(admin::list_api_keys))
+        .route("/api-keys/unused", get(admin::unused_api_keys))
+        .route("/api-keys/:key_id/extend", post(admin::extend_api_key))
+        .route("/api-keys/:key_id/upgrade", post(admin::upgrade_api_key))
+        .route(
+            "/ephemeral-keys",
+            get(admin::list_ephemeral_keys).post(admin::mint_ephemeral_key),
+        )
+        .route("/ephemeral-keys/:id", delete(admin::revoke_ephemeral_key))
+        .route("/prompts", get(admin::list_prompt_templates))
+        .route(
+            "/prompts/:name",
+            get(admin::get_prompt_template)
+                .post(admin::create_prompt_template)
+                .delete(admin::delete_prompt_template),
+        )
+        .route("/feature-flags", get(admin::list_feature_flags))
+        .route("/feature-flags/reload", post(admin::reload_feature_flags))
+        .route(
+            "/feature-flags/:name",
+            get(admin::get_feature_flag)
+                .put(admin::put_feature_flag)
+                .delete(admin::delete_feature_flag),
+        )
+        .route("/model-deprecations", get(admin::list_model_deprecations))
+        .route(
+            "/model-deprecations/:model",
+            get(admin::get_model_deprecation)
+                .put(admin::put_model_deprecation)
+                .delete(admin::delete_model_deprecation),
+        )
+        .route("/inference-presets", get(admin::list_inference_presets))
+        .route(
+            "/inference-presets/:name",
+            get(admin::get_inference_preset)
+                .put(admin::put_inference_preset)
+                .delete(admin::delete_inference_preset),
+        )
+        .route("/output-watchdog", get(admin::output_watchdog_trips))
+        .route("/usage-reconciliation", get(admin::get_usage_reconciliation))
+        .route("/usage-reconciliation/run", post(admin::run_usage_reconciliation))
+        .route("/model-mappings/reload", post(admin::reload_model_mappings))
+        .route("/cache/flush", post(admin::flush_caches))
+        .route("/tap", get(admin::tap))
+        .route("/routing-stats", get(admin::routing_stats))
+        .route("/priority-stats", get(admin::priority_stats))
+        .route("/stream-buffers", get(admin::stream_buffers))
+        // Admin check (runs after auth)
+        .layer(middleware::from_fn(require_admin))
+        // Authentication layer
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_api_key,
+        ));
+    #[cfg(feature = "admin-ui")]
+    let admin_routes = with_body_limit(admin_routes, body_limits.default_bytes);
+
+    // Debug routes (master key or "admin" scope); bodies are full API requests
+    let debug_routes = Router::new()
+        .route("/convert", post(debug::convert))
+        .route("/models/:alias", get(debug::resolve_model))
+        .route("/bedrock-clients", get(debug::bedrock_clients))
+        .route(
+            "/stream-recordings",
+            get(debug::list_stream_recordings)
+                .post(debug::arm_stream_recorder)
+                .delete(debug::clear_stream_recordings),
+        )
+        .route("/stream-recordings/:id", get(debug::get_stream_recording))
+        .layer(middleware::from_fn(require_admin))
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_api_key,
+        ));
+    let debug_routes = with_body_limit(debug_routes, body_limits.messages_bytes);
+
+    // Prometheus metrics (master key or "admin" scope); labels carry key ids
+    let metrics_routes = Router::new()
+        .route("/metrics", get(health::metrics))
+        .layer(middleware::from_fn(require_admin))
+        .layer(middleware::from_fn_with_state(
+            auth_state.clone(),
+            require_api_key,
+        ));
 
     // Clone settings for fallback handler
     let settings_for_fallback = state.settings.clone();
 
     // Combine all routes
     // Both Anthropic and OpenAI routes are under /v1
-    Router::new()
+    let router = Router::new()
         .nest("/v1", anthropic_routes)
         .nest("/v1", openai_routes)
+        .nest("/v1", key_routes)
+        .nest("/v1beta", gemini_routes);
+    #[cfg(feature = "admin-ui")]
+    let router = router
+        .nest("/admin", api_key_routes)
+        .nest("/admin", admin_routes);
+    let router = router
+        .nest("/debug", debug_routes)
         .nest("/api/event_logging", event_logging_routes)
         .merge(health_routes)
+        .merge(metrics_routes)
         // Fallback handler for unknown routes: check API key, return 401 or 403
         .fallback(move |request: Request<Body>| async move {
             fallback_handler(request, settings_for_fallback.require_api_key)
         })
+        // Error code and retryability on every error response
+        .layer(middleware::from_fn(tag_errors));
+    // gzip/zstd request bodies are decompressed ahead of the body limits
+    let compression = &state.settings.compression;
+    with_compression(router, compression)
         // Apply middleware layers (order matters: first added = outermost = runs first)
         .layer(create_cors_layer())
         // Custom request logging with trace IDs
@@ -94,6 +329,16 @@ pub fn create_router(state: AppState) -> Router {
         .with_state(state)
 }
 
+/// Apply a request body size limit to a route group
+///
+/// The middleware rejects a declared Content-Length over the limit up front;
+/// `DefaultBodyLimit` bounds chunked bodies while they are buffered.
+fn with_body_limit(router: Router<AppState>, limit: usize) -> Router<AppState> {
+    router
+        .layer(DefaultBodyLimit::max(limit))
+        .layer(middleware::from_fn_with_state(limit, enforce_body_limit))
+}
+
 /// Fallback handler for unknown routes
 ///
 /// Returns 401 if no API key is provided, 403 if route doesn't exist
@@ -125,5 +370,12 @@ fn create_cors_layer() -> CorsLayer {
             "x-ratelimit-remaining".parse().unwrap(),
             "x-ratelimit-reset".parse().unwrap(),
             "retry-after".parse().unwrap(),
+            // Expose budget soft-cap warning headers
+            "x-budget-warning".parse().unwrap(),
+            "x-budget-used-percent".parse().unwrap(),
+            "x-budget-remaining-usd".parse().unwrap(),
+            "x-budget-downgraded-from".parse().unwrap(),
+            // Expose fields the request conversion dropped or adjusted
+            "x-conversion-warnings".parse().unwrap(),
         ])
 }
@@ -3,15 +3,34 @@
 //! This module defines the shared application state that is passed
 //! to all request handlers via Axum's state extraction.
 
-use crate::config::{create_bedrock_client, create_dynamodb_client, Settings};
-use crate::db::{DynamoDbBackend, DynamoDbClient, StorageBackend};
+use crate::api::chat_completions::ChatCoalescer;
+use crate::api::messages::MessageCoalescer;
+use crate::config::{
+    build_aws_config, create_bedrock_client, create_bedrock_client_with_profile,
+    create_dynamodb_client, Settings, StorageConfig,
+};
+use crate::converters::SharedConverters;
+use crate::middleware::{ApiKeyInfo, GatewayMetrics, RateLimiterStore, SCOPE_CONTENT_OFFLOAD};
+#[cfg(feature = "redis")]
+use crate::middleware::RedisRateLimiterStore;
+use crate::db::{
+    DynamoDbBackend, DynamoDbClient, FeatureFlagRepository, MemoryBackend, ModelMappingError,
+    ModelMappingRepository, StorageBackend, StorageKind,
+};
+#[cfg(feature = "sqlite")]
+use crate::db::SqliteBackend;
 use crate::services::{
-    BedrockProvider, BedrockService, DeepSeekProvider, DeepSeekProviderConfig,
-    GeminiConfig as GeminiServiceConfig, GeminiProvider, GeminiService, LoadBalanceStrategy,
-    OpenAIProvider, OpenAIProviderConfig, ProviderRouter, PtcService, UsageTracker,
+    AnthropicConfig as AnthropicServiceConfig, AnthropicService, AwsCredential, AzureCredential, AzureOpenAIConfig as AzureOpenAIServiceConfig, AzureOpenAIService, Backend, BackendRegistry, BackendTarget, BedrockClientPool, BedrockProvider, BedrockService, CloudWatchInvocationMetrics, CompletionStore, ContentOffloader, CredentialPool, DeepSeekProvider, DeepSeekProviderConfig, FailoverPolicy,
+    EphemeralKeyManager, EvalSink, EvalTee, FeatureFlagService, GenerationRegistry, InferencePresetRegistry,
+    KeyActivityTracker, LoadBalanceStrategy, ModelDeprecationRegistry, ModelRoutingTable, OpenAIProvider, OpenAIProviderConfig, OutputWatchdog, PayloadLogHook, PiiTokenizer, PoolConfig, PriorityMetrics, PromptTemplateStore, ProviderRouter, ProvisionedGovernor, RequestTap, RoutingMetrics, S3EvalSink, SqsEvalSink, StreamBufferMetrics, StreamRecorder, TranscriptionService, UsageLedger, UsageReconciler, UsageTracker,
 };
+#[cfg(feature = "gemini")]
+use crate::services::{GeminiConfig as GeminiServiceConfig, GeminiProvider, GeminiService};
+#[cfg(feature = "ptc")]
+use crate::services::PtcService;
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Shared application state
 ///
@@ -31,6 +50,18 @@ pub struct AppState {
     /// Bedrock service for model inference
     pub bedrock: Arc<BedrockService>,
 
+    /// Bedrock services for named profiles (from BEDROCK_PROFILES)
+    pub bedrock_profiles: HashMap<String, Arc<BedrockService>>,
+
+    /// Model pattern -> backend routing table
+    pub model_routes: Arc<ModelRoutingTable>,
+
+    /// Inference backends by routing target, used by the API handlers
+    pub backends: Arc<BackendRegistry>,
+
+    /// Token buckets smoothing bursts on provisioned throughput
+    pub pt_governor: Arc<ProvisionedGovernor>,
+
     /// Usage tracker for recording API usage
     pub usage_tracker: Arc<UsageTracker>,
 
@@ -38,13 +69,87 @@ pub struct AppState {
     pub start_time: Instant,
 
     /// PTC service for Programmatic Tool Calling (optional)
+    #[cfg(feature = "ptc")]
     pub ptc_service: Option<Arc<PtcService>>,
 
     /// Gemini service for Google Gemini API (optional)
+    #[cfg(feature = "gemini")]
     pub gemini_service: Option<Arc<GeminiService>>,
 
     /// Unified provider router for model-based routing
     pub provider_router: Arc<ProviderRouter>,
+
+    /// In-memory ephemeral keys for local multi-client testing
+    pub ephemeral_keys: Arc<EphemeralKeyManager>,
+
+    /// Per-key activity tracker for key hygiene (None if disabled)
+    pub key_activity: Option<Arc<KeyActivityTracker>>,
+
+    /// Per-key/tenant feature flags layered over the static settings
+    pub feature_flags: Arc<FeatureFlagService>,
+
+    /// Converter instances shared across requests (model mappings reloadable)
+    pub converters: Arc<SharedConverters>,
+
+    /// Live request inspector feeding `/admin/tap`
+    pub request_tap: Arc<RequestTap>,
+
+    /// Which backend served each request and how many attempts it took
+    pub routing_metrics: Arc<RoutingMetrics>,
+
+    /// Request, latency and token metrics served on `/metrics`
+    pub metrics: Arc<GatewayMetrics>,
+
+    /// Requests per `x-priority` class and their provisioned-capacity waits
+    pub priority_metrics: Arc<PriorityMetrics>,
+
+    /// Depth of the buffers between backend streams and slow clients
+    pub stream_buffers: Arc<StreamBufferMetrics>,
+
+    /// Captures backend streams for `/debug/stream-recordings`
+    pub stream_recorder: Arc<StreamRecorder>,
+
+    /// Chat completions created with `store: true`
+    pub completion_store: Arc<CompletionStore>,
+
+    /// Named, versioned prompt templates managed through `/admin/prompts`
+    pub prompt_templates: Arc<PromptTemplateStore>,
+
+    /// Model deprecation schedules managed through `/admin/model-deprecations`
+    pub model_deprecations: Arc<ModelDeprecationRegistry>,
+
+    /// Default inference parameter presets managed through `/admin/inference-presets`
+    pub inference_presets: Arc<InferencePresetRegistry>,
+
+    /// Output-length ceiling for streams and its per-key trip counts
+    pub output_watchdog: Arc<OutputWatchdog>,
+
+    /// Token usage reported to clients, per Bedrock model and region
+    pub usage_ledger: Arc<UsageLedger>,
+
+    /// Periodic comparison of the ledger with CloudWatch (None if disabled)
+    pub usage_reconciler: Option<Arc<UsageReconciler>>,
+
+    /// Copies of sampled conversations for offline evaluation (None if disabled)
+    pub eval_tee: Option<Arc<EvalTee>>,
+
+    /// S3 offloading of oversized response content (None if disabled)
+    pub content_offloader: Option<Arc<ContentOffloader>>,
+
+    /// Rate limit buckets shared between replicas (None = per replica)
+    pub rate_limit_store: Option<Arc<dyn RateLimiterStore>>,
+
+    /// Transcription of audio content parts (None if disabled)
+    pub transcription: Option<Arc<TranscriptionService>>,
+
+    /// In-flight streaming generations, for the cancel endpoints
+    pub generations: Arc<GenerationRegistry>,
+
+    /// Identical non-streaming `/v1/messages` requests in flight
+    pub message_coalescer: Arc<MessageCoalescer>,
+
+    /// Identical non-streaming chat completions in flight
+    pub chat_coalescer: Arc<ChatCoalescer>,
 }
 
 impl AppState {
@@ -68,30 +173,112 @@ impl AppState {
         let dynamodb_sdk_client = create_dynamodb_client(&settings).await;
         let dynamodb = Arc::new(DynamoDbClient::new(settings.clone(), dynamodb_sdk_client));
 
-        // Create unified storage backend (wraps DynamoDB for now)
-        let storage: Arc<dyn StorageBackend> = Arc::new(DynamoDbBackend::new(dynamodb.clone()));
+        // Keys and usage go to the configured storage backend; the DynamoDB
+        // client stays for the tables that have no other backend yet
+        let (storage_kind, storage) = create_storage_backend(&settings.storage, dynamodb.clone()).await?;
+        tracing::info!(backend = storage_kind.as_str(), "Storage backend initialized");
 
         tracing::debug!("Creating Bedrock client");
-        // Check if multiple Bedrock profiles are configured
-        if settings.bedrock.has_multiple_profiles() {
-            tracing::warn!(
-                profile_count = settings.bedrock.profiles.len(),
-                "Multiple Bedrock profiles configured via BEDROCK_PROFILES. \
-                Multi-profile load balancing is not yet fully implemented. \
-                Using default credentials for now."
+        let bedrock_sdk_client = create_bedrock_client(&settings).await;
+        let mut bedrock = BedrockService::new(settings.clone(), bedrock_sdk_client);
+        if !settings.bedrock.credential_pool.is_empty() {
+            let credentials = settings
+                .bedrock
+                .credential_pool
+                .iter()
+                .map(|profile| match (&profile.access_key_id, &profile.secret_access_key) {
+                    (Some(key_id), Some(secret)) => AwsCredential::with_access_key(
+                        key_id,
+                        secret,
+                        &profile.region,
+                        &profile.name,
+                        profile.weight,
+                    ),
+                    _ => AwsCredential::with_profile(
+                        profile.profile.clone().unwrap_or_else(|| profile.name.clone()),
+                        &profile.region,
+                        &profile.name,
+                        profile.weight,
+                    ),
+                })
+                .collect();
+            let pool_config = PoolConfig::new(
+                LoadBalanceStrategy::from_str(&settings.backend_pool.strategy),
+            )
+            .with_max_failures(settings.backend_pool.max_failures)
+            .with_retry_after(settings.backend_pool.retry_after_secs)
+            .with_probe_successes(settings.backend_pool.probe_successes)
+            .with_failover(FailoverPolicy {
+                max_attempts: settings.backend_pool.failover_max_attempts,
+                cooldown_secs: settings.backend_pool.failover_cooldown_secs,
+            });
+            tracing::info!(
+                credentials = settings.bedrock.credential_pool.len(),
+                "Bedrock credential pool enabled"
             );
+            bedrock = bedrock.with_credential_pool(BedrockClientPool::new(
+                CredentialPool::new(credentials, pool_config),
+                settings.bedrock_endpoint_url.clone(),
+                settings.bedrock_client.clone(),
+            ));
         }
-        let bedrock_sdk_client = create_bedrock_client(&settings).await;
-        let bedrock = Arc::new(BedrockService::new(settings.clone(), bedrock_sdk_client));
+        let bedrock = Arc::new(bedrock);
+
+        // Named Bedrock profiles are addressable from the model routing table
+        let mut bedrock_profiles = HashMap::new();
+        for profile in &settings.bedrock.profiles {
+            tracing::debug!(
+                name = %profile.name,
+                region = %profile.region,
+                "Creating Bedrock client for profile"
+            );
+            let client = create_bedrock_client_with_profile(
+                profile.profile.as_deref(),
+                &profile.region,
+                settings.bedrock_endpoint_url.as_deref(),
+                &settings.bedrock_client,
+            )
+            .await;
+            bedrock_profiles.insert(
+                profile.name.clone(),
+                Arc::new(BedrockService::new(settings.clone(), client)),
+            );
+        }
+
+        let model_routes = Arc::new(ModelRoutingTable::from_config(&settings.routing));
+        tracing::info!(
+            route_count = model_routes.routes().len(),
+            bedrock_profiles = bedrock_profiles.len(),
+            "Model routing table initialized"
+        );
+
+        // Key hygiene tracking, flushed in the background
+        let key_activity = if settings.key_activity.enabled {
+            let tracker = Arc::new(KeyActivityTracker::new(storage.clone(), &settings.key_activity));
+            tracker.clone().spawn_flush_loop(Duration::from_secs(
+                settings.key_activity.flush_interval_secs.max(1),
+            ));
+            Some(tracker)
+        } else {
+            tracing::debug!("Key activity tracking disabled");
+            None
+        };
 
         tracing::debug!("Initializing usage tracker");
-        let usage_tracker = Arc::new(UsageTracker::new(dynamodb.clone()));
+        let usage_tracker = Arc::new(UsageTracker::new(storage.clone()));
 
         // Initialize PTC service if enabled
+        #[cfg(not(feature = "ptc"))]
+        if settings.features.enable_ptc {
+            tracing::warn!("ENABLE_PTC is set but this build lacks the `ptc` feature; PTC is disabled");
+        }
+        #[cfg(feature = "ptc")]
         let ptc_service = if settings.features.enable_ptc {
             tracing::info!("PTC enabled, initializing PTC service");
             match PtcService::new().await {
-                Ok(service) => Some(Arc::new(service)),
+                Ok(service) => Some(Arc::new(service.with_max_parallel_executions(
+                    settings.ptc.max_parallel_executions,
+                ))),
                 Err(e) => {
                     tracing::warn!("Failed to initialize PTC service: {}. PTC will be disabled.", e);
                     None
@@ -102,7 +289,28 @@ impl AppState {
             None
         };
 
+        // Initialize audio transcription if enabled
+        let transcription = if settings.transcription.enabled {
+            match TranscriptionService::new(settings.transcription.clone()) {
+                Ok(service) => {
+                    tracing::info!(model = %settings.transcription.model, "Audio transcription enabled");
+                    Some(Arc::new(service))
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to initialize transcription client: {}. Audio input will be rejected.", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // Initialize Gemini service if enabled
+        #[cfg(not(feature = "gemini"))]
+        if settings.gemini.is_available() {
+            tracing::warn!("Gemini keys are configured but this build lacks the `gemini` feature; Gemini is disabled");
+        }
+        #[cfg(feature = "gemini")]
         let gemini_service = if settings.gemini.is_available() {
             let api_keys = settings.gemini.get_all_keys();
             tracing::info!(
@@ -124,7 +332,9 @@ impl AppState {
             gemini_config = gemini_config
                 .with_strategy(strategy)
                 .with_max_failures(settings.backend_pool.max_failures)
-                .with_retry_after(settings.backend_pool.retry_after_secs);
+                .with_retry_after(settings.backend_pool.retry_after_secs)
+                .with_rate_limit_cooldown(settings.backend_pool.rate_limit_cooldown_secs)
+                .with_probe_successes(settings.backend_pool.probe_successes);
 
             match GeminiService::new(gemini_config) {
                 Ok(service) => {
@@ -152,6 +362,7 @@ impl AppState {
         provider_router.register(Arc::new(BedrockProvider::new(bedrock.clone())));
 
         // Register Gemini provider if available
+        #[cfg(feature = "gemini")]
         if let Some(ref gemini_svc) = gemini_service {
             provider_router.register(Arc::new(GeminiProvider::new(gemini_svc.clone())));
         }
@@ -200,19 +411,277 @@ impl AppState {
 
         let provider_router = Arc::new(provider_router);
 
+        // Initialize the Anthropic hosted API backend if enabled
+        let anthropic_service = if settings.anthropic.is_available() {
+            let mut anthropic_config =
+                AnthropicServiceConfig::with_keys(settings.anthropic.api_keys.clone())
+                    .with_timeout(settings.anthropic.timeout_seconds)
+                    .with_strategy(LoadBalanceStrategy::from_str(&settings.backend_pool.strategy))
+                    .with_max_failures(settings.backend_pool.max_failures)
+                    .with_retry_after(settings.backend_pool.retry_after_secs)
+                    .with_rate_limit_cooldown(settings.backend_pool.rate_limit_cooldown_secs)
+                    .with_probe_successes(settings.backend_pool.probe_successes);
+            if let Some(ref base_url) = settings.anthropic.base_url {
+                anthropic_config = anthropic_config.with_base_url(base_url);
+            }
+            match AnthropicService::new(anthropic_config) {
+                Ok(service) => {
+                    tracing::info!(
+                        key_count = service.key_count(),
+                        "Anthropic service initialized successfully"
+                    );
+                    Some(Arc::new(service))
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to initialize Anthropic service: {}. Anthropic will be disabled.", e);
+                    None
+                }
+            }
+        } else {
+            tracing::debug!("Anthropic disabled or no API key configured");
+            None
+        };
+
+        // Initialize the Azure OpenAI backend if enabled
+        let azure_service = if settings.azure_openai.is_available() {
+            let azure = &settings.azure_openai;
+            let credentials = azure
+                .endpoints
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, endpoint)| {
+                    let name = format!("azure_{}", idx + 1);
+                    match (azure.api_key_for(idx), &azure.tenant_id, &azure.client_id, &azure.client_secret) {
+                        (Some(key), ..) => Some(AzureCredential::with_api_key(endpoint, key, name, 1)),
+                        (None, Some(tenant), Some(client), Some(secret)) => Some(
+                            AzureCredential::with_entra_id(endpoint, tenant, client, secret, name, 1),
+                        ),
+                        _ => {
+                            tracing::warn!(endpoint = %endpoint, "No API key for Azure OpenAI endpoint, skipping");
+                            None
+                        }
+                    }
+                })
+                .collect();
+            let azure_config = AzureOpenAIServiceConfig::with_credentials(credentials)
+                .with_api_version(&azure.api_version)
+                .with_deployments(azure.deployments.clone())
+                .with_timeout(azure.timeout_seconds)
+                .with_strategy(LoadBalanceStrategy::from_str(&settings.backend_pool.strategy))
+                .with_max_failures(settings.backend_pool.max_failures)
+                .with_retry_after(settings.backend_pool.retry_after_secs)
+                .with_rate_limit_cooldown(settings.backend_pool.rate_limit_cooldown_secs)
+                .with_probe_successes(settings.backend_pool.probe_successes);
+            match AzureOpenAIService::new(azure_config) {
+                Ok(service) => {
+                    tracing::info!(
+                        resource_count = service.resource_count(),
+                        entra_id = azure.api_keys.is_empty(),
+                        "Azure OpenAI service initialized successfully"
+                    );
+                    Some(Arc::new(service))
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to initialize Azure OpenAI service: {}. Azure OpenAI will be disabled.", e);
+                    None
+                }
+            }
+        } else {
+            tracing::debug!("Azure OpenAI disabled or not configured");
+            None
+        };
+
+        // Backends the API handlers dispatch to, keyed like BackendTarget
+        let mut backends = BackendRegistry::new();
+        backends.register(&BackendTarget::Bedrock { profile: None }, bedrock.clone());
+        for (name, service) in &bedrock_profiles {
+            backends.register(
+                &BackendTarget::Bedrock { profile: Some(name.clone()) },
+                service.clone(),
+            );
+        }
+        #[cfg(feature = "gemini")]
+        if let Some(ref gemini_svc) = gemini_service {
+            backends.register(&BackendTarget::Gemini, gemini_svc.clone());
+        }
+        if let Some(anthropic_svc) = anthropic_service {
+            backends.register(&BackendTarget::Anthropic, anthropic_svc);
+        }
+        if let Some(azure_svc) = azure_service {
+            backends.register(&BackendTarget::Azure, azure_svc);
+        }
+        if settings.log_backend_payloads {
+            backends.add_hook(None, Arc::new(PayloadLogHook));
+        }
+        let stream_recorder = Arc::new(StreamRecorder::new());
+        backends.add_hook(None, stream_recorder.clone());
+        if settings.pii_tokenization.enabled {
+            let tokenizer = PiiTokenizer::new(&settings.pii_tokenization);
+            if tokenizer.is_empty() {
+                tracing::warn!("PII_TOKENIZATION_ENABLED is set without any known PII_TOKENIZATION_KINDS");
+            } else {
+                tracing::info!(kinds = ?settings.pii_tokenization.kinds, "PII tokenization enabled");
+                backends.set_pii_tokenizer(Arc::new(tokenizer));
+            }
+        }
+        let backends = Arc::new(backends);
+        let pt_governor = Arc::new(ProvisionedGovernor::from_config(&settings.provisioned_throughput));
+        let completion_store = Arc::new(CompletionStore::new(settings.completion_store.clone()));
+        let converters = Arc::new(SharedConverters::with_temperature_scaling(
+            settings.openai_temperature_scaling,
+        ));
+
+        let feature_flags = Arc::new(
+            FeatureFlagService::new(settings.features.clone())
+                .with_repository(FeatureFlagRepository::new(dynamodb.clone())),
+        );
+
+        let model_deprecations = Arc::new(ModelDeprecationRegistry::new(
+            settings.model_deprecations.schedules.clone(),
+        ));
+        let output_watchdog = Arc::new(OutputWatchdog::new(&settings.output_watchdog));
+
+        // Reported usage is reconciled against Bedrock's CloudWatch metrics
+        let usage_ledger = Arc::new(UsageLedger::new());
+        let usage_reconciler = if settings.usage_reconciliation.enabled {
+            let metrics = CloudWatchInvocationMetrics::new(build_aws_config(&settings).await);
+            let reconciler = Arc::new(UsageReconciler::new(
+                usage_ledger.clone(),
+                Arc::new(metrics),
+                settings.usage_reconciliation.clone(),
+            ));
+            reconciler.clone().spawn_loop();
+            Some(reconciler)
+        } else {
+            None
+        };
+
+        // Sampled conversations are copied to SQS and/or S3 off the request path
+        let eval_tee = if settings.eval_sink.enabled {
+            let config = &settings.eval_sink;
+            let sdk_config = build_aws_config(&settings).await;
+            let mut sinks: Vec<Arc<dyn EvalSink>> = Vec::new();
+            if let Some(ref queue_url) = config.sqs_queue_url {
+                sinks.push(Arc::new(SqsEvalSink::new(&sdk_config, queue_url)));
+            }
+            if let Some(ref bucket) = config.s3_bucket {
+                sinks.push(Arc::new(S3EvalSink::new(&sdk_config, bucket, &config.s3_prefix)));
+            }
+            if sinks.is_empty() {
+                tracing::warn!("EVAL_SINK_ENABLED is set without an SQS queue or S3 bucket; not capturing");
+                None
+            } else {
+                tracing::info!(
+                    sinks = sinks.len(),
+                    sample_rate = config.sample_rate,
+                    "Evaluation sink enabled"
+                );
+                Some(EvalTee::spawn(config, sinks))
+            }
+        } else {
+            None
+        };
+
+        // Oversized response content goes to S3 for keys that opt in
+        let content_offloader = match (&settings.content_offload, &settings.content_offload.bucket) {
+            (config, Some(bucket)) if config.enabled => {
+                tracing::info!(
+                    bucket = %bucket,
+                    threshold_bytes = config.threshold_bytes,
+                    "Content offloading enabled"
+                );
+                let sdk_config = build_aws_config(&settings).await;
+                Some(Arc::new(ContentOffloader::new(&sdk_config, bucket, config)))
+            }
+            (config, None) if config.enabled => {
+                tracing::warn!("CONTENT_OFFLOAD_ENABLED is set without CONTENT_OFFLOAD_BUCKET; not offloading");
+                None
+            }
+            _ => None,
+        };
+
+        let rate_limit_store = create_rate_limit_store(&settings).await;
+
         tracing::info!("Application state initialized successfully");
 
-        Ok(Self {
+        let state = Self {
             settings,
             dynamodb,
             storage,
             bedrock,
+            bedrock_profiles,
+            model_routes,
+            backends,
+            pt_governor,
             usage_tracker,
             start_time,
+            #[cfg(feature = "ptc")]
             ptc_service,
+            #[cfg(feature = "gemini")]
             gemini_service,
             provider_router,
-        })
+            ephemeral_keys: Arc::new(EphemeralKeyManager::new()),
+            key_activity,
+            feature_flags,
+            converters,
+            request_tap: Arc::new(RequestTap::new()),
+            routing_metrics: Arc::new(RoutingMetrics::new()),
+            metrics: Arc::new(GatewayMetrics::new()),
+            priority_metrics: Arc::new(PriorityMetrics::new()),
+            stream_buffers: Arc::new(StreamBufferMetrics::new()),
+            stream_recorder,
+            completion_store,
+            prompt_templates: Arc::new(PromptTemplateStore::new()),
+            model_deprecations,
+            inference_presets: Arc::new(InferencePresetRegistry::new()),
+            output_watchdog,
+            usage_ledger,
+            usage_reconciler,
+            eval_tee,
+            content_offloader,
+            rate_limit_store,
+            transcription,
+            generations: Arc::new(GenerationRegistry::new()),
+            message_coalescer: Arc::new(MessageCoalescer::new()),
+            chat_coalescer: Arc::new(ChatCoalescer::new()),
+        };
+
+        // Stored model mappings are optional; keep the built-in ones if unavailable
+        if let Err(e) = state.reload_converters().await {
+            tracing::warn!(error = %e, "Failed to load stored model mappings, using built-in mappings");
+        }
+        match state.feature_flags.reload().await {
+            Ok(count) => tracing::info!(flag_count = count, "Loaded stored feature flags"),
+            Err(e) => tracing::warn!(error = %e, "Failed to load stored feature flags, using settings defaults"),
+        }
+        match state.storage.list_inference_presets().await {
+            Ok(presets) => {
+                let count = state.inference_presets.load(presets);
+                tracing::info!(preset_count = count, "Loaded stored inference presets");
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to load stored inference presets"),
+        }
+
+        Ok(state)
+    }
+
+    /// Reload the shared converters' model mappings from the database
+    ///
+    /// Call after changing stored mappings. Returns the number of stored
+    /// mappings applied.
+    pub async fn reload_converters(&self) -> Result<usize, ModelMappingError> {
+        let mappings = ModelMappingRepository::new(self.dynamodb.clone())
+            .list_all()
+            .await?;
+        self.converters.apply_model_mappings(&mappings);
+
+        tracing::info!(
+            mapping_count = mappings.len(),
+            generation = self.converters.generation(),
+            "Reloaded converter model mappings"
+        );
+
+        Ok(mappings.len())
     }
 
     /// Get the application uptime in seconds
@@ -222,7 +691,14 @@ impl AppState {
 
     /// Check if PTC is enabled
     pub fn is_ptc_enabled(&self) -> bool {
-        self.settings.features.enable_ptc
+        cfg!(feature = "ptc") && self.settings.features.enable_ptc
+    }
+
+    /// The content offloader, if enabled and the key has opted in
+    pub fn content_offloader_for(&self, key_info: &ApiKeyInfo) -> Option<Arc<ContentOffloader>> {
+        self.content_offloader
+            .clone()
+            .filter(|_| key_info.has_scope(SCOPE_CONTENT_OFFLOAD))
     }
 
     /// Check if API key authentication is required
@@ -232,15 +708,119 @@ impl AppState {
 
     /// Check if Gemini is available
     pub fn is_gemini_available(&self) -> bool {
-        self.gemini_service.is_some()
+        #[cfg(feature = "gemini")]
+        let available = self.gemini_service.is_some();
+        #[cfg(not(feature = "gemini"))]
+        let available = false;
+        available
+    }
+
+    /// Resolve the backend for a model using the routing table
+    ///
+    /// Gemini, Anthropic and Azure routes fall back to the default Bedrock
+    /// client when their service is not configured, matching the pre-routing
+    /// behaviour.
+    pub fn resolve_backend(&self, model: &str) -> BackendTarget {
+        let target = self.model_routes.resolve(model);
+        if !self.is_target_available(&target) {
+            tracing::warn!(
+                model = %model,
+                backend = %target,
+                "Model routed to a backend that is not configured, falling back to Bedrock"
+            );
+            return BackendTarget::Bedrock { profile: None };
+        }
+        target
+    }
+
+    /// Resolve the backend for a request using the long-context beta
+    pub fn resolve_long_context_backend(&self, model: &str) -> BackendTarget {
+        let target = self.model_routes.resolve_long_context(model);
+        if !self.is_target_available(&target) {
+            return BackendTarget::Bedrock { profile: None };
+        }
+        target
+    }
+
+    /// Region label for a backend's metrics
+    ///
+    /// A Bedrock profile reports its own region and the credential pool
+    /// reports "pool", since the serving credential is only picked per call.
+    /// Other backends are global endpoints.
+    pub fn backend_region(&self, target: &BackendTarget) -> String {
+        let bedrock = &self.settings.bedrock;
+        match target {
+            BackendTarget::Bedrock { profile: Some(name) } => bedrock
+                .profiles
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name) || p.region.eq_ignore_ascii_case(name))
+                .map_or_else(|| self.settings.aws_region.clone(), |p| p.region.clone()),
+            BackendTarget::Bedrock { profile: None } if !bedrock.credential_pool.is_empty() => {
+                "pool".to_string()
+            }
+            BackendTarget::Bedrock { profile: None } => self.settings.aws_region.clone(),
+            _ => "global".to_string(),
+        }
+    }
+
+    /// Whether a Gemini, Anthropic or Azure target has its service configured
+    fn is_target_available(&self, target: &BackendTarget) -> bool {
+        match target {
+            BackendTarget::Gemini => self.is_gemini_available(),
+            BackendTarget::Anthropic | BackendTarget::Azure => self.backends.contains(target),
+            _ => true,
+        }
+    }
+
+    /// Get the Bedrock service for a profile (None selects the default client)
+    ///
+    /// Profiles are looked up by name first, then by region, so a backend
+    /// override such as `bedrock:us-west-2` picks the profile for that region.
+    pub fn bedrock_for(&self, profile: Option<&str>) -> Option<Arc<BedrockService>> {
+        let Some(name) = profile else {
+            return Some(self.bedrock.clone());
+        };
+
+        self.bedrock_profiles.get(name).cloned().or_else(|| {
+            self.settings
+                .bedrock
+                .profiles
+                .iter()
+                .find(|p| p.region == name)
+                .and_then(|p| self.bedrock_profiles.get(&p.name).cloned())
+        })
+    }
+
+    /// Get the inference backend serving a routing target
+    ///
+    /// Bedrock profiles may also be addressed by region, as in [`Self::bedrock_for`].
+    pub fn backend_for(&self, target: &BackendTarget) -> Option<Arc<dyn Backend>> {
+        self.backends.get(target).or_else(|| match target {
+            BackendTarget::Bedrock { profile: Some(region) } => self
+                .settings
+                .bedrock
+                .profiles
+                .iter()
+                .find(|p| p.region == *region)
+                .and_then(|p| {
+                    self.backends.get(&BackendTarget::Bedrock {
+                        profile: Some(p.name.clone()),
+                    })
+                }),
+            _ => None,
+        })
     }
 
     /// Check the health of AWS services
     ///
-    /// Returns a struct with the health status of DynamoDB and Bedrock.
+    /// Returns a struct with the health status of the storage backend
+    /// (reported as `dynamodb`) and Bedrock.
     pub async fn check_aws_health(&self) -> AwsHealthStatus {
-        let dynamodb_healthy = self.dynamodb.health_check().await;
+        let dynamodb_healthy = self.storage.health_check().await;
         let bedrock_healthy = self.bedrock.health_check();
+        #[cfg(not(feature = "gemini"))]
+        let gemini_healthy = false;
+        #[cfg(feature = "gemini")]
         let gemini_healthy = self.gemini_service
             .as_ref()
             .map(|s| s.health_check())
@@ -254,6 +834,71 @@ impl AppState {
     }
 }
 
+/// Connect the shared rate limit store named by `RATE_LIMIT_STORE`
+///
+/// An unreachable Redis at startup is logged and leaves every replica on its
+/// own in-memory buckets rather than failing to start.
+async fn create_rate_limit_store(settings: &Settings) -> Option<Arc<dyn RateLimiterStore>> {
+    let config = &settings.rate_limit;
+    if !config.enabled || config.store != "redis" {
+        return None;
+    }
+
+    #[cfg(feature = "redis")]
+    {
+        let url = config.redis_url.as_deref()?;
+        match RedisRateLimiterStore::connect(url, &config.redis_key_prefix).await {
+            Ok(store) => {
+                tracing::info!("Rate limit buckets shared through Redis");
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to connect to Redis; rate limiting per replica");
+                None
+            }
+        }
+    }
+    #[cfg(not(feature = "redis"))]
+    {
+        tracing::warn!("RATE_LIMIT_STORE=redis but this build lacks the `redis` feature; rate limiting per replica");
+        None
+    }
+}
+
+/// Create the storage backend named by `STORAGE_BACKEND`
+async fn create_storage_backend(
+    config: &StorageConfig,
+    dynamodb: Arc<DynamoDbClient>,
+) -> anyhow::Result<(StorageKind, Arc<dyn StorageBackend>)> {
+    let kind = StorageKind::parse(&config.backend).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown STORAGE_BACKEND '{}' (expected dynamodb, sqlite or memory)",
+            config.backend
+        )
+    })?;
+
+    let storage: Arc<dyn StorageBackend> = match kind {
+        StorageKind::DynamoDb => Arc::new(DynamoDbBackend::new(dynamodb)),
+        #[cfg(feature = "sqlite")]
+        StorageKind::Sqlite => {
+            let url = config
+                .database_url
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("STORAGE_BACKEND=sqlite requires DATABASE_URL"))?;
+            Arc::new(SqliteBackend::new(url).await?)
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageKind::Sqlite => {
+            anyhow::bail!("STORAGE_BACKEND=sqlite requires a build with the `sqlite` feature")
+        }
+        StorageKind::Memory => {
+            tracing::warn!("Using in-memory storage; keys and usage are lost on restart");
+            Arc::new(MemoryBackend::new())
+        }
+    };
+    Ok((kind, storage))
+}
+
 /// Health status of backend services
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct AwsHealthStatus {
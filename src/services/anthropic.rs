@@ -0,0 +1,588 @@
+//! Anthropic service for the hosted Messages API (api.anthropic.com)
+//!
+//! Lets a deployment serve some Claude models from Anthropic directly while
+//! others stay on Bedrock: models routed to `anthropic` in ROUTING are sent
+//! here. Requests are authenticated with API keys from a credential pool,
+//! rotating to the next key on rate limits and server errors the same way
+//! the Gemini service does.
+
+use crate::converters::converse_anthropic::AnthropicRequest;
+use crate::schemas::anthropic::{ErrorResponse, StreamEvent};
+use crate::services::backend_pool::{
+    ApiKeyCredential, Credential, CredentialPool, LoadBalanceStrategy, PoolConfig,
+};
+use crate::services::routing_metrics::RoutingOutcome;
+use crate::utils::SseDataDecoder;
+use reqwest::Client;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+
+/// Messages API version sent in the `anthropic-version` header
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Body fields `/v1/messages/count_tokens` accepts
+const COUNT_TOKENS_FIELDS: &[&str] = &["model", "messages", "system", "tools", "tool_choice", "thinking"];
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Errors that can occur when calling the Anthropic API
+#[derive(Error, Debug)]
+pub enum AnthropicServiceError {
+    #[error("HTTP request failed: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("API error: {status} {error_type} - {message}")]
+    ApiError {
+        status: u16,
+        error_type: String,
+        message: String,
+    },
+
+    #[error("Failed to parse response: {0}")]
+    ParseError(String),
+
+    #[error("Missing API key")]
+    MissingApiKey,
+
+    #[error("No available credentials in pool")]
+    NoAvailableCredentials,
+
+    #[error("Stream error: {0}")]
+    StreamError(String),
+}
+
+// ============================================================================
+// Anthropic Service
+// ============================================================================
+
+/// Configuration for Anthropic service
+#[derive(Debug, Clone)]
+pub struct AnthropicConfig {
+    /// API keys for authentication (supports multiple keys for load balancing)
+    pub api_keys: Vec<String>,
+
+    /// Base URL (default: api.anthropic.com/v1)
+    pub base_url: Option<String>,
+
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+
+    /// Load balance strategy
+    pub strategy: LoadBalanceStrategy,
+
+    /// Consecutive failures that open a credential's circuit
+    pub max_failures: u32,
+
+    /// Seconds a credential's circuit stays open before it is probed
+    pub retry_after_secs: u64,
+
+    /// Seconds a rate-limited key sits out when Anthropic sends no retry-after
+    pub rate_limit_cooldown_secs: u64,
+
+    /// Canary successes that close a credential's open circuit
+    pub probe_successes: u32,
+}
+
+impl AnthropicConfig {
+    /// Create config with multiple API keys
+    pub fn with_keys(api_keys: Vec<String>) -> Self {
+        Self {
+            api_keys,
+            base_url: None,
+            timeout_seconds: 600,
+            strategy: LoadBalanceStrategy::RoundRobin,
+            max_failures: 3,
+            retry_after_secs: 300,
+            rate_limit_cooldown_secs: 60,
+            probe_successes: 2,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout_seconds = seconds;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: LoadBalanceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_max_failures(mut self, max: u32) -> Self {
+        self.max_failures = max;
+        self
+    }
+
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = secs;
+        self
+    }
+
+    pub fn with_rate_limit_cooldown(mut self, secs: u64) -> Self {
+        self.rate_limit_cooldown_secs = secs;
+        self
+    }
+
+    pub fn with_probe_successes(mut self, successes: u32) -> Self {
+        self.probe_successes = successes;
+        self
+    }
+}
+
+/// Service for calling Anthropic's hosted Messages API
+/// Supports multiple API keys with load balancing
+#[derive(Clone)]
+pub struct AnthropicService {
+    /// HTTP client
+    client: Client,
+
+    /// Base URL for API calls
+    base_url: Option<String>,
+
+    /// Credential pool for API keys
+    credential_pool: Arc<CredentialPool<ApiKeyCredential>>,
+}
+
+impl AnthropicService {
+    /// Create a new Anthropic service
+    pub fn new(config: AnthropicConfig) -> Result<Self, AnthropicServiceError> {
+        if config.api_keys.is_empty() {
+            return Err(AnthropicServiceError::MissingApiKey);
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+
+        let credentials: Vec<ApiKeyCredential> = config
+            .api_keys
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| ApiKeyCredential::new(key, format!("anthropic_key_{}", idx + 1), 1))
+            .collect();
+
+        let pool_config = PoolConfig::new(config.strategy)
+            .with_max_failures(config.max_failures)
+            .with_retry_after(config.retry_after_secs)
+            .with_rate_limit_cooldown(config.rate_limit_cooldown_secs)
+            .with_probe_successes(config.probe_successes);
+
+        let credential_pool = CredentialPool::new(credentials, pool_config);
+
+        tracing::info!(
+            key_count = credential_pool.len(),
+            strategy = %config.strategy,
+            "Initialized Anthropic service with credential pool"
+        );
+
+        Ok(Self {
+            client,
+            base_url: config.base_url,
+            credential_pool: Arc::new(credential_pool),
+        })
+    }
+
+    /// Get the base URL
+    fn base_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(ANTHROPIC_API_BASE)
+    }
+
+    /// Record a successful request for a credential
+    pub fn record_success(&self, credential_name: &str) {
+        self.credential_pool.record_success(credential_name);
+    }
+
+    /// Record a failed request for a credential
+    /// Returns true if the failure opened the credential's circuit
+    pub fn record_failure(&self, credential_name: &str) -> bool {
+        self.credential_pool.record_failure(credential_name)
+    }
+
+    /// Get pool statistics
+    pub fn pool_stats(&self) -> crate::services::backend_pool::PoolStats {
+        self.credential_pool.stats()
+    }
+
+    /// Send a request, rotating to the next key when one is rate limited or failing
+    ///
+    /// A 429 puts the key in cooldown for the `retry-after` Anthropic sends,
+    /// while 5xx (including 529 overloaded) and connection errors count toward
+    /// disabling it. Either way the request is retried once per remaining
+    /// key. Other 4xx responses are returned as they are.
+    async fn send(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+        betas: &[String],
+    ) -> Result<(reqwest::Response, RoutingOutcome), AnthropicServiceError> {
+        let mut tried: Vec<String> = Vec::new();
+        let mut failed_time = Duration::ZERO;
+
+        loop {
+            let attempt_started = Instant::now();
+            let credential = self
+                .credential_pool
+                .get_next()
+                .ok_or(AnthropicServiceError::NoAvailableCredentials)?;
+            let credential_name = credential.name().to_string();
+            let api_key = credential.api_key().to_string();
+            tried.push(credential_name.clone());
+
+            tracing::debug!(url = %url, credential = %credential_name, "Calling Anthropic API");
+
+            let mut builder = self
+                .client
+                .post(url)
+                .header("x-api-key", &api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Content-Type", "application/json");
+            if !betas.is_empty() {
+                builder = builder.header("anthropic-beta", betas.join(","));
+            }
+
+            let error = match builder.json(body).send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    let failed_attempts = tried.len() as u32 - 1;
+                    if failed_attempts > 0 {
+                        tracing::info!(
+                            credential = %credential_name,
+                            attempts = tried.len(),
+                            added_latency_ms = failed_time.as_millis() as u64,
+                            "Anthropic request served after key rotation"
+                        );
+                    }
+                    let outcome = RoutingOutcome::first_attempt("anthropic")
+                        .with_credential(credential_name)
+                        .with_retries(failed_attempts, failed_time);
+                    return Ok((resp, outcome));
+                }
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    let retry_after = resp
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    let error = api_error(status, resp.text().await.unwrap_or_default());
+
+                    if status == 429 {
+                        self.credential_pool
+                            .record_rate_limited(&credential_name, retry_after);
+                    } else if status >= 500 {
+                        self.record_failure(&credential_name);
+                    } else {
+                        // The request itself was rejected; another key won't help
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => {
+                    self.record_failure(&credential_name);
+                    AnthropicServiceError::HttpError(e)
+                }
+            };
+
+            failed_time += attempt_started.elapsed();
+
+            let more_keys = self.credential_pool.healthy_count() > 0
+                && tried.len() < self.credential_pool.len();
+            if !more_keys {
+                return Err(error);
+            }
+            tracing::warn!(
+                credential = %credential_name,
+                error = %error,
+                "Anthropic request failed, retrying with next key"
+            );
+        }
+    }
+
+    /// Create a message (non-streaming)
+    ///
+    /// Returns the response body and how the request was routed across keys
+    pub async fn create_message(
+        &self,
+        request: &AnthropicRequest,
+    ) -> Result<(serde_json::Value, RoutingOutcome), AnthropicServiceError> {
+        let url = format!("{}/messages", self.base_url());
+        let (resp, outcome) = self.send(&url, &request.body, &request.betas).await?;
+        if let Some(ref credential_name) = outcome.credential {
+            self.record_success(credential_name);
+        }
+
+        let response_text = resp.text().await?;
+        let response = serde_json::from_str(&response_text).map_err(|e| {
+            tracing::error!(error = %e, body = %response_text, "Failed to parse Anthropic response");
+            AnthropicServiceError::ParseError(e.to_string())
+        })?;
+        Ok((response, outcome))
+    }
+
+    /// Create a message with streaming
+    ///
+    /// The request body must have `stream: true`. Returns the stream and its
+    /// routing outcome; the outcome names the serving key so the caller can
+    /// record success/failure
+    pub async fn create_message_stream(
+        &self,
+        request: &AnthropicRequest,
+    ) -> Result<(AnthropicStream, RoutingOutcome), AnthropicServiceError> {
+        let url = format!("{}/messages", self.base_url());
+        let (resp, outcome) = self.send(&url, &request.body, &request.betas).await?;
+        Ok((AnthropicStream::new(resp), outcome))
+    }
+
+    /// Count the prompt tokens of a request with Anthropic's tokenizer
+    pub async fn count_tokens(&self, request: &AnthropicRequest) -> Result<i32, AnthropicServiceError> {
+        let url = format!("{}/messages/count_tokens", self.base_url());
+        let mut body = request.body.clone();
+        if let Some(fields) = body.as_object_mut() {
+            fields.retain(|key, _| COUNT_TOKENS_FIELDS.contains(&key.as_str()));
+        }
+        let (resp, outcome) = self.send(&url, &body, &request.betas).await?;
+        if let Some(ref credential_name) = outcome.credential {
+            self.record_success(credential_name);
+        }
+
+        let counted: serde_json::Value = resp.json().await?;
+        counted
+            .get("input_tokens")
+            .and_then(|v| v.as_i64())
+            .map(|total| total as i32)
+            .ok_or_else(|| {
+                AnthropicServiceError::ParseError(
+                    "count_tokens response without input_tokens".to_string(),
+                )
+            })
+    }
+
+    /// Check if the service is healthy (at least one credential available)
+    pub fn health_check(&self) -> bool {
+        self.credential_pool.healthy_count() > 0
+    }
+
+    /// Get the number of API keys in the pool
+    pub fn key_count(&self) -> usize {
+        self.credential_pool.len()
+    }
+
+    /// Get the number of healthy keys
+    pub fn healthy_key_count(&self) -> usize {
+        self.credential_pool.healthy_count()
+    }
+}
+
+/// Anthropic model ID for a client-facing or Bedrock model name
+///
+/// Bedrock IDs such as `us.anthropic.claude-sonnet-4-5-20250929-v1:0` lose
+/// their region prefix, `anthropic.` and version suffix; Anthropic names pass
+/// through unchanged.
+pub fn anthropic_model_id(model: &str) -> String {
+    let model = model
+        .split_once("anthropic.")
+        .map_or(model, |(_, rest)| rest);
+    let model = match model.rsplit_once("-v") {
+        Some((base, version))
+            if !version.is_empty()
+                && version
+                    .split(':')
+                    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            base
+        }
+        _ => model,
+    };
+    model.to_string()
+}
+
+/// Error for a non-success response, preferring Anthropic's own error body
+fn api_error(status: u16, body: String) -> AnthropicServiceError {
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(parsed) => AnthropicServiceError::ApiError {
+            status,
+            error_type: parsed.error.error_type,
+            message: parsed.error.message,
+        },
+        Err(_) => AnthropicServiceError::ApiError {
+            status,
+            error_type: "api_error".to_string(),
+            message: body,
+        },
+    }
+}
+
+/// HTTP status Anthropic uses for an error type sent in a stream `error` event
+fn error_type_status(error_type: &str) -> u16 {
+    match error_type {
+        "invalid_request_error" => 400,
+        "authentication_error" => 401,
+        "permission_error" => 403,
+        "not_found_error" => 404,
+        "request_too_large" => 413,
+        "rate_limit_error" => 429,
+        "overloaded_error" => 529,
+        _ => 500,
+    }
+}
+
+// ============================================================================
+// Streaming Support
+// ============================================================================
+
+/// A stream of Messages API events
+pub struct AnthropicStream {
+    response: reqwest::Response,
+    decoder: AnthropicSseDecoder,
+}
+
+impl AnthropicStream {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            decoder: AnthropicSseDecoder::new(),
+        }
+    }
+
+    /// Receive the next event from the stream
+    ///
+    /// An `error` event ends the stream as an [`AnthropicServiceError::ApiError`].
+    pub async fn recv(&mut self) -> Result<Option<StreamEvent>, AnthropicServiceError> {
+        loop {
+            let event = match self.decoder.next_event() {
+                Some(event) => Some(event),
+                None => match self.response.chunk().await {
+                    Ok(Some(bytes)) => {
+                        self.decoder.push(&bytes);
+                        continue;
+                    }
+                    // Stream ended; a final event may lack its trailing blank line
+                    Ok(None) => self.decoder.finish(),
+                    Err(e) => return Err(AnthropicServiceError::StreamError(e.to_string())),
+                },
+            };
+            return match event {
+                Some(StreamEvent::Error { error }) => {
+                    let error_type = error["type"].as_str().unwrap_or("api_error").to_string();
+                    Err(AnthropicServiceError::ApiError {
+                        status: error_type_status(&error_type),
+                        message: error["message"].as_str().unwrap_or_default().to_string(),
+                        error_type,
+                    })
+                }
+                other => Ok(other),
+            };
+        }
+    }
+}
+
+/// Incremental decoder for Messages API SSE bodies
+///
+/// Splits events with [`SseDataDecoder`] and parses each payload as a
+/// [`StreamEvent`]. The event type is also in the JSON payload, so only
+/// `data:` lines are read.
+#[derive(Debug, Default)]
+pub struct AnthropicSseDecoder {
+    sse: SseDataDecoder,
+}
+
+impl AnthropicSseDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append raw bytes read from the response body
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.sse.push(bytes);
+    }
+
+    /// Take the next complete event, if one is buffered
+    pub fn next_event(&mut self) -> Option<StreamEvent> {
+        while let Some(data) = self.sse.next_data() {
+            if let Some(event) = parse_event(&data) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Flush a trailing event left unterminated at end of stream
+    pub fn finish(&mut self) -> Option<StreamEvent> {
+        self.next_event()
+            .or_else(|| self.sse.finish().and_then(|data| parse_event(&data)))
+    }
+}
+
+/// Parse one event payload
+fn parse_event(data: &str) -> Option<StreamEvent> {
+    match serde_json::from_str::<StreamEvent>(data) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            tracing::warn!(error = %e, data = %data, "Failed to parse Anthropic stream event");
+            None
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_model_id() {
+        assert_eq!(
+            anthropic_model_id("us.anthropic.claude-sonnet-4-5-20250929-v1:0"),
+            "claude-sonnet-4-5-20250929"
+        );
+        assert_eq!(
+            anthropic_model_id("anthropic.claude-3-5-sonnet-20241022-v2:0"),
+            "claude-3-5-sonnet-20241022"
+        );
+        assert_eq!(anthropic_model_id("claude-opus-4-5"), "claude-opus-4-5");
+        assert_eq!(anthropic_model_id("claude-3-7-sonnet-latest"), "claude-3-7-sonnet-latest");
+    }
+
+    #[test]
+    fn test_decoder_splits_events_and_reads_errors() {
+        let mut decoder = AnthropicSseDecoder::new();
+        decoder.push(b"event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\nevent: ping\r\n");
+        decoder.push(b"data: {\"type\":\"ping\"}\r\n\r\nevent: error\ndata: {\"type\":\"error\",");
+        assert!(matches!(decoder.next_event(), Some(StreamEvent::MessageStart { .. })));
+        assert!(matches!(decoder.next_event(), Some(StreamEvent::Ping)));
+        assert!(decoder.next_event().is_none());
+
+        decoder.push(b"\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}");
+        let Some(StreamEvent::Error { error }) = decoder.finish() else {
+            panic!("expected error event");
+        };
+        assert_eq!(error_type_status(error["type"].as_str().unwrap()), 529);
+    }
+
+    #[test]
+    fn test_service_requires_keys() {
+        assert!(AnthropicService::new(AnthropicConfig::with_keys(Vec::new())).is_err());
+        let service =
+            AnthropicService::new(AnthropicConfig::with_keys(vec!["sk-ant-1".to_string()])).unwrap();
+        assert_eq!(service.key_count(), 1);
+        assert!(service.health_check());
+    }
+}
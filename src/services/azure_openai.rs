@@ -0,0 +1,676 @@
+//! Azure OpenAI service for Chat Completions deployments
+//!
+//! Models routed to `azure` in ROUTING are sent to an Azure OpenAI resource.
+//! Azure addresses models by deployment rather than model name, so each
+//! request goes to `{endpoint}/openai/deployments/{deployment}/chat/completions`
+//! with the configured `api-version` query parameter; the deployment comes
+//! from the model -> deployment map and defaults to the model name.
+//!
+//! Resources authenticate with an `api-key` or with a Microsoft Entra ID
+//! (Azure AD) service principal whose bearer tokens are fetched with the
+//! client credentials flow and cached until shortly before they expire.
+//! Several resources can share the load through a credential pool, rotating
+//! on rate limits and server errors the same way the Gemini service does.
+
+use crate::services::backend_pool::{
+    AzureAuth, AzureCredential, Credential, CredentialPool, LoadBalanceStrategy, PoolConfig,
+};
+use crate::services::routing_metrics::RoutingOutcome;
+use crate::utils::SseDataDecoder;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Default data-plane API version (latest GA)
+pub const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+const ENTRA_TOKEN_URL: &str = "https://login.microsoftonline.com";
+
+/// OAuth scope for Azure OpenAI (Cognitive Services) tokens
+const ENTRA_SCOPE: &str = "https://cognitiveservices.azure.com/.default";
+
+/// Tokens are refreshed this long before Entra ID says they expire
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Errors that can occur when calling Azure OpenAI
+#[derive(Error, Debug)]
+pub enum AzureOpenAIServiceError {
+    #[error("HTTP request failed: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("API error: {status} {code} - {message}")]
+    ApiError {
+        status: u16,
+        code: String,
+        message: String,
+    },
+
+    #[error("Entra ID authentication failed: {0}")]
+    AuthError(String),
+
+    #[error("Failed to parse response: {0}")]
+    ParseError(String),
+
+    #[error("No Azure OpenAI resources configured")]
+    MissingCredentials,
+
+    #[error("No available credentials in pool")]
+    NoAvailableCredentials,
+
+    #[error("Stream error: {0}")]
+    StreamError(String),
+}
+
+// ============================================================================
+// Azure OpenAI Service
+// ============================================================================
+
+/// Configuration for Azure OpenAI service
+#[derive(Debug)]
+pub struct AzureOpenAIConfig {
+    /// Resources to spread requests over
+    pub credentials: Vec<AzureCredential>,
+
+    /// `api-version` query parameter
+    pub api_version: String,
+
+    /// Model name -> deployment name
+    pub deployments: HashMap<String, String>,
+
+    /// Request timeout in seconds
+    pub timeout_seconds: u64,
+
+    /// Load balance strategy
+    pub strategy: LoadBalanceStrategy,
+
+    /// Consecutive failures that open a credential's circuit
+    pub max_failures: u32,
+
+    /// Seconds a credential's circuit stays open before it is probed
+    pub retry_after_secs: u64,
+
+    /// Seconds a rate-limited resource sits out when Azure sends no retry-after
+    pub rate_limit_cooldown_secs: u64,
+
+    /// Canary successes that close a credential's open circuit
+    pub probe_successes: u32,
+}
+
+impl AzureOpenAIConfig {
+    /// Create config for a set of resources
+    pub fn with_credentials(credentials: Vec<AzureCredential>) -> Self {
+        Self {
+            credentials,
+            api_version: DEFAULT_API_VERSION.to_string(),
+            deployments: HashMap::new(),
+            timeout_seconds: 600,
+            strategy: LoadBalanceStrategy::RoundRobin,
+            max_failures: 3,
+            retry_after_secs: 300,
+            rate_limit_cooldown_secs: 60,
+            probe_successes: 2,
+        }
+    }
+
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    pub fn with_deployments(mut self, deployments: HashMap<String, String>) -> Self {
+        self.deployments = deployments;
+        self
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout_seconds = seconds;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: LoadBalanceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_max_failures(mut self, max: u32) -> Self {
+        self.max_failures = max;
+        self
+    }
+
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = secs;
+        self
+    }
+
+    pub fn with_rate_limit_cooldown(mut self, secs: u64) -> Self {
+        self.rate_limit_cooldown_secs = secs;
+        self
+    }
+
+    pub fn with_probe_successes(mut self, successes: u32) -> Self {
+        self.probe_successes = successes;
+        self
+    }
+}
+
+/// An Entra ID access token and when to stop using it
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Service for calling Azure OpenAI Chat Completions deployments
+/// Supports multiple resources with load balancing
+#[derive(Clone)]
+pub struct AzureOpenAIService {
+    /// HTTP client
+    client: Client,
+
+    /// `api-version` query parameter
+    api_version: String,
+
+    /// Model name -> deployment name
+    deployments: Arc<HashMap<String, String>>,
+
+    /// Credential pool for resources
+    credential_pool: Arc<CredentialPool<AzureCredential>>,
+
+    /// Entra ID tokens by credential name
+    tokens: Arc<Mutex<HashMap<String, CachedToken>>>,
+}
+
+impl AzureOpenAIService {
+    /// Create a new Azure OpenAI service
+    pub fn new(config: AzureOpenAIConfig) -> Result<Self, AzureOpenAIServiceError> {
+        if config.credentials.is_empty() {
+            return Err(AzureOpenAIServiceError::MissingCredentials);
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+
+        let pool_config = PoolConfig::new(config.strategy)
+            .with_max_failures(config.max_failures)
+            .with_retry_after(config.retry_after_secs)
+            .with_rate_limit_cooldown(config.rate_limit_cooldown_secs)
+            .with_probe_successes(config.probe_successes);
+
+        let credential_pool = CredentialPool::new(config.credentials, pool_config);
+
+        tracing::info!(
+            resource_count = credential_pool.len(),
+            api_version = %config.api_version,
+            deployments = config.deployments.len(),
+            strategy = %config.strategy,
+            "Initialized Azure OpenAI service with credential pool"
+        );
+
+        Ok(Self {
+            client,
+            api_version: config.api_version,
+            deployments: Arc::new(config.deployments),
+            credential_pool: Arc::new(credential_pool),
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Deployment serving a model (the model name when it is not mapped)
+    pub fn deployment_for(&self, model: &str) -> String {
+        self.deployments
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| model.to_string())
+    }
+
+    /// Record a successful request for a credential
+    pub fn record_success(&self, credential_name: &str) {
+        self.credential_pool.record_success(credential_name);
+    }
+
+    /// Record a failed request for a credential
+    /// Returns true if the failure opened the credential's circuit
+    pub fn record_failure(&self, credential_name: &str) -> bool {
+        self.credential_pool.record_failure(credential_name)
+    }
+
+    /// Get pool statistics
+    pub fn pool_stats(&self) -> crate::services::backend_pool::PoolStats {
+        self.credential_pool.stats()
+    }
+
+    /// Authentication header for a credential
+    async fn auth_header(
+        &self,
+        credential: &AzureCredential,
+    ) -> Result<(&'static str, String), AzureOpenAIServiceError> {
+        match credential.auth() {
+            AzureAuth::ApiKey(key) => Ok(("api-key", key.clone())),
+            AzureAuth::EntraId {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => {
+                let cached = self
+                    .tokens
+                    .lock()
+                    .ok()
+                    .and_then(|tokens| tokens.get(credential.name()).cloned())
+                    .filter(|cached| cached.refresh_at > Instant::now());
+                let token = match cached {
+                    Some(cached) => cached.token,
+                    None => {
+                        let fetched = self.fetch_token(tenant_id, client_id, client_secret).await?;
+                        if let Ok(mut tokens) = self.tokens.lock() {
+                            tokens.insert(credential.name().to_string(), fetched.clone());
+                        }
+                        fetched.token
+                    }
+                };
+                Ok(("Authorization", format!("Bearer {}", token)))
+            }
+        }
+    }
+
+    /// Exchange service principal credentials for an access token
+    async fn fetch_token(
+        &self,
+        tenant_id: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<CachedToken, AzureOpenAIServiceError> {
+        let url = format!("{}/{}/oauth2/v2.0/token", ENTRA_TOKEN_URL, tenant_id);
+        let resp = self
+            .client
+            .post(&url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+                ("scope", ENTRA_SCOPE),
+            ])
+            .send()
+            .await
+            .map_err(|e| AzureOpenAIServiceError::AuthError(e.to_string()))?;
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(AzureOpenAIServiceError::AuthError(format!("{} {}", status, body)));
+        }
+        let token: TokenResponse = resp
+            .json()
+            .await
+            .map_err(|e| AzureOpenAIServiceError::AuthError(e.to_string()))?;
+        let lifetime = Duration::from_secs(token.expires_in).saturating_sub(TOKEN_REFRESH_MARGIN);
+        Ok(CachedToken {
+            token: token.access_token,
+            refresh_at: Instant::now() + lifetime,
+        })
+    }
+
+    /// Send a request, rotating to the next resource when one is rate limited or failing
+    ///
+    /// A 429 puts the resource in cooldown for the `retry-after` Azure sends,
+    /// while 5xx, connection and token errors count toward disabling it.
+    /// Either way the request is retried once per remaining resource. Other
+    /// 4xx responses are returned as they are.
+    async fn send(
+        &self,
+        deployment: &str,
+        body: &serde_json::Value,
+    ) -> Result<(reqwest::Response, RoutingOutcome), AzureOpenAIServiceError> {
+        let mut tried: Vec<String> = Vec::new();
+        let mut failed_time = Duration::ZERO;
+
+        loop {
+            let attempt_started = Instant::now();
+            let credential = self
+                .credential_pool
+                .get_next()
+                .ok_or(AzureOpenAIServiceError::NoAvailableCredentials)?;
+            let credential_name = credential.name().to_string();
+            let url = format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                credential.endpoint(),
+                deployment,
+                self.api_version
+            );
+            tried.push(credential_name.clone());
+
+            tracing::debug!(url = %url, credential = %credential_name, "Calling Azure OpenAI");
+
+            let response = match self.auth_header(credential).await {
+                Ok((header, value)) => self
+                    .client
+                    .post(&url)
+                    .header(header, value)
+                    .header("Content-Type", "application/json")
+                    .json(body)
+                    .send()
+                    .await
+                    .map_err(AzureOpenAIServiceError::HttpError),
+                Err(e) => Err(e),
+            };
+
+            let error = match response {
+                Ok(resp) if resp.status().is_success() => {
+                    let failed_attempts = tried.len() as u32 - 1;
+                    if failed_attempts > 0 {
+                        tracing::info!(
+                            credential = %credential_name,
+                            attempts = tried.len(),
+                            added_latency_ms = failed_time.as_millis() as u64,
+                            "Azure OpenAI request served after resource rotation"
+                        );
+                    }
+                    let outcome = RoutingOutcome::first_attempt("azure")
+                        .with_credential(credential_name)
+                        .with_retries(failed_attempts, failed_time);
+                    return Ok((resp, outcome));
+                }
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    let retry_after = resp
+                        .headers()
+                        .get("retry-after")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    let error = api_error(status, resp.text().await.unwrap_or_default());
+
+                    if status == 429 {
+                        self.credential_pool
+                            .record_rate_limited(&credential_name, retry_after);
+                    } else if status >= 500 {
+                        self.record_failure(&credential_name);
+                    } else {
+                        // The request itself was rejected; another resource won't help
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => {
+                    self.record_failure(&credential_name);
+                    e
+                }
+            };
+
+            failed_time += attempt_started.elapsed();
+
+            let more_resources = self.credential_pool.healthy_count() > 0
+                && tried.len() < self.credential_pool.len();
+            if !more_resources {
+                return Err(error);
+            }
+            tracing::warn!(
+                credential = %credential_name,
+                error = %error,
+                "Azure OpenAI request failed, retrying with next resource"
+            );
+        }
+    }
+
+    /// Create a chat completion (non-streaming)
+    ///
+    /// Returns the response body and how the request was routed across resources
+    pub async fn chat_completion(
+        &self,
+        deployment: &str,
+        body: &serde_json::Value,
+    ) -> Result<(serde_json::Value, RoutingOutcome), AzureOpenAIServiceError> {
+        let (resp, outcome) = self.send(deployment, body).await?;
+        if let Some(ref credential_name) = outcome.credential {
+            self.record_success(credential_name);
+        }
+
+        let response_text = resp.text().await?;
+        let response = serde_json::from_str(&response_text).map_err(|e| {
+            tracing::error!(error = %e, body = %response_text, "Failed to parse Azure OpenAI response");
+            AzureOpenAIServiceError::ParseError(e.to_string())
+        })?;
+        Ok((response, outcome))
+    }
+
+    /// Create a chat completion with streaming
+    ///
+    /// The request body must have `stream: true`. Returns the stream and its
+    /// routing outcome; the outcome names the serving resource so the caller
+    /// can record success/failure
+    pub async fn chat_completion_stream(
+        &self,
+        deployment: &str,
+        body: &serde_json::Value,
+    ) -> Result<(AzureOpenAIStream, RoutingOutcome), AzureOpenAIServiceError> {
+        let (resp, outcome) = self.send(deployment, body).await?;
+        Ok((AzureOpenAIStream::new(resp), outcome))
+    }
+
+    /// Check if the service is healthy (at least one credential available)
+    pub fn health_check(&self) -> bool {
+        self.credential_pool.healthy_count() > 0
+    }
+
+    /// Get the number of resources in the pool
+    pub fn resource_count(&self) -> usize {
+        self.credential_pool.len()
+    }
+
+    /// Get the number of healthy resources
+    pub fn healthy_resource_count(&self) -> usize {
+        self.credential_pool.healthy_count()
+    }
+}
+
+/// Error for a non-success response, preferring Azure's own error body
+fn api_error(status: u16, body: String) -> AzureOpenAIServiceError {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+    match parsed.as_ref().map(|value| &value["error"]).filter(|e| e.is_object()) {
+        Some(error) => AzureOpenAIServiceError::ApiError {
+            status,
+            code: error["code"].as_str().unwrap_or("api_error").to_string(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+        },
+        None => AzureOpenAIServiceError::ApiError {
+            status,
+            code: "api_error".to_string(),
+            message: body,
+        },
+    }
+}
+
+// ============================================================================
+// Streaming Support
+// ============================================================================
+
+/// A stream of Chat Completions chunks
+pub struct AzureOpenAIStream {
+    response: reqwest::Response,
+    decoder: OpenAISseDecoder,
+}
+
+impl AzureOpenAIStream {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            decoder: OpenAISseDecoder::new(),
+        }
+    }
+
+    /// Receive the next chunk from the stream
+    ///
+    /// Returns None after `data: [DONE]` or when the body ends. A chunk
+    /// carrying an `error` object ends the stream as an
+    /// [`AzureOpenAIServiceError::ApiError`].
+    pub async fn recv(&mut self) -> Result<Option<serde_json::Value>, AzureOpenAIServiceError> {
+        loop {
+            let chunk = match self.decoder.next_chunk() {
+                Some(chunk) => Some(chunk),
+                None if self.decoder.is_done() => None,
+                None => match self.response.chunk().await {
+                    Ok(Some(bytes)) => {
+                        self.decoder.push(&bytes);
+                        continue;
+                    }
+                    // Stream ended; a final chunk may lack its trailing blank line
+                    Ok(None) => self.decoder.finish(),
+                    Err(e) => return Err(AzureOpenAIServiceError::StreamError(e.to_string())),
+                },
+            };
+            return match chunk {
+                Some(chunk) if chunk["error"].is_object() => {
+                    Err(api_error(500, chunk.to_string()))
+                }
+                other => Ok(other),
+            };
+        }
+    }
+}
+
+/// Incremental decoder for Chat Completions SSE bodies
+///
+/// Splits events with [`SseDataDecoder`] and parses each payload as JSON.
+/// The `[DONE]` sentinel ends decoding.
+#[derive(Debug, Default)]
+pub struct OpenAISseDecoder {
+    sse: SseDataDecoder,
+    done: bool,
+}
+
+impl OpenAISseDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append raw bytes read from the response body
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.sse.push(bytes);
+    }
+
+    /// Whether `[DONE]` has been seen
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Take the next complete chunk, if one is buffered
+    pub fn next_chunk(&mut self) -> Option<serde_json::Value> {
+        while !self.done {
+            let data = self.sse.next_data()?;
+            if let Some(chunk) = self.parse_chunk(&data) {
+                return Some(chunk);
+            }
+        }
+        None
+    }
+
+    /// Flush a trailing chunk left unterminated at end of stream
+    pub fn finish(&mut self) -> Option<serde_json::Value> {
+        if let Some(chunk) = self.next_chunk() {
+            return Some(chunk);
+        }
+        let chunk = self.sse.finish().and_then(|data| self.parse_chunk(&data));
+        self.done = true;
+        chunk
+    }
+
+    /// Parse one event payload
+    fn parse_chunk(&mut self, data: &str) -> Option<serde_json::Value> {
+        if self.done {
+            return None;
+        }
+        if data == "[DONE]" {
+            self.done = true;
+            return None;
+        }
+
+        match serde_json::from_str(data) {
+            Ok(chunk) => Some(chunk),
+            Err(e) => {
+                tracing::warn!(error = %e, data = %data, "Failed to parse Azure OpenAI stream chunk");
+                None
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(deployments: &[(&str, &str)]) -> AzureOpenAIService {
+        let credentials = vec![AzureCredential::with_api_key(
+            "https://res.openai.azure.com",
+            "key",
+            "azure_1",
+            1,
+        )];
+        let deployments = deployments
+            .iter()
+            .map(|(model, deployment)| (model.to_string(), deployment.to_string()))
+            .collect();
+        AzureOpenAIService::new(
+            AzureOpenAIConfig::with_credentials(credentials).with_deployments(deployments),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_deployment_for_model() {
+        let service = service(&[("gpt-4o", "prod-gpt4o")]);
+        assert_eq!(service.deployment_for("gpt-4o"), "prod-gpt4o");
+        assert_eq!(service.deployment_for("gpt-4o-mini"), "gpt-4o-mini");
+        assert_eq!(service.resource_count(), 1);
+        assert!(service.health_check());
+    }
+
+    #[test]
+    fn test_service_requires_credentials() {
+        assert!(AzureOpenAIService::new(AzureOpenAIConfig::with_credentials(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_decoder_stops_at_done() {
+        let mut decoder = OpenAISseDecoder::new();
+        decoder.push(b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n: keep-alive\r\n\r\n");
+        decoder.push(b"data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3}}\n\ndata: [DONE]\n\ndata: {}\n\n");
+        assert_eq!(decoder.next_chunk().unwrap()["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(decoder.next_chunk().unwrap()["usage"]["prompt_tokens"], 3);
+        assert!(decoder.next_chunk().is_none());
+        assert!(decoder.is_done());
+        assert!(decoder.finish().is_none());
+    }
+
+    #[test]
+    fn test_api_error_reads_azure_error_body() {
+        let error = api_error(
+            400,
+            r#"{"error":{"code":"content_filter","message":"filtered"}}"#.to_string(),
+        );
+        let AzureOpenAIServiceError::ApiError { status, code, message } = error else {
+            panic!("expected api error");
+        };
+        assert_eq!((status, code.as_str(), message.as_str()), (400, "content_filter", "filtered"));
+    }
+}
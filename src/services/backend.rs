@@ -0,0 +1,755 @@
+//! Pluggable inference backends
+//!
+//! The Bedrock Converse request/response types are the proxy's internal
+//! contract: every API handler builds a [`ConverseRequest`] and consumes a
+//! Converse output or event stream. A [`Backend`] serves that contract for
+//! one provider. Bedrock speaks it natively; Gemini, the hosted Anthropic
+//! API and Azure OpenAI adapt through
+//! [`converse_gemini`](crate::converters::converse_gemini),
+//! [`converse_anthropic`](crate::converters::converse_anthropic) and
+//! [`converse_openai`](crate::converters::converse_openai).
+//! Handlers resolve a backend from the [`BackendRegistry`] in `AppState`
+//! instead of reaching for a concrete service, so adding a provider means
+//! implementing this trait and registering it under its [`BackendTarget`].
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::types::{ContentBlock, SystemContentBlock};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::converters::converse_anthropic::{self, AnthropicConverseEvents};
+#[cfg(feature = "gemini")]
+use crate::converters::converse_gemini::{self, GeminiConverseEvents};
+use crate::converters::converse_openai::{self, OpenAIConverseEvents};
+use crate::services::anthropic::{anthropic_model_id, AnthropicService, AnthropicServiceError};
+use crate::services::azure_openai::{AzureOpenAIService, AzureOpenAIServiceError};
+use crate::services::backend_hooks::{BackendHook, HookedBackend};
+use crate::services::bedrock::{
+    BedrockError, BedrockService, BedrockStreamError, ConverseRequest, ConverseStreamResponse,
+};
+#[cfg(feature = "gemini")]
+use crate::services::gemini::{GeminiService, GeminiServiceError};
+use crate::services::model_routing::BackendTarget;
+use crate::services::pii_tokenizer::{PiiTokenizer, PiiTokenizingBackend};
+
+/// What a backend supports beyond plain Converse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    /// Honors provisioned throughput and service tiers
+    pub service_tiers: bool,
+    /// `count_tokens` asks the provider instead of estimating
+    pub native_token_counting: bool,
+    /// Understands Converse cache points
+    pub prompt_caching: bool,
+    /// Accepts `additionalModelRequestFields` (betas, thinking, ...)
+    pub additional_fields: bool,
+}
+
+/// An inference provider serving the Converse contract
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Features this backend supports
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Provider model ID for a client-facing model name
+    fn resolve_model_id(&self, model: &str) -> String;
+
+    /// Non-streaming inference
+    async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError>;
+
+    /// Streaming inference
+    async fn converse_stream(
+        &self,
+        request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError>;
+
+    /// Prompt tokens for a request
+    ///
+    /// Backends without a tokenizer endpoint estimate from the prompt text.
+    async fn count_tokens(&self, request: &ConverseRequest) -> Result<i32, BedrockError> {
+        Ok(estimate_tokens(request))
+    }
+
+    /// Whether the backend can currently serve requests
+    fn health_check(&self) -> bool;
+
+    /// Region requests are served from, where the provider has regions
+    fn region(&self) -> Option<String> {
+        None
+    }
+}
+
+/// OpenAI-style `system_fingerprint` for a backend, model and region
+///
+/// Changes whenever any of the three does, so clients relying on `seed` can
+/// tell their requests were served by a different configuration. Uses FNV-1a
+/// so the value is stable across builds.
+pub fn system_fingerprint(backend: &str, model: &str, region: Option<&str>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in [backend, model, region.unwrap_or_default()]
+        .join("|")
+        .bytes()
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("fp_{:012x}", hash >> 16)
+}
+
+/// Rough token count (~4 characters per token) of a request's text
+pub fn estimate_tokens(request: &ConverseRequest) -> i32 {
+    let system_chars: usize = request
+        .system
+        .iter()
+        .flatten()
+        .map(|block| match block {
+            SystemContentBlock::Text(text) => text.len(),
+            _ => 0,
+        })
+        .sum();
+    let message_chars: usize = request
+        .messages
+        .iter()
+        .flat_map(|message| message.content())
+        .map(|block| match block {
+            ContentBlock::Text(text) => text.len(),
+            ContentBlock::ToolUse(tool_use) => tool_use.name().len() + 16,
+            ContentBlock::ToolResult(tool_result) => tool_result
+                .content()
+                .iter()
+                .filter_map(|c| c.as_text().ok())
+                .map(|text| text.len())
+                .sum(),
+            _ => 0,
+        })
+        .sum();
+    ((system_chars + message_chars) / 4).max(1) as i32
+}
+
+// ============================================================================
+// Bedrock
+// ============================================================================
+
+#[async_trait]
+impl Backend for BedrockService {
+    fn name(&self) -> &'static str {
+        "bedrock"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            service_tiers: true,
+            native_token_counting: false,
+            prompt_caching: true,
+            additional_fields: true,
+        }
+    }
+
+    fn resolve_model_id(&self, model: &str) -> String {
+        self.get_bedrock_model_id(model)
+    }
+
+    async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        BedrockService::converse(self, request).await
+    }
+
+    async fn converse_stream(
+        &self,
+        request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        BedrockService::converse_stream(self, request).await
+    }
+
+    fn health_check(&self) -> bool {
+        BedrockService::health_check(self)
+    }
+
+    fn region(&self) -> Option<String> {
+        self.client()
+            .config()
+            .region()
+            .map(|region| region.to_string())
+    }
+}
+
+// ============================================================================
+// Gemini
+// ============================================================================
+
+#[cfg(feature = "gemini")]
+#[async_trait]
+impl Backend for GeminiService {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            service_tiers: false,
+            native_token_counting: true,
+            prompt_caching: false,
+            additional_fields: false,
+        }
+    }
+
+    /// Gemini model names are passed through unchanged
+    fn resolve_model_id(&self, model: &str) -> String {
+        model.to_string()
+    }
+
+    async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        let gemini_request = converse_gemini::to_gemini_request(&request)
+            .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (response, _) = self
+            .generate_content(&request.model_id, &gemini_request)
+            .await
+            .map_err(backend_error)?;
+        converse_gemini::to_converse_output(&response)
+            .map_err(|e| BedrockError::Deserialization(e.to_string()))
+    }
+
+    async fn converse_stream(
+        &self,
+        request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        let gemini_request = converse_gemini::to_gemini_request(&request)
+            .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (mut stream, outcome) = self
+            .generate_content_stream(&request.model_id, &gemini_request)
+            .await
+            .map_err(backend_error)?;
+        let credential_name = outcome.credential.unwrap_or_default();
+        let service = self.clone();
+
+        let events = async_stream::stream! {
+            let mut adapter = GeminiConverseEvents::new();
+            loop {
+                let converted = match stream.recv().await {
+                    Ok(Some(chunk)) => adapter.chunk_events(&chunk),
+                    Ok(None) => {
+                        service.record_success(&credential_name);
+                        match adapter.finish_events() {
+                            Ok(events) => {
+                                for event in events {
+                                    yield Ok(event);
+                                }
+                            }
+                            Err(e) => yield Err(BedrockStreamError::ParseError(e.to_string())),
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        service.record_failure(&credential_name);
+                        yield Err(BedrockStreamError::Backend(backend_error(e)));
+                        break;
+                    }
+                };
+                match converted {
+                    Ok(events) => {
+                        for event in events {
+                            yield Ok(event);
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(BedrockStreamError::ParseError(e.to_string()));
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(ConverseStreamResponse::from_events(events))
+    }
+
+    async fn count_tokens(&self, request: &ConverseRequest) -> Result<i32, BedrockError> {
+        let gemini_request = converse_gemini::to_gemini_request(request)
+            .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        GeminiService::count_tokens(self, &request.model_id, &gemini_request)
+            .await
+            .map_err(backend_error)
+    }
+
+    fn health_check(&self) -> bool {
+        GeminiService::health_check(self)
+    }
+}
+
+/// Classify a Gemini failure like the equivalent Bedrock error
+#[cfg(feature = "gemini")]
+fn backend_error(err: GeminiServiceError) -> BedrockError {
+    match err {
+        GeminiServiceError::ApiError { code, message } => match code {
+            400 => BedrockError::ValidationError(message),
+            401 | 403 => BedrockError::AccessDenied(message),
+            404 => BedrockError::ModelNotFound(message),
+            429 => BedrockError::Throttled(message),
+            500 => BedrockError::InternalError(message),
+            _ if code > 500 => BedrockError::ServiceUnavailable(message),
+            _ => BedrockError::Unknown(message),
+        },
+        GeminiServiceError::ParseError(message) => BedrockError::Deserialization(message),
+        GeminiServiceError::NoAvailableCredentials | GeminiServiceError::MissingApiKey => {
+            BedrockError::ServiceUnavailable(err.to_string())
+        }
+        GeminiServiceError::HttpError(_) | GeminiServiceError::StreamError(_) => {
+            BedrockError::ServiceUnavailable(err.to_string())
+        }
+    }
+}
+
+// ============================================================================
+// Anthropic
+// ============================================================================
+
+#[async_trait]
+impl Backend for AnthropicService {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            service_tiers: false,
+            native_token_counting: true,
+            prompt_caching: true,
+            additional_fields: true,
+        }
+    }
+
+    fn resolve_model_id(&self, model: &str) -> String {
+        anthropic_model_id(model)
+    }
+
+    async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        let anthropic_request =
+            converse_anthropic::to_anthropic_request(&request, &request.model_id, false)
+                .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (response, _) = self
+            .create_message(&anthropic_request)
+            .await
+            .map_err(anthropic_backend_error)?;
+        converse_anthropic::to_converse_output(&response)
+            .map_err(|e| BedrockError::Deserialization(e.to_string()))
+    }
+
+    async fn converse_stream(
+        &self,
+        request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        let anthropic_request =
+            converse_anthropic::to_anthropic_request(&request, &request.model_id, true)
+                .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (mut stream, outcome) = self
+            .create_message_stream(&anthropic_request)
+            .await
+            .map_err(anthropic_backend_error)?;
+        let credential_name = outcome.credential.unwrap_or_default();
+        let service = self.clone();
+
+        let events = async_stream::stream! {
+            let mut adapter = AnthropicConverseEvents::new();
+            loop {
+                let converted = match stream.recv().await {
+                    Ok(Some(event)) => adapter.event_events(&event),
+                    Ok(None) => {
+                        service.record_success(&credential_name);
+                        break;
+                    }
+                    Err(e) => {
+                        service.record_failure(&credential_name);
+                        yield Err(BedrockStreamError::Backend(anthropic_backend_error(e)));
+                        break;
+                    }
+                };
+                match converted {
+                    Ok(events) => {
+                        for event in events {
+                            yield Ok(event);
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(BedrockStreamError::ParseError(e.to_string()));
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(ConverseStreamResponse::from_events(events))
+    }
+
+    async fn count_tokens(&self, request: &ConverseRequest) -> Result<i32, BedrockError> {
+        let anthropic_request =
+            converse_anthropic::to_anthropic_request(request, &request.model_id, false)
+                .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        AnthropicService::count_tokens(self, &anthropic_request)
+            .await
+            .map_err(anthropic_backend_error)
+    }
+
+    fn health_check(&self) -> bool {
+        AnthropicService::health_check(self)
+    }
+}
+
+/// Classify an Anthropic failure like the equivalent Bedrock error
+fn anthropic_backend_error(err: AnthropicServiceError) -> BedrockError {
+    match err {
+        AnthropicServiceError::ApiError { status, message, .. } => match status {
+            400 | 413 => BedrockError::ValidationError(message),
+            401 | 403 => BedrockError::AccessDenied(message),
+            404 => BedrockError::ModelNotFound(message),
+            429 => BedrockError::Throttled(message),
+            500 => BedrockError::InternalError(message),
+            _ if status > 500 => BedrockError::ServiceUnavailable(message),
+            _ => BedrockError::Unknown(message),
+        },
+        AnthropicServiceError::ParseError(message) => BedrockError::Deserialization(message),
+        AnthropicServiceError::NoAvailableCredentials
+        | AnthropicServiceError::MissingApiKey
+        | AnthropicServiceError::HttpError(_)
+        | AnthropicServiceError::StreamError(_) => BedrockError::ServiceUnavailable(err.to_string()),
+    }
+}
+
+// ============================================================================
+// Azure OpenAI
+// ============================================================================
+
+#[async_trait]
+impl Backend for AzureOpenAIService {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            service_tiers: false,
+            native_token_counting: false,
+            prompt_caching: false,
+            additional_fields: false,
+        }
+    }
+
+    /// Azure addresses models by deployment name
+    fn resolve_model_id(&self, model: &str) -> String {
+        self.deployment_for(model)
+    }
+
+    async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        let body = converse_openai::to_openai_request(&request, false)
+            .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (response, _) = self
+            .chat_completion(&request.model_id, &body)
+            .await
+            .map_err(azure_backend_error)?;
+        converse_openai::to_converse_output(&response)
+            .map_err(|e| BedrockError::Deserialization(e.to_string()))
+    }
+
+    async fn converse_stream(
+        &self,
+        request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        let body = converse_openai::to_openai_request(&request, true)
+            .map_err(|e| BedrockError::ValidationError(e.to_string()))?;
+        let (mut stream, outcome) = self
+            .chat_completion_stream(&request.model_id, &body)
+            .await
+            .map_err(azure_backend_error)?;
+        let credential_name = outcome.credential.unwrap_or_default();
+        let service = self.clone();
+
+        let events = async_stream::stream! {
+            let mut adapter = OpenAIConverseEvents::new();
+            loop {
+                let converted = match stream.recv().await {
+                    Ok(Some(chunk)) => adapter.chunk_events(&chunk),
+                    Ok(None) => {
+                        service.record_success(&credential_name);
+                        match adapter.finish_events() {
+                            Ok(events) => {
+                                for event in events {
+                                    yield Ok(event);
+                                }
+                            }
+                            Err(e) => yield Err(BedrockStreamError::ParseError(e.to_string())),
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        service.record_failure(&credential_name);
+                        yield Err(BedrockStreamError::Backend(azure_backend_error(e)));
+                        break;
+                    }
+                };
+                match converted {
+                    Ok(events) => {
+                        for event in events {
+                            yield Ok(event);
+                        }
+                    }
+                    Err(e) => {
+                        yield Err(BedrockStreamError::ParseError(e.to_string()));
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(ConverseStreamResponse::from_events(events))
+    }
+
+    fn health_check(&self) -> bool {
+        AzureOpenAIService::health_check(self)
+    }
+}
+
+/// Classify an Azure OpenAI failure like the equivalent Bedrock error
+fn azure_backend_error(err: AzureOpenAIServiceError) -> BedrockError {
+    match err {
+        AzureOpenAIServiceError::ApiError { status, message, .. } => match status {
+            400 | 413 => BedrockError::ValidationError(message),
+            401 | 403 => BedrockError::AccessDenied(message),
+            404 => BedrockError::ModelNotFound(message),
+            429 => BedrockError::Throttled(message),
+            500 => BedrockError::InternalError(message),
+            _ if status > 500 => BedrockError::ServiceUnavailable(message),
+            _ => BedrockError::Unknown(message),
+        },
+        AzureOpenAIServiceError::AuthError(message) => BedrockError::AccessDenied(message),
+        AzureOpenAIServiceError::ParseError(message) => BedrockError::Deserialization(message),
+        AzureOpenAIServiceError::NoAvailableCredentials
+        | AzureOpenAIServiceError::MissingCredentials
+        | AzureOpenAIServiceError::HttpError(_)
+        | AzureOpenAIServiceError::StreamError(_) => {
+            BedrockError::ServiceUnavailable(err.to_string())
+        }
+    }
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Backends available to handlers, keyed by routing target
+///
+/// Bedrock profiles register as `bedrock:<name>` next to the default
+/// `bedrock` client, matching how [`BackendTarget`] displays them. Hooks
+/// registered with [`add_hook`](Self::add_hook) wrap the backends they apply
+/// to on lookup, and with a PII tokenizer set every backend is wrapped in a
+/// [`PiiTokenizingBackend`] outside the hooks, so hooks only see tokens.
+#[derive(Default, Clone)]
+pub struct BackendRegistry {
+    backends: HashMap<String, Arc<dyn Backend>>,
+    /// Hooks with the target they are scoped to (None for every backend)
+    hooks: Vec<(Option<String>, Arc<dyn BackendHook>)>,
+    pii_tokenizer: Option<Arc<PiiTokenizer>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register (or replace) the backend serving a target
+    pub fn register(&mut self, target: &BackendTarget, backend: Arc<dyn Backend>) {
+        self.backends.insert(target.to_string(), backend);
+    }
+
+    /// Run a hook around calls to one target, or to every backend for None
+    ///
+    /// A hook scoped to `bedrock` also applies to the named Bedrock profiles.
+    pub fn add_hook(&mut self, scope: Option<&BackendTarget>, hook: Arc<dyn BackendHook>) {
+        self.hooks.push((scope.map(|t| t.to_string()), hook));
+    }
+
+    /// Replace PII with tokens in requests to every backend
+    pub fn set_pii_tokenizer(&mut self, tokenizer: Arc<PiiTokenizer>) {
+        self.pii_tokenizer = Some(tokenizer);
+    }
+
+    /// Backend serving a target, if one is registered
+    pub fn get(&self, target: &BackendTarget) -> Option<Arc<dyn Backend>> {
+        let key = target.to_string();
+        let mut backend = self.backends.get(&key).cloned()?;
+
+        let hooks: Vec<_> = self
+            .hooks
+            .iter()
+            .filter(|(scope, _)| match scope {
+                None => true,
+                Some(scope) => *scope == key || scope == target.kind(),
+            })
+            .map(|(_, hook)| hook.clone())
+            .collect();
+        if !hooks.is_empty() {
+            backend = Arc::new(HookedBackend::new(backend, key, hooks));
+        }
+        if let Some(ref tokenizer) = self.pii_tokenizer {
+            backend = Arc::new(PiiTokenizingBackend::new(backend, tokenizer.clone()));
+        }
+        Some(backend)
+    }
+
+    /// Whether a backend is registered for a target
+    pub fn contains(&self, target: &BackendTarget) -> bool {
+        self.backends.contains_key(&target.to_string())
+    }
+
+    /// Health of every registered backend by target
+    pub fn health_status(&self) -> HashMap<String, bool> {
+        self.backends
+            .iter()
+            .map(|(target, backend)| (target.clone(), backend.health_check()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.backends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{ConversationRole, Message};
+
+    struct StubBackend;
+
+    #[async_trait]
+    impl Backend for StubBackend {
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities {
+                service_tiers: false,
+                native_token_counting: false,
+                prompt_caching: false,
+                additional_fields: false,
+            }
+        }
+
+        fn resolve_model_id(&self, model: &str) -> String {
+            model.to_string()
+        }
+
+        async fn converse(&self, _: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+            Err(BedrockError::ServiceUnavailable("stub".to_string()))
+        }
+
+        async fn converse_stream(
+            &self,
+            _: ConverseRequest,
+        ) -> Result<ConverseStreamResponse, BedrockError> {
+            Err(BedrockError::ServiceUnavailable("stub".to_string()))
+        }
+
+        fn health_check(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_registry_keys_bedrock_profiles_separately() {
+        let mut registry = BackendRegistry::new();
+        let east = BackendTarget::Bedrock {
+            profile: Some("east".to_string()),
+        };
+        registry.register(&east, Arc::new(StubBackend));
+        registry.register(&BackendTarget::Gemini, Arc::new(StubBackend));
+
+        assert!(registry.get(&east).is_some());
+        assert!(registry.get(&BackendTarget::Gemini).is_some());
+        assert!(registry
+            .get(&BackendTarget::Bedrock { profile: None })
+            .is_none());
+        assert_eq!(registry.health_status().get("bedrock:east"), Some(&true));
+    }
+
+    struct NoopHook;
+
+    #[async_trait]
+    impl BackendHook for NoopHook {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scoped_hooks_wrap_matching_backends() {
+        let east = BackendTarget::Bedrock {
+            profile: Some("east".to_string()),
+        };
+        let mut registry = BackendRegistry::new();
+        registry.register(&east, Arc::new(StubBackend));
+        registry.register(&BackendTarget::Gemini, Arc::new(StubBackend));
+        registry.add_hook(
+            Some(&BackendTarget::Bedrock { profile: None }),
+            Arc::new(NoopHook),
+        );
+
+        // Wrapped backends still report the inner backend
+        let hooked = registry.get(&east).unwrap();
+        assert_eq!(hooked.name(), "stub");
+        let err = hooked
+            .converse(ConverseRequest::new("model"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BedrockError::ServiceUnavailable(_)));
+        assert!(registry.get(&BackendTarget::Gemini).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_default_count_tokens_estimates() {
+        let request = ConverseRequest::new("stub").with_message(
+            Message::builder()
+                .role(ConversationRole::User)
+                .content(ContentBlock::Text("a".repeat(400)))
+                .build()
+                .unwrap(),
+        );
+
+        assert_eq!(StubBackend.count_tokens(&request).await.unwrap(), 100);
+    }
+
+    #[test]
+    fn test_system_fingerprint_tracks_configuration() {
+        let fingerprint = system_fingerprint("bedrock", "anthropic.claude", Some("us-east-1"));
+
+        assert!(fingerprint.starts_with("fp_"));
+        assert_eq!(
+            fingerprint,
+            system_fingerprint("bedrock", "anthropic.claude", Some("us-east-1"))
+        );
+        assert_ne!(
+            fingerprint,
+            system_fingerprint("bedrock", "anthropic.claude", Some("us-west-2"))
+        );
+    }
+
+    #[cfg(feature = "gemini")]
+    #[test]
+    fn test_gemini_errors_map_to_bedrock_classes() {
+        let throttled = backend_error(GeminiServiceError::ApiError {
+            code: 429,
+            message: "quota".to_string(),
+        });
+        assert!(matches!(throttled, BedrockError::Throttled(_)));
+        let invalid = backend_error(GeminiServiceError::ApiError {
+            code: 400,
+            message: "bad".to_string(),
+        });
+        assert!(matches!(invalid, BedrockError::ValidationError(_)));
+    }
+}
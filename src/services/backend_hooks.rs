@@ -0,0 +1,346 @@
+//! Hooks around backend calls
+//!
+//! A [`BackendHook`] runs before a request is sent to a [`Backend`], after a
+//! non-streaming response is received, and when a call fails. Hooks are
+//! registered on the [`BackendRegistry`](crate::services::BackendRegistry),
+//! either for every backend or for one routing target, and wrap the backend
+//! in a [`HookedBackend`] when it is looked up. This keeps cross-cutting
+//! concerns (payload logging, provider workarounds, request tweaks) out of
+//! the individual services.
+//!
+//! For streams, `before_send` and `on_error` apply to opening the stream;
+//! the events pass through untouched unless a hook wraps them in
+//! `wrap_stream` (the stream recorder does, to capture them).
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use std::sync::Arc;
+
+use crate::services::backend::{Backend, BackendCapabilities};
+use crate::services::bedrock::{BedrockError, ConverseRequest, ConverseStreamResponse};
+
+/// What a hook knows about the call it is observing
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    /// Backend name, e.g. "bedrock"
+    pub backend: &'static str,
+    /// Routing target the backend is registered under, e.g. "bedrock:east"
+    pub target: String,
+    /// Whether the call streams
+    pub streaming: bool,
+}
+
+/// Middleware around backend invocation
+///
+/// Every method has a no-op default, so a hook only implements the points it
+/// cares about. Returning an error from `before_send` aborts the call.
+#[async_trait]
+pub trait BackendHook: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Inspect or modify a request before it is sent
+    async fn before_send(
+        &self,
+        _ctx: &HookContext,
+        _request: &mut ConverseRequest,
+    ) -> Result<(), BedrockError> {
+        Ok(())
+    }
+
+    /// Inspect or modify a non-streaming response
+    async fn after_receive(&self, _ctx: &HookContext, _output: &mut ConverseOutput) {}
+
+    /// Observe a failed call
+    async fn on_error(&self, _ctx: &HookContext, _error: &BedrockError) {}
+
+    /// Wrap the event stream of a successfully opened streaming call
+    fn wrap_stream(
+        &self,
+        _ctx: &HookContext,
+        _model_id: &str,
+        stream: ConverseStreamResponse,
+    ) -> ConverseStreamResponse {
+        stream
+    }
+}
+
+/// A backend with hooks run around each call
+pub struct HookedBackend {
+    inner: Arc<dyn Backend>,
+    target: String,
+    hooks: Vec<Arc<dyn BackendHook>>,
+}
+
+impl HookedBackend {
+    pub fn new(
+        inner: Arc<dyn Backend>,
+        target: impl Into<String>,
+        hooks: Vec<Arc<dyn BackendHook>>,
+    ) -> Self {
+        Self {
+            inner,
+            target: target.into(),
+            hooks,
+        }
+    }
+
+    fn context(&self, streaming: bool) -> HookContext {
+        HookContext {
+            backend: self.inner.name(),
+            target: self.target.clone(),
+            streaming,
+        }
+    }
+
+    async fn before_send(
+        &self,
+        ctx: &HookContext,
+        request: &mut ConverseRequest,
+    ) -> Result<(), BedrockError> {
+        for hook in &self.hooks {
+            if let Err(e) = hook.before_send(ctx, request).await {
+                tracing::debug!(hook = hook.name(), target = %ctx.target, error = %e, "Backend hook rejected request");
+                self.on_error(ctx, &e).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_error(&self, ctx: &HookContext, error: &BedrockError) {
+        for hook in &self.hooks {
+            hook.on_error(ctx, error).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for HookedBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn resolve_model_id(&self, model: &str) -> String {
+        self.inner.resolve_model_id(model)
+    }
+
+    async fn converse(&self, mut request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        let ctx = self.context(false);
+        self.before_send(&ctx, &mut request).await?;
+
+        match self.inner.converse(request).await {
+            Ok(mut output) => {
+                for hook in &self.hooks {
+                    hook.after_receive(&ctx, &mut output).await;
+                }
+                Ok(output)
+            }
+            Err(e) => {
+                self.on_error(&ctx, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn converse_stream(
+        &self,
+        mut request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        let ctx = self.context(true);
+        self.before_send(&ctx, &mut request).await?;
+
+        let model_id = request.model_id.clone();
+        match self.inner.converse_stream(request).await {
+            Ok(stream) => Ok(self.hooks.iter().fold(stream, |stream, hook| {
+                hook.wrap_stream(&ctx, &model_id, stream)
+            })),
+            Err(e) => {
+                self.on_error(&ctx, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn count_tokens(&self, request: &ConverseRequest) -> Result<i32, BedrockError> {
+        self.inner.count_tokens(request).await
+    }
+
+    fn health_check(&self) -> bool {
+        self.inner.health_check()
+    }
+
+    fn region(&self) -> Option<String> {
+        self.inner.region()
+    }
+}
+
+// ============================================================================
+// Built-in Hooks
+// ============================================================================
+
+/// Logs each backend request and response (LOG_BACKEND_PAYLOADS)
+///
+/// Payloads are logged at debug level with their Converse debug form, so
+/// image and document bytes are included; enable only for troubleshooting.
+pub struct PayloadLogHook;
+
+#[async_trait]
+impl BackendHook for PayloadLogHook {
+    fn name(&self) -> &'static str {
+        "payload_log"
+    }
+
+    async fn before_send(
+        &self,
+        ctx: &HookContext,
+        request: &mut ConverseRequest,
+    ) -> Result<(), BedrockError> {
+        tracing::debug!(
+            target = %ctx.target,
+            model_id = %request.model_id,
+            streaming = ctx.streaming,
+            messages = ?request.messages,
+            system = ?request.system,
+            inference_config = ?request.inference_config,
+            tool_config = ?request.tool_config,
+            "Backend request payload"
+        );
+        Ok(())
+    }
+
+    async fn after_receive(&self, ctx: &HookContext, output: &mut ConverseOutput) {
+        tracing::debug!(
+            target = %ctx.target,
+            output = ?output.output(),
+            stop_reason = ?output.stop_reason(),
+            usage = ?output.usage(),
+            "Backend response payload"
+        );
+    }
+
+    async fn on_error(&self, ctx: &HookContext, error: &BedrockError) {
+        tracing::debug!(target = %ctx.target, error = %error, "Backend call failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FailingBackend;
+
+    #[async_trait]
+    impl Backend for FailingBackend {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities {
+                service_tiers: false,
+                native_token_counting: false,
+                prompt_caching: false,
+                additional_fields: false,
+            }
+        }
+
+        fn resolve_model_id(&self, model: &str) -> String {
+            model.to_string()
+        }
+
+        async fn converse(&self, request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+            Err(BedrockError::ValidationError(request.model_id))
+        }
+
+        async fn converse_stream(
+            &self,
+            request: ConverseRequest,
+        ) -> Result<ConverseStreamResponse, BedrockError> {
+            Err(BedrockError::ValidationError(request.model_id))
+        }
+
+        fn health_check(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Default)]
+    struct RewriteHook {
+        errors: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl BackendHook for RewriteHook {
+        fn name(&self) -> &'static str {
+            "rewrite"
+        }
+
+        async fn before_send(
+            &self,
+            _ctx: &HookContext,
+            request: &mut ConverseRequest,
+        ) -> Result<(), BedrockError> {
+            request.model_id = format!("{}-patched", request.model_id);
+            Ok(())
+        }
+
+        async fn on_error(&self, _ctx: &HookContext, _error: &BedrockError) {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_modify_request_and_observe_errors() {
+        let hook = Arc::new(RewriteHook::default());
+        let backend = HookedBackend::new(Arc::new(FailingBackend), "failing", vec![hook.clone()]);
+
+        let err = backend
+            .converse(ConverseRequest::new("model"))
+            .await
+            .unwrap_err();
+
+        // The backend saw the rewritten model ID
+        assert!(matches!(err, BedrockError::ValidationError(ref m) if m == "model-patched"));
+        assert_eq!(hook.errors.load(Ordering::SeqCst), 1);
+    }
+
+    struct RejectHook;
+
+    #[async_trait]
+    impl BackendHook for RejectHook {
+        fn name(&self) -> &'static str {
+            "reject"
+        }
+
+        async fn before_send(
+            &self,
+            _ctx: &HookContext,
+            _request: &mut ConverseRequest,
+        ) -> Result<(), BedrockError> {
+            Err(BedrockError::AccessDenied("blocked".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_before_send_error_aborts_call() {
+        let backend = HookedBackend::new(
+            Arc::new(FailingBackend),
+            "failing",
+            vec![Arc::new(RejectHook)],
+        );
+
+        let err = backend
+            .converse_stream(ConverseRequest::new("model"))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, BedrockError::AccessDenied(_)));
+    }
+}
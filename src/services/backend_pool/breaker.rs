@@ -0,0 +1,162 @@
+//! Circuit breaker for pooled credentials
+//!
+//! A credential's circuit is closed while its requests succeed. Once it
+//! fails too many times in a row the circuit opens and the credential leaves
+//! rotation for the retry period. After that the pool sends it one request
+//! at a time as a canary (half-open): enough canary successes close the
+//! circuit, and a canary failure opens it for another period.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// State of a credential's circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// In rotation
+    Closed,
+    /// Out of rotation until the retry period ends
+    Open,
+    /// Taking canary requests, one at a time
+    HalfOpen,
+}
+
+impl std::fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Closed/open/half-open state machine for one credential
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: BreakerState,
+    /// When the circuit last opened
+    opened_at: Option<Instant>,
+    /// When the canary in flight was sent, if one is
+    probe_started: Option<Instant>,
+    /// Canary successes since the circuit went half-open
+    probe_successes: u32,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            opened_at: None,
+            probe_started: None,
+            probe_successes: 0,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    /// Take the credential out of rotation
+    pub fn open(&mut self, now: Instant) {
+        *self = Self {
+            state: BreakerState::Open,
+            opened_at: Some(now),
+            ..Self::default()
+        };
+    }
+
+    /// Claim the next canary request, if one is due
+    ///
+    /// An open circuit goes half-open once `open_for` has passed. A canary
+    /// that never reports back frees its slot after `probe_timeout`.
+    pub fn try_probe(&mut self, now: Instant, open_for: Duration, probe_timeout: Duration) -> bool {
+        let due = match self.state {
+            BreakerState::Closed => false,
+            BreakerState::Open => self
+                .opened_at
+                .map_or(true, |at| now.saturating_duration_since(at) >= open_for),
+            BreakerState::HalfOpen => self
+                .probe_started
+                .map_or(true, |at| now.saturating_duration_since(at) >= probe_timeout),
+        };
+        if due {
+            if self.state == BreakerState::Open {
+                self.state = BreakerState::HalfOpen;
+                self.probe_successes = 0;
+            }
+            self.probe_started = Some(now);
+        }
+        due
+    }
+
+    /// Record a successful request; returns true when it closed the circuit
+    pub fn on_success(&mut self, probe_successes: u32) -> bool {
+        if self.state == BreakerState::Closed {
+            return false;
+        }
+        self.probe_started = None;
+        self.probe_successes += 1;
+        if self.probe_successes >= probe_successes.max(1) {
+            *self = Self::default();
+            return true;
+        }
+        false
+    }
+
+    /// Record a failed canary; returns true when it reopened the circuit
+    pub fn on_failure(&mut self, now: Instant) -> bool {
+        if self.state != BreakerState::HalfOpen {
+            return false;
+        }
+        self.open(now);
+        true
+    }
+
+    /// Close the circuit
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_transitions() {
+        let open_for = Duration::from_secs(300);
+        let probe_timeout = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::default();
+        assert!(!breaker.try_probe(start, open_for, probe_timeout));
+
+        breaker.open(start);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.try_probe(start + Duration::from_secs(10), open_for, probe_timeout));
+
+        // One canary at a time
+        let later = start + open_for;
+        assert!(breaker.try_probe(later, open_for, probe_timeout));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.try_probe(later, open_for, probe_timeout));
+
+        // A failed canary reopens the circuit for another period
+        assert!(breaker.on_failure(later));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.try_probe(later + Duration::from_secs(10), open_for, probe_timeout));
+
+        let later = later + open_for;
+        assert!(breaker.try_probe(later, open_for, probe_timeout));
+        assert!(!breaker.on_success(2));
+        assert!(breaker.try_probe(later, open_for, probe_timeout));
+        // A canary that never reported back frees the slot
+        assert!(breaker.try_probe(later + probe_timeout, open_for, probe_timeout));
+        assert!(breaker.on_success(2));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(!breaker.on_failure(later));
+    }
+}
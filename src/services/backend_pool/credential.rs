@@ -5,7 +5,10 @@
 
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::time::Instant;
+use std::sync::{MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::breaker::{BreakerState, CircuitBreaker};
 
 // ============================================================================
 // Credential Health
@@ -22,6 +25,10 @@ pub struct CredentialHealth {
     last_failure: std::sync::Mutex<Option<Instant>>,
     /// Last success timestamp
     last_success: std::sync::Mutex<Option<Instant>>,
+    /// Rate-limited (quota exhausted) until this instant
+    cooldown_until: std::sync::Mutex<Option<Instant>>,
+    /// Circuit opened by repeated failures
+    breaker: std::sync::Mutex<CircuitBreaker>,
 }
 
 impl Default for CredentialHealth {
@@ -37,6 +44,8 @@ impl CredentialHealth {
             failure_count: AtomicU32::new(0),
             last_failure: std::sync::Mutex::new(None),
             last_success: std::sync::Mutex::new(None),
+            cooldown_until: std::sync::Mutex::new(None),
+            breaker: std::sync::Mutex::new(CircuitBreaker::default()),
         }
     }
 
@@ -69,6 +78,39 @@ impl CredentialHealth {
     pub fn reset(&self) {
         self.failure_count.store(0, Ordering::SeqCst);
         self.enabled.store(true, Ordering::SeqCst);
+        if let Ok(mut until) = self.cooldown_until.lock() {
+            *until = None;
+        }
+        self.breaker().reset();
+    }
+
+    /// The credential's circuit breaker
+    pub fn breaker(&self) -> MutexGuard<'_, CircuitBreaker> {
+        self.breaker.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn breaker_state(&self) -> BreakerState {
+        self.breaker().state()
+    }
+
+    /// Keep the credential out of rotation for `duration` (quota exhausted)
+    pub fn start_cooldown(&self, duration: Duration) {
+        if let Ok(mut until) = self.cooldown_until.lock() {
+            *until = Some(Instant::now() + duration);
+        }
+    }
+
+    /// Time left until the credential's quota is expected back, if rate limited
+    pub fn cooldown_remaining(&self) -> Option<Duration> {
+        let until = (*self.cooldown_until.lock().ok()?)?;
+        until
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Whether the credential is rate limited right now
+    pub fn in_cooldown(&self) -> bool {
+        self.cooldown_remaining().is_some()
     }
 
     /// Check if enough time has passed since last failure for retry
@@ -354,6 +396,107 @@ impl Credential for AwsCredential {
     }
 }
 
+// ============================================================================
+// Azure Credential
+// ============================================================================
+
+/// How requests to an Azure OpenAI resource are authenticated
+#[derive(Debug, Clone)]
+pub enum AzureAuth {
+    /// Resource key sent in the `api-key` header
+    ApiKey(String),
+    /// Microsoft Entra ID (Azure AD) service principal, exchanged for a
+    /// bearer token with the client credentials flow
+    EntraId {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+/// Azure OpenAI resource credential
+#[derive(Debug)]
+pub struct AzureCredential {
+    /// Credential name for identification
+    name: String,
+    /// Resource endpoint, e.g. https://my-resource.openai.azure.com
+    endpoint: String,
+    /// Key or Entra ID authentication
+    auth: AzureAuth,
+    /// Weight for load balancing
+    weight: u32,
+    /// Health status
+    health: CredentialHealth,
+}
+
+impl AzureCredential {
+    /// Create a credential authenticated with a resource key
+    pub fn with_api_key(
+        endpoint: impl Into<String>,
+        api_key: impl Into<String>,
+        name: impl Into<String>,
+        weight: u32,
+    ) -> Self {
+        Self::new(endpoint, AzureAuth::ApiKey(api_key.into()), name, weight)
+    }
+
+    /// Create a credential authenticated with an Entra ID service principal
+    pub fn with_entra_id(
+        endpoint: impl Into<String>,
+        tenant_id: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        name: impl Into<String>,
+        weight: u32,
+    ) -> Self {
+        let auth = AzureAuth::EntraId {
+            tenant_id: tenant_id.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+        };
+        Self::new(endpoint, auth, name, weight)
+    }
+
+    fn new(endpoint: impl Into<String>, auth: AzureAuth, name: impl Into<String>, weight: u32) -> Self {
+        Self {
+            name: name.into(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            auth,
+            weight,
+            health: CredentialHealth::new(),
+        }
+    }
+
+    /// Get the resource endpoint (without trailing slash)
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Get the authentication method
+    pub fn auth(&self) -> &AzureAuth {
+        &self.auth
+    }
+
+    /// Check if this uses Entra ID auth
+    pub fn uses_entra_id(&self) -> bool {
+        matches!(self.auth, AzureAuth::EntraId { .. })
+    }
+}
+
+impl Credential for AzureCredential {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    fn health(&self) -> &CredentialHealth {
+        &self.health
+    }
+}
+
 // ============================================================================
 // Configuration Structures (for deserialization)
 // ============================================================================
@@ -498,4 +641,22 @@ mod tests {
         assert!(cred.uses_access_key());
         assert!(!cred.uses_profile());
     }
+
+    #[test]
+    fn test_azure_credential() {
+        let cred = AzureCredential::with_api_key("https://res.openai.azure.com/", "key", "azure_1", 1);
+        assert_eq!(cred.endpoint(), "https://res.openai.azure.com");
+        assert!(!cred.uses_entra_id());
+
+        let cred = AzureCredential::with_entra_id(
+            "https://res.openai.azure.com",
+            "tenant",
+            "client",
+            "secret",
+            "azure_2",
+            1,
+        );
+        assert_eq!(cred.name(), "azure_2");
+        assert!(cred.uses_entra_id());
+    }
 }
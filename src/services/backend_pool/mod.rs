@@ -6,7 +6,8 @@
 //! # Features
 //! - Generic credential pool supporting any backend type
 //! - Multiple load balancing strategies (RoundRobin, Weighted, Random, Failover)
-//! - Automatic health checking and credential disabling
+//! - Automatic health checking, with a circuit breaker per credential that
+//!   takes failing credentials out of rotation and probes them before they return
 //! - Backward compatible with single-credential configurations
 //!
 //! # Example
@@ -28,10 +29,14 @@
 //! }
 //! ```
 
+mod breaker;
 mod credential;
 mod pool;
 mod strategy;
 
-pub use credential::{ApiKeyCredential, AwsCredential, Credential, CredentialHealth};
-pub use pool::{CredentialPool, PoolConfig, PoolStats};
+pub use credential::{
+    ApiKeyCredential, AwsCredential, AzureAuth, AzureCredential, Credential, CredentialHealth,
+};
+pub use breaker::{BreakerState, CircuitBreaker};
+pub use pool::{CredentialBreaker, CredentialPool, FailoverPolicy, PoolConfig, PoolStats};
 pub use strategy::LoadBalanceStrategy;
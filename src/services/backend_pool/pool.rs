@@ -1,26 +1,62 @@
 //! Credential Pool Implementation
 //!
 //! This module provides the generic `CredentialPool` that manages multiple
-//! credentials with load balancing and health checking.
+//! credentials with load balancing and health checking. Credentials that keep
+//! failing are taken out of rotation by their circuit breaker (see
+//! [`super::breaker`]) and probed with canary requests before they return.
 
+use super::breaker::BreakerState;
 use super::credential::Credential;
 use super::strategy::{LoadBalanceStrategy, RoundRobinState, WeightedState};
 use rand::prelude::*;
+use serde::Serialize;
 use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// A canary that never reports back frees its credential's slot after this long
+const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
 
 // ============================================================================
 // Pool Configuration
 // ============================================================================
 
+/// When a request that failed on one credential is retried on another
+///
+/// Only failures that say nothing about the request itself (throttling,
+/// capacity) are retried; it is up to the service using the pool to decide
+/// which those are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverPolicy {
+    /// Credentials a request may try in total (1 = no failover)
+    pub max_attempts: u32,
+    /// Seconds a credential sits out after a request failed over away from it
+    pub cooldown_secs: u64,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 2,
+            cooldown_secs: 30,
+        }
+    }
+}
+
 /// Configuration for credential pool behavior
 #[derive(Debug, Clone)]
 pub struct PoolConfig {
     /// Load balancing strategy
     pub strategy: LoadBalanceStrategy,
-    /// Maximum failures before disabling a credential
+    /// Consecutive failures that open a credential's circuit
     pub max_failures: u32,
-    /// Seconds to wait before retrying a disabled credential
+    /// Seconds a credential's circuit stays open before canary requests probe it
     pub retry_after_secs: u64,
+    /// Canary successes that close an open circuit again
+    pub probe_successes: u32,
+    /// Seconds a rate-limited credential sits out when the backend gives no retry delay
+    pub rate_limit_cooldown_secs: u64,
+    /// Retrying failed requests on other credentials
+    pub failover: FailoverPolicy,
 }
 
 impl Default for PoolConfig {
@@ -29,6 +65,9 @@ impl Default for PoolConfig {
             strategy: LoadBalanceStrategy::RoundRobin,
             max_failures: 3,
             retry_after_secs: 300, // 5 minutes
+            probe_successes: 2,
+            rate_limit_cooldown_secs: 60,
+            failover: FailoverPolicy::default(),
         }
     }
 }
@@ -50,6 +89,21 @@ impl PoolConfig {
         self.retry_after_secs = secs;
         self
     }
+
+    pub fn with_probe_successes(mut self, successes: u32) -> Self {
+        self.probe_successes = successes;
+        self
+    }
+
+    pub fn with_rate_limit_cooldown(mut self, secs: u64) -> Self {
+        self.rate_limit_cooldown_secs = secs;
+        self
+    }
+
+    pub fn with_failover(mut self, failover: FailoverPolicy) -> Self {
+        self.failover = failover;
+        self
+    }
 }
 
 // ============================================================================
@@ -107,22 +161,37 @@ impl<C: Credential> CredentialPool<C> {
 
     /// Get the next available credential based on the load balancing strategy
     pub fn get_next(&self) -> Option<&C> {
+        self.get_next_except(&[])
+    }
+
+    /// Like [`get_next`](Self::get_next), never picking the named credentials
+    ///
+    /// Used to fail a request over to a credential it has not tried yet; with
+    /// a non-empty `exclude` there is no last-resort pick, so this returns
+    /// None once no other credential is usable.
+    pub fn get_next_except(&self, exclude: &[&str]) -> Option<&C> {
         if self.credentials.is_empty() {
             return None;
         }
 
+        // A credential whose circuit has been open long enough gets a canary
+        if let Some(cred) = self.claim_probe(exclude) {
+            return Some(cred);
+        }
+
         // Get list of healthy credentials
         let healthy_indices: Vec<usize> = self
             .credentials
             .iter()
             .enumerate()
+            .filter(|(_, c)| !exclude.contains(&c.name()))
             .filter(|(_, c)| self.is_credential_available(c))
             .map(|(i, _)| i)
             .collect();
 
         if healthy_indices.is_empty() {
             // Try to recover a disabled credential
-            return self.try_recover_credential();
+            return self.try_recover_credential(exclude);
         }
 
         let idx = match self.config.strategy {
@@ -201,24 +270,50 @@ impl<C: Credential> CredentialPool<C> {
         self.credentials.iter().filter(|c| !c.is_enabled()).count()
     }
 
+    /// Get the number of credentials whose circuit is open or half-open
+    pub fn tripped_count(&self) -> usize {
+        self.credentials
+            .iter()
+            .filter(|c| c.health().breaker_state() != BreakerState::Closed)
+            .count()
+    }
+
+    /// State of a credential's circuit breaker
+    pub fn breaker_state(&self, name: &str) -> Option<BreakerState> {
+        self.get_by_name(name).map(|c| c.health().breaker_state())
+    }
+
     /// Record a successful request for a credential
+    ///
+    /// Closes the credential's circuit once enough canaries have succeeded.
     pub fn record_success(&self, name: &str) {
         if let Some(cred) = self.credentials.iter().find(|c| c.name() == name) {
             cred.record_success();
+            if cred.health().breaker().on_success(self.config.probe_successes) {
+                tracing::info!(credential = name, "Credential circuit closed, back in rotation");
+            }
         }
     }
 
     /// Record a failed request for a credential
-    /// Returns true if the credential was disabled due to max failures
+    /// Returns true if the failure opened the credential's circuit
     pub fn record_failure(&self, name: &str) -> bool {
         if let Some(cred) = self.credentials.iter().find(|c| c.name() == name) {
             cred.record_failure();
-            if cred.failure_count() >= self.config.max_failures {
-                cred.disable();
+            let mut breaker = cred.health().breaker();
+            if breaker.on_failure(Instant::now()) {
+                tracing::warn!(credential = name, "Canary request failed, credential circuit reopened");
+                return true;
+            }
+            if breaker.state() == BreakerState::Closed
+                && cred.failure_count() >= self.config.max_failures
+            {
+                breaker.open(Instant::now());
                 tracing::warn!(
                     credential = name,
                     failures = cred.failure_count(),
-                    "Credential disabled due to max failures"
+                    open_secs = self.config.retry_after_secs,
+                    "Credential circuit opened due to max failures"
                 );
                 return true;
             }
@@ -226,6 +321,48 @@ impl<C: Credential> CredentialPool<C> {
         false
     }
 
+    /// Record that a credential's quota is exhausted (HTTP 429)
+    ///
+    /// The credential sits out of rotation for `retry_after`, or the configured
+    /// cooldown when the backend gives no delay. Quota exhaustion says nothing
+    /// about the credential being broken, so it does not count as a failure.
+    pub fn record_rate_limited(&self, name: &str, retry_after: Option<Duration>) {
+        if let Some(cred) = self.credentials.iter().find(|c| c.name() == name) {
+            let cooldown = retry_after
+                .unwrap_or_else(|| Duration::from_secs(self.config.rate_limit_cooldown_secs));
+            cred.health().start_cooldown(cooldown);
+            tracing::warn!(
+                credential = name,
+                cooldown_secs = cooldown.as_secs_f64(),
+                "Credential rate limited, cooling down"
+            );
+        }
+    }
+
+    /// Policy for retrying failed requests on other credentials
+    pub fn failover_policy(&self) -> FailoverPolicy {
+        self.config.failover
+    }
+
+    /// Record that a request failed over away from a credential
+    ///
+    /// The credential sits out for the failover cooldown, so the requests
+    /// that follow go elsewhere too.
+    pub fn record_failover(&self, name: &str) {
+        if let Some(cred) = self.credentials.iter().find(|c| c.name() == name) {
+            cred.health()
+                .start_cooldown(Duration::from_secs(self.config.failover.cooldown_secs));
+        }
+    }
+
+    /// Get the number of credentials waiting for their quota to come back
+    pub fn cooling_down_count(&self) -> usize {
+        self.credentials
+            .iter()
+            .filter(|c| c.is_enabled() && c.health().in_cooldown())
+            .count()
+    }
+
     /// Manually disable a credential
     pub fn disable(&self, name: &str) {
         if let Some(cred) = self.credentials.iter().find(|c| c.name() == name) {
@@ -247,24 +384,54 @@ impl<C: Credential> CredentialPool<C> {
             total: self.credentials.len(),
             healthy: self.healthy_count(),
             disabled: self.disabled_count(),
+            cooling_down: self.cooling_down_count(),
+            tripped: self.tripped_count(),
+            breakers: self
+                .credentials
+                .iter()
+                .map(|c| CredentialBreaker {
+                    credential: c.name().to_string(),
+                    state: c.health().breaker_state(),
+                    failures: c.failure_count(),
+                })
+                .collect(),
             strategy: self.config.strategy,
         }
     }
 
-    /// Check if a credential is available (enabled and not at max failures)
+    /// Check if a credential is available (enabled, circuit closed, not rate limited)
     fn is_credential_available(&self, cred: &C) -> bool {
         if !cred.is_enabled() {
             // Disabled credentials are not available
             // They can only be re-enabled via try_recover_credential or manual enable()
             return false;
         }
-        cred.failure_count() < self.config.max_failures
+        cred.health().breaker_state() == BreakerState::Closed && !cred.health().in_cooldown()
+    }
+
+    /// Claim a canary request for a credential whose circuit is due a probe
+    fn claim_probe(&self, exclude: &[&str]) -> Option<&C> {
+        let now = Instant::now();
+        let open_for = Duration::from_secs(self.config.retry_after_secs);
+        let cred = self
+            .credentials
+            .iter()
+            .filter(|c| !exclude.contains(&c.name()))
+            .filter(|c| c.is_enabled() && !c.health().in_cooldown())
+            .find(|c| c.health().breaker().try_probe(now, open_for, PROBE_TIMEOUT))?;
+        tracing::info!(credential = cred.name(), "Sending canary request to credential with open circuit");
+        Some(cred)
     }
 
     /// Try to recover a disabled credential for use
-    fn try_recover_credential(&self) -> Option<&C> {
+    fn try_recover_credential(&self, exclude: &[&str]) -> Option<&C> {
+        let candidates = || {
+            self.credentials
+                .iter()
+                .filter(|c| !exclude.contains(&c.name()))
+        };
         // Find a disabled credential that's ready for retry
-        for cred in &self.credentials {
+        for cred in candidates() {
             if !cred.is_enabled() && cred.health().should_retry(self.config.retry_after_secs) {
                 tracing::info!(
                     credential = cred.name(),
@@ -274,8 +441,19 @@ impl<C: Credential> CredentialPool<C> {
                 return Some(cred);
             }
         }
+        // Every enabled credential is rate limited: use the one whose quota returns first
+        let soonest = candidates()
+            .filter(|c| c.is_enabled())
+            .filter_map(|c| c.health().cooldown_remaining().map(|left| (left, c)))
+            .min_by_key(|(left, _)| *left);
+        if let Some((_, cred)) = soonest {
+            return Some(cred);
+        }
         // Last resort: return the first credential even if it's unhealthy
-        self.credentials.first()
+        match exclude {
+            [] => self.credentials.first(),
+            _ => None,
+        }
     }
 }
 
@@ -292,10 +470,25 @@ pub struct PoolStats {
     pub healthy: usize,
     /// Number of disabled credentials
     pub disabled: usize,
+    /// Number of enabled credentials waiting out a rate limit
+    pub cooling_down: usize,
+    /// Number of credentials whose circuit is open or half-open
+    pub tripped: usize,
+    /// Circuit breaker state of each credential, in pool order
+    pub breakers: Vec<CredentialBreaker>,
     /// Current load balancing strategy
     pub strategy: LoadBalanceStrategy,
 }
 
+/// One credential's circuit breaker, as reported by [`CredentialPool::stats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialBreaker {
+    pub credential: String,
+    pub state: BreakerState,
+    /// Consecutive failures
+    pub failures: u32,
+}
+
 impl PoolStats {
     /// Check if the pool is healthy (at least one credential available)
     pub fn is_healthy(&self) -> bool {
@@ -374,9 +567,10 @@ mod tests {
         assert!(!pool.record_failure("primary"));
         assert_eq!(pool.get_by_name("primary").unwrap().failure_count(), 1);
 
-        // Second failure - should disable
+        // Second failure - should open the circuit
         assert!(pool.record_failure("primary"));
-        assert!(!pool.get_by_name("primary").unwrap().is_enabled());
+        assert_eq!(pool.breaker_state("primary"), Some(BreakerState::Open));
+        assert_eq!(pool.stats().tripped, 1);
 
         // Pool should now use secondary
         let selected = pool.get_next().unwrap();
@@ -411,6 +605,82 @@ mod tests {
         assert_eq!(stats.healthy, 2);
     }
 
+    #[test]
+    fn test_rate_limited_credential_sits_out() {
+        let pool = CredentialPool::failover(create_test_credentials());
+
+        pool.record_rate_limited("primary", Some(Duration::from_secs(30)));
+        let primary = pool.get_by_name("primary").unwrap();
+        assert!(primary.is_enabled());
+        assert_eq!(primary.failure_count(), 0);
+        assert_eq!(pool.get_next().unwrap().name(), "secondary");
+
+        let stats = pool.stats();
+        assert_eq!(stats.healthy, 2);
+        assert_eq!(stats.cooling_down, 1);
+
+        // With every key rate limited, the one whose quota returns first is used
+        pool.record_rate_limited("secondary", Some(Duration::from_secs(5)));
+        pool.record_rate_limited("backup", None);
+        assert_eq!(pool.get_next().unwrap().name(), "secondary");
+
+        pool.enable("primary");
+        assert_eq!(pool.get_next().unwrap().name(), "primary");
+    }
+
+    #[test]
+    fn test_get_next_except_for_failover() {
+        let pool = CredentialPool::failover(create_test_credentials());
+
+        assert_eq!(pool.get_next_except(&["primary"]).unwrap().name(), "secondary");
+        assert_eq!(
+            pool.get_next_except(&["primary", "secondary"]).unwrap().name(),
+            "backup"
+        );
+        assert!(pool.get_next_except(&["primary", "secondary", "backup"]).is_none());
+
+        // Failed-over credentials sit out, but stay usable as a failover target
+        pool.record_failover("primary");
+        assert!(pool.get_by_name("primary").unwrap().health().in_cooldown());
+        assert_eq!(pool.get_next().unwrap().name(), "secondary");
+        pool.record_failover("secondary");
+        pool.record_failover("backup");
+        assert!(pool.get_next_except(&["backup"]).is_some());
+    }
+
+    #[test]
+    fn test_open_circuit_is_probed_before_rejoining() {
+        let pool = CredentialPool::new(
+            create_test_credentials(),
+            PoolConfig::new(LoadBalanceStrategy::Failover)
+                .with_max_failures(1)
+                .with_retry_after(0)
+                .with_probe_successes(2),
+        );
+
+        assert!(pool.record_failure("primary"));
+        // The retry period is over: the next request is primary's canary,
+        // and only one canary is in flight at a time
+        assert_eq!(pool.get_next().unwrap().name(), "primary");
+        assert_eq!(pool.breaker_state("primary"), Some(BreakerState::HalfOpen));
+        assert_eq!(pool.get_next().unwrap().name(), "secondary");
+
+        // A failed canary reopens the circuit
+        assert!(pool.record_failure("primary"));
+        assert_eq!(pool.breaker_state("primary"), Some(BreakerState::Open));
+
+        assert_eq!(pool.get_next().unwrap().name(), "primary");
+        pool.record_success("primary");
+        assert_eq!(pool.breaker_state("primary"), Some(BreakerState::HalfOpen));
+        assert_eq!(pool.get_next().unwrap().name(), "primary");
+        pool.record_success("primary");
+
+        let stats = pool.stats();
+        assert_eq!(stats.tripped, 0);
+        assert_eq!(stats.healthy, 3);
+        assert!(stats.breakers.iter().all(|b| b.state == BreakerState::Closed));
+    }
+
     #[test]
     fn test_get_by_name() {
         let pool = CredentialPool::round_robin(create_test_credentials());
@@ -1,11 +1,14 @@
 //! Bedrock service for AWS Bedrock API interactions
 //!
 //! This module handles communication with AWS Bedrock for model inference.
-//! It uses the Converse API and ConverseStream API for all models.
+//! It uses the Converse API and ConverseStream API for all chat models, and
+//! InvokeModel for the Titan and Cohere embedding models.
 
 use aws_sdk_bedrockruntime::{
-    operation::converse::{ConverseError, ConverseOutput},
-    operation::converse_stream::ConverseStreamError,
+    operation::converse::{builders::ConverseFluentBuilder, ConverseError, ConverseOutput},
+    operation::converse_stream::{builders::ConverseStreamFluentBuilder, ConverseStreamError},
+    operation::invoke_model::InvokeModelError,
+    primitives::Blob,
     types::{
         ConverseStreamOutput, InferenceConfiguration, Message as BedrockMessage,
         SystemContentBlock, ToolConfiguration,
@@ -14,7 +17,9 @@ use aws_sdk_bedrockruntime::{
 };
 use aws_smithy_runtime_api::client::result::SdkError;
 use crate::config::Settings;
-use futures::Stream;
+use crate::services::bedrock_clients::{BedrockClientPool, ClientCacheStats};
+use futures::{Stream, StreamExt, TryStreamExt};
+use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -29,6 +34,9 @@ pub struct BedrockService {
 
     /// AWS Bedrock Runtime SDK client
     client: BedrockRuntimeClient,
+
+    /// Credential pool with one cached client per credential (None = `client` only)
+    credential_pool: Option<Arc<BedrockClientPool>>,
 }
 
 impl BedrockService {
@@ -38,7 +46,19 @@ impl BedrockService {
     /// * `settings` - Application settings containing AWS configuration
     /// * `client` - AWS Bedrock Runtime SDK client
     pub fn new(settings: Arc<Settings>, client: BedrockRuntimeClient) -> Self {
-        Self { settings, client }
+        Self {
+            settings,
+            client,
+            credential_pool: None,
+        }
+    }
+
+    /// Spread requests over a credential pool
+    ///
+    /// `client` remains the fallback when no pooled credential is available.
+    pub fn with_credential_pool(mut self, pool: BedrockClientPool) -> Self {
+        self.credential_pool = Some(Arc::new(pool));
+        self
     }
 
     /// Get a reference to the underlying AWS SDK client
@@ -46,6 +66,90 @@ impl BedrockService {
         &self.client
     }
 
+    /// The credential pool, if requests are spread over one
+    pub fn credential_pool(&self) -> Option<&BedrockClientPool> {
+        self.credential_pool.as_deref()
+    }
+
+    /// Per-credential client cache counters (None without a credential pool)
+    pub fn client_cache_stats(&self) -> Option<ClientCacheStats> {
+        self.credential_pool.as_ref().map(|pool| pool.stats())
+    }
+
+    /// Drop the cached per-credential clients, returning how many were held
+    pub fn flush_client_cache(&self) -> usize {
+        self.credential_pool.as_ref().map_or(0, |pool| pool.clear())
+    }
+
+    /// Client for the next request, with the pooled credential it belongs to
+    async fn request_client(&self) -> (BedrockRuntimeClient, Option<String>) {
+        if let Some(ref pool) = self.credential_pool {
+            if let Some((credential, client)) = pool.next_client().await {
+                return (client, Some(credential));
+            }
+            tracing::warn!("No pooled Bedrock credential available, using the default client");
+        }
+        (self.client.clone(), None)
+    }
+
+    /// Send `request` with [`request_client`](Self::request_client), failing
+    /// over to other pooled credentials while Bedrock throttles the request
+    /// or is unavailable, up to the pool's
+    /// [`FailoverPolicy`](crate::services::FailoverPolicy)
+    ///
+    /// The request is cloned only while another attempt may follow.
+    async fn send_with_failover<R, T, F, Fut>(
+        &self,
+        operation: &'static str,
+        request: R,
+        send: F,
+    ) -> Result<T, BedrockError>
+    where
+        R: Clone,
+        F: Fn(BedrockRuntimeClient, R) -> Fut,
+        Fut: Future<Output = Result<T, BedrockError>>,
+    {
+        let (client, credential) = self.request_client().await;
+        let (Some(pool), Some(mut credential)) = (self.credential_pool.as_deref(), credential) else {
+            return send(client, request).await;
+        };
+        let max_attempts = pool.failover_policy().max_attempts.max(1);
+
+        let mut tried = Vec::new();
+        let mut client = client;
+        loop {
+            if tried.len() + 1 >= max_attempts as usize {
+                let result = send(client, request).await;
+                pool.record_result(&credential, &result);
+                return result;
+            }
+            let result = send(client, request.clone()).await;
+            pool.record_result(&credential, &result);
+
+            if !matches!(result, Err(ref e) if e.triggers_failover()) {
+                return result;
+            }
+            tried.push(credential);
+            let Some((next, next_client)) = pool.failover_client(&tried).await else {
+                return result;
+            };
+            let from = tried.last().map(String::as_str).unwrap_or_default();
+            if let Err(ref e) = result {
+                tracing::warn!(
+                    operation,
+                    from = %from,
+                    to = %next,
+                    attempt = tried.len() + 1,
+                    error = %e,
+                    "Bedrock request failed, failing over to another credential"
+                );
+            }
+            pool.record_failover(from);
+            credential = next;
+            client = next_client;
+        }
+    }
+
     /// Get the Bedrock model ID for an Anthropic model ID
     ///
     /// This method looks up the mapping from Anthropic model IDs to Bedrock model ARNs.
@@ -86,31 +190,17 @@ impl BedrockService {
             "Calling Bedrock Converse API"
         );
 
-        let mut converse_request = self
-            .client
-            .converse()
-            .model_id(&model_id)
-            .set_messages(Some(request.messages));
-
-        // Set system prompts if provided
-        if let Some(system) = request.system {
-            converse_request = converse_request.set_system(Some(system));
-        }
-
-        // Set inference configuration if provided
-        if let Some(inference_config) = request.inference_config {
-            converse_request = converse_request.inference_config(inference_config);
-        }
-
-        // Set tool configuration if provided
-        if let Some(tool_config) = request.tool_config {
-            converse_request = converse_request.tool_config(tool_config);
-        }
-
-        let result = converse_request
-            .send()
-            .await
-            .map_err(BedrockError::from_converse_error)?;
+        let result = self
+            .send_with_failover("converse", request, |client, request| {
+                let converse_request = converse_input(&client, &model_id, request);
+                async move {
+                    converse_request
+                        .send()
+                        .await
+                        .map_err(BedrockError::from_converse_error)
+                }
+            })
+            .await?;
 
         tracing::debug!(
             stop_reason = ?result.stop_reason(),
@@ -136,43 +226,216 @@ impl BedrockService {
             "Calling Bedrock ConverseStream API"
         );
 
-        let mut converse_request = self
-            .client
-            .converse_stream()
-            .model_id(&model_id)
-            .set_messages(Some(request.messages));
+        let result = self
+            .send_with_failover("converse_stream", request, |client, request| {
+                let converse_request = converse_stream_input(&client, &model_id, request);
+                async move {
+                    converse_request
+                        .send()
+                        .await
+                        .map_err(BedrockError::from_converse_stream_error)
+                }
+            })
+            .await?;
+
+        tracing::debug!("Bedrock ConverseStream response initiated");
+
+        Ok(ConverseStreamResponse {
+            inner: StreamSource::Bedrock(result.stream),
+        })
+    }
+
+    /// Embed texts with a Titan or Cohere embedding model
+    ///
+    /// Inputs are split into the batches the model family accepts and the
+    /// batches are sent concurrently; embeddings come back in input order.
+    pub async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingOutput, BedrockError> {
+        let model_id = self.get_bedrock_model_id(&request.model_id);
+        let family = EmbeddingFamily::from_model_id(&model_id).ok_or_else(|| {
+            BedrockError::ValidationError(format!(
+                "'{}' is not a supported embedding model (amazon.titan-embed-*, cohere.embed-*)",
+                model_id
+            ))
+        })?;
+
+        tracing::debug!(
+            model_id = %model_id,
+            input_count = request.inputs.len(),
+            "Calling Bedrock InvokeModel for embeddings"
+        );
+
+        let parts: Vec<EmbeddingOutput> = futures::stream::iter(request.inputs.chunks(family.batch_size()))
+            .map(|batch| {
+                let body = family.request_body(batch, request.dimensions);
+                let model_id = &model_id;
+                async move {
+                    let response = self.invoke_model(model_id, &body).await?;
+                    family.parse_response(&response, batch)
+                }
+            })
+            .buffered(EMBEDDING_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        Ok(parts.into_iter().fold(EmbeddingOutput::default(), |mut output, part| {
+            output.embeddings.extend(part.embeddings);
+            output.input_tokens += part.input_tokens;
+            output
+        }))
+    }
 
-        // Set system prompts if provided
-        if let Some(system) = request.system {
-            converse_request = converse_request.set_system(Some(system));
+    /// Call InvokeModel with a JSON body, returning the raw response body
+    async fn invoke_model(
+        &self,
+        model_id: &str,
+        body: &serde_json::Value,
+    ) -> Result<Vec<u8>, BedrockError> {
+        let body = serde_json::to_vec(body).map_err(|e| BedrockError::Serialization(e.to_string()))?;
+
+        self.send_with_failover("invoke_model", body, |client, body| async move {
+            client
+                .invoke_model()
+                .model_id(model_id)
+                .content_type("application/json")
+                .accept("application/json")
+                .body(Blob::new(body))
+                .send()
+                .await
+                .map(|output| output.body.into_inner())
+                .map_err(BedrockError::from_invoke_model_error)
+        })
+        .await
+    }
+}
+
+// ============================================================================
+// Embeddings
+// ============================================================================
+
+/// InvokeModel calls in flight per embeddings request
+const EMBEDDING_CONCURRENCY: usize = 8;
+
+/// Bedrock embedding model family, which fixes the InvokeModel body format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingFamily {
+    /// `amazon.titan-embed-*`: one text per call, reports its token count
+    Titan,
+    /// `cohere.embed-*`: up to 96 texts per call, no token count
+    Cohere,
+}
+
+impl EmbeddingFamily {
+    /// Family of a Bedrock model ID, also in inference profile or ARN form
+    pub fn from_model_id(model_id: &str) -> Option<Self> {
+        if model_id.contains("amazon.titan-embed") {
+            Some(Self::Titan)
+        } else if model_id.contains("cohere.embed") {
+            Some(Self::Cohere)
+        } else {
+            None
         }
+    }
 
-        // Set inference configuration if provided
-        if let Some(inference_config) = request.inference_config {
-            converse_request = converse_request.inference_config(inference_config);
+    /// Texts per InvokeModel call
+    fn batch_size(self) -> usize {
+        match self {
+            Self::Titan => 1,
+            Self::Cohere => 96,
         }
+    }
 
-        // Set tool configuration if provided
-        if let Some(tool_config) = request.tool_config {
-            converse_request = converse_request.tool_config(tool_config);
+    fn request_body(self, texts: &[String], dimensions: Option<u32>) -> serde_json::Value {
+        let mut body = match self {
+            Self::Titan => serde_json::json!({ "inputText": texts[0] }),
+            Self::Cohere => serde_json::json!({
+                "texts": texts,
+                "input_type": "search_document",
+                "truncate": "END",
+            }),
+        };
+        if let Some(dimensions) = dimensions {
+            let field = match self {
+                Self::Titan => "dimensions",
+                Self::Cohere => "output_dimension",
+            };
+            body[field] = dimensions.into();
         }
+        body
+    }
 
-        // Set additional model request fields if provided
-        if let Some(additional_fields) = request.additional_model_request_fields {
-            converse_request = converse_request.additional_model_request_fields(additional_fields);
+    /// Read the embeddings of one batch
+    ///
+    /// Cohere reports no token count, so its usage is estimated from the
+    /// text (~4 characters per token).
+    fn parse_response(self, body: &[u8], texts: &[String]) -> Result<EmbeddingOutput, BedrockError> {
+        let parse_error = |e: serde_json::Error| BedrockError::Deserialization(e.to_string());
+        match self {
+            Self::Titan => {
+                #[derive(serde::Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct TitanResponse {
+                    embedding: Vec<f32>,
+                    #[serde(default)]
+                    input_text_token_count: i32,
+                }
+                let response: TitanResponse = serde_json::from_slice(body).map_err(parse_error)?;
+                Ok(EmbeddingOutput {
+                    embeddings: vec![response.embedding],
+                    input_tokens: response.input_text_token_count,
+                })
+            }
+            Self::Cohere => {
+                #[derive(serde::Deserialize)]
+                #[serde(untagged)]
+                enum CohereEmbeddings {
+                    Float(Vec<Vec<f32>>),
+                    ByType { float: Vec<Vec<f32>> },
+                }
+                #[derive(serde::Deserialize)]
+                struct CohereResponse {
+                    embeddings: CohereEmbeddings,
+                }
+                let response: CohereResponse = serde_json::from_slice(body).map_err(parse_error)?;
+                let embeddings = match response.embeddings {
+                    CohereEmbeddings::Float(embeddings) | CohereEmbeddings::ByType { float: embeddings } => {
+                        embeddings
+                    }
+                };
+                if embeddings.len() != texts.len() {
+                    return Err(BedrockError::Deserialization(format!(
+                        "expected {} embeddings, got {}",
+                        texts.len(),
+                        embeddings.len()
+                    )));
+                }
+                let input_tokens = texts.iter().map(|text| (text.len() / 4).max(1) as i32).sum();
+                Ok(EmbeddingOutput {
+                    embeddings,
+                    input_tokens,
+                })
+            }
         }
+    }
+}
 
-        let result = converse_request
-            .send()
-            .await
-            .map_err(BedrockError::from_converse_stream_error)?;
+/// Request for a Bedrock embedding model
+#[derive(Debug, Clone)]
+pub struct EmbeddingRequest {
+    /// Model ID (alias or Bedrock format)
+    pub model_id: String,
 
-        tracing::debug!("Bedrock ConverseStream response initiated");
+    /// Texts to embed
+    pub inputs: Vec<String>,
 
-        Ok(ConverseStreamResponse {
-            inner: result.stream,
-        })
-    }
+    /// Output dimensions, for models that support choosing them
+    pub dimensions: Option<u32>,
+}
+
+/// Embeddings in input order, with the prompt tokens they consumed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmbeddingOutput {
+    pub embeddings: Vec<Vec<f32>>,
+    pub input_tokens: i32,
 }
 
 /// Request for Bedrock Converse API
@@ -247,6 +510,41 @@ impl ConverseRequest {
     }
 }
 
+/// Build a Converse call from a request
+fn converse_input(
+    client: &BedrockRuntimeClient,
+    model_id: &str,
+    request: ConverseRequest,
+) -> ConverseFluentBuilder {
+    client
+        .converse()
+        .model_id(model_id)
+        .set_messages(Some(request.messages))
+        .set_system(request.system)
+        .set_inference_config(request.inference_config)
+        .set_tool_config(request.tool_config)
+        .set_additional_model_request_fields(request.additional_model_request_fields)
+}
+
+/// Build a ConverseStream call from a request
+///
+/// Mirrors [`converse_input`]; both run inside the failover closure, once
+/// per credential attempt.
+fn converse_stream_input(
+    client: &BedrockRuntimeClient,
+    model_id: &str,
+    request: ConverseRequest,
+) -> ConverseStreamFluentBuilder {
+    client
+        .converse_stream()
+        .model_id(model_id)
+        .set_messages(Some(request.messages))
+        .set_system(request.system)
+        .set_inference_config(request.inference_config)
+        .set_tool_config(request.tool_config)
+        .set_additional_model_request_fields(request.additional_model_request_fields)
+}
+
 // ============================================================================
 // Streaming Response Types
 // ============================================================================
@@ -257,21 +555,40 @@ use aws_sdk_bedrockruntime::types::error::ConverseStreamOutputError;
 /// Wrapper for Bedrock ConverseStream response
 ///
 /// This struct wraps the AWS SDK's EventReceiver to provide a more
-/// ergonomic API for consuming streaming events.
+/// ergonomic API for consuming streaming events. Backends that adapt another
+/// provider to Converse hand their translated events over through
+/// [`ConverseStreamResponse::from_events`].
 pub struct ConverseStreamResponse {
-    inner: EventReceiver<ConverseStreamOutput, ConverseStreamOutputError>,
+    inner: StreamSource,
+}
+
+enum StreamSource {
+    Bedrock(EventReceiver<ConverseStreamOutput, ConverseStreamOutputError>),
+    Adapted(Pin<Box<dyn Stream<Item = Result<ConverseStreamOutput, BedrockStreamError>> + Send>>),
 }
 
 impl ConverseStreamResponse {
+    /// Wrap Converse events produced by a non-Bedrock backend
+    pub fn from_events(
+        events: impl Stream<Item = Result<ConverseStreamOutput, BedrockStreamError>> + Send + 'static,
+    ) -> Self {
+        Self {
+            inner: StreamSource::Adapted(Box::pin(events)),
+        }
+    }
+
     /// Get the next event from the stream
     ///
     /// Returns `Ok(Some(event))` for each event, `Ok(None)` when the stream ends,
     /// or `Err` on error.
     pub async fn recv(&mut self) -> Result<Option<ConverseStreamOutput>, BedrockStreamError> {
-        match self.inner.recv().await {
-            Ok(Some(event)) => Ok(Some(event)),
-            Ok(None) => Ok(None),
-            Err(e) => Err(BedrockStreamError::StreamError(e.to_string())),
+        match &mut self.inner {
+            StreamSource::Bedrock(receiver) => match receiver.recv().await {
+                Ok(Some(event)) => Ok(Some(event)),
+                Ok(None) => Ok(None),
+                Err(e) => Err(BedrockStreamError::from_output_error(e)),
+            },
+            StreamSource::Adapted(events) => events.next().await.transpose(),
         }
     }
 
@@ -279,14 +596,17 @@ impl ConverseStreamResponse {
     pub fn into_stream(
         self,
     ) -> Pin<Box<dyn Stream<Item = Result<ConverseStreamOutput, BedrockStreamError>> + Send>> {
+        let mut receiver = match self.inner {
+            StreamSource::Bedrock(receiver) => receiver,
+            StreamSource::Adapted(events) => return events,
+        };
         Box::pin(async_stream::stream! {
-            let mut receiver = self.inner;
             loop {
                 match receiver.recv().await {
                     Ok(Some(event)) => yield Ok(event),
                     Ok(None) => break,
                     Err(e) => {
-                        yield Err(BedrockStreamError::StreamError(e.to_string()));
+                        yield Err(BedrockStreamError::from_output_error(e));
                         break;
                     }
                 }
@@ -303,6 +623,45 @@ pub enum BedrockStreamError {
 
     #[error("Event parse error: {0}")]
     ParseError(String),
+
+    /// Bedrock reported an error event mid-stream
+    #[error("{0}")]
+    Backend(BedrockError),
+}
+
+impl BedrockStreamError {
+    /// Classify an error received from the event stream
+    fn from_output_error<R>(err: SdkError<ConverseStreamOutputError, R>) -> Self
+    where
+        R: std::fmt::Debug,
+    {
+        let SdkError::ServiceError(service_err) = &err else {
+            return BedrockStreamError::StreamError(err.to_string());
+        };
+        let backend = match service_err.err() {
+            ConverseStreamOutputError::ThrottlingException(e) => BedrockError::Throttled(
+                e.message().unwrap_or("Rate limited").to_string(),
+            ),
+            ConverseStreamOutputError::ServiceUnavailableException(e) => {
+                BedrockError::ServiceUnavailable(
+                    e.message().unwrap_or("Service unavailable").to_string(),
+                )
+            }
+            ConverseStreamOutputError::ValidationException(e) => BedrockError::ValidationError(
+                e.message().unwrap_or("Validation failed").to_string(),
+            ),
+            ConverseStreamOutputError::InternalServerException(e) => BedrockError::InternalError(
+                e.message().unwrap_or("Internal server error").to_string(),
+            ),
+            ConverseStreamOutputError::ModelStreamErrorException(e) => BedrockError::ApiError {
+                message: e.message().unwrap_or("Model stream error").to_string(),
+                error_type: BedrockErrorType::Server,
+                is_retryable: true,
+            },
+            _ => return BedrockStreamError::StreamError(err.to_string()),
+        };
+        BedrockStreamError::Backend(backend)
+    }
 }
 
 // ============================================================================
@@ -348,6 +707,10 @@ pub enum BedrockError {
     #[error("Access denied: {0}")]
     AccessDenied(String),
 
+    /// Model has reached end of life (or is legacy and no longer enabled)
+    #[error("Model end of life: {0}")]
+    ModelEndOfLife(String),
+
     /// Internal service error
     #[error("Internal error: {0}")]
     InternalError(String),
@@ -372,6 +735,29 @@ pub enum BedrockErrorType {
     Unknown,
 }
 
+/// Client-facing class of a Bedrock error
+///
+/// Single mapping table shared by the Anthropic and OpenAI APIs so the same
+/// Bedrock failure always yields the same status: throttling is 429, capacity
+/// problems are "overloaded" (529 for Anthropic, 503 for OpenAI), and a
+/// retired model is a 400 the client must fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The request itself is invalid (400)
+    InvalidRequest,
+    /// The proxy's credentials may not use the model (403)
+    PermissionDenied,
+    /// Rate limited by Bedrock (429)
+    RateLimited,
+    /// Bedrock lacks capacity right now (529 / 503)
+    Overloaded,
+    /// Unexpected failure (500)
+    Server,
+}
+
+/// Message fragments Bedrock uses for retired or legacy models
+const END_OF_LIFE_MARKERS: &[&str] = &["end of its life", "end of life", "marked by provider as legacy"];
+
 impl BedrockError {
     /// Create BedrockError from Converse API error
     pub fn from_converse_error<R>(err: SdkError<ConverseError, R>) -> Self
@@ -381,7 +767,7 @@ impl BedrockError {
         match &err {
             SdkError::ServiceError(service_err) => {
                 let error = service_err.err();
-                match error {
+                let mapped = match error {
                     ConverseError::ThrottlingException(e) => BedrockError::Throttled(
                         e.message().unwrap_or("Rate limited").to_string(),
                     ),
@@ -403,8 +789,19 @@ impl BedrockError {
                     ConverseError::ResourceNotFoundException(e) => BedrockError::ModelNotFound(
                         e.message().unwrap_or("Resource not found").to_string(),
                     ),
+                    ConverseError::ServiceUnavailableException(e) => {
+                        BedrockError::ServiceUnavailable(
+                            e.message().unwrap_or("Service unavailable").to_string(),
+                        )
+                    }
+                    ConverseError::ModelErrorException(e) => BedrockError::ApiError {
+                        message: e.message().unwrap_or("Model error").to_string(),
+                        error_type: BedrockErrorType::Server,
+                        is_retryable: true,
+                    },
                     _ => BedrockError::Unknown(format!("{:?}", error)),
-                }
+                };
+                mapped.or_end_of_life()
             }
             _ => BedrockError::Unknown(format!("{:?}", err)),
         }
@@ -418,7 +815,7 @@ impl BedrockError {
         match &err {
             SdkError::ServiceError(service_err) => {
                 let error = service_err.err();
-                match error {
+                let mapped = match error {
                     ConverseStreamError::ThrottlingException(e) => BedrockError::Throttled(
                         e.message().unwrap_or("Rate limited").to_string(),
                     ),
@@ -451,12 +848,138 @@ impl BedrockError {
                         is_retryable: true,
                     },
                     _ => BedrockError::Unknown(format!("{:?}", error)),
-                }
+                };
+                mapped.or_end_of_life()
+            }
+            _ => BedrockError::Unknown(format!("{:?}", err)),
+        }
+    }
+
+    /// Create BedrockError from InvokeModel API error
+    pub fn from_invoke_model_error<R>(err: SdkError<InvokeModelError, R>) -> Self
+    where
+        R: std::fmt::Debug,
+    {
+        match &err {
+            SdkError::ServiceError(service_err) => {
+                let error = service_err.err();
+                let mapped = match error {
+                    InvokeModelError::ThrottlingException(e) => BedrockError::Throttled(
+                        e.message().unwrap_or("Rate limited").to_string(),
+                    ),
+                    InvokeModelError::ServiceQuotaExceededException(e) => BedrockError::Throttled(
+                        e.message().unwrap_or("Service quota exceeded").to_string(),
+                    ),
+                    InvokeModelError::ValidationException(e) => BedrockError::ValidationError(
+                        e.message().unwrap_or("Validation failed").to_string(),
+                    ),
+                    InvokeModelError::ModelNotReadyException(e) => BedrockError::ServiceUnavailable(
+                        e.message().unwrap_or("Model not ready").to_string(),
+                    ),
+                    InvokeModelError::ModelTimeoutException(e) => BedrockError::ServiceUnavailable(
+                        e.message().unwrap_or("Model timeout").to_string(),
+                    ),
+                    InvokeModelError::InternalServerException(e) => BedrockError::InternalError(
+                        e.message().unwrap_or("Internal server error").to_string(),
+                    ),
+                    InvokeModelError::AccessDeniedException(e) => BedrockError::AccessDenied(
+                        e.message().unwrap_or("Access denied").to_string(),
+                    ),
+                    InvokeModelError::ResourceNotFoundException(e) => BedrockError::ModelNotFound(
+                        e.message().unwrap_or("Resource not found").to_string(),
+                    ),
+                    InvokeModelError::ServiceUnavailableException(e) => {
+                        BedrockError::ServiceUnavailable(
+                            e.message().unwrap_or("Service unavailable").to_string(),
+                        )
+                    }
+                    InvokeModelError::ModelErrorException(e) => BedrockError::ApiError {
+                        message: e.message().unwrap_or("Model error").to_string(),
+                        error_type: BedrockErrorType::Server,
+                        is_retryable: true,
+                    },
+                    _ => BedrockError::Unknown(format!("{:?}", error)),
+                };
+                mapped.or_end_of_life()
             }
             _ => BedrockError::Unknown(format!("{:?}", err)),
         }
     }
 
+    /// Reclassify a client error whose message says the model is retired
+    ///
+    /// Bedrock reports retired models as validation, not-found or
+    /// access-denied errors depending on the model's lifecycle stage.
+    fn or_end_of_life(self) -> Self {
+        match self {
+            BedrockError::ValidationError(msg)
+            | BedrockError::ModelNotFound(msg)
+            | BedrockError::AccessDenied(msg)
+                if is_end_of_life_message(&msg) =>
+            {
+                BedrockError::ModelEndOfLife(msg)
+            }
+            other => other,
+        }
+    }
+
+    /// Get the client-facing class of this error
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            BedrockError::Throttled(_) => ErrorClass::RateLimited,
+            BedrockError::ServiceUnavailable(_) => ErrorClass::Overloaded,
+            BedrockError::ValidationError(_)
+            | BedrockError::ModelNotFound(_)
+            | BedrockError::ModelEndOfLife(_)
+            | BedrockError::Serialization(_) => ErrorClass::InvalidRequest,
+            BedrockError::AccessDenied(_) => ErrorClass::PermissionDenied,
+            BedrockError::ApiError { error_type, .. } => match error_type {
+                BedrockErrorType::Throttling => ErrorClass::RateLimited,
+                BedrockErrorType::Validation | BedrockErrorType::Client => {
+                    ErrorClass::InvalidRequest
+                }
+                BedrockErrorType::Server | BedrockErrorType::Unknown => ErrorClass::Server,
+            },
+            BedrockError::InternalError(_)
+            | BedrockError::Deserialization(_)
+            | BedrockError::Unknown(_) => ErrorClass::Server,
+        }
+    }
+
+    /// Message to show API clients
+    pub fn client_message(&self) -> String {
+        match self {
+            BedrockError::ModelNotFound(msg) => format!("Model not found: {}", msg),
+            BedrockError::ModelEndOfLife(msg) => format!("Model is no longer available: {}", msg),
+            BedrockError::Serialization(msg) => format!("Serialization error: {}", msg),
+            BedrockError::Deserialization(msg) => format!("Response error: {}", msg),
+            BedrockError::Throttled(msg)
+            | BedrockError::ValidationError(msg)
+            | BedrockError::ServiceUnavailable(msg)
+            | BedrockError::AccessDenied(msg)
+            | BedrockError::InternalError(msg)
+            | BedrockError::Unknown(msg)
+            | BedrockError::ApiError { message: msg, .. } => msg.clone(),
+        }
+    }
+
+    /// Stable machine-readable code, reported to clients as `error_code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            BedrockError::Throttled(_) => "throttled",
+            BedrockError::ServiceUnavailable(_) => "service_unavailable",
+            BedrockError::ValidationError(_) => "validation_error",
+            BedrockError::ModelNotFound(_) => "model_not_found",
+            BedrockError::ModelEndOfLife(_) => "model_end_of_life",
+            BedrockError::AccessDenied(_) => "access_denied",
+            BedrockError::Serialization(_) => "serialization_error",
+            BedrockError::Deserialization(_) => "deserialization_error",
+            BedrockError::InternalError(_) => "internal_error",
+            BedrockError::ApiError { .. } => "model_error",
+            BedrockError::Unknown(_) => "unknown_error",
+        }
+    }
+
     /// Check if this error is retryable
     pub fn is_retryable(&self) -> bool {
         matches!(
@@ -468,6 +991,15 @@ impl BedrockError {
         )
     }
 
+    /// Whether another credential (or region) may succeed where this one
+    /// failed: throttling and missing capacity, not errors in the request
+    pub fn triggers_failover(&self) -> bool {
+        matches!(
+            self,
+            BedrockError::Throttled(_) | BedrockError::ServiceUnavailable(_)
+        )
+    }
+
     /// Get the error type for categorization
     pub fn error_type(&self) -> BedrockErrorType {
         match self {
@@ -475,6 +1007,7 @@ impl BedrockError {
             BedrockError::ValidationError(_) => BedrockErrorType::Validation,
             BedrockError::ModelNotFound(_)
             | BedrockError::AccessDenied(_)
+            | BedrockError::ModelEndOfLife(_)
             | BedrockError::Serialization(_)
             | BedrockError::Deserialization(_) => BedrockErrorType::Client,
             BedrockError::ServiceUnavailable(_) | BedrockError::InternalError(_) => {
@@ -486,11 +1019,70 @@ impl BedrockError {
     }
 }
 
+fn is_end_of_life_message(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    END_OF_LIFE_MARKERS.iter().any(|m| message.contains(m))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use aws_sdk_bedrockruntime::types::{ContentBlock as BedrockContentBlock, ConversationRole};
 
+    #[test]
+    fn test_bedrock_error_class_table() {
+        let cases = [
+            (BedrockError::Throttled("t".into()), ErrorClass::RateLimited),
+            (BedrockError::ServiceUnavailable("t".into()), ErrorClass::Overloaded),
+            (BedrockError::ValidationError("t".into()), ErrorClass::InvalidRequest),
+            (BedrockError::ModelNotFound("t".into()), ErrorClass::InvalidRequest),
+            (BedrockError::ModelEndOfLife("t".into()), ErrorClass::InvalidRequest),
+            (BedrockError::Serialization("t".into()), ErrorClass::InvalidRequest),
+            (BedrockError::AccessDenied("t".into()), ErrorClass::PermissionDenied),
+            (BedrockError::InternalError("t".into()), ErrorClass::Server),
+            (BedrockError::Deserialization("t".into()), ErrorClass::Server),
+            (BedrockError::Unknown("t".into()), ErrorClass::Server),
+            (
+                BedrockError::ApiError {
+                    message: "t".into(),
+                    error_type: BedrockErrorType::Server,
+                    is_retryable: true,
+                },
+                ErrorClass::Server,
+            ),
+            (
+                BedrockError::ApiError {
+                    message: "t".into(),
+                    error_type: BedrockErrorType::Throttling,
+                    is_retryable: true,
+                },
+                ErrorClass::RateLimited,
+            ),
+        ];
+        for (err, class) in cases {
+            assert_eq!(err.class(), class, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn test_end_of_life_detection() {
+        let eol = BedrockError::ModelNotFound(
+            "This model version has reached the end of its life.".to_string(),
+        )
+        .or_end_of_life();
+        assert!(matches!(eol, BedrockError::ModelEndOfLife(_)));
+
+        let legacy = BedrockError::AccessDenied(
+            "Access denied. This Model is marked by provider as Legacy".to_string(),
+        )
+        .or_end_of_life();
+        assert!(matches!(legacy, BedrockError::ModelEndOfLife(_)));
+        assert!(!legacy.is_retryable());
+
+        let denied = BedrockError::AccessDenied("not authorized".to_string()).or_end_of_life();
+        assert!(matches!(denied, BedrockError::AccessDenied(_)));
+    }
+
     #[test]
     fn test_bedrock_error_is_retryable() {
         assert!(BedrockError::Throttled("test".to_string()).is_retryable());
@@ -498,6 +1090,12 @@ mod tests {
         assert!(BedrockError::InternalError("test".to_string()).is_retryable());
         assert!(!BedrockError::ValidationError("test".to_string()).is_retryable());
         assert!(!BedrockError::AccessDenied("test".to_string()).is_retryable());
+
+        // Only capacity problems move a request to another credential
+        assert!(BedrockError::Throttled("test".to_string()).triggers_failover());
+        assert!(BedrockError::ServiceUnavailable("test".to_string()).triggers_failover());
+        assert!(!BedrockError::InternalError("test".to_string()).triggers_failover());
+        assert!(!BedrockError::ValidationError("test".to_string()).triggers_failover());
     }
 
     #[test]
@@ -514,6 +1112,36 @@ mod tests {
         assert!(request.inference_config.is_some());
     }
 
+    fn test_client() -> BedrockRuntimeClient {
+        BedrockRuntimeClient::from_conf(
+            aws_sdk_bedrockruntime::Config::builder()
+                .behavior_version(aws_sdk_bedrockruntime::config::BehaviorVersion::latest())
+                .region(aws_sdk_bedrockruntime::config::Region::new("us-east-1"))
+                .build(),
+        )
+    }
+
+    fn thinking_fields() -> aws_smithy_types::Document {
+        let mut thinking = std::collections::HashMap::new();
+        thinking.insert("type".to_string(), aws_smithy_types::Document::from("enabled"));
+        let mut fields = std::collections::HashMap::new();
+        fields.insert("thinking".to_string(), aws_smithy_types::Document::Object(thinking));
+        aws_smithy_types::Document::Object(fields)
+    }
+
+    #[test]
+    fn test_converse_input_carries_additional_fields() {
+        let request = ConverseRequest::new("claude-3-sonnet").with_additional_fields(thinking_fields());
+        let input = converse_input(&test_client(), "anthropic.claude-3-sonnet", request);
+
+        assert_eq!(input.get_model_id().as_deref(), Some("anthropic.claude-3-sonnet"));
+        assert_eq!(input.get_additional_model_request_fields(), &Some(thinking_fields()));
+
+        let request = ConverseRequest::new("claude-3-sonnet").with_additional_fields(thinking_fields());
+        let input = converse_stream_input(&test_client(), "anthropic.claude-3-sonnet", request);
+        assert_eq!(input.get_additional_model_request_fields(), &Some(thinking_fields()));
+    }
+
     #[test]
     fn test_converse_request_with_messages() {
         let message = BedrockMessage::builder()
@@ -527,4 +1155,46 @@ mod tests {
 
         assert_eq!(request.messages.len(), 1);
     }
+
+    #[test]
+    fn test_embedding_family_bodies() {
+        assert_eq!(
+            EmbeddingFamily::from_model_id("amazon.titan-embed-text-v2:0"),
+            Some(EmbeddingFamily::Titan)
+        );
+        assert_eq!(
+            EmbeddingFamily::from_model_id("us.cohere.embed-v4:0"),
+            Some(EmbeddingFamily::Cohere)
+        );
+        assert_eq!(EmbeddingFamily::from_model_id("anthropic.claude-3-haiku"), None);
+
+        let texts = vec!["hello".to_string()];
+        assert_eq!(
+            EmbeddingFamily::Titan.request_body(&texts, Some(256)),
+            serde_json::json!({"inputText": "hello", "dimensions": 256})
+        );
+        assert_eq!(
+            EmbeddingFamily::Cohere.request_body(&texts, None),
+            serde_json::json!({"texts": ["hello"], "input_type": "search_document", "truncate": "END"})
+        );
+    }
+
+    #[test]
+    fn test_embedding_responses() {
+        let texts = vec!["hello world!".to_string(), "hi".to_string()];
+        let titan = EmbeddingFamily::Titan
+            .parse_response(br#"{"embedding": [0.5, -0.25], "inputTextTokenCount": 3}"#, &texts[..1])
+            .unwrap();
+        assert_eq!(titan.embeddings, vec![vec![0.5, -0.25]]);
+        assert_eq!(titan.input_tokens, 3);
+
+        let cohere = EmbeddingFamily::Cohere
+            .parse_response(br#"{"id": "x", "embeddings": {"float": [[1.0], [2.0]]}}"#, &texts)
+            .unwrap();
+        assert_eq!(cohere.embeddings, vec![vec![1.0], vec![2.0]]);
+        assert_eq!(cohere.input_tokens, 4);
+
+        let mismatch = EmbeddingFamily::Cohere.parse_response(br#"{"embeddings": [[1.0]]}"#, &texts);
+        assert!(matches!(mismatch, Err(BedrockError::Deserialization(_))));
+    }
 }
@@ -0,0 +1,408 @@
+//! Per-credential Bedrock clients
+//!
+//! When Bedrock traffic is spread over a pool of AWS credentials, each
+//! credential gets its own SDK client. Clients are built on first use and
+//! cached, so requests never reload `aws_config`. A credential whose key
+//! material changes (a rotation) gets a fresh client the next time it is
+//! picked; credentials removed from the pool have their clients dropped.
+//!
+//! A request Bedrock throttles or cannot serve may be retried on another
+//! credential ([`BedrockClientPool::failover_client`]), preferring one in a
+//! region the request has not tried yet, since throttling and capacity are
+//! per region.
+
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_bedrockruntime::config::Credentials;
+use aws_sdk_bedrockruntime::Client as BedrockRuntimeClient;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use crate::config::aws::apply_client_config;
+use crate::config::AwsClientConfig;
+use crate::services::backend_pool::{
+    AwsCredential, Credential, CredentialBreaker, CredentialPool, FailoverPolicy,
+};
+use crate::services::bedrock::BedrockError;
+
+/// Provider name reported for static access keys from the pool
+const POOL_CREDENTIALS_PROVIDER: &str = "bedrock-credential-pool";
+
+/// What a client is built from; owned so no pool lock is held while building
+#[derive(Debug, Clone, Hash)]
+struct ClientSpec {
+    name: String,
+    region: String,
+    profile: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    session_token: Option<String>,
+}
+
+impl ClientSpec {
+    fn from_credential(credential: &AwsCredential) -> Self {
+        Self {
+            name: credential.name().to_string(),
+            region: credential.region().to_string(),
+            profile: credential.profile().map(str::to_string),
+            access_key_id: credential.access_key_id().map(str::to_string),
+            secret_access_key: credential.secret_access_key().map(str::to_string),
+            session_token: credential.session_token().map(str::to_string),
+        }
+    }
+
+    /// Changes whenever the key material or region does
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+struct CachedClient {
+    fingerprint: u64,
+    region: String,
+    client: BedrockRuntimeClient,
+    built_at: Instant,
+    requests: AtomicU64,
+}
+
+/// One cached client, as reported by [`BedrockClientPool::stats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedClientStats {
+    pub credential: String,
+    pub region: String,
+    pub age_secs: u64,
+    pub requests: u64,
+}
+
+/// Client cache counters
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientCacheStats {
+    /// Credentials in the pool
+    pub credentials: usize,
+    /// Healthy credentials in the pool
+    pub healthy_credentials: usize,
+    /// Clients built, including rebuilds
+    pub builds: u64,
+    /// Requests served by an already-cached client
+    pub hits: u64,
+    /// Clients rebuilt because their credential was rotated
+    pub rebuilds: u64,
+    pub clients: Vec<CachedClientStats>,
+    /// Circuit breaker state of each credential
+    pub breakers: Vec<CredentialBreaker>,
+}
+
+/// A pool of AWS credentials with one cached Bedrock client per credential
+pub struct BedrockClientPool {
+    pool: RwLock<Arc<CredentialPool<AwsCredential>>>,
+    clients: RwLock<HashMap<String, Arc<CachedClient>>>,
+    endpoint_url: Option<String>,
+    client_config: AwsClientConfig,
+    builds: AtomicU64,
+    hits: AtomicU64,
+    rebuilds: AtomicU64,
+}
+
+impl BedrockClientPool {
+    pub fn new(
+        pool: CredentialPool<AwsCredential>,
+        endpoint_url: Option<String>,
+        client_config: AwsClientConfig,
+    ) -> Self {
+        Self {
+            pool: RwLock::new(Arc::new(pool)),
+            clients: RwLock::new(HashMap::new()),
+            endpoint_url,
+            client_config,
+            builds: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            rebuilds: AtomicU64::new(0),
+        }
+    }
+
+    fn pool(&self) -> Arc<CredentialPool<AwsCredential>> {
+        self.pool.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Client for the next credential picked by the pool's strategy, with the
+    /// credential's name; None when no credential is available
+    pub async fn next_client(&self) -> Option<(String, BedrockRuntimeClient)> {
+        let spec = self.pool().get_next().map(ClientSpec::from_credential)?;
+        let client = self.client_for(&spec).await;
+        Some((spec.name, client))
+    }
+
+    /// Client to retry a request on after it failed with the `tried`
+    /// credentials: another region if one is available, else any other
+    /// credential; None when every credential has been tried
+    pub async fn failover_client(&self, tried: &[String]) -> Option<(String, BedrockRuntimeClient)> {
+        let spec = {
+            let pool = self.pool();
+            let tried_regions: Vec<&str> = tried
+                .iter()
+                .filter_map(|name| pool.get_by_name(name))
+                .map(|credential| credential.region())
+                .collect();
+            let same_region: Vec<&str> = pool
+                .all()
+                .iter()
+                .filter(|credential| tried_regions.contains(&credential.region()))
+                .map(|credential| credential.name())
+                .collect();
+            let tried: Vec<&str> = tried.iter().map(String::as_str).collect();
+            pool.get_next_except(&same_region)
+                .or_else(|| pool.get_next_except(&tried))
+                .map(ClientSpec::from_credential)?
+        };
+        let client = self.client_for(&spec).await;
+        Some((spec.name, client))
+    }
+
+    /// Policy for retrying throttled requests on other credentials
+    pub fn failover_policy(&self) -> FailoverPolicy {
+        self.pool().failover_policy()
+    }
+
+    /// Record that a request failed over away from a credential
+    pub fn record_failover(&self, credential: &str) {
+        self.pool().record_failover(credential);
+    }
+
+    async fn client_for(&self, spec: &ClientSpec) -> BedrockRuntimeClient {
+        let fingerprint = spec.fingerprint();
+        let cached = self
+            .clients
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&spec.name)
+            .cloned();
+        if let Some(ref cached) = cached {
+            if cached.fingerprint == fingerprint {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                cached.requests.fetch_add(1, Ordering::Relaxed);
+                return cached.client.clone();
+            }
+            self.rebuilds.fetch_add(1, Ordering::Relaxed);
+            tracing::info!(credential = %spec.name, "Bedrock credential rotated, rebuilding client");
+        }
+
+        let client = self.build_client(spec).await;
+        self.builds.fetch_add(1, Ordering::Relaxed);
+
+        let mut clients = self.clients.write().unwrap_or_else(|e| e.into_inner());
+        // A concurrent request may have built the same client meanwhile
+        let entry = clients
+            .entry(spec.name.clone())
+            .and_modify(|existing| {
+                if existing.fingerprint != fingerprint {
+                    *existing = Arc::new(CachedClient::new(fingerprint, spec, client.clone()));
+                }
+            })
+            .or_insert_with(|| Arc::new(CachedClient::new(fingerprint, spec, client)));
+        entry.requests.fetch_add(1, Ordering::Relaxed);
+        entry.client.clone()
+    }
+
+    async fn build_client(&self, spec: &ClientSpec) -> BedrockRuntimeClient {
+        tracing::debug!(
+            credential = %spec.name,
+            region = %spec.region,
+            "Building Bedrock client for pooled credential"
+        );
+        let mut loader = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(spec.region.clone()));
+        if let Some(ref profile) = spec.profile {
+            loader = loader.profile_name(profile);
+        } else if let (Some(key_id), Some(secret)) = (&spec.access_key_id, &spec.secret_access_key)
+        {
+            loader = loader.credentials_provider(Credentials::new(
+                key_id,
+                secret,
+                spec.session_token.clone(),
+                None,
+                POOL_CREDENTIALS_PROVIDER,
+            ));
+        }
+        let sdk_config = apply_client_config(loader, &self.client_config)
+            .load()
+            .await;
+
+        match self.endpoint_url {
+            Some(ref endpoint) => BedrockRuntimeClient::from_conf(
+                aws_sdk_bedrockruntime::config::Builder::from(&sdk_config)
+                    .endpoint_url(endpoint)
+                    .build(),
+            ),
+            None => BedrockRuntimeClient::new(&sdk_config),
+        }
+    }
+
+    /// Replace the pool's credentials
+    ///
+    /// Clients of credentials no longer in the pool are dropped now; those
+    /// whose key material changed are rebuilt on their next request.
+    pub fn rotate(&self, pool: CredentialPool<AwsCredential>) {
+        let names: Vec<String> = pool.all().iter().map(|c| c.name().to_string()).collect();
+        *self.pool.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(pool);
+        self.clients
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|name, _| names.contains(name));
+    }
+
+    /// Drop every cached client; each is rebuilt on its credential's next
+    /// request. Returns the number of clients dropped.
+    pub fn clear(&self) -> usize {
+        let mut clients = self.clients.write().unwrap_or_else(|e| e.into_inner());
+        let dropped = clients.len();
+        clients.clear();
+        dropped
+    }
+
+    /// Report the outcome of a request made with a pooled credential
+    ///
+    /// Throttling puts the credential in cooldown; server-side and other
+    /// retryable failures count toward disabling it.
+    pub fn record_result<T>(&self, credential: &str, result: &Result<T, BedrockError>) {
+        let pool = self.pool();
+        match result {
+            Ok(_) => pool.record_success(credential),
+            Err(BedrockError::Throttled(_)) => pool.record_rate_limited(credential, None),
+            Err(e) if e.is_retryable() => {
+                pool.record_failure(credential);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Cache counters and the clients currently held
+    pub fn stats(&self) -> ClientCacheStats {
+        let pool = self.pool();
+        let mut clients: Vec<CachedClientStats> = self
+            .clients
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(name, cached)| CachedClientStats {
+                credential: name.clone(),
+                region: cached.region.clone(),
+                age_secs: cached.built_at.elapsed().as_secs(),
+                requests: cached.requests.load(Ordering::Relaxed),
+            })
+            .collect();
+        clients.sort_by(|a, b| a.credential.cmp(&b.credential));
+
+        let pool_stats = pool.stats();
+        ClientCacheStats {
+            credentials: pool_stats.total,
+            healthy_credentials: pool_stats.healthy,
+            builds: self.builds.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            rebuilds: self.rebuilds.load(Ordering::Relaxed),
+            clients,
+            breakers: pool_stats.breakers,
+        }
+    }
+}
+
+impl CachedClient {
+    fn new(fingerprint: u64, spec: &ClientSpec, client: BedrockRuntimeClient) -> Self {
+        Self {
+            fingerprint,
+            region: spec.region.clone(),
+            client,
+            built_at: Instant::now(),
+            requests: AtomicU64::new(0),
+        }
+    }
+}
+
+impl std::fmt::Debug for BedrockClientPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BedrockClientPool")
+            .field("credentials", &self.pool().len())
+            .field("endpoint_url", &self.endpoint_url)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::backend_pool::{LoadBalanceStrategy, PoolConfig};
+
+    fn pool(secret: &str) -> CredentialPool<AwsCredential> {
+        CredentialPool::new(
+            vec![
+                AwsCredential::with_access_key("AKIA1", secret, "us-east-1", "east", 1),
+                AwsCredential::with_access_key("AKIA2", "secret-2", "us-west-2", "west", 1),
+            ],
+            PoolConfig::new(LoadBalanceStrategy::RoundRobin),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_clients_cached_per_credential_and_rebuilt_on_rotation() {
+        let clients = BedrockClientPool::new(
+            pool("secret-1"),
+            Some("http://localhost:4566".to_string()),
+            AwsClientConfig::default(),
+        );
+
+        for _ in 0..4 {
+            clients.next_client().await.unwrap();
+        }
+        let stats = clients.stats();
+        assert_eq!(stats.builds, 2);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.clients.len(), 2);
+        assert_eq!(stats.clients[0].credential, "east");
+        assert_eq!(stats.clients[0].requests, 2);
+
+        // New secret for "east": rebuilt on next use, "west" reused
+        clients.rotate(pool("secret-1b"));
+        clients.next_client().await.unwrap();
+        clients.next_client().await.unwrap();
+        let stats = clients.stats();
+        assert_eq!(stats.rebuilds, 1);
+        assert_eq!(stats.builds, 3);
+
+        assert_eq!(clients.clear(), 2);
+        clients.next_client().await.unwrap();
+        assert_eq!(clients.stats().builds, 4);
+    }
+
+    #[tokio::test]
+    async fn test_failover_prefers_another_region() {
+        let clients = BedrockClientPool::new(
+            CredentialPool::new(
+                vec![
+                    AwsCredential::with_access_key("AKIA1", "s1", "us-east-1", "east-a", 1),
+                    AwsCredential::with_access_key("AKIA2", "s2", "us-east-1", "east-b", 1),
+                    AwsCredential::with_access_key("AKIA3", "s3", "us-west-2", "west", 1),
+                ],
+                PoolConfig::new(LoadBalanceStrategy::Failover),
+            ),
+            Some("http://localhost:4566".to_string()),
+            AwsClientConfig::default(),
+        );
+
+        let tried = vec!["east-a".to_string()];
+        let (name, _) = clients.failover_client(&tried).await.unwrap();
+        assert_eq!(name, "west");
+
+        // Same region once every other region has been tried
+        let tried = vec!["east-a".to_string(), "west".to_string()];
+        let (name, _) = clients.failover_client(&tried).await.unwrap();
+        assert_eq!(name, "east-b");
+
+        let tried = vec!["east-a".to_string(), "east-b".to_string(), "west".to_string()];
+        assert!(clients.failover_client(&tried).await.is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! Stored chat completions
+//!
+//! Chat completions created with `store: true` are kept in memory so clients
+//! can retrieve them by completion ID, list them by model or metadata, and
+//! delete them. Each completion is visible only to the user whose key created
+//! it (the master key sees all). Entries older than the retention period are
+//! dropped, and the oldest entries are evicted beyond the configured maximum.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::config::CompletionStoreConfig;
+use crate::schemas::openai::{ChatCompletionResponse, ChatMessage};
+
+/// Most metadata pairs per request
+pub const MAX_METADATA_PAIRS: usize = 16;
+
+/// Longest metadata key, in characters
+const MAX_METADATA_KEY_CHARS: usize = 64;
+
+/// Longest metadata value, in characters
+const MAX_METADATA_VALUE_CHARS: usize = 512;
+
+/// Check request metadata against OpenAI's limits
+pub fn validate_metadata(metadata: &HashMap<String, String>) -> Result<(), String> {
+    if metadata.len() > MAX_METADATA_PAIRS {
+        return Err(format!(
+            "metadata: at most {} key-value pairs are allowed, got {}",
+            MAX_METADATA_PAIRS,
+            metadata.len()
+        ));
+    }
+
+    for (key, value) in metadata {
+        if key.chars().count() > MAX_METADATA_KEY_CHARS {
+            return Err(format!(
+                "metadata: key '{}' is longer than {} characters",
+                key, MAX_METADATA_KEY_CHARS
+            ));
+        }
+        if value.chars().count() > MAX_METADATA_VALUE_CHARS {
+            return Err(format!(
+                "metadata: value of '{}' is longer than {} characters",
+                key, MAX_METADATA_VALUE_CHARS
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A stored completion with the request messages that produced it
+#[derive(Debug, Clone)]
+pub struct StoredCompletion {
+    /// User ID of the key that created the completion
+    pub owner: String,
+    /// Unix timestamp when the completion was stored
+    pub stored_at: i64,
+    pub metadata: HashMap<String, String>,
+    /// Request messages (before conversion)
+    pub messages: Vec<ChatMessage>,
+    pub response: ChatCompletionResponse,
+}
+
+impl StoredCompletion {
+    pub fn new(
+        owner: impl Into<String>,
+        metadata: HashMap<String, String>,
+        messages: Vec<ChatMessage>,
+        response: ChatCompletionResponse,
+    ) -> Self {
+        Self {
+            owner: owner.into(),
+            stored_at: Utc::now().timestamp(),
+            metadata,
+            messages,
+            response,
+        }
+    }
+
+    /// Completion ID (`chatcmpl-...`)
+    pub fn id(&self) -> &str {
+        &self.response.id
+    }
+
+    /// Whether every filter pair is present in the metadata
+    pub fn matches_metadata(&self, filter: &HashMap<String, String>) -> bool {
+        filter
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
+}
+
+/// In-memory store of completions created with `store: true`
+#[derive(Debug)]
+pub struct CompletionStore {
+    config: CompletionStoreConfig,
+    entries: RwLock<HashMap<String, StoredCompletion>>,
+}
+
+impl CompletionStore {
+    pub fn new(config: CompletionStoreConfig) -> Self {
+        Self {
+            config,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `store: true` requests are persisted
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.config.retention_seconds > 0 && self.config.max_entries > 0
+    }
+
+    /// Store a completion, returning false if storing is disabled
+    pub fn insert(&self, completion: StoredCompletion) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        self.purge_expired();
+        let mut entries = self.entries.write().unwrap();
+        while entries.len() >= self.config.max_entries {
+            let oldest = entries
+                .values()
+                .min_by_key(|c| c.stored_at)
+                .map(|c| c.id().to_string());
+            match oldest {
+                Some(id) => entries.remove(&id),
+                None => break,
+            };
+        }
+        entries.insert(completion.id().to_string(), completion);
+        true
+    }
+
+    /// Look up a completion visible to `owner` (None = any owner)
+    pub fn get(&self, id: &str, owner: Option<&str>) -> Option<StoredCompletion> {
+        let now = Utc::now().timestamp();
+        self.entries
+            .read()
+            .unwrap()
+            .get(id)
+            .filter(|c| !self.is_expired(c, now) && Self::is_visible(c, owner))
+            .cloned()
+    }
+
+    /// Completions visible to `owner`, oldest first, optionally filtered by
+    /// model and metadata
+    pub fn list(
+        &self,
+        owner: Option<&str>,
+        model: Option<&str>,
+        metadata: &HashMap<String, String>,
+    ) -> Vec<StoredCompletion> {
+        self.purge_expired();
+        let mut completions: Vec<_> = self
+            .entries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|c| Self::is_visible(c, owner))
+            .filter(|c| model.is_none() || model == Some(c.response.model.as_str()))
+            .filter(|c| c.matches_metadata(metadata))
+            .cloned()
+            .collect();
+        completions.sort_by(|a, b| {
+            a.response
+                .created
+                .cmp(&b.response.created)
+                .then_with(|| a.id().cmp(b.id()))
+        });
+        completions
+    }
+
+    /// Delete a completion visible to `owner`, returning true if one was removed
+    pub fn delete(&self, id: &str, owner: Option<&str>) -> bool {
+        let mut entries = self.entries.write().unwrap();
+        match entries.get(id) {
+            Some(c) if Self::is_visible(c, owner) => entries.remove(id).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Drop completions past the retention period, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now().timestamp();
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|_, c| !self.is_expired(c, now));
+        before - entries.len()
+    }
+
+    /// Number of stored completions (including not-yet-purged expired ones)
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Check if no completions are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_expired(&self, completion: &StoredCompletion, now: i64) -> bool {
+        now - completion.stored_at >= self.config.retention_seconds
+    }
+
+    fn is_visible(completion: &StoredCompletion, owner: Option<&str>) -> bool {
+        owner.is_none() || owner == Some(completion.owner.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::openai::{ChatRole, CompletionUsage};
+
+    fn response(id: &str, created: i64) -> ChatCompletionResponse {
+        ChatCompletionResponse {
+            id: id.to_string(),
+            object: "chat.completion".to_string(),
+            created,
+            model: "gpt-4o".to_string(),
+            choices: Vec::new(),
+            usage: CompletionUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                completion_tokens_details: None,
+            },
+            system_fingerprint: None,
+            service_tier: None,
+            audio_transcriptions: None,
+        }
+    }
+
+    fn completion(id: &str, owner: &str, created: i64, tag: &str) -> StoredCompletion {
+        let message = ChatMessage {
+            role: ChatRole::User,
+            content: None,
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        };
+        StoredCompletion::new(
+            owner,
+            HashMap::from([("tag".to_string(), tag.to_string())]),
+            vec![message],
+            response(id, created),
+        )
+    }
+
+    #[test]
+    fn test_validate_metadata() {
+        let ok = HashMap::from([("team".to_string(), "search".to_string())]);
+        assert!(validate_metadata(&ok).is_ok());
+
+        let too_many: HashMap<_, _> = (0..17).map(|i| (i.to_string(), String::new())).collect();
+        assert!(validate_metadata(&too_many).is_err());
+
+        let long_key = HashMap::from([("k".repeat(65), String::new())]);
+        assert!(validate_metadata(&long_key).is_err());
+    }
+
+    #[test]
+    fn test_owner_scoping_and_filters() {
+        let store = CompletionStore::new(CompletionStoreConfig::default());
+        assert!(store.insert(completion("chatcmpl-1", "alice", 2, "a")));
+        assert!(store.insert(completion("chatcmpl-2", "alice", 1, "b")));
+        assert!(store.insert(completion("chatcmpl-3", "bob", 3, "a")));
+
+        assert!(store.get("chatcmpl-1", Some("alice")).is_some());
+        assert!(store.get("chatcmpl-1", Some("bob")).is_none());
+        assert!(store.get("chatcmpl-1", None).is_some());
+
+        let ids: Vec<_> = store
+            .list(Some("alice"), None, &HashMap::new())
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["chatcmpl-2", "chatcmpl-1"]);
+
+        let tagged = HashMap::from([("tag".to_string(), "a".to_string())]);
+        assert_eq!(store.list(None, Some("gpt-4o"), &tagged).len(), 2);
+
+        assert!(!store.delete("chatcmpl-3", Some("alice")));
+        assert!(store.delete("chatcmpl-3", Some("bob")));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_retention_and_eviction() {
+        let store = CompletionStore::new(CompletionStoreConfig {
+            enabled: true,
+            retention_seconds: 60,
+            max_entries: 2,
+        });
+
+        let mut expired = completion("chatcmpl-old", "alice", 0, "a");
+        expired.stored_at -= 120;
+        store.insert(expired);
+        assert!(store.get("chatcmpl-old", None).is_none());
+
+        store.insert(completion("chatcmpl-1", "alice", 1, "a"));
+        store.insert(completion("chatcmpl-2", "alice", 2, "a"));
+        store.insert(completion("chatcmpl-3", "alice", 3, "a"));
+        assert_eq!(store.len(), 2);
+
+        let disabled = CompletionStore::new(CompletionStoreConfig {
+            retention_seconds: 0,
+            ..CompletionStoreConfig::default()
+        });
+        assert!(!disabled.insert(completion("chatcmpl-1", "alice", 1, "a")));
+    }
+}
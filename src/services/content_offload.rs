@@ -0,0 +1,218 @@
+//! Offloading of oversized response content to S3
+//!
+//! Big tool results and generated files make for multi-megabyte responses
+//! and SSE events that clients have to hold in memory. For keys with the
+//! `content_offload` scope, [`ContentOffloader`] uploads text and tool result
+//! blocks above the configured size to S3 and replaces them in the response
+//! with a preview (their first characters) followed by a presigned URL for
+//! the full content.
+//!
+//! Offloading fails open: when an upload or presign fails, the block is sent
+//! inline as it was and the error is logged.
+
+use aws_config::SdkConfig;
+use aws_sdk_s3::presigning::PresigningConfig;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::ContentOffloadConfig;
+use crate::schemas::anthropic::{ContentBlock, ToolResultValue};
+
+/// Uploads oversized content blocks and links them from the response
+pub struct ContentOffloader {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+    threshold_bytes: usize,
+    preview_chars: usize,
+    url_ttl: Duration,
+}
+
+impl std::fmt::Debug for ContentOffloader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContentOffloader")
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("threshold_bytes", &self.threshold_bytes)
+            .finish()
+    }
+}
+
+impl ContentOffloader {
+    pub fn new(sdk_config: &SdkConfig, bucket: impl Into<String>, config: &ContentOffloadConfig) -> Self {
+        Self {
+            client: aws_sdk_s3::Client::new(sdk_config),
+            bucket: bucket.into(),
+            prefix: config.prefix.clone(),
+            threshold_bytes: config.threshold_bytes,
+            preview_chars: config.preview_chars,
+            url_ttl: Duration::from_secs(config.url_ttl_secs),
+        }
+    }
+
+    /// Offload the oversized blocks in place; returns how many were offloaded
+    ///
+    /// Text, `tool_result` and `server_tool_result` blocks are offloaded.
+    /// Thinking blocks (whose signature covers their text) and tool inputs
+    /// (which the client needs to run the tool) are always sent inline.
+    pub async fn offload_blocks(&self, request_id: &str, blocks: &mut [ContentBlock]) -> usize {
+        let mut offloaded = 0;
+        for block in blocks.iter_mut() {
+            match block {
+                ContentBlock::Text { text, .. } => {
+                    offloaded += self.offload_text(request_id, text).await as usize;
+                }
+                ContentBlock::ToolResult { content, .. } => match content {
+                    ToolResultValue::Text(text) => {
+                        offloaded += self.offload_text(request_id, text).await as usize;
+                    }
+                    ToolResultValue::Blocks(inner) => {
+                        for inner in inner.iter_mut() {
+                            if let ContentBlock::Text { text, .. } = inner {
+                                offloaded += self.offload_text(request_id, text).await as usize;
+                            }
+                        }
+                    }
+                },
+                ContentBlock::ServerToolResult { content, .. } => {
+                    offloaded += self.offload_json(request_id, content).await as usize;
+                }
+                _ => {}
+            }
+        }
+        offloaded
+    }
+
+    /// Replace `text` with its preview and a link if it is oversized
+    pub async fn offload_text(&self, request_id: &str, text: &mut String) -> bool {
+        if text.len() <= self.threshold_bytes {
+            return false;
+        }
+        match self.upload(request_id, "txt", "text/plain; charset=utf-8", text.as_bytes().to_vec()).await {
+            Ok((url, expires_at)) => {
+                *text = offload_notice(preview(text, self.preview_chars), text.len(), &url, expires_at);
+                true
+            }
+            Err(e) => {
+                tracing::warn!(request_id = %request_id, error = %e, "Failed to offload content; sending it inline");
+                false
+            }
+        }
+    }
+
+    /// Replace a server tool result with one text item linking its JSON
+    async fn offload_json(&self, request_id: &str, content: &mut Vec<Value>) -> bool {
+        let Ok(body) = serde_json::to_string(content) else {
+            return false;
+        };
+        if body.len() <= self.threshold_bytes {
+            return false;
+        }
+        match self.upload(request_id, "json", "application/json", body.as_bytes().to_vec()).await {
+            Ok((url, expires_at)) => {
+                let text = offload_notice(preview(&body, self.preview_chars), body.len(), &url, expires_at);
+                *content = vec![json!({"type": "text", "text": text})];
+                true
+            }
+            Err(e) => {
+                tracing::warn!(request_id = %request_id, error = %e, "Failed to offload content; sending it inline");
+                false
+            }
+        }
+    }
+
+    /// Upload one object and presign a GET for it
+    async fn upload(
+        &self,
+        request_id: &str,
+        extension: &str,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> Result<(String, DateTime<Utc>), String> {
+        let key = object_key(&self.prefix, request_id, extension);
+        let size = body.len();
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .content_type(content_type)
+            .body(body.into())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let presigning = PresigningConfig::expires_in(self.url_ttl).map_err(|e| e.to_string())?;
+        let presigned = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| e.to_string())?;
+        let expires_at = Utc::now() + chrono::Duration::seconds(self.url_ttl.as_secs() as i64);
+
+        tracing::debug!(request_id = %request_id, key = %key, size, "Offloaded response content to S3");
+        Ok((presigned.uri().to_string(), expires_at))
+    }
+}
+
+/// `{prefix}/{yyyy}/{mm}/{dd}/{request_id}/{uuid}.{extension}`
+fn object_key(prefix: &str, request_id: &str, extension: &str) -> String {
+    let date = Utc::now().format("%Y/%m/%d");
+    let name = format!("{}/{}/{}.{}", date, request_id, Uuid::new_v4().simple(), extension);
+    match prefix {
+        "" => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// The first `chars` characters of `text`
+fn preview(text: &str, chars: usize) -> &str {
+    match text.char_indices().nth(chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// The text sent in place of offloaded content
+fn offload_notice(preview: &str, size_bytes: usize, url: &str, expires_at: DateTime<Utc>) -> String {
+    format!(
+        "{}\n\n[Content truncated: {} bytes in total. Full content (link expires {}): {}]",
+        preview,
+        size_bytes,
+        expires_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_preview_respects_char_boundaries() {
+        assert_eq!(preview("héllo wörld", 4), "héll");
+        assert_eq!(preview("short", 100), "short");
+        assert_eq!(preview("日本語のテキスト", 3), "日本語");
+        assert_eq!(preview("anything", 0), "");
+    }
+
+    #[test]
+    fn test_notice_and_object_key() {
+        let expires_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        let notice = offload_notice("col_a,col_b", 1_048_576, "https://bucket.s3/obj?sig=1", expires_at);
+        assert!(notice.starts_with("col_a,col_b\n\n[Content truncated: 1048576 bytes"));
+        assert!(notice.contains("link expires 2025-01-02T03:04:05Z"));
+        assert!(notice.ends_with("https://bucket.s3/obj?sig=1]"));
+
+        let key = object_key("offload", "req-1", "txt");
+        assert!(key.starts_with("offload/"));
+        assert!(key.contains("/req-1/"));
+        assert!(key.ends_with(".txt"));
+        assert!(!object_key("", "req-1", "json").starts_with('/'));
+    }
+}
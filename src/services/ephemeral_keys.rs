@@ -0,0 +1,211 @@
+//! In-memory ephemeral API keys
+//!
+//! Ephemeral keys live only for the lifetime of the process. They are minted
+//! at startup (see `--ephemeral-keys`) or at runtime through the admin API, so
+//! several local clients can each get their own key with its own TTL and
+//! scopes, and individual keys can be listed or revoked without a restart.
+
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// A single ephemeral key
+#[derive(Debug, Clone, Serialize)]
+pub struct EphemeralKey {
+    /// Short identifier used for listing and revocation
+    pub id: String,
+    /// The secret key value (only returned when minted)
+    pub key: String,
+    /// Optional human-readable label (e.g. the client it was minted for)
+    pub label: Option<String>,
+    /// Permission scopes granted to this key
+    pub scopes: Vec<String>,
+    /// Unix timestamp when the key was minted
+    pub created_at: i64,
+    /// Unix timestamp when the key expires (None = valid until shutdown)
+    pub expires_at: Option<i64>,
+}
+
+impl EphemeralKey {
+    /// Check whether the key has expired at the given Unix timestamp
+    pub fn is_expired_at(&self, now: i64) -> bool {
+        self.expires_at.map(|exp| now >= exp).unwrap_or(false)
+    }
+
+    /// Listing view of the key with the secret redacted
+    pub fn summary(&self) -> EphemeralKeySummary {
+        EphemeralKeySummary {
+            id: self.id.clone(),
+            key_prefix: format!("{}...", crate::utils::truncate_str(&self.key, 8)),
+            label: self.label.clone(),
+            scopes: self.scopes.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+/// Ephemeral key as shown in listings (secret redacted)
+#[derive(Debug, Clone, Serialize)]
+pub struct EphemeralKeySummary {
+    pub id: String,
+    pub key_prefix: String,
+    pub label: Option<String>,
+    pub scopes: Vec<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// Thread-safe registry of ephemeral keys
+#[derive(Debug, Default)]
+pub struct EphemeralKeyManager {
+    keys: RwLock<HashMap<String, EphemeralKey>>,
+}
+
+impl EphemeralKeyManager {
+    /// Create an empty manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mint a new key
+    ///
+    /// `ttl_seconds` of None (or zero) keeps the key valid until shutdown.
+    pub fn mint(
+        &self,
+        label: Option<String>,
+        ttl_seconds: Option<i64>,
+        scopes: Vec<String>,
+    ) -> EphemeralKey {
+        let now = Utc::now().timestamp();
+        let simple = uuid::Uuid::new_v4().simple().to_string();
+        let key = EphemeralKey {
+            id: simple[..12].to_string(),
+            key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
+            label,
+            scopes,
+            created_at: now,
+            expires_at: ttl_seconds.filter(|ttl| *ttl > 0).map(|ttl| now + ttl),
+        };
+
+        self.keys
+            .write()
+            .unwrap()
+            .insert(key.key.clone(), key.clone());
+        key
+    }
+
+    /// Look up a key, returning None if it is unknown or expired
+    ///
+    /// Expired keys are dropped on lookup.
+    pub fn validate(&self, key: &str) -> Option<EphemeralKey> {
+        let now = Utc::now().timestamp();
+        let found = self.keys.read().unwrap().get(key).cloned()?;
+        if found.is_expired_at(now) {
+            self.keys.write().unwrap().remove(key);
+            return None;
+        }
+        Some(found)
+    }
+
+    /// List live keys (secrets redacted), oldest first
+    pub fn list(&self) -> Vec<EphemeralKeySummary> {
+        self.purge_expired();
+        let mut keys: Vec<_> = self
+            .keys
+            .read()
+            .unwrap()
+            .values()
+            .map(|k| k.summary())
+            .collect();
+        keys.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        keys
+    }
+
+    /// Revoke a key by id or by full key value
+    ///
+    /// Returns true if a key was removed.
+    pub fn revoke(&self, id_or_key: &str) -> bool {
+        let mut keys = self.keys.write().unwrap();
+        if keys.remove(id_or_key).is_some() {
+            return true;
+        }
+        let before = keys.len();
+        keys.retain(|_, k| k.id != id_or_key);
+        keys.len() != before
+    }
+
+    /// Drop all expired keys, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let now = Utc::now().timestamp();
+        let mut keys = self.keys.write().unwrap();
+        let before = keys.len();
+        keys.retain(|_, k| !k.is_expired_at(now));
+        before - keys.len()
+    }
+
+    /// Number of keys currently held (including not-yet-purged expired keys)
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    /// Check if no keys are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mint_and_validate() {
+        let manager = EphemeralKeyManager::new();
+        let key = manager.mint(Some("cli".to_string()), None, vec!["admin".to_string()]);
+
+        assert!(key.key.starts_with("sk-"));
+        assert_eq!(key.expires_at, None);
+
+        let found = manager.validate(&key.key).unwrap();
+        assert_eq!(found.id, key.id);
+        assert_eq!(found.scopes, vec!["admin".to_string()]);
+        assert!(manager.validate("sk-unknown").is_none());
+    }
+
+    #[test]
+    fn test_expired_key_rejected() {
+        let manager = EphemeralKeyManager::new();
+        let key = manager.mint(None, Some(60), Vec::new());
+        assert!(!key.is_expired_at(key.created_at + 59));
+        assert!(key.is_expired_at(key.created_at + 60));
+
+        // Force expiry
+        manager
+            .keys
+            .write()
+            .unwrap()
+            .get_mut(&key.key)
+            .unwrap()
+            .expires_at = Some(0);
+        assert!(manager.validate(&key.key).is_none());
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn test_list_and_revoke() {
+        let manager = EphemeralKeyManager::new();
+        let a = manager.mint(Some("a".to_string()), None, Vec::new());
+        let b = manager.mint(Some("b".to_string()), Some(3600), Vec::new());
+
+        let listed = manager.list();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|k| k.key_prefix.ends_with("...")));
+
+        assert!(manager.revoke(&a.id));
+        assert!(!manager.revoke(&a.id));
+        assert!(manager.revoke(&b.key));
+        assert!(manager.is_empty());
+    }
+}
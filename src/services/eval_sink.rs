@@ -0,0 +1,393 @@
+//! Evaluation sink
+//!
+//! Tees completed conversations into an SQS queue and/or S3 for offline
+//! quality scoring. Handlers ask [`EvalTee::capture`] for an [`EvalCapture`]
+//! when the request starts; it is only handed out for keys with the
+//! [`FLAG_EVAL_CAPTURE`](crate::services::feature_flags::FLAG_EVAL_CAPTURE)
+//! feature flag and for the configured share of their requests. Once the
+//! response is complete the capture becomes an [`EvalRecord`] that is queued
+//! in memory; a background task writes the queue out in batches.
+//!
+//! Nothing on the client path waits for the sink: a full queue drops the
+//! record and counts it, and sink failures are only logged.
+
+use async_trait::async_trait;
+use aws_config::SdkConfig;
+use chrono::Utc;
+use rand::Rng;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::config::EvalSinkConfig;
+use crate::middleware::ApiKeyInfo;
+
+/// One completed conversation
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalRecord {
+    pub request_id: String,
+    /// "messages" or "chat_completions"
+    pub api: &'static str,
+    pub model: String,
+    /// Truncated API key
+    pub key: String,
+    pub user_id: String,
+    /// The request as the client sent it
+    pub prompt: serde_json::Value,
+    /// The response body, or the streamed text and tool input
+    pub output: serde_json::Value,
+    pub stream: bool,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub latency_ms: u64,
+    /// RFC 3339 completion time
+    pub completed_at: String,
+}
+
+/// Destination for batches of records
+#[async_trait]
+pub trait EvalSink: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Write one batch
+    async fn write(&self, records: &[EvalRecord]) -> Result<(), String>;
+}
+
+/// Sends each record as one SQS message
+pub struct SqsEvalSink {
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+}
+
+impl SqsEvalSink {
+    pub fn new(sdk_config: &SdkConfig, queue_url: impl Into<String>) -> Self {
+        Self {
+            client: aws_sdk_sqs::Client::new(sdk_config),
+            queue_url: queue_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl EvalSink for SqsEvalSink {
+    fn name(&self) -> &'static str {
+        "sqs"
+    }
+
+    async fn write(&self, records: &[EvalRecord]) -> Result<(), String> {
+        let mut failed = 0;
+        for record in records {
+            let body = serde_json::to_string(record).map_err(|e| e.to_string())?;
+            if let Err(e) = self
+                .client
+                .send_message()
+                .queue_url(&self.queue_url)
+                .message_body(body)
+                .send()
+                .await
+            {
+                tracing::debug!(request_id = %record.request_id, error = %e, "SQS send failed");
+                failed += 1;
+            }
+        }
+        match failed {
+            0 => Ok(()),
+            n => Err(format!("{} of {} messages failed", n, records.len())),
+        }
+    }
+}
+
+/// Writes each batch as one JSON Lines object under a dated prefix
+pub struct S3EvalSink {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3EvalSink {
+    pub fn new(sdk_config: &SdkConfig, bucket: impl Into<String>, prefix: impl Into<String>) -> Self {
+        Self {
+            client: aws_sdk_s3::Client::new(sdk_config),
+            bucket: bucket.into(),
+            prefix: prefix.into(),
+        }
+    }
+
+    /// `{prefix}/{yyyy}/{mm}/{dd}/{uuid}.jsonl`
+    fn object_key(&self) -> String {
+        let date = Utc::now().format("%Y/%m/%d");
+        match self.prefix.as_str() {
+            "" => format!("{}/{}.jsonl", date, Uuid::new_v4()),
+            prefix => format!("{}/{}/{}.jsonl", prefix, date, Uuid::new_v4()),
+        }
+    }
+}
+
+#[async_trait]
+impl EvalSink for S3EvalSink {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn write(&self, records: &[EvalRecord]) -> Result<(), String> {
+        let mut body = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut body, record).map_err(|e| e.to_string())?;
+            body.push(b'\n');
+        }
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.object_key())
+            .content_type("application/x-ndjson")
+            .body(body.into())
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Sampling and queueing in front of the sinks
+#[derive(Debug)]
+pub struct EvalTee {
+    sample_rate: f64,
+    sender: mpsc::Sender<EvalRecord>,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl EvalTee {
+    /// Start the writer task for `sinks` and return the tee feeding it
+    pub fn spawn(config: &EvalSinkConfig, sinks: Vec<Arc<dyn EvalSink>>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let tee = Arc::new(Self {
+            sample_rate: config.sample_rate,
+            sender,
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        tokio::spawn(write_loop(
+            receiver,
+            sinks,
+            config.batch_size.max(1),
+            Duration::from_secs(config.flush_interval_secs.max(1)),
+        ));
+        tee
+    }
+
+    /// Start capturing a request, if it is sampled
+    ///
+    /// The caller checks the key's opt-in flag first.
+    pub fn capture(
+        self: &Arc<Self>,
+        request_id: &str,
+        api: &'static str,
+        model: &str,
+        key_info: &ApiKeyInfo,
+        prompt: &impl Serialize,
+        stream: bool,
+    ) -> Option<EvalCapture> {
+        if self.sample_rate <= 0.0 || rand::thread_rng().gen::<f64>() >= self.sample_rate {
+            return None;
+        }
+        let prompt = serde_json::to_value(prompt).ok()?;
+        Some(EvalCapture {
+            tee: self.clone(),
+            started: std::time::Instant::now(),
+            record: EvalRecord {
+                request_id: request_id.to_string(),
+                api,
+                model: model.to_string(),
+                key: key_info.api_key.clone(),
+                user_id: key_info.user_id.clone(),
+                prompt,
+                output: serde_json::Value::Null,
+                stream,
+                input_tokens: 0,
+                output_tokens: 0,
+                latency_ms: 0,
+                completed_at: String::new(),
+            },
+            streamed: String::new(),
+        })
+    }
+
+    /// Records queued so far
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Records dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn submit(&self, record: EvalRecord) {
+        match self.sender.try_send(record) {
+            Ok(()) => {
+                self.sent.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// A sampled request waiting for its output
+#[derive(Debug)]
+pub struct EvalCapture {
+    tee: Arc<EvalTee>,
+    started: std::time::Instant,
+    record: EvalRecord,
+    streamed: String,
+}
+
+impl EvalCapture {
+    /// Append streamed text or tool input
+    pub fn record_output(&mut self, text: &str) {
+        self.streamed.push_str(text);
+    }
+
+    /// Queue the record with a non-streaming response body
+    pub fn finish(mut self, output: &impl Serialize, input_tokens: i32, output_tokens: i32) {
+        self.record.output = serde_json::to_value(output).unwrap_or_default();
+        self.submit(input_tokens, output_tokens);
+    }
+
+    /// Queue the record with the streamed output
+    pub fn finish_stream(mut self, input_tokens: i32, output_tokens: i32) {
+        self.record.output = serde_json::Value::String(std::mem::take(&mut self.streamed));
+        self.submit(input_tokens, output_tokens);
+    }
+
+    fn submit(mut self, input_tokens: i32, output_tokens: i32) {
+        self.record.input_tokens = input_tokens;
+        self.record.output_tokens = output_tokens;
+        self.record.latency_ms = self.started.elapsed().as_millis() as u64;
+        self.record.completed_at = Utc::now().to_rfc3339();
+        self.tee.submit(self.record);
+    }
+}
+
+/// Batch records from the queue into every sink
+///
+/// A batch goes out when it is full or when the flush interval ticks.
+async fn write_loop(
+    mut receiver: mpsc::Receiver<EvalRecord>,
+    sinks: Vec<Arc<dyn EvalSink>>,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut ticker = tokio::time::interval(flush_interval);
+    loop {
+        tokio::select! {
+            received = receiver.recv() => match received {
+                Some(record) => {
+                    batch.push(record);
+                    if batch.len() >= batch_size {
+                        flush(&sinks, &mut batch).await;
+                    }
+                }
+                None => {
+                    flush(&sinks, &mut batch).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => flush(&sinks, &mut batch).await,
+        }
+    }
+}
+
+async fn flush(sinks: &[Arc<dyn EvalSink>], batch: &mut Vec<EvalRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    for sink in sinks {
+        if let Err(e) = sink.write(batch).await {
+            tracing::warn!(sink = sink.name(), records = batch.len(), error = %e, "Evaluation sink write failed");
+        }
+    }
+    batch.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<EvalRecord>>);
+
+    #[async_trait]
+    impl EvalSink for MemorySink {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        async fn write(&self, records: &[EvalRecord]) -> Result<(), String> {
+            self.0.lock().unwrap().extend_from_slice(records);
+            Ok(())
+        }
+    }
+
+    fn config(sample_rate: f64) -> EvalSinkConfig {
+        EvalSinkConfig {
+            enabled: true,
+            sample_rate,
+            batch_size: 2,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_batches_reach_the_sink() {
+        let sink = Arc::new(MemorySink::default());
+        let tee = EvalTee::spawn(&config(1.0), vec![sink.clone() as Arc<dyn EvalSink>]);
+        let key = ApiKeyInfo::anonymous();
+        let prompt = serde_json::json!({"messages": [{"role": "user", "content": "hi"}]});
+
+        let mut streamed = tee
+            .capture("req-1", "messages", "claude", &key, &prompt, true)
+            .unwrap();
+        streamed.record_output("Hel");
+        streamed.record_output("lo");
+        streamed.finish_stream(3, 2);
+        tee.capture("req-2", "chat_completions", "claude", &key, &prompt, false)
+            .unwrap()
+            .finish(&serde_json::json!({"id": "chatcmpl-1"}), 3, 1);
+
+        for _ in 0..50 {
+            if sink.0.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let records = sink.0.lock().unwrap().clone();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].output, "Hello");
+        assert_eq!(records[0].prompt, prompt);
+        assert_eq!(records[1].output["id"], "chatcmpl-1");
+        assert_eq!(tee.sent(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_zero_sample_rate_captures_nothing() {
+        let tee = EvalTee::spawn(&config(0.0), Vec::new());
+        let capture = tee.capture(
+            "req-1",
+            "messages",
+            "claude",
+            &ApiKeyInfo::anonymous(),
+            &serde_json::json!({}),
+            false,
+        );
+        assert!(capture.is_none());
+    }
+}
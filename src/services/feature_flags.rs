@@ -0,0 +1,176 @@
+//! Runtime feature flags
+//!
+//! The static [`FeatureFlags`] settings give every key the same answer. Flags
+//! stored in the feature flags table override them per key or tenant and can
+//! be rolled out to a share of tenants (see [`FeatureFlag::evaluate`]). Stored
+//! flags are cached in memory: they are read at startup, updated on every
+//! admin write and re-read on `POST /admin/feature-flags/reload`.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::config::FeatureFlags;
+use crate::db::models::FeatureFlag;
+use crate::db::repositories::{FeatureFlagError, FeatureFlagRepository};
+use crate::middleware::ApiKeyInfo;
+
+/// Programmatic tool calling (code execution containers)
+pub const FLAG_PTC: &str = "ptc";
+/// Automatic prompt cache breakpoints
+pub const FLAG_PROMPT_CACHING: &str = "prompt_caching";
+/// Tool use
+pub const FLAG_TOOL_USE: &str = "tool_use";
+/// Extended thinking
+pub const FLAG_EXTENDED_THINKING: &str = "extended_thinking";
+/// Document content blocks
+pub const FLAG_DOCUMENT_SUPPORT: &str = "document_support";
+/// Copies of sampled conversations sent to the evaluation sink (off unless stored)
+pub const FLAG_EVAL_CAPTURE: &str = "eval_capture";
+
+/// Per-key feature flag evaluation backed by the feature flags table
+pub struct FeatureFlagService {
+    defaults: FeatureFlags,
+    repo: Option<FeatureFlagRepository>,
+    flags: RwLock<HashMap<String, FeatureFlag>>,
+}
+
+impl FeatureFlagService {
+    /// Service answering from the static settings until flags are stored
+    pub fn new(defaults: FeatureFlags) -> Self {
+        Self {
+            defaults,
+            repo: None,
+            flags: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Persist flags to (and reload them from) the feature flags table
+    pub fn with_repository(mut self, repo: FeatureFlagRepository) -> Self {
+        self.repo = Some(repo);
+        self
+    }
+
+    /// Replace the cached flags with the stored ones, returning their count
+    pub async fn reload(&self) -> Result<usize, FeatureFlagError> {
+        let Some(ref repo) = self.repo else {
+            return Ok(self.flags.read().unwrap().len());
+        };
+        let stored = repo.list_all().await?;
+        let count = stored.len();
+        *self.flags.write().unwrap() = stored
+            .into_iter()
+            .map(|flag| (flag.name.clone(), flag))
+            .collect();
+        Ok(count)
+    }
+
+    /// All stored flags, by name
+    pub fn list(&self) -> Vec<FeatureFlag> {
+        let mut flags: Vec<FeatureFlag> = self.flags.read().unwrap().values().cloned().collect();
+        flags.sort_by(|a, b| a.name.cmp(&b.name));
+        flags
+    }
+
+    /// A stored flag
+    pub fn get(&self, name: &str) -> Option<FeatureFlag> {
+        self.flags.read().unwrap().get(name).cloned()
+    }
+
+    /// Store a flag, replacing any previous definition
+    pub async fn upsert(&self, mut flag: FeatureFlag) -> Result<FeatureFlag, FeatureFlagError> {
+        flag.rollout_percent = flag.rollout_percent.min(100);
+        flag.updated_at = Some(Utc::now().timestamp());
+        if let Some(ref repo) = self.repo {
+            repo.put(&flag).await?;
+        }
+        self.flags
+            .write()
+            .unwrap()
+            .insert(flag.name.clone(), flag.clone());
+        Ok(flag)
+    }
+
+    /// Delete a stored flag; the static default applies again afterwards
+    pub async fn remove(&self, name: &str) -> Result<bool, FeatureFlagError> {
+        if !self.flags.read().unwrap().contains_key(name) {
+            return Ok(false);
+        }
+        if let Some(ref repo) = self.repo {
+            repo.delete(name).await?;
+        }
+        Ok(self.flags.write().unwrap().remove(name).is_some())
+    }
+
+    /// Value of a flag from the static settings (unknown flags are off)
+    pub fn static_default(&self, name: &str) -> bool {
+        match name {
+            FLAG_PTC => self.defaults.enable_ptc,
+            FLAG_PROMPT_CACHING => self.defaults.prompt_caching_enabled,
+            FLAG_TOOL_USE => self.defaults.enable_tool_use,
+            FLAG_EXTENDED_THINKING => self.defaults.enable_extended_thinking,
+            FLAG_DOCUMENT_SUPPORT => self.defaults.enable_document_support,
+            _ => false,
+        }
+    }
+
+    /// Whether a flag is on for the calling key
+    pub fn is_enabled(&self, name: &str, key_info: &ApiKeyInfo) -> bool {
+        match self.flags.read().unwrap().get(name) {
+            Some(flag) => flag.evaluate(&key_info.api_key, &key_info.user_id),
+            None => self.static_default(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(api_key: &str, user_id: &str) -> ApiKeyInfo {
+        let mut key = ApiKeyInfo::anonymous();
+        key.api_key = api_key.to_string();
+        key.user_id = user_id.to_string();
+        key
+    }
+
+    #[tokio::test]
+    async fn test_stored_flag_overrides_static_default() {
+        let service = FeatureFlagService::new(FeatureFlags::default());
+        let caller = key("sk-a", "team-a");
+        assert!(service.is_enabled(FLAG_PROMPT_CACHING, &caller));
+        assert!(!service.is_enabled(FLAG_PTC, &caller));
+        assert!(!service.is_enabled("semantic_cache", &caller));
+
+        service
+            .upsert(FeatureFlag {
+                name: FLAG_PTC.to_string(),
+                enabled_tenants: vec!["team-a".to_string()],
+                rollout_percent: 150,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(service.is_enabled(FLAG_PTC, &caller));
+        let stored = service.get(FLAG_PTC).unwrap();
+        assert_eq!(stored.rollout_percent, 100);
+        assert!(stored.updated_at.is_some());
+
+        service
+            .upsert(FeatureFlag {
+                name: FLAG_PROMPT_CACHING.to_string(),
+                disabled_keys: vec!["sk-a".to_string()],
+                enabled: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(!service.is_enabled(FLAG_PROMPT_CACHING, &caller));
+        assert!(service.is_enabled(FLAG_PROMPT_CACHING, &key("sk-b", "team-a")));
+        assert_eq!(service.list().len(), 2);
+
+        assert!(service.remove(FLAG_PROMPT_CACHING).await.unwrap());
+        assert!(!service.remove(FLAG_PROMPT_CACHING).await.unwrap());
+        assert!(service.is_enabled(FLAG_PROMPT_CACHING, &caller));
+    }
+}
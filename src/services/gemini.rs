@@ -8,8 +8,11 @@ use crate::schemas::gemini::{GeminiError, GeminiRequest, GeminiResponse, StreamC
 use crate::services::backend_pool::{
     ApiKeyCredential, Credential, CredentialPool, LoadBalanceStrategy, PoolConfig,
 };
+use crate::services::routing_metrics::RoutingOutcome;
+use crate::utils::SseDataDecoder;
 use reqwest::Client;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 // ============================================================================
@@ -63,11 +66,17 @@ pub struct GeminiConfig {
     /// Load balance strategy
     pub strategy: LoadBalanceStrategy,
 
-    /// Maximum failures before disabling a credential
+    /// Consecutive failures that open a credential's circuit
     pub max_failures: u32,
 
-    /// Seconds to wait before retrying a disabled credential
+    /// Seconds a credential's circuit stays open before it is probed
     pub retry_after_secs: u64,
+
+    /// Seconds a rate-limited key sits out when Gemini gives no retry delay
+    pub rate_limit_cooldown_secs: u64,
+
+    /// Canary successes that close a credential's open circuit
+    pub probe_successes: u32,
 }
 
 impl GeminiConfig {
@@ -80,6 +89,8 @@ impl GeminiConfig {
             strategy: LoadBalanceStrategy::RoundRobin,
             max_failures: 3,
             retry_after_secs: 300,
+            rate_limit_cooldown_secs: 60,
+            probe_successes: 2,
         }
     }
 
@@ -92,6 +103,8 @@ impl GeminiConfig {
             strategy: LoadBalanceStrategy::RoundRobin,
             max_failures: 3,
             retry_after_secs: 300,
+            rate_limit_cooldown_secs: 60,
+            probe_successes: 2,
         }
     }
 
@@ -119,6 +132,16 @@ impl GeminiConfig {
         self.retry_after_secs = secs;
         self
     }
+
+    pub fn with_rate_limit_cooldown(mut self, secs: u64) -> Self {
+        self.rate_limit_cooldown_secs = secs;
+        self
+    }
+
+    pub fn with_probe_successes(mut self, successes: u32) -> Self {
+        self.probe_successes = successes;
+        self
+    }
 }
 
 /// Service for interacting with Google Gemini API
@@ -166,7 +189,9 @@ impl GeminiService {
         // Create pool config
         let pool_config = PoolConfig::new(config.strategy)
             .with_max_failures(config.max_failures)
-            .with_retry_after(config.retry_after_secs);
+            .with_retry_after(config.retry_after_secs)
+            .with_rate_limit_cooldown(config.rate_limit_cooldown_secs)
+            .with_probe_successes(config.probe_successes);
 
         let credential_pool = CredentialPool::new(credentials, pool_config);
 
@@ -206,7 +231,7 @@ impl GeminiService {
     }
 
     /// Record a failed request for a credential
-    /// Returns true if the credential was disabled due to max failures
+    /// Returns true if the failure opened the credential's circuit
     pub fn record_failure(&self, credential_name: &str) -> bool {
         self.credential_pool.record_failure(credential_name)
     }
@@ -216,86 +241,123 @@ impl GeminiService {
         self.credential_pool.stats()
     }
 
+    /// Send a request, rotating to the next key when one is rate limited or failing
+    ///
+    /// A 429 puts the key in cooldown for the `RetryInfo` delay Gemini sends
+    /// (quota exhausted), while 5xx responses and connection errors count
+    /// toward disabling it. Either way the request is retried once per
+    /// remaining key. Returns the successful response and how it was routed:
+    /// the serving key, the attempts made and the time the failed ones took.
+    async fn send(
+        &self,
+        url: &str,
+        request: &impl serde::Serialize,
+    ) -> Result<(reqwest::Response, RoutingOutcome), GeminiServiceError> {
+        let mut tried: Vec<String> = Vec::new();
+        let started = Instant::now();
+        let mut failed_time = Duration::ZERO;
+
+        loop {
+            let attempt_started = Instant::now();
+            let credential = self.get_credential()?;
+            let credential_name = credential.name().to_string();
+            let api_key = credential.api_key().to_string();
+            tried.push(credential_name.clone());
+
+            tracing::debug!(url = %url, credential = %credential_name, "Calling Gemini API");
+
+            let result = self
+                .client
+                .post(url)
+                .header("x-goog-api-key", &api_key)
+                .header("Content-Type", "application/json")
+                .json(request)
+                .send()
+                .await;
+
+            let error = match result {
+                Ok(resp) if resp.status().is_success() => {
+                    let failed_attempts = tried.len() as u32 - 1;
+                    if failed_attempts > 0 {
+                        tracing::info!(
+                            credential = %credential_name,
+                            attempts = tried.len(),
+                            added_latency_ms = failed_time.as_millis() as u64,
+                            total_ms = started.elapsed().as_millis() as u64,
+                            "Gemini request served after key rotation"
+                        );
+                    }
+                    let outcome = RoutingOutcome::first_attempt("gemini")
+                        .with_credential(credential_name)
+                        .with_retries(failed_attempts, failed_time);
+                    return Ok((resp, outcome));
+                }
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    let error_text = resp.text().await.unwrap_or_default();
+                    let parsed = serde_json::from_str::<GeminiError>(&error_text).ok();
+
+                    if status == 429 {
+                        let retry_delay = parsed.as_ref().and_then(|e| e.error.retry_delay());
+                        self.credential_pool
+                            .record_rate_limited(&credential_name, retry_delay);
+                    } else if status >= 500 {
+                        self.record_failure(&credential_name);
+                    } else {
+                        // The request itself was rejected; another key won't help
+                        return Err(api_error(status, error_text, parsed));
+                    }
+                    api_error(status, error_text, parsed)
+                }
+                Err(e) => {
+                    // Record failure on connection/timeout errors
+                    self.record_failure(&credential_name);
+                    GeminiServiceError::HttpError(e)
+                }
+            };
+
+            failed_time += attempt_started.elapsed();
+
+            let more_keys = self.credential_pool.healthy_count() > 0
+                && tried.len() < self.credential_pool.len();
+            if !more_keys {
+                return Err(error);
+            }
+            tracing::warn!(
+                credential = %credential_name,
+                error = %error,
+                "Gemini request failed, retrying with next key"
+            );
+        }
+    }
+
     /// Generate content (non-streaming)
     ///
     /// # Arguments
     /// * `model` - Model name (e.g., "gemini-2.0-flash")
     /// * `request` - The request body
+    ///
+    /// Returns the response and how the request was routed across keys
     pub async fn generate_content(
         &self,
         model: &str,
         request: &GeminiRequest,
-    ) -> Result<GeminiResponse, GeminiServiceError> {
-        let credential = self.get_credential()?;
-        let credential_name = credential.name().to_string();
-        let api_key = credential.api_key().to_string();
-
+    ) -> Result<(GeminiResponse, RoutingOutcome), GeminiServiceError> {
         let url = format!("{}/models/{}:generateContent", self.base_url(), model);
+        let (resp, outcome) = self.send(&url, request).await?;
 
-        tracing::debug!(
-            model = %model,
-            url = %url,
-            credential = %credential_name,
-            "Calling Gemini generateContent API"
-        );
-
-        let response = self
-            .client
-            .post(&url)
-            .header("x-goog-api-key", &api_key)
-            .header("Content-Type", "application/json")
-            .json(request)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-
-                    // Record failure for rate limit or server errors
-                    if status.as_u16() == 429 || status.as_u16() >= 500 {
-                        let disabled = self.record_failure(&credential_name);
-                        if disabled {
-                            tracing::warn!(
-                                credential = %credential_name,
-                                "Credential disabled due to repeated failures"
-                            );
-                        }
-                    }
-
-                    // Try to parse as Gemini error
-                    if let Ok(gemini_error) = serde_json::from_str::<GeminiError>(&error_text) {
-                        return Err(GeminiServiceError::ApiError {
-                            code: gemini_error.error.code,
-                            message: gemini_error.error.message,
-                        });
-                    }
-
-                    return Err(GeminiServiceError::ApiError {
-                        code: status.as_u16() as i32,
-                        message: error_text,
-                    });
-                }
-
-                // Record success
-                self.record_success(&credential_name);
+        // Record success
+        if let Some(ref credential_name) = outcome.credential {
+            self.record_success(credential_name);
+        }
 
-                let response_text = resp.text().await?;
+        let response_text = resp.text().await?;
 
-                serde_json::from_str(&response_text).map_err(|e| {
-                    tracing::error!(error = %e, body = %response_text, "Failed to parse Gemini response");
-                    GeminiServiceError::ParseError(e.to_string())
-                })
-            }
-            Err(e) => {
-                // Record failure on connection/timeout errors
-                self.record_failure(&credential_name);
-                Err(GeminiServiceError::HttpError(e))
-            }
-        }
+        let response = serde_json::from_str(&response_text).map_err(|e| {
+            tracing::error!(error = %e, body = %response_text, "Failed to parse Gemini response");
+            GeminiServiceError::ParseError(e.to_string())
+        })?;
+        Ok((response, outcome))
     }
 
     /// Generate content with streaming
@@ -304,70 +366,55 @@ impl GeminiService {
     /// * `model` - Model name (e.g., "gemini-2.0-flash")
     /// * `request` - The request body
     ///
-    /// Returns a tuple of (stream, credential_name) so the caller can record success/failure
+    /// Returns the stream and its routing outcome; the outcome names the
+    /// serving key so the caller can record success/failure
     pub async fn generate_content_stream(
         &self,
         model: &str,
         request: &GeminiRequest,
-    ) -> Result<(GeminiStream, String), GeminiServiceError> {
-        let credential = self.get_credential()?;
-        let credential_name = credential.name().to_string();
-        let api_key = credential.api_key().to_string();
-
+    ) -> Result<(GeminiStream, RoutingOutcome), GeminiServiceError> {
         let url = format!(
             "{}/models/{}:streamGenerateContent?alt=sse",
             self.base_url(),
             model
         );
+        let (resp, outcome) = self.send(&url, request).await?;
 
-        tracing::debug!(
-            model = %model,
-            url = %url,
-            credential = %credential_name,
-            "Calling Gemini streamGenerateContent API"
-        );
-
-        let response = self
-            .client
-            .post(&url)
-            .header("x-goog-api-key", &api_key)
-            .header("Content-Type", "application/json")
-            .json(request)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-
-                    // Record failure for rate limit or server errors
-                    if status.as_u16() == 429 || status.as_u16() >= 500 {
-                        self.record_failure(&credential_name);
-                    }
-
-                    if let Ok(gemini_error) = serde_json::from_str::<GeminiError>(&error_text) {
-                        return Err(GeminiServiceError::ApiError {
-                            code: gemini_error.error.code,
-                            message: gemini_error.error.message,
-                        });
-                    }
-
-                    return Err(GeminiServiceError::ApiError {
-                        code: status.as_u16() as i32,
-                        message: error_text,
-                    });
-                }
+        Ok((GeminiStream::new(resp), outcome))
+    }
 
-                Ok((GeminiStream::new(resp), credential_name))
-            }
-            Err(e) => {
-                self.record_failure(&credential_name);
-                Err(GeminiServiceError::HttpError(e))
-            }
+    /// Count the prompt tokens of a request with Gemini's tokenizer
+    ///
+    /// # Arguments
+    /// * `model` - Model name (e.g., "gemini-2.0-flash")
+    /// * `request` - The request that would be sent to `generateContent`
+    pub async fn count_tokens(
+        &self,
+        model: &str,
+        request: &GeminiRequest,
+    ) -> Result<i32, GeminiServiceError> {
+        let url = format!("{}/models/{}:countTokens", self.base_url(), model);
+        let mut generate_request = serde_json::to_value(request)
+            .map_err(|e| GeminiServiceError::ParseError(e.to_string()))?;
+        if let Some(fields) = generate_request.as_object_mut() {
+            fields.insert("model".to_string(), format!("models/{}", model).into());
+        }
+        let body = serde_json::json!({ "generateContentRequest": generate_request });
+        let (resp, outcome) = self.send(&url, &body).await?;
+        if let Some(ref credential_name) = outcome.credential {
+            self.record_success(credential_name);
         }
+
+        let counted: serde_json::Value = resp.json().await?;
+        counted
+            .get("totalTokens")
+            .and_then(|v| v.as_i64())
+            .map(|total| total as i32)
+            .ok_or_else(|| {
+                GeminiServiceError::ParseError(
+                    "countTokens response without totalTokens".to_string(),
+                )
+            })
     }
 
     /// Check if the service is healthy (at least one credential available)
@@ -386,6 +433,20 @@ impl GeminiService {
     }
 }
 
+/// Error for a non-success response, preferring Gemini's own error body
+fn api_error(status: u16, body: String, parsed: Option<GeminiError>) -> GeminiServiceError {
+    match parsed {
+        Some(gemini_error) => GeminiServiceError::ApiError {
+            code: gemini_error.error.code,
+            message: gemini_error.error.message,
+        },
+        None => GeminiServiceError::ApiError {
+            code: status as i32,
+            message: body,
+        },
+    }
+}
+
 // ============================================================================
 // Streaming Support
 // ============================================================================
@@ -393,55 +454,30 @@ impl GeminiService {
 /// A stream of Gemini response chunks
 pub struct GeminiStream {
     response: reqwest::Response,
-    buffer: String,
+    decoder: GeminiSseDecoder,
 }
 
 impl GeminiStream {
     fn new(response: reqwest::Response) -> Self {
         Self {
             response,
-            buffer: String::new(),
+            decoder: GeminiSseDecoder::new(),
         }
     }
 
     /// Receive the next chunk from the stream
     pub async fn recv(&mut self) -> Result<Option<StreamChunk>, GeminiServiceError> {
         loop {
-            // Check if we have a complete event in the buffer
-            if let Some(pos) = self.buffer.find("\n\n") {
-                let event = self.buffer[..pos].to_string();
-                self.buffer = self.buffer[pos + 2..].to_string();
-
-                // Parse SSE event
-                if let Some(data) = event.strip_prefix("data: ") {
-                    let data = data.trim();
-
-                    // Skip empty data or [DONE]
-                    if data.is_empty() || data == "[DONE]" {
-                        continue;
-                    }
-
-                    match serde_json::from_str::<StreamChunk>(data) {
-                        Ok(chunk) => return Ok(Some(chunk)),
-                        Err(e) => {
-                            tracing::warn!(error = %e, data = %data, "Failed to parse stream chunk");
-                            continue;
-                        }
-                    }
-                }
-                continue;
+            if let Some(chunk) = self.decoder.next_chunk() {
+                return Ok(Some(chunk));
             }
 
             // Read more data from the response
             match self.response.chunk().await {
-                Ok(Some(chunk)) => {
-                    if let Ok(text) = String::from_utf8(chunk.to_vec()) {
-                        self.buffer.push_str(&text);
-                    }
-                }
+                Ok(Some(bytes)) => self.decoder.push(&bytes),
                 Ok(None) => {
-                    // Stream ended
-                    return Ok(None);
+                    // Stream ended; a final event may lack its trailing blank line
+                    return Ok(self.decoder.finish());
                 }
                 Err(e) => {
                     return Err(GeminiServiceError::StreamError(e.to_string()));
@@ -451,6 +487,58 @@ impl GeminiStream {
     }
 }
 
+/// Incremental decoder for Gemini `alt=sse` response bodies
+///
+/// Splits events with [`SseDataDecoder`] and parses each payload as a
+/// [`StreamChunk`]; `[DONE]` and unparseable payloads are skipped.
+#[derive(Debug, Default)]
+pub struct GeminiSseDecoder {
+    sse: SseDataDecoder,
+}
+
+impl GeminiSseDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append raw bytes read from the response body
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.sse.push(bytes);
+    }
+
+    /// Take the next complete chunk, if one is buffered
+    pub fn next_chunk(&mut self) -> Option<StreamChunk> {
+        while let Some(data) = self.sse.next_data() {
+            if let Some(chunk) = parse_chunk(&data) {
+                return Some(chunk);
+            }
+        }
+        None
+    }
+
+    /// Flush a trailing event left unterminated at end of stream
+    pub fn finish(&mut self) -> Option<StreamChunk> {
+        self.next_chunk()
+            .or_else(|| self.sse.finish().and_then(|data| parse_chunk(&data)))
+    }
+}
+
+/// Parse one event payload
+fn parse_chunk(data: &str) -> Option<StreamChunk> {
+    if data == "[DONE]" {
+        return None;
+    }
+
+    match serde_json::from_str::<StreamChunk>(data) {
+        Ok(chunk) => Some(chunk),
+        Err(e) => {
+            tracing::warn!(error = %e, data = %data, "Failed to parse stream chunk");
+            None
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -491,6 +579,29 @@ mod tests {
         assert!(service.health_check());
     }
 
+    #[test]
+    fn test_quota_error_retry_delay() {
+        let body = r#"{"error": {
+            "code": 429,
+            "message": "Resource has been exhausted (e.g. check quota).",
+            "status": "RESOURCE_EXHAUSTED",
+            "details": [
+                {"@type": "type.googleapis.com/google.rpc.QuotaFailure", "violations": []},
+                {"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "34s"}
+            ]
+        }}"#;
+        let error: GeminiError = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            error.error.retry_delay(),
+            Some(std::time::Duration::from_secs(34))
+        );
+
+        let error: GeminiError =
+            serde_json::from_str(r#"{"error": {"code": 500, "message": "Internal error"}}"#)
+                .unwrap();
+        assert_eq!(error.error.retry_delay(), None);
+    }
+
     #[test]
     fn test_gemini_service_empty_keys_error() {
         let config = GeminiConfig::with_keys(vec![]);
@@ -0,0 +1,133 @@
+//! In-flight generation registry
+//!
+//! Streaming generations are registered under the ID the client sees in the
+//! first event (`msg_...` for messages, `chatcmpl-...` for chat completions),
+//! so `POST /v1/messages/{id}/cancel` and
+//! `POST /v1/chat/completions/{id}/cancel` can stop them. Cancelling drops the
+//! backend stream and ends the client's stream cleanly with the usage
+//! generated so far.
+//!
+//! Non-streaming requests are not registered: their ID only reaches the
+//! client once the generation has finished.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug)]
+struct Generation {
+    /// User ID of the key that started the generation
+    owner: String,
+    token: CancellationToken,
+}
+
+/// Registry of cancellable in-flight generations
+#[derive(Debug, Default)]
+pub struct GenerationRegistry {
+    generations: Mutex<HashMap<String, Generation>>,
+}
+
+impl GenerationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a generation; it is unregistered when the guard is dropped
+    pub fn register(self: &Arc<Self>, id: impl Into<String>, owner: &str) -> GenerationGuard {
+        let id = id.into();
+        let token = CancellationToken::new();
+        self.lock().insert(
+            id.clone(),
+            Generation {
+                owner: owner.to_string(),
+                token: token.clone(),
+            },
+        );
+        GenerationGuard {
+            registry: self.clone(),
+            id,
+            token,
+        }
+    }
+
+    /// Cancel a generation visible to `owner` (None = any owner), returning
+    /// false if no such generation is in flight
+    pub fn cancel(&self, id: &str, owner: Option<&str>) -> bool {
+        match self.lock().get(id) {
+            Some(generation) if owner.is_none() || owner == Some(generation.owner.as_str()) => {
+                generation.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Number of in-flight generations
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if no generations are in flight
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Generation>> {
+        self.generations.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registration of one generation, held by its stream
+#[derive(Debug)]
+pub struct GenerationGuard {
+    registry: Arc<GenerationRegistry>,
+    id: String,
+    token: CancellationToken,
+}
+
+impl GenerationGuard {
+    /// Generation ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the generation has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Wait until the generation is cancelled
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+}
+
+impl Drop for GenerationGuard {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_and_unregister() {
+        let registry = Arc::new(GenerationRegistry::new());
+        let guard = registry.register("msg_1", "alice");
+        assert_eq!(registry.len(), 1);
+
+        assert!(!registry.cancel("msg_1", Some("bob")));
+        assert!(!registry.cancel("msg_2", None));
+        assert!(!guard.is_cancelled());
+
+        assert!(registry.cancel("msg_1", Some("alice")));
+        assert!(guard.is_cancelled());
+        guard.cancelled().await;
+
+        drop(guard);
+        assert!(registry.is_empty());
+        assert!(!registry.cancel("msg_1", None));
+    }
+}
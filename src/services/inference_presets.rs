@@ -0,0 +1,387 @@
+//! Default inference parameter presets
+//!
+//! Operators store named presets of sampling defaults (temperature, top_p,
+//! an extended thinking budget and a system prompt) through
+//! `/admin/inference-presets`. A preset applies to the requests of API keys
+//! that name it in their `inference_preset` field, and to requests for the
+//! model aliases (exact names or `*` patterns) it lists.
+//!
+//! Presets only fill in what the client left out: a value in the request
+//! always wins. When both the key's preset and a model preset supply a
+//! parameter, the key's preset wins. Presets are stored through the storage
+//! backend and loaded into the registry at startup; the admin endpoints
+//! write to storage before updating the registry.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::schemas::anthropic::{MessageRequest, SystemContent, ThinkingConfig};
+use crate::schemas::openai::{ChatCompletionRequest, ChatMessage, ChatRole, MessageContent};
+
+use super::provider::model_matches_pattern;
+
+/// Longest preset name, in characters
+const MAX_NAME_CHARS: usize = 64;
+
+/// Smallest extended thinking budget Anthropic accepts
+const MIN_THINKING_BUDGET: i32 = 1024;
+
+/// Parameters a preset supplies when the request omits them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Extended thinking budget in tokens (Messages API only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+}
+
+impl PresetParams {
+    /// Check the values are ones the backends accept
+    pub fn validate(&self) -> Result<(), String> {
+        if self.temperature.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err("'temperature' must be between 0 and 1".to_string());
+        }
+        if self.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            return Err("'top_p' must be between 0 and 1".to_string());
+        }
+        if self.thinking_budget.is_some_and(|b| b < MIN_THINKING_BUDGET) {
+            return Err(format!("'thinking_budget' must be at least {}", MIN_THINKING_BUDGET));
+        }
+        Ok(())
+    }
+
+    /// These parameters, with the gaps filled from `fallback`
+    fn or(self, fallback: &PresetParams) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            thinking_budget: self.thinking_budget.or(fallback.thinking_budget),
+            system: self.system.or_else(|| fallback.system.clone()),
+        }
+    }
+
+    /// Fill in the parameters a Messages request omits; returns the ones set
+    ///
+    /// The thinking budget is only applied when it leaves room under the
+    /// request's `max_tokens`. Extended thinking does not allow sampling
+    /// changes, so temperature and top_p are not applied to requests that
+    /// end up with thinking enabled.
+    pub fn apply_to_message(&self, request: &mut MessageRequest) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if let Some(budget) = self.thinking_budget {
+            if request.thinking.is_none() && request.max_tokens > budget {
+                request.thinking = Some(ThinkingConfig {
+                    thinking_type: "enabled".to_string(),
+                    budget_tokens: Some(budget),
+                });
+                applied.push("thinking");
+            }
+        }
+        let thinking = request
+            .thinking
+            .as_ref()
+            .is_some_and(|t| t.thinking_type == "enabled");
+        if !thinking {
+            if request.temperature.is_none() && self.temperature.is_some() {
+                request.temperature = self.temperature;
+                applied.push("temperature");
+            }
+            if request.top_p.is_none() && self.top_p.is_some() {
+                request.top_p = self.top_p;
+                applied.push("top_p");
+            }
+        }
+        if request.system.is_none() && self.system.is_some() {
+            request.system = self.system.clone().map(SystemContent::Text);
+            applied.push("system");
+        }
+        applied
+    }
+
+    /// Fill in the parameters a Chat Completions request omits; returns the ones set
+    ///
+    /// The system prompt is added when the request has no system message.
+    pub fn apply_to_chat(&self, request: &mut ChatCompletionRequest) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if request.temperature.is_none() && self.temperature.is_some() {
+            request.temperature = self.temperature;
+            applied.push("temperature");
+        }
+        if request.top_p.is_none() && self.top_p.is_some() {
+            request.top_p = self.top_p;
+            applied.push("top_p");
+        }
+        if let Some(ref system) = self.system {
+            if !request.messages.iter().any(|m| m.role == ChatRole::System) {
+                request.messages.insert(
+                    0,
+                    ChatMessage {
+                        role: ChatRole::System,
+                        content: Some(MessageContent::Text(system.clone())),
+                        name: None,
+                        tool_calls: None,
+                        tool_call_id: None,
+                    },
+                );
+                applied.push("system");
+            }
+        }
+        applied
+    }
+}
+
+/// A named preset and the model aliases it is bound to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferencePreset {
+    pub name: String,
+    #[serde(flatten)]
+    pub params: PresetParams,
+    /// Model aliases (exact names or patterns) whose requests get this preset
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+impl InferencePreset {
+    /// Check the name and parameter values
+    pub fn validate(&self) -> Result<(), String> {
+        let name_chars = self.name.chars().count();
+        if name_chars == 0 || name_chars > MAX_NAME_CHARS {
+            return Err(format!("Preset name must be 1 to {} characters", MAX_NAME_CHARS));
+        }
+        self.params.validate()
+    }
+}
+
+/// Defaults resolved for one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPreset {
+    /// Presets that contributed, highest precedence first
+    pub names: Vec<String>,
+    pub params: PresetParams,
+}
+
+/// Inference presets by name
+#[derive(Default)]
+pub struct InferencePresetRegistry {
+    presets: RwLock<HashMap<String, InferencePreset>>,
+}
+
+impl InferencePresetRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All presets, by name
+    pub fn list(&self) -> Vec<InferencePreset> {
+        let mut presets: Vec<_> = self.presets.read().unwrap().values().cloned().collect();
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        presets
+    }
+
+    pub fn get(&self, name: &str) -> Option<InferencePreset> {
+        self.presets.read().unwrap().get(name).cloned()
+    }
+
+    /// Store a preset, replacing any previous one with the same name
+    pub fn upsert(&self, preset: InferencePreset) -> Result<(), String> {
+        preset.validate()?;
+        self.presets
+            .write()
+            .unwrap()
+            .insert(preset.name.clone(), preset);
+        Ok(())
+    }
+
+    /// Delete a preset
+    pub fn remove(&self, name: &str) -> bool {
+        self.presets.write().unwrap().remove(name).is_some()
+    }
+
+    /// Store presets read from storage, skipping invalid ones; returns the number stored
+    pub fn load(&self, presets: Vec<InferencePreset>) -> usize {
+        presets
+            .into_iter()
+            .filter(|preset| match self.upsert(preset.clone()) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!(preset = %preset.name, error = %e, "Skipping invalid stored inference preset");
+                    false
+                }
+            })
+            .count()
+    }
+
+    /// Defaults for a request for `model` from a key with preset `key_preset`
+    ///
+    /// The key's preset takes precedence over the model's. Of the presets
+    /// bound to the model, one naming it exactly wins over a pattern; among
+    /// patterns the first by name wins. A key naming a preset that does not
+    /// exist only gets the model's defaults.
+    pub fn resolve(&self, key_preset: Option<&str>, model: &str) -> Option<ResolvedPreset> {
+        let presets = self.presets.read().unwrap();
+        let key_preset = key_preset.and_then(|name| presets.get(name));
+
+        let mut bound: Vec<&InferencePreset> = presets
+            .values()
+            .filter(|p| p.models.iter().any(|m| model_matches_pattern(model, m)))
+            .collect();
+        bound.sort_by(|a, b| a.name.cmp(&b.name));
+        let model_preset = bound
+            .iter()
+            .find(|p| p.models.iter().any(|m| m == model))
+            .or_else(|| bound.first())
+            .copied();
+
+        let mut resolved: Option<ResolvedPreset> = None;
+        for preset in [key_preset, model_preset].into_iter().flatten() {
+            resolved = Some(match resolved {
+                None => ResolvedPreset {
+                    names: vec![preset.name.clone()],
+                    params: preset.params.clone(),
+                },
+                Some(r) if r.names.contains(&preset.name) => r,
+                Some(mut r) => {
+                    r.names.push(preset.name.clone());
+                    r.params = r.params.or(&preset.params);
+                    r
+                }
+            });
+        }
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(name: &str, params: PresetParams, models: &[&str]) -> InferencePreset {
+        InferencePreset {
+            name: name.to_string(),
+            params,
+            models: models.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    fn message_request(body: serde_json::Value) -> MessageRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let registry = InferencePresetRegistry::new();
+        registry
+            .upsert(preset(
+                "support",
+                PresetParams {
+                    temperature: Some(0.2),
+                    system: Some("Be brief.".to_string()),
+                    ..Default::default()
+                },
+                &[],
+            ))
+            .unwrap();
+        registry
+            .upsert(preset(
+                "sonnet",
+                PresetParams {
+                    temperature: Some(0.7),
+                    top_p: Some(0.9),
+                    ..Default::default()
+                },
+                &["claude-sonnet-*"],
+            ))
+            .unwrap();
+        registry
+            .upsert(preset(
+                "sonnet-4-5",
+                PresetParams { top_p: Some(0.5), ..Default::default() },
+                &["claude-sonnet-4-5"],
+            ))
+            .unwrap();
+
+        // The exact model binding wins over the pattern
+        let resolved = registry.resolve(None, "claude-sonnet-4-5").unwrap();
+        assert_eq!(resolved.names, vec!["sonnet-4-5"]);
+
+        // The key's preset wins, the model's fills the gaps
+        let resolved = registry.resolve(Some("support"), "claude-sonnet-4").unwrap();
+        assert_eq!(resolved.names, vec!["support", "sonnet"]);
+        assert_eq!(resolved.params.temperature, Some(0.2));
+        assert_eq!(resolved.params.top_p, Some(0.9));
+        assert_eq!(resolved.params.system.as_deref(), Some("Be brief."));
+
+        assert!(registry.resolve(Some("missing"), "claude-haiku-4-5").is_none());
+        assert!(registry
+            .upsert(preset(
+                "hot",
+                PresetParams { temperature: Some(1.5), ..Default::default() },
+                &[],
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_client_values_take_precedence() {
+        let params = PresetParams {
+            temperature: Some(0.2),
+            top_p: Some(0.9),
+            thinking_budget: None,
+            system: Some("Be brief.".to_string()),
+        };
+
+        let mut request = message_request(serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "temperature": 1.0,
+            "system": "Client prompt",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }));
+        assert_eq!(params.apply_to_message(&mut request), vec!["top_p"]);
+        assert_eq!(request.temperature, Some(1.0));
+        assert_eq!(request.top_p, Some(0.9));
+        assert!(matches!(request.system, Some(SystemContent::Text(ref s)) if s == "Client prompt"));
+
+        let mut request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .unwrap();
+        assert_eq!(params.apply_to_chat(&mut request), vec!["temperature", "top_p", "system"]);
+        assert_eq!(request.messages[0].role, ChatRole::System);
+        assert_eq!(params.apply_to_chat(&mut request), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_thinking_budget_default() {
+        let params = PresetParams {
+            temperature: Some(0.2),
+            thinking_budget: Some(2048),
+            ..Default::default()
+        };
+        let body = |max_tokens: i32| {
+            message_request(serde_json::json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": max_tokens,
+                "messages": [{"role": "user", "content": "Hi"}]
+            }))
+        };
+
+        // Thinking replaces the sampling defaults
+        let mut request = body(8192);
+        assert_eq!(params.apply_to_message(&mut request), vec!["thinking"]);
+        assert_eq!(request.thinking.unwrap().budget_tokens, Some(2048));
+        assert_eq!(request.temperature, None);
+
+        // No room for the budget under max_tokens
+        let mut request = body(1024);
+        assert_eq!(params.apply_to_message(&mut request), vec!["temperature"]);
+        assert!(request.thinking.is_none());
+    }
+}
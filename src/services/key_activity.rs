@@ -0,0 +1,147 @@
+//! API key activity tracking (key hygiene)
+//!
+//! Authentication records each request's key and source IP into an in-memory
+//! buffer; a background task periodically flushes the buffer to the
+//! configured storage backend (last-used timestamp, request count, recent
+//! source IPs), so the request path never waits on storage for bookkeeping.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::KeyActivityConfig;
+use crate::db::{StorageBackend, StorageError};
+
+/// Activity buffered for one key since the last flush
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PendingActivity {
+    last_used_at: i64,
+    requests: i64,
+    /// Distinct source IPs, newest first
+    source_ips: Vec<String>,
+}
+
+/// Buffers per-key activity and flushes it to storage
+pub struct KeyActivityTracker {
+    storage: Arc<dyn StorageBackend>,
+    max_recent_ips: usize,
+    pending: Mutex<HashMap<String, PendingActivity>>,
+}
+
+impl KeyActivityTracker {
+    /// Create a new tracker
+    pub fn new(storage: Arc<dyn StorageBackend>, config: &KeyActivityConfig) -> Self {
+        Self {
+            storage,
+            max_recent_ips: config.max_recent_ips,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record one authenticated request (non-blocking)
+    pub fn record(&self, api_key: &str, source_ip: Option<IpAddr>) {
+        let now = Utc::now().timestamp();
+        let mut pending = self.pending.lock().unwrap();
+        let entry = pending.entry(api_key.to_string()).or_default();
+        entry.last_used_at = entry.last_used_at.max(now);
+        entry.requests += 1;
+        if let Some(ip) = source_ip {
+            entry.source_ips = merge_recent_ips(
+                &[ip.to_string()],
+                &entry.source_ips,
+                self.max_recent_ips,
+            );
+        }
+    }
+
+    /// Number of keys with unflushed activity
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Write all buffered activity to storage
+    ///
+    /// Returns the number of keys flushed. Failed writes are logged and dropped;
+    /// activity tracking is best-effort.
+    pub async fn flush(&self) -> usize {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let mut flushed = 0;
+
+        for (api_key, activity) in pending {
+            let existing = match self.storage.get_api_key(&api_key).await {
+                Ok(Some(key)) => key.recent_source_ips,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to load key for activity flush");
+                    continue;
+                }
+            };
+            let ips = merge_recent_ips(&activity.source_ips, &existing, self.max_recent_ips);
+
+            match self
+                .storage
+                .record_key_activity(&api_key, activity.last_used_at, activity.requests, &ips)
+                .await
+            {
+                Ok(()) => flushed += 1,
+                Err(StorageError::NotFound) => {}
+                Err(e) => tracing::warn!(error = %e, "Failed to record key activity"),
+            }
+        }
+
+        flushed
+    }
+
+    /// Spawn the periodic flush task
+    pub fn spawn_flush_loop(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let flushed = self.flush().await;
+                if flushed > 0 {
+                    tracing::debug!(keys = flushed, "Flushed key activity");
+                }
+            }
+        })
+    }
+}
+
+/// Merge newer source IPs in front of existing ones, de-duplicated and capped
+pub fn merge_recent_ips(newest: &[String], existing: &[String], max: usize) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::with_capacity(max);
+    for ip in newest.iter().chain(existing) {
+        if merged.len() >= max {
+            break;
+        }
+        if !merged.contains(ip) {
+            merged.push(ip.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_merge_recent_ips() {
+        let merged = merge_recent_ips(
+            &ips(&["10.0.0.3", "10.0.0.1"]),
+            &ips(&["10.0.0.1", "10.0.0.2"]),
+            5,
+        );
+        assert_eq!(merged, ips(&["10.0.0.3", "10.0.0.1", "10.0.0.2"]));
+
+        let capped = merge_recent_ips(&ips(&["a", "b"]), &ips(&["c", "d"]), 3);
+        assert_eq!(capped, ips(&["a", "b", "c"]));
+    }
+}
@@ -0,0 +1,525 @@
+//! Minimal MCP client
+//!
+//! Talks to remote MCP servers over the streamable HTTP transport so the
+//! agent runner can list and call their tools. Only the client side of
+//! `initialize`, `tools/list` and `tools/call` is implemented: every request
+//! is a JSON-RPC POST, and the server may answer with a JSON body or a short
+//! SSE stream carrying the response. The `Mcp-Session-Id` the server returns
+//! from `initialize` is sent on every later request.
+//!
+//! Server URLs come from API clients, so connections are restricted: the URL
+//! must be https, its host must pass the operator's allowlist
+//! ([`McpConfig`]), and every address it resolves to must be public. The
+//! session's client is pinned to the checked addresses and does not follow
+//! redirects, and error responses are reported by status only.
+
+use reqwest::{redirect, Client, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::McpConfig;
+use crate::utils::truncate_str;
+
+/// Protocol revision sent in `initialize`
+pub const MCP_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Session header assigned by the server
+pub const MCP_SESSION_HEADER: &str = "mcp-session-id";
+
+/// Protocol version header sent after `initialize`
+const MCP_PROTOCOL_HEADER: &str = "mcp-protocol-version";
+
+/// Timeout for each MCP request, including tool calls
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+/// Pages of `tools/list` followed before giving up on a server
+const MAX_TOOL_PAGES: usize = 20;
+
+/// Errors from talking to an MCP server
+#[derive(Debug, Error)]
+pub enum McpError {
+    #[error("MCP request failed: {0}")]
+    Request(String),
+
+    #[error("MCP server not allowed: {0}")]
+    NotAllowed(String),
+
+    #[error("MCP server returned HTTP {status}")]
+    Http { status: u16 },
+
+    #[error("MCP error {code}: {message}")]
+    Rpc { code: i64, message: String },
+
+    #[error("Invalid MCP response: {0}")]
+    InvalidResponse(String),
+}
+
+/// A tool offered by an MCP server
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "inputSchema", default = "empty_schema")]
+    pub input_schema: Value,
+}
+
+fn empty_schema() -> Value {
+    json!({"type": "object", "properties": {}})
+}
+
+impl McpTool {
+    /// Anthropic tool definition for the model
+    pub fn to_tool_definition(&self) -> Value {
+        let mut definition = json!({
+            "name": self.name,
+            "input_schema": self.input_schema,
+        });
+        if let Some(ref description) = self.description {
+            definition["description"] = json!(description);
+        }
+        definition
+    }
+}
+
+/// Result of `tools/call`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct McpToolResult {
+    #[serde(default)]
+    pub content: Vec<Value>,
+    #[serde(rename = "isError", default)]
+    pub is_error: bool,
+}
+
+impl McpToolResult {
+    /// The result as text for a `tool_result` block
+    ///
+    /// Text items are joined with newlines; other items (images, embedded
+    /// resources) are passed on as their JSON.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|item| match item.get("type").and_then(Value::as_str) {
+                Some("text") => item
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                _ => item.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Whether `host` matches an allowlist entry (exact, or `*.domain` for subdomains)
+fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    allowed_hosts.is_empty()
+        || allowed_hosts.iter().any(|entry| match entry.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+            None => host == entry,
+        })
+}
+
+/// Whether an address is publicly routable
+///
+/// Loopback, private, link-local (including the instance metadata
+/// endpoint), carrier-grade NAT, documentation, multicast and reserved
+/// ranges are not; IPv4-mapped and NAT64 IPv6 addresses are judged by the
+/// IPv4 address they carry.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ipv4(v4);
+            }
+            let segments = v6.segments();
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_ipv4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local
+                || segments[0] == 0x2001 && segments[1] == 0x0db8) // documentation
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // carrier-grade NAT
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b == 18 || b == 19)) // benchmarking
+        || a >= 240) // reserved
+}
+
+/// Check a server URL against the policy and resolve it
+///
+/// Returns the parsed URL and the addresses to connect to. Fails with
+/// [`McpError::NotAllowed`] for non-https URLs, hosts outside the allowlist
+/// and hosts resolving to any non-public address.
+pub async fn resolve_server(config: &McpConfig, url: &str) -> Result<(Url, Vec<SocketAddr>), McpError> {
+    let url = Url::parse(url).map_err(|e| McpError::NotAllowed(format!("invalid URL: {}", e)))?;
+    if url.scheme() != "https" {
+        return Err(McpError::NotAllowed("only https URLs are allowed".to_string()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| McpError::NotAllowed("URL has no host".to_string()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    if !host_allowed(&host, &config.allowed_hosts) {
+        return Err(McpError::NotAllowed(format!("host '{}' is not on the allowlist", host)));
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|e| McpError::Request(format!("failed to resolve '{}': {}", host, e)))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(McpError::Request(format!("'{}' did not resolve", host)));
+    }
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(McpError::NotAllowed(format!(
+            "host '{}' resolves to a non-public address",
+            host
+        )));
+    }
+    Ok((url, addrs))
+}
+
+/// An initialized session with one MCP server
+#[derive(Debug)]
+pub struct McpSession {
+    client: Client,
+    url: Url,
+    authorization_token: Option<String>,
+    session_id: Option<String>,
+    next_id: AtomicU64,
+}
+
+impl McpSession {
+    /// Connect to a server: `initialize`, then `notifications/initialized`
+    ///
+    /// The URL is checked with [`resolve_server`] first, and the session
+    /// only ever connects to the addresses checked there.
+    pub async fn connect(
+        config: &McpConfig,
+        url: &str,
+        authorization_token: Option<String>,
+    ) -> Result<Self, McpError> {
+        let (url, addrs) = resolve_server(config, url).await?;
+        let host = url.host_str().unwrap_or_default().to_string();
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .redirect(redirect::Policy::none())
+            .resolve_to_addrs(&host, &addrs)
+            .build()
+            .map_err(|e| McpError::Request(e.to_string()))?;
+
+        let mut session = Self {
+            client,
+            url,
+            authorization_token,
+            session_id: None,
+            next_id: AtomicU64::new(1),
+        };
+
+        let params = json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        });
+        let (result, session_id) = session.send("initialize", Some(params)).await?;
+        session.session_id = session_id;
+        tracing::debug!(
+            url = %session.url,
+            server = ?result.pointer("/serverInfo/name"),
+            "Initialized MCP session"
+        );
+
+        session.notify("notifications/initialized").await?;
+        Ok(session)
+    }
+
+    /// All tools the server offers, following `nextCursor` pages
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_TOOL_PAGES {
+            let params = cursor.as_ref().map(|c| json!({"cursor": c}));
+            let (result, _) = self.send("tools/list", params).await?;
+            let page: Vec<McpTool> = serde_json::from_value(
+                result.get("tools").cloned().unwrap_or_else(|| json!([])),
+            )
+            .map_err(|e| McpError::InvalidResponse(e.to_string()))?;
+            tools.extend(page);
+
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+        Err(McpError::InvalidResponse(format!(
+            "tools/list returned more than {} pages",
+            MAX_TOOL_PAGES
+        )))
+    }
+
+    /// Call a tool
+    pub async fn call_tool(&self, name: &str, arguments: &Value) -> Result<McpToolResult, McpError> {
+        let params = json!({"name": name, "arguments": arguments});
+        let (result, _) = self.send("tools/call", Some(params)).await?;
+        serde_json::from_value(result).map_err(|e| McpError::InvalidResponse(e.to_string()))
+    }
+
+    fn request(&self) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream");
+        if let Some(ref token) = self.authorization_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(ref session_id) = self.session_id {
+            request = request
+                .header(MCP_SESSION_HEADER, session_id)
+                .header(MCP_PROTOCOL_HEADER, MCP_PROTOCOL_VERSION);
+        }
+        request
+    }
+
+    /// Send a request and return its result and the session header, if any
+    async fn send(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<(Value, Option<String>), McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut body = json!({"jsonrpc": "2.0", "id": id, "method": method});
+        if let Some(params) = params {
+            body["params"] = params;
+        }
+
+        let response = self
+            .request()
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| McpError::Request(e.to_string()))?;
+        let status = response.status();
+        let session_id = response
+            .headers()
+            .get(MCP_SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| McpError::Request(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(http_error(&self.url, status, &text));
+        }
+        Ok((parse_response(&content_type, &text, id)?, session_id))
+    }
+
+    /// Send a notification, which has no response body
+    async fn notify(&self, method: &str) -> Result<(), McpError> {
+        let response = self
+            .request()
+            .json(&json!({"jsonrpc": "2.0", "method": method}))
+            .send()
+            .await
+            .map_err(|e| McpError::Request(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(http_error(&self.url, status, &text));
+        }
+        Ok(())
+    }
+}
+
+/// Error for a failed response; the body is logged, never returned to the caller
+fn http_error(url: &Url, status: reqwest::StatusCode, body: &str) -> McpError {
+    tracing::debug!(
+        url = %url,
+        status = status.as_u16(),
+        body = %truncate_str(body, 512),
+        "MCP server returned an error"
+    );
+    McpError::Http {
+        status: status.as_u16(),
+    }
+}
+
+/// Extract the result of request `id` from a JSON or SSE response body
+pub fn parse_response(content_type: &str, body: &str, id: u64) -> Result<Value, McpError> {
+    let messages: Vec<Value> = if content_type.starts_with("text/event-stream") {
+        body.lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .filter_map(|data| serde_json::from_str(data.trim()).ok())
+            .collect()
+    } else {
+        let message: Value = serde_json::from_str(body)
+            .map_err(|e| McpError::InvalidResponse(e.to_string()))?;
+        match message {
+            Value::Array(batch) => batch,
+            single => vec![single],
+        }
+    };
+
+    let response = messages
+        .into_iter()
+        .find(|message| message.get("id").and_then(Value::as_u64) == Some(id))
+        .ok_or_else(|| McpError::InvalidResponse(format!("no response to request {}", id)))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(McpError::Rpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| McpError::InvalidResponse("response has no result".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_and_sse_responses() {
+        let body = r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"search","inputSchema":{"type":"object"}}]}}"#;
+        let result = parse_response("application/json", body, 2).unwrap();
+        let tools: Vec<McpTool> = serde_json::from_value(result["tools"].clone()).unwrap();
+        assert_eq!(tools[0].name, "search");
+        assert_eq!(tools[0].to_tool_definition()["input_schema"]["type"], "object");
+
+        // Progress notifications may come ahead of the response on the stream
+        let sse = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n\
+                   event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":3,\"result\":{\"content\":[{\"type\":\"text\",\"text\":\"42\"}]}}\n\n";
+        let result = parse_response("text/event-stream", sse, 3).unwrap();
+        let call: McpToolResult = serde_json::from_value(result).unwrap();
+        assert_eq!(call.text(), "42");
+        assert!(!call.is_error);
+
+        assert!(matches!(
+            parse_response("application/json", body, 9),
+            Err(McpError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_rpc_error() {
+        let body = r#"{"jsonrpc":"2.0","id":4,"error":{"code":-32602,"message":"Unknown tool"}}"#;
+        match parse_response("application/json; charset=utf-8", body, 4) {
+            Err(McpError::Rpc { code, message }) => {
+                assert_eq!(code, -32602);
+                assert_eq!(message, "Unknown tool");
+            }
+            other => panic!("expected RPC error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_host_allowlist() {
+        assert!(host_allowed("mcp.example.com", &[]));
+
+        let allowed = vec!["mcp.example.com".to_string(), "*.tools.example.org".to_string()];
+        assert!(host_allowed("mcp.example.com", &allowed));
+        assert!(host_allowed("a.tools.example.org", &allowed));
+        assert!(!host_allowed("tools.example.org", &allowed));
+        assert!(!host_allowed("eviltools.example.org", &allowed));
+        assert!(!host_allowed("mcp.example.com.evil.net", &allowed));
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["8.8.8.8", "2606:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_server_rejects_unsafe_urls() {
+        let config = McpConfig::default();
+        for url in [
+            "http://mcp.example.com/mcp",
+            "https://127.0.0.1/mcp",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]:8443/mcp",
+            "not a url",
+        ] {
+            assert!(
+                matches!(resolve_server(&config, url).await, Err(McpError::NotAllowed(_))),
+                "{url}"
+            );
+        }
+
+        let config = McpConfig {
+            allowed_hosts: vec!["mcp.example.com".to_string()],
+        };
+        assert!(matches!(
+            resolve_server(&config, "https://8.8.8.8/mcp").await,
+            Err(McpError::NotAllowed(_))
+        ));
+    }
+}
@@ -1,36 +1,144 @@
 //! Services module
 //!
-//! Contains business logic and external service integrations.
+//! Contains business logic and external service integrations. Services on the
+//! gateway's request path (priority, tap, feature flags, usage and key
+//! tracking) need the `dynamodb` feature, the Gemini client needs `gemini`,
+//! and the PTC sandbox needs `ptc`.
 
+pub mod anthropic;
+pub mod azure_openai;
+pub mod backend;
+pub mod backend_hooks;
 pub mod backend_pool;
 pub mod bedrock;
+pub mod bedrock_clients;
 pub mod bedrock_provider;
+pub mod completion_store;
+pub mod content_offload;
 pub mod deepseek_provider;
+pub mod ephemeral_keys;
+#[cfg(feature = "dynamodb")]
+pub mod eval_sink;
+#[cfg(feature = "dynamodb")]
+pub mod feature_flags;
+#[cfg(feature = "gemini")]
 pub mod gemini;
+pub mod generations;
+pub mod inference_presets;
+#[cfg(feature = "gemini")]
 pub mod gemini_provider;
+#[cfg(feature = "dynamodb")]
+pub mod key_activity;
+pub mod mcp_client;
+pub mod model_capabilities;
+pub mod model_deprecations;
+pub mod model_routing;
 pub mod openai_provider;
+pub mod output_watchdog;
+pub mod pii_tokenizer;
+#[cfg(feature = "dynamodb")]
+pub mod priority;
 pub mod prompt_cache;
+pub mod prompt_dumps;
+pub mod prompt_templates;
 pub mod provider;
 pub mod provider_router;
+#[cfg(feature = "dynamodb")]
+pub mod pt_governor;
 pub mod ptc;
+#[cfg(feature = "dynamodb")]
+pub mod request_tap;
+pub mod request_coalescing;
+pub mod routing_metrics;
+pub mod service_tier;
+pub mod stream_assembly;
+pub mod stream_buffers;
+pub mod stream_recorder;
+pub mod token_counting;
+pub mod tool_cache;
+pub mod transcription;
+pub mod usage_reconciliation;
+#[cfg(feature = "dynamodb")]
 pub mod usage_tracker;
 
+pub use anthropic::{AnthropicConfig, AnthropicService, AnthropicServiceError, AnthropicStream};
+pub use azure_openai::{
+    AzureOpenAIConfig, AzureOpenAIService, AzureOpenAIServiceError, AzureOpenAIStream,
+};
+pub use backend::{estimate_tokens, system_fingerprint, Backend, BackendCapabilities, BackendRegistry};
+pub use backend_hooks::{BackendHook, HookContext, HookedBackend, PayloadLogHook};
 pub use backend_pool::{
-    ApiKeyCredential, AwsCredential, Credential, CredentialHealth, CredentialPool,
-    LoadBalanceStrategy, PoolConfig, PoolStats,
+    ApiKeyCredential, AwsCredential, AzureAuth, AzureCredential, BreakerState, Credential,
+    CredentialBreaker, CredentialHealth, CredentialPool, FailoverPolicy, LoadBalanceStrategy,
+    PoolConfig, PoolStats,
 };
 pub use bedrock::{
     BedrockError, BedrockService, BedrockStreamError, ConverseRequest, ConverseStreamResponse,
+    EmbeddingFamily, EmbeddingOutput, EmbeddingRequest, ErrorClass,
 };
+pub use bedrock_clients::{BedrockClientPool, CachedClientStats, ClientCacheStats};
 pub use bedrock_provider::BedrockProvider;
+pub use completion_store::{CompletionStore, StoredCompletion};
+pub use content_offload::ContentOffloader;
 pub use deepseek_provider::{DeepSeekProvider, DeepSeekProviderConfig};
-pub use gemini::{GeminiConfig, GeminiService, GeminiServiceError, GeminiStream};
+pub use ephemeral_keys::{EphemeralKey, EphemeralKeyManager, EphemeralKeySummary};
+#[cfg(feature = "dynamodb")]
+pub use eval_sink::{EvalCapture, EvalRecord, EvalSink, EvalTee, S3EvalSink, SqsEvalSink};
+#[cfg(feature = "dynamodb")]
+pub use feature_flags::{
+    FeatureFlagService, FLAG_DOCUMENT_SUPPORT, FLAG_EVAL_CAPTURE, FLAG_EXTENDED_THINKING,
+    FLAG_PROMPT_CACHING, FLAG_PTC, FLAG_TOOL_USE,
+};
+#[cfg(feature = "gemini")]
+pub use gemini::{GeminiConfig, GeminiService, GeminiServiceError, GeminiSseDecoder, GeminiStream};
+#[cfg(feature = "gemini")]
 pub use gemini_provider::GeminiProvider;
+pub use generations::{GenerationGuard, GenerationRegistry};
+pub use inference_presets::{InferencePreset, InferencePresetRegistry, PresetParams, ResolvedPreset};
+#[cfg(feature = "dynamodb")]
+pub use key_activity::KeyActivityTracker;
+pub use mcp_client::{McpError, McpSession, McpTool, McpToolResult};
+pub use model_deprecations::{
+    DeprecationNotice, ModelDeprecationRegistry, DEPRECATION_HEADER, MODEL_REMAPPED_FROM_HEADER,
+    SUNSET_HEADER,
+};
+pub use model_routing::{BackendTarget, ModelRoute, ModelRoutingTable, BACKEND_OVERRIDE_HEADER};
 pub use openai_provider::{OpenAIProvider, OpenAIProviderConfig};
+pub use output_watchdog::{KeyTrips, OutputCeiling, OutputWatchdog};
+pub use pii_tokenizer::{PiiKind, PiiTokenizer, PiiTokenizingBackend, PiiVault};
 pub use provider::{LLMProvider, ProviderError, UnifiedChatRequest, UnifiedChatResponse};
 pub use provider_router::ProviderRouter;
+#[cfg(feature = "dynamodb")]
+pub use priority::{PriorityError, PriorityMetrics, PriorityStats, RequestPriority, PRIORITY_HEADER};
+pub use prompt_dumps::{spawn_dump, PromptDump};
+pub use prompt_templates::{
+    PromptTemplate, PromptTemplateDefinition, PromptTemplateError, PromptTemplateStore,
+    RenderedPrompt,
+};
+#[cfg(feature = "dynamodb")]
+pub use pt_governor::ProvisionedGovernor;
+#[cfg(feature = "dynamodb")]
+pub use request_tap::{RequestTap, TapEvent, TapHandle};
+pub use request_coalescing::{coalescing_key, RequestCoalescer};
+pub use routing_metrics::{BackendRoutingStats, RoutingMetrics, RoutingOutcome};
+pub use ptc::{PtcBudget, PtcError, PtcResult};
+#[cfg(feature = "ptc")]
 pub use ptc::{
-    ContainerInfo, ExecutionResult, PendingToolCall, PtcError, PtcHealthStatus, PtcResponse,
-    PtcResult, PtcService, PtcSession, SandboxConfig, SandboxExecutor, SessionState,
+    ContainerInfo, ExecutionResult, OutputImage, PendingToolCall, PtcHealthStatus, PtcResponse,
+    PtcService, PtcSession, SandboxConfig, SandboxExecutor, SessionState,
+};
+pub use service_tier::{select_tier, EffectiveTier, RequestedTier, TierDecision};
+pub use stream_buffers::{StreamBufferMetrics, StreamBufferStats};
+pub use stream_recorder::{
+    RecordedEvent, RecorderStatus, RecordingSummary, ReplayBackend, StreamRecorder,
+    StreamRecording,
+};
+pub use token_counting::{count_prompt, TokenBreakdown, TokenizerFamily, CHARS_PER_TOKEN};
+pub use tool_cache::{ToolResultCache, CACHE_ALL_TOOLS};
+pub use transcription::{TranscriptionError, TranscriptionService};
+pub use usage_reconciliation::{
+    CloudWatchInvocationMetrics, InvocationMetrics, LedgerEntry, ReconciliationReport,
+    TokenCounts, UsageDrift, UsageLedger, UsageReconciler,
 };
+#[cfg(feature = "dynamodb")]
 pub use usage_tracker::UsageTracker;
@@ -0,0 +1,295 @@
+//! Model capability registry
+//!
+//! Output and context limits for known Claude models, keyed by a fragment
+//! shared by the Anthropic model name and the Bedrock model ID (so
+//! `claude-3-7-sonnet` matches both `claude-3-7-sonnet-20250219` and
+//! `us.anthropic.claude-3-7-sonnet-20250219-v1:0`). Unknown models are not
+//! validated and are left for the backend to judge.
+
+use serde::Serialize;
+
+/// Extended output beta (`anthropic-beta: output-128k-2025-02-19`)
+pub const OUTPUT_128K_BETA: &str = "output-128k-2025-02-19";
+
+/// Long-context beta (`anthropic-beta: context-1m-2025-08-07`)
+pub const CONTEXT_1M_BETA: &str = "context-1m-2025-08-07";
+
+/// Thinking between tool calls (`anthropic-beta: interleaved-thinking-2025-05-14`),
+/// sent by Claude Code whenever thinking is on
+pub const INTERLEAVED_THINKING_BETA: &str = "interleaved-thinking-2025-05-14";
+
+/// Betas forwarded to Bedrock as `anthropic_beta` (others are proxy-local)
+const BEDROCK_BETAS: &[&str] = &[OUTPUT_128K_BETA, CONTEXT_1M_BETA, INTERLEAVED_THINKING_BETA];
+
+/// Limits of a model family
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ModelCapabilities {
+    /// Model ID fragment identifying the family
+    pub family: &'static str,
+    /// Default maximum output tokens
+    pub max_output_tokens: i32,
+    /// Maximum output tokens with the extended output beta (None if unsupported)
+    pub extended_output_tokens: Option<i32>,
+    /// Default context window in tokens
+    pub context_window: i32,
+    /// Context window with the long-context beta (None if unsupported)
+    pub long_context_window: Option<i32>,
+}
+
+impl ModelCapabilities {
+    /// Maximum output tokens given the request's betas
+    pub fn output_limit(&self, betas: &[String]) -> i32 {
+        match self.extended_output_tokens {
+            Some(extended) if has_beta(betas, OUTPUT_128K_BETA) => extended,
+            _ => self.max_output_tokens,
+        }
+    }
+
+    /// Context window given the request's betas
+    pub fn context_limit(&self, betas: &[String]) -> i32 {
+        match self.long_context_window {
+            Some(long) if has_beta(betas, CONTEXT_1M_BETA) => long,
+            _ => self.context_window,
+        }
+    }
+}
+
+/// Known model families, most specific fragment first
+const REGISTRY: &[ModelCapabilities] = &[
+    ModelCapabilities {
+        family: "claude-3-7-sonnet",
+        max_output_tokens: 64_000,
+        extended_output_tokens: Some(128_000),
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-sonnet-4-5",
+        max_output_tokens: 64_000,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: Some(1_000_000),
+    },
+    ModelCapabilities {
+        family: "claude-sonnet-4",
+        max_output_tokens: 64_000,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: Some(1_000_000),
+    },
+    ModelCapabilities {
+        family: "claude-haiku-4-5",
+        max_output_tokens: 64_000,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-opus-4-5",
+        max_output_tokens: 64_000,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-opus-4",
+        max_output_tokens: 32_000,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-3-5-sonnet",
+        max_output_tokens: 8_192,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-3-5-haiku",
+        max_output_tokens: 8_192,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-3-opus",
+        max_output_tokens: 4_096,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-3-sonnet",
+        max_output_tokens: 4_096,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+    ModelCapabilities {
+        family: "claude-3-haiku",
+        max_output_tokens: 4_096,
+        extended_output_tokens: None,
+        context_window: 200_000,
+        long_context_window: None,
+    },
+];
+
+/// Look up the capabilities of a model by Anthropic name or Bedrock ID
+pub fn capabilities_for(model: &str) -> Option<&'static ModelCapabilities> {
+    REGISTRY.iter().find(|caps| model.contains(caps.family))
+}
+
+/// Split `anthropic-beta` header values into individual betas
+pub fn parse_betas<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    values
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether a beta was requested
+pub fn has_beta(betas: &[String], beta: &str) -> bool {
+    betas.iter().any(|b| b == beta)
+}
+
+/// Betas Bedrock needs in `additionalModelRequestFields.anthropic_beta`
+pub fn bedrock_betas(betas: &[String]) -> Vec<String> {
+    betas
+        .iter()
+        .filter(|b| BEDROCK_BETAS.contains(&b.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Check a request's betas and `max_tokens` against the model's capabilities
+///
+/// `model` is the name shown in error messages; `bedrock_model` is also
+/// consulted so aliases mapped onto a known model are validated.
+pub fn validate_output_request(
+    model: &str,
+    bedrock_model: &str,
+    max_tokens: i32,
+    betas: &[String],
+) -> Result<(), String> {
+    let Some(caps) = lookup(model, bedrock_model) else {
+        return Ok(());
+    };
+
+    if has_beta(betas, OUTPUT_128K_BETA) && caps.extended_output_tokens.is_none() {
+        return Err(format!(
+            "The '{}' beta is not supported for model '{}'",
+            OUTPUT_128K_BETA, model
+        ));
+    }
+
+    let limit = caps.output_limit(betas);
+    if max_tokens > limit {
+        return Err(format!(
+            "max_tokens: {} > {}, which is the maximum allowed number of output tokens for {}",
+            max_tokens, limit, model
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check the long-context beta and, when known, the prompt size against the
+/// model's context window
+///
+/// `input_tokens` is None when the caller has no estimate; only beta
+/// eligibility is checked then.
+pub fn validate_context_request(
+    model: &str,
+    bedrock_model: &str,
+    input_tokens: Option<i32>,
+    betas: &[String],
+) -> Result<(), String> {
+    let Some(caps) = lookup(model, bedrock_model) else {
+        return Ok(());
+    };
+
+    if has_beta(betas, CONTEXT_1M_BETA) && caps.long_context_window.is_none() {
+        return Err(format!(
+            "The '{}' beta is not supported for model '{}'",
+            CONTEXT_1M_BETA, model
+        ));
+    }
+
+    let limit = caps.context_limit(betas);
+    match input_tokens {
+        Some(tokens) if tokens > limit => Err(format!(
+            "prompt is too long: {} tokens > {} maximum",
+            tokens, limit
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn lookup(model: &str, bedrock_model: &str) -> Option<&'static ModelCapabilities> {
+    capabilities_for(model).or_else(|| capabilities_for(bedrock_model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_name_and_bedrock_id() {
+        assert_eq!(
+            capabilities_for("claude-3-7-sonnet-20250219")
+                .unwrap()
+                .family,
+            "claude-3-7-sonnet"
+        );
+        assert_eq!(
+            capabilities_for("global.anthropic.claude-sonnet-4-5-20250929-v1:0")
+                .unwrap()
+                .family,
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(
+            capabilities_for("claude-opus-4-1-20250805")
+                .unwrap()
+                .max_output_tokens,
+            32_000
+        );
+        assert!(capabilities_for("gpt-4o").is_none());
+    }
+
+    #[test]
+    fn test_output_128k_beta() {
+        let betas = parse_betas(["output-128k-2025-02-19, interleaved-thinking-2025-05-14"]);
+        assert_eq!(bedrock_betas(&betas), vec![OUTPUT_128K_BETA]);
+
+        // Raised ceiling only with the beta
+        assert!(validate_output_request("claude-3-7-sonnet-20250219", "", 128_000, &betas).is_ok());
+        let err =
+            validate_output_request("claude-3-7-sonnet-20250219", "", 128_000, &[]).unwrap_err();
+        assert!(err.contains("128000 > 64000"));
+
+        // Beta on a model without extended output
+        assert!(validate_output_request("claude-sonnet-4-20250514", "", 1024, &betas).is_err());
+
+        // Unknown models pass through
+        assert!(validate_output_request("my-alias", "custom.model", 500_000, &betas).is_ok());
+    }
+
+    #[test]
+    fn test_context_1m_beta() {
+        let betas = parse_betas([CONTEXT_1M_BETA]);
+        assert_eq!(bedrock_betas(&betas), vec![CONTEXT_1M_BETA]);
+
+        let sonnet = "claude-sonnet-4-5-20250929";
+        assert!(validate_context_request(sonnet, "", Some(600_000), &betas).is_ok());
+        let err = validate_context_request(sonnet, "", Some(600_000), &[]).unwrap_err();
+        assert_eq!(err, "prompt is too long: 600000 tokens > 200000 maximum");
+
+        // Only Sonnet 4 / 4.5 have the long-context variant
+        assert!(validate_context_request("claude-opus-4-1-20250805", "", None, &betas).is_err());
+        assert!(validate_context_request("claude-3-5-haiku-20241022", "", Some(10), &[]).is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+//! Model deprecation registry
+//!
+//! Requests for a model with a deprecation schedule are answered with the
+//! `Deprecation` (RFC 9745) and `Sunset` (RFC 8594) response headers, so
+//! clients learn about an upcoming retirement before requests start failing.
+//! Once the sunset date has passed, schedules with `remap_after_sunset` run
+//! requests on the successor model instead, and the response names the model
+//! originally requested.
+//!
+//! Schedules are seeded from MODEL_DEPRECATIONS and changed at runtime
+//! through `/admin/model-deprecations`; runtime changes are lost on restart.
+
+use axum::http::{HeaderMap, HeaderValue};
+use chrono::{DateTime, Utc};
+use std::sync::RwLock;
+
+use crate::config::ModelDeprecation;
+
+use super::provider::model_matches_pattern;
+
+/// Date the requested model was deprecated, as `@<unix seconds>`
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+/// Date the requested model stops being served, as an HTTP date
+pub const SUNSET_HEADER: &str = "sunset";
+
+/// Model the client asked for, when a sunset model was remapped
+pub const MODEL_REMAPPED_FROM_HEADER: &str = "x-model-remapped-from";
+
+/// Deprecation status of a requested model
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeprecationNotice {
+    /// Model the client requested
+    pub model: String,
+    /// Schedule matching the model
+    pub schedule: ModelDeprecation,
+    /// Successor the request should run on instead (past sunset only)
+    pub remap_to: Option<String>,
+}
+
+impl DeprecationNotice {
+    /// Response headers announcing the deprecation (and any remapping)
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let values = [
+            (
+                DEPRECATION_HEADER,
+                Some(format!("@{}", self.schedule.deprecated_at.timestamp())),
+            ),
+            (SUNSET_HEADER, self.schedule.sunset_at.map(http_date)),
+            (
+                MODEL_REMAPPED_FROM_HEADER,
+                self.remap_to.as_ref().map(|_| self.model.clone()),
+            ),
+        ];
+        for (name, value) in values {
+            if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
+/// Format a timestamp as an HTTP date (RFC 9110 IMF-fixdate)
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Deprecation schedules by model pattern
+pub struct ModelDeprecationRegistry {
+    schedules: RwLock<Vec<ModelDeprecation>>,
+}
+
+impl ModelDeprecationRegistry {
+    /// Registry holding the given schedules (later duplicates replace earlier ones)
+    pub fn new(schedules: Vec<ModelDeprecation>) -> Self {
+        let registry = Self {
+            schedules: RwLock::new(Vec::new()),
+        };
+        for schedule in schedules {
+            registry.upsert(schedule);
+        }
+        registry
+    }
+
+    /// All schedules, by model pattern
+    pub fn list(&self) -> Vec<ModelDeprecation> {
+        let mut schedules = self.schedules.read().unwrap().clone();
+        schedules.sort_by(|a, b| a.model.cmp(&b.model));
+        schedules
+    }
+
+    /// The schedule stored for a model pattern
+    pub fn get(&self, model: &str) -> Option<ModelDeprecation> {
+        self.schedules
+            .read()
+            .unwrap()
+            .iter()
+            .find(|s| s.model == model)
+            .cloned()
+    }
+
+    /// Store a schedule, replacing any previous one for the same pattern
+    pub fn upsert(&self, schedule: ModelDeprecation) {
+        let mut schedules = self.schedules.write().unwrap();
+        match schedules.iter_mut().find(|s| s.model == schedule.model) {
+            Some(existing) => *existing = schedule,
+            None => schedules.push(schedule),
+        }
+    }
+
+    /// Delete the schedule for a model pattern
+    pub fn remove(&self, model: &str) -> bool {
+        let mut schedules = self.schedules.write().unwrap();
+        let before = schedules.len();
+        schedules.retain(|s| s.model != model);
+        schedules.len() != before
+    }
+
+    /// Deprecation status of `model` at `now`
+    ///
+    /// An exact schedule wins over a pattern. Returns None for models
+    /// without a schedule.
+    pub fn check(&self, model: &str, now: DateTime<Utc>) -> Option<DeprecationNotice> {
+        let schedules = self.schedules.read().unwrap();
+        let schedule = schedules
+            .iter()
+            .find(|s| s.model == model)
+            .or_else(|| {
+                schedules
+                    .iter()
+                    .find(|s| model_matches_pattern(model, &s.model))
+            })?
+            .clone();
+
+        let sunset = schedule.sunset_at.is_some_and(|sunset| now >= sunset);
+        let remap_to = schedule
+            .successor
+            .clone()
+            .filter(|successor| sunset && schedule.remap_after_sunset && successor != model);
+
+        Some(DeprecationNotice {
+            model: model.to_string(),
+            schedule,
+            remap_to,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn schedule(model: &str, remap_after_sunset: bool) -> ModelDeprecation {
+        ModelDeprecation {
+            model: model.to_string(),
+            deprecated_at: date("2024-07-21T00:00:00Z"),
+            sunset_at: Some(date("2025-01-21T00:00:00Z")),
+            successor: Some("claude-3-5-haiku-20241022".to_string()),
+            remap_after_sunset,
+        }
+    }
+
+    #[test]
+    fn test_deprecation_headers_and_remap() {
+        let registry = ModelDeprecationRegistry::new(vec![
+            schedule("claude-2*", false),
+            schedule("claude-2.1", true),
+        ]);
+        assert!(registry.check("claude-3-opus", Utc::now()).is_none());
+
+        let before_sunset = registry
+            .check("claude-2.1", date("2024-12-01T00:00:00Z"))
+            .unwrap();
+        assert_eq!(before_sunset.remap_to, None);
+        let headers = before_sunset.headers();
+        assert_eq!(headers[DEPRECATION_HEADER], "@1721520000");
+        assert_eq!(headers[SUNSET_HEADER], "Tue, 21 Jan 2025 00:00:00 GMT");
+        assert!(!headers.contains_key(MODEL_REMAPPED_FROM_HEADER));
+
+        let after_sunset = registry
+            .check("claude-2.1", date("2025-02-01T00:00:00Z"))
+            .unwrap();
+        assert_eq!(
+            after_sunset.remap_to.as_deref(),
+            Some("claude-3-5-haiku-20241022")
+        );
+        assert_eq!(
+            after_sunset.headers()[MODEL_REMAPPED_FROM_HEADER],
+            "claude-2.1"
+        );
+
+        // The pattern's schedule does not remap
+        let pattern = registry
+            .check("claude-2.0", date("2025-02-01T00:00:00Z"))
+            .unwrap();
+        assert_eq!(pattern.schedule.model, "claude-2*");
+        assert_eq!(pattern.remap_to, None);
+
+        assert!(registry.remove("claude-2.1"));
+        assert!(!registry.remove("claude-2.1"));
+        assert_eq!(registry.list().len(), 1);
+    }
+}
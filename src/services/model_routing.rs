@@ -0,0 +1,252 @@
+//! Model routing table — decides which backend serves a given model.
+//!
+//! Both `/v1/messages` and `/v1/chat/completions` consult this table before
+//! dispatching, so a model can be pinned to a specific Bedrock profile,
+//! Gemini, Anthropic's hosted API, Azure OpenAI or a passthrough upstream
+//! without code changes.
+
+use std::fmt;
+
+use crate::config::{ModelRouteConfig, RoutingConfig};
+
+use super::provider::model_matches_pattern;
+
+/// Header that lets a scoped key force the backend for a single request
+pub const BACKEND_OVERRIDE_HEADER: &str = "x-llm-backend";
+
+/// Backend a model is routed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendTarget {
+    /// AWS Bedrock, optionally pinned to a named profile from BEDROCK_PROFILES
+    Bedrock { profile: Option<String> },
+    /// Google Gemini API
+    Gemini,
+    /// Anthropic's hosted Messages API (api.anthropic.com)
+    Anthropic,
+    /// Azure OpenAI deployment
+    Azure,
+    /// Forward the request unchanged to an upstream speaking the same API
+    Passthrough,
+}
+
+impl BackendTarget {
+    /// Parse a backend spec such as "bedrock", "bedrock:east" or "gemini".
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim().to_lowercase();
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg.trim())),
+            None => (spec.as_str(), None),
+        };
+
+        match kind {
+            "bedrock" => Some(BackendTarget::Bedrock {
+                profile: arg.filter(|p| !p.is_empty()).map(|p| p.to_string()),
+            }),
+            "gemini" => Some(BackendTarget::Gemini),
+            "anthropic" => Some(BackendTarget::Anthropic),
+            "azure" => Some(BackendTarget::Azure),
+            "passthrough" => Some(BackendTarget::Passthrough),
+            _ => None,
+        }
+    }
+
+    /// Short backend name used in logs and error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BackendTarget::Bedrock { .. } => "bedrock",
+            BackendTarget::Gemini => "gemini",
+            BackendTarget::Anthropic => "anthropic",
+            BackendTarget::Azure => "azure",
+            BackendTarget::Passthrough => "passthrough",
+        }
+    }
+}
+
+impl fmt::Display for BackendTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendTarget::Bedrock { profile: Some(profile) } => write!(f, "bedrock:{}", profile),
+            other => write!(f, "{}", other.kind()),
+        }
+    }
+}
+
+/// A single pattern -> backend rule.
+#[derive(Debug, Clone)]
+pub struct ModelRoute {
+    pub pattern: String,
+    pub target: BackendTarget,
+}
+
+/// Ordered routing table; the first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRoutingTable {
+    routes: Vec<ModelRoute>,
+    /// Backend for requests using the long-context beta, if pinned
+    long_context: Option<BackendTarget>,
+}
+
+impl ModelRoutingTable {
+    /// Create an empty table (legacy routing only).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the table from settings, skipping rules with unknown backends.
+    pub fn from_config(config: &RoutingConfig) -> Self {
+        let mut table = Self::new();
+        for ModelRouteConfig { pattern, backend } in &config.routes {
+            match BackendTarget::parse(backend) {
+                Some(target) => table.add_route(pattern.clone(), target),
+                None => tracing::warn!(
+                    pattern = %pattern,
+                    backend = %backend,
+                    "Ignoring model route with unknown backend"
+                ),
+            }
+        }
+        if let Some(ref backend) = config.long_context_backend {
+            match BackendTarget::parse(backend) {
+                Some(target) => table.long_context = Some(target),
+                None => tracing::warn!(
+                    backend = %backend,
+                    "Ignoring unknown LONG_CONTEXT_BACKEND"
+                ),
+            }
+        }
+        table
+    }
+
+    /// Append a routing rule.
+    pub fn add_route(&mut self, pattern: impl Into<String>, target: BackendTarget) {
+        self.routes.push(ModelRoute {
+            pattern: pattern.into(),
+            target,
+        });
+    }
+
+    /// Configured rules, in evaluation order.
+    pub fn routes(&self) -> &[ModelRoute] {
+        &self.routes
+    }
+
+    /// Resolve the backend for a model.
+    ///
+    /// Falls back to the built-in defaults when no rule matches:
+    /// `gemini-*` goes to Gemini, everything else to the default Bedrock client.
+    pub fn resolve(&self, model: &str) -> BackendTarget {
+        self.matching_route(model)
+            .map(|route| route.target.clone())
+            .unwrap_or_else(|| Self::default_target(model))
+    }
+
+    /// First rule matching a model, if any.
+    pub fn matching_route(&self, model: &str) -> Option<&ModelRoute> {
+        self.routes
+            .iter()
+            .find(|route| model_matches_pattern(model, &route.pattern))
+    }
+
+    /// Backend pinned for long-context requests, if configured.
+    pub fn long_context_target(&self) -> Option<&BackendTarget> {
+        self.long_context.as_ref()
+    }
+
+    /// Resolve the backend for a request using the long-context beta.
+    ///
+    /// The 1M-context variants are only served in some regions, so such
+    /// requests go to the configured long-context backend when one is set.
+    pub fn resolve_long_context(&self, model: &str) -> BackendTarget {
+        self.long_context
+            .clone()
+            .unwrap_or_else(|| self.resolve(model))
+    }
+
+    fn default_target(model: &str) -> BackendTarget {
+        if model.starts_with("gemini-") {
+            BackendTarget::Gemini
+        } else {
+            BackendTarget::Bedrock { profile: None }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend_target() {
+        assert_eq!(
+            BackendTarget::parse("bedrock"),
+            Some(BackendTarget::Bedrock { profile: None })
+        );
+        assert_eq!(
+            BackendTarget::parse("Bedrock:east"),
+            Some(BackendTarget::Bedrock { profile: Some("east".to_string()) })
+        );
+        assert_eq!(BackendTarget::parse("azure"), Some(BackendTarget::Azure));
+        assert_eq!(BackendTarget::parse("anthropic"), Some(BackendTarget::Anthropic));
+        assert_eq!(BackendTarget::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_default_routing() {
+        let table = ModelRoutingTable::new();
+        assert_eq!(table.resolve("gemini-2.0-flash"), BackendTarget::Gemini);
+        assert_eq!(
+            table.resolve("claude-sonnet-4-20250514"),
+            BackendTarget::Bedrock { profile: None }
+        );
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let config = RoutingConfig {
+            routes: vec![
+                ModelRouteConfig {
+                    pattern: "claude-opus-*".to_string(),
+                    backend: "bedrock:west".to_string(),
+                },
+                ModelRouteConfig {
+                    pattern: "claude-*".to_string(),
+                    backend: "passthrough".to_string(),
+                },
+                ModelRouteConfig {
+                    pattern: "gpt-*".to_string(),
+                    backend: "nonsense".to_string(),
+                },
+            ],
+            long_context_backend: None,
+        };
+        let table = ModelRoutingTable::from_config(&config);
+
+        assert_eq!(table.routes().len(), 2);
+        assert_eq!(
+            table.resolve("claude-opus-4-5-20251101").to_string(),
+            "bedrock:west"
+        );
+        assert_eq!(table.resolve("claude-3-haiku-20240307"), BackendTarget::Passthrough);
+        assert_eq!(table.resolve("gpt-4o"), BackendTarget::Bedrock { profile: None });
+    }
+
+    #[test]
+    fn test_long_context_backend() {
+        let table = ModelRoutingTable::new();
+        assert_eq!(
+            table.resolve_long_context("claude-sonnet-4-5"),
+            BackendTarget::Bedrock { profile: None }
+        );
+
+        let config = RoutingConfig {
+            routes: Vec::new(),
+            long_context_backend: Some("bedrock:us-west-2".to_string()),
+        };
+        let table = ModelRoutingTable::from_config(&config);
+        assert_eq!(
+            table.resolve_long_context("claude-sonnet-4-5").to_string(),
+            "bedrock:us-west-2"
+        );
+        assert_eq!(table.resolve("claude-sonnet-4-5"), BackendTarget::Bedrock { profile: None });
+    }
+}
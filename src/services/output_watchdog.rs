@@ -0,0 +1,148 @@
+//! Output-length watchdog
+//!
+//! A cost guard independent of `max_tokens`: streams whose output passes the
+//! configured ceiling are cut off, the upstream stream is dropped and the
+//! client gets a truncated ending (`max_tokens` / `length`). Every trip is
+//! counted per key so runaway generations can be traced back to their
+//! caller through `GET /admin/output-watchdog`.
+
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::config::OutputWatchdogConfig;
+
+/// Output ceiling of one streamed request
+#[derive(Debug, Clone)]
+pub struct OutputCeiling {
+    max_tokens: i32,
+    api_key: String,
+    user_id: String,
+    model: String,
+    watchdog: Arc<OutputWatchdog>,
+}
+
+impl OutputCeiling {
+    /// Output tokens at which the stream is stopped
+    pub fn max_tokens(&self) -> i32 {
+        self.max_tokens
+    }
+
+    /// Record that the stream was stopped after `output_tokens`
+    pub fn trip(&self, output_tokens: i32) {
+        tracing::warn!(
+            user_id = %self.user_id,
+            model = %self.model,
+            output_tokens,
+            max_tokens = self.max_tokens,
+            "Output ceiling reached, stopping stream"
+        );
+        self.watchdog.record(self, output_tokens);
+    }
+}
+
+/// Watchdog trips of one key
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyTrips {
+    /// Truncated API key
+    pub api_key: String,
+    pub user_id: String,
+    pub count: u64,
+    pub last_model: String,
+    pub last_output_tokens: i32,
+    /// Unix timestamp of the latest trip
+    pub last_at: i64,
+}
+
+/// Output ceilings and per-key trip counts
+#[derive(Debug)]
+pub struct OutputWatchdog {
+    max_output_tokens: i32,
+    trips: Mutex<HashMap<String, KeyTrips>>,
+}
+
+impl OutputWatchdog {
+    pub fn new(config: &OutputWatchdogConfig) -> Self {
+        Self {
+            max_output_tokens: config.max_output_tokens,
+            trips: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Ceiling for a request, or None when the watchdog is off
+    pub fn ceiling(
+        self: &Arc<Self>,
+        api_key: &str,
+        user_id: &str,
+        model: &str,
+    ) -> Option<OutputCeiling> {
+        (self.max_output_tokens > 0).then(|| OutputCeiling {
+            max_tokens: self.max_output_tokens,
+            api_key: api_key.to_string(),
+            user_id: user_id.to_string(),
+            model: model.to_string(),
+            watchdog: self.clone(),
+        })
+    }
+
+    fn record(&self, ceiling: &OutputCeiling, output_tokens: i32) {
+        let mut trips = self.trips.lock().unwrap();
+        let entry = trips
+            .entry(ceiling.api_key.clone())
+            .or_insert_with(|| KeyTrips {
+                api_key: ceiling.api_key.clone(),
+                user_id: ceiling.user_id.clone(),
+                count: 0,
+                last_model: String::new(),
+                last_output_tokens: 0,
+                last_at: 0,
+            });
+        entry.count += 1;
+        entry.last_model = ceiling.model.clone();
+        entry.last_output_tokens = output_tokens;
+        entry.last_at = Utc::now().timestamp();
+    }
+
+    /// Trips per key, most frequent first
+    pub fn trips(&self) -> Vec<KeyTrips> {
+        let mut trips: Vec<KeyTrips> = self.trips.lock().unwrap().values().cloned().collect();
+        trips.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.api_key.cmp(&b.api_key))
+        });
+        trips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceiling_and_trips() {
+        let off = Arc::new(OutputWatchdog::new(&OutputWatchdogConfig::default()));
+        assert!(off.ceiling("sk-a", "team-a", "claude").is_none());
+
+        let watchdog = Arc::new(OutputWatchdog::new(&OutputWatchdogConfig {
+            max_output_tokens: 100,
+        }));
+        let ceiling = watchdog.ceiling("sk-a", "team-a", "claude").unwrap();
+        assert_eq!(ceiling.max_tokens(), 100);
+
+        ceiling.trip(104);
+        ceiling.trip(120);
+        watchdog
+            .ceiling("sk-b", "team-b", "gpt-4o")
+            .unwrap()
+            .trip(101);
+
+        let trips = watchdog.trips();
+        assert_eq!(trips.len(), 2);
+        assert_eq!(trips[0].api_key, "sk-a");
+        assert_eq!(trips[0].count, 2);
+        assert_eq!(trips[0].last_output_tokens, 120);
+        assert_eq!(trips[1].last_model, "gpt-4o");
+    }
+}
@@ -0,0 +1,602 @@
+//! PII tokenization
+//!
+//! With `PII_TOKENIZATION_ENABLED`, backends looked up from the registry are
+//! wrapped in a [`PiiTokenizingBackend`]. Before each call, the configured
+//! kinds of PII found in the system prompt, message text, tool inputs and tool
+//! results are replaced with placeholder tokens such as `[EMAIL_1]`. A value
+//! gets the same token everywhere in the conversation, so the model can still
+//! tell values apart and refer back to them.
+//!
+//! Tokens in the response text and tool inputs are replaced with the original
+//! values before the handler sees them; streamed deltas hold back a trailing
+//! partial token until it is complete. The mapping is a [`PiiVault`] owned by
+//! the backend call and dropped with it: nothing is stored or logged.
+//!
+//! Reasoning blocks pass through untouched, since their signatures cover the
+//! exact text, and attachments (images, documents) are not scanned.
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ContentBlockDelta, ContentBlockDeltaEvent, ConverseOutput as ConverseOutputType,
+    ConverseStreamOutput, SystemContentBlock, ToolResultContentBlock, ToolUseBlockDelta,
+};
+use aws_smithy_types::Document;
+use futures::StreamExt;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::PiiTokenizationConfig;
+use crate::services::backend::{Backend, BackendCapabilities};
+use crate::services::bedrock::{
+    BedrockError, BedrockStreamError, ConverseRequest, ConverseStreamResponse,
+};
+
+/// Longest token a streamed delta may end inside of, e.g. `[CREDIT_CARD_123]`
+const MAX_TOKEN_LEN: usize = 24;
+
+/// Kind of value replaced with a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PiiKind {
+    Email,
+    CreditCard,
+    Ssn,
+    Phone,
+    IpAddress,
+}
+
+impl PiiKind {
+    /// Every kind, in the order detectors run
+    ///
+    /// Emails go first so their digits are not taken for phone numbers, and
+    /// card numbers before phone numbers for the same reason.
+    const ALL: [PiiKind; 5] = [
+        PiiKind::Email,
+        PiiKind::CreditCard,
+        PiiKind::Ssn,
+        PiiKind::Phone,
+        PiiKind::IpAddress,
+    ];
+
+    /// Parse a kind as named in `PII_TOKENIZATION_KINDS`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "email" => Some(PiiKind::Email),
+            "credit_card" => Some(PiiKind::CreditCard),
+            "ssn" => Some(PiiKind::Ssn),
+            "phone" => Some(PiiKind::Phone),
+            "ip_address" => Some(PiiKind::IpAddress),
+            _ => None,
+        }
+    }
+
+    /// Label used in tokens
+    fn label(self) -> &'static str {
+        match self {
+            PiiKind::Email => "EMAIL",
+            PiiKind::CreditCard => "CREDIT_CARD",
+            PiiKind::Ssn => "SSN",
+            PiiKind::Phone => "PHONE",
+            PiiKind::IpAddress => "IP_ADDRESS",
+        }
+    }
+
+    fn pattern(self) -> &'static str {
+        match self {
+            PiiKind::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+            PiiKind::CreditCard => r"\b\d(?:[ -]?\d){12,18}\b",
+            PiiKind::Ssn => r"\b\d{3}-\d{2}-\d{4}\b",
+            PiiKind::Phone => r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\b\d{3})[ .-]?\d{3}[ .-]\d{4}\b",
+            PiiKind::IpAddress => {
+                r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b"
+            }
+        }
+    }
+}
+
+/// Whether a digit string passes the Luhn check card numbers carry
+fn luhn_valid(value: &str) -> bool {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Finds PII and replaces it with tokens
+#[derive(Debug)]
+pub struct PiiTokenizer {
+    detectors: Vec<(PiiKind, Regex)>,
+}
+
+impl PiiTokenizer {
+    /// Build the detectors for the configured kinds; unknown kinds are skipped
+    pub fn new(config: &PiiTokenizationConfig) -> Self {
+        for name in &config.kinds {
+            if PiiKind::parse(name).is_none() {
+                tracing::warn!(kind = %name, "Unknown PII kind in PII_TOKENIZATION_KINDS, ignoring");
+            }
+        }
+        let detectors = PiiKind::ALL
+            .into_iter()
+            .filter(|kind| config.kinds.iter().any(|name| PiiKind::parse(name) == Some(*kind)))
+            .map(|kind| (kind, Regex::new(kind.pattern()).expect("valid PII pattern")))
+            .collect();
+        Self { detectors }
+    }
+
+    /// Whether no kind is detected
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    /// Replace PII in a request, returning the mapping back to the originals
+    pub fn tokenize_request(&self, request: &mut ConverseRequest) -> PiiVault {
+        let mut vault = PiiVault::default();
+        for block in request.system.iter_mut().flatten() {
+            if let SystemContentBlock::Text(text) = block {
+                self.tokenize_text(&mut vault, text);
+            }
+        }
+        for block in request.messages.iter_mut().flat_map(|m| m.content.iter_mut()) {
+            match block {
+                ContentBlock::Text(text) => self.tokenize_text(&mut vault, text),
+                ContentBlock::ToolUse(tool_use) => self.tokenize_document(&mut vault, &mut tool_use.input),
+                ContentBlock::ToolResult(tool_result) => {
+                    for content in tool_result.content.iter_mut() {
+                        match content {
+                            ToolResultContentBlock::Text(text) => self.tokenize_text(&mut vault, text),
+                            ToolResultContentBlock::Json(json) => self.tokenize_document(&mut vault, json),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        vault
+    }
+
+    fn tokenize_text(&self, vault: &mut PiiVault, text: &mut String) {
+        for (kind, regex) in &self.detectors {
+            let replaced = regex.replace_all(text.as_str(), |caps: &Captures| {
+                let value = &caps[0];
+                if *kind == PiiKind::CreditCard && !luhn_valid(value) {
+                    return value.to_string();
+                }
+                vault.token_for(*kind, value)
+            });
+            let replaced = match replaced {
+                Cow::Owned(replaced) => replaced,
+                Cow::Borrowed(_) => continue,
+            };
+            *text = replaced;
+        }
+    }
+
+    fn tokenize_document(&self, vault: &mut PiiVault, document: &mut Document) {
+        match document {
+            Document::String(text) => self.tokenize_text(vault, text),
+            Document::Array(items) => items.iter_mut().for_each(|item| self.tokenize_document(vault, item)),
+            Document::Object(fields) => fields
+                .values_mut()
+                .for_each(|value| self.tokenize_document(vault, value)),
+            _ => {}
+        }
+    }
+}
+
+/// Token-to-value mapping for one backend call
+#[derive(Debug, Default)]
+pub struct PiiVault {
+    /// Original value to token
+    tokens: HashMap<String, String>,
+    /// Token to original value
+    originals: HashMap<String, String>,
+    /// Tokens handed out per kind, for numbering
+    counts: HashMap<PiiKind, usize>,
+}
+
+impl PiiVault {
+    /// Number of distinct values replaced
+    pub fn len(&self) -> usize {
+        self.originals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+
+    fn token_for(&mut self, kind: PiiKind, value: &str) -> String {
+        if let Some(token) = self.tokens.get(value) {
+            return token.clone();
+        }
+        let count = self.counts.entry(kind).or_insert(0);
+        *count += 1;
+        let token = format!("[{}_{}]", kind.label(), count);
+        self.tokens.insert(value.to_string(), token.clone());
+        self.originals.insert(token.clone(), value.to_string());
+        token
+    }
+
+    /// Text with known tokens replaced by their values, if it contained any
+    pub fn restore(&self, text: &str) -> Option<String> {
+        if self.is_empty() || !text.contains('[') {
+            return None;
+        }
+        let mut restored = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut changed = false;
+        while let Some(start) = rest.find('[') {
+            restored.push_str(&rest[..start]);
+            let candidate = &rest[start..];
+            let original = candidate
+                .find(']')
+                .map(|end| &candidate[..=end])
+                .and_then(|token| Some((token.len(), self.originals.get(token)?)));
+            match original {
+                Some((len, original)) => {
+                    restored.push_str(original);
+                    rest = &candidate[len..];
+                    changed = true;
+                }
+                None => {
+                    restored.push('[');
+                    rest = &candidate[1..];
+                }
+            }
+        }
+        restored.push_str(rest);
+        changed.then_some(restored)
+    }
+
+    fn restore_in_place(&self, text: &mut String) {
+        if let Some(restored) = self.restore(text) {
+            *text = restored;
+        }
+    }
+
+    fn restore_document(&self, document: &mut Document) {
+        match document {
+            Document::String(text) => self.restore_in_place(text),
+            Document::Array(items) => items.iter_mut().for_each(|item| self.restore_document(item)),
+            Document::Object(fields) => fields.values_mut().for_each(|value| self.restore_document(value)),
+            _ => {}
+        }
+    }
+
+    /// Put the original values back into a non-streaming response
+    pub fn restore_output(&self, output: &mut ConverseOutput) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(ConverseOutputType::Message(message)) = output.output.as_mut() {
+            for block in message.content.iter_mut() {
+                match block {
+                    ContentBlock::Text(text) => self.restore_in_place(text),
+                    ContentBlock::ToolUse(tool_use) => self.restore_document(&mut tool_use.input),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Put the original values back into streamed text and tool input deltas
+    pub fn restore_stream(self, stream: ConverseStreamResponse) -> ConverseStreamResponse {
+        if self.is_empty() {
+            return stream;
+        }
+        let mut events = stream.into_stream();
+
+        ConverseStreamResponse::from_events(async_stream::stream! {
+            let mut pending: HashMap<i32, PendingDelta> = HashMap::new();
+
+            while let Some(item) = events.next().await {
+                match item {
+                    Ok(ConverseStreamOutput::ContentBlockDelta(event)) => {
+                        let index = event.content_block_index();
+                        let delta = match event.delta() {
+                            Some(ContentBlockDelta::Text(text)) => Some((DeltaKind::Text, text.clone())),
+                            Some(ContentBlockDelta::ToolUse(tool)) => {
+                                Some((DeltaKind::ToolUse, tool.input().to_string()))
+                            }
+                            _ => None,
+                        };
+                        let Some((kind, text)) = delta else {
+                            yield Ok(ConverseStreamOutput::ContentBlockDelta(event));
+                            continue;
+                        };
+
+                        let buffer = pending.entry(index).or_insert_with(|| PendingDelta::new(kind));
+                        buffer.text.push_str(&text);
+                        let ready = buffer.take_ready();
+                        if !ready.is_empty() {
+                            yield self.delta_event(index, kind, ready);
+                        }
+                    }
+                    Ok(ConverseStreamOutput::ContentBlockStop(event)) => {
+                        if let Some(buffer) = pending.remove(&event.content_block_index()) {
+                            if !buffer.text.is_empty() {
+                                yield self.delta_event(event.content_block_index(), buffer.kind, buffer.text);
+                            }
+                        }
+                        yield Ok(ConverseStreamOutput::ContentBlockStop(event));
+                    }
+                    Ok(event) => yield Ok(event),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+
+            // Blocks the backend never closed
+            let mut rest: Vec<_> = pending.into_iter().filter(|(_, b)| !b.text.is_empty()).collect();
+            rest.sort_by_key(|(index, _)| *index);
+            for (index, buffer) in rest {
+                yield self.delta_event(index, buffer.kind, buffer.text);
+            }
+        })
+    }
+
+    fn delta_event(
+        &self,
+        index: i32,
+        kind: DeltaKind,
+        text: String,
+    ) -> Result<ConverseStreamOutput, BedrockStreamError> {
+        let text = self.restore(&text).unwrap_or(text);
+        let delta = match kind {
+            DeltaKind::Text => ContentBlockDelta::Text(text),
+            DeltaKind::ToolUse => ContentBlockDelta::ToolUse(
+                ToolUseBlockDelta::builder()
+                    .input(text)
+                    .build()
+                    .map_err(|e| BedrockStreamError::ParseError(e.to_string()))?,
+            ),
+        };
+        ContentBlockDeltaEvent::builder()
+            .content_block_index(index)
+            .delta(delta)
+            .build()
+            .map(ConverseStreamOutput::ContentBlockDelta)
+            .map_err(|e| BedrockStreamError::ParseError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DeltaKind {
+    Text,
+    ToolUse,
+}
+
+/// Streamed text of one block not yet passed on
+#[derive(Debug)]
+struct PendingDelta {
+    kind: DeltaKind,
+    text: String,
+}
+
+impl PendingDelta {
+    fn new(kind: DeltaKind) -> Self {
+        Self {
+            kind,
+            text: String::new(),
+        }
+    }
+
+    /// Split off everything except a trailing `[...` that may be a token
+    fn take_ready(&mut self) -> String {
+        let hold = match self.text.rfind('[') {
+            Some(start) if !self.text[start..].contains(']') && self.text.len() - start < MAX_TOKEN_LEN => {
+                start
+            }
+            _ => self.text.len(),
+        };
+        let held = self.text.split_off(hold);
+        std::mem::replace(&mut self.text, held)
+    }
+}
+
+/// A backend whose requests are tokenized and responses restored
+pub struct PiiTokenizingBackend {
+    inner: Arc<dyn Backend>,
+    tokenizer: Arc<PiiTokenizer>,
+}
+
+impl PiiTokenizingBackend {
+    pub fn new(inner: Arc<dyn Backend>, tokenizer: Arc<PiiTokenizer>) -> Self {
+        Self { inner, tokenizer }
+    }
+
+    fn tokenize(&self, request: &mut ConverseRequest) -> PiiVault {
+        let vault = self.tokenizer.tokenize_request(request);
+        if !vault.is_empty() {
+            tracing::debug!(backend = self.inner.name(), values = vault.len(), "Tokenized PII in request");
+        }
+        vault
+    }
+}
+
+#[async_trait]
+impl Backend for PiiTokenizingBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn resolve_model_id(&self, model: &str) -> String {
+        self.inner.resolve_model_id(model)
+    }
+
+    async fn converse(&self, mut request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        let vault = self.tokenize(&mut request);
+        let mut output = self.inner.converse(request).await?;
+        vault.restore_output(&mut output);
+        Ok(output)
+    }
+
+    async fn converse_stream(
+        &self,
+        mut request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        let vault = self.tokenize(&mut request);
+        let stream = self.inner.converse_stream(request).await?;
+        Ok(vault.restore_stream(stream))
+    }
+
+    async fn count_tokens(&self, request: &ConverseRequest) -> Result<i32, BedrockError> {
+        let mut request = request.clone();
+        self.tokenize(&mut request);
+        self.inner.count_tokens(&request).await
+    }
+
+    fn health_check(&self) -> bool {
+        self.inner.health_check()
+    }
+
+    fn region(&self) -> Option<String> {
+        self.inner.region()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{ConversationRole, Message, ToolUseBlock};
+
+    fn tokenizer() -> PiiTokenizer {
+        PiiTokenizer::new(&PiiTokenizationConfig {
+            enabled: true,
+            ..Default::default()
+        })
+    }
+
+    fn user_text(text: &str) -> Message {
+        Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(text.to_string()))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_values_get_stable_tokens_across_the_conversation() {
+        let mut request = ConverseRequest::new("model");
+        request.system = Some(vec![SystemContentBlock::Text(
+            "Support agent for jane@example.com".to_string(),
+        )]);
+        request.messages = vec![
+            user_text("My card 4111 1111 1111 1111 was charged, call +1 415-555-0123"),
+            user_text("Email jane@example.com or bob@example.org; ssn 123-45-6789 from 10.0.0.12"),
+        ];
+
+        let vault = tokenizer().tokenize_request(&mut request);
+        let text = |i: usize| request.messages[i].content()[0].as_text().unwrap().clone();
+
+        assert_eq!(
+            request.system.as_ref().unwrap()[0],
+            SystemContentBlock::Text("Support agent for [EMAIL_1]".to_string())
+        );
+        assert_eq!(text(0), "My card [CREDIT_CARD_1] was charged, call [PHONE_1]");
+        assert_eq!(text(1), "Email [EMAIL_1] or [EMAIL_2]; ssn [SSN_1] from [IP_ADDRESS_1]");
+        assert_eq!(vault.len(), 6);
+        assert_eq!(
+            vault.restore("Sent to [EMAIL_2], not [EMAIL_9]").as_deref(),
+            Some("Sent to bob@example.org, not [EMAIL_9]")
+        );
+    }
+
+    #[test]
+    fn test_card_numbers_need_a_valid_checksum() {
+        let mut request = ConverseRequest::new("model");
+        request.messages = vec![user_text("Order 1234 5678 9012 3456 shipped")];
+        let vault = tokenizer().tokenize_request(&mut request);
+        assert!(vault.is_empty());
+    }
+
+    #[test]
+    fn test_tool_inputs_are_tokenized_and_restored() {
+        let mut request = ConverseRequest::new("model");
+        let input = Document::Object(HashMap::from([(
+            "to".to_string(),
+            Document::String("jane@example.com".to_string()),
+        )]));
+        request.messages = vec![Message::builder()
+            .role(ConversationRole::Assistant)
+            .content(ContentBlock::ToolUse(
+                ToolUseBlock::builder()
+                    .tool_use_id("t1")
+                    .name("send_email")
+                    .input(input.clone())
+                    .build()
+                    .unwrap(),
+            ))
+            .build()
+            .unwrap()];
+
+        let vault = tokenizer().tokenize_request(&mut request);
+        let ContentBlock::ToolUse(tool_use) = &mut request.messages[0].content[0] else {
+            panic!("expected tool use");
+        };
+        assert_eq!(
+            tool_use.input,
+            Document::Object(HashMap::from([(
+                "to".to_string(),
+                Document::String("[EMAIL_1]".to_string()),
+            )]))
+        );
+        vault.restore_document(&mut tool_use.input);
+        assert_eq!(tool_use.input, input);
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_back_split_tokens() {
+        let mut request = ConverseRequest::new("model");
+        request.messages = vec![user_text("I am jane@example.com")];
+        let vault = tokenizer().tokenize_request(&mut request);
+
+        let delta = |text: &str| {
+            Ok(ConverseStreamOutput::ContentBlockDelta(
+                ContentBlockDeltaEvent::builder()
+                    .content_block_index(0)
+                    .delta(ContentBlockDelta::Text(text.to_string()))
+                    .build()
+                    .unwrap(),
+            ))
+        };
+        let stop = Ok(ConverseStreamOutput::ContentBlockStop(
+            aws_sdk_bedrockruntime::types::ContentBlockStopEvent::builder()
+                .content_block_index(0)
+                .build()
+                .unwrap(),
+        ));
+        let upstream = futures::stream::iter(vec![
+            delta("Hello [EMA"),
+            delta("IL_1], your ["),
+            delta("order"),
+            stop,
+        ]);
+
+        let mut stream = vault.restore_stream(ConverseStreamResponse::from_events(upstream));
+        let mut texts = Vec::new();
+        while let Some(event) = stream.recv().await.unwrap() {
+            if let ConverseStreamOutput::ContentBlockDelta(event) = event {
+                texts.push(event.delta().unwrap().as_text().unwrap().clone());
+            }
+        }
+        assert_eq!(texts, vec!["Hello ", "jane@example.com, your ", "[order"]);
+    }
+}
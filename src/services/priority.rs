@@ -0,0 +1,209 @@
+//! Request priority classes
+//!
+//! Clients mark traffic with the `x-priority` header (`low`, `normal` or
+//! `high`). Priority decides who waits for provisioned throughput:
+//! high-priority requests may use the headroom the governor holds back from
+//! everyone else, so they move ahead of queued normal traffic, while
+//! low-priority (batch) traffic never runs on provisioned capacity at all.
+//! Only keys holding the `priority` scope may send high-priority requests.
+//!
+//! [`PriorityMetrics`] counts requests per class, how many ran on
+//! provisioned capacity and how long they queued for it.
+
+use axum::http::HeaderMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::middleware::{ApiKeyInfo, SCOPE_PRIORITY};
+use crate::services::service_tier::EffectiveTier;
+
+/// Header carrying the request priority
+pub const PRIORITY_HEADER: &str = "x-priority";
+
+/// Priority class of a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestPriority {
+    /// Batch traffic; kept off provisioned capacity
+    Low,
+    #[default]
+    Normal,
+    /// Latency-sensitive traffic; queues ahead of normal requests
+    High,
+}
+
+/// Why an `x-priority` header was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PriorityError {
+    #[error("Invalid {PRIORITY_HEADER} value '{0}': expected 'low', 'normal' or 'high'")]
+    Invalid(String),
+    #[error("API key is not allowed to send {PRIORITY_HEADER}: {0}")]
+    NotAllowed(&'static str),
+}
+
+impl RequestPriority {
+    /// Parse a header value (case-insensitive)
+    pub fn parse(value: &str) -> Result<Self, PriorityError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "normal" => Ok(Self::Normal),
+            "high" => Ok(Self::High),
+            _ => Err(PriorityError::Invalid(value.to_string())),
+        }
+    }
+
+    /// Priority requested by the headers, checked against the key's scopes
+    pub fn from_headers(headers: &HeaderMap, key_info: &ApiKeyInfo) -> Result<Self, PriorityError> {
+        let Some(value) = headers.get(PRIORITY_HEADER) else {
+            return Ok(Self::Normal);
+        };
+        let priority = value
+            .to_str()
+            .map_err(|_| PriorityError::Invalid(String::from_utf8_lossy(value.as_bytes()).into()))
+            .and_then(Self::parse)?;
+        if priority == Self::High && !key_info.has_scope(SCOPE_PRIORITY) {
+            return Err(PriorityError::NotAllowed(priority.as_str()));
+        }
+        Ok(priority)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+
+    /// Whether requests of this class may run on provisioned throughput
+    pub fn allows_provisioned(self) -> bool {
+        self != Self::Low
+    }
+}
+
+/// Totals for one priority class
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PriorityStats {
+    pub priority: RequestPriority,
+    pub requests: u64,
+    /// Requests that ran on provisioned throughput
+    pub provisioned_requests: u64,
+    /// Requests that waited for provisioned capacity
+    pub queued_requests: u64,
+    /// Time spent waiting for provisioned capacity
+    pub queue_wait_ms: u64,
+}
+
+/// Per-priority request totals
+#[derive(Debug, Default)]
+pub struct PriorityMetrics {
+    classes: Mutex<BTreeMap<RequestPriority, PriorityStats>>,
+}
+
+impl PriorityMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one admitted request
+    pub fn record(&self, priority: RequestPriority, tier: EffectiveTier, queue_wait: Duration) {
+        let mut classes = self.classes.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = classes.entry(priority).or_insert_with(|| PriorityStats {
+            priority,
+            ..Default::default()
+        });
+        stats.requests += 1;
+        if tier == EffectiveTier::Provisioned {
+            stats.provisioned_requests += 1;
+        }
+        if !queue_wait.is_zero() {
+            stats.queued_requests += 1;
+            stats.queue_wait_ms += queue_wait.as_millis() as u64;
+        }
+    }
+
+    /// Totals per class, lowest priority first
+    pub fn snapshot(&self) -> Vec<PriorityStats> {
+        self.classes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(PRIORITY_HEADER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_priority_header_checked_against_scope() {
+        let mut key = ApiKeyInfo::anonymous();
+        assert_eq!(
+            RequestPriority::from_headers(&HeaderMap::new(), &key),
+            Ok(RequestPriority::Normal)
+        );
+        assert_eq!(
+            RequestPriority::from_headers(&headers("LOW"), &key),
+            Ok(RequestPriority::Low)
+        );
+        assert!(matches!(
+            RequestPriority::from_headers(&headers("urgent"), &key),
+            Err(PriorityError::Invalid(_))
+        ));
+        assert_eq!(
+            RequestPriority::from_headers(&headers("high"), &key),
+            Err(PriorityError::NotAllowed("high"))
+        );
+
+        key.scopes = vec![SCOPE_PRIORITY.to_string()];
+        assert_eq!(
+            RequestPriority::from_headers(&headers("high"), &key),
+            Ok(RequestPriority::High)
+        );
+    }
+
+    #[test]
+    fn test_metrics_split_by_class() {
+        let metrics = PriorityMetrics::new();
+        metrics.record(
+            RequestPriority::High,
+            EffectiveTier::Provisioned,
+            Duration::ZERO,
+        );
+        metrics.record(
+            RequestPriority::Normal,
+            EffectiveTier::Provisioned,
+            Duration::from_millis(250),
+        );
+        metrics.record(
+            RequestPriority::Low,
+            EffectiveTier::OnDemand,
+            Duration::ZERO,
+        );
+
+        let snapshot = metrics.snapshot();
+        let priorities: Vec<_> = snapshot.iter().map(|s| s.priority).collect();
+        assert_eq!(
+            priorities,
+            vec![
+                RequestPriority::Low,
+                RequestPriority::Normal,
+                RequestPriority::High
+            ]
+        );
+        assert_eq!(snapshot[0].provisioned_requests, 0);
+        assert_eq!(snapshot[1].queued_requests, 1);
+        assert_eq!(snapshot[1].queue_wait_ms, 250);
+    }
+}
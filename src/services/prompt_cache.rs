@@ -7,6 +7,11 @@
 //! 1. System prompt (last block)
 //! 2. Tools definition (last tool)
 //! 3-4. Recent user messages (last content block of most recent user turns)
+//!
+//! Breakpoints the client sets itself are checked with
+//! [`validate_cache_breakpoints`] against the rules Anthropic enforces, and a
+//! request carrying any is left to the client: mixing injected 5m breakpoints
+//! with the client's could break the TTL ordering rule.
 
 use crate::schemas::anthropic::{
     CacheControl, ContentBlock, Message, MessageContent, MessageRequest, SystemContent,
@@ -15,10 +20,20 @@ use crate::schemas::anthropic::{
 
 const MAX_BREAKPOINTS: usize = 4;
 
+const EMPTY_TEXT_ERROR: &str = "cache_control cannot be set for empty text blocks";
+
+const TTL_ORDER_ERROR: &str = "a ttl='1h' cache_control block must not come after a ttl='5m' \
+    cache_control block. Note that blocks are processed in the following order: `tools`, \
+    `system`, `messages`.";
+
 /// Injects cache_control breakpoints into a MessageRequest in-place.
 ///
-/// Does not overwrite existing cache_control values.
+/// Requests that already set cache_control anywhere are left unchanged.
 pub fn inject_cache_breakpoints(request: &mut MessageRequest) {
+    if client_breakpoints(request).map_or(true, |found| !found.is_empty()) {
+        return;
+    }
+
     let mut breakpoints_used = 0;
 
     // 1. System prompt — add cache_control to last system block
@@ -44,6 +59,106 @@ pub fn inject_cache_breakpoints(request: &mut MessageRequest) {
     inject_user_message_cache(&mut request.messages, remaining);
 }
 
+/// Check the client's own breakpoints against Anthropic's caching rules.
+///
+/// Returns how many there are, or an `invalid_request_error` message worded
+/// like Anthropic's: at most 4 breakpoints, `type` must be `ephemeral`, `ttl`
+/// must be `5m` or `1h`, no breakpoints on empty text, and no 1h breakpoint
+/// after a 5m one in processing order (tools, system, messages).
+pub fn validate_cache_breakpoints(request: &MessageRequest) -> Result<usize, String> {
+    let breakpoints = client_breakpoints(request)?;
+
+    let mut seen_short_ttl = false;
+    for (path, cache_control) in &breakpoints {
+        if cache_control.cache_type != "ephemeral" {
+            return Err(format!(
+                "{}.type: Input tag '{}' found using 'type' does not match any of the expected tags: 'ephemeral'",
+                path, cache_control.cache_type
+            ));
+        }
+        match cache_control.ttl.as_deref() {
+            None | Some("5m") => seen_short_ttl = true,
+            Some("1h") if seen_short_ttl => return Err(TTL_ORDER_ERROR.to_string()),
+            Some("1h") => {}
+            Some(_) => {
+                return Err(format!("{}.ephemeral.ttl: Input should be '5m' or '1h'", path));
+            }
+        }
+    }
+
+    if breakpoints.len() > MAX_BREAKPOINTS {
+        return Err(format!(
+            "A maximum of {} blocks with cache_control may be provided. Found {}.",
+            MAX_BREAKPOINTS,
+            breakpoints.len()
+        ));
+    }
+    Ok(breakpoints.len())
+}
+
+/// Breakpoints set by the client, in processing order, with their field paths.
+fn client_breakpoints(request: &MessageRequest) -> Result<Vec<(String, CacheControl)>, String> {
+    let mut found = Vec::new();
+
+    for (i, tool) in request.tools.iter().flatten().enumerate() {
+        if let Some(value) = tool.get("cache_control").filter(|v| !v.is_null()) {
+            let path = format!("tools.{}.cache_control", i);
+            let cache_control = serde_json::from_value(value.clone())
+                .map_err(|e| format!("{}: {}", path, e))?;
+            found.push((path, cache_control));
+        }
+    }
+
+    if let Some(SystemContent::Messages(messages)) = &request.system {
+        for (i, message) in messages.iter().enumerate() {
+            if let Some(ref cache_control) = message.cache_control {
+                if message.text.is_empty() {
+                    return Err(format!("system.{}.text: {}", i, EMPTY_TEXT_ERROR));
+                }
+                found.push((format!("system.{}.cache_control", i), cache_control.clone()));
+            }
+        }
+    }
+
+    for (m, message) in request.messages.iter().enumerate() {
+        let MessageContent::Blocks(blocks) = &message.content else {
+            continue;
+        };
+        for (b, block) in blocks.iter().enumerate() {
+            let cache_control = match block {
+                ContentBlock::Text {
+                    text,
+                    cache_control: Some(cache_control),
+                } => {
+                    if text.is_empty() {
+                        return Err(format!("messages.{}.content.{}.text: {}", m, b, EMPTY_TEXT_ERROR));
+                    }
+                    cache_control
+                }
+                ContentBlock::Image {
+                    cache_control: Some(cache_control),
+                    ..
+                }
+                | ContentBlock::Document {
+                    cache_control: Some(cache_control),
+                    ..
+                }
+                | ContentBlock::ToolResult {
+                    cache_control: Some(cache_control),
+                    ..
+                } => cache_control,
+                _ => continue,
+            };
+            found.push((
+                format!("messages.{}.content.{}.cache_control", m, b),
+                cache_control.clone(),
+            ));
+        }
+    }
+
+    Ok(found)
+}
+
 /// Inject cache_control on the last system content block.
 /// Returns 1 if a breakpoint was added, 0 otherwise.
 fn inject_system_cache(system: &mut SystemContent) -> usize {
@@ -165,6 +280,10 @@ mod tests {
             thinking: None,
             metadata: None,
             container: None,
+            service_tier: None,
+            seed: None,
+            prompt: None,
+            bedrock_extra_fields: None,
         }
     }
 
@@ -346,4 +465,89 @@ mod tests {
         assert!(has_cache(&req.messages[2])); // "recent"
         assert!(has_cache(&req.messages[0])); // "old" - within 4 breakpoint limit
     }
+
+    #[test]
+    fn test_client_breakpoints_skip_injection() {
+        let tools = vec![serde_json::json!({
+            "name": "tool", "description": "T", "input_schema": {"type": "object"},
+            "cache_control": {"type": "ephemeral"}
+        })];
+        let mut req = make_request(
+            Some(SystemContent::Text("System".into())),
+            Some(tools),
+            vec![Message::user("hi")],
+        );
+        inject_cache_breakpoints(&mut req);
+
+        assert!(matches!(req.system, Some(SystemContent::Text(_))));
+        assert!(matches!(req.messages[0].content, MessageContent::Text(_)));
+        assert_eq!(validate_cache_breakpoints(&req), Ok(1));
+    }
+
+    #[test]
+    fn test_breakpoint_rules() {
+        let tool = |cache_control: serde_json::Value| {
+            serde_json::json!({
+                "name": "tool", "description": "T", "input_schema": {"type": "object"},
+                "cache_control": cache_control
+            })
+        };
+        let system = |ttl: Option<&str>| {
+            Some(SystemContent::Messages(vec![SystemMessage {
+                message_type: "text".to_string(),
+                text: "System".into(),
+                cache_control: Some(CacheControl {
+                    cache_type: "ephemeral".to_string(),
+                    ttl: ttl.map(str::to_string),
+                }),
+            }]))
+        };
+
+        // 1h before 5m is fine; 5m before 1h is not
+        let req = make_request(
+            system(None),
+            Some(vec![tool(serde_json::json!({"type": "ephemeral", "ttl": "1h"}))]),
+            vec![Message::user("hi")],
+        );
+        assert_eq!(validate_cache_breakpoints(&req), Ok(2));
+        let req = make_request(
+            system(Some("1h")),
+            Some(vec![tool(serde_json::json!({"type": "ephemeral"}))]),
+            vec![Message::user("hi")],
+        );
+        assert_eq!(validate_cache_breakpoints(&req), Err(TTL_ORDER_ERROR.to_string()));
+
+        let req = make_request(None, Some(vec![tool(serde_json::json!({"type": "persistent"}))]), vec![]);
+        assert!(validate_cache_breakpoints(&req)
+            .unwrap_err()
+            .starts_with("tools.0.cache_control.type: Input tag 'persistent'"));
+
+        let req = make_request(system(Some("10m")), None, vec![]);
+        assert_eq!(
+            validate_cache_breakpoints(&req),
+            Err("system.0.cache_control.ephemeral.ttl: Input should be '5m' or '1h'".to_string())
+        );
+
+        let empty = Message {
+            role: "user".to_string(),
+            content: MessageContent::Blocks(vec![ContentBlock::Text {
+                text: String::new(),
+                cache_control: Some(CacheControl::new()),
+            }]),
+        };
+        let req = make_request(None, None, vec![empty]);
+        assert_eq!(
+            validate_cache_breakpoints(&req),
+            Err("messages.0.content.0.text: cache_control cannot be set for empty text blocks".to_string())
+        );
+
+        let tools = (0..5)
+            .map(|_| tool(serde_json::json!({"type": "ephemeral"})))
+            .collect();
+        let req = make_request(None, Some(tools), vec![Message::user("hi")]);
+        assert_eq!(
+            validate_cache_breakpoints(&req),
+            Err("A maximum of 4 blocks with cache_control may be provided. Found 5.".to_string())
+        );
+    }
 }
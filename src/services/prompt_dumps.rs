@@ -0,0 +1,170 @@
+//! Request dumps for `--print-prompts-dir`
+//!
+//! With PRINT_PROMPTS_DIR set, every Messages and Chat Completions request is
+//! written as pretty JSON to `<dir>/<request_id>.json` instead of being
+//! printed to stdout. A dump is a [`ConformanceCase`] whose golden backend
+//! request is what the converters made of the client request, plus the
+//! request ID, the time and the backend it was routed to. Attached to a bug
+//! report it shows the conversion at a glance, and the `conformance` binary
+//! can replay it to check a converter fix.
+//!
+//! Requests routed to Azure or passthrough backends are converted as for
+//! Bedrock, the closest pipeline the harness knows.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::conformance::{self, ConformanceCase, Expected, Pipeline};
+use crate::converters::ApiFormat;
+use crate::services::BackendTarget;
+
+/// Debug dump of one request
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptDump {
+    pub request_id: String,
+    pub recorded_at: DateTime<Utc>,
+    /// Backend the request was routed to
+    pub backend: String,
+    #[serde(flatten)]
+    pub case: ConformanceCase,
+    /// Why the converters rejected the request, if they did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversion_error: Option<String>,
+}
+
+impl PromptDump {
+    /// Dump a client request together with its conversion
+    ///
+    /// `client` is [`ApiFormat::Anthropic`] or [`ApiFormat::OpenAI`].
+    pub fn new(
+        request_id: &str,
+        client: ApiFormat,
+        backend: &BackendTarget,
+        request: Value,
+    ) -> Self {
+        let pipeline = match (client, backend) {
+            (ApiFormat::OpenAI, BackendTarget::Gemini) => Pipeline::OpenaiGemini,
+            (ApiFormat::OpenAI, _) => Pipeline::OpenaiBedrock,
+            (_, BackendTarget::Gemini) => Pipeline::AnthropicGemini,
+            _ => Pipeline::AnthropicBedrock,
+        };
+        let mut case = ConformanceCase {
+            name: request_id.to_string(),
+            pipeline,
+            request,
+            backend_response: None,
+            expected: Expected::default(),
+            ignore: Vec::new(),
+        };
+        let conversion_error = match conformance::convert(&case) {
+            Ok(actual) => {
+                case.expected.backend_request = Some(actual.backend_request);
+                None
+            }
+            Err(e) => Some(e),
+        };
+
+        Self {
+            request_id: request_id.to_string(),
+            recorded_at: Utc::now(),
+            backend: backend.to_string(),
+            case,
+            conversion_error,
+        }
+    }
+
+    /// Path of the dump inside `dir`
+    pub fn path(&self, dir: &Path) -> PathBuf {
+        let name: String = self
+            .request_id
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        dir.join(format!("{}.json", name))
+    }
+
+    /// Write the dump into `dir`, creating the directory if needed
+    pub async fn write(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        let path = self.path(dir);
+        let body = serde_json::to_vec_pretty(self)?;
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(&path, body).await?;
+        Ok(path)
+    }
+}
+
+/// Dump a request into `dir` in the background
+///
+/// Failures are logged; they never affect the request.
+pub fn spawn_dump<T: Serialize>(
+    dir: &str,
+    request_id: &str,
+    client: ApiFormat,
+    backend: &BackendTarget,
+    request: &T,
+) {
+    let request = match serde_json::to_value(request) {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::warn!(request_id = %request_id, error = %e, "Failed to serialize request dump");
+            return;
+        }
+    };
+    let dump = PromptDump::new(request_id, client, backend, request);
+    let dir = PathBuf::from(dir);
+    tokio::spawn(async move {
+        match dump.write(&dir).await {
+            Ok(path) => tracing::debug!(path = %path.display(), "Wrote request dump"),
+            Err(e) => tracing::warn!(
+                request_id = %dump.request_id,
+                dir = %dir.display(),
+                error = %e,
+                "Failed to write request dump"
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_dump_is_a_conformance_case() {
+        let request = json!({
+            "model": "claude-3-5-sonnet-20241022",
+            "max_tokens": 64,
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        let backend = BackendTarget::Bedrock { profile: None };
+        let dump = PromptDump::new("req/1", ApiFormat::Anthropic, &backend, request);
+        assert_eq!(dump.case.pipeline, Pipeline::AnthropicBedrock);
+        assert!(dump.conversion_error.is_none());
+        assert!(dump.case.expected.backend_request.is_some());
+
+        let dir = std::env::temp_dir().join(format!("prompt-dumps-{}", uuid::Uuid::new_v4()));
+        let path = dump.write(&dir).await.unwrap();
+        assert_eq!(path, dir.join("req_1.json"));
+
+        let written: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written["request_id"], "req/1");
+        let case: ConformanceCase = serde_json::from_value(written).unwrap();
+        assert!(conformance::run_case(&case).passed());
+        std::fs::remove_dir_all(&dir).ok();
+
+        let invalid = PromptDump::new(
+            "req-2",
+            ApiFormat::OpenAI,
+            &BackendTarget::Gemini,
+            json!({"model": "gemini-2.0-flash"}),
+        );
+        assert_eq!(invalid.case.pipeline, Pipeline::OpenaiGemini);
+        assert!(invalid.conversion_error.is_some());
+    }
+}
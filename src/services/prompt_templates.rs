@@ -0,0 +1,483 @@
+//! Named prompt templates
+//!
+//! Teams sharing the proxy can keep their prompts in one place: templates
+//! are stored through the admin API, and requests reference them by name
+//! instead of carrying the prompt text. A template holds an optional system
+//! prompt and leading messages with `{{variable}}` placeholders, rendered
+//! server-side from the variables the request supplies.
+//!
+//! Storing a template under an existing name adds a new version; earlier
+//! versions stay renderable, and requests that do not pin a version get the
+//! latest. Templates are kept in memory and are lost on restart.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::schemas::anthropic::{
+    Message, MessageContent, MessageRequest, SystemContent, SystemMessage,
+};
+
+/// Longest template name, in characters
+const MAX_NAME_CHARS: usize = 64;
+
+/// A variable a template accepts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    /// Value used when a request does not supply one; required otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A message of a template (or of a rendered prompt)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateMessage {
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+}
+
+/// Contents of a new template version
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PromptTemplateDefinition {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<TemplateMessage>,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+}
+
+/// One stored version of a template
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    /// Starts at 1 and increases with every stored definition
+    pub version: u32,
+    /// Unix timestamp when this version was stored
+    pub created_at: i64,
+    /// User ID of the key that stored this version
+    pub created_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<TemplateMessage>,
+    pub variables: Vec<TemplateVariable>,
+}
+
+/// A template rendered with a request's variables
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedPrompt {
+    pub name: String,
+    pub version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<TemplateMessage>,
+}
+
+impl RenderedPrompt {
+    /// Put the rendered prompt ahead of a request's own system prompt and
+    /// messages
+    pub fn apply_to(self, request: &mut MessageRequest) {
+        if let Some(system) = self.system {
+            request.system = Some(match request.system.take() {
+                None => SystemContent::Text(system),
+                Some(SystemContent::Text(text)) => {
+                    SystemContent::Text(format!("{}\n\n{}", system, text))
+                }
+                Some(SystemContent::Messages(mut messages)) => {
+                    messages.insert(0, SystemMessage::new(system));
+                    SystemContent::Messages(messages)
+                }
+            });
+        }
+
+        let messages = self.messages.into_iter().map(|m| Message {
+            role: m.role,
+            content: MessageContent::Text(m.content),
+        });
+        request.messages.splice(0..0, messages);
+    }
+}
+
+/// Prompt template errors
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PromptTemplateError {
+    #[error("Prompt template '{0}' was not found")]
+    NotFound(String),
+    #[error("Prompt template '{name}' has no version {version}")]
+    VersionNotFound { name: String, version: u32 },
+    #[error("Invalid prompt template: {0}")]
+    Invalid(String),
+    #[error("Missing value for prompt variable '{0}'")]
+    MissingVariable(String),
+    #[error("Prompt template does not declare variable '{0}'")]
+    UnknownVariable(String),
+}
+
+/// Names of the `{{variable}}` placeholders in `text`, in order
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + len].trim());
+        rest = &rest[start + 2 + len + 2..];
+    }
+    names
+}
+
+/// Replace the placeholders in `text` with their values
+fn substitute(text: &str, values: &HashMap<&str, &str>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        match values.get(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + len + 2]),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+impl PromptTemplateDefinition {
+    /// Check the definition before it is stored
+    fn validate(&self) -> Result<(), PromptTemplateError> {
+        if self.system.is_none() && self.messages.is_empty() {
+            return Err(PromptTemplateError::Invalid(
+                "a template needs a system prompt or at least one message".to_string(),
+            ));
+        }
+        if let Some(message) = self
+            .messages
+            .iter()
+            .find(|m| m.role != "user" && m.role != "assistant")
+        {
+            return Err(PromptTemplateError::Invalid(format!(
+                "message role must be 'user' or 'assistant', got '{}'",
+                message.role
+            )));
+        }
+        for (i, variable) in self.variables.iter().enumerate() {
+            if !is_valid_identifier(&variable.name) {
+                return Err(PromptTemplateError::Invalid(format!(
+                    "invalid variable name '{}'",
+                    variable.name
+                )));
+            }
+            if self.variables[..i].iter().any(|v| v.name == variable.name) {
+                return Err(PromptTemplateError::Invalid(format!(
+                    "variable '{}' is declared twice",
+                    variable.name
+                )));
+            }
+        }
+
+        let texts = self
+            .system
+            .iter()
+            .chain(self.messages.iter().map(|m| &m.content));
+        for text in texts {
+            if let Some(name) = placeholders(text)
+                .into_iter()
+                .find(|name| !self.variables.iter().any(|v| v.name == *name))
+            {
+                return Err(PromptTemplateError::Invalid(format!(
+                    "placeholder '{{{{{}}}}}' has no declared variable",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PromptTemplate {
+    /// Render the template with the supplied variables
+    ///
+    /// Every supplied variable must be declared, and every declared variable
+    /// without a default must be supplied.
+    pub fn render(
+        &self,
+        variables: &HashMap<String, String>,
+    ) -> Result<RenderedPrompt, PromptTemplateError> {
+        if let Some(name) = variables
+            .keys()
+            .find(|name| !self.variables.iter().any(|v| &v.name == *name))
+        {
+            return Err(PromptTemplateError::UnknownVariable(name.clone()));
+        }
+
+        let mut values = HashMap::new();
+        for variable in &self.variables {
+            let value = variables
+                .get(&variable.name)
+                .or(variable.default.as_ref())
+                .ok_or_else(|| PromptTemplateError::MissingVariable(variable.name.clone()))?;
+            values.insert(variable.name.as_str(), value.as_str());
+        }
+
+        Ok(RenderedPrompt {
+            name: self.name.clone(),
+            version: self.version,
+            system: self.system.as_deref().map(|s| substitute(s, &values)),
+            messages: self
+                .messages
+                .iter()
+                .map(|m| TemplateMessage {
+                    role: m.role.clone(),
+                    content: substitute(&m.content, &values),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// In-memory store of versioned prompt templates
+#[derive(Debug, Default)]
+pub struct PromptTemplateStore {
+    /// Template name -> versions, oldest first
+    templates: RwLock<HashMap<String, Vec<PromptTemplate>>>,
+}
+
+impl PromptTemplateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a new version of the named template
+    pub fn create(
+        &self,
+        name: &str,
+        definition: PromptTemplateDefinition,
+        created_by: &str,
+    ) -> Result<PromptTemplate, PromptTemplateError> {
+        if !is_valid_identifier(name) {
+            return Err(PromptTemplateError::Invalid(format!(
+                "template names are 1-{} letters, digits, '_', '-' or '.', got '{}'",
+                MAX_NAME_CHARS, name
+            )));
+        }
+        definition.validate()?;
+
+        let mut templates = self.templates.write().unwrap();
+        let versions = templates.entry(name.to_string()).or_default();
+        let template = PromptTemplate {
+            name: name.to_string(),
+            version: versions.last().map_or(1, |t| t.version + 1),
+            created_at: Utc::now().timestamp(),
+            created_by: created_by.to_string(),
+            description: definition.description,
+            system: definition.system,
+            messages: definition.messages,
+            variables: definition.variables,
+        };
+        versions.push(template.clone());
+        Ok(template)
+    }
+
+    /// A version of the named template (None = the latest)
+    pub fn get(
+        &self,
+        name: &str,
+        version: Option<u32>,
+    ) -> Result<PromptTemplate, PromptTemplateError> {
+        let templates = self.templates.read().unwrap();
+        let versions = templates
+            .get(name)
+            .ok_or_else(|| PromptTemplateError::NotFound(name.to_string()))?;
+        let template = match version {
+            Some(version) => versions.iter().find(|t| t.version == version),
+            None => versions.last(),
+        };
+        template
+            .cloned()
+            .ok_or_else(|| PromptTemplateError::VersionNotFound {
+                name: name.to_string(),
+                version: version.unwrap_or_default(),
+            })
+    }
+
+    /// All versions of the named template, oldest first
+    pub fn versions(&self, name: &str) -> Result<Vec<PromptTemplate>, PromptTemplateError> {
+        self.templates
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PromptTemplateError::NotFound(name.to_string()))
+    }
+
+    /// Latest version of every template, by name
+    pub fn list(&self) -> Vec<PromptTemplate> {
+        let mut latest: Vec<PromptTemplate> = self
+            .templates
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|versions| versions.last().cloned())
+            .collect();
+        latest.sort_by(|a, b| a.name.cmp(&b.name));
+        latest
+    }
+
+    /// Delete the named template with all its versions
+    pub fn delete(&self, name: &str) -> bool {
+        self.templates.write().unwrap().remove(name).is_some()
+    }
+
+    /// Render a version of the named template (None = the latest)
+    pub fn render(
+        &self,
+        name: &str,
+        version: Option<u32>,
+        variables: &HashMap<String, String>,
+    ) -> Result<RenderedPrompt, PromptTemplateError> {
+        self.get(name, version)?.render(variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(system: &str) -> PromptTemplateDefinition {
+        PromptTemplateDefinition {
+            system: Some(system.to_string()),
+            messages: vec![TemplateMessage {
+                role: "user".to_string(),
+                content: "Summarize this {{ doc_type }}:".to_string(),
+            }],
+            variables: vec![
+                TemplateVariable {
+                    name: "team".to_string(),
+                    default: None,
+                    description: None,
+                },
+                TemplateVariable {
+                    name: "doc_type".to_string(),
+                    default: Some("document".to_string()),
+                    description: None,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_variables_and_defaults() {
+        let store = PromptTemplateStore::new();
+        store
+            .create("summarize", definition("You help the {{team}} team."), "u1")
+            .unwrap();
+
+        let rendered = store
+            .render(
+                "summarize",
+                None,
+                &HashMap::from([("team".to_string(), "legal".to_string())]),
+            )
+            .unwrap();
+        assert_eq!(rendered.version, 1);
+        assert_eq!(rendered.system.as_deref(), Some("You help the legal team."));
+        assert_eq!(rendered.messages[0].content, "Summarize this document:");
+
+        assert_eq!(
+            store.render("summarize", None, &HashMap::new()),
+            Err(PromptTemplateError::MissingVariable("team".to_string()))
+        );
+        assert_eq!(
+            store.render(
+                "summarize",
+                None,
+                &HashMap::from([("tone".to_string(), "dry".to_string())])
+            ),
+            Err(PromptTemplateError::UnknownVariable("tone".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_new_definitions_add_versions() {
+        let store = PromptTemplateStore::new();
+        store
+            .create("summarize", definition("v1 for {{team}}"), "u1")
+            .unwrap();
+        let v2 = store
+            .create("summarize", definition("v2 for {{team}}"), "u2")
+            .unwrap();
+        assert_eq!(v2.version, 2);
+
+        let vars = HashMap::from([("team".to_string(), "ops".to_string())]);
+        let pinned = store.render("summarize", Some(1), &vars).unwrap();
+        assert_eq!(pinned.system.as_deref(), Some("v1 for ops"));
+        assert_eq!(store.get("summarize", None).unwrap().version, 2);
+        assert!(matches!(
+            store.get("summarize", Some(3)),
+            Err(PromptTemplateError::VersionNotFound { version: 3, .. })
+        ));
+        assert_eq!(store.versions("summarize").unwrap().len(), 2);
+
+        assert!(store.delete("summarize"));
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn test_rendered_prompt_goes_first() {
+        let rendered = RenderedPrompt {
+            name: "summarize".to_string(),
+            version: 1,
+            system: Some("Be brief.".to_string()),
+            messages: vec![TemplateMessage {
+                role: "user".to_string(),
+                content: "Summarize:".to_string(),
+            }],
+        };
+        let mut request = MessageRequest::new("claude", vec![Message::user("the text")], 100)
+            .with_system("Answer in French.");
+        rendered.apply_to(&mut request);
+
+        assert_eq!(
+            request.system,
+            Some(SystemContent::Text(
+                "Be brief.\n\nAnswer in French.".to_string()
+            ))
+        );
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(
+            request.messages[0].content,
+            MessageContent::Text("Summarize:".to_string())
+        );
+    }
+
+    #[test]
+    fn test_undeclared_placeholder_rejected() {
+        let store = PromptTemplateStore::new();
+        let result = store.create("bad", definition("Hello {{audience}}"), "u1");
+        assert!(matches!(result, Err(PromptTemplateError::Invalid(_))));
+        assert!(store.list().is_empty());
+    }
+}
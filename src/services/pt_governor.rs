@@ -0,0 +1,260 @@
+//! Token-rate governor for provisioned throughput
+//!
+//! Provisioned throughput serves a fixed number of tokens per minute. Bursts
+//! above it come back as throttling errors, which trip retries and circuit
+//! breakers for every client at once. The governor keeps a token bucket per
+//! provisioned ARN, sized to the configured tokens per minute, and makes
+//! requests wait their turn proxy-side instead.
+//!
+//! A request reserves its estimated input tokens plus its `max_tokens` up
+//! front. Non-streaming responses refund the output they did not use once
+//! the real count is known; streams keep the full reservation.
+//!
+//! A slice of every bucket is held back for high-priority requests: other
+//! requests wait until the bucket holds their tokens plus that headroom, so
+//! high-priority traffic is served ahead of them.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::ProvisionedThroughputConfig;
+use crate::services::backend::estimate_tokens;
+use crate::services::bedrock::ConverseRequest;
+use crate::services::priority::RequestPriority;
+
+/// Share of each bucket only high-priority requests may draw on
+const HIGH_PRIORITY_HEADROOM: f64 = 0.1;
+
+/// Tokens a request reserves: its estimated input plus `max_tokens`
+pub fn reservation(request: &ConverseRequest) -> u64 {
+    let max_tokens = request
+        .inference_config
+        .as_ref()
+        .and_then(|config| config.max_tokens())
+        .unwrap_or(0);
+    (estimate_tokens(request).max(0) + max_tokens.max(0)) as u64
+}
+
+/// One provisioned ARN's token bucket
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(tokens_per_minute: u64, now: Instant) -> Self {
+        let capacity = tokens_per_minute as f64;
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take tokens, returning how long the caller must wait before sending
+    ///
+    /// The bucket goes into debt so concurrent callers queue behind each
+    /// other; `headroom` tokens must stay in the bucket after the take.
+    /// Fails with the required wait when it exceeds `max_wait`.
+    fn take(
+        &mut self,
+        tokens: u64,
+        headroom: f64,
+        now: Instant,
+        max_wait: Duration,
+    ) -> Result<Duration, Duration> {
+        self.refill(now);
+        // A request larger than the whole bucket waits for a full bucket
+        let needed = (tokens as f64).min(self.capacity - headroom);
+        let deficit = needed + headroom - self.tokens;
+        let wait = if deficit > 0.0 {
+            Duration::from_secs_f64(deficit / self.per_second)
+        } else {
+            Duration::ZERO
+        };
+        if wait > max_wait {
+            return Err(wait);
+        }
+        self.tokens -= needed;
+        Ok(wait)
+    }
+
+    fn give_back(&mut self, tokens: u64) {
+        self.tokens = (self.tokens + tokens as f64).min(self.capacity);
+    }
+}
+
+/// Per-ARN token buckets for provisioned throughput
+#[derive(Debug)]
+pub struct ProvisionedGovernor {
+    /// Provisioned ARN -> tokens per minute
+    limits: HashMap<String, u64>,
+    max_wait: Duration,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl ProvisionedGovernor {
+    /// Build from config; limits keyed by client model name apply to its ARN
+    pub fn from_config(config: &ProvisionedThroughputConfig) -> Self {
+        let limits = config
+            .tokens_per_minute
+            .iter()
+            .map(|(key, tpm)| {
+                let arn = config
+                    .models
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_else(|| key.clone());
+                (arn, *tpm)
+            })
+            .collect();
+
+        Self {
+            limits,
+            max_wait: Duration::from_millis(config.max_wait_ms),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether requests to this ARN are governed
+    pub fn is_limited(&self, arn: &str) -> bool {
+        self.limits.contains_key(arn)
+    }
+
+    /// Reserve tokens for a request, waiting for capacity if needed
+    ///
+    /// Returns how long the request waited, or the wait that would be needed
+    /// when it exceeds the configured maximum; the caller should then answer
+    /// with a rate limit error.
+    pub async fn reserve(
+        &self,
+        arn: &str,
+        tokens: u64,
+        priority: RequestPriority,
+    ) -> Result<Duration, Duration> {
+        let wait = self.reserve_at(arn, tokens, priority, Instant::now())?;
+        if !wait.is_zero() {
+            tracing::debug!(arn = %arn, tokens, priority = priority.as_str(), wait_ms = wait.as_millis() as u64, "Waiting for provisioned throughput capacity");
+            tokio::time::sleep(wait).await;
+        }
+        Ok(wait)
+    }
+
+    fn reserve_at(
+        &self,
+        arn: &str,
+        tokens: u64,
+        priority: RequestPriority,
+        now: Instant,
+    ) -> Result<Duration, Duration> {
+        let Some(&tokens_per_minute) = self.limits.get(arn) else {
+            return Ok(Duration::ZERO);
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(arn.to_string())
+            .or_insert_with(|| Bucket::new(tokens_per_minute, now));
+        let headroom = match priority {
+            RequestPriority::High => 0.0,
+            _ => bucket.capacity * HIGH_PRIORITY_HEADROOM,
+        };
+        bucket.take(tokens, headroom, now, self.max_wait)
+    }
+
+    /// Return reserved tokens a request did not use
+    pub fn refund(&self, arn: &str, tokens: u64) {
+        if tokens == 0 {
+            return;
+        }
+        if let Some(bucket) = self.buckets.lock().unwrap().get_mut(arn) {
+            bucket.give_back(tokens);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NORMAL: RequestPriority = RequestPriority::Normal;
+
+    fn governor(tpm: u64, max_wait_ms: u64) -> ProvisionedGovernor {
+        let config = ProvisionedThroughputConfig {
+            models: HashMap::from([("claude-opus".to_string(), "arn:pt".to_string())]),
+            tokens_per_minute: HashMap::from([("claude-opus".to_string(), tpm)]),
+            max_wait_ms,
+        };
+        ProvisionedGovernor::from_config(&config)
+    }
+
+    #[test]
+    fn test_burst_waits_for_refill() {
+        let governor = governor(6000, 5000);
+        let now = Instant::now();
+
+        assert!(governor.is_limited("arn:pt"));
+        assert_eq!(
+            governor.reserve_at("arn:pt", 6000, NORMAL, now),
+            Ok(Duration::ZERO)
+        );
+        // 100 tokens per second refill: the next 200 tokens wait two seconds
+        assert_eq!(
+            governor.reserve_at("arn:pt", 200, NORMAL, now),
+            Ok(Duration::from_secs(2))
+        );
+        // A third caller queues behind the second and would wait too long
+        assert!(governor.reserve_at("arn:pt", 400, NORMAL, now).is_err());
+        // Unconfigured ARNs are not governed
+        assert_eq!(
+            governor.reserve_at("arn:other", 1_000_000, NORMAL, now),
+            Ok(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_refund_returns_unused_tokens() {
+        let governor = governor(6000, 0);
+        let now = Instant::now();
+
+        assert!(governor.reserve_at("arn:pt", 6000, NORMAL, now).is_ok());
+        assert!(governor.reserve_at("arn:pt", 1000, NORMAL, now).is_err());
+        governor.refund("arn:pt", 1000);
+        assert_eq!(
+            governor.reserve_at("arn:pt", 1000, NORMAL, now),
+            Ok(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_high_priority_queues_ahead() {
+        let governor = governor(6000, 5000);
+        let now = Instant::now();
+
+        // Normal traffic drains the bucket down to the headroom
+        assert_eq!(
+            governor.reserve_at("arn:pt", 6000, NORMAL, now),
+            Ok(Duration::ZERO)
+        );
+        assert_eq!(
+            governor.reserve_at("arn:pt", 600, NORMAL, now),
+            Err(Duration::from_secs(6))
+        );
+        // High priority is served from the headroom straight away
+        assert_eq!(
+            governor.reserve_at("arn:pt", 600, RequestPriority::High, now),
+            Ok(Duration::ZERO)
+        );
+    }
+}
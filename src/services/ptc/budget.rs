@@ -0,0 +1,33 @@
+//! Per-key PTC limits
+//!
+//! Kept apart from the sandbox-backed service so API keys carry their budget
+//! even in builds without the `ptc` feature.
+
+use crate::db::models::ApiKey;
+use serde::{Deserialize, Serialize};
+
+/// Per-key limits on a PTC session (unset fields are unlimited, except
+/// `max_iterations`, which falls back to the service default)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PtcBudget {
+    /// Code-execution iterations per session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<u32>,
+    /// Output token cap for each model turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens_per_iteration: Option<u32>,
+    /// Total tokens (input + output) the session may use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token_budget: Option<u64>,
+}
+
+impl PtcBudget {
+    /// Budget configured on a stored API key
+    pub fn from_db_key(key: &ApiKey) -> Self {
+        Self {
+            max_iterations: key.ptc_max_iterations,
+            max_tokens_per_iteration: key.ptc_max_tokens_per_iteration,
+            session_token_budget: key.ptc_session_token_budget,
+        }
+    }
+}
@@ -59,6 +59,10 @@ pub enum PtcError {
     #[error("Maximum code execution iterations ({0}) exceeded")]
     MaxIterationsExceeded(u32),
 
+    /// Session token budget exhausted
+    #[error("PTC session token budget ({0}) exhausted")]
+    TokenBudgetExhausted(u64),
+
     /// Image not found
     #[error("Docker image not found: {0}")]
     ImageNotFound(String),
@@ -92,6 +96,7 @@ impl PtcError {
             PtcError::InvalidToolResult(_) => 400,
             PtcError::ExecutionTimeout(_) => 504, // Gateway Timeout
             PtcError::MaxIterationsExceeded(_) => 429, // Too Many Requests
+            PtcError::TokenBudgetExhausted(_) => 429,
             _ => 500,
         }
     }
@@ -1,17 +1,25 @@
 //! Programmatic Tool Calling (PTC) module
 //!
 //! This module provides the PTC implementation for executing code
-//! in a secure Docker sandbox environment.
+//! in a secure Docker sandbox environment. The sandbox and service need the
+//! `ptc` feature; budgets and errors are always available.
 
+pub mod budget;
 pub mod exceptions;
 pub mod runner;
+#[cfg(feature = "ptc")]
 pub mod sandbox;
+#[cfg(feature = "ptc")]
 pub mod service;
 
+pub use budget::PtcBudget;
 pub use exceptions::{PtcError, PtcResult};
 pub use runner::{get_runner_script_bytes, RUNNER_SCRIPT};
-pub use sandbox::{ContainerInfo, ExecutionResult, SandboxConfig, SandboxExecutor};
+#[cfg(feature = "ptc")]
+pub use sandbox::{ContainerInfo, ExecutionResult, OutputImage, SandboxConfig, SandboxExecutor};
+#[cfg(feature = "ptc")]
 pub use service::{
     PendingToolCall, PtcHealthStatus, PtcResponse, PtcService, PtcSession, SessionState,
-    CODE_EXECUTION_TOOL_TYPE, DEFAULT_MAX_ITERATIONS, DEFAULT_SESSION_TIMEOUT_SECS, PTC_BETA_HEADER,
+    CODE_EXECUTION_TOOL_TYPE, DEFAULT_MAX_ITERATIONS, DEFAULT_MAX_PARALLEL_EXECUTIONS,
+    DEFAULT_SESSION_TIMEOUT_SECS, PTC_BETA_HEADER,
 };
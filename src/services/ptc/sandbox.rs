@@ -4,13 +4,18 @@
 //! for secure code execution in the PTC (Programmatic Tool Calling) system.
 
 use super::exceptions::{PtcError, PtcResult};
+use crate::schemas::anthropic::{ContentBlock, ImageSource};
+use crate::schemas::openai::ContentPart;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use bollard::container::{
-    Config, CreateContainerOptions, LogOutput, LogsOptions, RemoveContainerOptions,
-    StartContainerOptions, StopContainerOptions, UploadToContainerOptions,
+    Config, CreateContainerOptions, DownloadFromContainerOptions, LogOutput, LogsOptions,
+    RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
+    UploadToContainerOptions,
 };
 use bollard::exec::{CreateExecOptions, StartExecResults};
 use bollard::Docker;
 use futures::StreamExt;
+use std::io::Read;
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -36,6 +41,15 @@ pub const DEFAULT_EXECUTION_TIMEOUT: u64 = 60;
 /// Default session timeout in seconds (4.5 minutes)
 pub const DEFAULT_SESSION_TIMEOUT: u64 = 270;
 
+/// Default directory sandbox code writes images (plots) to
+pub const DEFAULT_OUTPUT_DIR: &str = "/tmp/outputs";
+
+/// Largest output image returned (Anthropic's per-image limit)
+pub const MAX_OUTPUT_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Most output images returned per execution
+pub const MAX_OUTPUT_IMAGES: usize = 10;
+
 // ============================================================================
 // Sandbox Configuration
 // ============================================================================
@@ -57,6 +71,9 @@ pub struct SandboxConfig {
     pub network_disabled: bool,
     /// Working directory in container
     pub working_dir: String,
+    /// Directory scanned for image outputs after each execution
+    /// (exposed to code as `PTC_OUTPUT_DIR`)
+    pub output_dir: String,
 }
 
 impl Default for SandboxConfig {
@@ -69,6 +86,7 @@ impl Default for SandboxConfig {
             execution_timeout: DEFAULT_EXECUTION_TIMEOUT,
             network_disabled: true,
             working_dir: "/tmp".to_string(),
+            output_dir: DEFAULT_OUTPUT_DIR.to_string(),
         }
     }
 }
@@ -105,6 +123,8 @@ pub struct ExecutionResult {
     pub exit_code: i64,
     /// Whether execution timed out
     pub timed_out: bool,
+    /// Images written to the output directory
+    pub images: Vec<OutputImage>,
 }
 
 impl ExecutionResult {
@@ -114,6 +134,98 @@ impl ExecutionResult {
     }
 }
 
+/// Image file produced by sandbox code (e.g. a matplotlib plot)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputImage {
+    /// File name within the output directory
+    pub file_name: String,
+    /// MIME type derived from the extension
+    pub media_type: &'static str,
+    /// Base64-encoded file content
+    pub data: String,
+}
+
+impl OutputImage {
+    /// Anthropic image content block
+    pub fn to_content_block(&self) -> ContentBlock {
+        ContentBlock::Image {
+            source: ImageSource {
+                source_type: "base64".to_string(),
+                media_type: self.media_type.to_string(),
+                data: self.data.clone(),
+            },
+            cache_control: None,
+        }
+    }
+
+    /// OpenAI image content part (data URL)
+    pub fn to_content_part(&self) -> ContentPart {
+        ContentPart::image_base64(self.media_type, &self.data)
+    }
+}
+
+/// MIME type of a supported image file name
+fn image_media_type(file_name: &str) -> Option<&'static str> {
+    let extension = file_name.rsplit_once('.')?.1.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Output directory for one run, under the configured output directory
+fn run_output_dir(output_dir: &str, run_id: &str) -> String {
+    format!("{}/run_{}", output_dir.trim_end_matches('/'), run_id)
+}
+
+/// Extract supported images from a tar archive of the output directory
+///
+/// Files that are not images, or larger than `MAX_OUTPUT_IMAGE_BYTES`, are
+/// skipped; at most `MAX_OUTPUT_IMAGES` are returned, in archive order.
+pub fn extract_output_images(archive: &[u8]) -> PtcResult<Vec<OutputImage>> {
+    let read_error =
+        |e: std::io::Error| PtcError::Internal(format!("Failed to read output archive: {}", e));
+
+    let mut images = Vec::new();
+    let mut tar = tar::Archive::new(archive);
+    for entry in tar.entries().map_err(read_error)? {
+        let mut entry = entry.map_err(read_error)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path().map_err(read_error)?;
+        let file_name = path.file_name().and_then(|n| n.to_str()).map(str::to_string);
+        let Some(file_name) = file_name else {
+            continue;
+        };
+        let Some(media_type) = image_media_type(&file_name) else {
+            continue;
+        };
+        if entry.header().size().unwrap_or(u64::MAX) > MAX_OUTPUT_IMAGE_BYTES {
+            tracing::warn!(file = %file_name, "Skipping oversized PTC output image");
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(read_error)?;
+        images.push(OutputImage {
+            file_name,
+            media_type,
+            data: BASE64.encode(&content),
+        });
+
+        if images.len() == MAX_OUTPUT_IMAGES {
+            break;
+        }
+    }
+
+    Ok(images)
+}
+
 // ============================================================================
 // Sandbox Executor
 // ============================================================================
@@ -203,6 +315,11 @@ impl SandboxExecutor {
             open_stdin: Some(true),
             // Keep container running with a simple command
             cmd: Some(vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()]),
+            // Headless matplotlib; code saves figures to PTC_OUTPUT_DIR
+            env: Some(vec![
+                "MPLBACKEND=Agg".to_string(),
+                format!("PTC_OUTPUT_DIR={}", self.config.output_dir),
+            ]),
             ..Default::default()
         };
 
@@ -379,12 +496,26 @@ impl SandboxExecutor {
         container_id: &str,
         command: Vec<&str>,
         timeout_secs: u64,
+    ) -> PtcResult<ExecutionResult> {
+        self.exec_command_with_env(container_id, command, Vec::new(), timeout_secs)
+            .await
+    }
+
+    /// Execute a command with extra environment variables (`KEY=value`)
+    /// and a custom timeout
+    pub async fn exec_command_with_env(
+        &self,
+        container_id: &str,
+        command: Vec<&str>,
+        env: Vec<String>,
+        timeout_secs: u64,
     ) -> PtcResult<ExecutionResult> {
         let exec_config = CreateExecOptions {
             cmd: Some(command.iter().map(|s| s.to_string()).collect()),
             attach_stdout: Some(true),
             attach_stderr: Some(true),
             working_dir: Some(self.config.working_dir.clone()),
+            env: (!env.is_empty()).then_some(env),
             ..Default::default()
         };
 
@@ -410,24 +541,85 @@ impl SandboxExecutor {
                 stderr: "Execution timed out".to_string(),
                 exit_code: -1,
                 timed_out: true,
+                images: Vec::new(),
             }),
         }
     }
 
     /// Execute Python code in the container
+    ///
+    /// Images the code writes to its output directory are returned with the
+    /// result. Each run gets its own script file and output directory
+    /// (`PTC_OUTPUT_DIR` points at it), so runs in the same container can
+    /// execute concurrently without seeing each other's files.
     pub async fn execute_python(
         &self,
         container_id: &str,
         code: &str,
     ) -> PtcResult<ExecutionResult> {
+        let run_id = uuid::Uuid::new_v4().simple().to_string();
+
         // Write code to a temporary file
-        let script_path = "/tmp/script.py";
-        self.copy_file_to_container(container_id, code.as_bytes(), script_path)
+        let script_path = format!("/tmp/script_{}.py", run_id);
+        self.copy_file_to_container(container_id, code.as_bytes(), &script_path)
+            .await?;
+
+        let output_dir = run_output_dir(&self.config.output_dir, &run_id);
+        self.exec_command(container_id, vec!["mkdir", "-p", &output_dir])
             .await?;
 
         // Execute the script
-        self.exec_command(container_id, vec!["python", script_path])
+        let mut result = self
+            .exec_command_with_env(
+                container_id,
+                vec!["python", &script_path],
+                vec![format!("PTC_OUTPUT_DIR={}", output_dir)],
+                self.config.execution_timeout,
+            )
+            .await?;
+
+        if !result.timed_out {
+            result.images = self.collect_images_from(container_id, &output_dir).await;
+        }
+
+        if let Err(e) = self
+            .exec_command(container_id, vec!["rm", "-rf", &output_dir, &script_path])
             .await
+        {
+            tracing::debug!(error = %e, "Failed to clean up PTC run files");
+        }
+
+        Ok(result)
+    }
+
+    /// Collect images from the output directory (empty if none or unreadable)
+    pub async fn collect_output_images(&self, container_id: &str) -> Vec<OutputImage> {
+        self.collect_images_from(container_id, &self.config.output_dir)
+            .await
+    }
+
+    /// Collect images from a directory in the container
+    async fn collect_images_from(&self, container_id: &str, dir: &str) -> Vec<OutputImage> {
+        let options = DownloadFromContainerOptions {
+            path: dir.to_string(),
+        };
+
+        let mut archive = Vec::new();
+        let mut stream = self.docker.download_from_container(container_id, Some(options));
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(bytes) => archive.extend_from_slice(&bytes),
+                Err(e) => {
+                    tracing::debug!(error = %e, "No PTC output directory to collect");
+                    return Vec::new();
+                }
+            }
+        }
+
+        extract_output_images(&archive).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to collect PTC output images");
+            Vec::new()
+        })
     }
 
     /// Collect output from an exec instance
@@ -472,6 +664,7 @@ impl SandboxExecutor {
             stderr,
             exit_code,
             timed_out: false,
+            images: Vec::new(),
         })
     }
 
@@ -534,6 +727,7 @@ mod tests {
             stderr: String::new(),
             exit_code: 0,
             timed_out: false,
+            images: Vec::new(),
         };
         assert!(result.is_success());
     }
@@ -545,6 +739,7 @@ mod tests {
             stderr: "error".to_string(),
             exit_code: 1,
             timed_out: false,
+            images: Vec::new(),
         };
         assert!(!result.is_success());
     }
@@ -556,6 +751,7 @@ mod tests {
             stderr: String::new(),
             exit_code: 0,
             timed_out: true,
+            images: Vec::new(),
         };
         assert!(!result.is_success());
     }
@@ -571,4 +767,50 @@ mod tests {
         assert_eq!(info.id, "abc123");
         assert!(info.running);
     }
+
+    #[test]
+    fn test_run_output_dir() {
+        assert_eq!(run_output_dir("/tmp/outputs", "ab12"), "/tmp/outputs/run_ab12");
+        assert_eq!(run_output_dir("/tmp/outputs/", "ab12"), "/tmp/outputs/run_ab12");
+    }
+
+    #[test]
+    fn test_extract_output_images() {
+        let mut archive = Vec::new();
+        {
+            let mut builder = tar::Builder::new(&mut archive);
+            for (path, content) in [
+                ("outputs/plot.png", &b"\x89PNG fake"[..]),
+                ("outputs/data.csv", &b"a,b\n1,2"[..]),
+                ("outputs/photo.JPG", &b"jpeg"[..]),
+            ] {
+                let mut header = tar::Header::new_gnu();
+                header.set_path(path).unwrap();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append(&header, content).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+
+        let images = extract_output_images(&archive).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].file_name, "plot.png");
+        assert_eq!(images[0].media_type, "image/png");
+        assert_eq!(images[1].media_type, "image/jpeg");
+
+        match images[0].to_content_block() {
+            ContentBlock::Image { source, .. } => {
+                assert_eq!(BASE64.decode(source.data).unwrap(), b"\x89PNG fake");
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+        match images[1].to_content_part() {
+            ContentPart::ImageUrl { image_url } => {
+                assert!(image_url.url.starts_with("data:image/jpeg;base64,"));
+            }
+            other => panic!("unexpected part {:?}", other),
+        }
+    }
 }
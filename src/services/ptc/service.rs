@@ -6,9 +6,11 @@
 //! - Code execution orchestration
 //! - Tool call handling
 
+use super::budget::PtcBudget;
 use super::exceptions::{PtcError, PtcResult};
 use super::sandbox::{ContainerInfo, ExecutionResult, SandboxConfig, SandboxExecutor};
-use crate::schemas::anthropic::{MessageRequest, MessageResponse};
+use crate::schemas::anthropic::{Container, MessageRequest, MessageResponse, StopReason, Usage};
+use futures::stream::{self, StreamExt};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -32,6 +34,9 @@ pub const DEFAULT_MAX_ITERATIONS: u32 = 10;
 /// Tool call batch window in milliseconds
 pub const TOOL_CALL_BATCH_WINDOW_MS: u64 = 100;
 
+/// Default number of code executions from one turn that run at once
+pub const DEFAULT_MAX_PARALLEL_EXECUTIONS: usize = 4;
+
 // ============================================================================
 // Session
 // ============================================================================
@@ -53,6 +58,10 @@ pub struct PtcSession {
     pub iteration_count: u32,
     /// Session state
     pub state: SessionState,
+    /// Limits from the key that created the session
+    pub budget: PtcBudget,
+    /// Tokens used by the session's model turns so far
+    pub tokens_used: u64,
 }
 
 /// State of a PTC session
@@ -95,6 +104,67 @@ impl PtcSession {
     pub fn touch(&mut self) {
         self.last_activity = chrono::Utc::now();
     }
+
+    /// Iteration limit for this session, given the service default
+    pub fn max_iterations(&self, default: u32) -> u32 {
+        self.budget.max_iterations.unwrap_or(default)
+    }
+
+    /// Check whether the session has used up its iterations or tokens
+    pub fn is_budget_exhausted(&self, default_max_iterations: u32) -> bool {
+        self.iteration_count >= self.max_iterations(default_max_iterations)
+            || self
+                .budget
+                .session_token_budget
+                .is_some_and(|budget| self.tokens_used >= budget)
+    }
+
+    /// `max_tokens` for the next model turn: the requested value capped by
+    /// the per-iteration limit and the tokens left in the session budget
+    pub fn next_max_tokens(&self, requested: u32) -> u32 {
+        let mut max_tokens = requested;
+        if let Some(cap) = self.budget.max_tokens_per_iteration {
+            max_tokens = max_tokens.min(cap);
+        }
+        if let Some(budget) = self.budget.session_token_budget {
+            let remaining = budget.saturating_sub(self.tokens_used);
+            max_tokens = max_tokens.min(u32::try_from(remaining).unwrap_or(u32::MAX));
+        }
+        max_tokens
+    }
+
+    /// Count a model turn's usage against the session token budget
+    pub fn record_usage(&mut self, usage: &Usage) {
+        let tokens = usage.input_tokens.max(0) as u64 + usage.output_tokens.max(0) as u64;
+        self.tokens_used = self.tokens_used.saturating_add(tokens);
+    }
+
+    /// Count `count` more code executions against the iteration limit
+    ///
+    /// All or nothing: if the executions would take the session past
+    /// `max_iterations`, none are counted.
+    pub fn reserve_iterations(&mut self, count: u32, max_iterations: u32) -> PtcResult<()> {
+        if self.iteration_count.saturating_add(count) > max_iterations {
+            return Err(PtcError::MaxIterationsExceeded(max_iterations));
+        }
+        self.iteration_count += count;
+        Ok(())
+    }
+
+    /// Time after which the session expires if left idle
+    pub fn expires_at(&self, timeout_secs: u64) -> chrono::DateTime<chrono::Utc> {
+        self.last_activity + chrono::Duration::seconds(timeout_secs as i64)
+    }
+
+    /// Container reference returned to clients for reuse
+    pub fn container_reference(&self, timeout_secs: u64) -> Container {
+        Container {
+            id: self.container.id.clone(),
+            expires_at: self
+                .expires_at(timeout_secs)
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        }
+    }
 }
 
 // ============================================================================
@@ -130,6 +200,8 @@ pub struct PtcService {
     session_timeout: u64,
     /// Max iterations per session
     max_iterations: u32,
+    /// Max code executions from one batch running at once
+    max_parallel_executions: usize,
     /// Tool call batch window (reserved for future use)
     #[allow(dead_code)]
     batch_window_ms: u64,
@@ -144,6 +216,7 @@ impl PtcService {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             session_timeout: DEFAULT_SESSION_TIMEOUT_SECS,
             max_iterations: DEFAULT_MAX_ITERATIONS,
+            max_parallel_executions: DEFAULT_MAX_PARALLEL_EXECUTIONS,
             batch_window_ms: TOOL_CALL_BATCH_WINDOW_MS,
         })
     }
@@ -160,10 +233,17 @@ impl PtcService {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             session_timeout,
             max_iterations,
+            max_parallel_executions: DEFAULT_MAX_PARALLEL_EXECUTIONS,
             batch_window_ms: TOOL_CALL_BATCH_WINDOW_MS,
         })
     }
 
+    /// Set how many code executions from one batch run at once (at least 1)
+    pub fn with_max_parallel_executions(mut self, max_parallel_executions: usize) -> Self {
+        self.max_parallel_executions = max_parallel_executions.max(1);
+        self
+    }
+
     // ========================================================================
     // PTC Detection
     // ========================================================================
@@ -215,6 +295,21 @@ impl PtcService {
             .map(|(id, _)| id.clone())
     }
 
+    /// Reuse the container named in a request's `container` field
+    ///
+    /// Marks the owning session active (extending its expiry) and returns the
+    /// reference to echo in the response.
+    pub async fn reuse_container(&self, container_id: &str) -> PtcResult<Container> {
+        let session_id = self
+            .get_session_by_container(container_id)
+            .await
+            .ok_or_else(|| PtcError::SessionNotFound(container_id.to_string()))?;
+
+        let timeout = self.session_timeout;
+        self.with_session(&session_id, |session| Ok(session.container_reference(timeout)))
+            .await
+    }
+
     /// Extract tools with allowed_callers from request
     pub fn get_callable_tools(&self, request: &MessageRequest) -> Vec<serde_json::Value> {
         request
@@ -248,6 +343,11 @@ impl PtcService {
 
     /// Create a new PTC session
     pub async fn create_session(&self) -> PtcResult<String> {
+        self.create_session_with_budget(PtcBudget::default()).await
+    }
+
+    /// Create a new PTC session limited by a key's budget
+    pub async fn create_session_with_budget(&self, budget: PtcBudget) -> PtcResult<String> {
         let session_id = format!("ptc_sess_{}", uuid::Uuid::new_v4());
         let container = self.sandbox.create_and_start(None).await?;
 
@@ -259,6 +359,8 @@ impl PtcService {
             pending_tool_calls: Vec::new(),
             iteration_count: 0,
             state: SessionState::Active,
+            budget,
+            tokens_used: 0,
         };
 
         let mut sessions = self.sessions.write().await;
@@ -355,8 +457,9 @@ impl PtcService {
             session.state = SessionState::Executing;
             session.iteration_count += 1;
 
-            if session.iteration_count > self.max_iterations {
-                return Err(PtcError::MaxIterationsExceeded(self.max_iterations));
+            let max_iterations = session.max_iterations(self.max_iterations);
+            if session.iteration_count > max_iterations {
+                return Err(PtcError::MaxIterationsExceeded(max_iterations));
             }
 
             Ok(session.container.id.clone())
@@ -384,6 +487,121 @@ impl PtcService {
         Ok(result)
     }
 
+    /// Execute the code blocks from one assistant turn
+    ///
+    /// Up to `max_parallel_executions` blocks run concurrently in the
+    /// session's container; results come back in the order of `codes`. Each
+    /// block counts as one iteration, and a batch that would exceed the
+    /// iteration limit is rejected before any of it runs. A failed block
+    /// does not stop the others.
+    pub async fn execute_batch(
+        &self,
+        session_id: &str,
+        codes: Vec<String>,
+    ) -> PtcResult<Vec<PtcResult<ExecutionResult>>> {
+        let count = u32::try_from(codes.len()).unwrap_or(u32::MAX);
+        let container_id = self
+            .with_session(session_id, |session| {
+                let max_iterations = session.max_iterations(self.max_iterations);
+                session.reserve_iterations(count, max_iterations)?;
+                session.state = SessionState::Executing;
+                Ok(session.container.id.clone())
+            })
+            .await?;
+
+        let started = std::time::Instant::now();
+        let results: Vec<_> = stream::iter(codes)
+            .map(|code| {
+                let container_id = container_id.as_str();
+                async move { self.sandbox.execute_python(container_id, &code).await }
+            })
+            .buffered(self.max_parallel_executions)
+            .collect()
+            .await;
+        tracing::debug!(
+            session_id = %session_id,
+            executions = results.len(),
+            max_parallel = self.max_parallel_executions,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Executed PTC batch"
+        );
+
+        self.with_session(session_id, |session| {
+            session.state = SessionState::Active;
+            Ok(())
+        })
+        .await?;
+
+        Ok(results)
+    }
+
+    // ========================================================================
+    // Budget Enforcement
+    // ========================================================================
+
+    /// Prepare the next model turn of a session's loop
+    ///
+    /// Caps `request.max_tokens` to the session's per-iteration limit and
+    /// remaining token budget. Returns `false` when the budget is already
+    /// used up; the loop should then stop with
+    /// [`finish_budget_exhausted`](Self::finish_budget_exhausted).
+    pub async fn begin_iteration(
+        &self,
+        session_id: &str,
+        request: &mut MessageRequest,
+    ) -> PtcResult<bool> {
+        let default_max_iterations = self.max_iterations;
+        self.with_session(session_id, |session| {
+            if session.is_budget_exhausted(default_max_iterations) {
+                return Ok(false);
+            }
+            let requested = u32::try_from(request.max_tokens).unwrap_or(0);
+            let max_tokens = session.next_max_tokens(requested);
+            if max_tokens == 0 {
+                return Ok(false);
+            }
+            request.max_tokens = i32::try_from(max_tokens).unwrap_or(i32::MAX);
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Count a model turn's usage against the session token budget
+    ///
+    /// Returns `false` once the session has used up its budget.
+    pub async fn record_usage(&self, session_id: &str, usage: &Usage) -> PtcResult<bool> {
+        let default_max_iterations = self.max_iterations;
+        self.with_session(session_id, |session| {
+            session.record_usage(usage);
+            Ok(!session.is_budget_exhausted(default_max_iterations))
+        })
+        .await
+    }
+
+    /// End a session's loop because its budget ran out
+    ///
+    /// The last response is returned to the client as final with stop reason
+    /// `budget_exhausted`, rather than failing the request.
+    pub async fn finish_budget_exhausted(
+        &self,
+        session_id: &str,
+        response: MessageResponse,
+    ) -> PtcResponse {
+        if let Ok(session) = self.get_session(session_id).await {
+            tracing::info!(
+                session_id = %session_id,
+                iterations = session.iteration_count,
+                tokens_used = session.tokens_used,
+                "PTC session budget exhausted"
+            );
+        }
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.state = SessionState::Completed;
+        }
+        PtcResponse::Final(response.with_stop_reason(StopReason::BudgetExhausted))
+    }
+
     // ========================================================================
     // Health Check
     // ========================================================================
@@ -462,6 +680,96 @@ mod tests {
         assert_ne!(SessionState::Active, SessionState::Executing);
     }
 
+    fn test_session(iteration_count: u32) -> PtcSession {
+        let now = chrono::Utc::now();
+        PtcSession {
+            id: "ptc_sess_1".to_string(),
+            container: ContainerInfo {
+                id: "abc123".to_string(),
+                name: "ptc-test".to_string(),
+                created_at: now,
+                running: true,
+            },
+            created_at: now,
+            last_activity: now,
+            pending_tool_calls: Vec::new(),
+            iteration_count,
+            state: SessionState::Active,
+            budget: PtcBudget::default(),
+            tokens_used: 0,
+        }
+    }
+
+    #[test]
+    fn test_reserve_iterations() {
+        let mut session = test_session(7);
+        session.reserve_iterations(3, 10).unwrap();
+        assert_eq!(session.iteration_count, 10);
+
+        // A batch over the limit is rejected whole
+        let mut session = test_session(7);
+        assert!(matches!(
+            session.reserve_iterations(4, 10),
+            Err(PtcError::MaxIterationsExceeded(10))
+        ));
+        assert_eq!(session.iteration_count, 7);
+    }
+
+    #[test]
+    fn test_budget_limits_iterations_and_tokens() {
+        let mut session = test_session(0);
+        session.budget = PtcBudget {
+            max_iterations: Some(3),
+            max_tokens_per_iteration: Some(1000),
+            session_token_budget: Some(2500),
+        };
+
+        assert_eq!(session.max_iterations(DEFAULT_MAX_ITERATIONS), 3);
+        assert_eq!(session.next_max_tokens(4096), 1000);
+        assert_eq!(session.next_max_tokens(500), 500);
+
+        session.record_usage(&Usage::new(1200, 800));
+        // Only 500 tokens left in the session
+        assert_eq!(session.next_max_tokens(4096), 500);
+        assert!(!session.is_budget_exhausted(DEFAULT_MAX_ITERATIONS));
+
+        session.record_usage(&Usage::new(300, 200));
+        assert!(session.is_budget_exhausted(DEFAULT_MAX_ITERATIONS));
+        assert_eq!(session.next_max_tokens(4096), 0);
+
+        // The iteration limit applies on its own too
+        let mut session = test_session(3);
+        session.budget.max_iterations = Some(3);
+        assert!(session.is_budget_exhausted(DEFAULT_MAX_ITERATIONS));
+        assert!(!test_session(3).is_budget_exhausted(DEFAULT_MAX_ITERATIONS));
+    }
+
+    #[test]
+    fn test_container_reference() {
+        let now = chrono::Utc::now();
+        let session = PtcSession {
+            id: "ptc_sess_1".to_string(),
+            container: ContainerInfo {
+                id: "abc123".to_string(),
+                name: "ptc-test".to_string(),
+                created_at: now,
+                running: true,
+            },
+            created_at: now,
+            last_activity: now,
+            pending_tool_calls: Vec::new(),
+            iteration_count: 0,
+            state: SessionState::Active,
+            budget: PtcBudget::default(),
+            tokens_used: 0,
+        };
+
+        let container = session.container_reference(270);
+        assert_eq!(container.id, "abc123");
+        let expires_at = chrono::DateTime::parse_from_rfc3339(&container.expires_at).unwrap();
+        assert_eq!(expires_at.timestamp() - now.timestamp(), 270);
+    }
+
     #[test]
     fn test_pending_tool_call() {
         let ptc = PendingToolCall {
@@ -0,0 +1,167 @@
+//! In-flight request coalescing
+//!
+//! Client retry storms send the same non-streaming request many times while
+//! the first attempt is still running. With coalescing enabled, requests
+//! that hash the same (same key, same routing headers, same body in
+//! canonical form) while an identical one is in flight wait for its result
+//! instead of calling the backend again; every waiter gets a copy of the one
+//! response, or of its error.
+//!
+//! The upstream call keeps running as long as any waiter is still waiting,
+//! so the first client disconnecting does not fail the others. Once the
+//! call finishes its entry is removed: coalescing only joins concurrent
+//! requests and never serves a finished response to a later one.
+
+use futures::future::{BoxFuture, FutureExt, Shared, WeakShared};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::services::tool_cache::input_hash;
+
+type InflightMap<T> = Mutex<HashMap<u64, WeakShared<BoxFuture<'static, T>>>>;
+
+/// Joins identical concurrent calls onto one execution
+pub struct RequestCoalescer<T> {
+    inflight: Arc<InflightMap<T>>,
+    collapsed: AtomicU64,
+}
+
+impl<T: Clone + Send + Sync + 'static> Default for RequestCoalescer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> RequestCoalescer<T> {
+    pub fn new() -> Self {
+        Self {
+            inflight: Arc::new(Mutex::new(HashMap::new())),
+            collapsed: AtomicU64::new(0),
+        }
+    }
+
+    /// Run `call`, or wait for the identical call already in flight under `key`
+    ///
+    /// Returns the result and whether it came from another request's call.
+    pub async fn run<F>(&self, key: u64, call: F) -> (T, bool)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let (shared, joined) = {
+            let mut inflight = self.inflight.lock().unwrap_or_else(PoisonError::into_inner);
+            match inflight.get(&key).and_then(WeakShared::upgrade) {
+                Some(shared) => {
+                    self.collapsed.fetch_add(1, Ordering::Relaxed);
+                    (shared, true)
+                }
+                None => {
+                    let map = Arc::downgrade(&self.inflight);
+                    let shared: Shared<BoxFuture<'static, T>> = async move {
+                        let result = call.await;
+                        if let Some(map) = map.upgrade() {
+                            map.lock().unwrap_or_else(PoisonError::into_inner).remove(&key);
+                        }
+                        result
+                    }
+                    .boxed()
+                    .shared();
+
+                    // Calls whose waiters all went away never finish; drop them
+                    inflight.retain(|_, weak| weak.upgrade().is_some());
+                    if let Some(weak) = shared.downgrade() {
+                        inflight.insert(key, weak);
+                    }
+                    (shared, false)
+                }
+            }
+        };
+        (shared.await, joined)
+    }
+
+    /// Requests answered by another request's call so far
+    pub fn collapsed(&self) -> u64 {
+        self.collapsed.load(Ordering::Relaxed)
+    }
+
+    /// Calls currently in flight
+    pub fn inflight(&self) -> usize {
+        self.inflight.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+/// Key for a request: who sent it, the headers that change how it is
+/// handled, and its body independent of object key order
+pub fn coalescing_key(scope: &[&str], body: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    scope.hash(&mut hasher);
+    input_hash(body).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::Notify;
+
+    #[tokio::test]
+    async fn test_concurrent_identical_calls_run_once() {
+        let coalescer = Arc::new(RequestCoalescer::<Result<String, String>>::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let release = Arc::new(Notify::new());
+
+        let call = |calls: Arc<AtomicUsize>, release: Arc<Notify>| async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            release.notified().await;
+            Ok::<_, String>("response".to_string())
+        };
+
+        let first = tokio::spawn({
+            let coalescer = coalescer.clone();
+            let fut = call(calls.clone(), release.clone());
+            async move { coalescer.run(1, fut).await }
+        });
+        while coalescer.inflight() == 0 {
+            tokio::task::yield_now().await;
+        }
+        let second = tokio::spawn({
+            let coalescer = coalescer.clone();
+            let fut = call(calls.clone(), release.clone());
+            async move { coalescer.run(1, fut).await }
+        });
+        while coalescer.collapsed() == 0 {
+            tokio::task::yield_now().await;
+        }
+        release.notify_one();
+
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+        assert_eq!(first, (Ok("response".to_string()), false));
+        assert_eq!(second, (Ok("response".to_string()), true));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(coalescer.inflight(), 0);
+
+        // Finished calls are not reused
+        let (_, joined) = coalescer.run(1, async { Err("later".to_string()) }).await;
+        assert!(!joined);
+    }
+
+    #[test]
+    fn test_coalescing_key() {
+        let a = json!({"model": "m", "messages": [{"role": "user", "content": "hi"}]});
+        let b: Value =
+            serde_json::from_str(r#"{"messages": [{"content": "hi", "role": "user"}], "model": "m"}"#)
+                .unwrap();
+        assert_eq!(coalescing_key(&["user-1", "sk-a"], &a), coalescing_key(&["user-1", "sk-a"], &b));
+        assert_ne!(coalescing_key(&["user-1", "sk-a"], &a), coalescing_key(&["user-1", "sk-b"], &a));
+        assert_ne!(
+            coalescing_key(&["user-1", "sk-a"], &a),
+            coalescing_key(&["user-1", "sk-a"], &json!({"model": "m2"}))
+        );
+    }
+}
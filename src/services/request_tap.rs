@@ -0,0 +1,314 @@
+//! Live request inspector
+//!
+//! Handlers publish sanitized summaries of requests as they start, stream and
+//! finish; `GET /admin/tap` relays them to operators as server-sent events.
+//! Summaries carry the model, a truncated key, token counts and latency, never
+//! prompt or completion content.
+//!
+//! Nothing is recorded while no one is watching: [`RequestTap::start`]
+//! returns `None` without subscribers, so requests that began before a tap
+//! was opened are not shown.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::middleware::ApiKeyInfo;
+use crate::services::CHARS_PER_TOKEN;
+
+/// Events buffered per subscriber before slow subscribers start losing them
+const TAP_CHANNEL_CAPACITY: usize = 1024;
+
+/// Minimum time between progress events for one request
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sanitized request summary sent to tap subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TapEvent {
+    Started {
+        request_id: String,
+        /// "messages" or "chat_completions"
+        api: &'static str,
+        model: String,
+        key: String,
+        stream: bool,
+    },
+    Progress {
+        request_id: String,
+        output_tokens: i32,
+        elapsed_ms: u64,
+    },
+    Finished {
+        request_id: String,
+        /// "completed", "error", "cancelled" (stopped through a cancel
+        /// endpoint), or "aborted" (dropped without a response, e.g. the
+        /// client disconnected mid-stream)
+        outcome: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+        input_tokens: i32,
+        output_tokens: i32,
+        latency_ms: u64,
+    },
+}
+
+/// Broadcast hub for tap events
+#[derive(Debug)]
+pub struct RequestTap {
+    sender: broadcast::Sender<TapEvent>,
+}
+
+impl Default for RequestTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestTap {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(TAP_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Subscribe to events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<TapEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Number of open taps
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Publish a `started` event and return the handle for the rest of the
+    /// request, or None when no tap is open
+    pub fn start(
+        &self,
+        request_id: &str,
+        api: &'static str,
+        model: &str,
+        key_info: &ApiKeyInfo,
+        stream: bool,
+    ) -> Option<TapHandle> {
+        if self.subscriber_count() == 0 {
+            return None;
+        }
+
+        let _ = self.sender.send(TapEvent::Started {
+            request_id: request_id.to_string(),
+            api,
+            model: model.to_string(),
+            // Already truncated by the auth middleware
+            key: key_info.api_key.clone(),
+            stream,
+        });
+
+        Some(TapHandle {
+            inner: Arc::new(TapInner {
+                sender: self.sender.clone(),
+                request_id: request_id.to_string(),
+                started: Instant::now(),
+                output: Mutex::new(TapOutput::default()),
+                finished: AtomicBool::new(false),
+            }),
+        })
+    }
+}
+
+/// Per-request publisher; clones share state
+///
+/// The first `complete` publishes the `finished` event. If every clone is
+/// dropped first, an `aborted` event is published instead.
+#[derive(Debug, Clone)]
+pub struct TapHandle {
+    inner: Arc<TapInner>,
+}
+
+#[derive(Debug)]
+struct TapInner {
+    sender: broadcast::Sender<TapEvent>,
+    request_id: String,
+    started: Instant,
+    output: Mutex<TapOutput>,
+    finished: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct TapOutput {
+    chars: usize,
+    reported_tokens: Option<i32>,
+    last_progress: Option<Instant>,
+}
+
+impl TapOutput {
+    fn tokens(&self) -> i32 {
+        self.reported_tokens
+            .unwrap_or_else(|| self.chars.div_ceil(CHARS_PER_TOKEN) as i32)
+    }
+}
+
+impl TapHandle {
+    /// Record streamed output, publishing progress at most once a second
+    pub fn record_output(&self, text: &str) {
+        let mut output = self.inner.output.lock().unwrap_or_else(|e| e.into_inner());
+        output.chars += text.len();
+
+        let now = Instant::now();
+        if output
+            .last_progress
+            .is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        output.last_progress = Some(now);
+
+        let _ = self.inner.sender.send(TapEvent::Progress {
+            request_id: self.inner.request_id.clone(),
+            output_tokens: output.tokens(),
+            elapsed_ms: self.inner.elapsed_ms(),
+        });
+    }
+
+    /// Record the backend's output token count
+    pub fn set_output_tokens(&self, tokens: i32) {
+        let mut output = self.inner.output.lock().unwrap_or_else(|e| e.into_inner());
+        output.reported_tokens = Some(tokens);
+    }
+
+    /// Publish the `finished` event (later calls are ignored)
+    ///
+    /// Statuses of 400 and above are reported as errors. `output_tokens` of
+    /// None uses the count recorded so far.
+    pub fn complete(&self, status: u16, input_tokens: i32, output_tokens: Option<i32>) {
+        let outcome = if status >= 400 { "error" } else { "completed" };
+        let output_tokens = output_tokens.unwrap_or_else(|| self.inner.output_tokens());
+        self.inner
+            .finish(outcome, Some(status), input_tokens, output_tokens);
+    }
+
+    /// Publish a `cancelled` finish with the usage generated so far
+    pub fn cancel(&self, input_tokens: i32, output_tokens: i32) {
+        self.inner
+            .finish("cancelled", None, input_tokens, output_tokens);
+    }
+}
+
+impl TapInner {
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn output_tokens(&self) -> i32 {
+        self.output
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .tokens()
+    }
+
+    fn finish(
+        &self,
+        outcome: &'static str,
+        status: Option<u16>,
+        input_tokens: i32,
+        output_tokens: i32,
+    ) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let _ = self.sender.send(TapEvent::Finished {
+            request_id: self.request_id.clone(),
+            outcome,
+            status,
+            input_tokens,
+            output_tokens,
+            latency_ms: self.elapsed_ms(),
+        });
+    }
+}
+
+impl Drop for TapInner {
+    fn drop(&mut self) {
+        let output_tokens = self.output_tokens();
+        self.finish("aborted", None, 0, output_tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_info() -> ApiKeyInfo {
+        ApiKeyInfo::master("sk-master-0123456789abcdef")
+    }
+
+    #[test]
+    fn test_no_handle_without_subscribers() {
+        let tap = RequestTap::new();
+        assert!(tap
+            .start("req-1", "messages", "claude", &key_info(), false)
+            .is_none());
+    }
+
+    #[test]
+    fn test_request_lifecycle() {
+        let tap = RequestTap::new();
+        let mut rx = tap.subscribe();
+
+        let handle = tap
+            .start("req-1", "messages", "claude-sonnet-4-5", &key_info(), true)
+            .unwrap();
+        match rx.try_recv().unwrap() {
+            TapEvent::Started { key, model, .. } => {
+                assert_eq!(model, "claude-sonnet-4-5");
+                assert!(!key.contains("0123456789abcdef"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        handle.record_output("12345678");
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            TapEvent::Progress {
+                output_tokens: 2,
+                ..
+            }
+        ));
+        // Throttled
+        handle.record_output("more");
+        assert!(rx.try_recv().is_err());
+
+        handle.clone().complete(200, 10, None);
+        handle.complete(500, 0, None);
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            TapEvent::Finished {
+                outcome: "completed",
+                status: Some(200),
+                output_tokens: 3,
+                ..
+            }
+        ));
+        drop(handle);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_dropped_request_is_aborted() {
+        let tap = RequestTap::new();
+        let mut rx = tap.subscribe();
+
+        drop(tap.start("req-1", "chat_completions", "gpt-4o", &key_info(), true));
+        let _started = rx.try_recv().unwrap();
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            TapEvent::Finished {
+                outcome: "aborted",
+                status: None,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+//! Request routing outcomes
+//!
+//! A request may take more than one upstream attempt before it is served:
+//! Gemini rotates to the next API key when one is rate limited or failing.
+//! Each request's [`RoutingOutcome`] records which backend served it, how
+//! many attempts that took and the latency the failed attempts added.
+//! [`RoutingMetrics`] aggregates the outcomes per backend so capacity
+//! planning can see retry amplification that clients never notice.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How one request was served
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoutingOutcome {
+    /// Backend that produced the response, e.g. "bedrock" or "gemini"
+    pub backend: String,
+    /// Credential of the backend that served it, when the backend pools them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+    /// Upstream attempts, including the one that succeeded
+    pub attempts: u32,
+    /// Time spent on attempts that failed before the serving one
+    pub added_latency_ms: u64,
+}
+
+impl RoutingOutcome {
+    /// Outcome of a request served on its first attempt
+    pub fn first_attempt(backend: impl Into<String>) -> Self {
+        Self {
+            backend: backend.into(),
+            credential: None,
+            attempts: 1,
+            added_latency_ms: 0,
+        }
+    }
+
+    /// Set the serving credential
+    pub fn with_credential(mut self, credential: impl Into<String>) -> Self {
+        self.credential = Some(credential.into());
+        self
+    }
+
+    /// Record failed attempts made before the serving one
+    pub fn with_retries(mut self, failed_attempts: u32, added_latency: Duration) -> Self {
+        self.attempts = failed_attempts + 1;
+        self.added_latency_ms = added_latency.as_millis() as u64;
+        self
+    }
+
+    /// Whether the request needed more than one attempt
+    pub fn retried(&self) -> bool {
+        self.attempts > 1
+    }
+}
+
+/// Aggregated outcomes for one backend
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BackendRoutingStats {
+    pub backend: String,
+    /// Requests served
+    pub requests: u64,
+    /// Requests that needed more than one attempt
+    pub retried_requests: u64,
+    /// Upstream attempts across all requests
+    pub attempts: u64,
+    /// Latency added by failed attempts across all requests
+    pub added_latency_ms: u64,
+}
+
+impl BackendRoutingStats {
+    /// Upstream attempts per served request
+    pub fn amplification(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.attempts as f64 / self.requests as f64
+    }
+}
+
+/// Per-backend totals of request routing outcomes
+#[derive(Debug, Default)]
+pub struct RoutingMetrics {
+    backends: Mutex<BTreeMap<String, BackendRoutingStats>>,
+}
+
+impl RoutingMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one served request
+    pub fn record(&self, outcome: &RoutingOutcome) {
+        let mut backends = self.backends.lock().unwrap_or_else(|e| e.into_inner());
+        let backend = outcome.backend.clone();
+        let stats = backends
+            .entry(backend.clone())
+            .or_insert_with(|| BackendRoutingStats {
+                backend,
+                ..Default::default()
+            });
+        stats.requests += 1;
+        stats.attempts += u64::from(outcome.attempts);
+        stats.added_latency_ms += outcome.added_latency_ms;
+        if outcome.retried() {
+            stats.retried_requests += 1;
+        }
+    }
+
+    /// Totals per backend, ordered by backend name
+    pub fn snapshot(&self) -> Vec<BackendRoutingStats> {
+        self.backends
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_aggregate_per_backend() {
+        let metrics = RoutingMetrics::new();
+        metrics.record(&RoutingOutcome::first_attempt("bedrock"));
+        metrics.record(
+            &RoutingOutcome::first_attempt("gemini")
+                .with_credential("key-2")
+                .with_retries(2, Duration::from_millis(350)),
+        );
+        metrics.record(&RoutingOutcome::first_attempt("gemini"));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].backend, "bedrock");
+        assert_eq!(snapshot[0].attempts, 1);
+
+        let gemini = &snapshot[1];
+        assert_eq!(gemini.requests, 2);
+        assert_eq!(gemini.retried_requests, 1);
+        assert_eq!(gemini.attempts, 4);
+        assert_eq!(gemini.added_latency_ms, 350);
+        assert_eq!(gemini.amplification(), 2.0);
+    }
+}
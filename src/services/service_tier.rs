@@ -0,0 +1,159 @@
+//! Service tier selection
+//!
+//! Maps the `service_tier` hint of Anthropic and OpenAI requests onto the
+//! Bedrock capacity a request runs on, and names the effective tier the way
+//! each provider echoes it in responses.
+//!
+//! Requests run on provisioned throughput when the model has a provisioned
+//! ARN configured and the hint allows it (Anthropic `auto`, OpenAI `auto` or
+//! `priority`); everything else runs on demand. OpenAI `flex` is accepted and
+//! served on demand, since batch inference cannot answer a synchronous call.
+
+use crate::config::ProvisionedThroughputConfig;
+
+/// Tier requested by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestedTier {
+    /// Use provisioned capacity when available (the default)
+    Auto,
+    /// On-demand only (Anthropic `standard_only`, OpenAI `default`)
+    Standard,
+    /// Provisioned capacity preferred (OpenAI `priority`)
+    Priority,
+    /// Cheaper, slower processing (OpenAI `flex`)
+    Flex,
+}
+
+impl RequestedTier {
+    /// Parse an Anthropic `service_tier` value
+    pub fn from_anthropic(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("auto") => Ok(Self::Auto),
+            Some("standard_only") => Ok(Self::Standard),
+            Some(other) => Err(format!(
+                "Invalid service_tier '{}': expected 'auto' or 'standard_only'",
+                other
+            )),
+        }
+    }
+
+    /// Parse an OpenAI `service_tier` value
+    pub fn from_openai(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("auto") => Ok(Self::Auto),
+            Some("default") => Ok(Self::Standard),
+            Some("priority") => Ok(Self::Priority),
+            Some("flex") => Ok(Self::Flex),
+            Some(other) => Err(format!(
+                "Invalid service_tier '{}': expected 'auto', 'default', 'flex' or 'priority'",
+                other
+            )),
+        }
+    }
+}
+
+/// Capacity a request actually runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectiveTier {
+    OnDemand,
+    Provisioned,
+}
+
+impl EffectiveTier {
+    /// Name echoed in Anthropic `usage.service_tier`
+    pub fn anthropic_name(self) -> &'static str {
+        match self {
+            EffectiveTier::OnDemand => "standard",
+            EffectiveTier::Provisioned => "priority",
+        }
+    }
+
+    /// Name echoed in the OpenAI `service_tier` response field
+    pub fn openai_name(self) -> &'static str {
+        match self {
+            EffectiveTier::OnDemand => "default",
+            EffectiveTier::Provisioned => "priority",
+        }
+    }
+}
+
+/// Routing decision for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TierDecision {
+    pub tier: EffectiveTier,
+    /// Bedrock model ID to call (the provisioned ARN when provisioned)
+    pub model_id: String,
+}
+
+/// Pick the tier and Bedrock model ID for a request
+///
+/// `model` is the client's model name and `bedrock_model` its on-demand
+/// Bedrock ID; a provisioned ARN may be configured for either.
+pub fn select_tier(
+    requested: RequestedTier,
+    config: &ProvisionedThroughputConfig,
+    model: &str,
+    bedrock_model: &str,
+) -> TierDecision {
+    let provisioned = match requested {
+        RequestedTier::Auto | RequestedTier::Priority => config.arn_for(model, bedrock_model),
+        RequestedTier::Standard | RequestedTier::Flex => None,
+    };
+
+    match provisioned {
+        Some(arn) => TierDecision {
+            tier: EffectiveTier::Provisioned,
+            model_id: arn.to_string(),
+        },
+        None => TierDecision {
+            tier: EffectiveTier::OnDemand,
+            model_id: bedrock_model.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const ARN: &str = "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/abc123";
+
+    fn config() -> ProvisionedThroughputConfig {
+        ProvisionedThroughputConfig {
+            models: HashMap::from([("claude-sonnet-4-5".to_string(), ARN.to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_hints() {
+        assert_eq!(RequestedTier::from_anthropic(None), Ok(RequestedTier::Auto));
+        assert_eq!(RequestedTier::from_anthropic(Some("standard_only")), Ok(RequestedTier::Standard));
+        assert!(RequestedTier::from_anthropic(Some("priority")).is_err());
+
+        assert_eq!(RequestedTier::from_openai(Some("default")), Ok(RequestedTier::Standard));
+        assert_eq!(RequestedTier::from_openai(Some("flex")), Ok(RequestedTier::Flex));
+        assert!(RequestedTier::from_openai(Some("standard_only")).is_err());
+    }
+
+    #[test]
+    fn test_select_tier() {
+        let bedrock_model = "global.anthropic.claude-sonnet-4-5-20250929-v1:0";
+
+        let auto = select_tier(RequestedTier::Auto, &config(), "claude-sonnet-4-5", bedrock_model);
+        assert_eq!(auto.tier, EffectiveTier::Provisioned);
+        assert_eq!(auto.model_id, ARN);
+        assert_eq!(auto.tier.anthropic_name(), "priority");
+
+        let standard =
+            select_tier(RequestedTier::Standard, &config(), "claude-sonnet-4-5", bedrock_model);
+        assert_eq!(standard.tier, EffectiveTier::OnDemand);
+        assert_eq!(standard.model_id, bedrock_model);
+        assert_eq!(standard.tier.openai_name(), "default");
+
+        // No provisioned capacity configured for this model
+        let priority = select_tier(RequestedTier::Priority, &config(), "claude-opus-4-1", "opus");
+        assert_eq!(priority.tier, EffectiveTier::OnDemand);
+    }
+}
@@ -0,0 +1,287 @@
+//! Stream-and-assemble mode for non-streaming requests
+//!
+//! Opening a ConverseStream returns as soon as the model starts generating and
+//! keeps the connection busy while it runs, so long generations are less
+//! likely to hit upstream timeouts or throttling than one blocking Converse
+//! call. For models enabled in
+//! [`StreamAssemblyConfig`](crate::config::StreamAssemblyConfig), handlers
+//! serving a non-streaming client call [`converse_assembled`], which streams
+//! upstream and rebuilds the same [`ConverseOutput`] a Converse call returns.
+
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ContentBlockDelta, ContentBlockStart, ConversationRole, ConverseStreamOutput,
+    Message, ReasoningContentBlock, ReasoningContentBlockDelta, ReasoningTextBlock, StopReason,
+    TokenUsage, ToolUseBlock,
+};
+use std::collections::BTreeMap;
+
+use crate::converters::bedrock_sdk::json_to_document;
+use crate::services::backend::Backend;
+use crate::services::bedrock::{
+    BedrockError, BedrockStreamError, ConverseRequest, ConverseStreamResponse,
+};
+
+/// Call a backend through its streaming API and return the assembled output
+pub async fn converse_assembled(
+    backend: &dyn Backend,
+    request: ConverseRequest,
+) -> Result<ConverseOutput, BedrockError> {
+    let stream = backend.converse_stream(request).await?;
+    assemble(stream).await
+}
+
+/// Collect a Converse event stream into a Converse output
+pub async fn assemble(mut stream: ConverseStreamResponse) -> Result<ConverseOutput, BedrockError> {
+    let mut transcript = Transcript::default();
+    while let Some(event) = stream.recv().await.map_err(stream_error)? {
+        transcript.apply(event);
+    }
+    transcript.finish()
+}
+
+fn stream_error(err: BedrockStreamError) -> BedrockError {
+    match err {
+        BedrockStreamError::Backend(err) => err,
+        BedrockStreamError::ParseError(message) => BedrockError::Deserialization(message),
+        BedrockStreamError::StreamError(message) => BedrockError::ServiceUnavailable(message),
+    }
+}
+
+/// One content block being assembled
+enum Block {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input: String,
+    },
+    Reasoning {
+        text: String,
+        signature: Option<String>,
+    },
+}
+
+/// Accumulated state of a streamed response
+#[derive(Default)]
+struct Transcript {
+    blocks: BTreeMap<i32, Block>,
+    stop_reason: Option<StopReason>,
+    usage: Option<TokenUsage>,
+}
+
+impl Transcript {
+    fn apply(&mut self, event: ConverseStreamOutput) {
+        match event {
+            ConverseStreamOutput::ContentBlockStart(start) => {
+                if let Some(ContentBlockStart::ToolUse(tool)) = start.start() {
+                    self.blocks.insert(
+                        start.content_block_index(),
+                        Block::ToolUse {
+                            id: tool.tool_use_id().to_string(),
+                            name: tool.name().to_string(),
+                            input: String::new(),
+                        },
+                    );
+                }
+            }
+            ConverseStreamOutput::ContentBlockDelta(delta) => {
+                let index = delta.content_block_index();
+                match delta.delta() {
+                    Some(ContentBlockDelta::Text(text)) => {
+                        if let Block::Text(buffer) = self
+                            .blocks
+                            .entry(index)
+                            .or_insert_with(|| Block::Text(String::new()))
+                        {
+                            buffer.push_str(text);
+                        }
+                    }
+                    Some(ContentBlockDelta::ToolUse(tool)) => {
+                        if let Some(Block::ToolUse { input, .. }) = self.blocks.get_mut(&index) {
+                            input.push_str(tool.input());
+                        }
+                    }
+                    Some(ContentBlockDelta::ReasoningContent(reasoning)) => {
+                        let block = self
+                            .blocks
+                            .entry(index)
+                            .or_insert_with(|| Block::Reasoning {
+                                text: String::new(),
+                                signature: None,
+                            });
+                        if let Block::Reasoning { text, signature } = block {
+                            match reasoning {
+                                ReasoningContentBlockDelta::Text(delta) => text.push_str(delta),
+                                ReasoningContentBlockDelta::Signature(value) => {
+                                    *signature = Some(value.clone())
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ConverseStreamOutput::MessageStop(stop) => {
+                self.stop_reason = Some(stop.stop_reason().clone());
+            }
+            ConverseStreamOutput::Metadata(metadata) => {
+                self.usage = metadata.usage().cloned();
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Result<ConverseOutput, BedrockError> {
+        let build_error = |e: aws_smithy_types::error::operation::BuildError| {
+            BedrockError::Deserialization(format!("Failed to assemble stream: {}", e))
+        };
+
+        let mut content = Vec::with_capacity(self.blocks.len());
+        for block in self.blocks.into_values() {
+            content.push(match block {
+                Block::Text(text) => ContentBlock::Text(text),
+                Block::ToolUse { id, name, input } => {
+                    // Empty input means a tool without parameters
+                    let input = if input.trim().is_empty() {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::from_str(&input).map_err(|e| {
+                            BedrockError::Deserialization(format!(
+                                "Invalid tool input for '{}': {}",
+                                name, e
+                            ))
+                        })?
+                    };
+                    ContentBlock::ToolUse(
+                        ToolUseBlock::builder()
+                            .tool_use_id(id)
+                            .name(name)
+                            .input(json_to_document(&input))
+                            .build()
+                            .map_err(build_error)?,
+                    )
+                }
+                Block::Reasoning { text, signature } => {
+                    ContentBlock::ReasoningContent(ReasoningContentBlock::ReasoningText(
+                        ReasoningTextBlock::builder()
+                            .text(text)
+                            .set_signature(signature)
+                            .build()
+                            .map_err(build_error)?,
+                    ))
+                }
+            });
+        }
+
+        let message = Message::builder()
+            .role(ConversationRole::Assistant)
+            .set_content(Some(content))
+            .build()
+            .map_err(build_error)?;
+
+        // A stream that ended without MessageStop was cut off upstream
+        let stop_reason = self.stop_reason.ok_or_else(|| {
+            BedrockError::ServiceUnavailable(
+                "Stream ended before the response finished".to_string(),
+            )
+        })?;
+
+        ConverseOutput::builder()
+            .output(aws_sdk_bedrockruntime::types::ConverseOutput::Message(
+                message,
+            ))
+            .stop_reason(stop_reason)
+            .set_usage(self.usage)
+            .build()
+            .map_err(build_error)
+    }
+}
+
+// Fixtures are decoded with the Gemini service's SSE decoder
+#[cfg(all(test, feature = "gemini"))]
+mod tests {
+    use super::*;
+    use crate::converters::converse_gemini::GeminiConverseEvents;
+    use crate::services::GeminiSseDecoder;
+
+    /// Converse events for a recorded Gemini stream
+    fn recorded_events(recording: &str) -> Vec<ConverseStreamOutput> {
+        let mut decoder = GeminiSseDecoder::new();
+        decoder.push(recording.as_bytes());
+        let mut adapter = GeminiConverseEvents::new();
+        let mut events = Vec::new();
+        while let Some(chunk) = decoder.next_chunk() {
+            events.extend(adapter.chunk_events(&chunk).unwrap());
+        }
+        if let Some(chunk) = decoder.finish() {
+            events.extend(adapter.chunk_events(&chunk).unwrap());
+        }
+        events.extend(adapter.finish_events().unwrap());
+        events
+    }
+
+    fn replay(events: Vec<ConverseStreamOutput>) -> ConverseStreamResponse {
+        ConverseStreamResponse::from_events(futures::stream::iter(events.into_iter().map(Ok)))
+    }
+
+    fn message_content(output: &ConverseOutput) -> &[ContentBlock] {
+        match output.output() {
+            Some(aws_sdk_bedrockruntime::types::ConverseOutput::Message(message)) => {
+                message.content()
+            }
+            _ => panic!("expected a message"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assembles_text_stream() {
+        let events = recorded_events(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/text_stream.sse"
+        )));
+
+        let output = assemble(replay(events)).await.unwrap();
+
+        assert_eq!(
+            message_content(&output),
+            &[ContentBlock::Text(
+                "The capital of France is Paris. It is known for the Eiffel Tower.".to_string()
+            )]
+        );
+        assert_eq!(output.stop_reason(), &StopReason::EndTurn);
+        assert_eq!(output.usage().unwrap().output_tokens(), 17);
+    }
+
+    #[tokio::test]
+    async fn test_assembles_tool_calls() {
+        let events = recorded_events(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/function_call_stream.sse"
+        )));
+
+        let output = assemble(replay(events)).await.unwrap();
+
+        let content = message_content(&output);
+        assert_eq!(content.len(), 3);
+        let ContentBlock::ToolUse(tool_use) = &content[1] else {
+            panic!("expected a tool use");
+        };
+        assert_eq!(tool_use.name(), "get_weather");
+        assert_eq!(output.stop_reason(), &StopReason::ToolUse);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_an_error() {
+        let mut events = recorded_events(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gemini/text_stream.sse"
+        )));
+        events.retain(|event| !matches!(event, ConverseStreamOutput::MessageStop(_)));
+
+        let err = assemble(replay(events)).await.unwrap_err();
+
+        assert!(matches!(err, BedrockError::ServiceUnavailable(_)));
+    }
+}
@@ -0,0 +1,291 @@
+//! Backpressure between backend streams and client connections
+//!
+//! A streamed response is produced by a generator that reads the backend and
+//! encodes SSE frames. [`buffered`] moves that generator onto its own task
+//! and hands frames to the client connection through a bounded channel, so
+//! the backend keeps being read while the client drains a burst, but a slow
+//! client never holds more than the configured number of frames. When the
+//! buffer is full the [`SlowClientPolicy`] applies: `pause` stops reading
+//! the backend until the client catches up, `disconnect` ends the stream if
+//! the buffer stays full past the timeout.
+//!
+//! [`StreamBufferMetrics`] reports buffer depth and how often clients fell
+//! behind, served at `GET /admin/stream-buffers`.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::config::{SlowClientPolicy, StreamBackpressureConfig};
+
+type Frames = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+
+/// Stream buffer totals across all streams
+#[derive(Debug, Default)]
+pub struct StreamBufferMetrics {
+    active_streams: AtomicUsize,
+    buffered_frames: AtomicUsize,
+    peak_stream_depth: AtomicUsize,
+    stalls: AtomicU64,
+    slow_client_disconnects: AtomicU64,
+}
+
+/// Snapshot of [`StreamBufferMetrics`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamBufferStats {
+    /// Buffered streams currently open
+    pub active_streams: usize,
+    /// Frames waiting in buffers right now, across all streams (including
+    /// the frame a stalled reader is holding)
+    pub buffered_frames: usize,
+    /// Deepest any single stream's buffer has been
+    pub peak_stream_depth: usize,
+    /// Times a full buffer held up the backend read
+    pub stalls: u64,
+    /// Streams ended under the `disconnect` policy
+    pub slow_client_disconnects: u64,
+}
+
+impl StreamBufferMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> StreamBufferStats {
+        StreamBufferStats {
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            buffered_frames: self.buffered_frames.load(Ordering::Relaxed),
+            peak_stream_depth: self.peak_stream_depth.load(Ordering::Relaxed),
+            stalls: self.stalls.load(Ordering::Relaxed),
+            slow_client_disconnects: self.slow_client_disconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Depth of one stream's buffer, shared by the reader task and the client
+/// side; the last one dropped releases whatever is still counted
+struct BufferDepth {
+    metrics: Arc<StreamBufferMetrics>,
+    frames: AtomicUsize,
+}
+
+impl BufferDepth {
+    fn new(metrics: Arc<StreamBufferMetrics>) -> Self {
+        metrics.active_streams.fetch_add(1, Ordering::Relaxed);
+        Self {
+            metrics,
+            frames: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self) {
+        let depth = self.frames.fetch_add(1, Ordering::Relaxed) + 1;
+        self.metrics.buffered_frames.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .peak_stream_depth
+            .fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn pop(&self) {
+        self.frames.fetch_sub(1, Ordering::Relaxed);
+        self.metrics.buffered_frames.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for BufferDepth {
+    fn drop(&mut self) {
+        let remaining = self.frames.load(Ordering::Relaxed);
+        self.metrics
+            .buffered_frames
+            .fetch_sub(remaining, Ordering::Relaxed);
+        self.metrics.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Read `frames` on a separate task into a bounded buffer and return the
+/// client side of the buffer
+///
+/// With `buffer_frames` 0 the frames are returned unchanged. Dropping the
+/// returned stream (the client disconnected) stops the reader at its next
+/// frame, which drops the generator as an unbuffered stream would.
+pub fn buffered(
+    frames: Frames,
+    config: &StreamBackpressureConfig,
+    metrics: Arc<StreamBufferMetrics>,
+) -> Frames {
+    if config.buffer_frames == 0 {
+        return frames;
+    }
+
+    let (sender, mut receiver) = mpsc::channel(config.buffer_frames);
+    let depth = Arc::new(BufferDepth::new(metrics));
+    let policy = config.slow_client_policy;
+    let timeout = config.slow_client_timeout();
+
+    let reader_depth = depth.clone();
+    tokio::spawn(async move {
+        let mut frames = frames;
+        while let Some(frame) = frames.next().await {
+            // Counted before sending so the client side never sees it uncounted
+            reader_depth.push();
+            let frame = match sender.try_send(frame) {
+                Ok(()) => continue,
+                Err(TrySendError::Closed(_)) => {
+                    reader_depth.pop();
+                    break;
+                }
+                Err(TrySendError::Full(frame)) => frame,
+            };
+
+            reader_depth.metrics.stalls.fetch_add(1, Ordering::Relaxed);
+            let sent = match policy {
+                SlowClientPolicy::Pause => sender.send(frame).await.is_ok(),
+                SlowClientPolicy::Disconnect => {
+                    match tokio::time::timeout(timeout, sender.send(frame)).await {
+                        Ok(result) => result.is_ok(),
+                        Err(_) => {
+                            reader_depth
+                                .metrics
+                                .slow_client_disconnects
+                                .fetch_add(1, Ordering::Relaxed);
+                            tracing::warn!(
+                                timeout_secs = timeout.as_secs(),
+                                "Client stopped reading, ending stream"
+                            );
+                            false
+                        }
+                    }
+                }
+            };
+            if !sent {
+                reader_depth.pop();
+                break;
+            }
+        }
+        // Release the count before the client side can see the stream end
+        drop(reader_depth);
+    });
+
+    Box::pin(async_stream::stream! {
+        while let Some(frame) = receiver.recv().await {
+            depth.pop();
+            yield frame;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(buffer_frames: usize, policy: SlowClientPolicy) -> StreamBackpressureConfig {
+        StreamBackpressureConfig {
+            buffer_frames,
+            slow_client_policy: policy,
+            slow_client_timeout_seconds: 0,
+        }
+    }
+
+    /// Endless frames, counting how many the generator produced
+    fn counting_frames(produced: Arc<AtomicUsize>) -> Frames {
+        Box::pin(async_stream::stream! {
+            loop {
+                let n = produced.fetch_add(1, Ordering::SeqCst);
+                yield Bytes::from(n.to_string());
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_frames_pass_through_in_order() {
+        let metrics = Arc::new(StreamBufferMetrics::new());
+        let frames: Frames = Box::pin(futures::stream::iter(
+            (0..10).map(|n| Bytes::from(n.to_string())),
+        ));
+
+        let out: Vec<_> = buffered(frames, &config(4, SlowClientPolicy::Pause), metrics.clone())
+            .collect()
+            .await;
+
+        let expected: Vec<_> = (0..10).map(|n| Bytes::from(n.to_string())).collect();
+        assert_eq!(out, expected);
+        let stats = metrics.snapshot();
+        assert_eq!(stats.active_streams, 0);
+        assert_eq!(stats.buffered_frames, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pause_bounds_backend_reads() {
+        let metrics = Arc::new(StreamBufferMetrics::new());
+        let produced = Arc::new(AtomicUsize::new(0));
+
+        let mut out = buffered(
+            counting_frames(produced.clone()),
+            &config(4, SlowClientPolicy::Pause),
+            metrics.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 4 buffered plus the one waiting to be sent
+        assert_eq!(produced.load(Ordering::SeqCst), 5);
+        let stats = metrics.snapshot();
+        assert_eq!(stats.buffered_frames, 5);
+        assert_eq!(stats.peak_stream_depth, 5);
+        assert_eq!(stats.stalls, 1);
+
+        // Reading resumes the backend
+        assert_eq!(out.next().await, Some(Bytes::from("0")));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(produced.load(Ordering::SeqCst), 6);
+
+        drop(out);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(metrics.snapshot().active_streams, 0);
+        assert_eq!(metrics.snapshot().buffered_frames, 0);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_ends_stalled_stream() {
+        let metrics = Arc::new(StreamBufferMetrics::new());
+        let produced = Arc::new(AtomicUsize::new(0));
+
+        let out = buffered(
+            counting_frames(produced),
+            &config(2, SlowClientPolicy::Disconnect),
+            metrics.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The client gets what was buffered, then the stream ends
+        let frames: Vec<_> = out.collect().await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(metrics.snapshot().slow_client_disconnects, 1);
+    }
+
+    #[tokio::test]
+    async fn test_zero_buffer_passes_stream_through() {
+        let metrics = Arc::new(StreamBufferMetrics::new());
+        let frames: Frames = Box::pin(futures::stream::iter(vec![Bytes::from("a")]));
+
+        let out: Vec<_> = buffered(frames, &config(0, SlowClientPolicy::Pause), metrics.clone())
+            .collect()
+            .await;
+
+        assert_eq!(out, vec![Bytes::from("a")]);
+        assert_eq!(
+            metrics.snapshot(),
+            StreamBufferStats {
+                active_streams: 0,
+                buffered_frames: 0,
+                peak_stream_depth: 0,
+                stalls: 0,
+                slow_client_disconnects: 0,
+            }
+        );
+    }
+}
@@ -0,0 +1,657 @@
+//! Stream recorder and replayer
+//!
+//! Streaming edge cases (tool calls interleaved with text, guardrail stops,
+//! errors after the first token) are hard to reproduce against a live model.
+//! [`StreamRecorder`] is a [`BackendHook`] that, once armed through
+//! `POST /debug/stream-recordings`, captures the complete Converse event
+//! sequence of the next streamed backend calls as [`StreamRecording`]s.
+//! Gemini backends are recorded after adaptation to Converse events, which is
+//! what the handlers consume.
+//!
+//! A recording saved as a fixture replays through [`ReplayBackend`], so the
+//! handlers' stream conversion can be tested deterministically without a
+//! backend. Streams the client abandons before the end are not kept.
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::converse::ConverseOutput;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlockDelta, ContentBlockDeltaEvent, ContentBlockStart, ContentBlockStartEvent,
+    ContentBlockStopEvent, ConversationRole, ConverseStreamMetadataEvent, ConverseStreamOutput,
+    MessageStartEvent, MessageStopEvent, ReasoningContentBlockDelta, StopReason, TokenUsage,
+    ToolUseBlockDelta, ToolUseBlockStart,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::services::backend::{Backend, BackendCapabilities};
+use crate::services::backend_hooks::{BackendHook, HookContext};
+use crate::services::bedrock::{
+    BedrockError, BedrockErrorType, BedrockStreamError, ConverseRequest, ConverseStreamResponse,
+};
+use crate::services::stream_assembly;
+
+/// Completed recordings kept in memory; the oldest are dropped first
+const MAX_RECORDINGS: usize = 32;
+
+/// Most streams one arming may capture
+pub const MAX_ARMED_STREAMS: u32 = 16;
+
+/// Tool use announced by a `content_block_start`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedToolUse {
+    pub tool_use_id: String,
+    pub name: String,
+}
+
+/// Content of a `content_block_delta`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedDelta {
+    Text { text: String },
+    ToolUse { input: String },
+    ReasoningText { text: String },
+    ReasoningSignature { signature: String },
+}
+
+/// How a stream failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedErrorKind {
+    Throttled,
+    ServiceUnavailable,
+    Validation,
+    /// Any other error the backend reported mid-stream
+    Server,
+    /// The event stream itself broke (transport or parse failure)
+    Stream,
+}
+
+/// One Converse stream event in serializable form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    MessageStart {
+        role: String,
+    },
+    ContentBlockStart {
+        index: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_use: Option<RecordedToolUse>,
+    },
+    ContentBlockDelta {
+        index: i32,
+        delta: RecordedDelta,
+    },
+    ContentBlockStop {
+        index: i32,
+    },
+    MessageStop {
+        stop_reason: String,
+    },
+    Metadata {
+        input_tokens: i32,
+        output_tokens: i32,
+    },
+    /// The stream ended with an error
+    Error {
+        kind: RecordedErrorKind,
+        message: String,
+    },
+}
+
+impl RecordedEvent {
+    /// Record a Converse event; None for events the handlers ignore
+    pub fn from_event(event: &ConverseStreamOutput) -> Option<Self> {
+        let recorded = match event {
+            ConverseStreamOutput::MessageStart(start) => RecordedEvent::MessageStart {
+                role: start.role().as_str().to_string(),
+            },
+            ConverseStreamOutput::ContentBlockStart(start) => RecordedEvent::ContentBlockStart {
+                index: start.content_block_index(),
+                tool_use: match start.start() {
+                    Some(ContentBlockStart::ToolUse(tool)) => Some(RecordedToolUse {
+                        tool_use_id: tool.tool_use_id().to_string(),
+                        name: tool.name().to_string(),
+                    }),
+                    _ => None,
+                },
+            },
+            ConverseStreamOutput::ContentBlockDelta(delta) => RecordedEvent::ContentBlockDelta {
+                index: delta.content_block_index(),
+                delta: match delta.delta()? {
+                    ContentBlockDelta::Text(text) => RecordedDelta::Text { text: text.clone() },
+                    ContentBlockDelta::ToolUse(tool) => RecordedDelta::ToolUse {
+                        input: tool.input().to_string(),
+                    },
+                    ContentBlockDelta::ReasoningContent(ReasoningContentBlockDelta::Text(text)) => {
+                        RecordedDelta::ReasoningText { text: text.clone() }
+                    }
+                    ContentBlockDelta::ReasoningContent(ReasoningContentBlockDelta::Signature(
+                        signature,
+                    )) => RecordedDelta::ReasoningSignature {
+                        signature: signature.clone(),
+                    },
+                    _ => return None,
+                },
+            },
+            ConverseStreamOutput::ContentBlockStop(stop) => RecordedEvent::ContentBlockStop {
+                index: stop.content_block_index(),
+            },
+            ConverseStreamOutput::MessageStop(stop) => RecordedEvent::MessageStop {
+                stop_reason: stop.stop_reason().as_str().to_string(),
+            },
+            ConverseStreamOutput::Metadata(metadata) => {
+                let usage = metadata.usage()?;
+                RecordedEvent::Metadata {
+                    input_tokens: usage.input_tokens(),
+                    output_tokens: usage.output_tokens(),
+                }
+            }
+            _ => return None,
+        };
+        Some(recorded)
+    }
+
+    /// Record the error that ended a stream
+    pub fn from_error(err: &BedrockStreamError) -> Self {
+        let (kind, message) = match err {
+            BedrockStreamError::Backend(BedrockError::Throttled(m)) => {
+                (RecordedErrorKind::Throttled, m.clone())
+            }
+            BedrockStreamError::Backend(BedrockError::ServiceUnavailable(m)) => {
+                (RecordedErrorKind::ServiceUnavailable, m.clone())
+            }
+            BedrockStreamError::Backend(BedrockError::ValidationError(m)) => {
+                (RecordedErrorKind::Validation, m.clone())
+            }
+            BedrockStreamError::Backend(BedrockError::ApiError { message, .. }) => {
+                (RecordedErrorKind::Server, message.clone())
+            }
+            BedrockStreamError::Backend(other) => (RecordedErrorKind::Server, other.to_string()),
+            BedrockStreamError::StreamError(m) | BedrockStreamError::ParseError(m) => {
+                (RecordedErrorKind::Stream, m.clone())
+            }
+        };
+        RecordedEvent::Error { kind, message }
+    }
+
+    /// Rebuild the Converse event (or stream error) this was recorded from
+    pub fn to_event(&self) -> Result<ConverseStreamOutput, BedrockStreamError> {
+        let build_error = |e: aws_smithy_types::error::operation::BuildError| {
+            BedrockStreamError::ParseError(format!("Invalid recorded event: {}", e))
+        };
+
+        let event = match self {
+            RecordedEvent::MessageStart { role } => ConverseStreamOutput::MessageStart(
+                MessageStartEvent::builder()
+                    .role(ConversationRole::from(role.as_str()))
+                    .build()
+                    .map_err(build_error)?,
+            ),
+            RecordedEvent::ContentBlockStart { index, tool_use } => {
+                let start = match tool_use {
+                    Some(tool) => Some(ContentBlockStart::ToolUse(
+                        ToolUseBlockStart::builder()
+                            .tool_use_id(&tool.tool_use_id)
+                            .name(&tool.name)
+                            .build()
+                            .map_err(build_error)?,
+                    )),
+                    None => None,
+                };
+                ConverseStreamOutput::ContentBlockStart(
+                    ContentBlockStartEvent::builder()
+                        .content_block_index(*index)
+                        .set_start(start)
+                        .build()
+                        .map_err(build_error)?,
+                )
+            }
+            RecordedEvent::ContentBlockDelta { index, delta } => {
+                let delta = match delta {
+                    RecordedDelta::Text { text } => ContentBlockDelta::Text(text.clone()),
+                    RecordedDelta::ToolUse { input } => ContentBlockDelta::ToolUse(
+                        ToolUseBlockDelta::builder()
+                            .input(input)
+                            .build()
+                            .map_err(build_error)?,
+                    ),
+                    RecordedDelta::ReasoningText { text } => ContentBlockDelta::ReasoningContent(
+                        ReasoningContentBlockDelta::Text(text.clone()),
+                    ),
+                    RecordedDelta::ReasoningSignature { signature } => {
+                        ContentBlockDelta::ReasoningContent(ReasoningContentBlockDelta::Signature(
+                            signature.clone(),
+                        ))
+                    }
+                };
+                ConverseStreamOutput::ContentBlockDelta(
+                    ContentBlockDeltaEvent::builder()
+                        .content_block_index(*index)
+                        .delta(delta)
+                        .build()
+                        .map_err(build_error)?,
+                )
+            }
+            RecordedEvent::ContentBlockStop { index } => ConverseStreamOutput::ContentBlockStop(
+                ContentBlockStopEvent::builder()
+                    .content_block_index(*index)
+                    .build()
+                    .map_err(build_error)?,
+            ),
+            RecordedEvent::MessageStop { stop_reason } => ConverseStreamOutput::MessageStop(
+                MessageStopEvent::builder()
+                    .stop_reason(StopReason::from(stop_reason.as_str()))
+                    .build()
+                    .map_err(build_error)?,
+            ),
+            RecordedEvent::Metadata {
+                input_tokens,
+                output_tokens,
+            } => ConverseStreamOutput::Metadata(
+                ConverseStreamMetadataEvent::builder()
+                    .usage(
+                        TokenUsage::builder()
+                            .input_tokens(*input_tokens)
+                            .output_tokens(*output_tokens)
+                            .total_tokens(input_tokens + output_tokens)
+                            .build()
+                            .map_err(build_error)?,
+                    )
+                    .build(),
+            ),
+            RecordedEvent::Error { kind, message } => {
+                let message = message.clone();
+                return Err(match kind {
+                    RecordedErrorKind::Throttled => {
+                        BedrockStreamError::Backend(BedrockError::Throttled(message))
+                    }
+                    RecordedErrorKind::ServiceUnavailable => {
+                        BedrockStreamError::Backend(BedrockError::ServiceUnavailable(message))
+                    }
+                    RecordedErrorKind::Validation => {
+                        BedrockStreamError::Backend(BedrockError::ValidationError(message))
+                    }
+                    RecordedErrorKind::Server => {
+                        BedrockStreamError::Backend(BedrockError::ApiError {
+                            message,
+                            error_type: BedrockErrorType::Server,
+                            is_retryable: true,
+                        })
+                    }
+                    RecordedErrorKind::Stream => BedrockStreamError::StreamError(message),
+                });
+            }
+        };
+        Ok(event)
+    }
+}
+
+/// Complete event sequence of one backend stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamRecording {
+    pub id: String,
+    /// Backend name, e.g. "bedrock" or "gemini"
+    pub backend: String,
+    /// Routing target the backend is registered under
+    pub target: String,
+    /// Provider model ID the stream was opened for
+    pub model_id: String,
+    pub recorded_at: DateTime<Utc>,
+    pub events: Vec<RecordedEvent>,
+}
+
+impl StreamRecording {
+    /// Read a recording saved as JSON (e.g. a test fixture)
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Replay the recorded events as a Converse stream
+    ///
+    /// A recorded error is returned in its place and ends the stream.
+    pub fn replay(&self) -> ConverseStreamResponse {
+        let events: Vec<_> = self.events.iter().map(RecordedEvent::to_event).collect();
+        let end = events
+            .iter()
+            .position(Result::is_err)
+            .map_or(events.len(), |i| i + 1);
+        ConverseStreamResponse::from_events(futures::stream::iter(events.into_iter().take(end)))
+    }
+
+    fn summary(&self) -> RecordingSummary {
+        RecordingSummary {
+            id: self.id.clone(),
+            backend: self.backend.clone(),
+            target: self.target.clone(),
+            model_id: self.model_id.clone(),
+            recorded_at: self.recorded_at,
+            events: self.events.len(),
+        }
+    }
+}
+
+/// Listing entry for a recording
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub id: String,
+    pub backend: String,
+    pub target: String,
+    pub model_id: String,
+    pub recorded_at: DateTime<Utc>,
+    pub events: usize,
+}
+
+/// Recorder status reported by the debug endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct RecorderStatus {
+    /// Streams still to be captured
+    pub armed: u32,
+    /// Only streams to this routing target are captured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub recordings: usize,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    armed: u32,
+    target: Option<String>,
+    recordings: VecDeque<StreamRecording>,
+}
+
+/// Captures backend streams while armed
+#[derive(Debug, Default)]
+pub struct StreamRecorder {
+    state: Arc<Mutex<RecorderState>>,
+    next_id: AtomicU64,
+}
+
+impl StreamRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture the next `count` streams, optionally only those to `target`
+    pub fn arm(&self, count: u32, target: Option<String>) -> RecorderStatus {
+        let mut state = self.state.lock().unwrap();
+        state.armed = count.min(MAX_ARMED_STREAMS);
+        state.target = target;
+        Self::status_of(&state)
+    }
+
+    pub fn status(&self) -> RecorderStatus {
+        Self::status_of(&self.state.lock().unwrap())
+    }
+
+    /// Completed recordings, oldest first
+    pub fn list(&self) -> Vec<RecordingSummary> {
+        let state = self.state.lock().unwrap();
+        state
+            .recordings
+            .iter()
+            .map(StreamRecording::summary)
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<StreamRecording> {
+        let state = self.state.lock().unwrap();
+        state.recordings.iter().find(|r| r.id == id).cloned()
+    }
+
+    /// Disarm and drop every recording; returns how many were dropped
+    pub fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.armed = 0;
+        state.target = None;
+        let dropped = state.recordings.len();
+        state.recordings.clear();
+        dropped
+    }
+
+    fn status_of(state: &RecorderState) -> RecorderStatus {
+        RecorderStatus {
+            armed: state.armed,
+            target: state.target.clone(),
+            recordings: state.recordings.len(),
+        }
+    }
+
+    /// Take one armed slot if this stream should be captured
+    fn claim(&self, ctx: &HookContext) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.armed == 0 || state.target.as_ref().is_some_and(|t| *t != ctx.target) {
+            return false;
+        }
+        state.armed -= 1;
+        true
+    }
+}
+
+#[async_trait]
+impl BackendHook for StreamRecorder {
+    fn name(&self) -> &'static str {
+        "stream_recorder"
+    }
+
+    fn wrap_stream(
+        &self,
+        ctx: &HookContext,
+        model_id: &str,
+        stream: ConverseStreamResponse,
+    ) -> ConverseStreamResponse {
+        if !self.claim(ctx) {
+            return stream;
+        }
+
+        let mut recording = StreamRecording {
+            id: format!("rec_{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1),
+            backend: ctx.backend.to_string(),
+            target: ctx.target.clone(),
+            model_id: model_id.to_string(),
+            recorded_at: Utc::now(),
+            events: Vec::new(),
+        };
+        let state = self.state.clone();
+        let mut events = stream.into_stream();
+
+        ConverseStreamResponse::from_events(async_stream::stream! {
+            while let Some(item) = events.next().await {
+                let failed = match &item {
+                    Ok(event) => {
+                        recording.events.extend(RecordedEvent::from_event(event));
+                        false
+                    }
+                    Err(e) => {
+                        recording.events.push(RecordedEvent::from_error(e));
+                        true
+                    }
+                };
+                yield item;
+                if failed {
+                    break;
+                }
+            }
+
+            tracing::info!(recording = %recording.id, events = recording.events.len(), "Stream recorded");
+            let mut state = state.lock().unwrap();
+            if state.recordings.len() >= MAX_RECORDINGS {
+                state.recordings.pop_front();
+            }
+            state.recordings.push_back(recording);
+        })
+    }
+}
+
+/// Backend that replays one recording for every streaming call
+///
+/// Non-streaming calls get the recording assembled into a Converse output,
+/// as in stream-and-assemble mode.
+#[derive(Debug, Clone)]
+pub struct ReplayBackend {
+    recording: StreamRecording,
+}
+
+impl ReplayBackend {
+    pub fn new(recording: StreamRecording) -> Self {
+        Self { recording }
+    }
+}
+
+#[async_trait]
+impl Backend for ReplayBackend {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            service_tiers: false,
+            native_token_counting: false,
+            prompt_caching: false,
+            additional_fields: false,
+        }
+    }
+
+    fn resolve_model_id(&self, model: &str) -> String {
+        model.to_string()
+    }
+
+    async fn converse(&self, _request: ConverseRequest) -> Result<ConverseOutput, BedrockError> {
+        stream_assembly::assemble(self.recording.replay()).await
+    }
+
+    async fn converse_stream(
+        &self,
+        _request: ConverseRequest,
+    ) -> Result<ConverseStreamResponse, BedrockError> {
+        Ok(self.recording.replay())
+    }
+
+    fn health_check(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::backend_hooks::HookedBackend;
+
+    const INTERLEAVED_TOOL_USE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/interleaved_tool_use.json"
+    );
+    const THROTTLED_MID_STREAM: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/streams/throttled_mid_stream.json"
+    );
+
+    async fn drain(mut stream: ConverseStreamResponse) -> (Vec<RecordedEvent>, Option<String>) {
+        let mut events = Vec::new();
+        loop {
+            match stream.recv().await {
+                Ok(Some(event)) => events.extend(RecordedEvent::from_event(&event)),
+                Ok(None) => return (events, None),
+                Err(e) => return (events, Some(e.to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_round_trips_events() {
+        let recording = StreamRecording::load(Path::new(INTERLEAVED_TOOL_USE)).unwrap();
+
+        let (events, error) = drain(recording.replay()).await;
+
+        assert_eq!(events, recording.events);
+        assert!(error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_ends_with_recorded_error() {
+        let recording = StreamRecording::load(Path::new(THROTTLED_MID_STREAM)).unwrap();
+
+        let (events, error) = drain(recording.replay()).await;
+
+        assert_eq!(events.len(), recording.events.len() - 1);
+        assert_eq!(
+            error.as_deref(),
+            Some("Throttled: Too many tokens, please wait")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorder_captures_only_while_armed() {
+        let fixture = StreamRecording::load(Path::new(INTERLEAVED_TOOL_USE)).unwrap();
+        let recorder = Arc::new(StreamRecorder::new());
+        let backend = HookedBackend::new(
+            Arc::new(ReplayBackend::new(fixture.clone())),
+            "bedrock",
+            vec![recorder.clone()],
+        );
+
+        // Disarmed: passes through without recording
+        let stream = backend
+            .converse_stream(ConverseRequest::new("m"))
+            .await
+            .unwrap();
+        drain(stream).await;
+        assert!(recorder.list().is_empty());
+
+        // Wrong target: not claimed
+        recorder.arm(1, Some("gemini".to_string()));
+        let stream = backend
+            .converse_stream(ConverseRequest::new("m"))
+            .await
+            .unwrap();
+        drain(stream).await;
+        assert!(recorder.list().is_empty());
+
+        recorder.arm(1, None);
+        let stream = backend
+            .converse_stream(ConverseRequest::new("model-x"))
+            .await
+            .unwrap();
+        drain(stream).await;
+        assert_eq!(recorder.status().armed, 0);
+
+        let recordings = recorder.list();
+        assert_eq!(recordings.len(), 1);
+        let recorded = recorder.get(&recordings[0].id).unwrap();
+        assert_eq!(recorded.backend, "replay");
+        assert_eq!(recorded.target, "bedrock");
+        assert_eq!(recorded.model_id, "model-x");
+        assert_eq!(recorded.events, fixture.events);
+
+        assert_eq!(recorder.clear(), 1);
+        assert!(recorder.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_stream_is_not_kept() {
+        let fixture = StreamRecording::load(Path::new(INTERLEAVED_TOOL_USE)).unwrap();
+        let recorder = Arc::new(StreamRecorder::new());
+        let backend = HookedBackend::new(
+            Arc::new(ReplayBackend::new(fixture)),
+            "bedrock",
+            vec![recorder.clone()],
+        );
+
+        recorder.arm(1, None);
+        let mut stream = backend
+            .converse_stream(ConverseRequest::new("m"))
+            .await
+            .unwrap();
+        stream.recv().await.unwrap();
+        drop(stream);
+
+        assert!(recorder.list().is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+//! Prompt token counting
+//!
+//! Counts the tokens of Anthropic-format prompts for backends without a
+//! tokenizer endpoint. The tokenizer is picked by model family:
+//!
+//! - OpenAI `gpt-4o`, `gpt-4.1` and o-series models use `o200k_base`
+//! - Older OpenAI models use `cl100k_base`
+//! - Claude models use `cl100k_base` scaled by [`CLAUDE_CL100K_RATIO`]. Claude's
+//!   tokenizer is not public; on mixed English and code prompts it produces
+//!   about 10% more tokens than `cl100k_base`.
+//! - Everything else (Llama, Mistral, Qwen, DeepSeek, ...) falls back to a
+//!   heuristic of one token per CJK character and four characters per token
+//!   for the rest, which is closer than a flat character ratio for CJK text.
+//!
+//! Counts are broken down into the system prompt, each message and the tool
+//! definitions, the way `count_tokens` reports them.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::Value;
+use tiktoken_rs::CoreBPE;
+
+/// Claude tokens per `cl100k_base` token
+pub const CLAUDE_CL100K_RATIO: f64 = 1.1;
+
+/// Characters per token for non-CJK text in heuristic estimates
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Flat cost of an image or binary document block
+const ATTACHMENT_TOKENS: usize = 1_600;
+
+/// Framing tokens around each message (role and turn separators)
+const MESSAGE_OVERHEAD_TOKENS: usize = 3;
+
+/// Framing tokens around each tool definition
+const TOOL_OVERHEAD_TOKENS: usize = 8;
+
+/// Tokenizer used to count a model's prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerFamily {
+    /// `o200k_base` (GPT-4o, GPT-4.1, o-series)
+    O200k,
+    /// `cl100k_base` (GPT-4, GPT-3.5)
+    Cl100k,
+    /// `cl100k_base` scaled to approximate Claude's tokenizer
+    Claude,
+    /// Character heuristic for models without a known tokenizer
+    Heuristic,
+}
+
+impl TokenizerFamily {
+    /// Pick the tokenizer for a client or backend model ID
+    ///
+    /// Region and provider prefixes (`us.anthropic.`, `azure/`, ...) are
+    /// ignored, so Bedrock and routed model IDs resolve like bare names.
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_ascii_lowercase();
+        let name = model.rsplit(['/', '.']).next().unwrap_or(&model);
+
+        if model.contains("claude") {
+            Self::Claude
+        } else if name.starts_with("gpt-4o")
+            || name.starts_with("chatgpt-4o")
+            || model.contains("gpt-4.1")
+            || model.contains("gpt-5")
+            || is_o_series(name)
+        {
+            Self::O200k
+        } else if model.contains("gpt-4")
+            || model.contains("gpt-3.5")
+            || model.contains("text-embedding")
+        {
+            Self::Cl100k
+        } else {
+            Self::Heuristic
+        }
+    }
+
+    /// Name reported in logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::O200k => "o200k_base",
+            Self::Cl100k => "cl100k_base",
+            Self::Claude => "claude",
+            Self::Heuristic => "heuristic",
+        }
+    }
+
+    /// Count the tokens of a piece of text
+    pub fn count_text(&self, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        match self {
+            Self::O200k => bpe_count(o200k(), text),
+            Self::Cl100k => bpe_count(cl100k(), text),
+            Self::Claude => match cl100k() {
+                Some(bpe) => {
+                    let count = bpe.encode_with_special_tokens(text).len() as f64;
+                    (count * CLAUDE_CL100K_RATIO).ceil() as usize
+                }
+                None => heuristic_count(text),
+            },
+            Self::Heuristic => heuristic_count(text),
+        }
+    }
+}
+
+/// `o1`, `o3-mini`, `o4-mini`, ...
+fn is_o_series(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+fn cl100k() -> Option<&'static CoreBPE> {
+    static BPE: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    BPE.get_or_init(|| load_bpe("cl100k_base", tiktoken_rs::cl100k_base))
+        .as_ref()
+}
+
+fn o200k() -> Option<&'static CoreBPE> {
+    static BPE: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    BPE.get_or_init(|| load_bpe("o200k_base", tiktoken_rs::o200k_base))
+        .as_ref()
+}
+
+fn load_bpe(name: &str, load: fn() -> anyhow::Result<CoreBPE>) -> Option<CoreBPE> {
+    load()
+        .map_err(|e| {
+            tracing::warn!(tokenizer = name, error = %e, "Failed to load tokenizer; using heuristic counts");
+        })
+        .ok()
+}
+
+fn bpe_count(bpe: Option<&CoreBPE>, text: &str) -> usize {
+    match bpe {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        None => heuristic_count(text),
+    }
+}
+
+/// One token per CJK character, ~4 characters per token otherwise
+fn heuristic_count(text: &str) -> usize {
+    let (cjk, other) = text.chars().fold((0usize, 0usize), |(cjk, other), c| {
+        if is_cjk(c) {
+            (cjk + 1, other)
+        } else {
+            (cjk, other + 1)
+        }
+    });
+    cjk + other.div_ceil(CHARS_PER_TOKEN)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // Hiragana, Katakana
+        | 0x3400..=0x4DBF    // CJK Extension A
+        | 0x4E00..=0x9FFF    // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF    // Hangul Syllables
+        | 0xF900..=0xFAFF    // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F  // CJK Extensions B-F, Compatibility Supplement
+    )
+}
+
+/// Token counts of a prompt by block
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TokenBreakdown {
+    /// System prompt
+    pub system: i32,
+    /// Each message, in request order
+    pub messages: Vec<i32>,
+    /// All tool definitions
+    pub tools: i32,
+}
+
+impl TokenBreakdown {
+    /// Total prompt tokens (at least 1)
+    pub fn total(&self) -> i32 {
+        (self.system + self.messages.iter().sum::<i32>() + self.tools).max(1)
+    }
+}
+
+/// Count an Anthropic-format prompt with the tokenizer for `model`
+pub fn count_prompt(
+    model: &str,
+    system: Option<&Value>,
+    messages: &[Value],
+    tools: Option<&[Value]>,
+) -> TokenBreakdown {
+    let family = TokenizerFamily::for_model(model);
+
+    let system = system.map(|s| count_content(family, s)).unwrap_or(0);
+    let messages = messages
+        .iter()
+        .map(|message| {
+            let content = message
+                .get("content")
+                .map(|c| count_content(family, c))
+                .unwrap_or(0);
+            (content + MESSAGE_OVERHEAD_TOKENS) as i32
+        })
+        .collect();
+    let tools = tools
+        .unwrap_or_default()
+        .iter()
+        .map(|tool| count_tool(family, tool))
+        .sum::<usize>();
+
+    TokenBreakdown {
+        system: system as i32,
+        messages,
+        tools: tools as i32,
+    }
+}
+
+/// Count a string or an array of content blocks
+fn count_content(family: TokenizerFamily, content: &Value) -> usize {
+    match content {
+        Value::String(text) => family.count_text(text),
+        Value::Array(blocks) => blocks.iter().map(|b| count_block(family, b)).sum(),
+        Value::Null => 0,
+        other => family.count_text(&other.to_string()),
+    }
+}
+
+fn count_block(family: TokenizerFamily, block: &Value) -> usize {
+    let text = |key: &str| block.get(key).and_then(Value::as_str).unwrap_or_default();
+
+    match text("type") {
+        "text" => family.count_text(text("text")),
+        "thinking" => family.count_text(text("thinking")),
+        "redacted_thinking" => 0,
+        "tool_use" | "server_tool_use" => {
+            let input = block.get("input").map(Value::to_string).unwrap_or_default();
+            family.count_text(text("name")) + family.count_text(&input)
+        }
+        "tool_result" => block
+            .get("content")
+            .map(|c| count_content(family, c))
+            .unwrap_or(0),
+        "image" => ATTACHMENT_TOKENS,
+        "document" => match block.pointer("/source/type").and_then(Value::as_str) {
+            Some("text") => family.count_text(
+                block
+                    .pointer("/source/data")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            ),
+            Some("content") => block
+                .pointer("/source/content")
+                .map(|c| count_content(family, c))
+                .unwrap_or(0),
+            _ => ATTACHMENT_TOKENS,
+        },
+        _ => family.count_text(&block.to_string()),
+    }
+}
+
+fn count_tool(family: TokenizerFamily, tool: &Value) -> usize {
+    let text = |key: &str| tool.get(key).and_then(Value::as_str).unwrap_or_default();
+    let schema = tool
+        .get("input_schema")
+        .map(Value::to_string)
+        .unwrap_or_default();
+
+    TOOL_OVERHEAD_TOKENS
+        + family.count_text(text("name"))
+        + family.count_text(text("description"))
+        + family.count_text(&schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_family_for_model() {
+        assert_eq!(TokenizerFamily::for_model("gpt-4o-mini"), TokenizerFamily::O200k);
+        assert_eq!(TokenizerFamily::for_model("azure/gpt-4.1"), TokenizerFamily::O200k);
+        assert_eq!(TokenizerFamily::for_model("o3-mini"), TokenizerFamily::O200k);
+        assert_eq!(TokenizerFamily::for_model("gpt-4-turbo"), TokenizerFamily::Cl100k);
+        assert_eq!(
+            TokenizerFamily::for_model("us.anthropic.claude-sonnet-4-20250514-v1:0"),
+            TokenizerFamily::Claude
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("qwen.qwen3-coder-480b-a35b-v1:0"),
+            TokenizerFamily::Heuristic
+        );
+    }
+
+    #[test]
+    fn test_heuristic_counts_cjk_per_character() {
+        assert_eq!(heuristic_count("你好世界"), 4);
+        assert_eq!(heuristic_count("abcdefgh"), 2);
+        assert_eq!(heuristic_count("こんにちは abc"), 6);
+    }
+
+    #[test]
+    fn test_bpe_counts() {
+        assert_eq!(TokenizerFamily::Cl100k.count_text("hello world"), 2);
+        assert_eq!(TokenizerFamily::O200k.count_text("hello world"), 2);
+        assert!(
+            TokenizerFamily::Claude.count_text("fn main() { println!(\"hi\"); }")
+                > TokenizerFamily::Cl100k.count_text("fn main() { println!(\"hi\"); }")
+        );
+    }
+
+    #[test]
+    fn test_count_prompt_breakdown() {
+        let messages = vec![
+            json!({"role": "user", "content": "hello world"}),
+            json!({"role": "assistant", "content": [
+                {"type": "tool_use", "id": "t1", "name": "search", "input": {"q": "rust"}}
+            ]}),
+            json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "found it"},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+            ]}),
+        ];
+        let tools = vec![json!({
+            "name": "search",
+            "description": "Search the web",
+            "input_schema": {"type": "object", "properties": {"q": {"type": "string"}}}
+        })];
+        let system = json!("You are helpful.");
+
+        let breakdown = count_prompt("gpt-4o", Some(&system), &messages, Some(&tools));
+
+        assert_eq!(breakdown.messages.len(), 3);
+        assert_eq!(breakdown.messages[0], 2 + MESSAGE_OVERHEAD_TOKENS as i32);
+        assert!(breakdown.messages[2] > ATTACHMENT_TOKENS as i32);
+        assert!(breakdown.system > 0);
+        assert!(breakdown.tools > TOOL_OVERHEAD_TOKENS as i32);
+        assert_eq!(
+            breakdown.total(),
+            breakdown.system + breakdown.messages.iter().sum::<i32>() + breakdown.tools
+        );
+    }
+
+    #[test]
+    fn test_empty_prompt_counts_one() {
+        assert_eq!(count_prompt("claude-3-5-haiku", None, &[], None).total(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+//! Session-scoped tool result caching
+//!
+//! Agents often repeat a tool call they already made (the same lookup after
+//! a failed attempt, the same exploratory snippet each turn). Within one
+//! agent run or PTC session, [`ToolResultCache`] keeps the successful results
+//! of the tools it is told to cache, keyed by tool name and a hash of the
+//! input, so a repeated call is answered without running the tool again.
+//!
+//! Caching is opt-in per tool because tools with side effects or changing
+//! output must run every time. Inputs are hashed in a canonical form, so
+//! calls differing only in object key order share an entry.
+
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Tool name that enables caching for every tool
+pub const CACHE_ALL_TOOLS: &str = "*";
+
+/// Most results kept per session; later results are not cached
+const MAX_ENTRIES: usize = 256;
+
+/// Results of a session's cacheable tool calls
+#[derive(Debug, Default)]
+pub struct ToolResultCache {
+    /// Tools whose results are reused (`*` for all)
+    tools: HashSet<String>,
+    entries: Mutex<HashMap<(String, u64), String>>,
+    hits: AtomicU64,
+}
+
+impl ToolResultCache {
+    /// Cache the results of the named tools (`*` for all)
+    pub fn new(tools: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            tools: tools.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether results of `tool` are cached
+    pub fn caches(&self, tool: &str) -> bool {
+        self.tools.contains(tool) || self.tools.contains(CACHE_ALL_TOOLS)
+    }
+
+    /// Result of an earlier identical call, if cached
+    pub fn get(&self, tool: &str, input: &Value) -> Option<String> {
+        if !self.caches(tool) {
+            return None;
+        }
+        let key = (tool.to_string(), input_hash(input));
+        let result = self.entries.lock().ok()?.get(&key).cloned();
+        if result.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Keep a successful result for later identical calls
+    pub fn insert(&self, tool: &str, input: &Value, result: &str) {
+        if !self.caches(tool) {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() < MAX_ENTRIES {
+                entries.insert((tool.to_string(), input_hash(input)), result.to_string());
+            }
+        }
+    }
+
+    /// Calls answered from the cache so far
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+/// Hash of a tool input, independent of object key order
+pub fn input_hash(input: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_value(input, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_value(value: &Value, hasher: &mut DefaultHasher) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            b'{'.hash(hasher);
+            for key in keys {
+                key.hash(hasher);
+                hash_value(&map[key], hasher);
+            }
+            b'}'.hash(hasher);
+        }
+        Value::Array(items) => {
+            b'['.hash(hasher);
+            for item in items {
+                hash_value(item, hasher);
+            }
+            b']'.hash(hasher);
+        }
+        other => other.to_string().hash(hasher),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hash_ignores_key_order() {
+        let a: Value = serde_json::from_str(r#"{"city": "Paris", "days": [1, 2]}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"days": [1, 2], "city": "Paris"}"#).unwrap();
+        assert_eq!(input_hash(&a), input_hash(&b));
+        assert_ne!(input_hash(&a), input_hash(&json!({"city": "Paris", "days": [2, 1]})));
+        assert_ne!(input_hash(&json!("1")), input_hash(&json!(1)));
+    }
+
+    #[test]
+    fn test_only_configured_tools_are_cached() {
+        let cache = ToolResultCache::new(["forecast".to_string()]);
+        let input = json!({"city": "Paris"});
+
+        assert_eq!(cache.get("forecast", &input), None);
+        cache.insert("forecast", &input, "Sunny");
+        cache.insert("send_email", &input, "Sent");
+
+        assert_eq!(cache.get("forecast", &input).as_deref(), Some("Sunny"));
+        assert_eq!(cache.get("forecast", &json!({"city": "Rome"})), None);
+        assert_eq!(cache.get("send_email", &input), None);
+        assert_eq!(cache.hits(), 1);
+
+        let all = ToolResultCache::new([CACHE_ALL_TOOLS.to_string()]);
+        assert!(all.caches("send_email"));
+        assert!(!ToolResultCache::default().caches("forecast"));
+    }
+}
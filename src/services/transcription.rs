@@ -0,0 +1,183 @@
+//! Audio transcription bridge
+//!
+//! Claude models on Bedrock do not accept audio, so OpenAI `input_audio`
+//! content parts are transcribed server-side and replaced by their text before
+//! conversion. Transcription goes through an OpenAI-compatible
+//! `/audio/transcriptions` endpoint: OpenAI Whisper, or a self-hosted Whisper
+//! server exposing the same API.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::TranscriptionConfig;
+use crate::schemas::openai::InputAudio;
+
+/// Errors from transcribing an audio part
+#[derive(Debug, Error)]
+pub enum TranscriptionError {
+    #[error("Unsupported audio format '{0}': expected 'wav' or 'mp3'")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid base64 audio: {0}")]
+    InvalidAudio(String),
+
+    #[error("Audio is {size} bytes, larger than the {limit} byte limit")]
+    TooLarge { size: usize, limit: usize },
+
+    #[error("Transcription request failed: {0}")]
+    Request(String),
+
+    #[error("Transcription backend returned {status}: {message}")]
+    Backend { status: u16, message: String },
+}
+
+impl TranscriptionError {
+    /// Whether the error is caused by the client's audio rather than the backend
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsupportedFormat(_) | Self::InvalidAudio(_) | Self::TooLarge { .. }
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+/// Client for an OpenAI-compatible transcription endpoint
+pub struct TranscriptionService {
+    client: Client,
+    config: TranscriptionConfig,
+}
+
+impl TranscriptionService {
+    pub fn new(config: TranscriptionConfig) -> Result<Self, reqwest::Error> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Transcribe one `input_audio` part
+    pub async fn transcribe(&self, audio: &InputAudio) -> Result<String, TranscriptionError> {
+        let media_type = audio
+            .media_type()
+            .ok_or_else(|| TranscriptionError::UnsupportedFormat(audio.format.clone()))?;
+        let bytes = BASE64
+            .decode(audio.data.as_bytes())
+            .map_err(|e| TranscriptionError::InvalidAudio(e.to_string()))?;
+        if bytes.len() > self.config.max_audio_bytes {
+            return Err(TranscriptionError::TooLarge {
+                size: bytes.len(),
+                limit: self.config.max_audio_bytes,
+            });
+        }
+
+        let boundary = format!("----llm-api-converter-{}", uuid::Uuid::new_v4().simple());
+        let file_name = format!("audio.{}", audio.format);
+        let body = multipart_body(&boundary, &self.config.model, &file_name, media_type, &bytes);
+
+        let url = format!(
+            "{}/audio/transcriptions",
+            self.config.base_url.trim_end_matches('/')
+        );
+        let mut request = self
+            .client
+            .post(&url)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body);
+        if let Some(ref api_key) = self.config.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| TranscriptionError::Request(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(TranscriptionError::Backend {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        let transcription: TranscriptionResponse = response
+            .json()
+            .await
+            .map_err(|e| TranscriptionError::Request(e.to_string()))?;
+        Ok(transcription.text)
+    }
+}
+
+/// `multipart/form-data` body with the `model` field and the audio `file`
+fn multipart_body(
+    boundary: &str,
+    model: &str,
+    file_name: &str,
+    media_type: &str,
+    audio: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(audio.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\n{model}\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: {media_type}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(audio);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_body() {
+        let body = multipart_body("b", "whisper-1", "audio.wav", "audio/wav", b"RIFF");
+        let body = String::from_utf8(body).unwrap();
+        assert_eq!(
+            body,
+            "--b\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"audio.wav\"\r\n\
+             Content-Type: audio/wav\r\n\r\nRIFF\r\n--b--\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_audio() {
+        let service = TranscriptionService::new(TranscriptionConfig {
+            max_audio_bytes: 2,
+            ..TranscriptionConfig::default()
+        })
+        .unwrap();
+
+        let audio = |data: &str, format: &str| InputAudio {
+            data: data.to_string(),
+            format: format.to_string(),
+        };
+        let err = service.transcribe(&audio("AAAA", "flac")).await.unwrap_err();
+        assert!(matches!(err, TranscriptionError::UnsupportedFormat(_)));
+        let err = service.transcribe(&audio("not base64!", "wav")).await.unwrap_err();
+        assert!(matches!(err, TranscriptionError::InvalidAudio(_)));
+        let err = service.transcribe(&audio("AAAA", "mp3")).await.unwrap_err();
+        assert!(err.is_client_error());
+    }
+}
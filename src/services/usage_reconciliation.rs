@@ -0,0 +1,500 @@
+//! Token usage reconciliation
+//!
+//! Handlers add the token counts they report to clients to a [`UsageLedger`],
+//! bucketed per minute by Bedrock model ID and region. A periodic
+//! [`UsageReconciler`] compares the ledger against the `InputTokenCount` and
+//! `OutputTokenCount` invocation metrics Bedrock publishes to CloudWatch and
+//! reports the drift per model and region; a stream whose usage event was
+//! missed shows up as the proxy counting fewer tokens than Bedrock billed.
+//!
+//! CloudWatch lags behind, so the compared window ends `lag_secs` before the
+//! run. Metrics cover every caller of the account: the comparison is only
+//! meaningful when this instance is the only one invoking the model in that
+//! account and region. The latest report is served at
+//! `GET /admin/usage-reconciliation`.
+
+use async_trait::async_trait;
+use aws_config::{Region, SdkConfig};
+use aws_sdk_cloudwatch::types::{Dimension, Statistic};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::config::UsageReconciliationConfig;
+use crate::services::Backend;
+
+/// CloudWatch namespace of Bedrock invocation metrics
+const BEDROCK_NAMESPACE: &str = "AWS/Bedrock";
+
+/// Seconds per ledger bucket (the finest CloudWatch period)
+const BUCKET_SECS: i64 = 60;
+
+/// Input and output tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenCounts {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+impl TokenCounts {
+    fn add(&mut self, other: TokenCounts) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// (Bedrock model ID, region)
+type LedgerKey = (String, String);
+
+/// Proxy-reported token usage per model and region, in minute buckets
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    buckets: Mutex<BTreeMap<LedgerKey, BTreeMap<i64, TokenCounts>>>,
+}
+
+impl UsageLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ledger entry for a request served by `backend`
+    ///
+    /// Only Bedrock publishes invocation metrics, so other backends get None.
+    pub fn entry(self: &Arc<Self>, backend: &dyn Backend, model_id: &str) -> Option<LedgerEntry> {
+        if backend.name() != "bedrock" {
+            return None;
+        }
+        Some(LedgerEntry {
+            ledger: self.clone(),
+            model_id: model_id.to_string(),
+            region: backend.region()?,
+        })
+    }
+
+    /// Add usage recorded at `at`
+    pub fn record_at(&self, model_id: &str, region: &str, usage: TokenCounts, at: DateTime<Utc>) {
+        let bucket = at.timestamp() - at.timestamp().rem_euclid(BUCKET_SECS);
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .entry((model_id.to_string(), region.to_string()))
+            .or_default()
+            .entry(bucket)
+            .or_default()
+            .add(usage);
+    }
+
+    /// Usage per model and region between `start` (inclusive) and `end`
+    ///
+    /// Models seen before the window are included with zero usage, so a
+    /// model the proxy stopped counting still gets compared.
+    pub fn totals(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<(LedgerKey, TokenCounts)> {
+        let buckets = self.buckets.lock().unwrap();
+        buckets
+            .iter()
+            .map(|(key, buckets)| {
+                let mut total = TokenCounts::default();
+                for (_, usage) in buckets.range(start.timestamp()..end.timestamp()) {
+                    total.add(*usage);
+                }
+                (key.clone(), total)
+            })
+            .collect()
+    }
+
+    /// Drop buckets older than `before`
+    pub fn prune(&self, before: DateTime<Utc>) {
+        let mut buckets = self.buckets.lock().unwrap();
+        for buckets in buckets.values_mut() {
+            *buckets = buckets.split_off(&before.timestamp());
+        }
+    }
+}
+
+/// Ledger slot of one request
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+    ledger: Arc<UsageLedger>,
+    model_id: String,
+    region: String,
+}
+
+impl LedgerEntry {
+    /// Add the request's reported usage
+    pub fn record(&self, input_tokens: i32, output_tokens: i32) {
+        self.ledger.record_at(
+            &self.model_id,
+            &self.region,
+            TokenCounts {
+                input_tokens: input_tokens.max(0) as i64,
+                output_tokens: output_tokens.max(0) as i64,
+            },
+            Utc::now(),
+        );
+    }
+}
+
+/// Source of the backend's own token counts
+#[async_trait]
+pub trait InvocationMetrics: Send + Sync {
+    /// Tokens the backend counted for a model between `start` and `end`
+    async fn token_counts(
+        &self,
+        model_id: &str,
+        region: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<TokenCounts, String>;
+}
+
+/// Bedrock invocation metrics read from CloudWatch
+pub struct CloudWatchInvocationMetrics {
+    sdk_config: SdkConfig,
+    clients: Mutex<HashMap<String, aws_sdk_cloudwatch::Client>>,
+}
+
+impl CloudWatchInvocationMetrics {
+    pub fn new(sdk_config: SdkConfig) -> Self {
+        Self {
+            sdk_config,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn client(&self, region: &str) -> aws_sdk_cloudwatch::Client {
+        self.clients
+            .lock()
+            .unwrap()
+            .entry(region.to_string())
+            .or_insert_with(|| {
+                let config = aws_sdk_cloudwatch::config::Builder::from(&self.sdk_config)
+                    .region(Region::new(region.to_string()))
+                    .build();
+                aws_sdk_cloudwatch::Client::from_conf(config)
+            })
+            .clone()
+    }
+
+    async fn sum(
+        &self,
+        client: &aws_sdk_cloudwatch::Client,
+        metric: &str,
+        model_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<i64, String> {
+        let output = client
+            .get_metric_statistics()
+            .namespace(BEDROCK_NAMESPACE)
+            .metric_name(metric)
+            .dimensions(Dimension::builder().name("ModelId").value(model_id).build())
+            .start_time(aws_smithy_types::DateTime::from_secs(start.timestamp()))
+            .end_time(aws_smithy_types::DateTime::from_secs(end.timestamp()))
+            .period((end - start).num_seconds().max(BUCKET_SECS) as i32)
+            .statistics(Statistic::Sum)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(output
+            .datapoints()
+            .iter()
+            .filter_map(|point| point.sum())
+            .sum::<f64>() as i64)
+    }
+}
+
+#[async_trait]
+impl InvocationMetrics for CloudWatchInvocationMetrics {
+    async fn token_counts(
+        &self,
+        model_id: &str,
+        region: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<TokenCounts, String> {
+        let client = self.client(region);
+        Ok(TokenCounts {
+            input_tokens: self
+                .sum(&client, "InputTokenCount", model_id, start, end)
+                .await?,
+            output_tokens: self
+                .sum(&client, "OutputTokenCount", model_id, start, end)
+                .await?,
+        })
+    }
+}
+
+/// Proxy and backend usage of one model and region
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageDrift {
+    pub model_id: String,
+    pub region: String,
+    /// Tokens the proxy reported
+    pub proxy: TokenCounts,
+    /// Tokens the backend counted (None if the metrics could not be read)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<TokenCounts>,
+    /// Proxy minus backend, in percent of the backend count
+    pub input_drift_percent: f64,
+    pub output_drift_percent: f64,
+    /// Whether either drift exceeds the threshold
+    pub drifted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of one reconciliation run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReconciliationReport {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub drift_threshold_percent: f64,
+    pub entries: Vec<UsageDrift>,
+}
+
+impl ReconciliationReport {
+    /// Entries whose drift exceeds the threshold
+    pub fn drifted(&self) -> impl Iterator<Item = &UsageDrift> {
+        self.entries.iter().filter(|entry| entry.drifted)
+    }
+}
+
+/// Proxy minus backend, in percent of the backend count
+fn drift_percent(proxy: i64, backend: i64) -> f64 {
+    match (proxy, backend) {
+        (0, 0) => 0.0,
+        (_, 0) => 100.0,
+        _ => (proxy - backend) as f64 / backend as f64 * 100.0,
+    }
+}
+
+/// Compares the ledger with the backend's metrics
+pub struct UsageReconciler {
+    ledger: Arc<UsageLedger>,
+    metrics: Arc<dyn InvocationMetrics>,
+    config: UsageReconciliationConfig,
+    last_report: Mutex<Option<ReconciliationReport>>,
+}
+
+impl UsageReconciler {
+    pub fn new(
+        ledger: Arc<UsageLedger>,
+        metrics: Arc<dyn InvocationMetrics>,
+        config: UsageReconciliationConfig,
+    ) -> Self {
+        Self {
+            ledger,
+            metrics,
+            config,
+            last_report: Mutex::new(None),
+        }
+    }
+
+    /// Reconcile the window ending `lag_secs` before `now`
+    pub async fn reconcile(&self, now: DateTime<Utc>) -> ReconciliationReport {
+        let end = now - Duration::seconds(self.config.lag_secs as i64);
+        let end = end - Duration::seconds(end.timestamp().rem_euclid(BUCKET_SECS));
+        let start = end - Duration::seconds((self.config.window_secs as i64).max(BUCKET_SECS));
+        let threshold = self.config.drift_threshold_percent;
+
+        let mut entries = Vec::new();
+        for ((model_id, region), proxy) in self.ledger.totals(start, end) {
+            let entry = match self
+                .metrics
+                .token_counts(&model_id, &region, start, end)
+                .await
+            {
+                Ok(backend) => {
+                    let input_drift_percent =
+                        drift_percent(proxy.input_tokens, backend.input_tokens);
+                    let output_drift_percent =
+                        drift_percent(proxy.output_tokens, backend.output_tokens);
+                    UsageDrift {
+                        drifted: input_drift_percent.abs() > threshold
+                            || output_drift_percent.abs() > threshold,
+                        model_id,
+                        region,
+                        proxy,
+                        backend: Some(backend),
+                        input_drift_percent,
+                        output_drift_percent,
+                        error: None,
+                    }
+                }
+                Err(error) => UsageDrift {
+                    model_id,
+                    region,
+                    proxy,
+                    backend: None,
+                    input_drift_percent: 0.0,
+                    output_drift_percent: 0.0,
+                    drifted: false,
+                    error: Some(error),
+                },
+            };
+            entries.push(entry);
+        }
+        self.ledger.prune(start);
+
+        let report = ReconciliationReport {
+            window_start: start,
+            window_end: end,
+            drift_threshold_percent: threshold,
+            entries,
+        };
+        for entry in report.drifted() {
+            tracing::warn!(
+                model_id = %entry.model_id,
+                region = %entry.region,
+                proxy_input_tokens = entry.proxy.input_tokens,
+                proxy_output_tokens = entry.proxy.output_tokens,
+                input_drift_percent = entry.input_drift_percent,
+                output_drift_percent = entry.output_drift_percent,
+                "Proxy token usage drifted from Bedrock invocation metrics"
+            );
+        }
+        for entry in report.entries.iter().filter(|e| e.error.is_some()) {
+            tracing::warn!(
+                model_id = %entry.model_id,
+                region = %entry.region,
+                error = ?entry.error,
+                "Failed to read Bedrock invocation metrics"
+            );
+        }
+
+        *self.last_report.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Report of the latest run, if any
+    pub fn last_report(&self) -> Option<ReconciliationReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    /// Spawn the periodic reconciliation task
+    pub fn spawn_loop(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(self.config.interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let report = self.reconcile(Utc::now()).await;
+                tracing::info!(
+                    models = report.entries.len(),
+                    drifted = report.drifted().count(),
+                    "Token usage reconciliation completed"
+                );
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedMetrics(TokenCounts);
+
+    #[async_trait]
+    impl InvocationMetrics for FixedMetrics {
+        async fn token_counts(
+            &self,
+            model_id: &str,
+            _region: &str,
+            _start: DateTime<Utc>,
+            _end: DateTime<Utc>,
+        ) -> Result<TokenCounts, String> {
+            match model_id {
+                "broken" => Err("AccessDenied".to_string()),
+                _ => Ok(self.0),
+            }
+        }
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn tokens(input_tokens: i64, output_tokens: i64) -> TokenCounts {
+        TokenCounts {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_reports_drift() {
+        let ledger = Arc::new(UsageLedger::new());
+        let model = "anthropic.claude-3-haiku-20240307-v1:0";
+        ledger.record_at(
+            model,
+            "us-east-1",
+            tokens(600, 100),
+            at("2024-06-01T10:10:30Z"),
+        );
+        ledger.record_at(
+            model,
+            "us-east-1",
+            tokens(400, 80),
+            at("2024-06-01T10:40:00Z"),
+        );
+        // Outside the window (after the lag cut-off)
+        ledger.record_at(
+            model,
+            "us-east-1",
+            tokens(999, 999),
+            at("2024-06-01T11:05:00Z"),
+        );
+        ledger.record_at(
+            model,
+            "us-west-2",
+            tokens(1000, 200),
+            at("2024-06-01T10:20:00Z"),
+        );
+        ledger.record_at(
+            "broken",
+            "us-east-1",
+            tokens(1, 1),
+            at("2024-06-01T10:20:00Z"),
+        );
+
+        let reconciler = UsageReconciler::new(
+            ledger.clone(),
+            Arc::new(FixedMetrics(tokens(1000, 200))),
+            UsageReconciliationConfig {
+                window_secs: 3600,
+                lag_secs: 600,
+                drift_threshold_percent: 5.0,
+                ..Default::default()
+            },
+        );
+        let report = reconciler.reconcile(at("2024-06-01T11:10:45Z")).await;
+        assert_eq!(report.window_start, at("2024-06-01T10:00:00Z"));
+        assert_eq!(report.window_end, at("2024-06-01T11:00:00Z"));
+        assert_eq!(report.entries.len(), 3);
+
+        let east = &report.entries[0];
+        assert_eq!(east.region, "us-east-1");
+        assert_eq!(east.proxy, tokens(1000, 180));
+        assert_eq!(east.input_drift_percent, 0.0);
+        assert_eq!(east.output_drift_percent, -10.0);
+        assert!(east.drifted);
+
+        let west = &report.entries[1];
+        assert!(!west.drifted);
+
+        let broken = &report.entries[2];
+        assert_eq!(broken.error.as_deref(), Some("AccessDenied"));
+        assert!(!broken.drifted);
+
+        assert_eq!(report.drifted().count(), 1);
+        assert_eq!(reconciler.last_report(), Some(report));
+    }
+}
@@ -1,14 +1,16 @@
 //! Usage tracking service
 //!
 //! This module handles tracking API usage statistics for billing and monitoring.
-//! Usage is recorded to DynamoDB and budget tracking is updated for each request.
+//! Usage is recorded to the storage backend and budget tracking is updated
+//! for each request.
 
-use crate::db::models::UsageRecord;
-use crate::db::repositories::{ApiKeyError, ApiKeyRepository, UsageRepository};
-use crate::db::DynamoDbClient;
+use crate::db::models::{ModelPricing, UsageRecord};
+use crate::db::{StorageBackend, StorageError};
 use crate::middleware::auth::ApiKeyInfo;
 use crate::schemas::anthropic::{MessageResponse, Usage};
+use crate::services::routing_metrics::RoutingOutcome;
 use chrono::Utc;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 // ============================================================================
@@ -16,7 +18,7 @@ use std::sync::Arc;
 // ============================================================================
 
 /// Get the pricing multiplier for a service tier
-fn get_tier_multiplier(tier: &str) -> f64 {
+pub fn get_tier_multiplier(tier: &str) -> f64 {
     match tier.to_lowercase().as_str() {
         "flex" => 0.5,      // 50% discount
         "priority" => 1.75, // 75% markup
@@ -32,34 +34,27 @@ fn get_tier_multiplier(tier: &str) -> f64 {
 /// Service for tracking API usage statistics.
 ///
 /// This service:
-/// - Records individual request usage to DynamoDB
+/// - Records individual request usage to the storage backend
 /// - Updates budget tracking for API keys
 /// - Calculates costs based on model pricing and service tier
 #[derive(Clone)]
 pub struct UsageTracker {
-    #[allow(dead_code)]
-    dynamodb: Arc<DynamoDbClient>,
-    usage_repo: UsageRepository,
-    api_key_repo: ApiKeyRepository,
+    storage: Arc<dyn StorageBackend>,
 }
 
 impl UsageTracker {
     /// Create a new usage tracker.
     ///
     /// # Arguments
-    /// * `dynamodb` - DynamoDB client for persisting usage data
-    pub fn new(dynamodb: Arc<DynamoDbClient>) -> Self {
-        Self {
-            usage_repo: UsageRepository::new(dynamodb.clone()),
-            api_key_repo: ApiKeyRepository::new(dynamodb.clone()),
-            dynamodb,
-        }
+    /// * `storage` - Storage backend for usage records and key budgets
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        Self { storage }
     }
 
     /// Record usage for a completed request
     ///
     /// This method:
-    /// 1. Creates a usage record in DynamoDB
+    /// 1. Creates a usage record in the storage backend
     /// 2. Calculates the cost based on model pricing and service tier
     /// 3. Updates the API key's budget usage
     ///
@@ -69,6 +64,7 @@ impl UsageTracker {
     /// * `model` - The model ID that was used
     /// * `usage` - Token usage from the response
     /// * `success` - Whether the request was successful
+    /// * `metadata` - Request metadata tags (e.g. OpenAI `metadata`)
     ///
     /// # Returns
     /// * `Ok(true)` - Budget limit was exceeded, key deactivated
@@ -81,22 +77,42 @@ impl UsageTracker {
         model: &str,
         usage: &Usage,
         success: bool,
+        metadata: &HashMap<String, String>,
     ) -> Result<bool, UsageError> {
-        let timestamp = Utc::now();
+        let record = Self::usage_record(key_info, request_id, model, usage, success, metadata);
+        self.record(key_info, record, usage).await
+    }
 
-        // Skip recording for master key
-        if key_info.is_master {
-            tracing::debug!(
-                request_id = %request_id,
-                "Skipping usage recording for master key"
-            );
-            return Ok(false);
-        }
+    /// Record usage for a request served through backend routing
+    ///
+    /// Like [`Self::record_usage`] for a successful request, additionally
+    /// storing which backend served it, the attempts it took and the latency
+    /// failed attempts added.
+    pub async fn record_routed_usage(
+        &self,
+        key_info: &ApiKeyInfo,
+        request_id: &str,
+        model: &str,
+        usage: &Usage,
+        metadata: &HashMap<String, String>,
+        routing: &RoutingOutcome,
+    ) -> Result<bool, UsageError> {
+        let record = Self::usage_record(key_info, request_id, model, usage, true, metadata)
+            .with_routing(routing);
+        self.record(key_info, record, usage).await
+    }
 
-        // Create usage record
-        let record = UsageRecord {
+    fn usage_record(
+        key_info: &ApiKeyInfo,
+        request_id: &str,
+        model: &str,
+        usage: &Usage,
+        success: bool,
+        metadata: &HashMap<String, String>,
+    ) -> UsageRecord {
+        UsageRecord {
             api_key: key_info.api_key.clone(),
-            timestamp: timestamp.to_rfc3339(),
+            timestamp: Utc::now().to_rfc3339(),
             request_id: request_id.to_string(),
             model: model.to_string(),
             input_tokens: usage.input_tokens as i64,
@@ -106,10 +122,34 @@ impl UsageTracker {
             success,
             duration_ms: None,
             error_message: None,
-        };
+            metadata: metadata.clone(),
+            backend: None,
+            attempts: None,
+            added_latency_ms: None,
+        }
+    }
+
+    /// Save a usage record and charge its cost to the key's budget
+    async fn record(
+        &self,
+        key_info: &ApiKeyInfo,
+        record: UsageRecord,
+        usage: &Usage,
+    ) -> Result<bool, UsageError> {
+        let request_id = record.request_id.as_str();
+        let model = record.model.as_str();
+
+        // Skip recording for master key
+        if key_info.is_master {
+            tracing::debug!(
+                request_id = %request_id,
+                "Skipping usage recording for master key"
+            );
+            return Ok(false);
+        }
 
         // Save usage record
-        self.usage_repo
+        self.storage
             .record_usage(&record)
             .await
             .map_err(|e| UsageError::Database(e.to_string()))?;
@@ -120,6 +160,8 @@ impl UsageTracker {
             model = %model,
             input_tokens = usage.input_tokens,
             output_tokens = usage.output_tokens,
+            backend = ?record.backend,
+            attempts = ?record.attempts,
             "Usage recorded"
         );
 
@@ -130,13 +172,12 @@ impl UsageTracker {
 
         if cost > 0.0 {
             let budget_exceeded = self
-                .api_key_repo
+                .storage
                 .increment_budget_used(&key_info.api_key, cost)
                 .await
                 .map_err(|e| match e {
-                    ApiKeyError::NotFound => UsageError::ApiKeyNotFound,
-                    ApiKeyError::DynamoDb(msg) => UsageError::Database(msg),
-                    ApiKeyError::ParseError(msg) => UsageError::Database(msg),
+                    StorageError::NotFound => UsageError::ApiKeyNotFound,
+                    other => UsageError::Database(other.to_string()),
                 })?;
 
             if budget_exceeded {
@@ -169,35 +210,29 @@ impl UsageTracker {
             &response.model,
             &response.usage,
             success,
+            &HashMap::new(),
         )
         .await
     }
 
     /// Calculate the cost of a request
     ///
-    /// Uses simplified pricing (will be replaced with DynamoDB lookup in production):
-    /// - Input tokens: $3 per million
-    /// - Output tokens: $15 per million
-    /// - Cached read: $0.30 per million
-    /// - Cache write: $3.75 per million
-    fn calculate_cost(&self, _model: &str, usage: &Usage, service_tier: &str) -> f64 {
-        // Simplified pricing (Claude 3.5 Sonnet approximate rates)
-        const INPUT_PRICE_PER_MILLION: f64 = 3.0;
-        const OUTPUT_PRICE_PER_MILLION: f64 = 15.0;
-        const CACHE_READ_PRICE_PER_MILLION: f64 = 0.30;
-        const CACHE_WRITE_PRICE_PER_MILLION: f64 = 3.75;
-
-        let input_cost = (usage.input_tokens as f64) * INPUT_PRICE_PER_MILLION / 1_000_000.0;
-        let output_cost = (usage.output_tokens as f64) * OUTPUT_PRICE_PER_MILLION / 1_000_000.0;
+    /// Uses the fallback rates of [`ModelPricing::fallback`] (will be
+    /// replaced with the pricing table lookup in production).
+    fn calculate_cost(&self, model: &str, usage: &Usage, service_tier: &str) -> f64 {
+        let pricing = ModelPricing::fallback(model);
+
+        let input_cost = (usage.input_tokens as f64) * pricing.input_price / 1_000_000.0;
+        let output_cost = (usage.output_tokens as f64) * pricing.output_price / 1_000_000.0;
 
         let cache_read_cost = usage
             .cache_read_input_tokens
-            .map(|t| (t as f64) * CACHE_READ_PRICE_PER_MILLION / 1_000_000.0)
+            .map(|t| (t as f64) * pricing.cache_read_price / 1_000_000.0)
             .unwrap_or(0.0);
 
         let cache_write_cost = usage
             .cache_creation_input_tokens
-            .map(|t| (t as f64) * CACHE_WRITE_PRICE_PER_MILLION / 1_000_000.0)
+            .map(|t| (t as f64) * pricing.cache_write_price / 1_000_000.0)
             .unwrap_or(0.0);
 
         let base_cost = input_cost + output_cost + cache_read_cost + cache_write_cost;
@@ -216,7 +251,7 @@ impl UsageTracker {
         since_timestamp: Option<&str>,
     ) -> Result<UsageStats, UsageError> {
         let records = self
-            .usage_repo
+            .storage
             .get_usage_by_api_key(api_key, since_timestamp, None, None)
             .await
             .map_err(|e| UsageError::Database(e.to_string()))?;
@@ -232,6 +267,15 @@ impl UsageTracker {
             stats.total_output_tokens += record.output_tokens;
             stats.total_cached_tokens += record.cached_tokens;
             stats.total_cache_write_tokens += record.cache_write_tokens;
+            if let Some(attempts) = record.attempts {
+                stats.total_attempts += attempts.max(1) as u64;
+                if attempts > 1 {
+                    stats.retried_requests += 1;
+                }
+            } else {
+                stats.total_attempts += 1;
+            }
+            stats.total_added_latency_ms += record.added_latency_ms.unwrap_or(0).max(0) as u64;
         }
 
         Ok(stats)
@@ -251,6 +295,12 @@ pub struct UsageStats {
     pub total_output_tokens: i64,
     pub total_cached_tokens: i64,
     pub total_cache_write_tokens: i64,
+    /// Upstream attempts, counting one for records without routing data
+    pub total_attempts: u64,
+    /// Requests that needed more than one upstream attempt
+    pub retried_requests: u64,
+    /// Latency added by failed attempts
+    pub total_added_latency_ms: u64,
 }
 
 // ============================================================================
@@ -304,6 +354,7 @@ mod tests {
             output_tokens: 500,
             cache_creation_input_tokens: None,
             cache_read_input_tokens: None,
+            service_tier: None,
         };
 
         // Manual calculation:
@@ -0,0 +1,134 @@
+//! CIDR network matching
+//!
+//! Minimal IPv4/IPv6 CIDR parsing for API key allowlists and the global
+//! denylist. Bare addresses are treated as single-host networks.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP network in CIDR notation (e.g. "10.0.0.0/8", "2001:db8::/32")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// Check whether an address falls inside this network
+    ///
+    /// IPv4-mapped IPv6 addresses are matched against IPv4 networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, normalize(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+
+    /// Check whether an address matches any network in the list
+    pub fn any_contains(networks: &[IpCidr], ip: IpAddr) -> bool {
+        networks.iter().any(|net| net.contains(ip))
+    }
+
+    /// Parse a list of CIDR strings, logging and skipping invalid entries
+    pub fn parse_list(entries: &[String]) -> Vec<IpCidr> {
+        entries
+            .iter()
+            .filter_map(|entry| match entry.parse() {
+                Ok(net) => Some(net),
+                Err(e) => {
+                    tracing::warn!(entry = %entry, error = %e, "Ignoring invalid CIDR entry");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr = normalize(
+            addr.parse::<IpAddr>()
+                .map_err(|_| format!("invalid IP address '{}'", addr))?,
+        );
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max_prefix)
+                .ok_or_else(|| format!("invalid prefix length '{}'", p))?,
+            None => max_prefix,
+        };
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ipv4_cidr() {
+        let net: IpCidr = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains(ip("10.1.200.3")));
+        assert!(!net.contains(ip("10.2.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.0.9")));
+
+        let host: IpCidr = "192.0.2.7".parse().unwrap();
+        assert!(host.contains(ip("192.0.2.7")));
+        assert!(!host.contains(ip("192.0.2.8")));
+
+        let all: IpCidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(ip("8.8.8.8")));
+        assert!(!all.contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn test_ipv6_cidr() {
+        let net: IpCidr = "2001:db8::/32".parse().unwrap();
+        assert!(net.contains(ip("2001:db8:1::1")));
+        assert!(!net.contains(ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn test_invalid_cidr() {
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("not-an-ip".parse::<IpCidr>().is_err());
+        let parsed = IpCidr::parse_list(&["10.0.0.0/8".to_string(), "bogus".to_string()]);
+        assert_eq!(parsed.len(), 1);
+    }
+}
@@ -0,0 +1,113 @@
+//! Client IP extraction
+//!
+//! Determines the source IP of a request from the socket peer address
+//! (requires the server to be started with connect info). Behind load
+//! balancers the `X-Forwarded-For` header is consulted, but only when the
+//! peer is one of the configured trusted proxies, trusting only the
+//! configured number of proxy hops.
+
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, Request};
+use std::net::{IpAddr, SocketAddr};
+
+use super::IpCidr;
+
+/// Proxies whose forwarding headers are honored
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    /// Networks the proxies connect from
+    pub networks: Vec<IpCidr>,
+    /// Number of proxies that append to `X-Forwarded-For`
+    pub hops: usize,
+}
+
+/// Get the client IP for a request
+///
+/// Forwarding headers are only read when `trusted` is set and the socket
+/// peer is inside one of its networks; any other peer is the client.
+pub fn client_ip<B>(request: &Request<B>, trusted: Option<&TrustedProxies>) -> Option<IpAddr> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())?;
+    match trusted {
+        Some(trusted) if IpCidr::any_contains(&trusted.networks, peer) => {
+            client_ip_from_headers(request.headers(), trusted.hops).or(Some(peer))
+        }
+        _ => Some(peer),
+    }
+}
+
+/// Get the client IP from forwarding headers
+///
+/// Each trusted proxy appends the address it received the request from, so the
+/// client is the entry `hops` positions from the right of `X-Forwarded-For`;
+/// anything further left is client-controlled.
+pub fn client_ip_from_headers(headers: &HeaderMap, hops: usize) -> Option<IpAddr> {
+    let hops = hops.max(1);
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            let entries: Vec<&str> = v.split(',').map(str::trim).collect();
+            let index = entries.len().saturating_sub(hops);
+            entries.get(index)?.parse().ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request_from(peer: &str, forwarded_for: &str) -> Request<Body> {
+        let mut request = Request::builder()
+            .header("x-forwarded-for", forwarded_for)
+            .header("x-real-ip", "198.51.100.1")
+            .body(Body::empty())
+            .unwrap();
+        let addr: SocketAddr = format!("{}:5555", peer).parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        request
+    }
+
+    fn trusted(hops: usize) -> TrustedProxies {
+        TrustedProxies {
+            networks: vec!["10.0.0.0/8".parse().unwrap()],
+            hops,
+        }
+    }
+
+    #[test]
+    fn test_forwarded_for_trusted_hops() {
+        let request = request_from("10.0.0.2", "1.2.3.4, 203.0.113.7, 10.0.0.1");
+
+        // One proxy (the load balancer) appended the real peer
+        assert_eq!(client_ip(&request, Some(&trusted(1))), Some("10.0.0.1".parse().unwrap()));
+        // CDN + load balancer
+        assert_eq!(client_ip(&request, Some(&trusted(2))), Some("203.0.113.7".parse().unwrap()));
+        // More hops than entries: left-most entry
+        assert_eq!(client_ip(&request, Some(&trusted(10))), Some("1.2.3.4".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let request = request_from("192.0.2.10", "203.0.113.7");
+        let peer: IpAddr = "192.0.2.10".parse().unwrap();
+
+        assert_eq!(client_ip(&request, None), Some(peer));
+        assert_eq!(client_ip(&request, Some(&trusted(1))), Some(peer));
+
+        // No X-Real-IP fallback, even from a trusted proxy
+        let mut request = request_from("10.0.0.2", "not-an-ip");
+        request.headers_mut().remove("x-forwarded-for");
+        assert_eq!(client_ip(&request, Some(&trusted(1))), Some("10.0.0.2".parse().unwrap()));
+
+        // Without connect info there is no trustworthy address
+        let request = Request::builder()
+            .header("x-forwarded-for", "203.0.113.7")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(client_ip(&request, Some(&trusted(1))), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! Base64 media helpers
+//!
+//! Images and documents arrive base64-encoded inside the JSON request body.
+//! Decoding them with `Engine::decode` allocates a second buffer per payload
+//! while the request still holds the encoded text, so a request with many
+//! images briefly needs ~1.75x its body size. These helpers decode in place,
+//! reusing the allocation of the encoded `String` (decoded data is always
+//! shorter than its encoding), and validate without allocating at all.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, DecodeError, DecodeSliceError, Engine};
+
+/// Encoded characters decoded per chunk (a multiple of 4)
+const CHUNK_CHARS: usize = 4096;
+
+/// Decoded bytes produced by one full chunk
+const CHUNK_BYTES: usize = CHUNK_CHARS / 4 * 3;
+
+/// Decode base64 text in place, reusing its buffer for the decoded bytes
+///
+/// Each chunk is copied to a small stack buffer and decoded back into the
+/// front of the same allocation; the write position never overtakes the read
+/// position, so no unread input is overwritten. Accepts exactly what
+/// `STANDARD.decode` accepts.
+pub fn decode_base64_in_place(data: String) -> Result<Vec<u8>, DecodeError> {
+    let mut buf = data.into_bytes();
+    check_padding_position(&buf)?;
+
+    let mut scratch = [0u8; CHUNK_CHARS];
+    let mut read = 0;
+    let mut written = 0;
+    while read < buf.len() {
+        let n = CHUNK_CHARS.min(buf.len() - read);
+        scratch[..n].copy_from_slice(&buf[read..read + n]);
+        written += BASE64
+            .decode_slice(&scratch[..n], &mut buf[written..])
+            .map_err(|e| offset_error(e, read, n))?;
+        read += n;
+    }
+
+    buf.truncate(written);
+    Ok(buf)
+}
+
+/// Take the base64 text out of `data` and decode it in place
+///
+/// `data` is left empty, so the encoded copy is gone once this returns.
+pub fn take_base64(data: &mut String) -> Result<Vec<u8>, DecodeError> {
+    decode_base64_in_place(std::mem::take(data))
+}
+
+/// Check that base64 text decodes, without allocating
+pub fn validate_base64(data: &str) -> Result<(), DecodeError> {
+    let bytes = data.as_bytes();
+    check_padding_position(bytes)?;
+
+    let mut scratch = [0u8; CHUNK_BYTES];
+    for (i, chunk) in bytes.chunks(CHUNK_CHARS).enumerate() {
+        BASE64
+            .decode_slice(chunk, &mut scratch)
+            .map_err(|e| offset_error(e, i * CHUNK_CHARS, chunk.len()))?;
+    }
+    Ok(())
+}
+
+/// Split a `data:` URL into its media type and base64 payload
+///
+/// `data:image/png;base64,<payload>` yields `("image/png", "<payload>")`.
+pub fn split_data_url(url: &str) -> Option<(&str, &str)> {
+    let (metadata, payload) = url.strip_prefix("data:")?.split_once(',')?;
+    let media_type = metadata.split(';').next().unwrap_or_default();
+    Some((media_type, payload))
+}
+
+/// Reject padding anywhere but the final two characters
+///
+/// Chunked decoding would otherwise accept padding at a chunk boundary.
+fn check_padding_position(bytes: &[u8]) -> Result<(), DecodeError> {
+    let body = &bytes[..bytes.len().saturating_sub(2)];
+    match body.iter().position(|&b| b == b'=') {
+        Some(pos) => Err(DecodeError::InvalidByte(pos, b'=')),
+        None => Ok(()),
+    }
+}
+
+/// Translate a chunk-relative error into one relative to the whole input
+fn offset_error(err: DecodeSliceError, chunk_start: usize, chunk_len: usize) -> DecodeError {
+    match err {
+        DecodeSliceError::DecodeError(DecodeError::InvalidByte(pos, byte)) => {
+            DecodeError::InvalidByte(chunk_start + pos, byte)
+        }
+        DecodeSliceError::DecodeError(DecodeError::InvalidLastSymbol(pos, byte)) => {
+            DecodeError::InvalidLastSymbol(chunk_start + pos, byte)
+        }
+        DecodeSliceError::DecodeError(DecodeError::InvalidLength(_))
+        | DecodeSliceError::OutputSliceTooSmall => DecodeError::InvalidLength(chunk_start + chunk_len),
+        DecodeSliceError::DecodeError(other) => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_decode_in_place_matches_engine() {
+        // Cover empty, short, padded and multi-chunk inputs
+        for len in [0, 1, 2, 3, 4, 100, CHUNK_BYTES, CHUNK_BYTES + 1, 3 * CHUNK_BYTES + 2] {
+            let original = sample(len);
+            let encoded = BASE64.encode(&original);
+            validate_base64(&encoded).unwrap();
+            assert_eq!(decode_base64_in_place(encoded).unwrap(), original, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn test_invalid_base64_rejected() {
+        let mut encoded = BASE64.encode(sample(3 * CHUNK_BYTES));
+        encoded.replace_range(CHUNK_CHARS + 5..CHUNK_CHARS + 6, "!");
+        assert_eq!(
+            decode_base64_in_place(encoded.clone()),
+            Err(DecodeError::InvalidByte(CHUNK_CHARS + 5, b'!'))
+        );
+        assert!(validate_base64(&encoded).is_err());
+
+        // Padding at a chunk boundary is not valid base64
+        let split = format!("{}{}", BASE64.encode(sample(CHUNK_BYTES - 1)), BASE64.encode(b"ab"));
+        assert_eq!(BASE64.decode(&split).is_err(), decode_base64_in_place(split).is_err());
+        assert!(validate_base64("QQ").is_err());
+    }
+
+    #[test]
+    fn test_split_data_url() {
+        let url = format!("data:image/jpeg;base64,{}", BASE64.encode(b"jpeg bytes"));
+        let (media_type, payload) = split_data_url(&url).unwrap();
+        assert_eq!(media_type, "image/jpeg");
+        assert_eq!(BASE64.decode(payload).unwrap(), b"jpeg bytes");
+
+        assert!(split_data_url("https://example.com/cat.png").is_none());
+    }
+}
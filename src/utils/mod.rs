@@ -2,12 +2,20 @@
 //!
 //! Contains retry logic, timeout handling, and other utilities.
 
+pub mod cidr;
+pub mod client_ip;
+pub mod media;
 pub mod retry;
+pub mod sse;
 pub mod string;
 pub mod timeout;
 pub mod tool_name_mapper;
 
+pub use cidr::IpCidr;
+pub use client_ip::{client_ip, client_ip_from_headers, TrustedProxies};
+pub use media::{split_data_url, take_base64, validate_base64};
 pub use retry::{retry, retry_with_backoff, RetryConfig, RetryResult};
-pub use string::{truncate_str, truncate_with_suffix};
+pub use sse::SseDataDecoder;
+pub use string::{redact_key, truncate_str, truncate_with_suffix};
 pub use timeout::{with_timeout, TimeoutConfig, TimeoutError};
 pub use tool_name_mapper::ToolNameMapper;
@@ -0,0 +1,92 @@
+//! Server-sent event decoding for upstream streams
+//!
+//! Upstream backends (Gemini, Azure OpenAI, the Anthropic API) stream
+//! `text/event-stream` bodies. [`SseDataDecoder`] buffers raw bytes until a
+//! full event is available, so multi-byte characters split across network
+//! reads survive, and hands back each event's `data:` payload. Events may be
+//! separated by `\n\n` or `\r\n\r\n`; other fields (`event:`, `id:`, comments)
+//! are ignored. Parsing the payload is left to each backend.
+
+/// Incremental decoder yielding the `data:` payload of each event
+#[derive(Debug, Default)]
+pub struct SseDataDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDataDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append raw bytes read from the response body
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Take the payload of the next complete event that carries data
+    pub fn next_data(&mut self) -> Option<String> {
+        while let Some((end, separator_len)) = find_event_end(&self.buffer) {
+            let event: Vec<u8> = self.buffer.drain(..end + separator_len).take(end).collect();
+            if let Some(data) = event_data(&event) {
+                return Some(data);
+            }
+        }
+        None
+    }
+
+    /// Take the payload of a trailing event left unterminated at end of stream
+    ///
+    /// Complete events still buffered should be drained with
+    /// [`next_data`](Self::next_data) first.
+    pub fn finish(&mut self) -> Option<String> {
+        let event = std::mem::take(&mut self.buffer);
+        event_data(&event)
+    }
+}
+
+/// Find the end of the first event and the length of its separator
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n");
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n");
+    match (lf, crlf) {
+        (Some(lf), Some(crlf)) if crlf < lf => Some((crlf, 4)),
+        (Some(lf), _) => Some((lf, 2)),
+        (None, Some(crlf)) => Some((crlf, 4)),
+        (None, None) => None,
+    }
+}
+
+/// Join an event's multi-line `data:` fields; `None` when there is no data
+fn event_data(event: &[u8]) -> Option<String> {
+    let event = String::from_utf8_lossy(event);
+    let data = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let data = data.trim();
+    (!data.is_empty()).then(|| data.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_splits_events() {
+        let mut decoder = SseDataDecoder::new();
+        decoder.push(b"event: a\ndata: {\"n\":1}\n\n: comment\r\n\r\ndata: line one\r\n");
+        decoder.push(b"data: line two\r\n\r\ndata: caf\xc3");
+        assert_eq!(decoder.next_data().as_deref(), Some("{\"n\":1}"));
+        assert_eq!(decoder.next_data().as_deref(), Some("line one\nline two"));
+        assert!(decoder.next_data().is_none());
+
+        // A character split across reads, then an unterminated final event
+        decoder.push(b"\xa9");
+        assert!(decoder.next_data().is_none());
+        assert_eq!(decoder.finish().as_deref(), Some("café"));
+        assert!(decoder.finish().is_none());
+    }
+}
@@ -55,6 +55,26 @@ pub fn truncate_with_suffix(s: &str, max_chars: usize, suffix: &str) -> String {
     }
 }
 
+/// Redact an API key for logs, keeping only its first 8 characters
+///
+/// Keys of 12 characters or fewer are too short to be real secrets and are
+/// kept whole. Truncation is character-safe.
+///
+/// # Example
+/// ```
+/// use anthropic_bedrock_proxy::utils::redact_key;
+///
+/// assert_eq!(redact_key("sk-0123456789abcdef"), "sk-01234...");
+/// assert_eq!(redact_key("disabled"), "disabled");
+/// ```
+pub fn redact_key(key: &str) -> String {
+    if key.chars().count() > 12 {
+        format!("{}...", truncate_str(key, 8))
+    } else {
+        key.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;